@@ -47,6 +47,7 @@ struct State {
     show_kill_all_sessions_warning: bool,
     request_ids: Vec<String>,
     is_web_client: bool,
+    serving_remote: bool,
 }
 
 register_plugin!(State);
@@ -370,6 +371,14 @@ impl State {
                 BareKey::Char('x') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
                     disconnect_other_clients()
                 },
+                BareKey::Char('s') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                    // only this session's remote server can be reached from here
+                    if self.serving_remote {
+                        stop_remote_serving();
+                    } else {
+                        start_remote_serving();
+                    }
+                },
                 BareKey::Char('c') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
                     if !self.search_term.is_empty() {
                         self.search_term.clear();
@@ -601,6 +610,9 @@ impl State {
         if let Some(current_session_name) = current_session_name {
             self.session_name = Some(current_session_name);
         }
+        self.serving_remote = session_infos
+            .iter()
+            .any(|s| s.is_current_session && s.remote_listen_address.is_some());
         self.sessions
             .set_sessions(session_ui_infos, forbidden_sessions);
     }
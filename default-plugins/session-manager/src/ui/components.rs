@@ -422,6 +422,9 @@ pub fn build_session_ui_line(session_ui_info: &SessionUiInfo, colors: Colors) ->
     ui_spans.push(session_name_span);
     ui_spans.push(tab_and_pane_count);
     ui_spans.push(connected_users_count);
+    if let Some(remote_state) = build_remote_state_span(session_ui_info, colors) {
+        ui_spans.push(remote_state);
+    }
     if session_ui_info.is_current_session {
         let current_session_indication = UiSpan::UiSpanTelescope(UiSpanTelescope::new(vec![
             StringAndLength::new(
@@ -436,6 +439,30 @@ pub fn build_session_ui_line(session_ui_info: &SessionUiInfo, colors: Colors) ->
     ui_spans
 }
 
+fn build_remote_state_span(session_ui_info: &SessionUiInfo, colors: Colors) -> Option<UiSpan> {
+    let remote_clients = format!("{}", session_ui_info.remote_client_count);
+    let remote_clients_styled = colors.connected_users(&remote_clients);
+    let mut details = match session_ui_info.remote_listen_address.as_ref() {
+        Some(address) => format!("serving on {address}"),
+        None if session_ui_info.remote_client_count > 0 => "stopped".to_owned(),
+        None => return None,
+    };
+    if let Some(controller) = session_ui_info.remote_controller.as_ref() {
+        details.push_str(&format!(", {controller} in control"));
+    }
+    Some(UiSpan::UiSpanTelescope(UiSpanTelescope::new(vec![
+        StringAndLength::new(
+            format!(" [remote: {remote_clients_styled} connected, {details}]"),
+            2 + 8 + remote_clients.width() + 12 + details.width() + 1,
+        ),
+        StringAndLength::new(
+            format!(" [remote: {remote_clients_styled}]"),
+            2 + 8 + remote_clients.width() + 1,
+        ),
+        StringAndLength::new(format!(" [R]"), 4),
+    ])))
+}
+
 pub fn build_tab_ui_line(tab_ui_info: &TabUiInfo, colors: Colors) -> Vec<UiSpan> {
     let mut ui_spans = vec![];
     let tab_name = &tab_ui_info.name;
@@ -945,11 +972,19 @@ pub fn render_controls_line(
             let kill_text = colors.bold("Kill");
             let kill_all = colors.shortcuts("<Ctrl d>");
             let kill_all_text = colors.bold("Kill all");
+            let remote = colors.shortcuts("<Ctrl s>");
+            let remote_text = colors.bold("Remote on/off");
 
-            if max_cols > 90 {
+            if max_cols > 115 {
+                print!(
+                    "\u{1b}[m\u{1b}[{y};{x}HHelp: {rename} - {rename_text}, {disconnect} - {disconnect_text}, {kill} - {kill_text}, {kill_all} - {kill_all_text}, {remote} - {remote_text}"
+                );
+            } else if max_cols > 90 {
                 print!(
                     "\u{1b}[m\u{1b}[{y};{x}HHelp: {rename} - {rename_text}, {disconnect} - {disconnect_text}, {kill} - {kill_text}, {kill_all} - {kill_all_text}"
                 );
+            } else if max_cols >= 37 {
+                print!("\u{1b}[m\u{1b}[{y};{x}H{rename}/{disconnect}/{kill}/{kill_all}/{remote}");
             } else if max_cols >= 28 {
                 print!("\u{1b}[m\u{1b}[{y};{x}H{rename}/{disconnect}/{kill}/{kill_all}");
             }
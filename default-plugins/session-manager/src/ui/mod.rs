@@ -227,6 +227,9 @@ pub struct SessionUiInfo {
     pub tabs: Vec<TabUiInfo>,
     pub connected_users: usize,
     pub is_current_session: bool,
+    pub remote_client_count: usize,
+    pub remote_controller: Option<String>,
+    pub remote_listen_address: Option<String>,
 }
 
 impl SessionUiInfo {
@@ -240,6 +243,9 @@ impl SessionUiInfo {
                 .collect(),
             connected_users: session_info.connected_clients,
             is_current_session: session_info.is_current_session,
+            remote_client_count: session_info.remote_client_count,
+            remote_controller: session_info.remote_controller.clone(),
+            remote_listen_address: session_info.remote_listen_address.clone(),
         }
     }
     pub fn line_count(&self, selected_index: &SelectedIndex) -> usize {
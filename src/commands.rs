@@ -1,5 +1,5 @@
 use dialoguer::Confirm;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::{fs::File, io::prelude::*, path::PathBuf, process, time::Duration};
 
 #[cfg(feature = "web_server_capability")]
@@ -49,7 +49,7 @@ use zellij_utils::{
     setup::Setup,
 };
 
-pub(crate) use zellij_utils::sessions::list_sessions;
+pub(crate) use zellij_utils::sessions::{list_sessions, remote_status};
 
 pub(crate) fn kill_all_sessions(yes: bool) {
     match get_sessions() {
@@ -214,6 +214,47 @@ pub(crate) fn start_web_server(
     std::process::exit(2);
 }
 
+#[cfg(feature = "remote")]
+pub(crate) fn remote_doctor(
+    config_path: Option<PathBuf>,
+    listen_addr: Option<SocketAddr>,
+    token_file: Option<PathBuf>,
+) {
+    use zellij_remote_bridge::{run_doctor, DoctorOptions};
+
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start the async runtime: {}", e);
+            process::exit(2);
+        },
+    };
+    let report = runtime.block_on(run_doctor(DoctorOptions {
+        config_path,
+        listen_addr,
+        token_file,
+    }));
+    println!("{}", report);
+    if !report.is_healthy() {
+        process::exit(1);
+    }
+}
+
+#[cfg(not(feature = "remote"))]
+pub(crate) fn remote_doctor(
+    _config_path: Option<PathBuf>,
+    _listen_addr: Option<SocketAddr>,
+    _token_file: Option<PathBuf>,
+) {
+    eprintln!(
+        "This version of Zellij was compiled without remote support, cannot run the remote doctor!"
+    );
+    process::exit(2);
+}
+
 fn create_new_client() -> ClientInfo {
     ClientInfo::New(generate_unique_session_name_or_exit(), None, None)
 }
@@ -4,7 +4,7 @@ mod tests;
 
 use clap::Parser;
 use zellij_utils::{
-    cli::{CliAction, CliArgs, Command, Sessions},
+    cli::{CliAction, CliArgs, Command, RemoteCli, Sessions},
     consts::{create_config_and_cache_folders, VERSION},
     data::UnblockCondition,
     envs,
@@ -18,6 +18,10 @@ fn main() {
     configure_logger();
     create_config_and_cache_folders();
     let opts = CliArgs::parse();
+    if opts.i_know_what_im_doing {
+        // Read by the server this process spawns, which inherits the environment
+        envs::set_remote_i_know_what_im_doing();
+    }
 
     {
         let config = Config::try_from(&opts).ok();
@@ -375,6 +379,15 @@ fn main() {
                 },
             }
         }
+    } else if let Some(Command::Remote(RemoteCli::Doctor {
+        remote_config,
+        addr,
+        token_file,
+    })) = &opts.command
+    {
+        commands::remote_doctor(remote_config.clone(), *addr, token_file.clone());
+    } else if let Some(Command::Remote(RemoteCli::Status { verbose })) = &opts.command {
+        commands::remote_status(*verbose);
     } else {
         commands::start_client(opts);
     }
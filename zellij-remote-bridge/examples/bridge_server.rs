@@ -0,0 +1,60 @@
+//! `RemoteBridge` driven by a config file.
+//!
+//! With `--remote-config <path>` the TOML file (see `BridgeConfig::from_file`) is
+//! watched while the bridge runs: token and allowlist changes apply to the next client
+//! to connect, without dropping those already connected. `--conformance-echo` sends
+//! clients offering the conformance echo extension the rendering fixtures.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use tokio_util::sync::CancellationToken;
+use zellij_remote_bridge::{
+    conformance_echo, watch_config_file, BridgeConfig, ExtensionRegistry, RemoteBridge,
+};
+
+#[derive(Parser, Debug)]
+#[clap(name = "bridge_server", about = "Zellij remote bridge")]
+struct Args {
+    /// TOML bridge config, reloaded when it changes
+    #[clap(long, value_name = "PATH", env = "ZELLIJ_REMOTE_CONFIG")]
+    remote_config: Option<PathBuf>,
+    /// Answer clients offering x-conformance-echo with the rendering fixtures
+    #[clap(long)]
+    conformance_echo: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let shutdown = CancellationToken::new();
+    let config = match &args.remote_config {
+        Some(path) => {
+            let config = BridgeConfig::from_file(path)?;
+            tokio::spawn(watch_config_file(
+                path.clone(),
+                config.clone(),
+                shutdown.clone(),
+            ));
+            config
+        },
+        None => BridgeConfig::default(),
+    };
+
+    let ctrl_c_shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        let _ = tokio::signal::ctrl_c().await;
+        ctrl_c_shutdown.cancel();
+    });
+    let mut extensions = ExtensionRegistry::new();
+    if args.conformance_echo {
+        conformance_echo::register(&mut extensions);
+    }
+    RemoteBridge::new(config)
+        .with_extensions(extensions)
+        .run_with_shutdown(shutdown)
+        .await
+}
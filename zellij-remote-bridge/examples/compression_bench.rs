@@ -0,0 +1,153 @@
+//! Size and CPU cost of compressing render messages, per codec and zstd level.
+//!
+//!     cargo run --release -p zellij-remote-bridge --example compression_bench [iterations]
+//!
+//! Compares a full 200x50 snapshot of shell-like output with a single-keystroke delta,
+//! the two ends of what `CompressionConfig` chooses between.
+
+use std::env;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use prost::Message;
+use zellij_remote_bridge::compression::MIN_COMPRESS_BYTES;
+use zellij_remote_protocol::{
+    stream_envelope, CellRun, CursorState, RowData, RowPatch, ScreenDelta, ScreenSnapshot,
+    StreamEnvelope, StyleDef,
+};
+
+const COLS: u32 = 200;
+const ROWS: u32 = 50;
+const ZSTD_LEVELS: [i32; 4] = [1, 3, 9, 19];
+
+fn snapshot() -> StreamEnvelope {
+    let lines = [
+        "drwxr-xr-x  12 user staff   384 Oct 17 09:12 zellij-remote-bridge",
+        "-rw-r--r--   1 user staff  4211 Oct 17 09:10 Cargo.toml",
+        "$ cargo test -p zellij-remote-core -- --nocapture",
+        "test result: ok. 312 passed; 0 failed; 0 ignored; finished in 1.84s",
+        "warning: unused variable: `frame_size`",
+    ];
+    let rows = (0..ROWS)
+        .map(|row| {
+            let line = lines[row as usize % lines.len()];
+            let codepoints: Vec<u32> = line
+                .chars()
+                .chain(std::iter::repeat(' '))
+                .take(COLS as usize)
+                .map(|c| c as u32)
+                .collect();
+            RowData {
+                row,
+                style_ids: codepoints
+                    .iter()
+                    .map(|&c| if c == ' ' as u32 { 0 } else { 1 + row % 4 })
+                    .collect(),
+                widths: vec![1; COLS as usize],
+                codepoints,
+                line_size: 0,
+                col_start: 0,
+            }
+        })
+        .collect();
+    StreamEnvelope {
+        msg: Some(stream_envelope::Msg::ScreenSnapshot(ScreenSnapshot {
+            state_id: 1,
+            style_table_reset: true,
+            styles: (1..=4)
+                .map(|style_id| StyleDef {
+                    style_id,
+                    style: None,
+                })
+                .collect(),
+            rows,
+            cursor: Some(CursorState::default()),
+            ..Default::default()
+        })),
+        envelope_seq: 0,
+    }
+}
+
+fn keystroke_delta() -> StreamEnvelope {
+    StreamEnvelope {
+        msg: Some(stream_envelope::Msg::ScreenDeltaStream(ScreenDelta {
+            base_state_id: 1,
+            state_id: 2,
+            row_patches: vec![RowPatch {
+                row: 49,
+                runs: vec![CellRun {
+                    col_start: 12,
+                    codepoints: vec!['l' as u32],
+                    widths: vec![1],
+                    style_ids: vec![0],
+                }],
+                line_size: 0,
+                echo: false,
+                row_crc32: 0,
+            }],
+            cursor: Some(CursorState::default()),
+            ..Default::default()
+        })),
+        envelope_seq: 0,
+    }
+}
+
+fn time<T>(iterations: u32, mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        std::hint::black_box(f());
+    }
+    start.elapsed() / iterations
+}
+
+fn bench(name: &str, envelope: &StreamEnvelope, iterations: u32) -> Result<()> {
+    let encoded = envelope.encode_to_vec();
+    println!("{} ({} bytes encoded)", name, encoded.len());
+    if encoded.len() < MIN_COMPRESS_BYTES {
+        println!(
+            "  below MIN_COMPRESS_BYTES ({}), sent uncompressed",
+            MIN_COMPRESS_BYTES
+        );
+    }
+    println!(
+        "  {:<10} {:>10} {:>8} {:>14} {:>14}",
+        "codec", "bytes", "ratio", "compress", "decompress"
+    );
+    println!(
+        "  {:<10} {:>10} {:>8.2} {:>14?} {:>14?}",
+        "none",
+        encoded.len(),
+        1.0,
+        time(iterations, || envelope.encode_to_vec()),
+        time(iterations, || StreamEnvelope::decode(&encoded[..]))
+    );
+    for level in ZSTD_LEVELS {
+        let compressed = zstd::bulk::compress(&encoded, level)?;
+        let compress_time = time(iterations, || zstd::bulk::compress(&encoded, level));
+        let decompress_time = time(iterations, || {
+            zstd::bulk::decompress(&compressed, encoded.len())
+        });
+        println!(
+            "  {:<10} {:>10} {:>8.2} {:>14?} {:>14?}",
+            format!("zstd-{}", level),
+            compressed.len(),
+            encoded.len() as f64 / compressed.len() as f64,
+            compress_time,
+            decompress_time
+        );
+    }
+    println!();
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let iterations: u32 = env::args()
+        .nth(1)
+        .map(|arg| arg.parse())
+        .transpose()?
+        .unwrap_or(200);
+
+    bench("Snapshot 200x50", &snapshot(), iterations)?;
+    bench("Keystroke delta", &keystroke_delta(), iterations)?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Allocations made encoding a 60 FPS delta stream, with and without buffer reuse.
+//!
+//!     cargo run --release -p zellij-remote-bridge --example encode_bench [seconds]
+//!
+//! Drives a 200x50 session one keystroke per frame, as a sender task would see it
+//! while someone types, and counts heap allocations in delta building and in
+//! `encode_envelope` versus `encode_envelope_into`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use zellij_remote_bridge::{encode_envelope, encode_envelope_into, EncodeBuffer};
+use zellij_remote_core::{Cell, RemoteSession, RenderUpdate};
+use zellij_remote_protocol::{stream_envelope, StateAck, StreamEnvelope};
+
+const COLS: usize = 200;
+const ROWS: usize = 50;
+const FPS: u64 = 60;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[derive(Default)]
+struct Tally {
+    allocations: usize,
+    bytes: usize,
+    time: Duration,
+}
+
+impl Tally {
+    fn measure<T>(&mut self, f: impl FnOnce() -> T) -> T {
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+        let start = Instant::now();
+        let result = f();
+        self.time += start.elapsed();
+        self.allocations += ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+        self.bytes += ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes;
+        result
+    }
+
+    fn print(&self, name: &str, frames: u64) {
+        println!(
+            "  {:<22} {:>10.2} {:>12.1} {:>12?}",
+            name,
+            self.allocations as f64 / frames as f64,
+            self.bytes as f64 / frames as f64,
+            self.time / frames as u32
+        );
+    }
+}
+
+fn type_char(session: &mut RemoteSession, frame: u64) {
+    let col = frame as usize % COLS;
+    let row = ROWS - 1;
+    session.frame_store.update_row(row, |r| {
+        r.set_cell(
+            col,
+            Cell {
+                codepoint: 'a' as u32 + (frame % 26) as u32,
+                width: 1,
+                style_id: 0,
+            },
+        )
+    });
+    session.frame_store.advance_state();
+}
+
+fn ack(session: &mut RemoteSession, state_id: u64) {
+    session.process_state_ack(
+        1,
+        &StateAck {
+            last_applied_state_id: state_id,
+            ..Default::default()
+        },
+    );
+}
+
+fn main() -> Result<()> {
+    let seconds: u64 = env::args()
+        .nth(1)
+        .map(|arg| arg.parse())
+        .transpose()?
+        .unwrap_or(10);
+    let frames = seconds * FPS;
+
+    let mut session = RemoteSession::new(COLS, ROWS);
+    session.add_client(1, 4);
+    if let Some(RenderUpdate::Snapshot(snapshot)) = session.get_render_update(1) {
+        ack(&mut session, snapshot.state_id);
+    }
+
+    let mut deltas = Tally::default();
+    let mut fresh = Tally::default();
+    let mut reused = Tally::default();
+    let mut buf = EncodeBuffer::new();
+    let mut sent = 0;
+
+    for frame in 0..frames {
+        type_char(&mut session, frame);
+        let Some(RenderUpdate::Delta(delta)) = deltas.measure(|| session.get_render_update(1))
+        else {
+            continue;
+        };
+        let state_id = delta.state_id;
+        let envelope = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ScreenDeltaStream(delta)),
+            envelope_seq: 0,
+        };
+        fresh.measure(|| encode_envelope(&envelope).map(|encoded| encoded.len()))?;
+        reused.measure(|| encode_envelope_into(&envelope, &mut buf).map(|e| e.len()))?;
+        sent += 1;
+        ack(&mut session, state_id);
+    }
+    if sent == 0 {
+        anyhow::bail!("the session produced no deltas");
+    }
+
+    println!(
+        "{} deltas, {}s of typing at {} FPS ({}x{})",
+        sent, seconds, FPS, COLS, ROWS
+    );
+    println!(
+        "  {:<22} {:>10} {:>12} {:>12}",
+        "per frame", "allocs", "bytes", "time"
+    );
+    deltas.print("build delta", sent);
+    fresh.print("encode_envelope", sent);
+    reused.print("encode_envelope_into", sent);
+    Ok(())
+}
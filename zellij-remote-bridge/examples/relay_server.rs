@@ -0,0 +1,44 @@
+//! Minimal relay for hosts behind NAT.
+//!
+//! Hosts register with `RELAY_URL=https://<relay>:4433` (see `spike_server`), clients
+//! connect to `https://<relay>:4433/s/<session>`.
+//!
+//! Environment: `LISTEN_ADDR` (default `0.0.0.0:4433`), `RELAY_TOKEN` (registration
+//! secret, plaintext or a `sha256:` hash), `TLS_CERT`/`TLS_KEY` (PEM files; a
+//! self-signed certificate is generated otherwise).
+
+use anyhow::Result;
+use tokio_util::sync::CancellationToken;
+use zellij_remote_bridge::{RelayServer, RelayServerConfig};
+use zellij_remote_core::TokenHash;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let listen_addr: std::net::SocketAddr = std::env::var("LISTEN_ADDR")
+        .unwrap_or_else(|_| "0.0.0.0:4433".to_string())
+        .parse()
+        .expect("Invalid LISTEN_ADDR");
+    let relay_token = std::env::var("RELAY_TOKEN").ok().map(|token| {
+        TokenHash::parse(&token).unwrap_or_else(|| TokenHash::from_plaintext(token.as_bytes()))
+    });
+
+    let relay = RelayServer::bind(RelayServerConfig {
+        listen_addr,
+        tls_cert: std::env::var_os("TLS_CERT").map(Into::into),
+        tls_key: std::env::var_os("TLS_KEY").map(Into::into),
+        relay_token,
+        ..Default::default()
+    })
+    .await?;
+    println!("Relay listening on {}", relay.local_addr()?);
+
+    let shutdown = CancellationToken::new();
+    let ctrl_c_shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        let _ = tokio::signal::ctrl_c().await;
+        ctrl_c_shutdown.cancel();
+    });
+    relay.run_with_shutdown(shutdown).await
+}
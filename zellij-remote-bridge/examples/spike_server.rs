@@ -1,13 +1,14 @@
 use anyhow::Result;
 use bytes::BytesMut;
-use prost::Message;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::RwLock;
-use zellij_remote_bridge::{decode_datagram_envelope, encode_envelope};
+use tokio_util::codec::Decoder;
+use zellij_remote_bridge::{
+    decode_datagram_envelope, encode_envelope, RelayClient, RelayClientConfig, ZrpCodec,
+};
 use zellij_remote_core::{
-    Cell, FrameStore, InputError, LeaseResult, RemoteSession, RenderUpdate, ResumeResult,
+    AttachRequest, Cell, FrameStore, InputError, LeaseResult, RemoteSession, RemoteSessionHandle,
+    RenderUpdate, SessionHandleError, WindowBounds,
 };
 use zellij_remote_protocol::{
     datagram_envelope, input_event, key_event, stream_envelope, Capabilities, ClientHello,
@@ -19,8 +20,6 @@ const SCREEN_COLS: usize = 80;
 const SCREEN_ROWS: usize = 24;
 const DEFAULT_RENDER_WINDOW: u32 = 4;
 
-static CLIENT_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
-
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
@@ -42,28 +41,51 @@ async fn main() -> Result<()> {
 
     let server = wtransport::Endpoint::server(config)?;
 
-    let session = Arc::new(RwLock::new(RemoteSession::new(SCREEN_COLS, SCREEN_ROWS)));
-
-    {
-        let mut s = session.write().await;
-        draw_welcome_screen(&mut s.frame_store);
-        s.frame_store.advance_state();
-        s.record_state_snapshot();
-    }
+    let mut initial_session = RemoteSession::new(SCREEN_COLS, SCREEN_ROWS);
+    draw_welcome_screen(&mut initial_session.frame_store);
+    initial_session.frame_store.advance_state();
+    initial_session.record_state_snapshot();
+    let session = RemoteSessionHandle::spawn(initial_session);
 
     let session_updater = session.clone();
     tokio::spawn(async move {
         let mut counter = 0u32;
         loop {
             tokio::time::sleep(Duration::from_millis(500)).await;
-            let mut session = session_updater.write().await;
-            update_animation(&mut session.frame_store, counter);
-            session.frame_store.advance_state();
-            session.record_state_snapshot();
+            let updated = session_updater
+                .with_session(move |session| {
+                    update_animation(&mut session.frame_store, counter);
+                    session.frame_store.advance_state();
+                    session.record_state_snapshot();
+                })
+                .await;
+            if updated.is_err() {
+                break;
+            }
             counter += 1;
         }
     });
 
+    // Behind NAT: register with a relay and serve the clients it forwards
+    if let Ok(relay_url) = std::env::var("RELAY_URL") {
+        let mut relay = RelayClient::register(RelayClientConfig {
+            relay_url,
+            session_name: std::env::var("SESSION_NAME").unwrap_or_else(|_| "spike".to_string()),
+            relay_token: std::env::var("RELAY_TOKEN").ok(),
+            accept_invalid_certs: true,
+        })
+        .await?;
+        loop {
+            let connection = relay.next_connection().await?;
+            let session = session.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(connection, session).await {
+                    log::error!("Connection error: {}", e);
+                }
+            });
+        }
+    }
+
     log::info!("WebTransport server listening on {}", listen_addr);
 
     loop {
@@ -85,47 +107,29 @@ async fn main() -> Result<()> {
 
 async fn handle_connection(
     connection: wtransport::Connection,
-    session: Arc<RwLock<RemoteSession>>,
+    session: RemoteSessionHandle,
 ) -> Result<()> {
     let (mut send, mut recv) = connection.accept_bi().await?;
 
     let client_hello = read_client_hello(&mut recv).await?;
 
-    let (client_id, resumed) = {
-        let mut s = session.write().await;
-
-        if !client_hello.resume_token.is_empty() {
-            match s.try_resume(&client_hello.resume_token, DEFAULT_RENDER_WINDOW) {
-                ResumeResult::Resumed {
-                    client_id,
-                    baseline_state_id,
-                } => {
-                    log::info!(
-                        "Client {} resumed from state_id={} (total clients: {})",
-                        client_id,
-                        baseline_state_id,
-                        s.client_count()
-                    );
-                    (client_id, true)
-                },
-                reason => {
-                    log::info!("Resume token rejected ({:?}), creating new client", reason);
-                    let client_id = CLIENT_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
-                    s.add_client(client_id, DEFAULT_RENDER_WINDOW);
-                    (client_id, false)
-                },
-            }
-        } else {
-            let client_id = CLIENT_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
-            s.add_client(client_id, DEFAULT_RENDER_WINDOW);
-            log::info!(
-                "Client {} connected (total clients: {})",
-                client_id,
-                s.client_count()
-            );
-            (client_id, false)
-        }
-    };
+    let window_bounds = WindowBounds::negotiate(
+        WindowBounds::default(),
+        client_hello.min_render_window,
+        client_hello.max_render_window,
+    );
+    let attached = session
+        .attach(AttachRequest {
+            resume_token: client_hello.resume_token.clone(),
+            window_bounds,
+            desired_size: Some(DisplaySize { cols: 80, rows: 24 }),
+        })
+        .await?;
+    let client_id = attached.client_id;
+    let resumed = attached.resumed;
+    if let Some(reason) = &attached.resume_rejected {
+        log::info!("Resume token rejected ({:?}), created new client", reason);
+    }
 
     log::info!(
         "Received ClientHello from {} (client_id={}, resumed={})",
@@ -134,28 +138,18 @@ async fn handle_connection(
         resumed
     );
 
-    let (server_hello, resume_token) = {
-        let mut s = session.write().await;
-        let lease = s.lease_manager.request_control(
-            client_id,
-            Some(DisplaySize { cols: 80, rows: 24 }),
-            false,
-        );
-
-        let lease_info = match lease {
-            LeaseResult::Granted(l) => Some(l),
-            LeaseResult::Denied { .. } => s.lease_manager.get_current_lease(),
-        };
-
-        let resume_token = s.generate_resume_token(client_id);
-        (
-            build_server_hello(&client_hello, client_id, lease_info, resume_token.clone()),
-            resume_token,
-        )
-    };
+    let resume_token = attached.resume_token;
+    let server_hello = build_server_hello(
+        &client_hello,
+        client_id,
+        attached.lease,
+        resume_token.clone(),
+        window_bounds,
+    );
 
     let encoded = encode_envelope(&StreamEnvelope {
         msg: Some(stream_envelope::Msg::ServerHello(server_hello)),
+        envelope_seq: 0,
     })?;
     send.write_all(&encoded).await?;
     log::info!(
@@ -165,18 +159,20 @@ async fn handle_connection(
     );
 
     {
-        let mut s = session.write().await;
+        let update = session.get_update(client_id).await?;
         if resumed {
-            if let Some(RenderUpdate::Delta(delta)) = s.get_render_update(client_id) {
+            if let Some(RenderUpdate::Delta(delta)) = update {
                 let encoded = encode_envelope(&StreamEnvelope {
                     msg: Some(stream_envelope::Msg::ScreenDeltaStream(delta)),
+                    envelope_seq: 0,
                 })?;
                 send.write_all(&encoded).await?;
                 log::info!("Sent resume delta to client {}", client_id);
             }
-        } else if let Some(RenderUpdate::Snapshot(snapshot)) = s.get_render_update(client_id) {
+        } else if let Some(RenderUpdate::Snapshot(snapshot)) = update {
             let encoded = encode_envelope(&StreamEnvelope {
                 msg: Some(stream_envelope::Msg::ScreenSnapshot(snapshot)),
+                envelope_seq: 0,
             })?;
             send.write_all(&encoded).await?;
             log::info!("Sent initial ScreenSnapshot to client {}", client_id);
@@ -190,12 +186,18 @@ async fn handle_connection(
                 Ok(datagram) => {
                     if let Ok(envelope) = decode_datagram_envelope(&datagram) {
                         if let Some(datagram_envelope::Msg::StateAck(state_ack)) = envelope.msg {
-                            let mut s = session_for_datagrams.write().await;
-                            s.process_state_ack(client_id, &state_ack);
+                            let last_applied_state_id = state_ack.last_applied_state_id;
+                            if session_for_datagrams
+                                .ack(client_id, state_ack)
+                                .await
+                                .is_err()
+                            {
+                                break;
+                            }
                             log::debug!(
                                 "Processed StateAck from client {}: last_applied={}",
                                 client_id,
-                                state_ack.last_applied_state_id
+                                last_applied_state_id
                             );
                         }
                     }
@@ -227,44 +229,44 @@ async fn handle_connection(
                 while let Some(envelope) = decode_envelope(&mut buffer)? {
                     match envelope.msg {
                         Some(stream_envelope::Msg::InputEvent(input)) => {
-                            let ack = {
-                                let mut s = session.write().await;
-                                match s.process_input(client_id, &input) {
-                                    Ok(ack) => {
+                            let ack = match session.input(client_id, input.clone()).await {
+                                Ok(ack) => {
+                                    session.with_session(move |s| {
                                         handle_input_effect(&mut s.frame_store, &input);
                                         s.frame_store.advance_state();
-                                        Some(ack)
-                                    }
-                                    Err(InputError::NotController) => {
-                                        log::warn!("Client {} sent input but is not controller", client_id);
-                                        None
-                                    }
-                                    Err(InputError::Duplicate) => {
-                                        log::debug!("Duplicate input from client {}", client_id);
-                                        None
-                                    }
-                                    Err(e) => {
-                                        log::warn!("Input error from client {}: {:?}", client_id, e);
-                                        None
-                                    }
+                                    }).await?;
+                                    Some(ack)
+                                }
+                                Err(SessionHandleError::Input(InputError::NotController)) => {
+                                    log::warn!("Client {} sent input but is not controller", client_id);
+                                    None
+                                }
+                                Err(SessionHandleError::Input(InputError::Duplicate)) => {
+                                    log::debug!("Duplicate input from client {}", client_id);
+                                    None
+                                }
+                                Err(SessionHandleError::Closed) => {
+                                    anyhow::bail!("session stopped");
+                                }
+                                Err(e) => {
+                                    log::warn!("Input error from client {}: {:?}", client_id, e);
+                                    None
                                 }
                             };
 
                             if let Some(ack) = ack {
                                 let encoded = encode_envelope(&StreamEnvelope {
                                     msg: Some(stream_envelope::Msg::InputAck(ack)),
+                                    envelope_seq: 0,
                                 })?;
                                 send.write_all(&encoded).await?;
                             }
                         }
                         Some(stream_envelope::Msg::RequestControl(req)) => {
                             let response = {
-                                let mut s = session.write().await;
-                                let result = s.lease_manager.request_control(
-                                    client_id,
-                                    req.desired_size,
-                                    req.force,
-                                );
+                                let result = session
+                                    .request_control(client_id, req.desired_size, req.force)
+                                    .await?;
 
                                 match result {
                                     LeaseResult::Granted(lease) => {
@@ -285,6 +287,7 @@ async fn handle_connection(
 
                             let encoded = encode_envelope(&StreamEnvelope {
                                 msg: Some(response),
+                                envelope_seq: 0,
                             })?;
                             send.write_all(&encoded).await?;
                         }
@@ -295,15 +298,13 @@ async fn handle_connection(
                 }
             }
             _ = tokio::time::sleep(Duration::from_millis(100)) => {
-                let update = {
-                    let mut s = session.write().await;
-                    s.get_render_update(client_id)
-                };
+                let update = session.get_update(client_id).await?;
 
                 match update {
                     Some(RenderUpdate::Snapshot(snapshot)) => {
                         let encoded = encode_envelope(&StreamEnvelope {
                             msg: Some(stream_envelope::Msg::ScreenSnapshot(snapshot)),
+                            envelope_seq: 0,
                         })?;
                         if let Err(e) = send.write_all(&encoded).await {
                             log::warn!("Failed to send snapshot to client {}: {}", client_id, e);
@@ -314,6 +315,7 @@ async fn handle_connection(
                         if !delta.row_patches.is_empty() || delta.cursor.is_some() {
                             let encoded = encode_envelope(&StreamEnvelope {
                                 msg: Some(stream_envelope::Msg::ScreenDeltaStream(delta)),
+                                envelope_seq: 0,
                             })?;
                             if let Err(e) = send.write_all(&encoded).await {
                                 log::warn!("Failed to send delta to client {}: {}", client_id, e);
@@ -327,15 +329,13 @@ async fn handle_connection(
         }
     }
 
-    {
-        let mut s = session.write().await;
-        s.remove_client(client_id);
-        log::info!(
-            "Client {} disconnected (remaining: {})",
-            client_id,
-            s.client_count()
-        );
-    }
+    session.detach(client_id).await?;
+    let remaining = session.with_session(|s| s.client_count()).await?;
+    log::info!(
+        "Client {} disconnected (remaining: {})",
+        client_id,
+        remaining
+    );
 
     Ok(())
 }
@@ -420,34 +420,7 @@ async fn read_client_hello(recv: &mut wtransport::RecvStream) -> Result<ClientHe
 }
 
 fn decode_envelope(buf: &mut BytesMut) -> Result<Option<StreamEnvelope>> {
-    use bytes::Buf;
-
-    if buf.is_empty() {
-        return Ok(None);
-    }
-
-    let mut peek = &buf[..];
-    let len = match prost::encoding::decode_varint(&mut peek) {
-        Ok(len) => len as usize,
-        Err(_) => {
-            if buf.len() < 10 {
-                return Ok(None);
-            }
-            anyhow::bail!("invalid varint in frame header");
-        },
-    };
-
-    let varint_len = buf.len() - peek.len();
-    let total_len = varint_len + len;
-
-    if buf.len() < total_len {
-        return Ok(None);
-    }
-
-    buf.advance(varint_len);
-    let frame_data = buf.split_to(len);
-    let envelope = StreamEnvelope::decode(&frame_data[..])?;
-    Ok(Some(envelope))
+    Ok(ZrpCodec::<StreamEnvelope>::new().decode(buf)?)
 }
 
 fn build_server_hello(
@@ -455,6 +428,7 @@ fn build_server_hello(
     client_id: u64,
     lease: Option<zellij_remote_protocol::ControllerLease>,
     resume_token: Vec<u8>,
+    window_bounds: WindowBounds,
 ) -> ServerHello {
     let negotiated_caps = Capabilities {
         supports_datagrams: client_hello
@@ -469,6 +443,18 @@ fn build_server_hello(
         supports_images: false,
         supports_clipboard: false,
         supports_hyperlinks: false,
+        supports_line_size: false,
+        supports_text_mode: false,
+        supports_frame_hash: false,
+        supports_style_retention: false,
+        supports_priority_lanes: false,
+        supports_row_checksums: false,
+        supports_push_streams: false,
+        supports_bell: false,
+        supports_lease_status: false,
+        max_message_bytes: 0,
+        supports_pane_layout: false,
+        supports_keybinding_info: false,
     };
 
     ServerHello {
@@ -484,7 +470,13 @@ fn build_server_hello(
         resume_token,
         snapshot_interval_ms: 5000,
         max_inflight_inputs: 256,
-        render_window: DEFAULT_RENDER_WINDOW,
+        render_window: window_bounds.clamp(DEFAULT_RENDER_WINDOW),
+        min_render_window: window_bounds.min,
+        max_render_window: window_bounds.max,
+        keepalive_interval_ms: 0,
+        alternate_endpoints: vec![],
+        compression: None,
+        extensions: vec![],
     }
 }
 
@@ -0,0 +1,61 @@
+use std::net::IpAddr;
+
+use tokio::sync::broadcast;
+use zellij_remote_protocol::ClientRole;
+
+const AUDIT_CHANNEL_SIZE: usize = 256;
+
+/// Security-relevant events emitted by the bridge, attributed to the token a client used.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuditEvent {
+    ClientAuthenticated {
+        client_id: u64,
+        client_name: String,
+        /// Where the client connected from, as named by a trusted proxy if it came
+        /// through one; None for clients that came through a relay
+        client_ip: Option<IpAddr>,
+        /// None when authentication is disabled
+        token_id: Option<String>,
+        role: ClientRole,
+    },
+    AuthenticationFailed {
+        client_id: u64,
+        client_ip: Option<IpAddr>,
+        /// Set when the token matched but was rejected (e.g. expired)
+        token_id: Option<String>,
+        reason: String,
+    },
+    ClientDisconnected {
+        client_id: u64,
+        token_id: Option<String>,
+    },
+}
+
+/// Fan-out of audit events to any number of subscribers; events are also logged.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    tx: broadcast::Sender<AuditEvent>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(AUDIT_CHANNEL_SIZE);
+        Self { tx }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<AuditEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn emit(&self, event: AuditEvent) {
+        log::info!(target: "zellij_remote_audit", "{:?}", event);
+        // No subscribers is fine: the event has already been logged
+        let _ = self.tx.send(event);
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,296 @@
+//! The length-prefixed framing every ZRP stream uses.
+//!
+//! A frame is a protobuf message behind its length as a varint. `ZrpCodec` reads and
+//! writes them for `tokio_util::codec`, and the helpers in [`crate::framing`], the
+//! server's read loop and the example clients all go through it, so they agree on
+//! what's incomplete, what's malformed and how big a frame may get.
+
+use std::marker::PhantomData;
+
+use bytes::{Buf, BufMut, BytesMut};
+use prost::Message;
+use tokio_util::codec::{Decoder, Encoder};
+use zellij_remote_protocol::StreamEnvelope;
+
+/// Largest frame a codec reads or writes unless given another limit
+pub const DEFAULT_MAX_FRAME_BYTES: usize = 16 * 1024 * 1024;
+/// Longest a varint length can be
+const MAX_VARINT_BYTES: usize = 10;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FrameError {
+    #[error("frame size {len} exceeds maximum allowed size {max} bytes")]
+    TooLarge { len: u64, max: usize },
+    #[error("invalid varint in frame header")]
+    InvalidLength,
+    #[error("failed to decode frame: {0}")]
+    Decode(#[from] prost::DecodeError),
+    #[error("failed to encode frame: {0}")]
+    Encode(#[from] prost::EncodeError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Frames `M` messages, refusing any bigger than `max_frame_bytes`.
+///
+/// An oversized length is refused as soon as it's read, before its body is buffered,
+/// so a peer can't make the reader hold more than the limit.
+#[derive(Debug)]
+pub struct ZrpCodec<M = StreamEnvelope> {
+    max_frame_bytes: usize,
+    _message: PhantomData<fn() -> M>,
+}
+
+impl<M> ZrpCodec<M> {
+    pub fn new() -> Self {
+        Self::with_max_frame_bytes(DEFAULT_MAX_FRAME_BYTES)
+    }
+
+    pub fn with_max_frame_bytes(max_frame_bytes: usize) -> Self {
+        Self {
+            max_frame_bytes,
+            _message: PhantomData,
+        }
+    }
+
+    pub fn max_frame_bytes(&self) -> usize {
+        self.max_frame_bytes
+    }
+
+    /// Write `message` as a frame onto the end of `buf`
+    pub fn encode_into<B: BufMut>(&self, message: &M, buf: &mut B) -> Result<(), FrameError>
+    where
+        M: Message,
+    {
+        let len = message.encoded_len();
+        if len > self.max_frame_bytes {
+            return Err(FrameError::TooLarge {
+                len: len as u64,
+                max: self.max_frame_bytes,
+            });
+        }
+        prost::encoding::encode_varint(len as u64, buf);
+        message.encode(buf)?;
+        Ok(())
+    }
+}
+
+impl<M> Default for ZrpCodec<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Clone for ZrpCodec<M> {
+    fn clone(&self) -> Self {
+        Self::with_max_frame_bytes(self.max_frame_bytes)
+    }
+}
+
+impl<M: Message + Default> Decoder for ZrpCodec<M> {
+    type Item = M;
+    type Error = FrameError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<M>, FrameError> {
+        let mut peek = &src[..];
+        let len = match prost::encoding::decode_varint(&mut peek) {
+            Ok(len) => len,
+            // Every byte so far says another follows
+            Err(_) if src.len() < MAX_VARINT_BYTES => return Ok(None),
+            Err(_) => return Err(FrameError::InvalidLength),
+        };
+        if len > self.max_frame_bytes as u64 {
+            return Err(FrameError::TooLarge {
+                len,
+                max: self.max_frame_bytes,
+            });
+        }
+        let len = len as usize;
+        let varint_len = src.len() - peek.len();
+        if src.len() < varint_len + len {
+            src.reserve(varint_len + len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(varint_len);
+        let frame = src.split_to(len);
+        Ok(Some(M::decode(frame.freeze())?))
+    }
+}
+
+impl<M: Message> Encoder<&M> for ZrpCodec<M> {
+    type Error = FrameError;
+
+    fn encode(&mut self, message: &M, dst: &mut BytesMut) -> Result<(), FrameError> {
+        dst.reserve(message.encoded_len() + MAX_VARINT_BYTES);
+        self.encode_into(message, dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zellij_remote_protocol::{stream_envelope, ClientHello, PushHeader};
+
+    fn hello(client_name: &str) -> StreamEnvelope {
+        StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ClientHello(ClientHello {
+                client_name: client_name.to_string(),
+                ..Default::default()
+            })),
+            envelope_seq: 3,
+        }
+    }
+
+    fn encoded(messages: &[StreamEnvelope]) -> BytesMut {
+        let mut codec = ZrpCodec::new();
+        let mut buf = BytesMut::new();
+        for message in messages {
+            codec.encode(message, &mut buf).unwrap();
+        }
+        buf
+    }
+
+    #[test]
+    fn test_every_split_point_decodes_the_same() {
+        let messages = vec![hello("a"), hello(&"b".repeat(300)), hello("")];
+        let wire = encoded(&messages);
+
+        for split in 0..=wire.len() {
+            let mut codec = ZrpCodec::<StreamEnvelope>::new();
+            let mut buf = BytesMut::from(&wire[..split]);
+            let mut decoded = vec![];
+            while let Some(message) = codec.decode(&mut buf).unwrap() {
+                decoded.push(message);
+            }
+            buf.extend_from_slice(&wire[split..]);
+            while let Some(message) = codec.decode(&mut buf).unwrap() {
+                decoded.push(message);
+            }
+            assert_eq!(decoded, messages, "split at {}", split);
+            assert!(buf.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_one_byte_at_a_time() {
+        let messages = vec![hello("first"), hello(&"x".repeat(200))];
+        let wire = encoded(&messages);
+
+        let mut codec = ZrpCodec::<StreamEnvelope>::new();
+        let mut buf = BytesMut::new();
+        let mut completed_at = vec![];
+        for (i, byte) in wire.iter().enumerate() {
+            buf.put_u8(*byte);
+            if let Some(message) = codec.decode(&mut buf).unwrap() {
+                assert_eq!(message, messages[completed_at.len()]);
+                completed_at.push(i);
+            }
+        }
+        let first_len = encoded(&messages[..1]).len();
+        assert_eq!(completed_at, vec![first_len - 1, wire.len() - 1]);
+    }
+
+    #[test]
+    fn test_partial_multibyte_length_is_incomplete() {
+        let wire = encoded(&[hello(&"y".repeat(1000))]);
+        // 1000+ bytes takes a two byte varint
+        assert!(wire[0] & 0x80 != 0);
+        let mut buf = BytesMut::from(&wire[..1]);
+        assert!(ZrpCodec::<StreamEnvelope>::new()
+            .decode(&mut buf)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            buf.len(),
+            1,
+            "nothing consumed before the frame is complete"
+        );
+
+        // Nine continuation bytes could still be a length, ten can't
+        let mut buf = BytesMut::from(&[0xFF; 9][..]);
+        assert!(ZrpCodec::<StreamEnvelope>::new()
+            .decode(&mut buf)
+            .unwrap()
+            .is_none());
+        let mut buf = BytesMut::from(&[0xFF; 10][..]);
+        assert!(matches!(
+            ZrpCodec::<StreamEnvelope>::new().decode(&mut buf),
+            Err(FrameError::InvalidLength)
+        ));
+    }
+
+    #[test]
+    fn test_oversized_frames_refused_before_their_body_arrives() {
+        let message = hello(&"z".repeat(100));
+        let wire = encoded(std::slice::from_ref(&message));
+        let body_len = message.encoded_len();
+
+        let mut exact = ZrpCodec::<StreamEnvelope>::with_max_frame_bytes(body_len);
+        assert_eq!(
+            exact.decode(&mut wire.clone()).unwrap(),
+            Some(message.clone())
+        );
+
+        // Only the length has arrived, and that's enough to refuse it
+        let mut codec = ZrpCodec::<StreamEnvelope>::with_max_frame_bytes(body_len - 1);
+        let mut header = BytesMut::from(&wire[..1]);
+        match codec.decode(&mut header) {
+            Err(FrameError::TooLarge { len, max }) => {
+                assert_eq!(len, body_len as u64);
+                assert_eq!(max, body_len - 1);
+            },
+            other => panic!("expected TooLarge, got {:?}", other),
+        }
+
+        // A length past what usize holds on any target is refused too
+        let mut huge = BytesMut::new();
+        prost::encoding::encode_varint(u64::MAX, &mut huge);
+        assert!(matches!(
+            ZrpCodec::<StreamEnvelope>::new().decode(&mut huge),
+            Err(FrameError::TooLarge { .. })
+        ));
+
+        let mut out = BytesMut::new();
+        assert!(matches!(
+            codec.encode(&message, &mut out),
+            Err(FrameError::TooLarge { .. })
+        ));
+        assert!(out.is_empty(), "nothing written for a refused frame");
+    }
+
+    #[test]
+    fn test_corrupt_body_is_a_decode_error() {
+        let mut buf = BytesMut::from(&[5u8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF][..]);
+        assert!(matches!(
+            ZrpCodec::<StreamEnvelope>::new().decode(&mut buf),
+            Err(FrameError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn test_empty_message_is_a_zero_length_frame() {
+        let mut buf = BytesMut::new();
+        ZrpCodec::new()
+            .encode(&PushHeader::default(), &mut buf)
+            .unwrap();
+        assert_eq!(&buf[..], &[0]);
+        assert_eq!(
+            ZrpCodec::<PushHeader>::new().decode(&mut buf).unwrap(),
+            Some(PushHeader::default())
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_stream_ending_mid_frame_is_an_error() {
+        let wire = encoded(&[hello("one"), hello("two")]);
+        let mut codec = ZrpCodec::<StreamEnvelope>::new();
+        let mut buf = BytesMut::from(&wire[..wire.len() - 1]);
+        assert!(codec.decode_eof(&mut buf).unwrap().is_some());
+        assert!(matches!(codec.decode_eof(&mut buf), Err(FrameError::Io(_))));
+
+        let mut buf = BytesMut::new();
+        assert!(codec.decode_eof(&mut buf).unwrap().is_none());
+    }
+}
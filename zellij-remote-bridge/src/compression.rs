@@ -0,0 +1,263 @@
+//! Per-category compression of stream envelopes.
+//!
+//! The client lists the codecs it can decompress in `ClientHello.supported_codecs`;
+//! the server picks one per message category and reports it in
+//! `ServerHello.compression`. Compressed messages travel as a `CompressedEnvelope`
+//! wrapping the encoded original, so peers that negotiated nothing never see one.
+
+use anyhow::Result;
+use prost::Message;
+use zellij_remote_protocol::{
+    stream_envelope, Codec, CompressedEnvelope, CompressionConfig, StreamEnvelope,
+};
+
+use crate::framing::{encode_envelope, encode_envelope_into, EncodeBuffer};
+
+/// Messages smaller than this go out as they are; the codec header would eat the gain
+pub const MIN_COMPRESS_BYTES: usize = 256;
+/// zstd level for snapshots: most of the ratio of higher levels at a fraction of the CPU
+pub const ZSTD_LEVEL: i32 = 3;
+/// Largest message a `CompressedEnvelope` may expand to
+pub const MAX_UNCOMPRESSED_BYTES: usize = 16 * 1024 * 1024;
+
+/// Pick a codec per category from what the client can decompress.
+///
+/// Snapshots are large and compress well, so they get zstd. Deltas are small and
+/// latency-bound; compressing them costs more time than the bytes it saves.
+pub fn negotiate_compression(client_codecs: &[i32]) -> CompressionConfig {
+    let snapshots = if client_codecs.contains(&(Codec::Zstd as i32)) {
+        Codec::Zstd
+    } else {
+        Codec::None
+    };
+    CompressionConfig {
+        snapshots: snapshots as i32,
+        deltas: Codec::None as i32,
+    }
+}
+
+/// The codec `config` assigns to `envelope`'s category.
+pub fn codec_for(config: &CompressionConfig, envelope: &StreamEnvelope) -> Codec {
+    match envelope.msg {
+        Some(stream_envelope::Msg::ScreenSnapshot(_)) => config.snapshots(),
+        Some(stream_envelope::Msg::ScreenDeltaStream(_)) => config.deltas(),
+        _ => Codec::None,
+    }
+}
+
+/// Like [`encode_envelope`], compressing the message if `config` says so and it pays off.
+pub fn encode_envelope_compressed(
+    envelope: &StreamEnvelope,
+    config: &CompressionConfig,
+) -> Result<Vec<u8>> {
+    match compressed_form(envelope, config)? {
+        Some(compressed) => encode_envelope(&compressed),
+        None => encode_envelope(envelope),
+    }
+}
+
+/// Like [`encode_envelope_compressed`], writing the frame into `buf`.
+pub fn encode_envelope_compressed_into<'a>(
+    envelope: &StreamEnvelope,
+    config: &CompressionConfig,
+    buf: &'a mut EncodeBuffer,
+) -> Result<&'a [u8]> {
+    match compressed_form(envelope, config)? {
+        Some(compressed) => encode_envelope_into(&compressed, buf),
+        None => encode_envelope_into(envelope, buf),
+    }
+}
+
+/// The `CompressedEnvelope` to send in place of `envelope`, if compressing pays off
+fn compressed_form(
+    envelope: &StreamEnvelope,
+    config: &CompressionConfig,
+) -> Result<Option<StreamEnvelope>> {
+    let codec = codec_for(config, envelope);
+    if codec == Codec::None || envelope.encoded_len() < MIN_COMPRESS_BYTES {
+        return Ok(None);
+    }
+
+    let encoded = envelope.encode_to_vec();
+    let payload = compress(codec, &encoded)?;
+    if payload.len() >= encoded.len() {
+        return Ok(None);
+    }
+    Ok(Some(StreamEnvelope {
+        msg: Some(stream_envelope::Msg::Compressed(CompressedEnvelope {
+            codec: codec as i32,
+            uncompressed_len: encoded.len() as u32,
+            payload,
+        })),
+        envelope_seq: envelope.envelope_seq,
+    }))
+}
+
+/// Unwrap a `CompressedEnvelope`, keeping its `envelope_seq`; any other message is
+/// returned unchanged.
+pub fn decompress_envelope(envelope: StreamEnvelope) -> Result<StreamEnvelope> {
+    let compressed = match envelope.msg {
+        Some(stream_envelope::Msg::Compressed(compressed)) => compressed,
+        msg => {
+            return Ok(StreamEnvelope {
+                msg,
+                envelope_seq: envelope.envelope_seq,
+            })
+        },
+    };
+    let len = compressed.uncompressed_len as usize;
+    if len > MAX_UNCOMPRESSED_BYTES {
+        anyhow::bail!("compressed envelope expands to {} bytes", len);
+    }
+    let encoded = decompress(compressed.codec(), &compressed.payload, len)?;
+    let mut inner = StreamEnvelope::decode(&encoded[..])?;
+    if matches!(inner.msg, Some(stream_envelope::Msg::Compressed(_))) {
+        anyhow::bail!("nested compressed envelope");
+    }
+    inner.envelope_seq = envelope.envelope_seq;
+    Ok(inner)
+}
+
+pub fn compress(codec: Codec, data: &[u8]) -> Result<Vec<u8>> {
+    match codec {
+        Codec::None => Ok(data.to_vec()),
+        Codec::Zstd => Ok(zstd::bulk::compress(data, ZSTD_LEVEL)?),
+    }
+}
+
+pub fn decompress(codec: Codec, data: &[u8], uncompressed_len: usize) -> Result<Vec<u8>> {
+    let decompressed = match codec {
+        Codec::None => data.to_vec(),
+        Codec::Zstd => zstd::bulk::decompress(data, uncompressed_len)?,
+    };
+    if decompressed.len() != uncompressed_len {
+        anyhow::bail!(
+            "decompressed {} bytes, expected {}",
+            decompressed.len(),
+            uncompressed_len
+        );
+    }
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framing::{decode_envelope, DecodeResult};
+    use bytes::BytesMut;
+    use zellij_remote_protocol::{Ping, RowData, ScreenDelta, ScreenSnapshot};
+
+    fn snapshot(rows: u32) -> StreamEnvelope {
+        StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ScreenSnapshot(ScreenSnapshot {
+                rows: (0..rows)
+                    .map(|row| RowData {
+                        row,
+                        codepoints: vec!['x' as u32; 80],
+                        widths: vec![1; 80],
+                        style_ids: vec![0; 80],
+                        line_size: 0,
+                        col_start: 0,
+                    })
+                    .collect(),
+                ..Default::default()
+            })),
+            envelope_seq: 0,
+        }
+    }
+
+    fn roundtrip(encoded: &[u8]) -> StreamEnvelope {
+        let mut buf = BytesMut::from(encoded);
+        match decode_envelope(&mut buf).unwrap() {
+            DecodeResult::Complete(envelope) => envelope,
+            DecodeResult::Incomplete => panic!("expected complete decode"),
+        }
+    }
+
+    #[test]
+    fn test_negotiation_follows_client_codecs() {
+        let config = negotiate_compression(&[Codec::Zstd as i32]);
+        assert_eq!(config.snapshots(), Codec::Zstd);
+        assert_eq!(config.deltas(), Codec::None);
+
+        let config = negotiate_compression(&[]);
+        assert_eq!(config.snapshots(), Codec::None);
+    }
+
+    #[test]
+    fn test_snapshot_compressed_and_restored() {
+        let config = negotiate_compression(&[Codec::Zstd as i32]);
+        let original = StreamEnvelope {
+            envelope_seq: 7,
+            ..snapshot(24)
+        };
+        let encoded = encode_envelope_compressed(&original, &config).unwrap();
+        assert!(encoded.len() < encode_envelope(&original).unwrap().len());
+
+        let received = roundtrip(&encoded);
+        assert!(matches!(
+            received.msg,
+            Some(stream_envelope::Msg::Compressed(_))
+        ));
+        assert_eq!(received.envelope_seq, 7);
+        assert_eq!(decompress_envelope(received).unwrap(), original);
+    }
+
+    #[test]
+    fn test_small_and_uncategorized_messages_sent_plain() {
+        let config = CompressionConfig {
+            snapshots: Codec::Zstd as i32,
+            deltas: Codec::Zstd as i32,
+        };
+        let delta = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ScreenDeltaStream(
+                ScreenDelta::default(),
+            )),
+            envelope_seq: 0,
+        };
+        let ping = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::Ping(Ping::default())),
+            envelope_seq: 0,
+        };
+        for envelope in [delta, ping] {
+            let encoded = encode_envelope_compressed(&envelope, &config).unwrap();
+            assert_eq!(encoded, encode_envelope(&envelope).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_compressed_into_matches_compressed() {
+        let config = negotiate_compression(&[Codec::Zstd as i32]);
+        let mut buf = EncodeBuffer::new();
+        for envelope in [snapshot(24), snapshot(0)] {
+            assert_eq!(
+                encode_envelope_compressed_into(&envelope, &config, &mut buf).unwrap(),
+                &encode_envelope_compressed(&envelope, &config).unwrap()[..]
+            );
+        }
+    }
+
+    #[test]
+    fn test_bad_compressed_envelopes_rejected() {
+        let oversized = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::Compressed(CompressedEnvelope {
+                codec: Codec::Zstd as i32,
+                uncompressed_len: (MAX_UNCOMPRESSED_BYTES + 1) as u32,
+                payload: vec![],
+            })),
+            envelope_seq: 0,
+        };
+        assert!(decompress_envelope(oversized).is_err());
+
+        let payload = compress(Codec::Zstd, &snapshot(24).encode_to_vec()).unwrap();
+        let wrong_len = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::Compressed(CompressedEnvelope {
+                codec: Codec::Zstd as i32,
+                uncompressed_len: 10,
+                payload,
+            })),
+            envelope_seq: 0,
+        };
+        assert!(decompress_envelope(wrong_len).is_err());
+    }
+}
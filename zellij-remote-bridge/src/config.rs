@@ -1,5 +1,17 @@
-use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
+use tokio_util::sync::CancellationToken;
+use zellij_remote_core::{SharedTokenRegistry, TokenEntry, TokenHash, TokenRegistry};
+use zellij_remote_protocol::ClientRole;
+
+use crate::relay::RelayClientConfig;
+
+/// How often [`watch_config_file`] checks the file for changes
+pub const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
 #[derive(Debug, Clone)]
 pub struct BridgeConfig {
@@ -10,6 +22,19 @@ pub struct BridgeConfig {
     pub max_clients_per_session: usize,
     pub render_window: u32,
     pub controller_lease_duration_ms: u32,
+    /// Accepted bearer tokens; empty disables authentication. Cloned configs share the
+    /// same registry, so `tokens.replace(..)` takes effect without a restart.
+    pub tokens: SharedTokenRegistry,
+    /// Source addresses allowed to connect; empty allows any. Shared like `tokens`.
+    pub allowlist: SharedAllowlist,
+    /// Reverse proxies trusted to name the client they forward in `X-Forwarded-For`;
+    /// empty trusts none
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Serve clients through a relay instead of listening on `listen_addr`
+    pub relay: Option<RelayClientConfig>,
+    /// Most frames per second sent to the controller and to viewers. Shared like
+    /// `tokens`, so whatever paces frames should read it per frame.
+    pub frame_rate_caps: SharedFrameRateCaps,
 }
 
 impl Default for BridgeConfig {
@@ -22,6 +47,470 @@ impl Default for BridgeConfig {
             max_clients_per_session: 10,
             render_window: 4,
             controller_lease_duration_ms: 30000,
+            tokens: SharedTokenRegistry::default(),
+            allowlist: SharedAllowlist::default(),
+            trusted_proxies: Vec::new(),
+            relay: None,
+            frame_rate_caps: SharedFrameRateCaps::default(),
         }
     }
 }
+
+/// Most frames per second a client is sent, by its role; None leaves it uncapped.
+/// Passive viewers rarely need as many updates as the person typing, so capping
+/// them cuts the bandwidth of sessions with many watchers. Frames held back by a cap
+/// coalesce into the next one, so nothing on screen is lost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameRateCaps {
+    pub controller_max_fps: Option<u32>,
+    pub viewer_max_fps: Option<u32>,
+}
+
+impl FrameRateCaps {
+    /// The least time between two frames for a controller or a viewer (0: uncapped)
+    pub fn min_frame_interval_ms(&self, controller: bool) -> u64 {
+        let max_fps = if controller {
+            self.controller_max_fps
+        } else {
+            self.viewer_max_fps
+        };
+        max_fps
+            .filter(|fps| *fps > 0)
+            .map_or(0, |fps| 1000 / fps as u64)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid bridge config: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("token {id:?}: {reason}")]
+    InvalidToken { id: String, reason: &'static str },
+}
+
+/// On-disk form of [`BridgeConfig`]; anything left out keeps its default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    listen_addr: Option<SocketAddr>,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+    session_name: Option<String>,
+    max_clients_per_session: Option<usize>,
+    render_window: Option<u32>,
+    controller_lease_duration_ms: Option<u32>,
+    controller_max_fps: Option<u32>,
+    viewer_max_fps: Option<u32>,
+    allowed_ips: Vec<IpAddr>,
+    trusted_proxies: Vec<IpAddr>,
+    tokens: Vec<TokenFileEntry>,
+    relay: Option<RelayFileEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TokenFileEntry {
+    id: String,
+    /// `TokenHash::encode` form, so plaintext tokens never sit in the file
+    hash: String,
+    #[serde(default)]
+    role: Option<String>,
+    #[serde(default)]
+    expires_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RelayFileEntry {
+    relay_url: String,
+    session_name: Option<String>,
+    relay_token: Option<String>,
+    #[serde(default)]
+    accept_invalid_certs: bool,
+}
+
+impl BridgeConfig {
+    /// Load a TOML config file, e.g.
+    ///
+    /// ```toml
+    /// listen_addr = "0.0.0.0:4433"
+    /// session_name = "work"
+    /// allowed_ips = ["100.64.0.7"]
+    /// trusted_proxies = ["10.0.0.1"]
+    /// viewer_max_fps = 10
+    ///
+    /// [[tokens]]
+    /// id = "alice"
+    /// hash = "sha256:<salt>:<digest>"
+    /// role = "viewer"   # "controller" (the default), or "snapshot" for one-shot captures;
+    ///                   # recorded in audit events but not enforced by the bridge yet
+    /// ```
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml_str(&contents)
+    }
+
+    pub fn from_toml_str(contents: &str) -> Result<Self, ConfigError> {
+        let file: ConfigFile = toml::from_str(contents)?;
+        let defaults = Self::default();
+
+        let mut tokens = Vec::with_capacity(file.tokens.len());
+        for entry in file.tokens {
+            let hash = TokenHash::parse(&entry.hash).ok_or(ConfigError::InvalidToken {
+                id: entry.id.clone(),
+                reason: "hash must be sha256:<salt>:<digest>",
+            })?;
+            let role = match entry.role.as_deref() {
+                None | Some("controller") => ClientRole::Controller,
+                Some("viewer") => ClientRole::Viewer,
+                Some("snapshot") => ClientRole::Snapshot,
+                Some(_) => {
+                    return Err(ConfigError::InvalidToken {
+                        id: entry.id,
+                        reason: "role must be \"controller\", \"viewer\" or \"snapshot\"",
+                    })
+                },
+            };
+            tokens.push(TokenEntry {
+                id: entry.id,
+                hash,
+                role,
+                expires_at_ms: entry.expires_at_ms,
+            });
+        }
+
+        let session_name = file.session_name.unwrap_or(defaults.session_name);
+        let relay = file.relay.map(|relay| RelayClientConfig {
+            relay_url: relay.relay_url,
+            session_name: relay.session_name.unwrap_or_else(|| session_name.clone()),
+            relay_token: relay.relay_token,
+            accept_invalid_certs: relay.accept_invalid_certs,
+        });
+
+        Ok(Self {
+            listen_addr: file.listen_addr.unwrap_or(defaults.listen_addr),
+            tls_cert: file.tls_cert,
+            tls_key: file.tls_key,
+            session_name,
+            max_clients_per_session: file
+                .max_clients_per_session
+                .unwrap_or(defaults.max_clients_per_session),
+            render_window: file.render_window.unwrap_or(defaults.render_window),
+            controller_lease_duration_ms: file
+                .controller_lease_duration_ms
+                .unwrap_or(defaults.controller_lease_duration_ms),
+            tokens: SharedTokenRegistry::new(TokenRegistry::new(tokens)),
+            allowlist: SharedAllowlist::new(file.allowed_ips),
+            trusted_proxies: file.trusted_proxies,
+            relay,
+            frame_rate_caps: SharedFrameRateCaps::new(FrameRateCaps {
+                controller_max_fps: file.controller_max_fps,
+                viewer_max_fps: file.viewer_max_fps,
+            }),
+        })
+    }
+
+    /// Take on `new`'s tokens and allowlist, which apply from the next connection on,
+    /// and its frame rate caps, which apply from the next frame; connected clients
+    /// stay. Returns the settings that differ but only take effect once the bridge
+    /// restarts.
+    pub fn reload_from(&self, new: &BridgeConfig) -> Vec<&'static str> {
+        self.tokens.replace(new.tokens.snapshot());
+        self.allowlist.replace(new.allowlist.snapshot());
+        self.frame_rate_caps.replace(new.frame_rate_caps.get());
+
+        let mut needs_restart = Vec::new();
+        if self.listen_addr != new.listen_addr {
+            needs_restart.push("listen_addr");
+        }
+        if self.tls_cert != new.tls_cert || self.tls_key != new.tls_key {
+            needs_restart.push("tls");
+        }
+        if self.session_name != new.session_name {
+            needs_restart.push("session_name");
+        }
+        if self.max_clients_per_session != new.max_clients_per_session {
+            needs_restart.push("max_clients_per_session");
+        }
+        if self.render_window != new.render_window {
+            needs_restart.push("render_window");
+        }
+        if self.controller_lease_duration_ms != new.controller_lease_duration_ms {
+            needs_restart.push("controller_lease_duration_ms");
+        }
+        if self.trusted_proxies != new.trusted_proxies {
+            needs_restart.push("trusted_proxies");
+        }
+        if self.relay != new.relay {
+            needs_restart.push("relay");
+        }
+        needs_restart
+    }
+}
+
+/// Source addresses allowed to connect, shared between the accept loop and whoever
+/// reloads them. An empty list allows any address.
+#[derive(Debug, Clone, Default)]
+pub struct SharedAllowlist(Arc<RwLock<Vec<IpAddr>>>);
+
+impl SharedAllowlist {
+    pub fn new(addrs: Vec<IpAddr>) -> Self {
+        Self(Arc::new(RwLock::new(addrs)))
+    }
+
+    pub fn replace(&self, addrs: Vec<IpAddr>) {
+        match self.0.write() {
+            Ok(mut guard) => *guard = addrs,
+            Err(poisoned) => *poisoned.into_inner() = addrs,
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<IpAddr> {
+        match self.0.read() {
+            Ok(guard) => guard.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// IPv4 peers seen through a dual-stack socket match their IPv4 entry.
+    pub fn allows(&self, addr: IpAddr) -> bool {
+        let addrs = self.snapshot();
+        addrs.is_empty() || addrs.contains(&addr.to_canonical())
+    }
+}
+
+/// Frame rate caps shared between the frame pacing and whoever reloads them.
+#[derive(Debug, Clone, Default)]
+pub struct SharedFrameRateCaps(Arc<RwLock<FrameRateCaps>>);
+
+impl SharedFrameRateCaps {
+    pub fn new(caps: FrameRateCaps) -> Self {
+        Self(Arc::new(RwLock::new(caps)))
+    }
+
+    pub fn replace(&self, caps: FrameRateCaps) {
+        match self.0.write() {
+            Ok(mut guard) => *guard = caps,
+            Err(poisoned) => *poisoned.into_inner() = caps,
+        }
+    }
+
+    pub fn get(&self) -> FrameRateCaps {
+        match self.0.read() {
+            Ok(guard) => *guard,
+            Err(poisoned) => *poisoned.into_inner(),
+        }
+    }
+}
+
+/// Re-read `path` whenever it changes and apply it to `config` with
+/// [`BridgeConfig::reload_from`]. A file that fails to load is logged and ignored, so a
+/// half-written edit never drops the tokens in use. Runs until `shutdown` fires.
+pub async fn watch_config_file(path: PathBuf, config: BridgeConfig, shutdown: CancellationToken) {
+    poll_config_file(path, config, CONFIG_POLL_INTERVAL, shutdown).await
+}
+
+async fn poll_config_file(
+    path: PathBuf,
+    config: BridgeConfig,
+    poll_interval: Duration,
+    shutdown: CancellationToken,
+) {
+    let mut last_seen = file_stamp(&path);
+    let mut interval = tokio::time::interval(poll_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+
+        let stamp = file_stamp(&path);
+        if stamp == last_seen || stamp.is_none() {
+            continue;
+        }
+        last_seen = stamp;
+
+        match BridgeConfig::from_file(&path) {
+            Ok(new_config) => {
+                let needs_restart = config.reload_from(&new_config);
+                log::info!(
+                    "Reloaded bridge config from {}: {} tokens, {} allowed addresses",
+                    path.display(),
+                    new_config.tokens.snapshot().len(),
+                    new_config.allowlist.snapshot().len()
+                );
+                if !needs_restart.is_empty() {
+                    log::warn!(
+                        "Bridge config changes to {} take effect after a restart",
+                        needs_restart.join(", ")
+                    );
+                }
+            },
+            Err(e) => log::error!("Keeping the current bridge config: {}", e),
+        }
+    }
+}
+
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(token: &[u8]) -> String {
+        TokenHash::from_plaintext(token).encode()
+    }
+
+    #[test]
+    fn test_from_toml_fills_defaults() {
+        let config = BridgeConfig::from_toml_str(&format!(
+            r#"
+            session_name = "work"
+            allowed_ips = ["100.64.0.7"]
+            trusted_proxies = ["10.0.0.1"]
+
+            [[tokens]]
+            id = "alice"
+            hash = "{}"
+
+            [[tokens]]
+            id = "kiosk"
+            hash = "{}"
+            role = "viewer"
+            "#,
+            hash(b"alice-token"),
+            hash(b"kiosk-token")
+        ))
+        .unwrap();
+
+        assert_eq!(config.session_name, "work");
+        assert_eq!(config.listen_addr, BridgeConfig::default().listen_addr);
+        assert_eq!(config.tokens.snapshot().len(), 2);
+        assert!(matches!(
+            config.tokens.snapshot().authenticate(b"kiosk-token"),
+            zellij_remote_core::AuthOutcome::Accepted {
+                role: ClientRole::Viewer,
+                ..
+            }
+        ));
+        assert!(config.allowlist.allows("100.64.0.7".parse().unwrap()));
+        assert!(!config.allowlist.allows("100.64.0.8".parse().unwrap()));
+        assert!(config
+            .allowlist
+            .allows("::ffff:100.64.0.7".parse().unwrap()));
+        assert_eq!(
+            config.trusted_proxies,
+            vec!["10.0.0.1".parse::<IpAddr>().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_from_toml_rejects_bad_entries() {
+        let err = BridgeConfig::from_toml_str(
+            r#"
+            [[tokens]]
+            id = "alice"
+            hash = "plaintext"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidToken { .. }));
+
+        let err = BridgeConfig::from_toml_str(&format!(
+            "[[tokens]]\nid = \"alice\"\nhash = \"{}\"\nrole = \"admin\"\n",
+            hash(b"alice-token")
+        ))
+        .unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidToken { .. }));
+
+        assert!(matches!(
+            BridgeConfig::from_toml_str("listen_port = 4433"),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_frame_rate_caps_from_toml() {
+        let config =
+            BridgeConfig::from_toml_str("controller_max_fps = 60\nviewer_max_fps = 10\n").unwrap();
+        let caps = config.frame_rate_caps.get();
+        assert_eq!(caps.min_frame_interval_ms(true), 16);
+        assert_eq!(caps.min_frame_interval_ms(false), 100);
+
+        let uncapped = BridgeConfig::from_toml_str("viewer_max_fps = 0\n")
+            .unwrap()
+            .frame_rate_caps
+            .get();
+        assert_eq!(uncapped.min_frame_interval_ms(true), 0);
+        assert_eq!(uncapped.min_frame_interval_ms(false), 0);
+    }
+
+    #[test]
+    fn test_reload_swaps_tokens_and_reports_restart_settings() {
+        let running = BridgeConfig::default();
+        let cloned = running.clone();
+
+        let new = BridgeConfig::from_toml_str(&format!(
+            "listen_addr = \"0.0.0.0:4433\"\nallowed_ips = [\"10.0.0.1\"]\nviewer_max_fps = 10\n\n[[tokens]]\nid = \"alice\"\nhash = \"{}\"\n",
+            hash(b"alice-token")
+        ))
+        .unwrap();
+
+        assert_eq!(running.reload_from(&new), vec!["listen_addr"]);
+        // Clones share the live settings
+        assert_eq!(cloned.tokens.snapshot().len(), 1);
+        assert!(!cloned.allowlist.allows("10.0.0.2".parse().unwrap()));
+        assert_eq!(cloned.frame_rate_caps.get().viewer_max_fps, Some(10));
+        assert!(running.reload_from(&running.clone()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watch_applies_file_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bridge.toml");
+        std::fs::write(&path, "").unwrap();
+
+        let config = BridgeConfig::from_file(&path).unwrap();
+        let shutdown = CancellationToken::new();
+        let watcher = tokio::spawn(poll_config_file(
+            path.clone(),
+            config.clone(),
+            Duration::from_millis(10),
+            shutdown.clone(),
+        ));
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        std::fs::write(
+            &path,
+            format!(
+                "[[tokens]]\nid = \"alice\"\nhash = \"{}\"\n",
+                hash(b"alice-token")
+            ),
+        )
+        .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(config.tokens.snapshot().len(), 1);
+
+        // A broken edit keeps the tokens in use
+        std::fs::write(&path, "[[tokens]]\nid = \"alice\"\n").unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(config.tokens.snapshot().len(), 1);
+
+        shutdown.cancel();
+        watcher.await.unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+//! Conformance echo mode.
+//!
+//! A client that offers the [`CONFORMANCE_ECHO_EXTENSION`] handshake extension is
+//! sent the rendering fixtures from `zellij_remote_protocol::fixtures` right after
+//! the `ServerHello`, so a third-party implementation can check how it draws them
+//! without a live session. The offer's payload names the fixtures to send, comma
+//! separated; an empty payload asks for all of them. An offer naming a fixture the
+//! server doesn't have is declined.
+
+use anyhow::Result;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use zellij_remote_protocol::fixtures::{fixture, Fixture, FIXTURES};
+use zellij_remote_protocol::StreamEnvelope;
+
+use crate::extensions::ExtensionRegistry;
+use crate::framing::encode_envelope;
+
+pub const CONFORMANCE_ECHO_EXTENSION: &str = "x-conformance-echo";
+
+/// Accept conformance echo offers on this registry
+pub fn register(registry: &mut ExtensionRegistry) {
+    registry.register(CONFORMANCE_ECHO_EXTENSION, |offer: &[u8]| {
+        let fixtures = requested_fixtures(offer)?;
+        let names: Vec<&str> = fixtures.iter().map(|fixture| fixture.name).collect();
+        Some(names.join(",").into_bytes())
+    });
+}
+
+/// The fixtures a payload names, in order, or None if it names one that doesn't exist
+fn requested_fixtures(payload: &[u8]) -> Option<Vec<&'static Fixture>> {
+    let names = std::str::from_utf8(payload).ok()?.trim();
+    if names.is_empty() {
+        return Some(FIXTURES.iter().collect());
+    }
+    names.split(',').map(|name| fixture(name.trim())).collect()
+}
+
+/// The messages echoing the fixtures `payload` names, state ids counting up from 1
+/// across all of them
+pub fn echo_messages(payload: &[u8]) -> Vec<StreamEnvelope> {
+    let mut next_state_id = 1;
+    let mut messages = Vec::new();
+    for fixture in requested_fixtures(payload).unwrap_or_default() {
+        let fixture_messages = (fixture.build)(next_state_id);
+        next_state_id += fixture_messages.len() as u64;
+        messages.extend(fixture_messages.into_iter().map(|msg| StreamEnvelope {
+            msg: Some(msg),
+            envelope_seq: 0,
+        }));
+    }
+    messages
+}
+
+/// Write the fixtures `payload` names to `writer`, returning how many messages went out
+pub async fn send_echo<W>(writer: &mut W, payload: &[u8]) -> Result<usize>
+where
+    W: AsyncWrite + Unpin,
+{
+    let messages = echo_messages(payload);
+    for envelope in &messages {
+        writer.write_all(&encode_envelope(envelope)?).await?;
+    }
+    writer.flush().await?;
+    Ok(messages.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framing::{decode_envelope, DecodeResult};
+    use bytes::BytesMut;
+    use zellij_remote_protocol::{stream_envelope, Extension};
+
+    fn offer(payload: &str) -> Extension {
+        Extension {
+            name: CONFORMANCE_ECHO_EXTENSION.to_string(),
+            payload: payload.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_offers_answered_with_the_fixtures_sent() {
+        let mut registry = ExtensionRegistry::new();
+        register(&mut registry);
+
+        let all = registry.negotiate(&[offer("")]);
+        let names: Vec<&str> = FIXTURES.iter().map(|fixture| fixture.name).collect();
+        assert_eq!(all[0].payload, names.join(",").into_bytes());
+
+        let some = registry.negotiate(&[offer("wide_chars, styles")]);
+        assert_eq!(some[0].payload, b"wide_chars,styles".to_vec());
+
+        assert!(registry.negotiate(&[offer("styles,nope")]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_echo_numbers_states_across_fixtures() {
+        let mut written = Vec::new();
+        let sent = send_echo(&mut written, b"wide_chars,styles").await.unwrap();
+
+        let mut buffer = BytesMut::from(&written[..]);
+        let mut state_ids = Vec::new();
+        while let DecodeResult::Complete(envelope) = decode_envelope(&mut buffer).unwrap() {
+            state_ids.push(match envelope.msg {
+                Some(stream_envelope::Msg::ScreenSnapshot(snapshot)) => snapshot.state_id,
+                Some(stream_envelope::Msg::ScreenDeltaStream(delta)) => delta.state_id,
+                other => panic!("Unexpected {:?}", other),
+            });
+        }
+        // wide_chars is a snapshot and a delta, styles one snapshot
+        assert_eq!(sent, 3);
+        assert_eq!(state_ids, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,213 @@
+//! Protocol traffic dumped as JSON, for bug reports.
+//!
+//! Each envelope sent or received becomes one line of canonical JSON (keys sorted, no
+//! whitespace) with a timestamp, its direction, the channel it went over and its size
+//! on the wire, so a report can be read without capturing and decoding QUIC. Tokens
+//! are redacted, an envelope too big to be worth reading is reduced to its type, and
+//! the dump stops growing at `MAX_DUMP_BYTES`.
+
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Size a dump stops growing at
+pub const MAX_DUMP_BYTES: u64 = 64 * 1024 * 1024;
+/// Longest line a single envelope is written as; bigger ones only keep their type
+pub const MAX_RECORD_BYTES: usize = 64 * 1024;
+/// Fields holding credentials, written as their length only
+const REDACTED_FIELDS: &[&str] = &[
+    "bearer_token",
+    "resume_token",
+    "relay_token",
+    "token_secret",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// Writes one JSON line per envelope until it has written `max_bytes`
+pub struct DebugDump<W: Write> {
+    writer: W,
+    started: Instant,
+    written: u64,
+    max_bytes: u64,
+    full: bool,
+}
+
+impl DebugDump<LineWriter<File>> {
+    /// Dump to a new file at `path`, replacing any there
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(
+            LineWriter::new(File::create(path)?),
+            MAX_DUMP_BYTES,
+        ))
+    }
+}
+
+impl<W: Write> DebugDump<W> {
+    pub fn new(writer: W, max_bytes: u64) -> Self {
+        Self {
+            writer,
+            started: Instant::now(),
+            written: 0,
+            max_bytes,
+            full: false,
+        }
+    }
+
+    /// Write `envelope`, `encoded_len` bytes on the wire, as one line. Once the dump
+    /// is full a last line says so and everything after is dropped.
+    pub fn record<M: Serialize>(
+        &mut self,
+        direction: Direction,
+        channel: &str,
+        envelope: &M,
+        encoded_len: usize,
+    ) -> io::Result<()> {
+        if self.full {
+            return Ok(());
+        }
+        let mut envelope = serde_json::to_value(envelope)?;
+        redact(&mut envelope);
+        let mut record = json!({
+            "ts_ms": unix_time_ms(),
+            "elapsed_ms": self.started.elapsed().as_millis() as u64,
+            "dir": direction,
+            "channel": channel,
+            "len": encoded_len,
+            "envelope": envelope,
+        });
+        let mut line = serde_json::to_string(&record)?;
+        if line.len() > MAX_RECORD_BYTES {
+            let message_type = message_type(&record["envelope"]).map(str::to_owned);
+            record["envelope"] = json!({
+                "truncated": true,
+                "type": message_type,
+            });
+            line = serde_json::to_string(&record)?;
+        }
+        if self.written + line.len() as u64 + 1 > self.max_bytes {
+            self.full = true;
+            line = serde_json::to_string(&json!({
+                "ts_ms": unix_time_ms(),
+                "dump_full": true,
+                "written": self.written,
+            }))?;
+        }
+        self.written += line.len() as u64 + 1;
+        writeln!(self.writer, "{}", line)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+}
+
+/// Replace every credential field below `value` with its length
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if REDACTED_FIELDS.contains(&name.as_str()) {
+                    let len = field.as_array().map_or(0, Vec::len);
+                    *field = Value::String(format!("<redacted {} bytes>", len));
+                } else {
+                    redact(field);
+                }
+            }
+        },
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {},
+    }
+}
+
+/// The name of the message an envelope carries, e.g. "ScreenSnapshot"
+fn message_type(envelope: &Value) -> Option<&str> {
+    envelope["msg"]
+        .as_object()?
+        .keys()
+        .next()
+        .map(String::as_str)
+}
+
+fn unix_time_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zellij_remote_protocol::{stream_envelope, ClientHello, ScreenSnapshot, StreamEnvelope};
+
+    fn lines(dump: DebugDump<Vec<u8>>) -> Vec<Value> {
+        String::from_utf8(dump.writer)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_tokens_redacted_and_keys_sorted() {
+        let mut dump = DebugDump::new(Vec::new(), MAX_DUMP_BYTES);
+        let hello = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ClientHello(ClientHello {
+                client_name: "ios".to_string(),
+                bearer_token: b"secret".to_vec(),
+                ..Default::default()
+            })),
+            envelope_seq: 1,
+        };
+        dump.record(Direction::Sent, "control", &hello, 42).unwrap();
+
+        let raw = String::from_utf8(dump.writer.clone()).unwrap();
+        assert!(!raw.contains("115,101,99"), "token bytes leaked: {}", raw);
+        assert!(raw.starts_with("{\"channel\":\"control\",\"dir\":\"sent\""));
+
+        let record = &lines(dump)[0];
+        let hello = &record["envelope"]["msg"]["ClientHello"];
+        assert_eq!(hello["bearer_token"], "<redacted 6 bytes>");
+        assert_eq!(hello["client_name"], "ios");
+        assert_eq!(record["len"], 42);
+    }
+
+    #[test]
+    fn test_big_envelopes_and_full_dumps_cut_short() {
+        let snapshot = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ScreenSnapshot(ScreenSnapshot {
+                state_id: 1,
+                ..Default::default()
+            })),
+            envelope_seq: 0,
+        };
+        let mut huge = snapshot.clone();
+        if let Some(stream_envelope::Msg::ScreenSnapshot(snapshot)) = &mut huge.msg {
+            snapshot.rows = vec![Default::default(); MAX_RECORD_BYTES / 8];
+        }
+
+        let mut dump = DebugDump::new(Vec::new(), 1024);
+        dump.record(Direction::Received, "lane", &huge, 1).unwrap();
+        for _ in 0..20 {
+            dump.record(Direction::Received, "lane", &snapshot, 1)
+                .unwrap();
+        }
+        assert!(dump.is_full());
+
+        let records = lines(dump);
+        assert_eq!(records[0]["envelope"]["truncated"], true);
+        assert_eq!(records[0]["envelope"]["type"], "ScreenSnapshot");
+        assert_eq!(records.last().unwrap()["dump_full"], true);
+        assert!(records.len() < 21);
+    }
+}
@@ -0,0 +1,729 @@
+//! `zellij remote doctor`: checks that a bridge config can actually serve clients.
+//!
+//! Each check reports what it found and, when something is wrong, what to do about it.
+//! Checks that depend on an earlier one that failed are skipped rather than run into
+//! the same problem again.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use bytes::BytesMut;
+use tokio_util::sync::CancellationToken;
+use wtransport::tls::Sha256DigestFmt;
+use wtransport::{ClientConfig, Endpoint, Identity, ServerConfig};
+use zellij_remote_core::{AuthOutcome, TokenRegistry};
+use zellij_remote_protocol::{
+    stream_envelope, ClientHello, ProtocolVersion, StreamEnvelope, ZRP_VERSION_MAJOR,
+    ZRP_VERSION_MINOR,
+};
+
+use crate::config::{BridgeConfig, SharedAllowlist};
+use crate::framing::{decode_envelope, encode_envelope, DecodeResult};
+use crate::server::RemoteBridge;
+
+/// How long the loopback probes wait before calling a check failed
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Certificates closer than this to expiring get a warning
+pub const CERT_EXPIRY_WARNING_DAYS: i64 = 14;
+
+const PROBE_PAYLOAD: &[u8] = b"zellij-remote-doctor";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    /// Not run because something it depends on failed or wasn't provided
+    Skip,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            CheckStatus::Pass => "ok",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "FAIL",
+            CheckStatus::Skip => "skip",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to change, for anything that isn't a pass
+    pub hint: Option<String>,
+}
+
+impl CheckResult {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn skip(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Skip,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DoctorOptions {
+    /// Bridge config to check; defaults are used when absent
+    pub config_path: Option<PathBuf>,
+    /// Overrides the config's `listen_addr`
+    pub listen_addr: Option<SocketAddr>,
+    /// Bearer token file a client would present
+    pub token_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DoctorReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl DoctorReport {
+    /// True if nothing failed; warnings don't count.
+    pub fn is_healthy(&self) -> bool {
+        self.checks.iter().all(|c| c.status != CheckStatus::Fail)
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "[{:>4}] {}: {}", check.status, check.name, check.detail)?;
+            if let Some(hint) = &check.hint {
+                writeln!(f, "       -> {}", hint)?;
+            }
+        }
+        let failed = self
+            .checks
+            .iter()
+            .filter(|c| c.status == CheckStatus::Fail)
+            .count();
+        if failed == 0 {
+            write!(f, "All checks passed")
+        } else {
+            write!(f, "{} check(s) failed", failed)
+        }
+    }
+}
+
+/// Run every check in order and collect the results.
+pub async fn run_doctor(options: DoctorOptions) -> DoctorReport {
+    let mut report = DoctorReport::default();
+
+    let config = match load_config(&options) {
+        Ok((mut config, detail)) => {
+            if let Some(addr) = options.listen_addr {
+                config.listen_addr = addr;
+            }
+            report.checks.push(CheckResult::pass("config", detail));
+            config
+        },
+        Err(result) => {
+            report.checks.push(result);
+            for name in [
+                "bind",
+                "certificate",
+                "quic loopback",
+                "token",
+                "end-to-end",
+            ] {
+                report
+                    .checks
+                    .push(CheckResult::skip(name, "config could not be loaded"));
+            }
+            return report;
+        },
+    };
+
+    report.checks.push(check_bind(config.listen_addr));
+    report.checks.push(check_certificate(&config).await);
+    let quic = check_quic_loopback().await;
+    let quic_ok = quic.status != CheckStatus::Fail;
+    report.checks.push(quic);
+
+    let registry = config.tokens.snapshot();
+    let (token_check, token) = check_token_file(options.token_file.as_deref(), &registry);
+    let token_ok = token_check.status != CheckStatus::Fail;
+    report.checks.push(token_check);
+
+    report.checks.push(if !quic_ok {
+        CheckResult::skip("end-to-end", "QUIC does not work on loopback")
+    } else if !token_ok {
+        CheckResult::skip("end-to-end", "no usable bearer token")
+    } else if !registry.is_empty() && token.is_none() {
+        CheckResult::skip(
+            "end-to-end",
+            "authentication is enabled; pass --token-file to test a client login",
+        )
+    } else {
+        check_end_to_end(&config, token.unwrap_or_default()).await
+    });
+
+    report
+}
+
+fn load_config(options: &DoctorOptions) -> Result<(BridgeConfig, String), CheckResult> {
+    match &options.config_path {
+        Some(path) => match BridgeConfig::from_file(path) {
+            Ok(config) => Ok((config, format!("loaded {}", path.display()))),
+            Err(e) => Err(CheckResult::fail(
+                "config",
+                e.to_string(),
+                "fix the config file, or omit it to check the built-in defaults",
+            )),
+        },
+        None => Ok((
+            BridgeConfig::default(),
+            "no config file given, using defaults".to_string(),
+        )),
+    }
+}
+
+/// Whether the listen address can be bound right now.
+pub fn check_bind(addr: SocketAddr) -> CheckResult {
+    const NAME: &str = "bind";
+    match UdpSocket::bind(addr) {
+        Ok(_) => CheckResult::pass(NAME, format!("UDP {} is free", addr)),
+        Err(e) => bind_error_result(addr, &e),
+    }
+}
+
+fn bind_error_result(addr: SocketAddr, e: &io::Error) -> CheckResult {
+    const NAME: &str = "bind";
+    match e.kind() {
+        io::ErrorKind::AddrInUse => CheckResult::warn(
+            NAME,
+            format!("UDP {} is already in use", addr),
+            "expected if a zellij server is already running; otherwise stop whatever holds the port or change listen_addr",
+        ),
+        io::ErrorKind::PermissionDenied => CheckResult::fail(
+            NAME,
+            format!("not allowed to bind UDP {}: {}", addr, e),
+            "ports below 1024 need elevated privileges; pick a higher port",
+        ),
+        io::ErrorKind::AddrNotAvailable => CheckResult::fail(
+            NAME,
+            format!("{} is not an address of this host", addr.ip()),
+            "set listen_addr to one of this machine's addresses, or 0.0.0.0 for all of them",
+        ),
+        _ => CheckResult::fail(
+            NAME,
+            format!("cannot bind UDP {}: {}", addr, e),
+            "check listen_addr",
+        ),
+    }
+}
+
+/// Whether the configured certificate loads, and how long it stays valid.
+pub async fn check_certificate(config: &BridgeConfig) -> CheckResult {
+    const NAME: &str = "certificate";
+    let (cert_path, key_path) =
+        match (&config.tls_cert, &config.tls_key) {
+            (Some(cert), Some(key)) => (cert, key),
+            (None, None) => return CheckResult::warn(
+                NAME,
+                "no certificate configured; a self-signed one is generated on every start",
+                "clients have to skip validation; set tls_cert and tls_key to a real certificate",
+            ),
+            _ => {
+                return CheckResult::fail(
+                    NAME,
+                    "only one of tls_cert and tls_key is set",
+                    "set both, or neither to use a self-signed certificate",
+                )
+            },
+        };
+
+    let identity = match Identity::load_pemfiles(cert_path, key_path).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            return CheckResult::fail(
+                NAME,
+                format!("cannot load {}: {}", cert_path.display(), e),
+                "check that both files exist, are readable and are PEM encoded, and that the key matches the certificate",
+            )
+        },
+    };
+    let Some(leaf) = identity.certificate_chain().as_slice().first() else {
+        return CheckResult::fail(
+            NAME,
+            format!("{} contains no certificate", cert_path.display()),
+            "point tls_cert at a PEM certificate chain",
+        );
+    };
+    let fingerprint = leaf.hash().fmt(Sha256DigestFmt::DottedHex);
+
+    match certificate_validity(leaf.der()) {
+        Ok(Validity::Valid { days_left }) if days_left < CERT_EXPIRY_WARNING_DAYS => {
+            CheckResult::warn(
+                NAME,
+                format!("expires in {} day(s), sha256 {}", days_left, fingerprint),
+                "renew the certificate",
+            )
+        },
+        Ok(Validity::Valid { days_left }) => CheckResult::pass(
+            NAME,
+            format!(
+                "valid for {} more day(s), sha256 {}",
+                days_left, fingerprint
+            ),
+        ),
+        Ok(Validity::Expired { not_after }) => CheckResult::fail(
+            NAME,
+            format!("expired {}, sha256 {}", not_after, fingerprint),
+            "renew the certificate",
+        ),
+        Ok(Validity::NotYetValid { not_before }) => CheckResult::fail(
+            NAME,
+            format!("not valid until {}, sha256 {}", not_before, fingerprint),
+            "check the system clock, or reissue the certificate",
+        ),
+        Err(e) => CheckResult::fail(
+            NAME,
+            format!("cannot parse {}: {}", cert_path.display(), e),
+            "point tls_cert at an X.509 certificate",
+        ),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Validity {
+    Valid { days_left: i64 },
+    Expired { not_after: String },
+    NotYetValid { not_before: String },
+}
+
+fn certificate_validity(der: &[u8]) -> Result<Validity> {
+    let (_, cert) =
+        x509_parser::parse_x509_certificate(der).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let validity = cert.validity();
+    if let Some(left) = validity.time_to_expiration() {
+        return Ok(Validity::Valid {
+            days_left: left.whole_days(),
+        });
+    }
+    if validity.not_before.timestamp() > x509_parser::time::ASN1Time::now().timestamp() {
+        return Ok(Validity::NotYetValid {
+            not_before: validity.not_before.to_string(),
+        });
+    }
+    Ok(Validity::Expired {
+        not_after: validity.not_after.to_string(),
+    })
+}
+
+/// Whether QUIC traffic gets through on this host at all, using a throwaway server
+/// on an ephemeral loopback port.
+pub async fn check_quic_loopback() -> CheckResult {
+    const NAME: &str = "quic loopback";
+    match tokio::time::timeout(PROBE_TIMEOUT, quic_echo_probe()).await {
+        Ok(Ok(rtt)) => CheckResult::pass(NAME, format!("echo round trip in {:?}", rtt)),
+        Ok(Err(e)) => CheckResult::fail(
+            NAME,
+            format!("{:#}", e),
+            "a firewall or sandbox may be blocking UDP; remote clients need UDP to reach the server",
+        ),
+        Err(_) => CheckResult::fail(
+            NAME,
+            format!("no answer within {:?}", PROBE_TIMEOUT),
+            "a firewall may be dropping UDP on loopback",
+        ),
+    }
+}
+
+async fn quic_echo_probe() -> Result<Duration> {
+    let identity = Identity::self_signed(["localhost"])?;
+    let server_config = ServerConfig::builder()
+        .with_bind_address(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .with_identity(identity)
+        .build();
+    let server = Endpoint::server(server_config).context("cannot open a QUIC endpoint")?;
+    let port = server.local_addr()?.port();
+
+    let echo = tokio::spawn(async move {
+        let connection = server.accept().await.await?.accept().await?;
+        let (mut send, mut recv) = connection.accept_bi().await?;
+        let buf = read_probe(&mut recv).await?;
+        send.write_all(&buf).await?;
+        send.finish().await?;
+        anyhow::Ok(())
+    });
+
+    let start = std::time::Instant::now();
+    let client = loopback_client()?;
+    let connection = client
+        .connect(format!("https://127.0.0.1:{}", port))
+        .await
+        .context("QUIC handshake on loopback failed")?;
+    let (mut send, mut recv) = connection.open_bi().await?.await?;
+    send.write_all(PROBE_PAYLOAD).await?;
+    let buf = read_probe(&mut recv).await?;
+    let rtt = start.elapsed();
+    if buf != PROBE_PAYLOAD {
+        anyhow::bail!("echo came back corrupted");
+    }
+    echo.await??;
+    Ok(rtt)
+}
+
+async fn read_probe(recv: &mut wtransport::RecvStream) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; PROBE_PAYLOAD.len()];
+    let mut filled = 0;
+    while filled < buf.len() {
+        match recv.read(&mut buf[filled..]).await? {
+            Some(n) => filled += n,
+            None => anyhow::bail!("probe stream closed early"),
+        }
+    }
+    Ok(buf)
+}
+
+fn loopback_client() -> Result<Endpoint<wtransport::endpoint::endpoint_side::Client>> {
+    let config = ClientConfig::builder()
+        .with_bind_default()
+        .with_no_cert_validation()
+        .build();
+    Ok(Endpoint::client(config)?)
+}
+
+/// Whether the token file is private and holds a token the config accepts.
+///
+/// Returns the token so the end-to-end check can log in with it.
+pub fn check_token_file(
+    path: Option<&Path>,
+    registry: &TokenRegistry,
+) -> (CheckResult, Option<Vec<u8>>) {
+    const NAME: &str = "token";
+    let Some(path) = path else {
+        let result = if registry.is_empty() {
+            CheckResult::warn(
+                NAME,
+                "no tokens configured; any client that can reach the port gets in",
+                "add [[tokens]] to the config before listening on anything but loopback",
+            )
+        } else {
+            CheckResult::skip(
+                NAME,
+                format!(
+                    "{} token(s) configured, no --token-file given",
+                    registry.len()
+                ),
+            )
+        };
+        return (result, None);
+    };
+
+    let token = match fs::read_to_string(path) {
+        Ok(contents) => contents.trim().as_bytes().to_vec(),
+        Err(e) => {
+            return (
+                CheckResult::fail(
+                    NAME,
+                    format!("cannot read {}: {}", path.display(), e),
+                    "check the path and that it is readable by this user",
+                ),
+                None,
+            )
+        },
+    };
+    if token.is_empty() {
+        return (
+            CheckResult::fail(
+                NAME,
+                format!("{} is empty", path.display()),
+                "write the bearer token into the file",
+            ),
+            None,
+        );
+    }
+
+    let insecure = insecure_permissions(path);
+    let result = match (registry.authenticate(&token), insecure) {
+        (AuthOutcome::Rejected, _) if registry.is_empty() => CheckResult::warn(
+            NAME,
+            "the config has no tokens, so the server accepts any client",
+            "add the token's hash to [[tokens]] to require it",
+        ),
+        (AuthOutcome::Rejected, _) => CheckResult::fail(
+            NAME,
+            format!("{} does not match any configured token", path.display()),
+            "compare the file with the [[tokens]] hashes in the config",
+        ),
+        (AuthOutcome::Expired { token_id }, _) => CheckResult::fail(
+            NAME,
+            format!("token {:?} has expired", token_id),
+            "raise or remove its expires_at_ms, or issue a new token",
+        ),
+        (AuthOutcome::Accepted { token_id, role }, Some(mode)) => CheckResult::warn(
+            NAME,
+            format!(
+                "accepted as {:?} ({:?}), but {} has mode {:o}",
+                token_id,
+                role,
+                path.display(),
+                mode
+            ),
+            format!("chmod 600 {}", path.display()),
+        ),
+        (AuthOutcome::Accepted { token_id, role }, None) => {
+            CheckResult::pass(NAME, format!("accepted as {:?} ({:?})", token_id, role))
+        },
+    };
+    (result, Some(token))
+}
+
+/// The file's mode if anyone but the owner can read or write it.
+#[cfg(unix)]
+fn insecure_permissions(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    let mode = fs::metadata(path).ok()?.mode() & 0o777;
+    (mode & 0o077 != 0).then_some(mode)
+}
+
+#[cfg(not(unix))]
+fn insecure_permissions(_path: &Path) -> Option<u32> {
+    None
+}
+
+/// Start a bridge with `config` on a spare loopback port and log in to it the way a
+/// remote client would.
+pub async fn check_end_to_end(config: &BridgeConfig, bearer_token: Vec<u8>) -> CheckResult {
+    const NAME: &str = "end-to-end";
+    let port = match UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).and_then(|s| s.local_addr()) {
+        Ok(addr) => addr.port(),
+        Err(e) => {
+            return CheckResult::fail(
+                NAME,
+                format!("no free loopback port: {}", e),
+                "check the loopback interface",
+            )
+        },
+    };
+    // Same certificate and tokens, but reachable from here whatever the allowlist says
+    let probe_config = BridgeConfig {
+        listen_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+        allowlist: SharedAllowlist::default(),
+        relay: None,
+        ..config.clone()
+    };
+
+    let shutdown = CancellationToken::new();
+    let bridge = RemoteBridge::new(probe_config);
+    let server = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { bridge.run_with_shutdown(shutdown).await }
+    });
+
+    let result = tokio::time::timeout(PROBE_TIMEOUT, client_login(port, bearer_token)).await;
+    shutdown.cancel();
+    let server_error = match server.await {
+        Ok(Err(e)) => Some(e),
+        _ => None,
+    };
+
+    match (result, server_error) {
+        (_, Some(e)) => CheckResult::fail(
+            NAME,
+            format!("the bridge did not start: {:#}", e),
+            "see the certificate and bind checks above",
+        ),
+        (Ok(Ok(hello)), None) => CheckResult::pass(
+            NAME,
+            format!(
+                "logged in to session {:?} as client {}",
+                hello.session_name, hello.client_id
+            ),
+        ),
+        (Ok(Err(e)), None) => CheckResult::fail(
+            NAME,
+            format!("{:#}", e),
+            "see the checks above; run with RUST_LOG=debug for the server's side",
+        ),
+        (Err(_), None) => CheckResult::fail(
+            NAME,
+            format!("no ServerHello within {:?}", PROBE_TIMEOUT),
+            "run with RUST_LOG=debug for the server's side",
+        ),
+    }
+}
+
+async fn client_login(
+    port: u16,
+    bearer_token: Vec<u8>,
+) -> Result<zellij_remote_protocol::ServerHello> {
+    let client = loopback_client()?;
+    let connection = client
+        .connect(format!("https://127.0.0.1:{}", port))
+        .await
+        .context("cannot connect to the bridge")?;
+    let (mut send, mut recv) = connection.open_bi().await?.await?;
+
+    let hello = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::ClientHello(ClientHello {
+            client_name: "zellij-remote-doctor".to_string(),
+            version: Some(ProtocolVersion {
+                major: ZRP_VERSION_MAJOR,
+                minor: ZRP_VERSION_MINOR,
+            }),
+            bearer_token,
+            ..Default::default()
+        })),
+        envelope_seq: 0,
+    };
+    send.write_all(&encode_envelope(&hello)?).await?;
+
+    let mut buffer = BytesMut::new();
+    loop {
+        let mut chunk = [0u8; 1024];
+        let n = recv.read(&mut chunk).await?.unwrap_or(0);
+        if n == 0 {
+            anyhow::bail!("the bridge closed the stream during the handshake");
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        if let DecodeResult::Complete(envelope) = decode_envelope(&mut buffer)? {
+            match envelope.msg {
+                Some(stream_envelope::Msg::ServerHello(hello)) => return Ok(hello),
+                Some(stream_envelope::Msg::ProtocolError(e)) => {
+                    anyhow::bail!("the bridge refused the login: {}", e.message)
+                },
+                _ => anyhow::bail!("expected ServerHello, got another message"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zellij_remote_core::{TokenEntry, TokenHash};
+    use zellij_remote_protocol::ClientRole;
+
+    fn registry(token: &str, expires_at_ms: Option<u64>) -> TokenRegistry {
+        TokenRegistry::new(vec![TokenEntry {
+            id: "laptop".to_string(),
+            hash: TokenHash::from_plaintext(token.as_bytes()),
+            role: ClientRole::Controller,
+            expires_at_ms,
+        }])
+    }
+
+    fn token_file(contents: &str, mode: u32) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "{}", contents).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(file.path(), fs::Permissions::from_mode(mode)).unwrap();
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+        file
+    }
+
+    #[test]
+    fn test_token_file_checked_against_registry() {
+        let file = token_file("secret\n", 0o600);
+        let (result, token) = check_token_file(Some(file.path()), &registry("secret", None));
+        assert_eq!(result.status, CheckStatus::Pass);
+        assert_eq!(token.as_deref(), Some(&b"secret"[..]));
+
+        let (result, _) = check_token_file(Some(file.path()), &registry("other", None));
+        assert_eq!(result.status, CheckStatus::Fail);
+
+        let (result, _) = check_token_file(Some(file.path()), &registry("secret", Some(1)));
+        assert_eq!(result.status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_token_file_problems_reported() {
+        let empty = token_file("  \n", 0o600);
+        let (result, token) = check_token_file(Some(empty.path()), &registry("secret", None));
+        assert_eq!(result.status, CheckStatus::Fail);
+        assert!(token.is_none());
+
+        let (result, _) = check_token_file(
+            Some(Path::new("/nonexistent/zellij-token")),
+            &registry("secret", None),
+        );
+        assert_eq!(result.status, CheckStatus::Fail);
+
+        #[cfg(unix)]
+        {
+            let readable = token_file("secret", 0o644);
+            let (result, _) = check_token_file(Some(readable.path()), &registry("secret", None));
+            assert_eq!(result.status, CheckStatus::Warn);
+            assert!(result.hint.unwrap().contains("chmod 600"));
+        }
+    }
+
+    #[test]
+    fn test_bind_in_use_is_a_warning() {
+        let held = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let result = check_bind(held.local_addr().unwrap());
+        assert_eq!(result.status, CheckStatus::Warn);
+    }
+
+    #[test]
+    fn test_self_signed_certificate_is_valid() {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        match certificate_validity(cert.cert.der()).unwrap() {
+            Validity::Valid { days_left } => assert!(days_left > CERT_EXPIRY_WARNING_DAYS),
+            other => panic!("expected a valid certificate, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_report_unhealthy_only_on_failure() {
+        let mut report = DoctorReport {
+            checks: vec![
+                CheckResult::pass("config", "defaults"),
+                CheckResult::warn("bind", "in use", "stop it"),
+            ],
+        };
+        assert!(report.is_healthy());
+        report
+            .checks
+            .push(CheckResult::fail("token", "rejected", "fix it"));
+        assert!(!report.is_healthy());
+        assert!(report.to_string().ends_with("1 check(s) failed"));
+    }
+}
@@ -0,0 +1,119 @@
+//! Handshake extensions.
+//!
+//! A client offers experimental features by name in `ClientHello.extensions`; the
+//! server answers each one a registered handler accepts with an entry of the same
+//! name in `ServerHello.extensions`. Trying out a compression scheme or an auth
+//! variant then takes a handler, not a protocol version bump or a change to the
+//! handshake itself.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use zellij_remote_protocol::Extension;
+
+/// Decides whether a connection gets an extension the client offered.
+pub trait ExtensionHandler: Send + Sync {
+    /// The payload to answer `offer` with, or None to decline; a declined extension
+    /// is left out of the `ServerHello`.
+    fn negotiate(&self, offer: &[u8]) -> Option<Vec<u8>>;
+}
+
+impl<F> ExtensionHandler for F
+where
+    F: Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync,
+{
+    fn negotiate(&self, offer: &[u8]) -> Option<Vec<u8>> {
+        self(offer)
+    }
+}
+
+/// Handlers by extension name. Cloning shares the handlers.
+#[derive(Clone, Default)]
+pub struct ExtensionRegistry {
+    handlers: BTreeMap<String, Arc<dyn ExtensionHandler>>,
+}
+
+impl ExtensionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle offers of `name`, replacing the handler it had, if any
+    pub fn register(&mut self, name: impl Into<String>, handler: impl ExtensionHandler + 'static) {
+        self.handlers.insert(name.into(), Arc::new(handler));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.handlers.keys().map(String::as_str)
+    }
+
+    /// The `ServerHello.extensions` answering a client's offers: every offer whose
+    /// handler accepts it, in the order offered. Unknown names are ignored, and a
+    /// name offered twice is answered once, for its first offer.
+    pub fn negotiate(&self, offers: &[Extension]) -> Vec<Extension> {
+        let mut accepted: Vec<Extension> = Vec::new();
+        for offer in offers {
+            if accepted.iter().any(|ext| ext.name == offer.name) {
+                continue;
+            }
+            let Some(handler) = self.handlers.get(&offer.name) else {
+                continue;
+            };
+            if let Some(payload) = handler.negotiate(&offer.payload) {
+                log::info!("Accepted handshake extension {}", offer.name);
+                accepted.push(Extension {
+                    name: offer.name.clone(),
+                    payload,
+                });
+            }
+        }
+        accepted
+    }
+}
+
+impl std::fmt::Debug for ExtensionRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(name: &str, payload: &[u8]) -> Extension {
+        Extension {
+            name: name.to_string(),
+            payload: payload.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_negotiate_answers_accepted_offers_only() {
+        let mut registry = ExtensionRegistry::new();
+        // Picks the first level offered that it supports
+        registry.register("x-zstd-level", |offer: &[u8]| {
+            offer
+                .iter()
+                .find(|level| **level <= 9)
+                .map(|level| vec![*level])
+        });
+        registry.register("x-declines", |_: &[u8]| None);
+
+        let accepted = registry.negotiate(&[
+            offer("x-unknown", b"ignored"),
+            offer("x-zstd-level", &[19, 3]),
+            offer("x-declines", &[]),
+            offer("x-zstd-level", &[1]),
+        ]);
+        assert_eq!(accepted, vec![offer("x-zstd-level", &[3])]);
+
+        assert!(ExtensionRegistry::default()
+            .negotiate(&[offer("x-zstd-level", &[3])])
+            .is_empty());
+    }
+}
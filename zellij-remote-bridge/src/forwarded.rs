@@ -0,0 +1,84 @@
+//! Client addresses behind a reverse proxy.
+//!
+//! A proxy that terminates WebTransport and opens its own session to the bridge hides
+//! the client's address: the bridge only sees the proxy's. Proxies listed in
+//! `BridgeConfig::trusted_proxies` may name the client in an `X-Forwarded-For` header
+//! on the session request, and the bridge uses that address for the allowlist and in
+//! audit events. The header is ignored from anyone else, so clients can't pick their
+//! own address.
+
+use std::net::IpAddr;
+
+/// Session request header a trusted proxy names the client in
+pub const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// The address a connection from `peer` comes from. When `peer` is a trusted proxy,
+/// that is the nearest address in `forwarded_for` (a comma separated list, the client
+/// first and each proxy after) that isn't another trusted proxy; otherwise `peer`.
+/// An entry that doesn't parse ends the search, as nothing before it can be trusted.
+pub fn client_ip(peer: IpAddr, forwarded_for: Option<&str>, trusted_proxies: &[IpAddr]) -> IpAddr {
+    let trusted = |addr: IpAddr| trusted_proxies.contains(&addr.to_canonical());
+    let mut client = peer.to_canonical();
+    if !trusted(client) {
+        return client;
+    }
+    let Some(forwarded_for) = forwarded_for else {
+        return client;
+    };
+    for entry in forwarded_for.rsplit(',') {
+        let Ok(addr) = entry.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = addr.to_canonical();
+        if !trusted(client) {
+            break;
+        }
+    }
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn test_header_only_believed_from_trusted_proxies() {
+        let proxies = [ip("10.0.0.1")];
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), Some("203.0.113.9"), &proxies),
+            ip("203.0.113.9")
+        );
+        // Anyone else gets their own address, whatever they claim
+        assert_eq!(
+            client_ip(ip("198.51.100.4"), Some("203.0.113.9"), &proxies),
+            ip("198.51.100.4")
+        );
+        assert_eq!(client_ip(ip("10.0.0.1"), None, &proxies), ip("10.0.0.1"));
+        assert_eq!(
+            client_ip(ip("::ffff:10.0.0.1"), Some("203.0.113.9"), &proxies),
+            ip("203.0.113.9")
+        );
+    }
+
+    #[test]
+    fn test_nearest_untrusted_entry_is_the_client() {
+        let proxies = [ip("10.0.0.1"), ip("10.0.0.2")];
+        // A client spoofing an entry of its own comes before the one the proxy added
+        assert_eq!(
+            client_ip(
+                ip("10.0.0.1"),
+                Some("1.2.3.4, 203.0.113.9, 10.0.0.2"),
+                &proxies
+            ),
+            ip("203.0.113.9")
+        );
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), Some("garbage, 10.0.0.2"), &proxies),
+            ip("10.0.0.2")
+        );
+    }
+}
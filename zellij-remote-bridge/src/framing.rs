@@ -1,7 +1,10 @@
 use anyhow::Result;
-use bytes::{Buf, Bytes, BytesMut};
+use bytes::{Bytes, BytesMut};
 use prost::Message;
-use zellij_remote_protocol::{DatagramEnvelope, StreamEnvelope};
+use tokio_util::codec::Decoder;
+use zellij_remote_protocol::{DatagramEnvelope, RelayEnvelope, StreamEnvelope};
+
+use crate::codec::ZrpCodec;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DecodeResult<T> {
@@ -10,11 +13,73 @@ pub enum DecodeResult<T> {
 }
 
 pub fn encode_envelope(envelope: &StreamEnvelope) -> Result<Vec<u8>> {
-    let len = envelope.encoded_len();
-    let mut buf = BytesMut::with_capacity(len + 5);
-    prost::encoding::encode_varint(len as u64, &mut buf);
-    envelope.encode(&mut buf)?;
-    Ok(buf.to_vec())
+    encode_frame(envelope)
+}
+
+/// Encode a RelayEnvelope with the same varint length prefix as stream envelopes
+pub fn encode_relay_envelope(envelope: &RelayEnvelope) -> Result<Vec<u8>> {
+    encode_frame(envelope)
+}
+
+fn encode_frame<M: Message>(message: &M) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    encode_frame_into(message, &mut buf)?;
+    Ok(buf)
+}
+
+pub(crate) fn encode_frame_into<M: Message>(message: &M, buf: &mut Vec<u8>) -> Result<()> {
+    let len = message.encoded_len();
+    buf.reserve(len + prost::length_delimiter_len(len));
+    ZrpCodec::new().encode_into(message, buf)?;
+    Ok(())
+}
+
+/// Buffers larger than this are released after use instead of kept for the next
+/// message, so one big snapshot doesn't pin its size for the life of the connection
+const MAX_RETAINED_BYTES: usize = 256 * 1024;
+
+/// An output buffer a sender task keeps across messages.
+///
+/// At 60 frames a second [`encode_envelope`] allocates a fresh `Vec` for every
+/// delta; encoding into an `EncodeBuffer` reuses one allocation instead.
+#[derive(Debug, Default)]
+pub struct EncodeBuffer {
+    buf: Vec<u8>,
+}
+
+impl EncodeBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buf: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// An empty buffer, keeping the allocation unless it grew past `MAX_RETAINED_BYTES`
+    pub(crate) fn reset(&mut self) -> &mut Vec<u8> {
+        if self.buf.capacity() > MAX_RETAINED_BYTES {
+            self.buf = Vec::new();
+        }
+        self.buf.clear();
+        &mut self.buf
+    }
+}
+
+/// Like [`encode_envelope`], writing the frame into `buf`; the returned slice is
+/// valid until the buffer is used again.
+pub fn encode_envelope_into<'a>(
+    envelope: &StreamEnvelope,
+    buf: &'a mut EncodeBuffer,
+) -> Result<&'a [u8]> {
+    encode_frame_into(envelope, buf.reset())?;
+    Ok(&buf.buf)
 }
 
 /// Encode a DatagramEnvelope to Bytes (no length prefix for datagrams)
@@ -31,32 +96,18 @@ pub fn decode_datagram_envelope(bytes: &[u8]) -> Result<DatagramEnvelope, prost:
 }
 
 pub fn decode_envelope(buf: &mut BytesMut) -> Result<DecodeResult<StreamEnvelope>> {
-    if buf.is_empty() {
-        return Ok(DecodeResult::Incomplete);
-    }
-
-    let mut peek = &buf[..];
-    let len = match prost::encoding::decode_varint(&mut peek) {
-        Ok(len) => len as usize,
-        Err(_) => {
-            if buf.len() < 10 {
-                return Ok(DecodeResult::Incomplete);
-            }
-            anyhow::bail!("invalid varint in frame header");
-        },
-    };
-
-    let varint_len = buf.len() - peek.len();
-    let total_len = varint_len + len;
+    decode_frame(buf)
+}
 
-    if buf.len() < total_len {
-        return Ok(DecodeResult::Incomplete);
-    }
+pub fn decode_relay_envelope(buf: &mut BytesMut) -> Result<DecodeResult<RelayEnvelope>> {
+    decode_frame(buf)
+}
 
-    buf.advance(varint_len);
-    let frame_data = buf.split_to(len);
-    let envelope = StreamEnvelope::decode(&frame_data[..])?;
-    Ok(DecodeResult::Complete(envelope))
+pub(crate) fn decode_frame<M: Message + Default>(buf: &mut BytesMut) -> Result<DecodeResult<M>> {
+    Ok(match ZrpCodec::<M>::new().decode(buf)? {
+        Some(message) => DecodeResult::Complete(message),
+        None => DecodeResult::Incomplete,
+    })
 }
 
 #[cfg(test)]
@@ -79,11 +130,33 @@ mod tests {
                     supports_images: false,
                     supports_clipboard: false,
                     supports_hyperlinks: false,
+                    supports_line_size: false,
+                    supports_text_mode: false,
+                    supports_frame_hash: false,
+                    supports_style_retention: false,
+                    supports_priority_lanes: false,
+                    supports_row_checksums: false,
+                    supports_push_streams: false,
+                    supports_bell: false,
+                    supports_lease_status: false,
+                    max_message_bytes: 0,
+                    supports_pane_layout: false,
+                    supports_keybinding_info: false,
                 }),
                 client_name: "test-client".to_string(),
                 bearer_token: vec![],
                 resume_token: vec![],
+                min_render_window: 0,
+                max_render_window: 0,
+                supported_codecs: vec![],
+                debug_frame_info: false,
+                locale: None,
+                color_transform: None,
+                extensions: vec![],
+                terminal_answers: None,
+                flash_guard: None,
             })),
+            envelope_seq: 0,
         }
     }
 
@@ -143,7 +216,14 @@ mod tests {
                 snapshot_interval_ms: 5000,
                 max_inflight_inputs: 256,
                 render_window: 4,
+                min_render_window: 0,
+                max_render_window: 0,
+                keepalive_interval_ms: 0,
+                alternate_endpoints: vec![],
+                compression: None,
+                extensions: vec![],
             })),
+            envelope_seq: 0,
         };
 
         let encoded1 = encode_envelope(&msg1).unwrap();
@@ -215,9 +295,40 @@ mod tests {
         assert!(result.is_err(), "should error on corrupted protobuf");
     }
 
+    #[test]
+    fn test_encode_into_matches_encode_and_reuses_buffer() {
+        let hello = make_client_hello();
+        let mut buf = EncodeBuffer::new();
+        assert_eq!(
+            encode_envelope_into(&hello, &mut buf).unwrap(),
+            &encode_envelope(&hello).unwrap()[..]
+        );
+        let capacity = buf.capacity();
+
+        let empty = StreamEnvelope {
+            msg: None,
+            envelope_seq: 0,
+        };
+        assert_eq!(
+            encode_envelope_into(&empty, &mut buf).unwrap(),
+            &encode_envelope(&empty).unwrap()[..]
+        );
+        assert_eq!(buf.capacity(), capacity);
+    }
+
+    #[test]
+    fn test_oversized_buffer_released() {
+        let mut buf = EncodeBuffer::with_capacity(MAX_RETAINED_BYTES + 1);
+        encode_envelope_into(&make_client_hello(), &mut buf).unwrap();
+        assert!(buf.capacity() <= MAX_RETAINED_BYTES);
+    }
+
     #[test]
     fn test_empty_envelope() {
-        let envelope = StreamEnvelope { msg: None };
+        let envelope = StreamEnvelope {
+            msg: None,
+            envelope_seq: 0,
+        };
         let encoded = encode_envelope(&envelope).unwrap();
         let mut buf = BytesMut::from(&encoded[..]);
 
@@ -2,11 +2,13 @@ use anyhow::Result;
 use bytes::BytesMut;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
+use zellij_remote_core::{AuthOutcome, TokenRegistry, WindowBounds};
 use zellij_remote_protocol::{
-    stream_envelope, Capabilities, ClientHello, ControllerLease, ControllerPolicy, ProtocolVersion,
-    ServerHello, SessionState, StreamEnvelope,
+    protocol_error, stream_envelope, Capabilities, ClientHello, ClientRole, ControllerLease,
+    ControllerPolicy, ProtocolError, ProtocolVersion, ServerHello, SessionState, StreamEnvelope,
 };
 
+use crate::extensions::ExtensionRegistry;
 use crate::framing::{decode_envelope, encode_envelope, DecodeResult};
 
 const DEFAULT_SNAPSHOT_INTERVAL_MS: u32 = 5000;
@@ -16,13 +18,64 @@ pub struct HandshakeResult {
     pub client_hello: ClientHello,
     pub server_hello: ServerHello,
     pub client_id: u64,
+    /// Which configured token the client authenticated with (None if auth is disabled)
+    pub token_id: Option<String>,
+    /// Highest role the client's token allows. Only recorded for the audit log: the
+    /// bridge doesn't enforce roles yet, so a viewer token isn't reported as such in the
+    /// `ServerHello` and nothing stops it from asking for control
+    pub role: ClientRole,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("authentication failed: invalid bearer token")]
+    InvalidToken,
+    #[error("authentication failed: token '{token_id}' has expired")]
+    ExpiredToken { token_id: String },
+}
+
+impl AuthError {
+    pub fn token_id(&self) -> Option<&str> {
+        match self {
+            AuthError::InvalidToken => None,
+            AuthError::ExpiredToken { token_id } => Some(token_id),
+        }
+    }
 }
 
 pub async fn run_handshake<R, W>(
+    reader: R,
+    writer: W,
+    session_name: String,
+    client_id: u64,
+) -> Result<HandshakeResult>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    run_authenticated_handshake(
+        reader,
+        writer,
+        session_name,
+        client_id,
+        &TokenRegistry::default(),
+        &ExtensionRegistry::default(),
+    )
+    .await
+}
+
+/// Run the handshake, checking the client's bearer token against `tokens` and
+/// answering the extensions it offers with `extensions`.
+///
+/// An empty registry disables authentication. On failure an `UNAUTHORIZED` error is sent
+/// to the client and an [`AuthError`] is returned.
+pub async fn run_authenticated_handshake<R, W>(
     mut reader: R,
     mut writer: W,
     session_name: String,
     client_id: u64,
+    tokens: &TokenRegistry,
+    extensions: &ExtensionRegistry,
 ) -> Result<HandshakeResult>
 where
     R: AsyncRead + Unpin,
@@ -43,9 +96,30 @@ where
                 Some(stream_envelope::Msg::ClientHello(client_hello)) => {
                     log::info!("Received ClientHello from {}", client_hello.client_name);
 
-                    let server_hello = build_server_hello(&client_hello, &session_name, client_id);
+                    let (token_id, role) = match authenticate(tokens, &client_hello) {
+                        Ok(granted) => granted,
+                        Err(e) => {
+                            let error = ProtocolError {
+                                code: protocol_error::Code::Unauthorized as i32,
+                                message: e.to_string(),
+                                fatal: true,
+                                retry_after_ms: 0,
+                            };
+                            let encoded = encode_envelope(&StreamEnvelope {
+                                msg: Some(stream_envelope::Msg::ProtocolError(error)),
+                                envelope_seq: 0,
+                            })?;
+                            writer.write_all(&encoded).await?;
+                            return Err(e.into());
+                        },
+                    };
+
+                    let mut server_hello =
+                        build_server_hello(&client_hello, &session_name, client_id);
+                    server_hello.extensions = extensions.negotiate(&client_hello.extensions);
                     let response = StreamEnvelope {
                         msg: Some(stream_envelope::Msg::ServerHello(server_hello.clone())),
+                        envelope_seq: 0,
                     };
                     let encoded = encode_envelope(&response)?;
                     writer.write_all(&encoded).await?;
@@ -56,6 +130,8 @@ where
                         client_hello,
                         server_hello,
                         client_id,
+                        token_id,
+                        role,
                     });
                 },
                 _ => {
@@ -69,11 +145,31 @@ where
     }
 }
 
+/// The token the client used and the role it grants; the role is attributed, not enforced.
+fn authenticate(
+    tokens: &TokenRegistry,
+    client_hello: &ClientHello,
+) -> std::result::Result<(Option<String>, ClientRole), AuthError> {
+    if tokens.is_empty() {
+        return Ok((None, ClientRole::Controller));
+    }
+    match tokens.authenticate(&client_hello.bearer_token) {
+        AuthOutcome::Accepted { token_id, role } => Ok((Some(token_id), role)),
+        AuthOutcome::Expired { token_id } => Err(AuthError::ExpiredToken { token_id }),
+        AuthOutcome::Rejected => Err(AuthError::InvalidToken),
+    }
+}
+
 pub fn build_server_hello(
     client_hello: &ClientHello,
     session_name: &str,
     client_id: u64,
 ) -> ServerHello {
+    let window_bounds = WindowBounds::negotiate(
+        WindowBounds::default(),
+        client_hello.min_render_window,
+        client_hello.max_render_window,
+    );
     let negotiated_caps = Capabilities {
         supports_datagrams: client_hello
             .capabilities
@@ -87,6 +183,18 @@ pub fn build_server_hello(
         supports_images: false,
         supports_clipboard: false,
         supports_hyperlinks: false,
+        supports_line_size: false,
+        supports_text_mode: false,
+        supports_frame_hash: false,
+        supports_style_retention: false,
+        supports_priority_lanes: false,
+        supports_row_checksums: false,
+        supports_push_streams: false,
+        supports_bell: false,
+        supports_lease_status: false,
+        max_message_bytes: 0,
+        supports_pane_layout: false,
+        supports_keybinding_info: false,
     };
 
     ServerHello {
@@ -105,11 +213,18 @@ pub fn build_server_hello(
             current_size: None,
             remaining_ms: 0,
             duration_ms: 30000,
+            owner_name: String::new(),
         }),
         resume_token: vec![],
         snapshot_interval_ms: DEFAULT_SNAPSHOT_INTERVAL_MS,
         max_inflight_inputs: 256,
-        render_window: zellij_remote_protocol::DEFAULT_RENDER_WINDOW,
+        render_window: window_bounds.clamp(zellij_remote_protocol::DEFAULT_RENDER_WINDOW),
+        min_render_window: window_bounds.min,
+        max_render_window: window_bounds.max,
+        keepalive_interval_ms: 0,
+        alternate_endpoints: vec![],
+        compression: None,
+        extensions: vec![],
     }
 }
 
@@ -117,6 +232,8 @@ pub fn build_server_hello(
 mod tests {
     use super::*;
     use tokio::io::duplex;
+    use zellij_remote_core::{TokenEntry, TokenHash};
+    use zellij_remote_protocol::Extension;
 
     fn make_client_hello() -> ClientHello {
         ClientHello {
@@ -130,10 +247,31 @@ mod tests {
                 supports_images: false,
                 supports_clipboard: false,
                 supports_hyperlinks: false,
+                supports_line_size: false,
+                supports_text_mode: false,
+                supports_frame_hash: false,
+                supports_style_retention: false,
+                supports_priority_lanes: false,
+                supports_row_checksums: false,
+                supports_push_streams: false,
+                supports_bell: false,
+                supports_lease_status: false,
+                max_message_bytes: 0,
+                supports_pane_layout: false,
+                supports_keybinding_info: false,
             }),
             client_name: "test-client".to_string(),
             bearer_token: vec![],
             resume_token: vec![],
+            min_render_window: 0,
+            max_render_window: 0,
+            supported_codecs: vec![],
+            debug_frame_info: false,
+            locale: None,
+            color_transform: None,
+            extensions: vec![],
+            terminal_answers: None,
+            flash_guard: None,
         }
     }
 
@@ -152,6 +290,7 @@ mod tests {
         let client_hello = make_client_hello();
         let envelope = StreamEnvelope {
             msg: Some(stream_envelope::Msg::ClientHello(client_hello.clone())),
+            envelope_seq: 0,
         };
         let encoded = encode_envelope(&envelope).unwrap();
         client_write.write_all(&encoded).await.unwrap();
@@ -207,6 +346,7 @@ mod tests {
 
         let envelope = StreamEnvelope {
             msg: Some(stream_envelope::Msg::ClientHello(client_hello)),
+            envelope_seq: 0,
         };
         let encoded = encode_envelope(&envelope).unwrap();
         client_write.write_all(&encoded).await.unwrap();
@@ -265,6 +405,7 @@ mod tests {
         // Send ServerHello instead of ClientHello
         let wrong_message = StreamEnvelope {
             msg: Some(stream_envelope::Msg::ServerHello(ServerHello::default())),
+            envelope_seq: 0,
         };
         let encoded = encode_envelope(&wrong_message).unwrap();
         client_write.write_all(&encoded).await.unwrap();
@@ -291,6 +432,7 @@ mod tests {
         let client_hello = make_client_hello();
         let envelope = StreamEnvelope {
             msg: Some(stream_envelope::Msg::ClientHello(client_hello)),
+            envelope_seq: 0,
         };
         let encoded = encode_envelope(&envelope).unwrap();
 
@@ -309,6 +451,143 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    fn controller_token(id: &str, token: &[u8]) -> TokenEntry {
+        TokenEntry {
+            id: id.to_string(),
+            hash: TokenHash::from_plaintext(token),
+            role: ClientRole::Controller,
+            expires_at_ms: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handshake_attributes_token() {
+        let (client_stream, server_stream) = duplex(4096);
+        let (_client_read, mut client_write) = tokio::io::split(client_stream);
+        let (server_read, server_write) = tokio::io::split(server_stream);
+
+        let tokens = TokenRegistry::new(vec![
+            controller_token("alice", b"alice-token"),
+            TokenEntry {
+                role: ClientRole::Viewer,
+                ..controller_token("kiosk", b"kiosk-token")
+            },
+        ]);
+
+        let mut client_hello = make_client_hello();
+        client_hello.bearer_token = b"kiosk-token".to_vec();
+        let encoded = encode_envelope(&StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ClientHello(client_hello)),
+            envelope_seq: 0,
+        })
+        .unwrap();
+        client_write.write_all(&encoded).await.unwrap();
+
+        let result = run_authenticated_handshake(
+            server_read,
+            server_write,
+            "test".to_string(),
+            7,
+            &tokens,
+            &ExtensionRegistry::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.token_id.as_deref(), Some("kiosk"));
+        assert_eq!(result.role, ClientRole::Viewer);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_rejects_unknown_token() {
+        let (client_stream, server_stream) = duplex(4096);
+        let (mut client_read, mut client_write) = tokio::io::split(client_stream);
+        let (server_read, server_write) = tokio::io::split(server_stream);
+
+        let tokens = TokenRegistry::new(vec![controller_token("alice", b"alice-token")]);
+
+        let mut client_hello = make_client_hello();
+        client_hello.bearer_token = b"wrong".to_vec();
+        let encoded = encode_envelope(&StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ClientHello(client_hello)),
+            envelope_seq: 0,
+        })
+        .unwrap();
+        client_write.write_all(&encoded).await.unwrap();
+
+        let err = run_authenticated_handshake(
+            server_read,
+            server_write,
+            "test".to_string(),
+            7,
+            &tokens,
+            &ExtensionRegistry::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AuthError>(),
+            Some(AuthError::InvalidToken)
+        ));
+
+        let mut buffer = BytesMut::new();
+        let mut chunk = [0u8; 1024];
+        let n = client_read.read(&mut chunk).await.unwrap();
+        buffer.extend_from_slice(&chunk[..n]);
+        match decode_envelope(&mut buffer).unwrap() {
+            DecodeResult::Complete(response) => match response.msg {
+                Some(stream_envelope::Msg::ProtocolError(error)) => {
+                    assert_eq!(error.code, protocol_error::Code::Unauthorized as i32);
+                    assert!(error.fatal);
+                },
+                _ => panic!("expected ProtocolError"),
+            },
+            DecodeResult::Incomplete => panic!("expected complete response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handshake_answers_registered_extensions() {
+        let (client_stream, server_stream) = duplex(4096);
+        let (_client_read, mut client_write) = tokio::io::split(client_stream);
+        let (server_read, server_write) = tokio::io::split(server_stream);
+
+        let mut extensions = ExtensionRegistry::new();
+        extensions.register("x-echo", |offer: &[u8]| Some(offer.to_vec()));
+
+        let mut client_hello = make_client_hello();
+        client_hello.extensions = ["x-echo", "x-unknown"]
+            .into_iter()
+            .map(|name| Extension {
+                name: name.to_string(),
+                payload: b"trial".to_vec(),
+            })
+            .collect();
+        let encoded = encode_envelope(&StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ClientHello(client_hello)),
+            envelope_seq: 0,
+        })
+        .unwrap();
+        client_write.write_all(&encoded).await.unwrap();
+
+        let result = run_authenticated_handshake(
+            server_read,
+            server_write,
+            "test".to_string(),
+            7,
+            &TokenRegistry::default(),
+            &extensions,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.server_hello.extensions,
+            vec![Extension {
+                name: "x-echo".to_string(),
+                payload: b"trial".to_vec(),
+            }]
+        );
+    }
+
     #[test]
     fn test_build_server_hello_required_fields() {
         let client_hello = make_client_hello();
@@ -322,6 +601,24 @@ mod tests {
         assert!(hello.snapshot_interval_ms > 0);
         assert!(hello.max_inflight_inputs > 0);
         assert!(hello.render_window > 0);
+        assert!(hello.min_render_window <= hello.render_window);
+        assert!(hello.render_window <= hello.max_render_window);
+    }
+
+    #[test]
+    fn test_build_server_hello_negotiates_render_window() {
+        let mut client_hello = make_client_hello();
+        client_hello.min_render_window = 8;
+        client_hello.max_render_window = 1_000;
+
+        let hello = build_server_hello(&client_hello, "test", 1);
+
+        assert_eq!(hello.min_render_window, 8);
+        assert_eq!(
+            hello.max_render_window,
+            zellij_remote_protocol::DEFAULT_MAX_RENDER_WINDOW
+        );
+        assert_eq!(hello.render_window, 8);
     }
 
     #[test]
@@ -332,6 +629,15 @@ mod tests {
             client_name: "minimal".to_string(),
             bearer_token: vec![],
             resume_token: vec![],
+            min_render_window: 0,
+            max_render_window: 0,
+            supported_codecs: vec![],
+            debug_frame_info: false,
+            locale: None,
+            color_transform: None,
+            extensions: vec![],
+            terminal_answers: None,
+            flash_guard: None,
         };
 
         let hello = build_server_hello(&client_hello, "test", 1);
@@ -1,12 +1,50 @@
+pub mod audit;
+pub mod codec;
+pub mod compression;
 pub mod config;
+pub mod conformance_echo;
+pub mod debug_dump;
+pub mod doctor;
+pub mod extensions;
+pub mod forwarded;
 pub mod framing;
 pub mod handshake;
+pub mod proxy;
+pub mod push;
+pub mod relay;
+pub mod sequencing;
 pub mod server;
 
-pub use config::BridgeConfig;
+pub use audit::{AuditEvent, AuditLog};
+pub use codec::{FrameError, ZrpCodec, DEFAULT_MAX_FRAME_BYTES};
+pub use compression::{
+    decompress_envelope, encode_envelope_compressed, encode_envelope_compressed_into,
+    negotiate_compression,
+};
+pub use config::{
+    watch_config_file, BridgeConfig, ConfigError, FrameRateCaps, SharedAllowlist,
+    SharedFrameRateCaps,
+};
+pub use conformance_echo::CONFORMANCE_ECHO_EXTENSION;
+pub use debug_dump::DebugDump;
+pub use doctor::{run_doctor, CheckStatus, DoctorOptions, DoctorReport};
+pub use extensions::{ExtensionHandler, ExtensionRegistry};
+pub use forwarded::{client_ip, FORWARDED_FOR_HEADER};
 pub use framing::{
-    decode_datagram_envelope, decode_envelope, encode_datagram_envelope, encode_envelope,
-    DecodeResult,
+    decode_datagram_envelope, decode_envelope, decode_relay_envelope, encode_datagram_envelope,
+    encode_envelope, encode_envelope_into, encode_relay_envelope, DecodeResult, EncodeBuffer,
+};
+pub use handshake::{
+    build_server_hello, run_authenticated_handshake, run_handshake, AuthError, HandshakeResult,
+};
+pub use proxy::{ProxyConfig, Socks5UdpRelay, TargetAddr};
+pub use push::{
+    decode_uni_stream_header, encode_lane_prologue, encode_push_prologue, send_push,
+    UniStreamHeader,
+};
+pub use relay::{
+    relay_session_path, RelayClient, RelayClientConfig, RelayClientError, RelayServer,
+    RelayServerConfig,
 };
-pub use handshake::{build_server_hello, run_handshake, HandshakeResult};
+pub use sequencing::{EnvelopeSequenceChecker, EnvelopeSequencer, SequenceGap};
 pub use server::RemoteBridge;
@@ -0,0 +1,149 @@
+//! Typed server-opened unidirectional streams.
+//!
+//! With `supports_push_streams` negotiated, every unidirectional stream the server
+//! opens starts with one prologue byte naming what it carries: a render lane, whose
+//! `LaneOpen` follows, or a push. A push is a single bulk transfer (a snapshot, a
+//! scrollback chunk, a file) on a stream of its own: a length-prefixed `PushHeader`,
+//! then the body until the stream ends. Big transfers then never queue behind, or in
+//! front of, the interactive bidirectional stream.
+
+use anyhow::Result;
+use bytes::{Buf, BytesMut};
+use zellij_remote_protocol::{stream_envelope, Lane, LaneOpen, PushHeader, StreamEnvelope};
+
+use crate::framing::{decode_frame, encode_frame_into, DecodeResult};
+
+pub const PROLOGUE_LANE: u8 = 0x01;
+pub const PROLOGUE_PUSH: u8 = 0x02;
+
+/// What a server-opened unidirectional stream carries, read from its start
+#[derive(Debug, Clone, PartialEq)]
+pub enum UniStreamHeader {
+    Lane(Lane),
+    Push(PushHeader),
+}
+
+/// The start of a lane stream for a client with push streams: prologue and `LaneOpen`
+pub fn encode_lane_prologue(lane: Lane) -> Result<Vec<u8>> {
+    let mut buf = vec![PROLOGUE_LANE];
+    let open = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::LaneOpen(LaneOpen {
+            lane: lane as i32,
+        })),
+        envelope_seq: 0,
+    };
+    encode_frame_into(&open, &mut buf)?;
+    Ok(buf)
+}
+
+/// The start of a push stream: prologue and header; the body follows as is
+pub fn encode_push_prologue(header: &PushHeader) -> Result<Vec<u8>> {
+    let mut buf = vec![PROLOGUE_PUSH];
+    encode_frame_into(header, &mut buf)?;
+    Ok(buf)
+}
+
+/// Read the prologue and header off the front of `buf`, leaving whatever follows.
+/// Nothing is consumed until the whole header has arrived.
+pub fn decode_uni_stream_header(buf: &mut BytesMut) -> Result<DecodeResult<UniStreamHeader>> {
+    let Some(&prologue) = buf.first() else {
+        return Ok(DecodeResult::Incomplete);
+    };
+    let mut rest = BytesMut::from(&buf[1..]);
+    let header = match prologue {
+        PROLOGUE_LANE => match decode_frame::<StreamEnvelope>(&mut rest)? {
+            DecodeResult::Complete(StreamEnvelope {
+                msg: Some(stream_envelope::Msg::LaneOpen(open)),
+                ..
+            }) => UniStreamHeader::Lane(open.lane()),
+            DecodeResult::Complete(_) => anyhow::bail!("lane stream did not start with LaneOpen"),
+            DecodeResult::Incomplete => return Ok(DecodeResult::Incomplete),
+        },
+        PROLOGUE_PUSH => match decode_frame::<PushHeader>(&mut rest)? {
+            DecodeResult::Complete(header) => UniStreamHeader::Push(header),
+            DecodeResult::Incomplete => return Ok(DecodeResult::Incomplete),
+        },
+        other => anyhow::bail!("unknown stream type {:#04x}", other),
+    };
+    let consumed = buf.len() - rest.len();
+    buf.advance(consumed);
+    Ok(DecodeResult::Complete(header))
+}
+
+/// Send one push on a new unidirectional stream and finish it. `header.total_bytes`
+/// is filled in from `body`.
+pub async fn send_push(
+    connection: &wtransport::Connection,
+    header: PushHeader,
+    body: &[u8],
+) -> Result<()> {
+    let header = PushHeader {
+        total_bytes: body.len() as u64,
+        ..header
+    };
+    let mut stream = connection.open_uni().await?.await?;
+    stream.write_all(&encode_push_prologue(&header)?).await?;
+    stream.write_all(body).await?;
+    stream.finish().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zellij_remote_protocol::PushKind;
+
+    fn header() -> PushHeader {
+        PushHeader {
+            push_id: 7,
+            kind: PushKind::Scrollback as i32,
+            total_bytes: 5,
+            name: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_push_header_leaves_body() {
+        let mut buf = BytesMut::from(&encode_push_prologue(&header()).unwrap()[..]);
+        buf.extend_from_slice(b"hello");
+
+        let decoded = decode_uni_stream_header(&mut buf).unwrap();
+        assert_eq!(
+            decoded,
+            DecodeResult::Complete(UniStreamHeader::Push(header()))
+        );
+        assert_eq!(&buf[..], b"hello");
+    }
+
+    #[test]
+    fn test_lane_prologue_roundtrip() {
+        let mut buf = BytesMut::from(&encode_lane_prologue(Lane::Bulk).unwrap()[..]);
+        assert_eq!(buf[0], PROLOGUE_LANE);
+
+        let decoded = decode_uni_stream_header(&mut buf).unwrap();
+        assert_eq!(
+            decoded,
+            DecodeResult::Complete(UniStreamHeader::Lane(Lane::Bulk))
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_partial_header_consumes_nothing() {
+        let encoded = encode_push_prologue(&header()).unwrap();
+        for len in 0..encoded.len() {
+            let mut buf = BytesMut::from(&encoded[..len]);
+            assert_eq!(
+                decode_uni_stream_header(&mut buf).unwrap(),
+                DecodeResult::Incomplete
+            );
+            assert_eq!(buf.len(), len);
+        }
+    }
+
+    #[test]
+    fn test_unknown_stream_type_rejected() {
+        let mut buf = BytesMut::from(&[0x7f, 0x00][..]);
+        assert!(decode_uni_stream_header(&mut buf).is_err());
+    }
+}
@@ -0,0 +1,783 @@
+//! Relay ("rendezvous") mode for hosts that can't accept inbound connections, e.g.
+//! behind NAT.
+//!
+//! A host dials out to the relay at [`RELAY_HOST_PATH`] and registers its session on a
+//! control stream. Clients connect to the relay at `/s/<session>` instead of the host.
+//! For each client the relay sends [`RelayIncoming`] over the control stream, the host
+//! opens a fresh connection to the relay and claims the client with [`RelayAccept`], and
+//! the relay splices the two WebTransport sessions together: every stream and datagram
+//! on one side is forwarded to the other. The host ends up with an ordinary
+//! `Connection` and runs the usual handshake over it, so authentication and resume
+//! tokens stay end-to-end between client and host.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use bytes::BytesMut;
+use dashmap::DashMap;
+use rand::RngCore;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+use wtransport::endpoint::endpoint_side;
+use wtransport::{ClientConfig, Connection, Endpoint, Identity, ServerConfig, VarInt};
+use zellij_remote_core::TokenHash;
+use zellij_remote_protocol::{
+    relay_envelope, relay_error, RelayAccept, RelayEnvelope, RelayError, RelayIncoming,
+    RelayRegister, RelayRegistered,
+};
+
+use crate::framing::{decode_relay_envelope, encode_relay_envelope, DecodeResult};
+
+/// Path hosts connect to; clients use [`relay_session_path`]
+pub const RELAY_HOST_PATH: &str = "/.relay";
+const RELAY_SESSION_PREFIX: &str = "/s/";
+/// How long a client waits on the relay for its host to claim it
+pub const DEFAULT_RELAY_ACCEPT_TIMEOUT_MS: u64 = 10_000;
+/// Keeps the host's control connection from idling out while no clients arrive
+const RELAY_KEEP_ALIVE: Duration = Duration::from_secs(5);
+const HOST_KEY_SIZE: usize = 16;
+
+/// Path clients connect to on the relay to reach `session_name`.
+pub fn relay_session_path(session_name: &str) -> String {
+    format!("{}{}", RELAY_SESSION_PREFIX, session_name)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RelayClientError {
+    #[error("relay rejected the request ({code:?}): {message}")]
+    Rejected {
+        code: relay_error::Code,
+        message: String,
+    },
+    #[error("relay closed the control stream")]
+    Closed,
+    #[error("unexpected message from relay")]
+    UnexpectedMessage,
+}
+
+/// Host-side relay settings; set `BridgeConfig::relay` to serve clients through a relay
+/// instead of listening.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayClientConfig {
+    /// Base URL of the relay, e.g. `https://relay.example.com:4433`
+    pub relay_url: String,
+    pub session_name: String,
+    /// Registration secret, when the relay requires one
+    pub relay_token: Option<String>,
+    /// Skip relay certificate validation (self-signed relays)
+    pub accept_invalid_certs: bool,
+}
+
+impl RelayClientConfig {
+    fn host_url(&self) -> String {
+        format!(
+            "{}{}",
+            self.relay_url.trim_end_matches('/'),
+            RELAY_HOST_PATH
+        )
+    }
+}
+
+/// A host's registration with a relay. Dropping it unregisters the session.
+pub struct RelayClient {
+    config: RelayClientConfig,
+    endpoint: Endpoint<endpoint_side::Client>,
+    // Kept open for the lifetime of the registration; the relay treats the control
+    // stream closing as the host going away
+    _control: Connection,
+    _control_send: wtransport::SendStream,
+    control_recv: wtransport::RecvStream,
+    buffer: BytesMut,
+    host_key: Vec<u8>,
+}
+
+impl RelayClient {
+    /// Dial the relay and register `config.session_name`.
+    pub async fn register(config: RelayClientConfig) -> Result<Self> {
+        let builder = ClientConfig::builder().with_bind_default();
+        let builder = if config.accept_invalid_certs {
+            builder.with_no_cert_validation()
+        } else {
+            builder.with_native_certs()
+        };
+        let endpoint =
+            Endpoint::client(builder.keep_alive_interval(Some(RELAY_KEEP_ALIVE)).build())?;
+
+        let control = endpoint
+            .connect(config.host_url())
+            .await
+            .context("failed to connect to relay")?;
+        let (mut control_send, mut control_recv) = control.open_bi().await?.await?;
+        write_relay_message(
+            &mut control_send,
+            relay_envelope::Msg::Register(RelayRegister {
+                session_name: config.session_name.clone(),
+                relay_token: config
+                    .relay_token
+                    .as_deref()
+                    .unwrap_or_default()
+                    .as_bytes()
+                    .to_vec(),
+            }),
+        )
+        .await?;
+
+        let mut buffer = BytesMut::new();
+        let host_key = match read_relay_message(&mut control_recv, &mut buffer).await? {
+            Some(relay_envelope::Msg::Registered(registered)) => registered.host_key,
+            Some(relay_envelope::Msg::Error(error)) => return Err(rejected(error).into()),
+            Some(_) => return Err(RelayClientError::UnexpectedMessage.into()),
+            None => return Err(RelayClientError::Closed.into()),
+        };
+        log::info!(
+            "Registered session '{}' with relay {}",
+            config.session_name,
+            config.relay_url
+        );
+
+        Ok(Self {
+            config,
+            endpoint,
+            _control: control,
+            _control_send: control_send,
+            control_recv,
+            buffer,
+            host_key,
+        })
+    }
+
+    /// Wait for the next client to arrive at the relay and return a connection that
+    /// carries it, ready for the handshake.
+    pub async fn next_connection(&mut self) -> Result<Connection> {
+        loop {
+            let incoming =
+                match read_relay_message(&mut self.control_recv, &mut self.buffer).await? {
+                    Some(relay_envelope::Msg::Incoming(incoming)) => incoming,
+                    Some(relay_envelope::Msg::Error(error)) => {
+                        log::warn!("Relay error: {}", rejected(error));
+                        continue;
+                    },
+                    Some(_) => return Err(RelayClientError::UnexpectedMessage.into()),
+                    None => return Err(RelayClientError::Closed.into()),
+                };
+
+            log::info!(
+                "Relay client {} waiting from {}",
+                incoming.relay_client_id,
+                incoming.client_addr
+            );
+            match self.accept(incoming.relay_client_id).await {
+                Ok(connection) => return Ok(connection),
+                // The client will time out on the relay; keep serving the others
+                Err(e) => log::warn!(
+                    "Failed to accept relay client {}: {}",
+                    incoming.relay_client_id,
+                    e
+                ),
+            }
+        }
+    }
+
+    async fn accept(&self, relay_client_id: u64) -> Result<Connection> {
+        let connection = self.endpoint.connect(self.config.host_url()).await?;
+        let (mut send, _recv) = connection.open_bi().await?.await?;
+        write_relay_message(
+            &mut send,
+            relay_envelope::Msg::Accept(RelayAccept {
+                relay_client_id,
+                host_key: self.host_key.clone(),
+            }),
+        )
+        .await?;
+        Ok(connection)
+    }
+}
+
+fn rejected(error: RelayError) -> RelayClientError {
+    RelayClientError::Rejected {
+        code: error.code(),
+        message: error.message,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RelayServerConfig {
+    pub listen_addr: SocketAddr,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    /// Hosts must present this token to register; `None` lets any host register
+    pub relay_token: Option<TokenHash>,
+    pub accept_timeout: Duration,
+}
+
+impl Default for RelayServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: "0.0.0.0:4433".parse().unwrap(),
+            tls_cert: None,
+            tls_key: None,
+            relay_token: None,
+            accept_timeout: Duration::from_millis(DEFAULT_RELAY_ACCEPT_TIMEOUT_MS),
+        }
+    }
+}
+
+struct RegisteredHost {
+    host_key: Vec<u8>,
+    incoming: mpsc::UnboundedSender<RelayIncoming>,
+}
+
+struct PendingClient {
+    session_name: String,
+    host: oneshot::Sender<Connection>,
+}
+
+struct RelayState {
+    relay_token: Option<TokenHash>,
+    accept_timeout: Duration,
+    hosts: DashMap<String, RegisteredHost>,
+    pending: DashMap<u64, PendingClient>,
+    next_client_id: AtomicU64,
+}
+
+/// Forwards clients to hosts registered with it. Hosts and clients both connect with
+/// WebTransport; the relay never sees session traffic other than as opaque streams.
+pub struct RelayServer {
+    endpoint: Endpoint<endpoint_side::Server>,
+    state: Arc<RelayState>,
+}
+
+impl RelayServer {
+    pub async fn bind(config: RelayServerConfig) -> Result<Self> {
+        let identity = match (&config.tls_cert, &config.tls_key) {
+            (Some(cert_path), Some(key_path)) => Identity::load_pemfiles(cert_path, key_path)
+                .await
+                .context("failed to load TLS certificate/key")?,
+            _ => {
+                log::warn!("No TLS cert configured, generating self-signed certificate");
+                Identity::self_signed(["localhost"])
+                    .map_err(|e| anyhow::anyhow!("failed to create self-signed identity: {}", e))?
+            },
+        };
+        let server_config = ServerConfig::builder()
+            .with_bind_address(config.listen_addr)
+            .with_identity(identity)
+            .keep_alive_interval(Some(RELAY_KEEP_ALIVE))
+            .build();
+        let endpoint = Endpoint::server(server_config)?;
+
+        if config.relay_token.is_none() {
+            log::warn!("No relay token configured, any host can register a session");
+        }
+
+        Ok(Self {
+            endpoint,
+            state: Arc::new(RelayState {
+                relay_token: config.relay_token,
+                accept_timeout: config.accept_timeout,
+                hosts: DashMap::new(),
+                pending: DashMap::new(),
+                next_client_id: AtomicU64::new(1),
+            }),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.endpoint.local_addr()?)
+    }
+
+    pub async fn run_with_shutdown(&self, shutdown: CancellationToken) -> Result<()> {
+        log::info!("Relay listening on {}", self.local_addr()?);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => {
+                    log::info!("Relay shutdown requested");
+                    return Ok(());
+                }
+                incoming = self.endpoint.accept() => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_session(incoming, state).await {
+                            log::warn!("Relay session error: {}", e);
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
+
+async fn handle_session(
+    incoming: wtransport::endpoint::IncomingSession,
+    state: Arc<RelayState>,
+) -> Result<()> {
+    let request = incoming.await?;
+    let path = request.path().to_string();
+
+    if path == RELAY_HOST_PATH {
+        let connection = request.accept().await?;
+        return handle_host(connection, state).await;
+    }
+    if let Some(session_name) = path.strip_prefix(RELAY_SESSION_PREFIX) {
+        if state.hosts.contains_key(session_name) {
+            let session_name = session_name.to_string();
+            let connection = request.accept().await?;
+            return handle_client(connection, session_name, state).await;
+        }
+    }
+    log::debug!("Relay: no session at {}", path);
+    request.not_found().await;
+    Ok(())
+}
+
+async fn handle_host(connection: Connection, state: Arc<RelayState>) -> Result<()> {
+    let (mut send, mut recv) = connection.accept_bi().await?;
+    let mut buffer = BytesMut::new();
+    match read_relay_message(&mut recv, &mut buffer).await? {
+        Some(relay_envelope::Msg::Register(register)) => {
+            serve_host(&connection, send, recv, buffer, register, &state).await
+        },
+        Some(relay_envelope::Msg::Accept(accept)) => {
+            let session_name = state
+                .pending
+                .get(&accept.relay_client_id)
+                .map(|pending| pending.session_name.clone());
+            let authorized = session_name.is_some_and(|name| {
+                state
+                    .hosts
+                    .get(&name)
+                    .is_some_and(|host| keys_match(&host.host_key, &accept.host_key))
+            });
+            let pending = if authorized {
+                state.pending.remove(&accept.relay_client_id)
+            } else {
+                None
+            };
+            match pending {
+                Some((_, pending)) => {
+                    // The client task splices from here; if it timed out meanwhile the
+                    // connection is dropped and closes
+                    let _ = pending.host.send(connection);
+                    Ok(())
+                },
+                None => {
+                    send_relay_error(
+                        &mut send,
+                        relay_error::Code::UnknownClient,
+                        "no such client waiting for this host",
+                    )
+                    .await
+                },
+            }
+        },
+        _ => {
+            send_relay_error(
+                &mut send,
+                relay_error::Code::BadMessage,
+                "expected RelayRegister or RelayAccept",
+            )
+            .await
+        },
+    }
+}
+
+async fn serve_host(
+    connection: &Connection,
+    mut send: wtransport::SendStream,
+    mut recv: wtransport::RecvStream,
+    mut buffer: BytesMut,
+    register: RelayRegister,
+    state: &RelayState,
+) -> Result<()> {
+    if let Some(relay_token) = &state.relay_token {
+        if !relay_token.verify(&register.relay_token) {
+            log::warn!(
+                "Relay: host at {} failed to register '{}': invalid relay token",
+                connection.remote_address(),
+                register.session_name
+            );
+            return send_relay_error(
+                &mut send,
+                relay_error::Code::Unauthorized,
+                "invalid relay token",
+            )
+            .await;
+        }
+    }
+
+    let session_name = register.session_name;
+    let mut host_key = vec![0u8; HOST_KEY_SIZE];
+    rand::thread_rng().fill_bytes(&mut host_key);
+    let (incoming_tx, mut incoming_rx) = mpsc::unbounded_channel();
+    match state.hosts.entry(session_name.clone()) {
+        dashmap::mapref::entry::Entry::Occupied(_) => {
+            return send_relay_error(
+                &mut send,
+                relay_error::Code::SessionTaken,
+                "session is already registered",
+            )
+            .await;
+        },
+        dashmap::mapref::entry::Entry::Vacant(entry) => {
+            entry.insert(RegisteredHost {
+                host_key: host_key.clone(),
+                incoming: incoming_tx,
+            });
+        },
+    }
+    log::info!(
+        "Relay: session '{}' registered from {}",
+        session_name,
+        connection.remote_address()
+    );
+
+    let result = async {
+        write_relay_message(
+            &mut send,
+            relay_envelope::Msg::Registered(RelayRegistered { host_key }),
+        )
+        .await?;
+        loop {
+            tokio::select! {
+                incoming = incoming_rx.recv() => {
+                    let Some(incoming) = incoming else { break };
+                    write_relay_message(&mut send, relay_envelope::Msg::Incoming(incoming)).await?;
+                }
+                // Hosts don't send anything after registering; EOF unregisters
+                message = read_relay_message(&mut recv, &mut buffer) => {
+                    if !matches!(message, Ok(Some(_))) {
+                        break;
+                    }
+                }
+            }
+        }
+        anyhow::Ok(())
+    }
+    .await;
+
+    state.hosts.remove(&session_name);
+    // Waiting clients give up as soon as their sender is dropped
+    state
+        .pending
+        .retain(|_, pending| pending.session_name != session_name);
+    log::info!("Relay: session '{}' unregistered", session_name);
+    result
+}
+
+async fn handle_client(
+    connection: Connection,
+    session_name: String,
+    state: Arc<RelayState>,
+) -> Result<()> {
+    let relay_client_id = state.next_client_id.fetch_add(1, Ordering::Relaxed);
+    let (host_tx, host_rx) = oneshot::channel();
+    state.pending.insert(
+        relay_client_id,
+        PendingClient {
+            session_name: session_name.clone(),
+            host: host_tx,
+        },
+    );
+
+    let notified = state.hosts.get(&session_name).is_some_and(|host| {
+        host.incoming
+            .send(RelayIncoming {
+                relay_client_id,
+                client_addr: connection.remote_address().to_string(),
+            })
+            .is_ok()
+    });
+    let accepted = if notified {
+        tokio::time::timeout(state.accept_timeout, host_rx)
+            .await
+            .ok()
+            .and_then(Result::ok)
+    } else {
+        None
+    };
+    let Some(host) = accepted else {
+        state.pending.remove(&relay_client_id);
+        log::warn!(
+            "Relay: host for '{}' did not accept client {}",
+            session_name,
+            relay_client_id
+        );
+        connection.close(VarInt::from_u32(0), b"host unavailable");
+        return Ok(());
+    };
+
+    log::info!(
+        "Relay: client {} from {} connected to '{}'",
+        relay_client_id,
+        connection.remote_address(),
+        session_name
+    );
+    splice(connection, host).await;
+    log::info!("Relay: client {} disconnected", relay_client_id);
+    Ok(())
+}
+
+/// Forward every stream and datagram between two connections until either closes.
+pub async fn splice(client: Connection, host: Connection) {
+    let client = Arc::new(client);
+    let host = Arc::new(host);
+    loop {
+        tokio::select! {
+            stream = client.accept_bi() => {
+                let Ok(stream) = stream else { break };
+                tokio::spawn(forward_bi(stream, host.clone()));
+            }
+            stream = host.accept_bi() => {
+                let Ok(stream) = stream else { break };
+                tokio::spawn(forward_bi(stream, client.clone()));
+            }
+            stream = client.accept_uni() => {
+                let Ok(stream) = stream else { break };
+                tokio::spawn(forward_uni(stream, host.clone()));
+            }
+            stream = host.accept_uni() => {
+                let Ok(stream) = stream else { break };
+                tokio::spawn(forward_uni(stream, client.clone()));
+            }
+            datagram = client.receive_datagram() => {
+                let Ok(datagram) = datagram else { break };
+                if let Err(e) = host.send_datagram(datagram.payload()) {
+                    log::debug!("Relay: dropped datagram to host: {}", e);
+                }
+            }
+            datagram = host.receive_datagram() => {
+                let Ok(datagram) = datagram else { break };
+                if let Err(e) = client.send_datagram(datagram.payload()) {
+                    log::debug!("Relay: dropped datagram to client: {}", e);
+                }
+            }
+        }
+    }
+    client.close(VarInt::from_u32(0), b"peer disconnected");
+    host.close(VarInt::from_u32(0), b"peer disconnected");
+}
+
+async fn forward_bi(
+    (mut send, mut recv): (wtransport::SendStream, wtransport::RecvStream),
+    to: Arc<Connection>,
+) {
+    let Ok(opening) = to.open_bi().await else {
+        return;
+    };
+    let Ok((mut to_send, mut to_recv)) = opening.await else {
+        return;
+    };
+    tokio::join!(pipe(&mut recv, &mut to_send), pipe(&mut to_recv, &mut send));
+}
+
+async fn forward_uni(mut recv: wtransport::RecvStream, to: Arc<Connection>) {
+    let Ok(opening) = to.open_uni().await else {
+        return;
+    };
+    let Ok(mut to_send) = opening.await else {
+        return;
+    };
+    pipe(&mut recv, &mut to_send).await;
+}
+
+async fn pipe(from: &mut wtransport::RecvStream, to: &mut wtransport::SendStream) {
+    if tokio::io::copy(from, to).await.is_ok() {
+        let _ = to.finish().await;
+    }
+}
+
+async fn read_relay_message<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buffer: &mut BytesMut,
+) -> Result<Option<relay_envelope::Msg>> {
+    loop {
+        if let DecodeResult::Complete(envelope) = decode_relay_envelope(buffer)? {
+            return Ok(envelope.msg);
+        }
+        let mut chunk = [0u8; 1024];
+        let n = reader.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+}
+
+async fn write_relay_message<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    msg: relay_envelope::Msg,
+) -> Result<()> {
+    let encoded = encode_relay_envelope(&RelayEnvelope { msg: Some(msg) })?;
+    writer.write_all(&encoded).await?;
+    Ok(())
+}
+
+/// Send an error and wait for the peer to receive it, since the connection is usually
+/// dropped right after.
+async fn send_relay_error(
+    send: &mut wtransport::SendStream,
+    code: relay_error::Code,
+    message: &str,
+) -> Result<()> {
+    write_relay_message(
+        send,
+        relay_envelope::Msg::Error(RelayError {
+            code: code as i32,
+            message: message.to_string(),
+        }),
+    )
+    .await?;
+    send.finish().await?;
+    Ok(())
+}
+
+fn keys_match(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_relay_config(relay_token: Option<&str>) -> RelayServerConfig {
+        RelayServerConfig {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            relay_token: relay_token.map(|token| TokenHash::from_plaintext(token.as_bytes())),
+            accept_timeout: Duration::from_secs(2),
+            ..Default::default()
+        }
+    }
+
+    async fn start_relay(relay_token: Option<&str>) -> (String, CancellationToken) {
+        let relay = RelayServer::bind(test_relay_config(relay_token))
+            .await
+            .unwrap();
+        let url = format!("https://127.0.0.1:{}", relay.local_addr().unwrap().port());
+        let shutdown = CancellationToken::new();
+        let relay_shutdown = shutdown.clone();
+        tokio::spawn(async move { relay.run_with_shutdown(relay_shutdown).await });
+        (url, shutdown)
+    }
+
+    fn host_config(relay_url: &str, relay_token: Option<&str>) -> RelayClientConfig {
+        RelayClientConfig {
+            relay_url: relay_url.to_string(),
+            session_name: "test-session".to_string(),
+            relay_token: relay_token.map(str::to_string),
+            accept_invalid_certs: true,
+        }
+    }
+
+    fn test_client() -> Endpoint<endpoint_side::Client> {
+        Endpoint::client(
+            ClientConfig::builder()
+                .with_bind_default()
+                .with_no_cert_validation()
+                .build(),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_relay_splices_streams_and_datagrams() {
+        let (relay_url, shutdown) = start_relay(Some("relay-secret")).await;
+        let mut host = RelayClient::register(host_config(&relay_url, Some("relay-secret")))
+            .await
+            .unwrap();
+
+        let host_task = tokio::spawn(async move {
+            let connection = host.next_connection().await.unwrap();
+            let (mut send, mut recv) = connection.accept_bi().await.unwrap();
+            let mut hello = [0u8; 5];
+            recv.read_exact(&mut hello).await.unwrap();
+            assert_eq!(&hello, b"hello");
+            send.write_all(b"welcome").await.unwrap();
+            send.finish().await.unwrap();
+
+            let datagram = connection.receive_datagram().await.unwrap();
+            connection.send_datagram(datagram.payload()).unwrap();
+            // Hold the connection until the client has read everything
+            let _ = connection.closed().await;
+        });
+
+        let client = test_client()
+            .connect(format!(
+                "{}{}",
+                relay_url,
+                relay_session_path("test-session")
+            ))
+            .await
+            .unwrap();
+        let (mut send, mut recv) = client.open_bi().await.unwrap().await.unwrap();
+        send.write_all(b"hello").await.unwrap();
+        let mut reply = Vec::new();
+        recv.read_to_end(&mut reply).await.unwrap();
+        assert_eq!(reply, b"welcome");
+
+        // Datagrams are unreliable, so retry until the echo comes back
+        let echoed = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                client.send_datagram(&b"ping"[..]).unwrap();
+                tokio::select! {
+                    datagram = client.receive_datagram() => return datagram.unwrap().payload(),
+                    _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(&echoed[..], b"ping");
+
+        client.close(VarInt::from_u32(0), b"done");
+        host_task.await.unwrap();
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_relay_rejects_invalid_token_and_duplicate_session() {
+        let (relay_url, shutdown) = start_relay(Some("relay-secret")).await;
+
+        let err = RelayClient::register(host_config(&relay_url, Some("wrong")))
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<RelayClientError>(),
+            Some(RelayClientError::Rejected {
+                code: relay_error::Code::Unauthorized,
+                ..
+            })
+        ));
+
+        let _host = RelayClient::register(host_config(&relay_url, Some("relay-secret")))
+            .await
+            .unwrap();
+        let err = RelayClient::register(host_config(&relay_url, Some("relay-secret")))
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<RelayClientError>(),
+            Some(RelayClientError::Rejected {
+                code: relay_error::Code::SessionTaken,
+                ..
+            })
+        ));
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_relay_refuses_unknown_session() {
+        let (relay_url, shutdown) = start_relay(None).await;
+        let result = test_client()
+            .connect(format!("{}{}", relay_url, relay_session_path("missing")))
+            .await;
+        assert!(result.is_err());
+        shutdown.cancel();
+    }
+}
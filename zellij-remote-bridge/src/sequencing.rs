@@ -0,0 +1,139 @@
+//! Envelope sequence numbers.
+//!
+//! A sender may stamp `StreamEnvelope.envelope_seq` on what it writes to a stream: 1
+//! on the first envelope, one more on each after. Streams are reliable and ordered,
+//! so a receiver seeing anything else has found a bug on the sending side (a message
+//! built but never written, two writers interleaving) rather than loss on the wire.
+//! 0 means unstamped and is never checked, so peers that don't stamp still work.
+
+use zellij_remote_protocol::StreamEnvelope;
+
+/// Stamps the envelopes written to one stream
+#[derive(Debug, Default)]
+pub struct EnvelopeSequencer {
+    last: u64,
+}
+
+impl EnvelopeSequencer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give `envelope` the next sequence number and return it
+    pub fn stamp(&mut self, envelope: &mut StreamEnvelope) -> u64 {
+        self.last = self.last.saturating_add(1);
+        envelope.envelope_seq = self.last;
+        self.last
+    }
+
+    /// The number the last envelope was stamped with, 0 before the first
+    pub fn last(&self) -> u64 {
+        self.last
+    }
+}
+
+/// A stamped envelope that wasn't the one after the last
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceGap {
+    pub expected: u64,
+    pub received: u64,
+}
+
+impl SequenceGap {
+    /// Envelopes missing between the last and this one; 0 for a repeat or step back
+    pub fn missing(&self) -> u64 {
+        self.received.saturating_sub(self.expected)
+    }
+}
+
+/// Checks the envelopes read from one stream
+#[derive(Debug, Default)]
+pub struct EnvelopeSequenceChecker {
+    last: u64,
+    gaps: u64,
+}
+
+impl EnvelopeSequenceChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check a received envelope, returning the gap if its number isn't the one
+    /// expected. Counting carries on from its number either way, so one lost write
+    /// is one gap rather than one per envelope after it. The first stamped envelope
+    /// sets the count, as the reader may start after the sender did (e.g. once the
+    /// handshake is done).
+    pub fn observe(&mut self, envelope: &StreamEnvelope) -> Option<SequenceGap> {
+        let received = envelope.envelope_seq;
+        if received == 0 {
+            return None;
+        }
+        let last = std::mem::replace(&mut self.last, received);
+        let expected = last.saturating_add(1);
+        if last == 0 || received == expected {
+            return None;
+        }
+        self.gaps += 1;
+        Some(SequenceGap { expected, received })
+    }
+
+    /// Gaps seen so far
+    pub fn gaps(&self) -> u64 {
+        self.gaps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zellij_remote_protocol::{stream_envelope, Ping};
+
+    fn ping() -> StreamEnvelope {
+        StreamEnvelope {
+            msg: Some(stream_envelope::Msg::Ping(Ping::default())),
+            envelope_seq: 0,
+        }
+    }
+
+    #[test]
+    fn test_stamped_envelopes_check_in_order() {
+        let mut sequencer = EnvelopeSequencer::new();
+        let mut checker = EnvelopeSequenceChecker::new();
+        for expected in 1..=3 {
+            let mut envelope = ping();
+            assert_eq!(sequencer.stamp(&mut envelope), expected);
+            assert_eq!(checker.observe(&envelope), None);
+        }
+        // Unstamped envelopes are let through without disturbing the count
+        assert_eq!(checker.observe(&ping()), None);
+        let mut envelope = ping();
+        sequencer.stamp(&mut envelope);
+        assert_eq!(checker.observe(&envelope), None);
+        assert_eq!(checker.gaps(), 0);
+    }
+
+    #[test]
+    fn test_gaps_counted_once_and_resynced() {
+        let mut checker = EnvelopeSequenceChecker::new();
+        let at = |seq| StreamEnvelope {
+            envelope_seq: seq,
+            ..ping()
+        };
+        assert_eq!(checker.observe(&at(3)), None);
+        assert_eq!(checker.observe(&at(1)).unwrap().missing(), 0);
+        let gap = checker.observe(&at(4)).unwrap();
+        assert_eq!(
+            gap,
+            SequenceGap {
+                expected: 2,
+                received: 4
+            }
+        );
+        assert_eq!(gap.missing(), 2);
+        assert_eq!(checker.observe(&at(5)), None);
+
+        let repeat = checker.observe(&at(5)).unwrap();
+        assert_eq!(repeat.missing(), 0);
+        assert_eq!(checker.gaps(), 3);
+    }
+}
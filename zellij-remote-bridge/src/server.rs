@@ -1,20 +1,45 @@
 use anyhow::{Context, Result};
+use std::net::IpAddr;
 use std::sync::atomic::{AtomicU64, Ordering};
 use tokio_util::sync::CancellationToken;
 use wtransport::{Endpoint, Identity, ServerConfig};
+use zellij_remote_core::SharedTokenRegistry;
 
+use crate::audit::{AuditEvent, AuditLog};
 use crate::config::BridgeConfig;
-use crate::handshake::run_handshake;
+use crate::conformance_echo::{self, CONFORMANCE_ECHO_EXTENSION};
+use crate::extensions::ExtensionRegistry;
+use crate::forwarded::{client_ip, FORWARDED_FOR_HEADER};
+use crate::handshake::{run_authenticated_handshake, AuthError};
+use crate::relay::{RelayClient, RelayClientConfig};
 
 static CLIENT_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
 
 pub struct RemoteBridge {
     config: BridgeConfig,
+    audit: AuditLog,
+    extensions: ExtensionRegistry,
 }
 
 impl RemoteBridge {
     pub fn new(config: BridgeConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            audit: AuditLog::new(),
+            extensions: ExtensionRegistry::default(),
+        }
+    }
+
+    /// Answer the handshake extensions clients offer with these handlers; none are
+    /// accepted by default.
+    pub fn with_extensions(mut self, extensions: ExtensionRegistry) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Subscribe to authentication and connection audit events.
+    pub fn subscribe_audit(&self) -> tokio::sync::broadcast::Receiver<AuditEvent> {
+        self.audit.subscribe()
     }
 
     pub async fn run(&self) -> Result<()> {
@@ -22,6 +47,10 @@ impl RemoteBridge {
     }
 
     pub async fn run_with_shutdown(&self, shutdown: CancellationToken) -> Result<()> {
+        if let Some(relay) = &self.config.relay {
+            return self.run_relayed(relay.clone(), shutdown).await;
+        }
+
         let identity = self.build_identity().await?;
 
         let config = ServerConfig::builder()
@@ -35,6 +64,9 @@ impl RemoteBridge {
             "WebTransport server listening on {}",
             self.config.listen_addr
         );
+        if self.config.tokens.snapshot().is_empty() {
+            log::warn!("No bearer tokens configured, remote clients are not authenticated");
+        }
 
         loop {
             tokio::select! {
@@ -47,37 +79,153 @@ impl RemoteBridge {
 
                     log::info!("Incoming connection from {}", session_request.authority());
 
+                    let remote_addr = session_request.remote_address();
+                    let client_ip = client_ip(
+                        remote_addr.ip(),
+                        session_request
+                            .headers()
+                            .get(FORWARDED_FOR_HEADER)
+                            .map(String::as_str),
+                        &self.config.trusted_proxies,
+                    );
+                    if client_ip != remote_addr.ip().to_canonical() {
+                        log::info!("Connection from {} forwarded by {}", client_ip, remote_addr);
+                    }
+                    if !self.config.allowlist.allows(client_ip) {
+                        log::warn!("Rejecting connection from {}: not allowlisted", client_ip);
+                        session_request.forbidden().await;
+                        continue;
+                    }
+
                     let connection = session_request.accept().await?;
-                    let session_name = self.config.session_name.clone();
+                    self.spawn_connection(connection, Some(client_ip));
+                }
+            }
+        }
+    }
 
-                    tokio::spawn(async move {
-                        if let Err(e) = Self::handle_connection(connection, session_name).await {
-                            log::error!("Connection error: {}", e);
-                        }
-                    });
+    /// Register with a relay and serve the clients it forwards; for hosts that can't
+    /// accept inbound connections.
+    async fn run_relayed(
+        &self,
+        relay: RelayClientConfig,
+        shutdown: CancellationToken,
+    ) -> Result<()> {
+        let mut relay_client = RelayClient::register(relay).await?;
+        if self.config.tokens.snapshot().is_empty() {
+            log::warn!("No bearer tokens configured, remote clients are not authenticated");
+        }
+
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => {
+                    log::info!("Server shutdown requested");
+                    return Ok(());
+                }
+                connection = relay_client.next_connection() => {
+                    let connection = connection?;
+                    log::info!("Incoming relayed connection");
+                    self.spawn_connection(connection, None);
                 }
             }
         }
     }
 
+    fn spawn_connection(&self, connection: wtransport::Connection, client_ip: Option<IpAddr>) {
+        let session_name = self.config.session_name.clone();
+        let tokens = self.config.tokens.clone();
+        let audit = self.audit.clone();
+        let extensions = self.extensions.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = Self::handle_connection(
+                connection,
+                client_ip,
+                session_name,
+                tokens,
+                audit,
+                extensions,
+            )
+            .await
+            {
+                log::error!("Connection error: {}", e);
+            }
+        });
+    }
+
     async fn handle_connection(
         connection: wtransport::Connection,
+        client_ip: Option<IpAddr>,
         session_name: String,
+        tokens: SharedTokenRegistry,
+        audit: AuditLog,
+        extensions: ExtensionRegistry,
     ) -> Result<()> {
-        let (send, recv) = connection.accept_bi().await?;
+        let (mut send, mut recv) = connection.accept_bi().await?;
         let client_id = CLIENT_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
 
-        let result = run_handshake(recv, send, session_name, client_id).await?;
+        // Snapshot per connection so a reload applies to the next client to connect
+        let registry = tokens.snapshot();
+        let result = match run_authenticated_handshake(
+            &mut recv,
+            &mut send,
+            session_name,
+            client_id,
+            &registry,
+            &extensions,
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(e) => {
+                if let Some(auth_error) = e.downcast_ref::<AuthError>() {
+                    audit.emit(AuditEvent::AuthenticationFailed {
+                        client_id,
+                        client_ip,
+                        token_id: auth_error.token_id().map(str::to_string),
+                        reason: auth_error.to_string(),
+                    });
+                }
+                return Err(e);
+            },
+        };
 
         log::info!(
             "Handshake complete: client_id={}, client_name={}",
             result.client_id,
             result.client_hello.client_name
         );
+        audit.emit(AuditEvent::ClientAuthenticated {
+            client_id,
+            client_name: result.client_hello.client_name.clone(),
+            client_ip,
+            token_id: result.token_id.clone(),
+            role: result.role,
+        });
+
+        if let Some(echo) = result
+            .server_hello
+            .extensions
+            .iter()
+            .find(|ext| ext.name == CONFORMANCE_ECHO_EXTENSION)
+        {
+            let sent = conformance_echo::send_echo(&mut send, &echo.payload).await?;
+            log::info!(
+                "Sent {} conformance fixture messages to {}",
+                sent,
+                client_id
+            );
+            send.finish().await.ok();
+        }
 
         // For spike: just keep connection alive
         // Real implementation will proceed to main loop
         tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+
+        audit.emit(AuditEvent::ClientDisconnected {
+            client_id,
+            token_id: result.token_id,
+        });
         Ok(())
     }
 
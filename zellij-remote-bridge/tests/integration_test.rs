@@ -21,10 +21,31 @@ fn make_client_hello() -> ClientHello {
             supports_images: false,
             supports_clipboard: false,
             supports_hyperlinks: false,
+            supports_line_size: false,
+            supports_text_mode: false,
+            supports_frame_hash: false,
+            supports_style_retention: false,
+            supports_priority_lanes: false,
+            supports_row_checksums: false,
+            supports_push_streams: false,
+            supports_bell: false,
+            supports_lease_status: false,
+            max_message_bytes: 0,
+            supports_pane_layout: false,
+            supports_keybinding_info: false,
         }),
         client_name: "integration-test".to_string(),
         bearer_token: vec![],
         resume_token: vec![],
+        min_render_window: 0,
+        max_render_window: 0,
+        supported_codecs: vec![],
+        debug_frame_info: false,
+        locale: None,
+        color_transform: None,
+        extensions: vec![],
+        terminal_answers: None,
+        flash_guard: None,
     }
 }
 
@@ -41,6 +62,7 @@ async fn test_full_handshake_flow_over_duplex() {
     let client_hello = make_client_hello();
     let envelope = StreamEnvelope {
         msg: Some(stream_envelope::Msg::ClientHello(client_hello.clone())),
+        envelope_seq: 0,
     };
     let encoded = encode_envelope(&envelope).unwrap();
     client_write.write_all(&encoded).await.unwrap();
@@ -84,6 +106,7 @@ async fn test_multiple_messages_in_sequence() {
     let client_hello = make_client_hello();
     let envelope = StreamEnvelope {
         msg: Some(stream_envelope::Msg::ClientHello(client_hello)),
+        envelope_seq: 0,
     };
     let encoded = encode_envelope(&envelope).unwrap();
     client_write.write_all(&encoded).await.unwrap();
@@ -124,6 +147,8 @@ fn test_screen_snapshot_encode_decode_via_framing() {
             codepoints: vec![72, 101, 108, 108, 111],
             widths: vec![1, 1, 1, 1, 1],
             style_ids: vec![0, 0, 0, 0, 0],
+            line_size: 0,
+            col_start: 0,
         }],
         cursor: Some(CursorState {
             row: 0,
@@ -131,12 +156,22 @@ fn test_screen_snapshot_encode_decode_via_framing() {
             visible: true,
             blink: true,
             shape: 1,
+            color: None,
         }),
         delivered_input_watermark: 100,
+        server_time_ms: 0,
+        frame_sequence: 0,
+        frame_hash: 0,
+        size_changed: None,
+        chunk_index: 0,
+        chunk_count: 0,
+        echo_suppressed: false,
+        debug_info: None,
     };
 
     let envelope = StreamEnvelope {
         msg: Some(stream_envelope::Msg::ScreenSnapshot(snapshot.clone())),
+        envelope_seq: 0,
     };
 
     let encoded = encode_envelope(&envelope).unwrap();
@@ -174,6 +209,9 @@ fn test_screen_delta_encode_decode_via_framing() {
                 widths: vec![1, 1, 1],
                 style_ids: vec![5, 5, 5],
             }],
+            line_size: 0,
+            echo: false,
+            row_crc32: 0,
         }],
         cursor: Some(CursorState {
             row: 10,
@@ -181,12 +219,21 @@ fn test_screen_delta_encode_decode_via_framing() {
             visible: true,
             blink: false,
             shape: 2,
+            color: None,
         }),
         delivered_input_watermark: 50,
+        server_time_ms: 0,
+        frame_sequence: 0,
+        frame_hash: 0,
+        chunk_index: 0,
+        chunk_count: 0,
+        echo_suppressed: false,
+        debug_info: None,
     };
 
     let envelope = StreamEnvelope {
         msg: Some(stream_envelope::Msg::ScreenDeltaStream(delta.clone())),
+        envelope_seq: 0,
     };
 
     let encoded = encode_envelope(&envelope).unwrap();
@@ -216,6 +263,8 @@ fn test_large_snapshot_framing() {
             codepoints: vec![32; 200],
             widths: vec![1; 200],
             style_ids: vec![0; 200],
+            line_size: 0,
+            col_start: 0,
         })
         .collect();
 
@@ -230,10 +279,19 @@ fn test_large_snapshot_framing() {
         rows,
         cursor: None,
         delivered_input_watermark: 0,
+        server_time_ms: 0,
+        frame_sequence: 0,
+        frame_hash: 0,
+        size_changed: None,
+        chunk_index: 0,
+        chunk_count: 0,
+        echo_suppressed: false,
+        debug_info: None,
     };
 
     let envelope = StreamEnvelope {
         msg: Some(stream_envelope::Msg::ScreenSnapshot(snapshot)),
+        envelope_seq: 0,
     };
 
     let encoded = encode_envelope(&envelope).unwrap();
@@ -264,10 +322,31 @@ fn test_build_server_hello_negotiates_capabilities() {
             supports_images: true,
             supports_clipboard: true,
             supports_hyperlinks: true,
+            supports_line_size: false,
+            supports_text_mode: false,
+            supports_frame_hash: false,
+            supports_style_retention: false,
+            supports_priority_lanes: false,
+            supports_row_checksums: false,
+            supports_push_streams: false,
+            supports_bell: false,
+            supports_lease_status: false,
+            max_message_bytes: 0,
+            supports_pane_layout: false,
+            supports_keybinding_info: false,
         }),
         client_name: "test".to_string(),
         bearer_token: vec![],
         resume_token: vec![],
+        min_render_window: 0,
+        max_render_window: 0,
+        supported_codecs: vec![],
+        debug_frame_info: false,
+        locale: None,
+        color_transform: None,
+        extensions: vec![],
+        terminal_answers: None,
+        flash_guard: None,
     };
 
     let hello = build_server_hello(&client_hello_with_datagrams, "session", 1);
@@ -0,0 +1,213 @@
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use zellij_remote_protocol::ClientRole;
+
+use crate::resume_token::constant_time_eq;
+
+const SALT_SIZE: usize = 16;
+const DIGEST_SIZE: usize = 32;
+const HASH_SCHEME: &str = "sha256";
+
+/// A bearer token stored as `SHA-256(salt || token)` so the plaintext never has to be
+/// kept in memory or configuration.
+///
+/// The encoded form is `sha256:<salt-hex>:<digest-hex>`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TokenHash {
+    salt: Vec<u8>,
+    digest: [u8; DIGEST_SIZE],
+}
+
+impl TokenHash {
+    /// Hash a plaintext token with a freshly generated random salt.
+    pub fn from_plaintext(token: &[u8]) -> Self {
+        let mut salt = vec![0u8; SALT_SIZE];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self::with_salt(token, salt)
+    }
+
+    pub fn with_salt(token: &[u8], salt: Vec<u8>) -> Self {
+        let digest = salted_digest(&salt, token);
+        Self { salt, digest }
+    }
+
+    /// Parse the `sha256:<salt-hex>:<digest-hex>` form produced by [`TokenHash::encode`].
+    pub fn parse(encoded: &str) -> Option<Self> {
+        let mut parts = encoded.trim().splitn(3, ':');
+        if parts.next()? != HASH_SCHEME {
+            return None;
+        }
+        let salt = decode_hex(parts.next()?)?;
+        let digest: [u8; DIGEST_SIZE] = decode_hex(parts.next()?)?.try_into().ok()?;
+        if salt.is_empty() {
+            return None;
+        }
+        Some(Self { salt, digest })
+    }
+
+    pub fn encode(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            HASH_SCHEME,
+            encode_hex(&self.salt),
+            encode_hex(&self.digest)
+        )
+    }
+
+    /// Check a presented token in constant time.
+    pub fn verify(&self, presented: &[u8]) -> bool {
+        let candidate = salted_digest(&self.salt, presented);
+        constant_time_eq(&candidate, &self.digest)
+    }
+}
+
+impl std::fmt::Debug for TokenHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenHash")
+            .field("scheme", &HASH_SCHEME)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Source of the expected bearer token, consulted on every authentication attempt.
+///
+/// Implement this to fetch tokens from a secret manager. Returning `None` (e.g. the
+/// secret manager is unreachable) rejects every client rather than disabling auth.
+pub trait TokenProvider: Send + Sync {
+    fn current_token(&self) -> Option<TokenHash>;
+}
+
+impl TokenProvider for TokenHash {
+    fn current_token(&self) -> Option<TokenHash> {
+        Some(self.clone())
+    }
+}
+
+impl<F> TokenProvider for F
+where
+    F: Fn() -> Option<TokenHash> + Send + Sync,
+{
+    fn current_token(&self) -> Option<TokenHash> {
+        self()
+    }
+}
+
+/// One entry in a multi-token deployment: a named token with its own role and expiry.
+#[derive(Debug, Clone)]
+pub struct TokenEntry {
+    /// Stable identifier used to attribute connections (never the token itself)
+    pub id: String,
+    pub hash: TokenHash,
+    pub role: ClientRole,
+    /// Unix time in ms after which the token is rejected (None = never expires)
+    pub expires_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthOutcome {
+    Accepted { token_id: String, role: ClientRole },
+    Expired { token_id: String },
+    Rejected,
+}
+
+/// A list of bearer tokens that can be checked and replaced as a whole.
+///
+/// An empty registry means authentication is disabled.
+#[derive(Debug, Clone, Default)]
+pub struct TokenRegistry {
+    entries: Vec<TokenEntry>,
+}
+
+impl TokenRegistry {
+    pub fn new(entries: Vec<TokenEntry>) -> Self {
+        Self { entries }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn authenticate(&self, presented: &[u8]) -> AuthOutcome {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        self.authenticate_at(presented, now_ms)
+    }
+
+    /// Every entry is checked so the time taken doesn't reveal which token matched.
+    pub fn authenticate_at(&self, presented: &[u8], now_ms: u64) -> AuthOutcome {
+        let mut matched: Option<&TokenEntry> = None;
+        for entry in &self.entries {
+            if entry.hash.verify(presented) && matched.is_none() {
+                matched = Some(entry);
+            }
+        }
+
+        match matched {
+            Some(entry) if entry.expires_at_ms.is_some_and(|exp| now_ms >= exp) => {
+                AuthOutcome::Expired {
+                    token_id: entry.id.clone(),
+                }
+            },
+            Some(entry) => AuthOutcome::Accepted {
+                token_id: entry.id.clone(),
+                role: entry.role,
+            },
+            None => AuthOutcome::Rejected,
+        }
+    }
+}
+
+/// A [`TokenRegistry`] shared between the accept loop and whoever reloads it, so the
+/// token list can be swapped without restarting the listener.
+#[derive(Debug, Clone, Default)]
+pub struct SharedTokenRegistry(Arc<RwLock<TokenRegistry>>);
+
+impl SharedTokenRegistry {
+    pub fn new(registry: TokenRegistry) -> Self {
+        Self(Arc::new(RwLock::new(registry)))
+    }
+
+    pub fn replace(&self, registry: TokenRegistry) {
+        match self.0.write() {
+            Ok(mut guard) => *guard = registry,
+            Err(poisoned) => *poisoned.into_inner() = registry,
+        }
+    }
+
+    pub fn snapshot(&self) -> TokenRegistry {
+        match self.0.read() {
+            Ok(guard) => guard.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+fn salted_digest(salt: &[u8], token: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(token);
+    hasher.finalize().into()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
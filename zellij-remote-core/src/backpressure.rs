@@ -1,18 +1,108 @@
+use std::collections::VecDeque;
+
+use zellij_remote_protocol::{DEFAULT_MAX_RENDER_WINDOW, DEFAULT_MIN_RENDER_WINDOW};
+
 const DEFAULT_WINDOW_SIZE: u32 = 4;
 
+/// Loss reported by the client (parts per million) at or above which the window shrinks
+const LOSS_SHRINK_PPM: u32 = 10_000;
+const SRTT_ALPHA: f64 = 0.125;
+const THROUGHPUT_ALPHA: f64 = 0.125;
+const FRAME_BYTES_ALPHA: f64 = 0.125;
+
+/// Range the render window may be tuned within.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowBounds {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl WindowBounds {
+    pub fn fixed(size: u32) -> Self {
+        Self {
+            min: size,
+            max: size,
+        }
+    }
+
+    /// Intersect the server's limits with the bounds a client asked for (0 = no preference).
+    pub fn negotiate(server: WindowBounds, client_min: u32, client_max: u32) -> Self {
+        let max = match client_max {
+            0 => server.max,
+            requested => requested.clamp(server.min, server.max),
+        };
+        let min = match client_min {
+            0 => server.min,
+            requested => requested.clamp(server.min, max),
+        };
+        Self {
+            min: min.min(max),
+            max,
+        }
+    }
+
+    pub fn clamp(&self, size: u32) -> u32 {
+        size.clamp(self.min, self.max)
+    }
+
+    pub fn is_fixed(&self) -> bool {
+        self.min == self.max
+    }
+}
+
+impl Default for WindowBounds {
+    fn default() -> Self {
+        Self {
+            min: DEFAULT_MIN_RENDER_WINDOW,
+            max: DEFAULT_MAX_RENDER_WINDOW,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SentFrame {
+    state_id: u64,
+    bytes: usize,
+    sent_at_ms: u64,
+}
+
+/// Flow control for render updates: at most `window_size` state_ids may be unacked.
+///
+/// With non-fixed bounds the window is tuned from acks: it grows by one per clean ack
+/// towards the bandwidth-delay product (measured throughput x srtt, in frames) and
+/// halves, at most once per srtt, when the client reports loss.
 #[derive(Debug)]
 pub struct RenderWindow {
     window_size: u32,
     oldest_unacked_state_id: u64,
     newest_sent_state_id: u64,
+    bounds: WindowBounds,
+    in_flight: VecDeque<SentFrame>,
+    srtt_ms: Option<f64>,
+    /// Delivery rate in bytes per ms; jumps up to new maxima, decays slowly
+    throughput: Option<f64>,
+    avg_frame_bytes: Option<f64>,
+    last_shrink_ms: Option<u64>,
 }
 
 impl RenderWindow {
     pub fn new(window_size: u32) -> Self {
+        Self::with_bounds(window_size, WindowBounds::fixed(window_size))
+    }
+
+    /// A window starting at `initial_size` (clamped to `bounds`) that tunes itself when
+    /// sends and acks are reported with [`Self::mark_sent_at`] / [`Self::ack_received_at`].
+    pub fn with_bounds(initial_size: u32, bounds: WindowBounds) -> Self {
         Self {
-            window_size,
+            window_size: bounds.clamp(initial_size),
             oldest_unacked_state_id: 0,
             newest_sent_state_id: 0,
+            bounds,
+            in_flight: VecDeque::new(),
+            srtt_ms: None,
+            throughput: None,
+            avg_frame_bytes: None,
+            last_shrink_ms: None,
         }
     }
 
@@ -35,6 +125,91 @@ impl RenderWindow {
         }
     }
 
+    /// Like [`Self::mark_sent`], also recording the delta's size and send time for tuning.
+    pub fn mark_sent_at(&mut self, state_id: u64, bytes: usize, now_ms: u64) {
+        self.mark_sent(state_id);
+        self.avg_frame_bytes = Some(match self.avg_frame_bytes {
+            Some(avg) => avg + FRAME_BYTES_ALPHA * (bytes as f64 - avg),
+            None => bytes as f64,
+        });
+        self.in_flight.push_back(SentFrame {
+            state_id,
+            bytes,
+            sent_at_ms: now_ms,
+        });
+    }
+
+    /// Like [`Self::ack_received`], also tuning the window from the ack's timing and
+    /// the client's loss estimate.
+    pub fn ack_received_at(&mut self, state_id: u64, now_ms: u64, loss_ppm: u32) {
+        self.update_estimates(state_id, now_ms);
+        if !self.bounds.is_fixed() {
+            self.tune(now_ms, loss_ppm);
+        }
+        self.ack_received(state_id);
+    }
+
+    fn update_estimates(&mut self, state_id: u64, now_ms: u64) {
+        let mut acked_bytes = 0;
+        let mut first_sent_ms = None;
+        let mut newest_sent_ms = None;
+        while let Some(frame) = self.in_flight.front() {
+            if frame.state_id > state_id {
+                break;
+            }
+            acked_bytes += frame.bytes;
+            first_sent_ms.get_or_insert(frame.sent_at_ms);
+            newest_sent_ms = Some(frame.sent_at_ms);
+            self.in_flight.pop_front();
+        }
+        let (Some(first_sent_ms), Some(newest_sent_ms)) = (first_sent_ms, newest_sent_ms) else {
+            return;
+        };
+
+        // Acks are cumulative, so the most recently sent frame gives the tightest RTT
+        let rtt = now_ms.saturating_sub(newest_sent_ms) as f64;
+        self.srtt_ms = Some(match self.srtt_ms {
+            Some(srtt) => srtt + SRTT_ALPHA * (rtt - srtt),
+            None => rtt,
+        });
+
+        let elapsed = now_ms.saturating_sub(first_sent_ms).max(1) as f64;
+        let rate = acked_bytes as f64 / elapsed;
+        self.throughput = Some(match self.throughput {
+            Some(current) if rate < current => current + THROUGHPUT_ALPHA * (rate - current),
+            _ => rate,
+        });
+    }
+
+    fn tune(&mut self, now_ms: u64, loss_ppm: u32) {
+        if loss_ppm >= LOSS_SHRINK_PPM {
+            let srtt = self.srtt_ms.unwrap_or(0.0) as u64;
+            let recently_shrunk = self
+                .last_shrink_ms
+                .is_some_and(|last| now_ms.saturating_sub(last) < srtt);
+            if !recently_shrunk {
+                self.window_size = self.bounds.clamp(self.window_size / 2);
+                self.last_shrink_ms = Some(now_ms);
+            }
+            return;
+        }
+
+        if let Some(target) = self.bdp_target() {
+            if self.window_size < target {
+                self.window_size += 1;
+            }
+        }
+    }
+
+    /// Window needed to keep the link busy: the bandwidth-delay product in frames, plus
+    /// one frame of headroom so the window can probe for more throughput.
+    pub fn bdp_target(&self) -> Option<u32> {
+        let bdp_bytes = self.throughput? * self.srtt_ms?;
+        let avg_frame_bytes = self.avg_frame_bytes?.max(1.0);
+        let frames = (bdp_bytes / avg_frame_bytes).ceil() as u32;
+        Some(self.bounds.clamp(frames.saturating_add(1)))
+    }
+
     pub fn ack_received(&mut self, state_id: u64) {
         if state_id > self.newest_sent_state_id {
             return;
@@ -77,6 +252,35 @@ impl RenderWindow {
     pub fn reset_for_snapshot(&mut self, new_state_id: u64) {
         self.oldest_unacked_state_id = new_state_id;
         self.newest_sent_state_id = new_state_id;
+        self.in_flight.clear();
+    }
+
+    /// Like [`Self::reset_for_snapshot`], tracking the snapshot for RTT/throughput.
+    /// Snapshots don't count towards the average frame size.
+    pub fn reset_for_snapshot_at(&mut self, new_state_id: u64, bytes: usize, now_ms: u64) {
+        self.reset_for_snapshot(new_state_id);
+        self.in_flight.push_back(SentFrame {
+            state_id: new_state_id,
+            bytes,
+            sent_at_ms: now_ms,
+        });
+    }
+
+    pub fn set_bounds(&mut self, bounds: WindowBounds) {
+        self.bounds = bounds;
+        self.window_size = bounds.clamp(self.window_size);
+    }
+
+    pub fn bounds(&self) -> WindowBounds {
+        self.bounds
+    }
+
+    pub fn srtt_ms(&self) -> Option<f64> {
+        self.srtt_ms
+    }
+
+    pub fn throughput_bytes_per_ms(&self) -> Option<f64> {
+        self.throughput
     }
 
     pub fn window_size(&self) -> u32 {
@@ -0,0 +1,91 @@
+//! Coalescing terminal bells into `BellEvent`s.
+//!
+//! Mobile clients turn a bell into a haptic buzz, so a program ringing in a loop
+//! (a held-down key at a prompt, `yes $'\a'`) must not turn into a buzz storm. The
+//! first bell from a pane goes out at once; bells in the following
+//! [`BELL_COALESCE_MS`] are held back and go out as one event with their count once
+//! the window has passed.
+
+use std::collections::HashMap;
+
+use zellij_remote_protocol::{BellEvent, BellUrgency};
+
+/// Minimum gap between two `BellEvent`s for the same pane
+pub const BELL_COALESCE_MS: u64 = 1_000;
+
+#[derive(Debug)]
+struct PaneBells {
+    last_sent_ms: u64,
+    held: u32,
+    urgency: BellUrgency,
+}
+
+/// Bells per pane, session wide; every client that negotiated `supports_bell` gets
+/// the same events.
+#[derive(Debug, Default)]
+pub struct BellCoalescer {
+    panes: HashMap<u32, PaneBells>,
+}
+
+impl BellCoalescer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a bell from `pane_id`, returning the event to send right away, if any.
+    pub fn ring(&mut self, pane_id: u32, urgency: BellUrgency, now_ms: u64) -> Option<BellEvent> {
+        match self.panes.get_mut(&pane_id) {
+            Some(pane) if now_ms.saturating_sub(pane.last_sent_ms) < BELL_COALESCE_MS => {
+                pane.held += 1;
+                if (urgency as i32) > (pane.urgency as i32) || pane.held == 1 {
+                    pane.urgency = urgency;
+                }
+                None
+            },
+            _ => {
+                self.panes.insert(
+                    pane_id,
+                    PaneBells {
+                        last_sent_ms: now_ms,
+                        held: 0,
+                        urgency,
+                    },
+                );
+                Some(BellEvent {
+                    pane_id,
+                    urgency: urgency as i32,
+                    count: 1,
+                })
+            },
+        }
+    }
+
+    /// Bells held back whose window has passed, one event per pane, carrying the most
+    /// urgent of them. Call periodically.
+    pub fn flush(&mut self, now_ms: u64) -> Vec<BellEvent> {
+        let mut events = vec![];
+        self.panes.retain(|pane_id, pane| {
+            if now_ms.saturating_sub(pane.last_sent_ms) < BELL_COALESCE_MS {
+                return true;
+            }
+            if pane.held == 0 {
+                return false;
+            }
+            events.push(BellEvent {
+                pane_id: *pane_id,
+                urgency: pane.urgency as i32,
+                count: pane.held,
+            });
+            pane.last_sent_ms = now_ms;
+            pane.held = 0;
+            true
+        });
+        events.sort_by_key(|event| event.pane_id);
+        events
+    }
+
+    /// Whether any bells are held back, i.e. `flush` still has something to do
+    pub fn has_held(&self) -> bool {
+        self.panes.values().any(|pane| pane.held > 0)
+    }
+}
@@ -0,0 +1,374 @@
+//! Splitting snapshots and deltas for clients that cap their message size.
+//!
+//! A client on an extremely wide terminal (or a transport with small buffers) sets
+//! `max_message_bytes` in its capabilities. A snapshot or delta that would encode
+//! bigger than that goes out as several messages with the same `state_id`, numbered
+//! by `chunk_index` out of `chunk_count`. Rows are cut into column windows
+//! (`RowData.col_start`), patches into runs, and a wide character always stays in one
+//! piece. The client puts the chunks back together with a [`ChunkAssembler`] before
+//! applying anything, so the screen never shows half a state.
+
+use std::collections::HashMap;
+
+use prost::Message;
+use zellij_remote_protocol::{CellRun, RowData, RowPatch, ScreenDelta, ScreenSnapshot};
+
+/// Smallest `max_message_bytes` honoured; lower values are raised to it
+pub const MIN_MESSAGE_BYTES: usize = 4_096;
+/// Most a cell can add to a message: codepoint, width and style id as u32 varints
+const CELL_BYTES: usize = 15;
+/// Room kept in every chunk for the header fields, cursor and per-row framing
+const OVERHEAD_BYTES: usize = 1_024;
+
+/// How many cells fit in one chunk of at most `max_bytes`.
+pub fn cells_per_chunk(max_bytes: usize) -> usize {
+    (max_bytes.max(MIN_MESSAGE_BYTES) - OVERHEAD_BYTES) / CELL_BYTES
+}
+
+/// `snapshot` as chunks of at most `max_bytes` each, or unchanged if it fits. The
+/// styles go in the first chunk.
+pub fn chunk_snapshot(snapshot: ScreenSnapshot, max_bytes: usize) -> Vec<ScreenSnapshot> {
+    if snapshot.encoded_len() <= max_bytes {
+        return vec![snapshot];
+    }
+    let first_budget = budget_after(max_bytes, snapshot.styles.iter().map(|s| s.encoded_len()));
+    let pieces = split_cells(
+        &snapshot.rows,
+        first_budget,
+        cells_per_chunk(max_bytes),
+        |row| row.codepoints.len(),
+        |row| &row.widths,
+        |row, start, end| RowData {
+            row: row.row,
+            col_start: row.col_start + start as u32,
+            codepoints: row.codepoints[start..end].to_vec(),
+            widths: row.widths[start..end].to_vec(),
+            style_ids: row.style_ids[start..end].to_vec(),
+            line_size: row.line_size,
+        },
+    );
+    let chunk_count = pieces.len() as u32;
+    let mut header = snapshot;
+    header.rows = Vec::new();
+    let styles = std::mem::take(&mut header.styles);
+    pieces
+        .into_iter()
+        .enumerate()
+        .map(|(index, rows)| ScreenSnapshot {
+            styles: if index == 0 {
+                styles.clone()
+            } else {
+                Vec::new()
+            },
+            rows,
+            chunk_index: index as u32,
+            chunk_count,
+            ..header.clone()
+        })
+        .collect()
+}
+
+/// `delta` as chunks of at most `max_bytes` each, or unchanged if it fits. The added
+/// styles go in the first chunk, a row's checksum with its last piece.
+pub fn chunk_delta(delta: ScreenDelta, max_bytes: usize) -> Vec<ScreenDelta> {
+    if delta.encoded_len() <= max_bytes {
+        return vec![delta];
+    }
+    let first_budget = budget_after(
+        max_bytes,
+        delta.styles_added.iter().map(|s| s.encoded_len()),
+    );
+    let budget = cells_per_chunk(max_bytes);
+    // Runs are split like rows, then gathered back into patches per chunk
+    let runs: Vec<(usize, CellRun)> = delta
+        .row_patches
+        .iter()
+        .enumerate()
+        .flat_map(|(patch, row_patch)| row_patch.runs.iter().map(move |run| (patch, run.clone())))
+        .collect();
+    let pieces = split_cells(
+        &runs,
+        first_budget,
+        budget,
+        |(_, run)| run.codepoints.len(),
+        |(_, run)| &run.widths,
+        |(patch, run), start, end| {
+            (
+                *patch,
+                CellRun {
+                    col_start: run.col_start + start as u32,
+                    codepoints: run.codepoints[start..end].to_vec(),
+                    widths: run.widths[start..end].to_vec(),
+                    style_ids: run.style_ids[start..end].to_vec(),
+                },
+            )
+        },
+    );
+    let mut last_piece = HashMap::new();
+    for (index, piece) in pieces.iter().enumerate() {
+        for (patch, _) in piece {
+            last_piece.insert(*patch, index);
+        }
+    }
+    // Patches without runs (a line size change) ride along with the last chunk
+    let chunk_count = pieces.len() as u32;
+    let mut header = delta;
+    let row_patches = std::mem::take(&mut header.row_patches);
+    let styles_added = std::mem::take(&mut header.styles_added);
+    pieces
+        .into_iter()
+        .enumerate()
+        .map(|(index, piece)| {
+            let mut patches: Vec<RowPatch> = Vec::new();
+            let mut patch_indexes: Vec<usize> = Vec::new();
+            for (patch, run) in piece {
+                if patch_indexes.last() != Some(&patch) {
+                    let source = &row_patches[patch];
+                    patches.push(RowPatch {
+                        runs: Vec::new(),
+                        row_crc32: if last_piece[&patch] == index {
+                            source.row_crc32
+                        } else {
+                            0
+                        },
+                        ..source.clone()
+                    });
+                    patch_indexes.push(patch);
+                }
+                if let Some(patch) = patches.last_mut() {
+                    patch.runs.push(run);
+                }
+            }
+            if index + 1 == chunk_count as usize {
+                patches.extend(
+                    row_patches
+                        .iter()
+                        .filter(|patch| patch.runs.is_empty())
+                        .cloned(),
+                );
+            }
+            ScreenDelta {
+                styles_added: if index == 0 {
+                    styles_added.clone()
+                } else {
+                    Vec::new()
+                },
+                row_patches: patches,
+                chunk_index: index as u32,
+                chunk_count,
+                ..header.clone()
+            }
+        })
+        .collect()
+}
+
+/// Cells left for the first chunk once `styles` are in it. Never below two, so a
+/// wide character always fits and the split can't stall.
+fn budget_after(max_bytes: usize, styles: impl Iterator<Item = usize>) -> usize {
+    let style_bytes: usize = styles.map(|len| len + 2).sum();
+    cells_per_chunk(max_bytes)
+        .saturating_sub(style_bytes / CELL_BYTES)
+        .max(2)
+}
+
+/// `items` cut into chunks of at most `budget` cells (`first_budget` for the first),
+/// splitting an item with `slice` where it doesn't fit whole. A window never ends
+/// between a wide character and its continuation cell.
+fn split_cells<T, P>(
+    items: &[T],
+    first_budget: usize,
+    budget: usize,
+    cell_count: impl Fn(&T) -> usize,
+    widths_of: impl Fn(&T) -> &Vec<u32>,
+    slice: impl Fn(&T, usize, usize) -> P,
+) -> Vec<Vec<P>> {
+    let mut chunks = vec![Vec::new()];
+    let mut left = first_budget;
+    for item in items {
+        let len = cell_count(item);
+        let widths = widths_of(item);
+        let mut start = 0;
+        loop {
+            let mut end = (start + left).min(len);
+            if end < len && end > start && widths.get(end - 1).is_some_and(|&w| w > 1) {
+                end -= 1;
+            }
+            if end == start && start < len {
+                chunks.push(Vec::new());
+                left = budget;
+                continue;
+            }
+            chunks.last_mut().unwrap().push(slice(item, start, end));
+            left -= end - start;
+            start = end;
+            if start >= len {
+                break;
+            }
+        }
+    }
+    chunks
+}
+
+/// Whether a message is one chunk of several, or `None` if its numbering is broken.
+fn is_chunked(chunk_index: u32, chunk_count: u32) -> Option<bool> {
+    match chunk_count {
+        0 | 1 => Some(false),
+        _ if chunk_index < chunk_count => Some(true),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+struct Partial<T> {
+    state_id: u64,
+    chunks: Vec<Option<T>>,
+}
+
+impl<T> Partial<T> {
+    fn new(state_id: u64, chunk_count: u32) -> Self {
+        Self {
+            state_id,
+            chunks: (0..chunk_count).map(|_| None).collect(),
+        }
+    }
+
+    /// Store `chunk`; the chunks in order once every one has arrived.
+    fn insert(&mut self, index: u32, chunk: T) -> Option<Vec<T>> {
+        self.chunks[index as usize] = Some(chunk);
+        if self.chunks.iter().all(Option::is_some) {
+            Some(self.chunks.drain(..).flatten().collect())
+        } else {
+            None
+        }
+    }
+}
+
+/// Chunks in, whole snapshots and deltas out. Unchunked messages pass straight
+/// through. Chunks may arrive in any order (pushed snapshots each have a stream of
+/// their own); a set still missing chunks is dropped once a newer state starts
+/// arriving, and chunks for a state no newer than one already put together are
+/// ignored.
+#[derive(Debug, Default)]
+pub struct ChunkAssembler {
+    snapshot: Option<Partial<ScreenSnapshot>>,
+    delta: Option<Partial<ScreenDelta>>,
+    assembled_state_id: u64,
+}
+
+impl ChunkAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_snapshot(&mut self, chunk: ScreenSnapshot) -> Option<ScreenSnapshot> {
+        let chunked = is_chunked(chunk.chunk_index, chunk.chunk_count)?;
+        if !chunked {
+            return Some(chunk);
+        }
+        if chunk.state_id <= self.assembled_state_id {
+            return None;
+        }
+        let (index, count) = (chunk.chunk_index, chunk.chunk_count);
+        let partial = gathering(&mut self.snapshot, chunk.state_id, count)?;
+        let chunks = partial.insert(index, chunk)?;
+        self.snapshot = None;
+        let whole = merge_snapshot(chunks);
+        self.assembled_state_id = whole.state_id;
+        Some(whole)
+    }
+
+    pub fn push_delta(&mut self, chunk: ScreenDelta) -> Option<ScreenDelta> {
+        let chunked = is_chunked(chunk.chunk_index, chunk.chunk_count)?;
+        if !chunked {
+            return Some(chunk);
+        }
+        if chunk.state_id <= self.assembled_state_id {
+            return None;
+        }
+        let (index, count) = (chunk.chunk_index, chunk.chunk_count);
+        let partial = gathering(&mut self.delta, chunk.state_id, count)?;
+        let chunks = partial.insert(index, chunk)?;
+        self.delta = None;
+        let whole = merge_delta(chunks);
+        self.assembled_state_id = whole.state_id;
+        Some(whole)
+    }
+
+    /// Whether some chunks are held waiting for the rest of their set
+    pub fn is_gathering(&self) -> bool {
+        self.snapshot.is_some() || self.delta.is_some()
+    }
+}
+
+/// The set `state_id` belongs to, started afresh if it is newer than the one held.
+fn gathering<T>(
+    slot: &mut Option<Partial<T>>,
+    state_id: u64,
+    chunk_count: u32,
+) -> Option<&mut Partial<T>> {
+    match slot {
+        Some(partial) if partial.state_id > state_id => return None,
+        Some(partial) if partial.state_id == state_id => {
+            if partial.chunks.len() != chunk_count as usize {
+                log::debug!("Chunk count changed within state {}", state_id);
+                return None;
+            }
+        },
+        _ => *slot = Some(Partial::new(state_id, chunk_count)),
+    }
+    slot.as_mut()
+}
+
+fn merge_snapshot(chunks: Vec<ScreenSnapshot>) -> ScreenSnapshot {
+    let mut chunks = chunks.into_iter();
+    let mut snapshot = chunks.next().unwrap_or_default();
+    let mut pieces = std::mem::take(&mut snapshot.rows);
+    for chunk in chunks {
+        pieces.extend(chunk.rows);
+    }
+    pieces.sort_by_key(|piece| (piece.row, piece.col_start));
+    for piece in pieces {
+        match snapshot.rows.last_mut() {
+            Some(row) if row.row == piece.row => {
+                row.codepoints.extend(piece.codepoints);
+                row.widths.extend(piece.widths);
+                row.style_ids.extend(piece.style_ids);
+            },
+            _ => snapshot.rows.push(RowData {
+                col_start: 0,
+                ..piece
+            }),
+        }
+    }
+    snapshot.chunk_index = 0;
+    snapshot.chunk_count = 0;
+    snapshot
+}
+
+fn merge_delta(chunks: Vec<ScreenDelta>) -> ScreenDelta {
+    let mut chunks = chunks.into_iter();
+    let mut delta = chunks.next().unwrap_or_default();
+    let pieces: Vec<RowPatch> = std::mem::take(&mut delta.row_patches)
+        .into_iter()
+        .chain(chunks.flat_map(|chunk| chunk.row_patches))
+        .collect();
+    let mut rows: HashMap<u32, usize> = HashMap::new();
+    for piece in pieces {
+        match rows.get(&piece.row) {
+            Some(&at) => {
+                let patch = &mut delta.row_patches[at];
+                patch.runs.extend(piece.runs);
+                if piece.row_crc32 != 0 {
+                    patch.row_crc32 = piece.row_crc32;
+                }
+            },
+            None => {
+                rows.insert(piece.row, delta.row_patches.len());
+                delta.row_patches.push(piece);
+            },
+        }
+    }
+    delta.chunk_index = 0;
+    delta.chunk_count = 0;
+    delta
+}
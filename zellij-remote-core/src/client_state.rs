@@ -1,39 +1,178 @@
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
+use std::ops::Range;
+use std::time::Instant;
+
+use prost::Message;
 
 use crate::backpressure::RenderWindow;
+use crate::clock::{SharedClock, SystemClock};
+use crate::color_transform::transform_style_defs;
+use crate::debug_info::{self, DEBUG_FRAME_INFO_AVAILABLE};
 use crate::delta::DeltaEngine;
-use crate::frame::FrameData;
+use crate::flash_guard::{FlashGuard, HeldRows};
+use crate::frame::{FrameData, FrameFingerprint, LineSize};
+use crate::frame_hash::{hash_frame, stamp_row_checksums};
+use crate::session::RenderUpdate;
+use crate::style_convert::downgrade_underline;
 use crate::style_table::StyleTable;
-use zellij_remote_protocol::{ScreenDelta, ScreenSnapshot, StateAck};
+use crate::text_mode::{
+    strip_delta_styles, strip_snapshot_styles, TextModeChange, TextModeController,
+};
+use zellij_remote_protocol::{
+    ColorTransform, DebugFrameInfo, DisplaySize, FlashGuardSettings, ScreenDelta, ScreenSnapshot,
+    SizeChanged, StateAck, StyleDef,
+};
+
+/// Deltas smaller than this always go out as deltas: a snapshot couldn't save much,
+/// and it costs the client its baseline and render window.
+pub const DELTA_FALLBACK_MIN_BYTES: usize = 1024;
 
+/// Per-client render state. Baselines are kept as [`FrameFingerprint`]s rather than
+/// frames, so each client costs O(rows) however large the screen; the cells needed
+/// for sparse deltas come from the session's shared state history.
 #[derive(Debug)]
 pub struct ClientRenderState {
     render_window: RenderWindow,
-    acked_baseline: Option<FrameData>,
+    acked_baseline: Option<FrameFingerprint>,
     acked_baseline_state_id: u64,
-    pending_frame: Option<FrameData>,
+    pending_frame: Option<FrameFingerprint>,
     pending_state_id: u64,
+    /// Sequence number of the last snapshot or delta prepared for this client
+    frame_sequence: u64,
+    /// Time for the render window's send/ack timing, pacing and `server_time_ms`
+    clock: SharedClock,
+    /// Monotonic origin for the render window's send/ack timing
+    started: Instant,
+    text_mode: TextModeController,
+    /// Integrity mode: put `frame_hash` on every snapshot and delta
+    frame_hash_enabled: bool,
+    /// Snapshots the client asked for because its screen didn't match `frame_hash`
+    frame_hash_mismatches: u64,
+    /// Put `row_crc32` on every row patch
+    row_checksums_enabled: bool,
+    /// Snapshots the client asked for after a patched row didn't match `row_crc32`
+    row_checksum_mismatches: u64,
+    /// Snapshots may extend the client's style table instead of resetting it
+    style_retention_enabled: bool,
+    /// Send underline styles and colors as they are; otherwise styles go out with a
+    /// single, uncolored underline
+    styled_underlines: bool,
+    /// Send DEC line sizes (`supports_line_size`); otherwise every row goes out single
+    line_sizes: bool,
+    /// Recoloring the client asked for, applied to every style it's sent
+    color_transform: ColorTransform,
+    /// Style ids below this are known to be in the client's style table (0: unknown)
+    styles_acked: usize,
+    /// Style table size the client will hold once it applies each frame sent since the
+    /// last ack, by state id, oldest first
+    sent_styles: VecDeque<(u64, usize)>,
+    /// Screen size (cols, rows) of the last frame prepared for this client
+    sent_size: Option<(usize, usize)>,
+    /// Size the client had before a resize that no snapshot has reported yet
+    resized_from: Option<(usize, usize)>,
+    /// False while the client reports it is backgrounded; no frames are prepared
+    visible: bool,
+    /// Rows the client subscribed to; frames carry no others (None: the whole screen)
+    region: Option<Range<usize>>,
+    /// Frame rate cap: the least time between two frames (0: uncapped)
+    min_frame_interval_ms: u64,
+    /// When the last snapshot or delta was prepared
+    last_frame_ms: Option<u64>,
+    /// Attach `DebugFrameInfo` to every snapshot and delta
+    debug_frame_info: bool,
+    /// Deltas that encode larger than this go out as snapshots (None: no cap)
+    max_delta_bytes: Option<usize>,
+    /// Deltas replaced by a snapshot because they were too large
+    delta_fallbacks: u64,
+    /// The next delta diffs every row: its baseline is a shared snapshot, older than
+    /// the dirty rows the session tracks
+    full_diff_due: bool,
+    flash_guard: FlashGuard,
+    /// Rows the frame being prepared holds back, set just before preparing it
+    frame_held: HeldRows,
+    /// Rows the pending frame held back, with the content the client kept for them
+    pending_held: HeldRows,
+    /// Rows the acked baseline held back; the state history's frame differs there
+    baseline_held: HeldRows,
 }
 
 impl ClientRenderState {
     pub fn new(window_size: u32) -> Self {
+        Self::with_clock(window_size, SystemClock::shared())
+    }
+
+    pub fn with_clock(window_size: u32, clock: SharedClock) -> Self {
         Self {
             render_window: RenderWindow::new(window_size),
             acked_baseline: None,
             acked_baseline_state_id: 0,
             pending_frame: None,
             pending_state_id: 0,
+            frame_sequence: 0,
+            started: clock.now(),
+            clock,
+            text_mode: TextModeController::default(),
+            frame_hash_enabled: false,
+            frame_hash_mismatches: 0,
+            row_checksums_enabled: false,
+            row_checksum_mismatches: 0,
+            style_retention_enabled: false,
+            styled_underlines: false,
+            line_sizes: false,
+            color_transform: ColorTransform::default(),
+            styles_acked: 0,
+            sent_styles: VecDeque::new(),
+            sent_size: None,
+            resized_from: None,
+            visible: true,
+            region: None,
+            min_frame_interval_ms: 0,
+            last_frame_ms: None,
+            debug_frame_info: false,
+            max_delta_bytes: None,
+            delta_fallbacks: 0,
+            full_diff_due: false,
+            flash_guard: FlashGuard::default(),
+            frame_held: HeldRows::new(),
+            pending_held: HeldRows::new(),
+            baseline_held: HeldRows::new(),
         }
     }
 
     pub fn process_state_ack(&mut self, ack: &StateAck) {
-        self.render_window.ack_received(ack.last_applied_state_id);
+        let now_ms = self.elapsed_ms();
+        self.render_window.ack_received_at(
+            ack.last_applied_state_id,
+            now_ms,
+            ack.estimated_loss_ppm,
+        );
+        self.ack_styles(ack.last_applied_state_id);
     }
 
-    pub fn advance_baseline(&mut self, acked_state_id: u64, acked_frame: FrameData) {
+    /// Move the style watermark to what the client held once it applied
+    /// `acked_state_id`, which needn't be the newest frame sent
+    fn ack_styles(&mut self, acked_state_id: u64) {
+        while let Some(&(state_id, style_count)) = self.sent_styles.front() {
+            if state_id > acked_state_id {
+                break;
+            }
+            self.styles_acked = style_count;
+            self.sent_styles.pop_front();
+        }
+    }
+
+    pub fn advance_baseline(&mut self, acked_state_id: u64, acked_frame: FrameFingerprint) {
         if acked_state_id >= self.acked_baseline_state_id || self.acked_baseline.is_none() {
             self.acked_baseline = Some(acked_frame);
             self.acked_baseline_state_id = acked_state_id;
+            self.baseline_held = if acked_state_id >= self.pending_state_id {
+                self.pending_held.clone()
+            } else {
+                HeldRows::new()
+            };
+        }
+        if self.sent_size.is_none() {
+            self.sent_size = Some((acked_frame.cols, acked_frame.rows.len()));
         }
     }
 
@@ -45,33 +184,256 @@ impl ClientRenderState {
         self.render_window.can_send()
     }
 
+    /// Check the link for text mode as a frame becomes ready. Leaving text mode drops
+    /// the baseline so the next frame is a snapshot that brings the styles back.
+    pub fn observe_link(&mut self) -> Option<TextModeChange> {
+        let now_ms = self.elapsed_ms();
+        let change = self.text_mode.observe(now_ms, &self.render_window);
+        if change == Some(TextModeChange::Exited) {
+            self.reset_baseline();
+        }
+        change
+    }
+
+    /// Check the session's size against what the client was last sent. Deltas can't
+    /// change the screen size, so a resize drops the baseline and the snapshot that
+    /// follows carries a `SizeChanged`. Returns true if the size changed.
+    pub fn observe_size(&mut self, cols: usize, rows: usize) -> bool {
+        match self.sent_size {
+            Some(sent) if sent != (cols, rows) => {
+                self.resized_from.get_or_insert(sent);
+                self.reset_baseline();
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// False while text mode or the frame rate cap holds frames back to coalesce them.
+    pub fn frame_due(&self) -> bool {
+        let now_ms = self.elapsed_ms();
+        self.text_mode.frame_due(now_ms)
+            && self
+                .last_frame_ms
+                .is_none_or(|last| now_ms.saturating_sub(last) >= self.min_frame_interval_ms)
+    }
+
+    /// Cap the client's frame rate; 0 lifts the cap. Returns whether it changed.
+    pub fn set_min_frame_interval(&mut self, interval_ms: u64) -> bool {
+        let changed = self.min_frame_interval_ms != interval_ms;
+        self.min_frame_interval_ms = interval_ms;
+        changed
+    }
+
+    /// Whether frames can be held back to coalesce them, and so need flushing once due.
+    /// Rows the flash guard holds need the same.
+    pub fn is_paced(&self) -> bool {
+        self.text_mode.is_active()
+            || self.min_frame_interval_ms > 0
+            || self.flash_guard.is_holding()
+    }
+
+    /// Apply the flash guard settings from the client's `ClientHello`
+    pub fn set_flash_guard(&mut self, settings: &FlashGuardSettings) {
+        self.flash_guard.configure(settings);
+    }
+
+    pub fn flash_guard(&self) -> &FlashGuard {
+        &self.flash_guard
+    }
+
+    /// Hold back the rows of a frame about to be prepared that keep flashing (see
+    /// [`FlashGuard::hold`]). Pass the rows returned to [`Self::set_frame_held`] before
+    /// preparing the frame.
+    pub fn hold_flashing_rows(
+        &mut self,
+        frame: &mut FrameData,
+        fingerprint: &mut FrameFingerprint,
+    ) -> HeldRows {
+        let now_ms = self.elapsed_ms();
+        self.flash_guard.hold(frame, fingerprint, now_ms)
+    }
+
+    /// Whether a held row is due to change on the client though the screen hasn't
+    pub fn flash_release_due(&self) -> bool {
+        self.flash_guard.release_due(self.elapsed_ms())
+    }
+
+    /// The rows held back in the frame about to be prepared; taken by the next snapshot
+    /// or delta
+    pub fn set_frame_held(&mut self, held: HeldRows) {
+        self.frame_held = held;
+    }
+
+    /// Rows where the client's baseline is content the flash guard held, rather than
+    /// the state history's
+    pub fn baseline_held(&self) -> &HeldRows {
+        &self.baseline_held
+    }
+
+    pub fn text_mode(&self) -> &TextModeController {
+        &self.text_mode
+    }
+
+    pub fn text_mode_mut(&mut self) -> &mut TextModeController {
+        &mut self.text_mode
+    }
+
+    /// `baseline_frame` is the frame at [`Self::baseline_state_id`], if the caller still
+    /// has it; without it changed rows go out whole instead of as sparse runs. The
+    /// update is a snapshot instead when the delta turns out too large (see
+    /// [`Self::finish_update`]).
     pub fn prepare_delta(
         &mut self,
         current_frame: &FrameData,
+        current_fingerprint: &FrameFingerprint,
         current_state_id: u64,
         style_table: &mut StyleTable,
         dirty_rows: Option<&HashSet<usize>>,
-    ) -> Option<ScreenDelta> {
+        baseline_frame: Option<&FrameData>,
+    ) -> Option<RenderUpdate> {
         let baseline = self.acked_baseline.as_ref()?;
 
         if !self.render_window.can_send() {
             return None;
         }
 
-        let delta = DeltaEngine::compute_delta(
+        let started = Instant::now();
+        let mut delta = DeltaEngine::compute_delta_incremental(
             baseline,
+            baseline_frame,
             current_frame,
+            current_fingerprint,
             style_table,
             self.acked_baseline_state_id,
             current_state_id,
             dirty_rows,
         );
+        if self.frame_hash_due() {
+            delta.frame_hash = hash_frame(current_frame);
+        }
+        if self.row_checksums_due() {
+            stamp_row_checksums(&mut delta, current_frame);
+        }
+        if self.debug_frame_info {
+            let rows_diffed = debug_info::rows_diffed(dirty_rows, current_frame.rows.len());
+            delta.debug_info = Some(debug_info::for_delta(
+                &delta,
+                rows_diffed,
+                started.elapsed(),
+            ));
+        }
+        Some(self.finish_update(delta, current_frame, current_fingerprint, style_table))
+    }
+
+    /// Send `delta`, diffed up to `current_frame`, through [`Self::finish_delta`], or
+    /// a snapshot of `current_frame` in its place when the delta encodes larger than
+    /// [`Self::set_max_delta_bytes`] allows, or rewrites most rows and would be larger
+    /// than the snapshot. Replacements are counted in [`Self::delta_fallbacks`].
+    pub fn finish_update(
+        &mut self,
+        mut delta: ScreenDelta,
+        current_frame: &FrameData,
+        current_fingerprint: &FrameFingerprint,
+        style_table: &mut StyleTable,
+    ) -> RenderUpdate {
+        // Before measuring, so styles count towards the delta's size
+        self.add_delta_styles(&mut delta, style_table);
+        if self.delta_too_large(&delta, current_frame, style_table) {
+            self.delta_fallbacks += 1;
+            let mut snapshot = self.prepare_snapshot(current_frame, delta.state_id, style_table);
+            snapshot.delivered_input_watermark = delta.delivered_input_watermark;
+            return RenderUpdate::Snapshot(snapshot);
+        }
+        let size = (current_frame.cols, current_frame.rows.len());
+        RenderUpdate::Delta(self.finish_delta(delta, current_fingerprint, size, style_table))
+    }
+
+    fn delta_too_large(
+        &self,
+        delta: &ScreenDelta,
+        current_frame: &FrameData,
+        style_table: &mut StyleTable,
+    ) -> bool {
+        let encoded_len = delta.encoded_len();
+        if self.max_delta_bytes.is_some_and(|max| encoded_len > max) {
+            return true;
+        }
+        // Only a delta patching most rows can outgrow a snapshot, so the snapshot is
+        // built just for those
+        if encoded_len < DELTA_FALLBACK_MIN_BYTES
+            || delta.row_patches.len() * 2 <= current_frame.rows.len()
+        {
+            return false;
+        }
+        let snapshot = DeltaEngine::compute_snapshot(current_frame, style_table, delta.state_id);
+        encoded_len > snapshot.encoded_len()
+    }
+
+    /// Put every style past the watermark in the delta's `styles_added`, in the
+    /// client's form. Styles are interned before the delta is computed, so a lost
+    /// delta's styles ride along with the next one.
+    fn add_delta_styles(&self, delta: &mut ScreenDelta, style_table: &StyleTable) {
+        if self.text_mode.is_active() {
+            return;
+        }
+        delta.styles_added = style_defs_since(style_table, self.styles_acked);
+        if !self.styled_underlines {
+            downgrade_style_defs(&mut delta.styles_added);
+        }
+        transform_style_defs(&self.color_transform, &mut delta.styles_added);
+    }
+
+    /// The bookkeeping half of [`Self::prepare_delta`], for a delta whose rows were
+    /// diffed (and hashed, if [`Self::frame_hash_due`]) elsewhere and whose styles were
+    /// added: stamps the frame sequence and time, and records it as sent. `size` is
+    /// the frame's (cols, rows).
+    fn finish_delta(
+        &mut self,
+        mut delta: ScreenDelta,
+        current_fingerprint: &FrameFingerprint,
+        size: (usize, usize),
+        style_table: &StyleTable,
+    ) -> ScreenDelta {
+        delta.frame_sequence = self.next_frame_sequence();
+        delta.server_time_ms = self.clock.unix_ms();
+        if let Some(region) = &self.region {
+            delta
+                .row_patches
+                .retain(|patch| region.contains(&(patch.row as usize)));
+        }
+        if self.text_mode.is_active() {
+            strip_delta_styles(&mut delta);
+            delta.frame_hash = 0;
+            for patch in delta.row_patches.iter_mut() {
+                patch.row_crc32 = 0;
+            }
+            self.sent_styles
+                .push_back((delta.state_id, self.styles_acked));
+        } else {
+            self.sent_styles
+                .push_back((delta.state_id, style_table.current_count()));
+        }
 
-        self.render_window.mark_sent(current_state_id);
-        self.pending_frame = Some(current_frame.clone());
+        let current_state_id = delta.state_id;
+        let debug_info = delta.debug_info.take();
+        let encoded_len = delta.encoded_len();
+        let now_ms = self.elapsed_ms();
+        self.text_mode.record_frame(now_ms);
+        self.last_frame_ms = Some(now_ms);
+        self.render_window
+            .mark_sent_at(current_state_id, encoded_len, now_ms);
+        self.pending_frame = Some(current_fingerprint.clone());
         self.pending_state_id = current_state_id;
+        self.pending_held = std::mem::take(&mut self.frame_held);
+        self.sent_size = Some(size);
+        self.full_diff_due = false;
 
-        Some(delta)
+        delta.debug_info = debug_info.map(|info| DebugFrameInfo {
+            encoded_bytes: encoded_len as u32,
+            ..info
+        });
+        delta
     }
 
     pub fn prepare_snapshot(
@@ -80,18 +442,284 @@ impl ClientRenderState {
         current_state_id: u64,
         style_table: &mut StyleTable,
     ) -> ScreenSnapshot {
-        let snapshot = DeltaEngine::compute_snapshot(current_frame, style_table, current_state_id);
+        let started = Instant::now();
+        let mut snapshot =
+            DeltaEngine::compute_snapshot(current_frame, style_table, current_state_id);
+        snapshot.frame_sequence = self.next_frame_sequence();
+        snapshot.server_time_ms = self.clock.unix_ms();
+        let new_size = (current_frame.cols, current_frame.rows.len());
+        if let Some(old_size) = self.resized_from.take().filter(|old| *old != new_size) {
+            snapshot.size_changed = Some(size_changed(old_size, new_size));
+        }
+        self.sent_size = Some(new_size);
+        if let Some(region) = &self.region {
+            snapshot
+                .rows
+                .retain(|row| region.contains(&(row.row as usize)));
+        }
+        if self.text_mode.is_active() {
+            strip_snapshot_styles(&mut snapshot);
+            // Whether or not it arrives, the client may be left with the default style only
+            self.styles_acked = 0;
+            self.sent_styles.clear();
+            self.sent_styles.push_back((current_state_id, 0));
+        } else {
+            if self.style_retention_enabled && self.styles_acked > 0 {
+                let styles_acked = self.styles_acked;
+                snapshot.style_table_reset = false;
+                snapshot
+                    .styles
+                    .retain(|def| def.style_id as usize >= styles_acked);
+            }
+            if !self.styled_underlines {
+                downgrade_style_defs(&mut snapshot.styles);
+            }
+            transform_style_defs(&self.color_transform, &mut snapshot.styles);
+            // Frames sent before it are no use to the watermark once it resets the table
+            self.sent_styles.clear();
+            self.sent_styles
+                .push_back((current_state_id, style_table.current_count()));
+            if self.frame_hash_enabled && self.region.is_none() {
+                snapshot.frame_hash = hash_frame(current_frame);
+            }
+        }
 
-        self.render_window.reset_for_snapshot(current_state_id);
-        self.acked_baseline = Some(current_frame.clone());
+        let encoded_len = snapshot.encoded_len();
+        let now_ms = self.elapsed_ms();
+        self.text_mode.record_frame(now_ms);
+        self.last_frame_ms = Some(now_ms);
+        self.render_window
+            .reset_for_snapshot_at(current_state_id, encoded_len, now_ms);
+        let fingerprint = FrameFingerprint::of(current_frame);
+        self.acked_baseline = Some(fingerprint.clone());
         self.acked_baseline_state_id = current_state_id;
-        self.pending_frame = Some(current_frame.clone());
+        self.pending_frame = Some(fingerprint);
         self.pending_state_id = current_state_id;
+        self.pending_held = std::mem::take(&mut self.frame_held);
+        self.baseline_held = self.pending_held.clone();
+        self.full_diff_due = false;
 
+        if self.debug_frame_info {
+            snapshot.debug_info = Some(debug_info::for_snapshot(
+                &snapshot,
+                encoded_len,
+                started.elapsed(),
+            ));
+        }
         snapshot
     }
 
-    pub fn pending_frame(&self) -> Option<&FrameData> {
+    /// Whether a snapshot built for no client in particular (a preloaded one, see
+    /// `RemoteSession::preload_snapshot`) can be this client's first frame: it has
+    /// been sent nothing yet, and wants the whole screen with its styles.
+    pub fn can_take_shared_snapshot(&self) -> bool {
+        self.frame_sequence == 0 && self.region.is_none() && !self.text_mode.is_active()
+    }
+
+    /// Record a shared snapshot of `fingerprint` at `state_id`, holding the first
+    /// `style_count` styles, as sent. Unless the client takes styles as they are, its
+    /// style table is left untrusted, so the next delta redefines every style in the
+    /// client's own form.
+    pub fn record_shared_snapshot(
+        &mut self,
+        state_id: u64,
+        fingerprint: &FrameFingerprint,
+        style_count: usize,
+        encoded_len: usize,
+    ) {
+        self.next_frame_sequence();
+        self.sent_size = Some((fingerprint.cols, fingerprint.rows.len()));
+        self.resized_from = None;
+        let verbatim_styles =
+            self.styled_underlines && self.color_transform == ColorTransform::default();
+        self.styles_acked = 0;
+        self.sent_styles.clear();
+        self.sent_styles
+            .push_back((state_id, if verbatim_styles { style_count } else { 0 }));
+
+        let now_ms = self.elapsed_ms();
+        self.text_mode.record_frame(now_ms);
+        self.last_frame_ms = Some(now_ms);
+        self.render_window
+            .reset_for_snapshot_at(state_id, encoded_len, now_ms);
+        self.acked_baseline = Some(fingerprint.clone());
+        self.acked_baseline_state_id = state_id;
+        self.pending_frame = Some(fingerprint.clone());
+        self.pending_state_id = state_id;
+        self.frame_held.clear();
+        self.pending_held.clear();
+        self.baseline_held.clear();
+        self.full_diff_due = true;
+    }
+
+    /// Whether the next delta must diff every row rather than just the dirty ones
+    pub fn full_diff_due(&self) -> bool {
+        self.full_diff_due
+    }
+
+    /// Send deltas that would encode larger than `max_bytes` as snapshots; None only
+    /// replaces deltas larger than the snapshot itself
+    pub fn set_max_delta_bytes(&mut self, max_bytes: Option<usize>) {
+        self.max_delta_bytes = max_bytes;
+    }
+
+    pub fn delta_fallbacks(&self) -> u64 {
+        self.delta_fallbacks
+    }
+
+    pub fn set_frame_hash_enabled(&mut self, enabled: bool) {
+        self.frame_hash_enabled = enabled;
+    }
+
+    pub fn frame_hash_enabled(&self) -> bool {
+        self.frame_hash_enabled
+    }
+
+    /// Count a snapshot request blamed on a `frame_hash` mismatch; returns the new total.
+    pub fn record_frame_hash_mismatch(&mut self) -> u64 {
+        self.frame_hash_mismatches += 1;
+        self.frame_hash_mismatches
+    }
+
+    pub fn frame_hash_mismatches(&self) -> u64 {
+        self.frame_hash_mismatches
+    }
+
+    pub fn set_row_checksums_enabled(&mut self, enabled: bool) {
+        self.row_checksums_enabled = enabled;
+    }
+
+    pub fn row_checksums_enabled(&self) -> bool {
+        self.row_checksums_enabled
+    }
+
+    /// Ignored unless this build has the `debug-frame-info` feature
+    pub fn set_debug_frame_info_enabled(&mut self, enabled: bool) {
+        self.debug_frame_info = enabled && DEBUG_FRAME_INFO_AVAILABLE;
+    }
+
+    pub fn debug_frame_info_enabled(&self) -> bool {
+        self.debug_frame_info
+    }
+
+    /// Count a snapshot request blamed on a `row_crc32` mismatch; returns the new total.
+    pub fn record_row_checksum_mismatch(&mut self) -> u64 {
+        self.row_checksum_mismatches += 1;
+        self.row_checksum_mismatches
+    }
+
+    pub fn row_checksum_mismatches(&self) -> u64 {
+        self.row_checksum_mismatches
+    }
+
+    pub fn set_style_retention_enabled(&mut self, enabled: bool) {
+        self.style_retention_enabled = enabled;
+    }
+
+    pub fn style_retention_enabled(&self) -> bool {
+        self.style_retention_enabled
+    }
+
+    pub fn set_styled_underlines(&mut self, enabled: bool) {
+        self.styled_underlines = enabled;
+    }
+
+    pub fn styled_underlines(&self) -> bool {
+        self.styled_underlines
+    }
+
+    /// Rows whose line size the client has differ from the new setting, so the next
+    /// delta goes over every row.
+    pub fn set_line_sizes(&mut self, enabled: bool) {
+        if enabled != self.line_sizes {
+            self.line_sizes = enabled;
+            self.full_diff_due = true;
+        }
+    }
+
+    pub fn line_sizes(&self) -> bool {
+        self.line_sizes
+    }
+
+    /// Make every row of a frame about to be prepared for a client without line sizes
+    /// single, in `frame` and `fingerprint`, so deltas, fingerprints and hashes
+    /// describe what the client shows and rows whose line size alone changed aren't
+    /// patched.
+    pub fn flatten_line_sizes(&self, frame: &mut FrameData, fingerprint: &mut FrameFingerprint) {
+        if self.line_sizes {
+            return;
+        }
+        for row in frame.rows.iter_mut() {
+            row.set_line_size(LineSize::Single);
+        }
+        for row in fingerprint.rows.iter_mut() {
+            row.line_size = LineSize::Single;
+        }
+    }
+
+    /// A different transform recolors styles the client already holds, so its next
+    /// snapshot resets its style table.
+    pub fn set_color_transform(&mut self, transform: ColorTransform) {
+        if transform != self.color_transform {
+            self.color_transform = transform;
+            self.forget_styles();
+        }
+    }
+
+    pub fn color_transform(&self) -> &ColorTransform {
+        &self.color_transform
+    }
+
+    /// Stop trusting the client's style table, so the next snapshot resets it.
+    pub fn forget_styles(&mut self) {
+        self.styles_acked = 0;
+        self.sent_styles.clear();
+    }
+
+    /// Returns whether the visibility changed.
+    pub fn set_visible(&mut self, visible: bool) -> bool {
+        let changed = self.visible != visible;
+        self.visible = visible;
+        changed
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Send only the rows in `region`, or the whole screen for None. Rows newly in
+    /// range were never sent, so a change makes the next frame a snapshot. Returns
+    /// whether the region changed.
+    pub fn set_region(&mut self, region: Option<Range<usize>>) -> bool {
+        if self.region == region {
+            return false;
+        }
+        self.region = region;
+        self.reset_baseline();
+        true
+    }
+
+    pub fn region(&self) -> Option<&Range<usize>> {
+        self.region.as_ref()
+    }
+
+    pub fn frame_sequence(&self) -> u64 {
+        self.frame_sequence
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.clock
+            .now()
+            .saturating_duration_since(self.started)
+            .as_millis() as u64
+    }
+
+    fn next_frame_sequence(&mut self) -> u64 {
+        self.frame_sequence += 1;
+        self.frame_sequence
+    }
+
+    pub fn pending_frame(&self) -> Option<&FrameFingerprint> {
         self.pending_frame.as_ref()
     }
 
@@ -115,9 +743,25 @@ impl ClientRenderState {
         self.acked_baseline.is_some()
     }
 
+    pub fn baseline(&self) -> Option<&FrameFingerprint> {
+        self.acked_baseline.as_ref()
+    }
+
+    /// Whether the next delta should carry `frame_hash`
+    pub fn frame_hash_due(&self) -> bool {
+        // The hash covers the whole screen, which a region client doesn't have
+        self.frame_hash_enabled && !self.text_mode.is_active() && self.region.is_none()
+    }
+
+    /// Whether the next delta's patches should carry `row_crc32`
+    pub fn row_checksums_due(&self) -> bool {
+        self.row_checksums_enabled && !self.text_mode.is_active()
+    }
+
     pub fn reset_baseline(&mut self) {
         self.acked_baseline = None;
         self.acked_baseline_state_id = 0;
+        self.baseline_held.clear();
     }
 }
 
@@ -126,3 +770,33 @@ impl Default for ClientRenderState {
         Self::new(4)
     }
 }
+
+fn downgrade_style_defs(defs: &mut [StyleDef]) {
+    for style in defs.iter_mut().filter_map(|def| def.style.as_mut()) {
+        downgrade_underline(style);
+    }
+}
+
+fn size_changed(old: (usize, usize), new: (usize, usize)) -> SizeChanged {
+    let display_size = |(cols, rows): (usize, usize)| DisplaySize {
+        cols: cols as u32,
+        rows: rows as u32,
+    };
+    SizeChanged {
+        old_size: Some(display_size(old)),
+        new_size: Some(display_size(new)),
+        // Width changes make the pty rewrap its lines
+        reflowed: old.0 != new.0,
+    }
+}
+
+fn style_defs_since(style_table: &StyleTable, baseline: usize) -> Vec<StyleDef> {
+    style_table
+        .styles_since(baseline)
+        .into_iter()
+        .map(|(id, style)| StyleDef {
+            style_id: id,
+            style: Some(style.clone()),
+        })
+        .collect()
+}
@@ -0,0 +1,99 @@
+//! Where the session gets the time from.
+//!
+//! Leases, input reordering, RTT samples, frame pacing and resume tokens all read the
+//! [`Clock`] given to the [`RemoteSession`](crate::session::RemoteSession) rather than
+//! the system, so tests can run them on a [`ManualClock`] and move time on by hand.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Monotonic time, for timeouts and intervals
+    fn now(&self) -> Instant;
+    /// Milliseconds since the Unix epoch, for times that outlive the process, like
+    /// when a resume token was issued
+    fn unix_ms(&self) -> u64;
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The system's clocks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    pub fn shared() -> SharedClock {
+        Arc::new(SystemClock)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn unix_ms(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// Unix time a [`ManualClock`] starts at unless told otherwise
+pub const MANUAL_CLOCK_START_UNIX_MS: u64 = 1_700_000_000_000;
+
+/// A clock that only moves when advanced, for deterministic tests. Clones share the
+/// time, so a test can keep one and hand [`Self::shared`] to what it tests.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    origin: Instant,
+    start_unix_ms: u64,
+    elapsed_ms: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::starting_at(MANUAL_CLOCK_START_UNIX_MS)
+    }
+
+    pub fn starting_at(unix_ms: u64) -> Self {
+        Self {
+            origin: Instant::now(),
+            start_unix_ms: unix_ms,
+            elapsed_ms: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.elapsed_ms
+            .fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    /// How far the clock has been advanced
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.elapsed_ms.load(Ordering::SeqCst))
+    }
+
+    pub fn shared(&self) -> SharedClock {
+        Arc::new(self.clone())
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.origin + self.elapsed()
+    }
+
+    fn unix_ms(&self) -> u64 {
+        self.start_unix_ms + self.elapsed_ms.load(Ordering::SeqCst)
+    }
+}
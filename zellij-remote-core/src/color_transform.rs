@@ -0,0 +1,196 @@
+//! Recoloring the styles sent to one client (`ClientHello.color_transform`), so a
+//! low-vision or colorblind user gets readable output without the session's theme
+//! changing for everyone else.
+//!
+//! Colors are worked on as RGB, resolved as [`SvgTheme::default`] does: the 16 ANSI
+//! colors at xterm's defaults and the terminal's default colors as a dark theme's. A
+//! color the transform leaves alone keeps its original form, so unaffected ANSI
+//! colors still come out in the client's own palette.
+
+use zellij_remote_protocol::{color, Color, ColorTransform, Style, StyleDef};
+
+use crate::style_convert::rgb_color;
+use crate::svg_export::SvgTheme;
+
+/// WCAG AA contrast for body text
+pub const MIN_CONTRAST_RATIO: f64 = 4.5;
+
+type Rgb = (u8, u8, u8);
+
+const BLACK: Rgb = (0, 0, 0);
+const WHITE: Rgb = (0xff, 0xff, 0xff);
+
+pub fn is_identity(transform: &ColorTransform) -> bool {
+    !(transform.force_dark_background || transform.high_contrast || transform.protanopia_safe)
+}
+
+pub fn transform_style_defs(transform: &ColorTransform, defs: &mut [StyleDef]) {
+    if is_identity(transform) {
+        return;
+    }
+    let theme = SvgTheme::default();
+    for style in defs.iter_mut().filter_map(|def| def.style.as_mut()) {
+        transform_style(transform, style, &theme);
+    }
+}
+
+fn transform_style(transform: &ColorTransform, style: &mut Style, theme: &SvgTheme) {
+    if transform.high_contrast {
+        style.dim = false;
+    }
+    let reverse = style.reverse;
+    let Style {
+        fg,
+        bg,
+        underline_color,
+        ..
+    } = style;
+    // The colors as they are seen, with the default each falls back to
+    let ((fg, fg_default), (bg, bg_default)) = if reverse {
+        ((bg, theme.background), (fg, theme.foreground))
+    } else {
+        ((fg, theme.foreground), (bg, theme.background))
+    };
+
+    if transform.protanopia_safe {
+        for slot in [&mut *fg, &mut *bg, &mut *underline_color] {
+            map_explicit(slot, theme, daltonize);
+        }
+    }
+    if transform.force_dark_background {
+        map_explicit(bg, theme, |rgb| {
+            if lightness(rgb) > 0.5 {
+                invert_lightness(rgb)
+            } else {
+                rgb
+            }
+        });
+        for slot in [&mut *fg, &mut *underline_color] {
+            map_explicit(slot, theme, |rgb| {
+                if lightness(rgb) < 0.5 {
+                    invert_lightness(rgb)
+                } else {
+                    rgb
+                }
+            });
+        }
+    }
+    if transform.high_contrast {
+        let fg_rgb = theme.resolve(fg.as_ref(), fg_default);
+        let bg_rgb = theme.resolve(bg.as_ref(), bg_default);
+        if contrast_ratio(fg_rgb, bg_rgb) < MIN_CONTRAST_RATIO {
+            let (r, g, b) = if contrast_ratio(WHITE, bg_rgb) >= contrast_ratio(BLACK, bg_rgb) {
+                WHITE
+            } else {
+                BLACK
+            };
+            *fg = Some(rgb_color(r, g, b));
+        }
+    }
+}
+
+/// Replace a color the program set (not the terminal default) with `f` of it, if that
+/// differs
+fn map_explicit(slot: &mut Option<Color>, theme: &SvgTheme, f: impl Fn(Rgb) -> Rgb) {
+    let explicit = matches!(
+        slot.as_ref().and_then(|c| c.value.as_ref()),
+        Some(color::Value::Ansi256(_) | color::Value::Rgb(_))
+    );
+    if !explicit {
+        return;
+    }
+    let rgb = theme.resolve(slot.as_ref(), BLACK);
+    let (r, g, b) = f(rgb);
+    if (r, g, b) != rgb {
+        *slot = Some(rgb_color(r, g, b));
+    }
+}
+
+/// WCAG relative luminance, 0 (black) to 1 (white)
+fn luminance((r, g, b): Rgb) -> f64 {
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// 1:1 (same) to 21:1 (black on white)
+pub fn contrast_ratio(a: Rgb, b: Rgb) -> f64 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// HSL lightness, 0 to 1
+fn lightness((r, g, b): Rgb) -> f64 {
+    let max = r.max(g).max(b) as f64;
+    let min = r.min(g).min(b) as f64;
+    (max + min) / 2.0 / 255.0
+}
+
+/// The same hue and saturation, as light as the color was dark
+fn invert_lightness(rgb: Rgb) -> Rgb {
+    let (h, s, l) = to_hsl(rgb);
+    from_hsl(h, s, 1.0 - l)
+}
+
+fn to_hsl((r, g, b): Rgb) -> (f64, f64, f64) {
+    let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let d = max - min;
+    if d == 0.0 {
+        return (0.0, 0.0, l);
+    }
+    let s = d / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        ((g - b) / d).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+    (h * 60.0, s, l)
+}
+
+fn from_hsl(h: f64, s: f64, l: f64) -> Rgb {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = c * (1.0 - ((h / 60.0).rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match (h / 60.0) as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = l - c / 2.0;
+    let channel = |v: f64| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+/// Shift what a protanope can't see (the difference between the color and how it
+/// looks without L cones) into green and blue, after Fidaner et al.
+fn daltonize((r, g, b): Rgb) -> Rgb {
+    let (r, g, b) = (r as f64, g as f64, b as f64);
+    let l = 17.8824 * r + 43.5161 * g + 4.11935 * b;
+    let m = 3.45565 * r + 27.1554 * g + 3.86714 * b;
+    let s = 0.0299566 * r + 0.184309 * g + 1.46709 * b;
+    // Protanopia: L is predicted from M and S
+    let l = 2.02344 * m - 2.52581 * s;
+    let seen_r = 0.0809444479 * l - 0.130504409 * m + 0.116721066 * s;
+    let seen_g = -0.0102485335 * l + 0.0540193266 * m - 0.113614708 * s;
+    let seen_b = -0.000365296938 * l - 0.00412161469 * m + 0.693511405 * s;
+    let (err_r, err_g, err_b) = (r - seen_r, g - seen_g, b - seen_b);
+    let channel = |v: f64| v.round().clamp(0.0, 255.0) as u8;
+    (
+        channel(r),
+        channel(g + 0.7 * err_r + err_g),
+        channel(b + 0.7 * err_r + err_b),
+    )
+}
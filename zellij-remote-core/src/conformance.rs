@@ -0,0 +1,770 @@
+//! Conformance vectors for remote protocol clients.
+//!
+//! A vector is a run of snapshots and deltas produced by [`DeltaEngine`], plus the screen
+//! a client must hold once it has applied all of them. The suite is checked in as an
+//! encoded `ConformanceSuite` at `zellij-remote-core/conformance/vectors.pb`, so clients
+//! written in other languages can replay it against their own screen model.
+//! [`ClientScreen`] is the reference client the vectors are checked against.
+
+use std::collections::BTreeMap;
+
+use crate::delta::DeltaEngine;
+use crate::frame::{Cell, Cursor, CursorShape, FrameData, FrameStore, LineSize, RowData};
+use crate::frame_hash::{FrameHasher, RowChecksum};
+use crate::style_convert::{ansi256_color, rgb_color};
+use crate::style_table::StyleTable;
+use zellij_remote_protocol::{
+    conformance_step, ConformanceStep, ConformanceSuite, ConformanceVector, CursorState,
+    DisplaySize, LineSize as ProtoLineSize, RowData as ProtoRowData, ScreenDelta, ScreenSnapshot,
+    Style, StyleDef, UnderlineStyle,
+};
+
+/// Bumped when existing vectors change meaning; adding vectors doesn't bump it.
+pub const CONFORMANCE_SUITE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConformanceError {
+    /// A delta arrived before any snapshot
+    NoSnapshot,
+    /// The delta was computed against a state the client doesn't hold
+    BaseMismatch {
+        expected: u64,
+        received: u64,
+    },
+    RowOutOfRange {
+        row: u32,
+    },
+    ColumnOutOfRange {
+        row: u32,
+        col: u32,
+    },
+    /// Codepoint, width and style arrays of a row or run differ in length
+    LengthMismatch {
+        row: u32,
+    },
+    UnknownStyle {
+        style_id: u32,
+    },
+    /// A run starting on a continuation cell or ending on a wide character whose
+    /// continuation it leaves out
+    SplitWideChar {
+        row: u32,
+        col: u32,
+    },
+    /// A step carrying neither a snapshot nor a delta
+    EmptyStep,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConformanceFailure {
+    Step {
+        index: usize,
+        error: ConformanceError,
+    },
+    /// Every step applied, but the resulting screen isn't the expected one
+    Mismatch {
+        expected: Box<ScreenSnapshot>,
+        actual: Box<ScreenSnapshot>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ScreenCell {
+    codepoint: u32,
+    width: u32,
+    style_id: u32,
+}
+
+impl Default for ScreenCell {
+    fn default() -> Self {
+        Self {
+            codepoint: ' ' as u32,
+            width: 1,
+            style_id: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ScreenRow {
+    cells: Vec<ScreenCell>,
+    /// Raw `LineSize`, with the snapshot's implied single size spelled out
+    line_size: i32,
+}
+
+impl ScreenRow {
+    fn blank(cols: usize) -> Self {
+        Self {
+            cells: vec![ScreenCell::default(); cols],
+            line_size: ProtoLineSize::Single as i32,
+        }
+    }
+
+    /// After a run wrote `start..end`, blank the halves of wide characters it cut off
+    /// on either side: a wide character before it whose continuation it overwrote,
+    /// and continuation cells after it whose wide character it overwrote. Blanks keep
+    /// their style, as a terminal erasing half a wide character does.
+    fn clear_broken_wide_chars(&mut self, start: usize, end: usize) {
+        let blank = |cell: &ScreenCell| ScreenCell {
+            style_id: cell.style_id,
+            ..ScreenCell::default()
+        };
+        if let Some(before) = start.checked_sub(1).and_then(|col| self.cells.get_mut(col)) {
+            if before.width > 1 {
+                *before = blank(before);
+            }
+        }
+        for cell in self.cells[end..]
+            .iter_mut()
+            .take_while(|cell| cell.width == 0)
+        {
+            *cell = blank(cell);
+        }
+    }
+}
+
+/// Minimal client-side screen model: applies snapshots and deltas exactly as the
+/// protocol specifies and rejects anything a conforming client must reject.
+///
+/// A step that fails leaves the screen as it was.
+#[derive(Debug, Clone, Default)]
+pub struct ClientScreen {
+    state_id: Option<u64>,
+    cols: usize,
+    rows: Vec<ScreenRow>,
+    styles: BTreeMap<u32, Style>,
+    cursor: CursorState,
+}
+
+impl ClientScreen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// State the screen holds, or `None` before the first snapshot.
+    pub fn state_id(&self) -> Option<u64> {
+        self.state_id
+    }
+
+    pub fn apply_snapshot(&mut self, snapshot: &ScreenSnapshot) -> Result<(), ConformanceError> {
+        let size = snapshot.size.clone().unwrap_or_default();
+        let cols = size.cols as usize;
+        let mut next = ClientScreen {
+            state_id: Some(snapshot.state_id),
+            cols,
+            rows: (0..size.rows).map(|_| ScreenRow::blank(cols)).collect(),
+            styles: if snapshot.style_table_reset {
+                BTreeMap::new()
+            } else {
+                self.styles.clone()
+            },
+            cursor: snapshot.cursor.clone().unwrap_or_default(),
+        };
+        next.add_styles(&snapshot.styles);
+
+        for row_data in &snapshot.rows {
+            next.apply_row_data(row_data)?;
+        }
+
+        *self = next;
+        Ok(())
+    }
+
+    pub fn apply_delta(&mut self, delta: &ScreenDelta) -> Result<(), ConformanceError> {
+        let state_id = self.state_id.ok_or(ConformanceError::NoSnapshot)?;
+        if delta.base_state_id != state_id {
+            return Err(ConformanceError::BaseMismatch {
+                expected: state_id,
+                received: delta.base_state_id,
+            });
+        }
+
+        let mut next = self.clone();
+        next.add_styles(&delta.styles_added);
+        for patch in &delta.row_patches {
+            let cols = next.cols;
+            let styles = &next.styles;
+            let row = next
+                .rows
+                .get_mut(patch.row as usize)
+                .ok_or(ConformanceError::RowOutOfRange { row: patch.row })?;
+
+            // Unspecified means the line size didn't change
+            if patch.line_size != ProtoLineSize::Unspecified as i32 {
+                row.line_size = patch.line_size;
+            }
+            for run in &patch.runs {
+                if run.widths.len() != run.codepoints.len()
+                    || run.style_ids.len() != run.codepoints.len()
+                {
+                    return Err(ConformanceError::LengthMismatch { row: patch.row });
+                }
+                let start = run.col_start as usize;
+                let end = start + run.codepoints.len();
+                if end > cols {
+                    return Err(ConformanceError::ColumnOutOfRange {
+                        row: patch.row,
+                        col: run.col_start.max(cols as u32),
+                    });
+                }
+                // A wide character in the last column has no room for its continuation
+                let splits_end = run.widths.last().is_some_and(|&width| width > 1) && end < cols;
+                if run.widths.first() == Some(&0) || splits_end {
+                    return Err(ConformanceError::SplitWideChar {
+                        row: patch.row,
+                        col: if splits_end {
+                            end as u32
+                        } else {
+                            run.col_start
+                        },
+                    });
+                }
+                for (i, &codepoint) in run.codepoints.iter().enumerate() {
+                    row.cells[start + i] =
+                        known_cell(styles, codepoint, run.widths[i], run.style_ids[i])?;
+                }
+                row.clear_broken_wide_chars(start, end);
+            }
+        }
+        if let Some(cursor) = &delta.cursor {
+            next.cursor = cursor.clone();
+        }
+        next.state_id = Some(delta.state_id);
+
+        *self = next;
+        Ok(())
+    }
+
+    /// Style of the cell at `row`, `col`, as the client would render it.
+    pub fn style_at(&self, row: usize, col: usize) -> Option<Style> {
+        let cell = self.rows.get(row)?.cells.get(col)?;
+        Some(self.styles.get(&cell.style_id).cloned().unwrap_or_default())
+    }
+
+    /// Hash of the screen as integrity mode defines it, to compare with `frame_hash`.
+    pub fn frame_hash(&self) -> u64 {
+        let mut hasher = FrameHasher::new(self.cols as u32, self.rows.len() as u32);
+        for row in &self.rows {
+            hasher.row(row.line_size);
+            for cell in &row.cells {
+                hasher.cell(cell.codepoint, cell.width, cell.style_id);
+            }
+        }
+        hasher.finish(self.cursor.row, self.cursor.col, self.cursor.visible)
+    }
+
+    /// `row_crc32` of one row of the screen.
+    pub fn row_crc32(&self, row: usize) -> Option<u32> {
+        let row = self.rows.get(row)?;
+        let mut checksum = RowChecksum::new(row.line_size);
+        for cell in &row.cells {
+            checksum.cell(cell.codepoint, cell.width, cell.style_id);
+        }
+        Some(checksum.finish())
+    }
+
+    /// Rows `delta` patched whose `row_crc32` doesn't match the screen, to check after
+    /// applying it. Patches without a checksum are skipped.
+    pub fn mismatched_rows(&self, delta: &ScreenDelta) -> Vec<u32> {
+        delta
+            .row_patches
+            .iter()
+            .filter(|patch| patch.row_crc32 != 0)
+            .filter(|patch| self.row_crc32(patch.row as usize) != Some(patch.row_crc32))
+            .map(|patch| patch.row)
+            .collect()
+    }
+
+    /// The screen in the canonical form the server's snapshots use: every row, every
+    /// known style in id order and the single line size left implied.
+    pub fn to_snapshot(&self) -> ScreenSnapshot {
+        let rows = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(row_idx, row)| ProtoRowData {
+                row: row_idx as u32,
+                codepoints: row.cells.iter().map(|c| c.codepoint).collect(),
+                widths: row.cells.iter().map(|c| c.width).collect(),
+                style_ids: row.cells.iter().map(|c| c.style_id).collect(),
+                line_size: if row.line_size == ProtoLineSize::Single as i32 {
+                    ProtoLineSize::Unspecified as i32
+                } else {
+                    row.line_size
+                },
+                col_start: 0,
+            })
+            .collect();
+
+        ScreenSnapshot {
+            state_id: self.state_id.unwrap_or(0),
+            size: Some(DisplaySize {
+                cols: self.cols as u32,
+                rows: self.rows.len() as u32,
+            }),
+            style_table_reset: true,
+            styles: self
+                .styles
+                .iter()
+                .map(|(&style_id, style)| StyleDef {
+                    style_id,
+                    style: Some(style.clone()),
+                })
+                .collect(),
+            rows,
+            cursor: Some(self.cursor.clone()),
+            delivered_input_watermark: 0,
+            server_time_ms: 0,
+            frame_sequence: 0,
+            frame_hash: 0,
+            size_changed: None,
+            chunk_index: 0,
+            chunk_count: 0,
+            echo_suppressed: false,
+            debug_info: None,
+        }
+    }
+
+    fn add_styles(&mut self, styles: &[StyleDef]) {
+        for def in styles {
+            self.styles
+                .insert(def.style_id, def.style.clone().unwrap_or_default());
+        }
+    }
+
+    fn apply_row_data(&mut self, row_data: &ProtoRowData) -> Result<(), ConformanceError> {
+        let cols = self.cols;
+        let styles = &self.styles;
+        let row = self
+            .rows
+            .get_mut(row_data.row as usize)
+            .ok_or(ConformanceError::RowOutOfRange { row: row_data.row })?;
+        if row_data.widths.len() != row_data.codepoints.len()
+            || row_data.style_ids.len() != row_data.codepoints.len()
+        {
+            return Err(ConformanceError::LengthMismatch { row: row_data.row });
+        }
+        if row_data.codepoints.len() > cols {
+            return Err(ConformanceError::ColumnOutOfRange {
+                row: row_data.row,
+                col: cols as u32,
+            });
+        }
+
+        // Snapshot rows may be shorter than the screen; the rest stays blank
+        for (i, &codepoint) in row_data.codepoints.iter().enumerate() {
+            row.cells[i] =
+                known_cell(styles, codepoint, row_data.widths[i], row_data.style_ids[i])?;
+        }
+        row.line_size = if row_data.line_size == ProtoLineSize::Unspecified as i32 {
+            ProtoLineSize::Single as i32
+        } else {
+            row_data.line_size
+        };
+        Ok(())
+    }
+}
+
+/// Style 0 is the default style and always known.
+fn known_cell(
+    styles: &BTreeMap<u32, Style>,
+    codepoint: u32,
+    width: u32,
+    style_id: u32,
+) -> Result<ScreenCell, ConformanceError> {
+    if style_id != 0 && !styles.contains_key(&style_id) {
+        return Err(ConformanceError::UnknownStyle { style_id });
+    }
+    Ok(ScreenCell {
+        codepoint,
+        width,
+        style_id,
+    })
+}
+
+/// Replay `vector` into a fresh [`ClientScreen`] and compare the result.
+pub fn run_vector(vector: &ConformanceVector) -> Result<(), ConformanceFailure> {
+    let mut screen = ClientScreen::new();
+    for (index, step) in vector.steps.iter().enumerate() {
+        let result = match &step.frame {
+            Some(conformance_step::Frame::Snapshot(snapshot)) => screen.apply_snapshot(snapshot),
+            Some(conformance_step::Frame::Delta(delta)) => screen.apply_delta(delta),
+            None => Err(ConformanceError::EmptyStep),
+        };
+        result.map_err(|error| ConformanceFailure::Step { index, error })?;
+    }
+
+    let expected = vector.expected.clone().unwrap_or_default();
+    let actual = screen.to_snapshot();
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(ConformanceFailure::Mismatch {
+            expected: Box::new(expected),
+            actual: Box::new(actual),
+        })
+    }
+}
+
+/// Build the suite from the current [`DeltaEngine`]. The output is deterministic, so
+/// it can be compared byte for byte with the checked-in vectors.
+pub fn generate_suite() -> ConformanceSuite {
+    ConformanceSuite {
+        version: CONFORMANCE_SUITE_VERSION,
+        vectors: vec![
+            snapshot_only(),
+            plain_text_deltas(),
+            sparse_runs(),
+            styles_added(),
+            wide_chars(),
+            line_sizes(),
+            cursor_only_deltas(),
+            cleared_rows(),
+            resync_after_resize(),
+        ],
+    }
+}
+
+fn snapshot_only() -> ConformanceVector {
+    let mut v = VectorBuilder::new(12, 3);
+    v.text(0, 0, "hello, world", 0);
+    v.cursor(1, 0);
+    v.snapshot();
+    v.finish(
+        "snapshot_only",
+        "A single snapshot: rows, size, the default style and the cursor",
+    )
+}
+
+fn plain_text_deltas() -> ConformanceVector {
+    let mut v = VectorBuilder::new(10, 2);
+    v.snapshot();
+    v.text(0, 0, "$ ls", 0);
+    v.cursor(0, 4);
+    v.delta();
+    v.text(0, 4, " -la", 0);
+    v.cursor(0, 8);
+    v.delta();
+    v.text(1, 0, "total 0", 0);
+    v.cursor(1, 7);
+    v.delta();
+    v.finish(
+        "plain_text_deltas",
+        "Unstyled text typed across several deltas, each based on the previous state",
+    )
+}
+
+fn sparse_runs() -> ConformanceVector {
+    let mut v = VectorBuilder::new(10, 2);
+    v.text(0, 0, "abcdefghij", 0);
+    v.text(1, 0, "0123456789", 0);
+    v.snapshot();
+    v.text(0, 1, "B", 0);
+    v.text(0, 6, "GH", 0);
+    // Rewritten with the same content: dirty, but no patch goes out for it
+    v.text(1, 0, "0123456789", 0);
+    v.delta();
+    v.finish(
+        "sparse_runs",
+        "One row patch with two runs; cells between and around them are left untouched",
+    )
+}
+
+fn styles_added() -> ConformanceVector {
+    let mut v = VectorBuilder::new(8, 2);
+    v.snapshot();
+    let bold_red = v.style(Style {
+        fg: Some(rgb_color(255, 0, 0)),
+        bold: true,
+        ..Default::default()
+    });
+    v.text(0, 0, "error", bold_red);
+    v.delta();
+    let curly = v.style(Style {
+        underline: UnderlineStyle::Curly as i32,
+        underline_color: Some(ansi256_color(208)),
+        ..Default::default()
+    });
+    v.text(1, 0, "warn", curly);
+    v.text(1, 5, "!", bold_red);
+    v.delta();
+    v.finish(
+        "styles_added",
+        "Styles arrive in styles_added of the delta that first uses them and are reused after",
+    )
+}
+
+fn wide_chars() -> ConformanceVector {
+    let mut v = VectorBuilder::new(6, 1);
+    v.wide(0, 0, '中', 0);
+    v.wide(0, 2, '文', 0);
+    v.snapshot();
+    // Two narrow characters replace the second wide one and its continuation cell
+    v.text(0, 2, "ok", 0);
+    v.delta();
+    v.wide(0, 4, '字', 0);
+    v.delta();
+    v.finish(
+        "wide_chars",
+        "Double-width characters: width 2 followed by a continuation cell (codepoint 0, width 0)",
+    )
+}
+
+fn line_sizes() -> ConformanceVector {
+    let mut v = VectorBuilder::new(6, 3);
+    v.text(0, 0, "TITLE", 0);
+    v.line_size(0, LineSize::DoubleWidth);
+    v.snapshot();
+    v.text(1, 0, "BIG", 0);
+    v.text(2, 0, "BIG", 0);
+    v.line_size(1, LineSize::DoubleHeightTop);
+    v.line_size(2, LineSize::DoubleHeightBottom);
+    v.delta();
+    // Back to single: the patch carries LINE_SIZE_SINGLE and no runs
+    v.line_size(0, LineSize::Single);
+    v.delta();
+    v.finish(
+        "line_sizes",
+        "Snapshot rows leave single size implied; patches carry a line size only when it changes",
+    )
+}
+
+fn cursor_only_deltas() -> ConformanceVector {
+    let mut v = VectorBuilder::new(4, 2);
+    v.snapshot();
+    v.cursor(1, 3);
+    v.delta();
+    v.set_cursor(Cursor {
+        row: 1,
+        col: 3,
+        visible: false,
+        blink: true,
+        shape: CursorShape::Block,
+        color: None,
+    });
+    v.delta();
+    v.set_cursor(Cursor {
+        row: 0,
+        col: 2,
+        visible: true,
+        blink: false,
+        shape: CursorShape::Bar,
+        color: None,
+    });
+    v.delta();
+    v.finish(
+        "cursor_only_deltas",
+        "Deltas without row patches: cursor moves, hides and changes shape and blink",
+    )
+}
+
+fn cleared_rows() -> ConformanceVector {
+    let mut v = VectorBuilder::new(5, 3);
+    let dim = v.style(Style {
+        dim: true,
+        ..Default::default()
+    });
+    v.text(0, 0, "one", dim);
+    v.text(1, 0, "two", 0);
+    v.text(2, 0, "three", 0);
+    v.snapshot();
+    v.clear_row(0);
+    v.clear_row(2);
+    v.cursor(0, 0);
+    v.delta();
+    v.finish(
+        "cleared_rows",
+        "Rows reset to blank cells with the default style, as after a clear",
+    )
+}
+
+fn resync_after_resize() -> ConformanceVector {
+    let mut v = VectorBuilder::new(4, 2);
+    let bold = v.style(Style {
+        bold: true,
+        ..Default::default()
+    });
+    v.text(0, 0, "old", bold);
+    v.snapshot();
+    v.text(1, 0, "xy", bold);
+    v.delta();
+
+    // The new snapshot resets the style table, so style 1 now means something else
+    v.resize(6, 3);
+    v.reset_styles();
+    let italic = v.style(Style {
+        fg: Some(ansi256_color(4)),
+        italic: true,
+        ..Default::default()
+    });
+    v.text(2, 0, "new", italic);
+    v.snapshot();
+    v.text(0, 0, "fresh", 0);
+    v.delta();
+    v.finish(
+        "resync_after_resize",
+        "A second snapshot with a new size and style_table_reset replaces all earlier state",
+    )
+}
+
+/// Drives a [`FrameStore`] the way the server does and records what a single client
+/// that applies every frame would be sent.
+struct VectorBuilder {
+    store: FrameStore,
+    styles: StyleTable,
+    /// Frame and state the client holds; the next delta is computed against it
+    baseline: FrameData,
+    baseline_state_id: u64,
+    /// Styles the client already knows; any added later go out with the next delta
+    styles_sent: usize,
+    steps: Vec<ConformanceStep>,
+}
+
+impl VectorBuilder {
+    fn new(cols: usize, rows: usize) -> Self {
+        let store = FrameStore::new(cols, rows);
+        Self {
+            baseline: store.current_frame().clone(),
+            store,
+            styles: StyleTable::new(),
+            baseline_state_id: 0,
+            styles_sent: 0,
+            steps: Vec::new(),
+        }
+    }
+
+    fn style(&mut self, style: Style) -> u32 {
+        self.styles.get_or_insert(&style)
+    }
+
+    fn reset_styles(&mut self) {
+        self.styles.reset();
+        // Nothing in the store may point at the discarded styles
+        let cols = self.store.current_frame().cols;
+        for row_idx in 0..self.store.current_frame().rows.len() {
+            self.store.set_row(row_idx, RowData::new(cols));
+        }
+    }
+
+    fn text(&mut self, row: usize, col: usize, text: &str, style_id: u32) {
+        self.store.update_row(row, |r| {
+            for (i, c) in text.chars().enumerate() {
+                r.set_cell(
+                    col + i,
+                    Cell {
+                        codepoint: c as u32,
+                        width: 1,
+                        style_id,
+                    },
+                );
+            }
+        });
+    }
+
+    fn wide(&mut self, row: usize, col: usize, c: char, style_id: u32) {
+        self.store.update_row(row, |r| {
+            r.set_cell(
+                col,
+                Cell {
+                    codepoint: c as u32,
+                    width: 2,
+                    style_id,
+                },
+            );
+            r.set_cell(
+                col + 1,
+                Cell {
+                    codepoint: 0,
+                    width: 0,
+                    style_id,
+                },
+            );
+        });
+    }
+
+    fn line_size(&mut self, row: usize, line_size: LineSize) {
+        self.store.update_row(row, |r| r.set_line_size(line_size));
+    }
+
+    fn clear_row(&mut self, row: usize) {
+        let cols = self.store.current_frame().cols;
+        self.store.set_row(row, RowData::new(cols));
+    }
+
+    fn cursor(&mut self, row: u32, col: u32) {
+        let cursor = Cursor {
+            row,
+            col,
+            ..self.store.current_frame().cursor
+        };
+        self.store.set_cursor(cursor);
+    }
+
+    fn set_cursor(&mut self, cursor: Cursor) {
+        self.store.set_cursor(cursor);
+    }
+
+    fn resize(&mut self, cols: usize, rows: usize) {
+        self.store.resize(cols, rows);
+    }
+
+    fn snapshot(&mut self) {
+        self.store.advance_state();
+        self.store.take_dirty_rows();
+        let state_id = self.store.current_state_id();
+        let snapshot =
+            DeltaEngine::compute_snapshot(self.store.current_frame(), &mut self.styles, state_id);
+        self.mark_sent();
+        self.steps.push(ConformanceStep {
+            frame: Some(conformance_step::Frame::Snapshot(snapshot)),
+        });
+    }
+
+    fn delta(&mut self) {
+        self.store.advance_state();
+        let dirty_rows = self.store.take_dirty_rows();
+        let mut delta = DeltaEngine::compute_delta(
+            &self.baseline,
+            self.store.current_frame(),
+            &mut self.styles,
+            self.baseline_state_id,
+            self.store.current_state_id(),
+            Some(&dirty_rows),
+        );
+        // Styles are registered while the frame is built, before the delta is computed,
+        // so the ones this client hasn't seen yet are attached here
+        delta.styles_added = self
+            .styles
+            .styles_since(self.styles_sent)
+            .into_iter()
+            .map(|(id, style)| StyleDef {
+                style_id: id,
+                style: Some(style.clone()),
+            })
+            .collect();
+        self.mark_sent();
+        self.steps.push(ConformanceStep {
+            frame: Some(conformance_step::Frame::Delta(delta)),
+        });
+    }
+
+    fn mark_sent(&mut self) {
+        self.baseline = self.store.current_frame().clone();
+        self.baseline_state_id = self.store.current_state_id();
+        self.styles_sent = self.styles.current_count();
+    }
+
+    fn finish(mut self, name: &str, description: &str) -> ConformanceVector {
+        let expected = DeltaEngine::compute_snapshot(
+            self.store.current_frame(),
+            &mut self.styles,
+            self.store.current_state_id(),
+        );
+        ConformanceVector {
+            name: name.to_owned(),
+            description: description.to_owned(),
+            steps: self.steps,
+            expected: Some(expected),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! `DebugFrameInfo` for clients that ask for it in `ClientHello.debug_frame_info`,
+//! so client developers can show what each update cost the server.
+//!
+//! Only builds with the `debug-frame-info` feature attach it; everywhere else the
+//! request is ignored.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use zellij_remote_protocol::{DebugFrameInfo, ScreenDelta, ScreenSnapshot};
+
+/// Whether this build attaches `DebugFrameInfo` at all
+pub const DEBUG_FRAME_INFO_AVAILABLE: bool = cfg!(feature = "debug-frame-info");
+
+/// Stats for a delta that took `elapsed` to diff `rows_diffed` rows. The encoded
+/// size is filled in once the delta is final.
+pub fn for_delta(delta: &ScreenDelta, rows_diffed: usize, elapsed: Duration) -> DebugFrameInfo {
+    DebugFrameInfo {
+        rows_diffed: rows_diffed as u32,
+        cells_changed: delta
+            .row_patches
+            .iter()
+            .flat_map(|patch| &patch.runs)
+            .map(|run| run.codepoints.len() as u32)
+            .sum(),
+        encoded_bytes: 0,
+        compute_micros: micros(elapsed),
+    }
+}
+
+/// Stats for a snapshot of `encoded_bytes` that took `elapsed` to build
+pub fn for_snapshot(
+    snapshot: &ScreenSnapshot,
+    encoded_bytes: usize,
+    elapsed: Duration,
+) -> DebugFrameInfo {
+    DebugFrameInfo {
+        rows_diffed: snapshot.rows.len() as u32,
+        cells_changed: snapshot
+            .rows
+            .iter()
+            .map(|row| row.codepoints.len() as u32)
+            .sum(),
+        encoded_bytes: encoded_bytes as u32,
+        compute_micros: micros(elapsed),
+    }
+}
+
+/// How many rows of a `rows`-row frame a diff over `dirty_rows` compares (all of
+/// them without dirty tracking)
+pub fn rows_diffed(dirty_rows: Option<&HashSet<usize>>, rows: usize) -> usize {
+    dirty_rows.map_or(rows, |dirty| {
+        dirty.iter().filter(|&&row| row < rows).count()
+    })
+}
+
+fn micros(elapsed: Duration) -> u32 {
+    elapsed.as_micros().min(u32::MAX as u128) as u32
+}
@@ -1,12 +1,20 @@
-use crate::frame::{CursorShape, FrameData, Row};
+use crate::frame::{Cursor, CursorColor, CursorShape, FrameData, FrameFingerprint, LineSize, Row};
 use crate::style_table::StyleTable;
+use prost::encoding::encoded_len_varint;
 use std::collections::HashSet;
+use std::ops::Range;
 use std::sync::Arc;
 use zellij_remote_protocol::{
-    CellRun, CursorShape as ProtoCursorShape, CursorState, DisplaySize, RowData, RowPatch,
-    ScreenDelta, ScreenSnapshot, StyleDef,
+    color, CellRun, Color, CursorShape as ProtoCursorShape, CursorState, DisplaySize,
+    LineSize as ProtoLineSize, Rgb, RowData, RowPatch, ScreenDelta, ScreenSnapshot, StyleDef,
 };
 
+/// Longest gap of unchanged cells a row patch run may take in rather than start
+/// another run after it, which it does when resending them is smaller. A cell costs
+/// at least three bytes and a run at most eleven before its cells, so a longer gap
+/// never pays off.
+pub const RUN_MERGE_MAX_GAP: usize = 3;
+
 pub struct DeltaEngine;
 
 impl DeltaEngine {
@@ -44,7 +52,10 @@ impl DeltaEngine {
             let baseline_row = baseline.rows.get(row_idx);
             let current_row = &current.rows[row_idx];
 
-            if let Some(patch) = Self::encode_row_patch(row_idx, baseline_row, current_row) {
+            let baseline_line_size = baseline_row.map(Row::line_size);
+            if let Some(patch) =
+                Self::encode_row_patch(row_idx, baseline_row, baseline_line_size, current_row)
+            {
                 row_patches.push(patch);
             }
         }
@@ -54,7 +65,9 @@ impl DeltaEngine {
         // (and we already handled them above with baseline_row=None)
         if dirty_rows.is_none() && current.rows.len() > baseline.rows.len() {
             for row_idx in baseline.rows.len()..current.rows.len() {
-                if let Some(patch) = Self::encode_row_patch(row_idx, None, &current.rows[row_idx]) {
+                if let Some(patch) =
+                    Self::encode_row_patch(row_idx, None, None, &current.rows[row_idx])
+                {
                     row_patches.push(patch);
                 }
             }
@@ -64,7 +77,7 @@ impl DeltaEngine {
             .styles_since(style_baseline)
             .into_iter()
             .map(|(id, style)| StyleDef {
-                style_id: id as u32,
+                style_id: id,
                 style: Some(style.clone()),
             })
             .collect();
@@ -82,6 +95,112 @@ impl DeltaEngine {
             cursor,
             styles_added,
             delivered_input_watermark: 0,
+            server_time_ms: 0,
+            frame_sequence: 0,
+            frame_hash: 0,
+            chunk_index: 0,
+            chunk_count: 0,
+            echo_suppressed: current.echo_suppressed,
+            debug_info: None,
+        }
+    }
+
+    /// Like [`Self::compute_delta`], for callers that keep only fingerprints of the
+    /// baseline. Rows whose fingerprint changed are encoded against `baseline_frame`
+    /// when the caller can still look it up (e.g. in [`crate::StateHistory`]), giving
+    /// the same sparse runs as `compute_delta`; without it they are sent whole.
+    ///
+    /// `baseline_frame`, if given, must be the frame `baseline` was taken from.
+    #[allow(clippy::too_many_arguments)]
+    pub fn compute_delta_incremental(
+        baseline: &FrameFingerprint,
+        baseline_frame: Option<&FrameData>,
+        current: &FrameData,
+        current_fingerprint: &FrameFingerprint,
+        style_table: &mut StyleTable,
+        base_state_id: u64,
+        current_state_id: u64,
+        dirty_rows: Option<&HashSet<usize>>,
+    ) -> ScreenDelta {
+        let style_baseline = style_table.current_count();
+        let mut delta = Self::diff_incremental(
+            baseline,
+            baseline_frame,
+            current,
+            current_fingerprint,
+            base_state_id,
+            current_state_id,
+            dirty_rows,
+        );
+        delta.styles_added = style_table
+            .styles_since(style_baseline)
+            .into_iter()
+            .map(|(id, style)| StyleDef {
+                style_id: id,
+                style: Some(style.clone()),
+            })
+            .collect();
+        delta
+    }
+
+    /// The row and cursor changes of [`Self::compute_delta_incremental`], without
+    /// touching the style table, so it can run off the session lock.
+    pub fn diff_incremental(
+        baseline: &FrameFingerprint,
+        baseline_frame: Option<&FrameData>,
+        current: &FrameData,
+        current_fingerprint: &FrameFingerprint,
+        base_state_id: u64,
+        current_state_id: u64,
+        dirty_rows: Option<&HashSet<usize>>,
+    ) -> ScreenDelta {
+        let mut candidate_rows: Vec<usize> = match dirty_rows {
+            Some(dirty) => dirty
+                .iter()
+                .filter(|&idx| *idx < current.rows.len())
+                .copied()
+                .collect(),
+            None => (0..current.rows.len()).collect(),
+        };
+        candidate_rows.sort_unstable();
+
+        let mut row_patches = Vec::with_capacity(candidate_rows.len());
+        for row_idx in candidate_rows {
+            let baseline_fingerprint = baseline.rows.get(row_idx);
+            if baseline_fingerprint == current_fingerprint.rows.get(row_idx) {
+                continue;
+            }
+            let baseline_row = baseline_frame.and_then(|frame| frame.rows.get(row_idx));
+            if let Some(patch) = Self::encode_row_patch(
+                row_idx,
+                baseline_row,
+                baseline_fingerprint.map(|fingerprint| fingerprint.line_size),
+                &current.rows[row_idx],
+            ) {
+                row_patches.push(patch);
+            }
+        }
+
+        let cursor = if baseline.cursor != current.cursor {
+            Some(Self::encode_cursor(&current.cursor))
+        } else {
+            None
+        };
+
+        ScreenDelta {
+            base_state_id,
+            state_id: current_state_id,
+            row_patches,
+            cursor,
+            styles_added: Vec::new(),
+            delivered_input_watermark: 0,
+            server_time_ms: 0,
+            frame_sequence: 0,
+            frame_hash: 0,
+            chunk_index: 0,
+            chunk_count: 0,
+            echo_suppressed: current.echo_suppressed,
+            debug_info: None,
         }
     }
 
@@ -99,7 +218,7 @@ impl DeltaEngine {
         let styles: Vec<StyleDef> = style_table
             .all_styles()
             .map(|(id, style)| StyleDef {
-                style_id: id as u32,
+                style_id: id,
                 style: Some(style.clone()),
             })
             .collect();
@@ -115,12 +234,27 @@ impl DeltaEngine {
             styles,
             style_table_reset: true,
             delivered_input_watermark: 0,
+            server_time_ms: 0,
+            frame_sequence: 0,
+            frame_hash: 0,
+            size_changed: None,
+            chunk_index: 0,
+            chunk_count: 0,
+            echo_suppressed: frame.echo_suppressed,
+            debug_info: None,
         }
     }
 
     /// Encode a row patch with sparse CellRuns containing only changed cells.
     /// Returns None if no cells changed (handles dirty false positives).
-    fn encode_row_patch(row_idx: usize, baseline: Option<&Row>, current: &Row) -> Option<RowPatch> {
+    /// Without `baseline` cells every cell counts as changed; `baseline_line_size` is
+    /// None for a row the client doesn't have.
+    fn encode_row_patch(
+        row_idx: usize,
+        baseline: Option<&Row>,
+        baseline_line_size: Option<LineSize>,
+        current: &Row,
+    ) -> Option<RowPatch> {
         let cols = current.cols();
         let mut runs: Vec<CellRun> = Vec::new();
 
@@ -135,20 +269,44 @@ impl DeltaEngine {
                 break;
             }
 
-            // Found a changed cell - find the extent of the changed region
-            let start_col = col;
-            let mut codepoints = Vec::new();
-            let mut widths = Vec::new();
-            let mut style_ids = Vec::new();
-
+            // Found a changed cell - find the extent of the changed region first, so
+            // each run's vectors are allocated once at their final size
+            let mut start_col = col;
             while col < cols && Self::cell_changed(baseline, current, col) {
-                if let Some(cell) = current.get_cell(col) {
-                    codepoints.push(cell.codepoint);
-                    widths.push(cell.width as u32);
-                    style_ids.push(cell.style_id as u32);
-                }
                 col += 1;
             }
+            // Runs never split a wide character: a run starting on a continuation cell
+            // takes in its leading cell, one ending on a wide character its continuations
+            while start_col > 0 && Self::is_continuation(current, start_col) {
+                start_col -= 1;
+            }
+            while col < cols && Self::is_continuation(current, col) {
+                col += 1;
+            }
+            // Take in short gaps of unchanged cells when resending them costs fewer
+            // bytes than starting another run after them
+            while let Some(next) = Self::next_change(baseline, current, col, RUN_MERGE_MAX_GAP) {
+                if Self::cells_encoded_len(current, col..next) >= Self::run_overhead(next) {
+                    break;
+                }
+                col = next;
+                while col < cols && Self::cell_changed(baseline, current, col) {
+                    col += 1;
+                }
+                while col < cols && Self::is_continuation(current, col) {
+                    col += 1;
+                }
+            }
+
+            let len = col - start_col;
+            let mut codepoints = Vec::with_capacity(len);
+            let mut widths = Vec::with_capacity(len);
+            let mut style_ids = Vec::with_capacity(len);
+            for cell in (start_col..col).filter_map(|c| current.get_cell(c)) {
+                codepoints.push(cell.codepoint);
+                widths.push(cell.width as u32);
+                style_ids.push(cell.style_id);
+            }
 
             if !codepoints.is_empty() {
                 runs.push(CellRun {
@@ -160,16 +318,69 @@ impl DeltaEngine {
             }
         }
 
-        if runs.is_empty() {
+        // Line size travels only when it changed (or on a new row that isn't single size)
+        let line_size_changed = match baseline_line_size {
+            Some(base_line_size) => base_line_size != current.line_size(),
+            None => current.line_size() != LineSize::Single,
+        };
+        let line_size = if line_size_changed {
+            Self::encode_line_size(current.line_size())
+        } else {
+            ProtoLineSize::Unspecified
+        };
+
+        if runs.is_empty() && !line_size_changed {
             None
         } else {
             Some(RowPatch {
                 row: row_idx as u32,
                 runs,
+                line_size: line_size as i32,
+                echo: false,
+                row_crc32: 0,
             })
         }
     }
 
+    /// The first changed cell within `max_gap` cells from `col`, if any
+    fn next_change(
+        baseline: Option<&Row>,
+        current: &Row,
+        col: usize,
+        max_gap: usize,
+    ) -> Option<usize> {
+        let end = current.cols().min(col + max_gap + 1);
+        (col..end).find(|&c| Self::cell_changed(baseline, current, c))
+    }
+
+    /// What the cells in `cols` add to a run's packed fields
+    fn cells_encoded_len(row: &Row, cols: Range<usize>) -> usize {
+        cols.filter_map(|c| row.get_cell(c))
+            .map(|cell| {
+                encoded_len_varint(cell.codepoint as u64)
+                    + encoded_len_varint(cell.width as u64)
+                    + encoded_len_varint(cell.style_id as u64)
+            })
+            .sum()
+    }
+
+    /// Bytes a run costs before its cells: its key and length in the patch, its
+    /// `col_start` and the key and length of each packed field. Lengths are taken as
+    /// one byte, which holds for runs of up to about 40 cells.
+    fn run_overhead(col_start: usize) -> usize {
+        let col_start_len = if col_start == 0 {
+            0
+        } else {
+            1 + encoded_len_varint(col_start as u64)
+        };
+        2 + col_start_len + 3 * 2
+    }
+
+    /// Whether the cell at `col` is the right half of a wide character
+    fn is_continuation(row: &Row, col: usize) -> bool {
+        row.get_cell(col).is_some_and(|cell| cell.width == 0)
+    }
+
     /// Check if a cell has changed between baseline and current.
     /// Returns true if baseline is None (new row) or cell values differ.
     fn cell_changed(baseline: Option<&Row>, current: &Row, col: usize) -> bool {
@@ -199,7 +410,7 @@ impl DeltaEngine {
             if let Some(cell) = row.get_cell(i) {
                 codepoints.push(cell.codepoint);
                 widths.push(cell.width as u32);
-                style_ids.push(cell.style_id as u32);
+                style_ids.push(cell.style_id);
             }
         }
 
@@ -208,10 +419,25 @@ impl DeltaEngine {
             codepoints,
             widths,
             style_ids,
+            // Single is the implied default, so it isn't spelled out in snapshots
+            line_size: match row.line_size() {
+                LineSize::Single => ProtoLineSize::Unspecified as i32,
+                other => Self::encode_line_size(other) as i32,
+            },
+            col_start: 0,
+        }
+    }
+
+    pub(crate) fn encode_line_size(line_size: LineSize) -> ProtoLineSize {
+        match line_size {
+            LineSize::Single => ProtoLineSize::Single,
+            LineSize::DoubleWidth => ProtoLineSize::DoubleWidth,
+            LineSize::DoubleHeightTop => ProtoLineSize::DoubleHeightTop,
+            LineSize::DoubleHeightBottom => ProtoLineSize::DoubleHeightBottom,
         }
     }
 
-    fn encode_cursor(cursor: &crate::frame::Cursor) -> CursorState {
+    fn encode_cursor(cursor: &Cursor) -> CursorState {
         CursorState {
             row: cursor.row,
             col: cursor.col,
@@ -222,6 +448,38 @@ impl DeltaEngine {
                 CursorShape::Underline => ProtoCursorShape::Underline as i32,
                 CursorShape::Bar => ProtoCursorShape::Beam as i32,
             },
+            color: cursor.color.map(|c| Color {
+                value: Some(color::Value::Rgb(Rgb {
+                    r: c.r as u32,
+                    g: c.g as u32,
+                    b: c.b as u32,
+                })),
+            }),
+        }
+    }
+
+    /// The cursor a client should draw for `state`. An unspecified shape is a block;
+    /// a color other than RGB is left to the client's terminal.
+    pub fn decode_cursor(state: &CursorState) -> Cursor {
+        let color = match state.color.as_ref().and_then(|c| c.value.as_ref()) {
+            Some(color::Value::Rgb(rgb)) => Some(CursorColor {
+                r: rgb.r.min(255) as u8,
+                g: rgb.g.min(255) as u8,
+                b: rgb.b.min(255) as u8,
+            }),
+            _ => None,
+        };
+        Cursor {
+            row: state.row,
+            col: state.col,
+            visible: state.visible,
+            blink: state.blink,
+            shape: match state.shape() {
+                ProtoCursorShape::Underline => CursorShape::Underline,
+                ProtoCursorShape::Beam => CursorShape::Bar,
+                ProtoCursorShape::Block | ProtoCursorShape::Unspecified => CursorShape::Block,
+            },
+            color,
         }
     }
 }
@@ -0,0 +1,109 @@
+//! Classifying row patches as local echo of the controller's input.
+//!
+//! The server writes the controller's keystrokes to the PTY and later sees the
+//! shell echo them back. Patches that only contain recently typed text are flagged
+//! with `RowPatch.echo`, so a client's `PredictionEngine` can tell the echo it
+//! predicted apart from output that really conflicts with its overlay.
+
+use std::collections::VecDeque;
+
+use zellij_remote_protocol::{input_event, key_event, CellRun, InputEvent, ScreenDelta};
+
+/// Typed characters remembered for matching; enough for a fast typist's burst
+/// between two frames, small enough that unrelated output rarely matches.
+const MAX_RECENT_CHARS: usize = 256;
+
+const MODIFIER_ALT: u32 = 2;
+const MODIFIER_CTRL: u32 = 4;
+const MODIFIER_SUPER: u32 = 8;
+
+/// Printable text the controller typed recently, with the input seq of each char.
+#[derive(Debug, Default)]
+pub struct RecentInput {
+    chars: VecDeque<(u64, u32)>,
+}
+
+impl RecentInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the printable text `event` writes to the PTY, if any.
+    pub fn record(&mut self, event: &InputEvent) {
+        for ch in typed_text(event) {
+            if self.chars.len() == MAX_RECENT_CHARS {
+                self.chars.pop_front();
+            }
+            self.chars.push_back((event.input_seq, ch as u32));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.chars.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// Flag the patches of `delta` on `cursor_row` whose runs are all text typed at
+    /// or before `watermark`.
+    pub fn mark_echo(&self, delta: &mut ScreenDelta, cursor_row: u32, watermark: u64) {
+        let typed: Vec<u32> = self
+            .chars
+            .iter()
+            .filter(|(seq, _)| *seq <= watermark)
+            .map(|(_, ch)| *ch)
+            .collect();
+        if typed.is_empty() {
+            return;
+        }
+        for patch in delta.row_patches.iter_mut() {
+            patch.echo = patch.row == cursor_row
+                && !patch.runs.is_empty()
+                && patch.runs.iter().all(|run| is_typed(run, &typed));
+        }
+    }
+}
+
+/// Whether the visible text of `run` appears, contiguously, in `typed`.
+fn is_typed(run: &CellRun, typed: &[u32]) -> bool {
+    // Wide characters are followed by a zero-width continuation cell
+    let text: Vec<u32> = run
+        .codepoints
+        .iter()
+        .zip(run.widths.iter())
+        .filter(|(_, width)| **width > 0)
+        .map(|(codepoint, _)| *codepoint)
+        .collect();
+    !text.is_empty() && typed.windows(text.len()).any(|window| window == text)
+}
+
+fn typed_text(event: &InputEvent) -> Vec<char> {
+    match &event.payload {
+        Some(input_event::Payload::TextUtf8(bytes)) => String::from_utf8_lossy(bytes)
+            .chars()
+            .filter(|ch| !ch.is_control())
+            .collect(),
+        Some(input_event::Payload::Key(key)) => {
+            let bits = key.modifiers.as_ref().map_or(0, |m| m.bits);
+            if bits & (MODIFIER_ALT | MODIFIER_CTRL | MODIFIER_SUPER) != 0 {
+                return vec![];
+            }
+            match key.key {
+                Some(key_event::Key::UnicodeScalar(scalar)) => char::from_u32(scalar)
+                    .filter(|ch| !ch.is_control())
+                    .into_iter()
+                    .collect(),
+                _ => vec![],
+            }
+        },
+        // Escape sequences move the cursor or edit the line rather than print
+        Some(input_event::Payload::RawBytes(bytes)) if !bytes.contains(&0x1b) => bytes
+            .iter()
+            .filter(|b| (b' '..=b'~').contains(b))
+            .map(|b| *b as char)
+            .collect(),
+        _ => vec![],
+    }
+}
@@ -0,0 +1,97 @@
+//! Client-side failover between the paths a server can be reached on.
+//!
+//! The server lists its other addresses in `ServerHello.alternate_endpoints` (e.g. a LAN
+//! address and a tailnet address). When the current path dies the client reconnects to
+//! the next one with its resume token, so switching from Wi-Fi to VPN keeps the session.
+
+use zellij_remote_protocol::Endpoint;
+
+/// Parse `host:port`, with IPv6 literals in brackets (`[::1]:4433`).
+pub fn parse_endpoint(s: &str) -> Option<Endpoint> {
+    let (host, port) = s.trim().rsplit_once(':')?;
+    let host = match host.strip_prefix('[') {
+        Some(bracketed) => bracketed.strip_suffix(']')?,
+        None if host.contains(':') => return None,
+        None => host,
+    };
+    let port: u16 = port.parse().ok()?;
+    if host.is_empty() || port == 0 {
+        return None;
+    }
+    Some(Endpoint {
+        host: host.to_string(),
+        port: port as u32,
+    })
+}
+
+/// Format an endpoint as `host:port`, bracketing IPv6 literals.
+pub fn format_endpoint(endpoint: &Endpoint) -> String {
+    if endpoint.host.contains(':') {
+        format!("[{}]:{}", endpoint.host, endpoint.port)
+    } else {
+        format!("{}:{}", endpoint.host, endpoint.port)
+    }
+}
+
+/// The endpoints a client may connect to, primary first, and which one it's on.
+#[derive(Debug, Clone)]
+pub struct EndpointFailover {
+    endpoints: Vec<Endpoint>,
+    current: usize,
+    /// Endpoints that failed since the last successful connection
+    failed: usize,
+}
+
+impl EndpointFailover {
+    pub fn new(primary: Endpoint) -> Self {
+        Self {
+            endpoints: vec![primary],
+            current: 0,
+            failed: 0,
+        }
+    }
+
+    /// Replace the alternates with those from the latest `ServerHello`. The primary
+    /// stays first and the client stays on its current endpoint.
+    pub fn set_alternates(&mut self, alternates: &[Endpoint]) {
+        let current = self.current().clone();
+        self.endpoints.truncate(1);
+        for endpoint in alternates {
+            if endpoint.port != 0 && !self.endpoints.contains(endpoint) {
+                self.endpoints.push(endpoint.clone());
+            }
+        }
+        // An alternate the server no longer lists sends the client back to the primary
+        self.current = self
+            .endpoints
+            .iter()
+            .position(|e| *e == current)
+            .unwrap_or(0);
+    }
+
+    pub fn current(&self) -> &Endpoint {
+        &self.endpoints[self.current]
+    }
+
+    pub fn alternates(&self) -> &[Endpoint] {
+        &self.endpoints[1..]
+    }
+
+    pub fn record_connected(&mut self) {
+        self.failed = 0;
+    }
+
+    /// Move to the next endpoint after the current path died. Returns false once every
+    /// endpoint has failed since the last successful connection; the client is then
+    /// back on the primary and should back off before retrying.
+    pub fn fail_over(&mut self) -> bool {
+        self.failed += 1;
+        if self.failed >= self.endpoints.len() {
+            self.failed = 0;
+            self.current = 0;
+            return false;
+        }
+        self.current = (self.current + 1) % self.endpoints.len();
+        true
+    }
+}
@@ -0,0 +1,218 @@
+//! Holding back content that flashes, for clients whose users could be harmed by it.
+//!
+//! Some applications blink a region ten or more times a second. A row that keeps
+//! flipping back to content it had a moment ago more than
+//! [`FLASH_GUARD_MAX_FLASHES_PER_SECOND`] times a second is held: the client keeps the
+//! content it last had, brought up to date at most once per
+//! [`FLASH_GUARD_MIN_UPDATE_INTERVAL_MS`], until the row settles and goes out as it is.
+//! The guard is on for every client that doesn't set `FlashGuardSettings.disabled` in
+//! its `ClientHello`.
+//!
+//! Held rows are put in place of the current ones before a frame is diffed, so deltas,
+//! fingerprints and hashes all describe what the client really shows.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::frame::{FrameData, FrameFingerprint, Row, RowFingerprint};
+use zellij_remote_protocol::FlashGuardSettings;
+
+/// Flips per second above which a row counts as flashing; three is the usual limit
+/// for photosensitive seizures
+pub const FLASH_GUARD_MAX_FLASHES_PER_SECOND: u32 = 3;
+/// Least time between two changes of a held row
+pub const FLASH_GUARD_MIN_UPDATE_INTERVAL_MS: u64 = 1_000;
+/// How far back a row's flips are counted
+const FLASH_WINDOW_MS: u64 = 1_000;
+/// Distinct contents remembered per row; changing back to one of them is a flip
+const RECENT_CONTENTS: usize = 4;
+
+/// Rows a frame holds back, by index, with the content the client keeps for each
+pub type HeldRows = BTreeMap<usize, Row>;
+
+#[derive(Debug, Clone)]
+struct Held {
+    row: Row,
+    fingerprint: RowFingerprint,
+    since_ms: u64,
+}
+
+/// The recent changes of one row, as prepared for the client
+#[derive(Debug, Clone)]
+struct RowHistory {
+    row: Row,
+    fingerprint: RowFingerprint,
+    /// Hashes of the row's last few contents, newest last
+    recent: VecDeque<u64>,
+    /// When the row changed back to a recent content, within `FLASH_WINDOW_MS`
+    flips: VecDeque<u64>,
+    held: Option<Held>,
+}
+
+impl RowHistory {
+    fn new(row: &Row, fingerprint: RowFingerprint) -> Self {
+        Self {
+            row: row.clone(),
+            fingerprint,
+            recent: VecDeque::from([fingerprint.hash]),
+            flips: VecDeque::new(),
+            held: None,
+        }
+    }
+
+    fn flips_since(&self, now_ms: u64) -> usize {
+        self.flips
+            .iter()
+            .filter(|&&at| now_ms.saturating_sub(at) < FLASH_WINDOW_MS)
+            .count()
+    }
+
+    fn observe(&mut self, row: &Row, fingerprint: RowFingerprint, now_ms: u64, limits: Limits) {
+        while self
+            .flips
+            .front()
+            .is_some_and(|&at| now_ms.saturating_sub(at) >= FLASH_WINDOW_MS)
+        {
+            self.flips.pop_front();
+        }
+        if fingerprint != self.fingerprint {
+            if self.recent.contains(&fingerprint.hash) {
+                self.flips.push_back(now_ms);
+            }
+            self.recent.retain(|hash| *hash != fingerprint.hash);
+            self.recent.push_back(fingerprint.hash);
+            if self.recent.len() > RECENT_CONTENTS {
+                self.recent.pop_front();
+            }
+            let shown = std::mem::replace(&mut self.row, row.clone());
+            let shown_fingerprint = std::mem::replace(&mut self.fingerprint, fingerprint);
+            if self.held.is_none() && self.flips.len() > limits.max_flips {
+                // The client keeps what it had before this flip
+                self.held = Some(Held {
+                    row: shown,
+                    fingerprint: shown_fingerprint,
+                    since_ms: now_ms,
+                });
+            }
+        }
+        if let Some(held) = &mut self.held {
+            if self.flips.len() <= limits.max_flips {
+                self.held = None;
+            } else if now_ms.saturating_sub(held.since_ms) >= limits.min_update_interval_ms {
+                held.row = self.row.clone();
+                held.fingerprint = self.fingerprint;
+                held.since_ms = now_ms;
+            }
+        }
+    }
+
+    fn release_due(&self, now_ms: u64, limits: Limits) -> bool {
+        self.held.as_ref().is_some_and(|held| {
+            held.fingerprint != self.fingerprint
+                && (self.flips_since(now_ms) <= limits.max_flips
+                    || now_ms.saturating_sub(held.since_ms) >= limits.min_update_interval_ms)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    max_flips: usize,
+    min_update_interval_ms: u64,
+}
+
+/// Per-client flash detection, fed every frame prepared for the client. Off until
+/// [`Self::configure`]d.
+#[derive(Debug)]
+pub struct FlashGuard {
+    enabled: bool,
+    limits: Limits,
+    cols: usize,
+    rows: Vec<RowHistory>,
+}
+
+impl Default for FlashGuard {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            limits: Limits {
+                max_flips: FLASH_GUARD_MAX_FLASHES_PER_SECOND as usize,
+                min_update_interval_ms: FLASH_GUARD_MIN_UPDATE_INTERVAL_MS,
+            },
+            cols: 0,
+            rows: Vec::new(),
+        }
+    }
+}
+
+impl FlashGuard {
+    /// Apply the settings from a client's `ClientHello`: on unless `disabled`, with 0
+    /// limits meaning the defaults. Turning it off releases every held row.
+    pub fn configure(&mut self, settings: &FlashGuardSettings) {
+        self.enabled = !settings.disabled;
+        let max_flashes = match settings.max_flashes_per_second {
+            0 => FLASH_GUARD_MAX_FLASHES_PER_SECOND,
+            max => max,
+        };
+        self.limits = Limits {
+            max_flips: max_flashes as usize,
+            min_update_interval_ms: match settings.min_update_interval_ms {
+                0 => FLASH_GUARD_MIN_UPDATE_INTERVAL_MS,
+                interval => u64::from(interval),
+            },
+        };
+        if !self.enabled {
+            self.rows.clear();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether any row is being held back
+    pub fn is_holding(&self) -> bool {
+        self.rows.iter().any(|row| row.held.is_some())
+    }
+
+    /// Look at a frame about to be prepared for the client and hold back its flashing
+    /// rows: each one is replaced, in `frame` and `fingerprint`, by the content the
+    /// client keeps. Returns the rows held. A resize starts the history over.
+    pub fn hold(
+        &mut self,
+        frame: &mut FrameData,
+        fingerprint: &mut FrameFingerprint,
+        now_ms: u64,
+    ) -> HeldRows {
+        let mut held_rows = HeldRows::new();
+        if !self.enabled {
+            return held_rows;
+        }
+        if self.cols != frame.cols || self.rows.len() != frame.rows.len() {
+            self.cols = frame.cols;
+            self.rows = frame
+                .rows
+                .iter()
+                .zip(&fingerprint.rows)
+                .map(|(row, fingerprint)| RowHistory::new(row, *fingerprint))
+                .collect();
+            return held_rows;
+        }
+        for (idx, history) in self.rows.iter_mut().enumerate() {
+            history.observe(&frame.rows[idx], fingerprint.rows[idx], now_ms, self.limits);
+            if let Some(held) = &history.held {
+                frame.rows[idx] = held.row.clone();
+                fingerprint.rows[idx] = held.fingerprint;
+                held_rows.insert(idx, held.row.clone());
+            }
+        }
+        held_rows
+    }
+
+    /// Whether a held row is due to change on the client, as it has settled or been
+    /// held for the update interval. It needs a frame even if the screen doesn't change.
+    pub fn release_due(&self, now_ms: u64) -> bool {
+        self.rows
+            .iter()
+            .any(|row| row.release_due(now_ms, self.limits))
+    }
+}
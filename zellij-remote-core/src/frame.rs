@@ -1,11 +1,13 @@
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Cell {
     pub codepoint: u32,
     pub width: u8,
-    pub style_id: u16,
+    pub style_id: u32,
 }
 
 impl Default for Cell {
@@ -18,15 +20,27 @@ impl Default for Cell {
     }
 }
 
+/// DEC line size (DECSWL/DECDWL/DECDHL); applies to every cell of a row.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LineSize {
+    #[default]
+    Single,
+    DoubleWidth,
+    DoubleHeightTop,
+    DoubleHeightBottom,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct RowData {
     pub cells: Vec<Cell>,
+    pub line_size: LineSize,
 }
 
 impl RowData {
     pub fn new(cols: usize) -> Self {
         Self {
             cells: vec![Cell::default(); cols],
+            line_size: LineSize::Single,
         }
     }
 }
@@ -57,6 +71,16 @@ impl Row {
     pub fn cols(&self) -> usize {
         self.0.cells.len()
     }
+
+    pub fn line_size(&self) -> LineSize {
+        self.0.line_size
+    }
+
+    pub fn set_line_size(&mut self, line_size: LineSize) {
+        if self.0.line_size != line_size {
+            Arc::make_mut(&mut self.0).line_size = line_size;
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -66,6 +90,15 @@ pub struct Cursor {
     pub visible: bool,
     pub blink: bool,
     pub shape: CursorShape,
+    /// Set by the program (OSC 12); `None` leaves it to the client's terminal
+    pub color: Option<CursorColor>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -75,6 +108,17 @@ pub enum CursorShape {
     Bar = 2,
 }
 
+impl Cursor {
+    /// Whether `other` draws the same cursor, blink aside. Terminals toggle blink
+    /// on their own, so a change to it alone isn't worth a new state.
+    pub fn same_except_blink(&self, other: &Cursor) -> bool {
+        Cursor {
+            blink: other.blink,
+            ..*self
+        } == *other
+    }
+}
+
 impl Default for Cursor {
     fn default() -> Self {
         Self {
@@ -83,6 +127,7 @@ impl Default for Cursor {
             visible: true,
             blink: true,
             shape: CursorShape::Block,
+            color: None,
         }
     }
 }
@@ -92,6 +137,8 @@ pub struct FrameData {
     pub rows: Vec<Row>,
     pub cols: usize,
     pub cursor: Cursor,
+    /// The terminal behind the cursor doesn't echo input (e.g. a password prompt)
+    pub echo_suppressed: bool,
 }
 
 impl FrameData {
@@ -100,6 +147,45 @@ impl FrameData {
             rows: (0..rows).map(|_| Row::new(cols)).collect(),
             cols,
             cursor: Cursor::default(),
+            echo_suppressed: false,
+        }
+    }
+}
+
+/// What a client's baseline needs to remember about a row: a 64-bit hash of its cells
+/// and its line size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowFingerprint {
+    pub hash: u64,
+    pub line_size: LineSize,
+}
+
+impl RowFingerprint {
+    pub fn of(row: &Row) -> Self {
+        let mut hasher = DefaultHasher::new();
+        row.0.cells.hash(&mut hasher);
+        Self {
+            hash: hasher.finish(),
+            line_size: row.line_size(),
+        }
+    }
+}
+
+/// A frame reduced to one fingerprint per row plus the cursor, so baselines can be
+/// kept per client without holding on to every client's copy of the cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameFingerprint {
+    pub rows: Vec<RowFingerprint>,
+    pub cols: usize,
+    pub cursor: Cursor,
+}
+
+impl FrameFingerprint {
+    pub fn of(frame: &FrameData) -> Self {
+        Self {
+            rows: frame.rows.iter().map(RowFingerprint::of).collect(),
+            cols: frame.cols,
+            cursor: frame.cursor,
         }
     }
 }
@@ -155,10 +241,20 @@ impl FrameStore {
         self.current.cursor = cursor;
     }
 
+    pub fn set_echo_suppressed(&mut self, echo_suppressed: bool) {
+        self.current.echo_suppressed = echo_suppressed;
+    }
+
     pub fn advance_state(&mut self) {
         self.state_id += 1;
     }
 
+    /// Number states after `state_id` from now on, e.g. one reached before a restart,
+    /// so no id a client already holds is reused for a different screen.
+    pub fn continue_after_state(&mut self, state_id: u64) {
+        self.state_id = self.state_id.max(state_id.saturating_add(1));
+    }
+
     pub fn take_dirty_rows(&mut self) -> HashSet<usize> {
         std::mem::take(&mut self.dirty_rows)
     }
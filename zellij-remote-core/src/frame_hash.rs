@@ -0,0 +1,161 @@
+//! Frame hashes for integrity mode.
+//!
+//! A client that sets `Capabilities.supports_frame_hash` gets `frame_hash` on every
+//! snapshot and delta: a hash of the screen it should hold once it has applied that
+//! message. It hashes its own screen after applying and, on a mismatch, asks for a
+//! snapshot with `REASON_DECODE_ERROR`; the server counts those per client. Frames sent
+//! in text mode carry no hash, since their styles are stripped.
+//!
+//! The hash is 64-bit FNV-1a over little-endian `u32`s, so clients in any language can
+//! compute it: cols and rows, then for each row its `LineSize` (single spelled out as
+//! `LINE_SIZE_SINGLE`) followed by each cell's codepoint, width and style id, then the
+//! cursor's row, col and visible flag (0 or 1). Styles themselves aren't hashed; the
+//! style ids stand in for them.
+//!
+//! Row checksums (`Capabilities.supports_row_checksums`) narrow this down to the rows a
+//! delta touches, for clients that mostly get deltas as datagrams: each `RowPatch`
+//! carries `row_crc32`, the CRC-32 (IEEE, as in zlib) of the same bytes the frame hash
+//! feeds for that row, its line size and then its cells. A client that finds a patched
+//! row differing asks for a snapshot with `REASON_BASE_MISMATCH`.
+
+use crate::delta::DeltaEngine;
+use crate::frame::{FrameData, Row};
+use zellij_remote_protocol::{LineSize as ProtoLineSize, ScreenDelta};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Feeds a screen to the hash in the order the module docs describe.
+#[derive(Debug, Clone)]
+pub struct FrameHasher {
+    hash: u64,
+}
+
+impl FrameHasher {
+    pub fn new(cols: u32, rows: u32) -> Self {
+        let mut hasher = Self {
+            hash: FNV_OFFSET_BASIS,
+        };
+        hasher.write_u32(cols);
+        hasher.write_u32(rows);
+        hasher
+    }
+
+    /// Start the next row; `line_size` is a raw protocol `LineSize`.
+    pub fn row(&mut self, line_size: i32) {
+        let line_size = if line_size == ProtoLineSize::Unspecified as i32 {
+            ProtoLineSize::Single as i32
+        } else {
+            line_size
+        };
+        self.write_u32(line_size as u32);
+    }
+
+    pub fn cell(&mut self, codepoint: u32, width: u32, style_id: u32) {
+        self.write_u32(codepoint);
+        self.write_u32(width);
+        self.write_u32(style_id);
+    }
+
+    pub fn finish(mut self, cursor_row: u32, cursor_col: u32, cursor_visible: bool) -> u64 {
+        self.write_u32(cursor_row);
+        self.write_u32(cursor_col);
+        self.write_u32(cursor_visible as u32);
+        self.hash
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        for byte in value.to_le_bytes() {
+            self.hash ^= byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+/// The hash a client holding `frame` must arrive at.
+pub fn hash_frame(frame: &FrameData) -> u64 {
+    let mut hasher = FrameHasher::new(frame.cols as u32, frame.rows.len() as u32);
+    for row in &frame.rows {
+        hasher.row(DeltaEngine::encode_line_size(row.line_size()) as i32);
+        for cell in &row.0.cells {
+            hasher.cell(cell.codepoint, cell.width as u32, cell.style_id);
+        }
+    }
+    hasher.finish(frame.cursor.row, frame.cursor.col, frame.cursor.visible)
+}
+
+/// Feeds one row to its CRC-32 in the order the module docs describe.
+#[derive(Debug, Clone)]
+pub struct RowChecksum {
+    crc: u32,
+}
+
+impl RowChecksum {
+    /// `line_size` is a raw protocol `LineSize`.
+    pub fn new(line_size: i32) -> Self {
+        let line_size = if line_size == ProtoLineSize::Unspecified as i32 {
+            ProtoLineSize::Single as i32
+        } else {
+            line_size
+        };
+        let mut checksum = Self { crc: !0 };
+        checksum.write_u32(line_size as u32);
+        checksum
+    }
+
+    pub fn cell(&mut self, codepoint: u32, width: u32, style_id: u32) {
+        self.write_u32(codepoint);
+        self.write_u32(width);
+        self.write_u32(style_id);
+    }
+
+    pub fn finish(self) -> u32 {
+        !self.crc
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        for byte in value.to_le_bytes() {
+            self.crc = CRC32_TABLE[((self.crc ^ byte as u32) & 0xff) as usize] ^ (self.crc >> 8);
+        }
+    }
+}
+
+/// The `row_crc32` of `row`.
+pub fn row_crc32(row: &Row) -> u32 {
+    let mut checksum = RowChecksum::new(DeltaEngine::encode_line_size(row.line_size()) as i32);
+    for cell in &row.0.cells {
+        checksum.cell(cell.codepoint, cell.width as u32, cell.style_id);
+    }
+    checksum.finish()
+}
+
+/// Put the checksum of each patched row of `frame` on `delta`'s patches.
+pub fn stamp_row_checksums(delta: &mut ScreenDelta, frame: &FrameData) {
+    for patch in delta.row_patches.iter_mut() {
+        if let Some(row) = frame.rows.get(patch.row as usize) {
+            patch.row_crc32 = row_crc32(row);
+        }
+    }
+}
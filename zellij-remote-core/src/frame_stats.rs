@@ -0,0 +1,137 @@
+//! Client-side frame timing, fed from the `server_time_ms` and `frame_sequence`
+//! stamped on every `ScreenSnapshot`/`ScreenDelta`.
+
+const INTERVAL_ALPHA: f64 = 0.125;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameArrival {
+    /// The next frame in sequence (or the first one seen on this connection)
+    InOrder,
+    /// Frames were skipped, e.g. lost datagrams
+    Gap { missed: u64 },
+    /// Older than a frame already seen (a reordered datagram)
+    Stale,
+    /// The frame carries no sequence number (server predates frame timestamps)
+    Untracked,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    frames_received: u64,
+    frames_missed: u64,
+    frames_stale: u64,
+    last_sequence: u64,
+    last_arrival_ms: Option<u64>,
+    last_transit_ms: Option<i64>,
+    min_transit_ms: Option<i64>,
+    interval_ms: Option<f64>,
+    max_interval_ms: u64,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a frame arriving at local wall-clock time `now_ms` (Unix ms).
+    pub fn record_frame(
+        &mut self,
+        frame_sequence: u64,
+        server_time_ms: u64,
+        now_ms: u64,
+    ) -> FrameArrival {
+        if frame_sequence == 0 {
+            return FrameArrival::Untracked;
+        }
+        if frame_sequence <= self.last_sequence {
+            self.frames_stale += 1;
+            return FrameArrival::Stale;
+        }
+
+        let arrival = if self.last_sequence == 0 || frame_sequence == self.last_sequence + 1 {
+            FrameArrival::InOrder
+        } else {
+            let missed = frame_sequence - self.last_sequence - 1;
+            self.frames_missed += missed;
+            FrameArrival::Gap { missed }
+        };
+        self.last_sequence = frame_sequence;
+        self.frames_received += 1;
+
+        if let Some(previous) = self.last_arrival_ms {
+            let interval = now_ms.saturating_sub(previous);
+            self.max_interval_ms = self.max_interval_ms.max(interval);
+            self.interval_ms = Some(match self.interval_ms {
+                Some(avg) => avg + INTERVAL_ALPHA * (interval as f64 - avg),
+                None => interval as f64,
+            });
+        }
+        self.last_arrival_ms = Some(now_ms);
+
+        if server_time_ms > 0 {
+            let transit = now_ms as i64 - server_time_ms as i64;
+            self.last_transit_ms = Some(transit);
+            self.min_transit_ms = Some(self.min_transit_ms.map_or(transit, |m| m.min(transit)));
+        }
+
+        arrival
+    }
+
+    /// Forget per-connection state after reconnecting: the server restarts its
+    /// sequence and the path (and so the minimum transit time) may have changed.
+    /// Cumulative counters are kept.
+    pub fn reset_connection(&mut self) {
+        self.last_sequence = 0;
+        self.last_arrival_ms = None;
+        self.last_transit_ms = None;
+        self.min_transit_ms = None;
+    }
+
+    pub fn frames_received(&self) -> u64 {
+        self.frames_received
+    }
+
+    pub fn frames_missed(&self) -> u64 {
+        self.frames_missed
+    }
+
+    pub fn frames_stale(&self) -> u64 {
+        self.frames_stale
+    }
+
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+
+    /// Server-to-client latency of the last frame. Only meaningful when both clocks
+    /// are synchronized; with skewed clocks it can even be negative.
+    pub fn latency_ms(&self) -> Option<i64> {
+        self.last_transit_ms
+    }
+
+    /// How much later the last frame arrived than the fastest frame seen. Clock
+    /// offset cancels out, so this exposes queueing even with unsynchronized clocks.
+    pub fn queuing_delay_ms(&self) -> Option<u64> {
+        match (self.last_transit_ms, self.min_transit_ms) {
+            (Some(last), Some(min)) => Some((last - min) as u64),
+            _ => None,
+        }
+    }
+
+    /// Smoothed time between frames, e.g. to pace cursor interpolation.
+    pub fn frame_interval_ms(&self) -> Option<f64> {
+        self.interval_ms
+    }
+
+    pub fn max_frame_interval_ms(&self) -> u64 {
+        self.max_interval_ms
+    }
+
+    /// Whether no frame has arrived for `threshold_ms`. Servers only send frames when
+    /// the screen changes, so treat this as a stall only while output is expected
+    /// (e.g. input is awaiting its echo).
+    pub fn is_stalled(&self, now_ms: u64, threshold_ms: u64) -> bool {
+        self.last_arrival_ms
+            .is_some_and(|last| now_ms.saturating_sub(last) >= threshold_ms)
+    }
+}
@@ -1,46 +1,142 @@
-use std::collections::VecDeque;
-use zellij_remote_protocol::{InputAck, InputEvent};
+use std::collections::{BTreeMap, VecDeque};
+use zellij_remote_protocol::{InputAck, InputBatch, InputEvent};
 
-#[cfg(not(test))]
 use std::time::Instant;
 
-#[cfg(test)]
-use crate::lease::Instant;
+use crate::clock::{SharedClock, SystemClock};
+
+/// Most events [`InputSender`] puts in one batch
+pub const MAX_INPUT_BATCH_EVENTS: usize = 64;
+
+pub const DEFAULT_INPUT_REORDER_EVENTS: u64 = 16;
+pub const DEFAULT_INPUT_REORDER_HOLD_MS: u64 = 250;
+/// Widest reorder window a receiver accepts, bounding how many inputs it holds
+pub const MAX_INPUT_REORDER_EVENTS: u64 = 1024;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum InputProcessResult {
     Processed,
+    /// Arrived early, within the reorder window: kept until the inputs before it do
+    Held,
     Duplicate,
-    OutOfOrder { expected: u64, received: u64 },
+    OutOfOrder {
+        expected: u64,
+        received: u64,
+    },
+}
+
+/// How far ahead of the next expected seq an input may arrive and still be kept, and
+/// for how long. Held inputs are released in order once the gap before them fills;
+/// those still waiting after `hold_ms` are dropped, left for the client to resend.
+/// `max_events` of 0 rejects every gap as out of order; receivers cap it at
+/// [`MAX_INPUT_REORDER_EVENTS`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReorderWindow {
+    pub max_events: u64,
+    pub hold_ms: u64,
+}
+
+impl ReorderWindow {
+    pub const fn disabled() -> Self {
+        Self {
+            max_events: 0,
+            hold_ms: 0,
+        }
+    }
+}
+
+impl Default for ReorderWindow {
+    fn default() -> Self {
+        Self {
+            max_events: DEFAULT_INPUT_REORDER_EVENTS,
+            hold_ms: DEFAULT_INPUT_REORDER_HOLD_MS,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HeldInput {
+    event: InputEvent,
+    /// Echoed in the ack if this input ends up last in a delivered run
+    client_time_ms: u32,
+    held_at: Instant,
 }
 
+/// Applies a client's inputs once each, in seq order.
+///
+/// Duplicate detection needs no per-input history: everything at or below the
+/// watermark (`last_processed_seq`) has been applied, and only the inputs held in the
+/// reorder window above it are tracked, at most `max_events` of them. The state is
+/// fixed-size however long the session runs. The trade-off is that an input arriving
+/// further ahead than the window is refused rather than remembered, and the client
+/// resends it. Seqs don't wrap: once the watermark reaches `u64::MAX` every input
+/// is a duplicate, so a client whose counter wrapped can never replay old inputs and
+/// has to attach afresh.
 #[derive(Debug)]
 pub struct InputReceiver {
     last_processed_seq: u64,
     pending_rtt_sample: Option<(u64, u32)>,
+    reorder_window: ReorderWindow,
+    held: BTreeMap<u64, HeldInput>,
+    /// Held inputs the last `process_input` released, in order
+    released: Vec<InputEvent>,
+    clock: SharedClock,
 }
 
 impl InputReceiver {
     pub fn new() -> Self {
-        Self {
-            last_processed_seq: 0,
-            pending_rtt_sample: None,
-        }
+        Self::new_from_seq(0)
     }
 
     pub fn new_from_seq(last_acked_seq: u64) -> Self {
         Self {
             last_processed_seq: last_acked_seq,
             pending_rtt_sample: None,
+            reorder_window: ReorderWindow::disabled(),
+            held: BTreeMap::new(),
+            released: Vec::new(),
+            clock: SystemClock::shared(),
         }
     }
 
+    /// Time how long inputs are held by `clock`
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_reorder_window(mut self, window: ReorderWindow) -> Self {
+        self.set_reorder_window(window);
+        self
+    }
+
+    /// Narrowing the window drops held inputs that no longer fit in it
+    pub fn set_reorder_window(&mut self, window: ReorderWindow) {
+        self.reorder_window = ReorderWindow {
+            max_events: window.max_events.min(MAX_INPUT_REORDER_EVENTS),
+            ..window
+        };
+        let limit = self
+            .last_processed_seq
+            .saturating_add(self.reorder_window.max_events);
+        self.held.retain(|seq, _| *seq <= limit);
+    }
+
+    pub fn reorder_window(&self) -> ReorderWindow {
+        self.reorder_window
+    }
+
+    /// The seq that applies next; stuck at `u64::MAX` once that has been applied
+    fn expected_seq(&self) -> u64 {
+        self.last_processed_seq.saturating_add(1)
+    }
+
     pub fn process_input(&mut self, input: &InputEvent) -> InputProcessResult {
         let seq = input.input_seq;
 
         if seq == 0 {
             return InputProcessResult::OutOfOrder {
-                expected: self.last_processed_seq + 1,
+                expected: self.expected_seq(),
                 received: seq,
             };
         }
@@ -49,20 +145,132 @@ impl InputReceiver {
             return InputProcessResult::Duplicate;
         }
 
-        let expected = self.last_processed_seq + 1;
-        if seq != expected {
-            return InputProcessResult::OutOfOrder {
-                expected,
-                received: seq,
-            };
+        if seq != self.expected_seq() {
+            return self.hold(std::slice::from_ref(input), input.client_time_ms);
         }
 
         self.last_processed_seq = seq;
         self.pending_rtt_sample = Some((seq, input.client_time_ms));
+        self.released = self.release_held();
 
         InputProcessResult::Processed
     }
 
+    /// Held inputs released by the last `process_input` that returned `Processed`, to
+    /// apply after it
+    pub fn take_released(&mut self) -> Vec<InputEvent> {
+        std::mem::take(&mut self.released)
+    }
+
+    /// Process a batch atomically: either all of its new events are accepted or none
+    /// are. Events already processed (a resent batch overlapping an acked one) are
+    /// skipped; on success returns the events to apply, followed by any held inputs
+    /// the batch closed the gap to. The next ack covers all of them, with the
+    /// `client_time_ms` the last one came with as its RTT sample. A batch that starts
+    /// early but ends within the reorder window is held whole.
+    pub fn process_batch(
+        &mut self,
+        batch: &InputBatch,
+    ) -> Result<Vec<InputEvent>, InputProcessResult> {
+        let expected = self.expected_seq();
+        let Some(first) = batch.events.first() else {
+            return Err(InputProcessResult::Duplicate);
+        };
+        for (offset, event) in batch.events.iter().enumerate() {
+            let consecutive = first.input_seq.checked_add(offset as u64);
+            if first.input_seq == 0 || consecutive != Some(event.input_seq) {
+                return Err(InputProcessResult::OutOfOrder {
+                    expected: consecutive.unwrap_or(expected),
+                    received: event.input_seq,
+                });
+            }
+        }
+
+        let skip = self.last_processed_seq.saturating_sub(first.input_seq - 1);
+        let new_events = batch.events.get(skip as usize..).unwrap_or_default();
+        let Some(last) = new_events.last() else {
+            return Err(InputProcessResult::Duplicate);
+        };
+        if new_events[0].input_seq != expected {
+            return Err(self.hold(new_events, batch.client_time_ms));
+        }
+
+        self.last_processed_seq = last.input_seq;
+        self.pending_rtt_sample = Some((last.input_seq, batch.client_time_ms));
+        let mut events = new_events.to_vec();
+        events.extend(self.release_held());
+        Ok(events)
+    }
+
+    /// Keep `events`, consecutive and starting past the next expected seq, until the
+    /// gap before them fills, if they all fit in the reorder window
+    fn hold(&mut self, events: &[InputEvent], client_time_ms: u32) -> InputProcessResult {
+        let expected = self.expected_seq();
+        let first = events[0].input_seq;
+        let last = events[events.len() - 1].input_seq;
+        if last - self.last_processed_seq > self.reorder_window.max_events {
+            return InputProcessResult::OutOfOrder {
+                expected,
+                received: first,
+            };
+        }
+
+        self.expire_held();
+        if events
+            .iter()
+            .all(|event| self.held.contains_key(&event.input_seq))
+        {
+            return InputProcessResult::Duplicate;
+        }
+        let held_at = self.clock.now();
+        for event in events {
+            self.held
+                .entry(event.input_seq)
+                .or_insert_with(|| HeldInput {
+                    event: event.clone(),
+                    client_time_ms,
+                    held_at,
+                });
+        }
+        InputProcessResult::Held
+    }
+
+    fn expire_held(&mut self) {
+        let hold_ms = self.reorder_window.hold_ms as u128;
+        let now = self.clock.now();
+        self.held
+            .retain(|_, held| now.saturating_duration_since(held.held_at).as_millis() < hold_ms);
+    }
+
+    /// Take the held inputs that now follow on from the last processed seq, dropping
+    /// any the last processed seq already covers
+    fn release_held(&mut self) -> Vec<InputEvent> {
+        let mut released = Vec::new();
+        if self.held.is_empty() {
+            return released;
+        }
+        self.expire_held();
+        self.held = match self.last_processed_seq.checked_add(1) {
+            Some(next) => self.held.split_off(&next),
+            None => BTreeMap::new(),
+        };
+        while let Some(held) = self
+            .last_processed_seq
+            .checked_add(1)
+            .and_then(|next| self.held.remove(&next))
+        {
+            self.last_processed_seq = held.event.input_seq;
+            self.pending_rtt_sample = Some((held.event.input_seq, held.client_time_ms));
+            released.push(held.event);
+        }
+        released
+    }
+
+    /// How many early inputs are waiting for the gap before them to fill
+    pub fn held_count(&self) -> usize {
+        self.held.len()
+    }
+
     pub fn generate_ack(&mut self) -> InputAck {
         let (rtt_sample_seq, echoed_client_time_ms) =
             self.pending_rtt_sample.take().unwrap_or((0, 0));
@@ -109,6 +317,9 @@ pub struct InputSender {
     next_seq: u64,
     inflight: VecDeque<InflightInput>,
     max_inflight: usize,
+    /// Events held back while the window is full, sent together by `take_batch`
+    queued: Vec<InputEvent>,
+    clock: SharedClock,
 }
 
 impl InputSender {
@@ -117,9 +328,17 @@ impl InputSender {
             next_seq: 1,
             inflight: VecDeque::new(),
             max_inflight,
+            queued: Vec::new(),
+            clock: SystemClock::shared(),
         }
     }
 
+    /// Time round trips by `clock`
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn can_send(&self) -> bool {
         self.inflight.len() < self.max_inflight
     }
@@ -133,7 +352,7 @@ impl InputSender {
             self.inflight.push_back(InflightInput {
                 seq,
                 client_time_ms,
-                sent_at: Instant::now(),
+                sent_at: self.clock.now(),
             });
             self.next_seq += 1;
         }
@@ -153,7 +372,7 @@ impl InputSender {
                 if input.seq == ack.rtt_sample_seq
                     && input.client_time_ms == ack.echoed_client_time_ms
                 {
-                    let elapsed = input.sent_at.elapsed();
+                    let elapsed = self.clock.now().saturating_duration_since(input.sent_at);
                     rtt_sample = Some(RttSample {
                         rtt_ms: elapsed.as_millis() as u32,
                         seq: input.seq,
@@ -171,13 +390,47 @@ impl InputSender {
         self.inflight.len()
     }
 
+    /// Hold an event back while the window is full, instead of dropping it. The event
+    /// is given the seq it will be sent with, which is returned. While anything is
+    /// queued, later events must be queued too to keep them in order.
+    pub fn queue(&mut self, mut event: InputEvent) -> u64 {
+        event.input_seq = self.next_seq + self.queued.len() as u64;
+        let seq = event.input_seq;
+        self.queued.push(event);
+        seq
+    }
+
+    pub fn queued_count(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Once the window has room, take up to [`MAX_INPUT_BATCH_EVENTS`] queued events as
+    /// one batch and mark them sent.
+    pub fn take_batch(&mut self, client_time_ms: u32) -> Option<InputBatch> {
+        if self.queued.is_empty() || !self.can_send() {
+            return None;
+        }
+        let count = self.queued.len().min(MAX_INPUT_BATCH_EVENTS);
+        let events: Vec<InputEvent> = self.queued.drain(..count).collect();
+        for event in &events {
+            self.mark_sent(event.input_seq, client_time_ms);
+        }
+        Some(InputBatch {
+            client_time_ms,
+            events,
+        })
+    }
+
     /// Returns how long (in ms) the oldest unacked input has been waiting.
     /// Returns None if no inputs are inflight.
     /// Used for stall detection: if oldest > 4×RTO, connection may be stuck.
     pub fn oldest_inflight_age_ms(&self) -> Option<u32> {
-        self.inflight
-            .front()
-            .map(|input| input.sent_at.elapsed().as_millis() as u32)
+        self.inflight.front().map(|input| {
+            self.clock
+                .now()
+                .saturating_duration_since(input.sent_at)
+                .as_millis() as u32
+        })
     }
 
     /// Adjust the maximum inflight window size (minimum 1 to prevent deadlock)
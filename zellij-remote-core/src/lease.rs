@@ -1,94 +1,9 @@
-use std::collections::HashSet;
-use zellij_remote_protocol::{ControllerLease, ControllerPolicy, DisplaySize};
+use std::collections::{HashMap, HashSet};
+use zellij_remote_protocol::{ControllerLease, ControllerPolicy, DisplaySize, LeaseStatus};
 
-#[cfg(not(test))]
 use std::time::{Duration, Instant};
 
-#[cfg(test)]
-pub use test_time::{Duration, Instant, TestClock};
-
-#[cfg(test)]
-mod test_time {
-    use std::cell::RefCell;
-
-    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
-    pub struct Instant(u64);
-
-    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-    pub struct Duration(u64);
-
-    thread_local! {
-        static CURRENT_TIME: RefCell<u64> = const { RefCell::new(0) };
-    }
-
-    impl Instant {
-        pub fn now() -> Self {
-            CURRENT_TIME.with(|t| Instant(*t.borrow()))
-        }
-
-        pub fn elapsed(&self) -> Duration {
-            let now = Self::now();
-            Duration(now.0.saturating_sub(self.0))
-        }
-
-        pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
-            Duration(self.0.saturating_sub(earlier.0))
-        }
-    }
-
-    impl Duration {
-        pub const fn from_millis(millis: u64) -> Self {
-            Duration(millis)
-        }
-
-        pub const fn from_secs(secs: u64) -> Self {
-            Duration(secs * 1000)
-        }
-
-        pub fn as_millis(&self) -> u128 {
-            self.0 as u128
-        }
-
-        pub fn saturating_sub(self, rhs: Duration) -> Duration {
-            Duration(self.0.saturating_sub(rhs.0))
-        }
-    }
-
-    impl std::ops::Add<Duration> for Instant {
-        type Output = Instant;
-        fn add(self, rhs: Duration) -> Instant {
-            Instant(self.0 + rhs.0)
-        }
-    }
-
-    impl PartialOrd<Duration> for Duration {
-        fn partial_cmp(&self, other: &Duration) -> Option<std::cmp::Ordering> {
-            Some(self.0.cmp(&other.0))
-        }
-    }
-
-    impl Ord for Duration {
-        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-            self.0.cmp(&other.0)
-        }
-    }
-
-    pub struct TestClock;
-
-    impl TestClock {
-        pub fn reset() {
-            CURRENT_TIME.with(|t| *t.borrow_mut() = 0);
-        }
-
-        pub fn advance(duration: Duration) {
-            CURRENT_TIME.with(|t| *t.borrow_mut() += duration.0);
-        }
-
-        pub fn set(millis: u64) {
-            CURRENT_TIME.with(|t| *t.borrow_mut() = millis);
-        }
-    }
-}
+use crate::clock::{SharedClock, SystemClock};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum LeaseState {
@@ -125,6 +40,96 @@ pub enum LeaseEvent {
         owner: u64,
         reason: String,
     },
+    /// `new_owner` took the lease from `owner`, now a viewer. Input `owner` already
+    /// sent is still accepted for `grace`, if set.
+    TakenOver {
+        lease_id: u64,
+        owner: u64,
+        new_owner: u64,
+        grace: Option<Duration>,
+    },
+}
+
+/// How long a controller displaced by a force takeover under `ExplicitOnly` can
+/// still have its in-flight input accepted
+pub const DEFAULT_TAKEOVER_GRACE_MS: u64 = 2_000;
+
+/// How long the controller has to answer a handoff request before it counts as denied
+pub const DEFAULT_HANDOFF_TIMEOUT_MS: u64 = 10_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandoffRequestResult {
+    /// Nobody held control (or the requester already did), so no need to ask
+    Granted(ControllerLease),
+    /// Relay the request to `controller`, who has `timeout_ms` to answer
+    Pending {
+        handoff_id: u64,
+        controller: u64,
+        timeout_ms: u32,
+    },
+    Denied {
+        reason: String,
+        current_lease: Option<ControllerLease>,
+    },
+}
+
+/// How a pending handoff ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandoffOutcome {
+    /// The lease moved to the requester; the previous owner is now a viewer
+    Approved {
+        handoff_id: u64,
+        requester: u64,
+        previous_owner: u64,
+        previous_lease_id: u64,
+        lease: ControllerLease,
+    },
+    Denied {
+        handoff_id: u64,
+        requester: u64,
+        reason: String,
+        current_lease: Option<ControllerLease>,
+    },
+}
+
+/// Control handed back to the client a time-boxed lease was borrowed from
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaseReturn {
+    /// The time-boxed lease, if it was still running and has just been ended
+    pub ended_lease_id: Option<u64>,
+    pub lease: ControllerLease,
+}
+
+#[derive(Debug, Clone)]
+struct TimeBox {
+    lease_id: u64,
+    deadline: Instant,
+}
+
+/// A controller displaced by a takeover whose in-flight input is still accepted
+#[derive(Debug, Clone)]
+struct TakeoverGrace {
+    owner: u64,
+    until: Instant,
+}
+
+/// A controller whose lease was taken by a time-boxed request, waiting to get it back
+#[derive(Debug, Clone)]
+struct SuspendedLease {
+    owner: u64,
+    size: DisplaySize,
+    /// Set if the suspended lease was itself time-boxed
+    deadline: Option<Instant>,
+}
+
+#[derive(Debug, Clone)]
+struct PendingHandoff {
+    handoff_id: u64,
+    requester: u64,
+    desired_size: DisplaySize,
+    /// The lease the controller was asked to give up
+    lease_id: u64,
+    requested_at: Instant,
 }
 
 pub struct LeaseManager {
@@ -133,6 +138,25 @@ pub struct LeaseManager {
     next_lease_id: u64,
     default_duration: Duration,
     viewers: HashSet<u64>,
+    /// Demote the controller to viewer after this long without input (None = never)
+    idle_timeout: Option<Duration>,
+    last_input_at: Option<Instant>,
+    /// At most one handoff is in flight at a time
+    pending_handoff: Option<PendingHandoff>,
+    handoff_timeout: Duration,
+    next_handoff_id: u64,
+    /// Set while the current lease is time-boxed
+    time_box: Option<TimeBox>,
+    /// Controllers displaced by time-boxed leases, most recent last
+    lease_stack: Vec<SuspendedLease>,
+    takeover_grace: Duration,
+    /// Set while a controller displaced by a force takeover is in its grace period
+    grace: Option<TakeoverGrace>,
+    /// The last takeover, until [`Self::take_takeover`] picks it up
+    takeover: Option<LeaseEvent>,
+    /// Client names, for denial reasons and `ControllerLease.owner_name`
+    names: HashMap<u64, String>,
+    clock: SharedClock,
 }
 
 impl LeaseManager {
@@ -143,6 +167,90 @@ impl LeaseManager {
             next_lease_id: 1,
             default_duration: duration,
             viewers: HashSet::new(),
+            idle_timeout: None,
+            last_input_at: None,
+            pending_handoff: None,
+            handoff_timeout: Duration::from_millis(DEFAULT_HANDOFF_TIMEOUT_MS),
+            next_handoff_id: 1,
+            time_box: None,
+            lease_stack: Vec::new(),
+            takeover_grace: Duration::from_millis(DEFAULT_TAKEOVER_GRACE_MS),
+            grace: None,
+            takeover: None,
+            names: HashMap::new(),
+            clock: SystemClock::shared(),
+        }
+    }
+
+    /// Time leases, idle timeouts, handoffs and grace periods by `clock`
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn since(&self, earlier: Instant) -> Duration {
+        self.clock.now().saturating_duration_since(earlier)
+    }
+
+    /// Name `client_id` in the leases and denials it shows up in; empty clears it.
+    /// Forgotten when the client is removed.
+    pub fn set_client_name(&mut self, client_id: u64, name: &str) {
+        if name.is_empty() {
+            self.names.remove(&client_id);
+        } else {
+            self.names.insert(client_id, name.to_string());
+        }
+    }
+
+    pub fn client_name(&self, client_id: u64) -> Option<&str> {
+        self.names.get(&client_id).map(String::as_str)
+    }
+
+    /// "name (client N)", or "client N" for a client without a name
+    fn describe_client(&self, client_id: u64) -> String {
+        match self.client_name(client_id) {
+            Some(name) => format!("{} (client {})", name, client_id),
+            None => format!("client {}", client_id),
+        }
+    }
+
+    /// How long input from a controller displaced by a force takeover under
+    /// `ExplicitOnly` is still accepted; zero turns the grace period off.
+    pub fn set_takeover_grace(&mut self, grace: Duration) {
+        self.takeover_grace = grace;
+    }
+
+    pub fn set_handoff_timeout(&mut self, timeout: Duration) {
+        self.handoff_timeout = timeout;
+    }
+
+    /// Applies to requests from now on; the current lease is kept
+    pub fn set_policy(&mut self, policy: ControllerPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn set_idle_timeout(&mut self, idle_timeout: Option<Duration>) {
+        self.idle_timeout = idle_timeout;
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// Record that the controller just sent input, resetting its idle timer. Input
+    /// also renews the lease, the way a keepalive would.
+    pub fn record_input(&mut self, client_id: u64) {
+        let now = self.clock.now();
+        if let LeaseState::Active {
+            owner_client_id,
+            granted_at,
+            ..
+        } = &mut self.state
+        {
+            if *owner_client_id == client_id {
+                *granted_at = now;
+                self.last_input_at = Some(now);
+            }
         }
     }
 
@@ -151,6 +259,19 @@ impl LeaseManager {
         client_id: u64,
         desired_size: Option<DisplaySize>,
         force: bool,
+    ) -> LeaseResult {
+        self.request_time_boxed_control(client_id, desired_size, force, None)
+    }
+
+    /// Like `request_control`, but a takeover with `max_duration` set only borrows the
+    /// lease: once it runs out (or the borrower lets go) control goes back to the
+    /// previous owner, if still connected. Time-boxed takeovers nest.
+    pub fn request_time_boxed_control(
+        &mut self,
+        client_id: u64,
+        desired_size: Option<DisplaySize>,
+        force: bool,
+        max_duration: Option<Duration>,
     ) -> LeaseResult {
         let size = desired_size.unwrap_or(DisplaySize { cols: 80, rows: 24 });
 
@@ -158,7 +279,7 @@ impl LeaseManager {
             LeaseState::NoController | LeaseState::Expired { .. } => {
                 let lease_id = self.next_lease_id;
                 self.next_lease_id += 1;
-                let now = Instant::now();
+                let now = self.clock.now();
 
                 self.state = LeaseState::Active {
                     owner_client_id: client_id,
@@ -169,6 +290,10 @@ impl LeaseManager {
                 };
 
                 self.viewers.remove(&client_id);
+                self.last_input_at = Some(now);
+                // Nobody to hand back to
+                self.time_box = None;
+                self.lease_stack.clear();
 
                 LeaseResult::Granted(self.build_lease(
                     lease_id,
@@ -189,7 +314,7 @@ impl LeaseManager {
                         *lease_id,
                         client_id,
                         current_size,
-                        duration.saturating_sub(granted_at.elapsed()),
+                        duration.saturating_sub(self.since(*granted_at)),
                     ));
                 }
 
@@ -202,7 +327,47 @@ impl LeaseManager {
                 if can_takeover {
                     let new_lease_id = self.next_lease_id;
                     self.next_lease_id += 1;
-                    let now = Instant::now();
+                    let now = self.clock.now();
+
+                    // Input the displaced controller sent before hearing about the
+                    // takeover is only worth keeping when it had to be forced
+                    let grace = (self.policy == ControllerPolicy::ExplicitOnly
+                        && force
+                        && self.takeover_grace > Duration::from_millis(0))
+                    .then_some(self.takeover_grace);
+                    self.grace = grace.map(|grace| TakeoverGrace {
+                        owner: *owner_client_id,
+                        until: now + grace,
+                    });
+                    self.takeover = Some(LeaseEvent::TakenOver {
+                        lease_id: *lease_id,
+                        owner: *owner_client_id,
+                        new_owner: client_id,
+                        grace,
+                    });
+
+                    match max_duration {
+                        Some(max_duration) => {
+                            let deadline = self
+                                .time_box
+                                .take()
+                                .filter(|time_box| time_box.lease_id == *lease_id)
+                                .map(|time_box| time_box.deadline);
+                            self.lease_stack.push(SuspendedLease {
+                                owner: *owner_client_id,
+                                size: current_size.clone(),
+                                deadline,
+                            });
+                            self.time_box = Some(TimeBox {
+                                lease_id: new_lease_id,
+                                deadline: now + max_duration,
+                            });
+                        },
+                        None => {
+                            self.time_box = None;
+                            self.lease_stack.clear();
+                        },
+                    }
 
                     self.viewers.insert(*owner_client_id);
 
@@ -215,6 +380,7 @@ impl LeaseManager {
                     };
 
                     self.viewers.remove(&client_id);
+                    self.last_input_at = Some(now);
 
                     LeaseResult::Granted(self.build_lease(
                         new_lease_id,
@@ -225,14 +391,15 @@ impl LeaseManager {
                 } else {
                     LeaseResult::Denied {
                         reason: format!(
-                            "Lease held by client {} (policy: {:?})",
-                            owner_client_id, self.policy
+                            "Lease held by {} (policy: {:?})",
+                            self.describe_client(*owner_client_id),
+                            self.policy
                         ),
                         current_lease: Some(self.build_lease(
                             *lease_id,
                             *owner_client_id,
                             current_size,
-                            duration.saturating_sub(granted_at.elapsed()),
+                            duration.saturating_sub(self.since(*granted_at)),
                         )),
                     }
                 }
@@ -257,6 +424,269 @@ impl LeaseManager {
         false
     }
 
+    /// Take the lease away from its owner (e.g. at the local user's request); the owner
+    /// stays attached as a viewer.
+    pub fn revoke(&mut self, reason: &str) -> Option<LeaseEvent> {
+        if let LeaseState::Active {
+            owner_client_id,
+            lease_id,
+            ..
+        } = &self.state
+        {
+            let owner = *owner_client_id;
+            let lease_id = *lease_id;
+            self.state = LeaseState::Expired {
+                previous_owner: owner,
+            };
+            self.last_input_at = None;
+            self.viewers.insert(owner);
+            // A revoked lease isn't handed back to anyone
+            self.time_box = None;
+            self.lease_stack.clear();
+            return Some(LeaseEvent::Revoked {
+                lease_id,
+                owner,
+                reason: reason.to_string(),
+            });
+        }
+        None
+    }
+
+    /// Ask the controller to hand control to `client_id` rather than taking it by force.
+    pub fn request_handoff(
+        &mut self,
+        client_id: u64,
+        desired_size: Option<DisplaySize>,
+    ) -> HandoffRequestResult {
+        let (owner, lease_id) = match &self.state {
+            LeaseState::Active {
+                owner_client_id,
+                lease_id,
+                ..
+            } if *owner_client_id != client_id => (*owner_client_id, *lease_id),
+            // Free, or already ours: an ordinary request is granted
+            _ => match self.request_control(client_id, desired_size, false) {
+                LeaseResult::Granted(lease) => return HandoffRequestResult::Granted(lease),
+                LeaseResult::Denied {
+                    reason,
+                    current_lease,
+                } => {
+                    return HandoffRequestResult::Denied {
+                        reason,
+                        current_lease,
+                    }
+                },
+            },
+        };
+
+        if self.pending_handoff.is_some() {
+            return HandoffRequestResult::Denied {
+                reason: "Another handoff request is waiting for the controller".to_string(),
+                current_lease: self.get_current_lease(),
+            };
+        }
+
+        let handoff_id = self.next_handoff_id;
+        self.next_handoff_id += 1;
+        self.pending_handoff = Some(PendingHandoff {
+            handoff_id,
+            requester: client_id,
+            desired_size: desired_size.unwrap_or(DisplaySize { cols: 80, rows: 24 }),
+            lease_id,
+            requested_at: self.clock.now(),
+        });
+        HandoffRequestResult::Pending {
+            handoff_id,
+            controller: owner,
+            timeout_ms: self.handoff_timeout.as_millis() as u32,
+        }
+    }
+
+    /// The controller's answer to a handoff request. Returns None if `client_id` isn't
+    /// the controller that was asked, or the handoff is no longer pending.
+    pub fn respond_to_handoff(
+        &mut self,
+        client_id: u64,
+        handoff_id: u64,
+        approved: bool,
+    ) -> Option<HandoffOutcome> {
+        let pending = self.pending_handoff.as_ref()?;
+        let is_asked_controller = matches!(
+            &self.state,
+            LeaseState::Active { owner_client_id, lease_id, .. }
+                if *owner_client_id == client_id && *lease_id == pending.lease_id
+        );
+        if pending.handoff_id != handoff_id || !is_asked_controller {
+            return None;
+        }
+        let pending = self.pending_handoff.take()?;
+
+        if !approved {
+            return Some(HandoffOutcome::Denied {
+                handoff_id,
+                requester: pending.requester,
+                reason: format!("{} declined the handoff", self.describe_client(client_id)),
+                current_lease: self.get_current_lease(),
+            });
+        }
+
+        let lease_id = self.next_lease_id;
+        self.next_lease_id += 1;
+        let now = self.clock.now();
+        self.state = LeaseState::Active {
+            owner_client_id: pending.requester,
+            lease_id,
+            granted_at: now,
+            duration: self.default_duration,
+            current_size: pending.desired_size.clone(),
+        };
+        self.viewers.insert(client_id);
+        self.viewers.remove(&pending.requester);
+        self.last_input_at = Some(now);
+        self.time_box = None;
+        self.lease_stack.clear();
+
+        Some(HandoffOutcome::Approved {
+            handoff_id,
+            requester: pending.requester,
+            previous_owner: client_id,
+            previous_lease_id: pending.lease_id,
+            lease: self.build_lease(
+                lease_id,
+                pending.requester,
+                &pending.desired_size,
+                self.default_duration,
+            ),
+        })
+    }
+
+    /// Deny a pending handoff the controller didn't answer in time, or whose lease
+    /// changed hands in the meantime.
+    pub fn poll_handoff(&mut self) -> Option<HandoffOutcome> {
+        let pending = self.pending_handoff.as_ref()?;
+        let lease_unchanged = matches!(
+            &self.state,
+            LeaseState::Active { lease_id, .. } if *lease_id == pending.lease_id
+        );
+        let reason = if !lease_unchanged {
+            "Control changed hands before the controller answered"
+        } else if self.since(pending.requested_at) >= self.handoff_timeout {
+            "Controller did not answer the handoff request"
+        } else {
+            return None;
+        };
+        let pending = self.pending_handoff.take()?;
+        Some(HandoffOutcome::Denied {
+            handoff_id: pending.handoff_id,
+            requester: pending.requester,
+            reason: reason.to_string(),
+            current_lease: self.get_current_lease(),
+        })
+    }
+
+    /// Hand control back to the most recent still-connected owner once the current
+    /// time-boxed lease has run out or ended some other way (release, expiry,
+    /// disconnect). With nobody left to return to, the borrower simply keeps control.
+    pub fn poll_time_box(&mut self) -> Option<LeaseReturn> {
+        let time_box = self.time_box.as_ref()?;
+        let now = self.clock.now();
+        let (ended_lease_id, borrower) = match &self.state {
+            LeaseState::Active {
+                owner_client_id,
+                lease_id,
+                ..
+            } if *lease_id == time_box.lease_id => {
+                if now < time_box.deadline {
+                    return None;
+                }
+                (Some(*lease_id), Some(*owner_client_id))
+            },
+            LeaseState::Active { .. } => {
+                // Control changed hands for good in the meantime
+                self.time_box = None;
+                self.lease_stack.clear();
+                return None;
+            },
+            LeaseState::NoController | LeaseState::Expired { .. } => (None, None),
+        };
+        self.time_box = None;
+
+        // Skip owners whose own time box ran out while they were suspended
+        let target = loop {
+            match self.lease_stack.pop() {
+                Some(suspended) if suspended.deadline.is_some_and(|d| d <= now) => continue,
+                other => break other,
+            }
+        };
+        let Some(target) = target else {
+            self.lease_stack.clear();
+            return None;
+        };
+
+        let lease_id = self.next_lease_id;
+        self.next_lease_id += 1;
+        self.state = LeaseState::Active {
+            owner_client_id: target.owner,
+            lease_id,
+            granted_at: now,
+            duration: self.default_duration,
+            current_size: target.size.clone(),
+        };
+        self.time_box = target
+            .deadline
+            .map(|deadline| TimeBox { lease_id, deadline });
+        if let Some(borrower) = borrower {
+            self.viewers.insert(borrower);
+        }
+        self.viewers.remove(&target.owner);
+        self.last_input_at = Some(now);
+
+        Some(LeaseReturn {
+            ended_lease_id,
+            lease: self.build_lease(lease_id, target.owner, &target.size, self.default_duration),
+        })
+    }
+
+    /// Remaining time before the current lease is handed back, if it is time-boxed
+    pub fn time_box_remaining(&self) -> Option<Duration> {
+        let time_box = self.time_box.as_ref()?;
+        Some(
+            time_box
+                .deadline
+                .saturating_duration_since(self.clock.now()),
+        )
+    }
+
+    /// Remaining time before the controller is demoted for being idle, if an idle
+    /// timeout is set and the controller has sent input
+    pub fn idle_remaining(&self) -> Option<Duration> {
+        if !matches!(self.state, LeaseState::Active { .. }) {
+            return None;
+        }
+        let timeout = self.idle_timeout?;
+        let last_input_at = self.last_input_at?;
+        Some(timeout.saturating_sub(self.since(last_input_at)))
+    }
+
+    /// Where the current lease stands, for clients counting it down. `expires` is
+    /// whether `tick` runs, i.e. whether the lease lapses without input or keepalives.
+    pub fn lease_status(&self, expires: bool) -> Option<LeaseStatus> {
+        let lease = self.get_current_lease()?;
+        let idle_remaining = if expires { self.idle_remaining() } else { None };
+        Some(LeaseStatus {
+            lease: Some(lease),
+            idle_remaining_ms: idle_remaining.map_or(0, |d| d.as_millis() as u32),
+            time_box_remaining_ms: self
+                .time_box_remaining()
+                .map_or(0, |d| d.as_millis() as u32),
+            expires,
+        })
+    }
+
+    pub fn has_pending_handoff(&self) -> bool {
+        self.pending_handoff.is_some()
+    }
+
     pub fn keepalive(&mut self, client_id: u64, lease_id: u64) -> bool {
         if let LeaseState::Active {
             owner_client_id,
@@ -270,7 +700,7 @@ impl LeaseManager {
                 self.state = LeaseState::Active {
                     owner_client_id: *owner_client_id,
                     lease_id: *current_lease_id,
-                    granted_at: Instant::now(),
+                    granted_at: self.clock.now(),
                     duration: *duration,
                     current_size: current_size.clone(),
                 };
@@ -289,15 +719,31 @@ impl LeaseManager {
             ..
         } = &self.state
         {
-            if granted_at.elapsed() >= *duration {
-                let event = LeaseEvent::Expired {
-                    lease_id: *lease_id,
-                    owner: *owner_client_id,
+            let owner = *owner_client_id;
+            let lease_id = *lease_id;
+
+            let idle = match (self.idle_timeout, self.last_input_at) {
+                (Some(timeout), Some(last_input_at)) => self.since(last_input_at) >= timeout,
+                _ => false,
+            };
+            if idle {
+                self.state = LeaseState::Expired {
+                    previous_owner: owner,
                 };
+                self.last_input_at = None;
+                self.viewers.insert(owner);
+                return Some(LeaseEvent::Revoked {
+                    lease_id,
+                    owner,
+                    reason: "idle".to_string(),
+                });
+            }
+
+            if self.since(*granted_at) >= *duration {
                 self.state = LeaseState::Expired {
-                    previous_owner: *owner_client_id,
+                    previous_owner: owner,
                 };
-                return Some(event);
+                return Some(LeaseEvent::Expired { lease_id, owner });
             }
         }
         None
@@ -334,6 +780,21 @@ impl LeaseManager {
         false
     }
 
+    /// The takeover the last granted request caused, if it hasn't been taken yet
+    pub fn take_takeover(&mut self) -> Option<LeaseEvent> {
+        self.takeover.take()
+    }
+
+    /// Whether input from `client_id` is applied: it is the controller, or was until a
+    /// force takeover and its grace period hasn't run out.
+    pub fn accepts_input(&self, client_id: u64) -> bool {
+        self.is_controller(client_id)
+            || self
+                .grace
+                .as_ref()
+                .is_some_and(|grace| grace.owner == client_id && self.clock.now() < grace.until)
+    }
+
     pub fn is_controller(&self, client_id: u64) -> bool {
         if let LeaseState::Active {
             owner_client_id, ..
@@ -354,7 +815,7 @@ impl LeaseManager {
             current_size,
         } = &self.state
         {
-            let remaining = duration.saturating_sub(granted_at.elapsed());
+            let remaining = duration.saturating_sub(self.since(*granted_at));
             Some(self.build_lease(*lease_id, *owner_client_id, current_size, remaining))
         } else {
             None
@@ -368,7 +829,33 @@ impl LeaseManager {
     }
 
     pub fn remove_client(&mut self, client_id: u64) -> Option<LeaseEvent> {
+        self.remove_client_with_reason(client_id, "disconnect")
+    }
+
+    /// Like [`Self::remove_client`], revoking the client's lease with `reason`.
+    pub fn remove_client_with_reason(
+        &mut self,
+        client_id: u64,
+        reason: &str,
+    ) -> Option<LeaseEvent> {
         self.viewers.remove(&client_id);
+        self.names.remove(&client_id);
+        self.lease_stack
+            .retain(|suspended| suspended.owner != client_id);
+        if self
+            .grace
+            .as_ref()
+            .is_some_and(|grace| grace.owner == client_id)
+        {
+            self.grace = None;
+        }
+        if self
+            .pending_handoff
+            .as_ref()
+            .is_some_and(|pending| pending.requester == client_id)
+        {
+            self.pending_handoff = None;
+        }
 
         if let LeaseState::Active {
             owner_client_id,
@@ -380,7 +867,7 @@ impl LeaseManager {
                 let event = LeaseEvent::Revoked {
                     lease_id: *lease_id,
                     owner: *owner_client_id,
-                    reason: "disconnect".to_string(),
+                    reason: reason.to_string(),
                 };
                 self.state = LeaseState::Expired {
                     previous_owner: client_id,
@@ -413,6 +900,10 @@ impl LeaseManager {
             current_size: Some(size.clone()),
             remaining_ms: remaining.as_millis() as u32,
             duration_ms: self.default_duration.as_millis() as u32,
+            owner_name: self
+                .client_name(owner_client_id)
+                .unwrap_or_default()
+                .to_string(),
         }
     }
 }
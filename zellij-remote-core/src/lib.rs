@@ -1,32 +1,81 @@
+pub mod auth;
 pub mod backpressure;
+pub mod bell;
+pub mod chunking;
 pub mod client_state;
+pub mod clock;
+pub mod color_transform;
+pub mod conformance;
+pub mod debug_info;
 pub mod delta;
+pub mod echo;
+pub mod failover;
+pub mod flash_guard;
 pub mod frame;
+pub mod frame_hash;
+pub mod frame_stats;
 pub mod input;
 pub mod lease;
+pub mod persist;
 pub mod prediction;
+pub mod render_job;
 pub mod render_seq;
 pub mod resume_token;
 pub mod rtt;
 pub mod session;
+pub mod session_handle;
+pub mod size_limits;
 pub mod state_history;
+pub mod style_convert;
 pub mod style_table;
+pub mod svg_export;
+pub mod text_mode;
+pub mod token_keys;
 
 #[cfg(test)]
 mod tests;
 
-pub use backpressure::RenderWindow;
+pub use auth::{
+    AuthOutcome, SharedTokenRegistry, TokenEntry, TokenHash, TokenProvider, TokenRegistry,
+};
+pub use backpressure::{RenderWindow, WindowBounds};
+pub use bell::BellCoalescer;
+pub use chunking::{chunk_delta, chunk_snapshot, ChunkAssembler, MIN_MESSAGE_BYTES};
 pub use client_state::ClientRenderState;
+pub use clock::{Clock, ManualClock, SharedClock, SystemClock};
+pub use color_transform::{contrast_ratio, transform_style_defs};
+pub use conformance::{ClientScreen, ConformanceError, ConformanceFailure};
 pub use delta::DeltaEngine;
-pub use frame::{Cell, Cursor, CursorShape, Frame, FrameData, FrameStore, Row, RowData};
+pub use echo::RecentInput;
+pub use failover::EndpointFailover;
+pub use flash_guard::{FlashGuard, HeldRows};
+pub use frame::{
+    Cell, Cursor, CursorColor, CursorShape, Frame, FrameData, FrameFingerprint, FrameStore,
+    LineSize, Row, RowData, RowFingerprint,
+};
+pub use frame_hash::{hash_frame, FrameHasher};
+pub use frame_stats::{FrameArrival, FrameStats};
 pub use input::{
-    AckResult, InflightInput, InputProcessResult, InputReceiver, InputSender, RttSample,
+    AckResult, InflightInput, InputProcessResult, InputReceiver, InputSender, ReorderWindow,
+    RttSample, MAX_INPUT_REORDER_EVENTS,
+};
+pub use lease::{
+    HandoffOutcome, HandoffRequestResult, LeaseEvent, LeaseManager, LeaseResult, LeaseReturn,
+    LeaseState,
+};
+pub use persist::{
+    persist_session, restore_session, RestoreError, RestoredSession, PERSISTED_SESSION_VERSION,
 };
-pub use lease::{LeaseEvent, LeaseManager, LeaseResult, LeaseState};
 pub use prediction::{Confidence, Prediction, PredictionEngine, ReconcileResult};
+pub use render_job::{RenderJob, RenderOutput};
 pub use render_seq::{DatagramDecision, RenderSender, RenderSeqTracker};
 pub use resume_token::{ResumeResult, ResumeToken};
-pub use rtt::{LinkState, RttEstimator};
-pub use session::{InputError, RemoteSession, RenderUpdate};
+pub use rtt::{LatencyHistogram, LatencyPercentiles, LinkState, RttEstimator};
+pub use session::{CachedSnapshot, InputError, RemoteSession, RenderUpdate, SessionEvent};
+pub use session_handle::{AttachRequest, Attached, RemoteSessionHandle, SessionHandleError};
+pub use size_limits::{SizeError, SizeLimits};
 pub use state_history::StateHistory;
 pub use style_table::StyleTable;
+pub use svg_export::{frame_to_svg, SvgTheme};
+pub use text_mode::{TextModeChange, TextModeController};
+pub use token_keys::TokenKeyring;
@@ -0,0 +1,156 @@
+//! Saving what resume tokens depend on, so a restarted server honours the tokens it
+//! issued before.
+//!
+//! A [`PersistedSession`] carries the session id, the token keyring, the next client
+//! id and the last frame the screen sent. Restoring one gives a session that opens the
+//! old tokens ([`RemoteSession::predates_restore`]) and a frame to serve until the
+//! screen renders again. State ids carry on past the saved one, so a returning client
+//! gets a snapshot rather than a delta against a screen the new process never had.
+
+use std::collections::HashMap;
+
+use crate::delta::DeltaEngine;
+use crate::frame::{Cell, FrameStore, LineSize, RowData};
+use crate::session::RemoteSession;
+use crate::style_table::StyleTable;
+use crate::token_keys::TokenKeyring;
+use zellij_remote_protocol::{LineSize as ProtoLineSize, PersistedSession, ScreenSnapshot};
+
+/// Bumped when a saved session can no longer be read the same way.
+pub const PERSISTED_SESSION_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RestoreError {
+    UnknownVersion(u32),
+    /// The token secret isn't 32 bytes
+    BadSecret,
+    /// Saved longer ago than any token it could honour stays valid
+    Stale {
+        age_ms: u64,
+    },
+}
+
+/// A session rebuilt from a [`PersistedSession`]
+pub struct RestoredSession {
+    pub session: RemoteSession,
+    /// The frame and style table the screen last sent, if one was saved
+    pub last_frame: Option<(FrameStore, StyleTable)>,
+    pub next_client_id: u64,
+}
+
+/// Capture `session` for [`restore_session`]. `last_frame` is the screen's latest
+/// frame with the style table its style ids refer to.
+pub fn persist_session(
+    session: &RemoteSession,
+    last_frame: Option<(&FrameStore, &StyleTable)>,
+    next_client_id: u64,
+    now_ms: u64,
+) -> PersistedSession {
+    let (master, epoch, client_epochs) = session.token_keys().parts();
+    let snapshot = last_frame.map(|(frame_store, style_table)| {
+        DeltaEngine::compute_snapshot(
+            frame_store.current_frame(),
+            &mut style_table.clone(),
+            session.frame_store.current_state_id(),
+        )
+    });
+    PersistedSession {
+        version: PERSISTED_SESSION_VERSION,
+        session_id: session.session_id,
+        token_secret: master.to_vec(),
+        token_epoch: epoch,
+        client_token_epochs: client_epochs.clone(),
+        next_client_id,
+        snapshot,
+        saved_at_ms: now_ms,
+    }
+}
+
+/// Rebuild a session saved by [`persist_session`] at most `max_age_ms` ago.
+pub fn restore_session(
+    persisted: &PersistedSession,
+    cols: usize,
+    rows: usize,
+    max_age_ms: u64,
+    now_ms: u64,
+) -> Result<RestoredSession, RestoreError> {
+    if persisted.version != PERSISTED_SESSION_VERSION {
+        return Err(RestoreError::UnknownVersion(persisted.version));
+    }
+    let age_ms = now_ms.saturating_sub(persisted.saved_at_ms);
+    if age_ms > max_age_ms {
+        return Err(RestoreError::Stale { age_ms });
+    }
+    let master: [u8; 32] = persisted
+        .token_secret
+        .as_slice()
+        .try_into()
+        .map_err(|_| RestoreError::BadSecret)?;
+
+    let token_keys = TokenKeyring::restore(
+        master,
+        persisted.token_epoch,
+        persisted.client_token_epochs.clone(),
+    );
+    let last_state_id = persisted.snapshot.as_ref().map_or(0, |s| s.state_id);
+    let session = RemoteSession::restore(
+        cols,
+        rows,
+        persisted.session_id,
+        token_keys,
+        last_state_id,
+        now_ms,
+    );
+    Ok(RestoredSession {
+        session,
+        last_frame: persisted.snapshot.as_ref().map(frame_from_snapshot),
+        next_client_id: persisted.next_client_id,
+    })
+}
+
+/// The frame a snapshot describes, with a style table its style ids resolve in.
+/// Cells with a style the snapshot doesn't define fall back to the default style.
+fn frame_from_snapshot(snapshot: &ScreenSnapshot) -> (FrameStore, StyleTable) {
+    let mut style_table = StyleTable::new();
+    let mut style_ids: HashMap<u32, u32> = HashMap::from([(0, 0)]);
+    let mut styles = snapshot.styles.iter().collect::<Vec<_>>();
+    styles.sort_by_key(|def| def.style_id);
+    for def in styles {
+        if def.style_id == 0 {
+            continue;
+        }
+        let style = def.style.clone().unwrap_or_default();
+        style_ids.insert(def.style_id, style_table.get_or_insert(&style));
+    }
+
+    let size = snapshot.size.clone().unwrap_or_default();
+    let cols = size.cols as usize;
+    let mut frame_store = FrameStore::new(cols, size.rows as usize);
+    for row_data in &snapshot.rows {
+        let mut row = RowData::new(cols);
+        row.line_size = match row_data.line_size() {
+            ProtoLineSize::DoubleWidth => LineSize::DoubleWidth,
+            ProtoLineSize::DoubleHeightTop => LineSize::DoubleHeightTop,
+            ProtoLineSize::DoubleHeightBottom => LineSize::DoubleHeightBottom,
+            ProtoLineSize::Single | ProtoLineSize::Unspecified => LineSize::Single,
+        };
+        let cells = row_data
+            .codepoints
+            .iter()
+            .zip(&row_data.widths)
+            .zip(&row_data.style_ids)
+            .take(cols);
+        for (col, ((&codepoint, &width), style_id)) in cells.enumerate() {
+            row.cells[col] = Cell {
+                codepoint,
+                width: width.min(u8::MAX as u32) as u8,
+                style_id: style_ids.get(style_id).copied().unwrap_or(0),
+            };
+        }
+        frame_store.set_row(row_data.row as usize, row);
+    }
+    if let Some(cursor) = &snapshot.cursor {
+        frame_store.set_cursor(DeltaEngine::decode_cursor(cursor));
+    }
+    (frame_store, style_table)
+}
@@ -3,12 +3,14 @@
 //! The prediction engine maintains an overlay of unconfirmed input effects
 //! on top of the server's confirmed state. When the server acknowledges
 //! input via `delivered_input_watermark`, predictions are confirmed or
-//! rolled back if they don't match.
+//! rolled back if they don't match. Deltas flag patches that are echo of
+//! typed input, so only conflicting non-echo output forces a rollback.
 
 use crate::frame::{Cell, Cursor, FrameData};
 use std::collections::VecDeque;
 use std::sync::Arc;
 use std::time::Instant;
+use zellij_remote_protocol::{RowPatch, ScreenDelta};
 
 #[derive(Clone, Debug)]
 pub struct Prediction {
@@ -40,6 +42,8 @@ pub struct PredictionEngine {
     max_pending: usize,
     misprediction_count: u32,
     misprediction_threshold: u32,
+    /// The server says typed characters aren't echoed right now
+    echo_suppressed: bool,
 }
 
 impl Default for PredictionEngine {
@@ -57,6 +61,7 @@ impl PredictionEngine {
             max_pending: 100,
             misprediction_count: 0,
             misprediction_threshold: 5,
+            echo_suppressed: false,
         }
     }
 
@@ -162,13 +167,7 @@ impl PredictionEngine {
             if confirmed_cursor.col != server_cursor.col
                 || confirmed_cursor.row != server_cursor.row
             {
-                self.misprediction_count += 1;
-                self.pending.clear();
-
-                if self.misprediction_count >= self.misprediction_threshold {
-                    self.enabled = false;
-                }
-
+                self.record_misprediction();
                 return ReconcileResult::Misprediction;
             }
         }
@@ -177,8 +176,56 @@ impl PredictionEngine {
         ReconcileResult::Confirmed
     }
 
+    /// Reconcile against a delta whose patches carry echo hints.
+    ///
+    /// Unlike [`Self::reconcile`], the cursor isn't compared: echo of typed text may
+    /// trail the watermark by a frame or two. Only a patch without `echo` that puts
+    /// different content in a predicted cell counts as a misprediction.
+    pub fn reconcile_delta(&mut self, delta: &ScreenDelta) -> ReconcileResult {
+        self.set_echo_suppressed(delta.echo_suppressed);
+        let conflict = delta
+            .row_patches
+            .iter()
+            .filter(|patch| !patch.echo)
+            .any(|patch| self.pending.iter().any(|pred| conflicts(pred, patch)));
+        if conflict {
+            self.record_misprediction();
+            return ReconcileResult::Misprediction;
+        }
+
+        let watermark = delta.delivered_input_watermark;
+        if watermark <= self.last_confirmed_seq {
+            return ReconcileResult::NoChange;
+        }
+        self.last_confirmed_seq = watermark;
+
+        let before = self.pending.len();
+        while self
+            .pending
+            .front()
+            .is_some_and(|pred| pred.input_seq <= watermark)
+        {
+            self.pending.pop_front();
+        }
+        if self.pending.len() == before {
+            return ReconcileResult::NoChange;
+        }
+
+        self.misprediction_count = self.misprediction_count.saturating_sub(1);
+        ReconcileResult::Confirmed
+    }
+
+    fn record_misprediction(&mut self) {
+        self.misprediction_count += 1;
+        self.pending.clear();
+
+        if self.misprediction_count >= self.misprediction_threshold {
+            self.enabled = false;
+        }
+    }
+
     pub fn confidence(&self, ch: char) -> Confidence {
-        if !self.enabled {
+        if !self.enabled || self.echo_suppressed {
             return Confidence::None;
         }
 
@@ -203,6 +250,20 @@ impl PredictionEngine {
         self.misprediction_count = 0;
     }
 
+    /// Stop predicting while the server reports echo off (a password prompt), dropping
+    /// what was predicted so nothing typed into the prompt stays on screen. Unlike
+    /// [`Self::disable`] this doesn't outlast the prompt.
+    pub fn set_echo_suppressed(&mut self, echo_suppressed: bool) {
+        if echo_suppressed && !self.echo_suppressed {
+            self.pending.clear();
+        }
+        self.echo_suppressed = echo_suppressed;
+    }
+
+    pub fn is_echo_suppressed(&self) -> bool {
+        self.echo_suppressed
+    }
+
     pub fn pending_count(&self) -> usize {
         self.pending.len()
     }
@@ -224,6 +285,21 @@ impl PredictionEngine {
     }
 }
 
+/// Whether `patch` writes something other than the prediction into a predicted cell.
+fn conflicts(pred: &Prediction, patch: &RowPatch) -> bool {
+    pred.cells
+        .iter()
+        .filter(|(_, row, _)| *row == patch.row as usize)
+        .any(|(col, _, cell)| {
+            patch.runs.iter().any(|run| {
+                let start = run.col_start as usize;
+                col.checked_sub(start)
+                    .and_then(|offset| run.codepoints.get(offset))
+                    .is_some_and(|codepoint| *codepoint != cell.codepoint)
+            })
+        })
+}
+
 fn char_display_width(ch: char) -> u8 {
     if ch.is_ascii() {
         1
@@ -257,6 +333,7 @@ mod tests {
             visible: true,
             blink: true,
             shape: crate::frame::CursorShape::Block,
+            color: None,
         }
     }
 
@@ -442,4 +519,91 @@ mod tests {
         assert_eq!(result, ReconcileResult::NoChange);
         assert_eq!(engine.pending_count(), 1);
     }
+
+    fn patch(row: u32, col_start: u32, text: &str, echo: bool) -> RowPatch {
+        RowPatch {
+            row,
+            runs: vec![zellij_remote_protocol::CellRun {
+                col_start,
+                codepoints: text.chars().map(|c| c as u32).collect(),
+                widths: vec![1; text.chars().count()],
+                style_ids: vec![0; text.chars().count()],
+            }],
+            line_size: 0,
+            echo,
+            row_crc32: 0,
+        }
+    }
+
+    fn delta(watermark: u64, row_patches: Vec<RowPatch>) -> ScreenDelta {
+        ScreenDelta {
+            delivered_input_watermark: watermark,
+            row_patches,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_echo_lagging_watermark_is_not_a_misprediction() {
+        let mut engine = PredictionEngine::new();
+        engine.predict_char('a', 1, &make_cursor(5, 0), 80);
+        engine.predict_char('b', 2, &make_cursor(6, 0), 80);
+
+        // Both keystrokes were written, but only the first has echoed so far
+        let result = engine.reconcile_delta(&delta(2, vec![patch(0, 5, "a", true)]));
+        assert_eq!(result, ReconcileResult::Confirmed);
+        assert_eq!(engine.misprediction_count(), 0);
+        assert_eq!(engine.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_conflicting_output_rolls_back() {
+        let mut engine = PredictionEngine::new();
+        engine.predict_char('a', 1, &make_cursor(5, 0), 80);
+        engine.predict_char('b', 2, &make_cursor(6, 0), 80);
+
+        let result = engine.reconcile_delta(&delta(1, vec![patch(0, 0, "$ ***", false)]));
+        assert_eq!(result, ReconcileResult::Misprediction);
+        assert_eq!(engine.pending_count(), 0);
+        assert_eq!(engine.misprediction_count(), 1);
+    }
+
+    #[test]
+    fn test_matching_or_distant_output_keeps_predictions() {
+        let mut engine = PredictionEngine::new();
+        engine.predict_char('a', 1, &make_cursor(5, 0), 80);
+        engine.predict_char('b', 2, &make_cursor(6, 0), 80);
+
+        // A redraw that agrees with the overlay, and output on another row
+        let result = engine.reconcile_delta(&delta(
+            1,
+            vec![patch(0, 0, "$ ls a", false), patch(3, 0, "done", false)],
+        ));
+        assert_eq!(result, ReconcileResult::Confirmed);
+        assert_eq!(engine.pending_count(), 1);
+    }
+
+    #[test]
+    fn test_no_prediction_while_echo_suppressed() {
+        let mut engine = PredictionEngine::new();
+        engine.predict_char('a', 1, &make_cursor(5, 0), 80);
+
+        // A password prompt turned echo off
+        engine.reconcile_delta(&ScreenDelta {
+            echo_suppressed: true,
+            ..delta(0, vec![])
+        });
+        assert_eq!(engine.pending_count(), 0);
+        assert!(engine
+            .predict_char('b', 2, &make_cursor(6, 0), 80)
+            .is_none());
+        assert_eq!(engine.confidence('b'), Confidence::None);
+
+        // Back at the shell, predicting picks up again
+        engine.reconcile_delta(&delta(2, vec![]));
+        assert!(engine.is_enabled());
+        assert!(engine
+            .predict_char('c', 3, &make_cursor(0, 1), 80)
+            .is_some());
+    }
 }
@@ -0,0 +1,163 @@
+//! Render updates split around the expensive part.
+//!
+//! Diffing a client's baseline against the current frame only reads frames, so with
+//! many clients attached it can run in parallel, off the session lock.
+//! [`crate::RemoteSession::begin_render_update`] does the per-client checks and
+//! captures what the diff needs (frames are `Arc`'d rows, so this is cheap);
+//! [`RenderJob::compute`] diffs; [`crate::RemoteSession::finish_render_update`]
+//! does the baseline and render window bookkeeping back under the lock.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::debug_info;
+use crate::delta::DeltaEngine;
+use crate::flash_guard::HeldRows;
+use crate::frame::{FrameData, FrameFingerprint};
+use crate::frame_hash::{hash_frame, stamp_row_checksums};
+use crate::session::RenderUpdate;
+use zellij_remote_protocol::ScreenDelta;
+
+/// The work behind one client's render update.
+#[derive(Debug)]
+pub struct RenderJob {
+    client_id: u64,
+    work: Work,
+}
+
+#[derive(Debug)]
+enum Work {
+    /// Prepared under the lock already; snapshots read the whole style table
+    Ready(RenderUpdate),
+    Delta(Box<DeltaInputs>),
+}
+
+#[derive(Debug)]
+pub(crate) struct DeltaInputs {
+    pub(crate) baseline: FrameFingerprint,
+    pub(crate) baseline_frame: Option<FrameData>,
+    pub(crate) current: FrameData,
+    pub(crate) current_fingerprint: FrameFingerprint,
+    pub(crate) base_state_id: u64,
+    pub(crate) state_id: u64,
+    pub(crate) dirty_rows: Arc<HashSet<usize>>,
+    pub(crate) delivered_input_watermark: u64,
+    pub(crate) frame_hash: bool,
+    pub(crate) row_checksums: bool,
+    pub(crate) debug_frame_info: bool,
+    /// Rows of `current` the flash guard put back as the client last had them
+    pub(crate) held_rows: HeldRows,
+}
+
+/// A computed render update, to hand back to the session.
+#[derive(Debug)]
+pub struct RenderOutput {
+    client_id: u64,
+    result: Output,
+}
+
+#[derive(Debug)]
+pub(crate) enum Output {
+    Ready(RenderUpdate),
+    Delta {
+        delta: ScreenDelta,
+        fingerprint: FrameFingerprint,
+        /// The frame diffed up to, for a snapshot should the delta be too large
+        frame: FrameData,
+        held_rows: HeldRows,
+    },
+}
+
+impl RenderJob {
+    pub(crate) fn ready(client_id: u64, update: RenderUpdate) -> Self {
+        Self {
+            client_id,
+            work: Work::Ready(update),
+        }
+    }
+
+    pub(crate) fn delta(client_id: u64, inputs: DeltaInputs) -> Self {
+        Self {
+            client_id,
+            work: Work::Delta(Box::new(inputs)),
+        }
+    }
+
+    pub fn client_id(&self) -> u64 {
+        self.client_id
+    }
+
+    /// Whether [`Self::compute`] has real work to do
+    pub fn is_delta(&self) -> bool {
+        matches!(self.work, Work::Delta(_))
+    }
+
+    /// Rows [`Self::compute`] will diff, a rough measure of its cost; 0 for updates
+    /// prepared already
+    pub fn rows_to_diff(&self) -> usize {
+        match &self.work {
+            Work::Ready(_) => 0,
+            Work::Delta(inputs) => {
+                debug_info::rows_diffed(Some(&inputs.dirty_rows), inputs.current.rows.len())
+            },
+        }
+    }
+
+    /// Diff the frames. Needs no access to the session.
+    pub fn compute(self) -> RenderOutput {
+        let result = match self.work {
+            Work::Ready(update) => Output::Ready(update),
+            Work::Delta(inputs) => {
+                let started = Instant::now();
+                let mut delta = DeltaEngine::diff_incremental(
+                    &inputs.baseline,
+                    inputs.baseline_frame.as_ref(),
+                    &inputs.current,
+                    &inputs.current_fingerprint,
+                    inputs.base_state_id,
+                    inputs.state_id,
+                    Some(&inputs.dirty_rows),
+                );
+                delta.delivered_input_watermark = inputs.delivered_input_watermark;
+                if inputs.frame_hash {
+                    delta.frame_hash = hash_frame(&inputs.current);
+                }
+                if inputs.row_checksums {
+                    stamp_row_checksums(&mut delta, &inputs.current);
+                }
+                if inputs.debug_frame_info {
+                    let rows_diffed = debug_info::rows_diffed(
+                        Some(&inputs.dirty_rows),
+                        inputs.current.rows.len(),
+                    );
+                    delta.debug_info = Some(debug_info::for_delta(
+                        &delta,
+                        rows_diffed,
+                        started.elapsed(),
+                    ));
+                }
+                Output::Delta {
+                    delta,
+                    fingerprint: inputs.current_fingerprint,
+                    frame: inputs.current,
+                    held_rows: inputs.held_rows,
+                }
+            },
+        };
+        RenderOutput {
+            client_id: self.client_id,
+            result,
+        }
+    }
+}
+
+impl RenderOutput {
+    pub fn client_id(&self) -> u64 {
+        self.client_id
+    }
+
+    pub(crate) fn into_result(self) -> Output {
+        self.result
+    }
+}
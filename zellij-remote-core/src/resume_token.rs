@@ -1,13 +1,21 @@
-use std::time::{SystemTime, UNIX_EPOCH};
-
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
 use hmac::{Hmac, Mac};
+use rand::RngCore;
 use sha2::Sha256;
 
+use crate::clock::{Clock, SystemClock};
+
 type HmacSha256 = Hmac<Sha256>;
 
 const PAYLOAD_SIZE: usize = 40;
-const SIGNATURE_SIZE: usize = 32;
-const SIGNED_TOKEN_SIZE: usize = PAYLOAD_SIZE + SIGNATURE_SIZE;
+/// Leading byte of sealed tokens, authenticated as associated data
+const SEALED_TOKEN_VERSION: u8 = 2;
+pub(crate) const XNONCE_SIZE: usize = 24;
+pub(crate) const TAG_SIZE: usize = 16;
+const SEALED_TOKEN_SIZE: usize = 1 + XNONCE_SIZE + PAYLOAD_SIZE + TAG_SIZE;
+/// Domain separation so the AEAD key differs from the secret it is derived from
+const SEALING_KEY_CONTEXT: &[u8] = b"zellij-remote resume token v2";
 const DEFAULT_TOKEN_EXPIRY_MS: u64 = 300_000; // 5 minutes
 const DEFAULT_MAX_CLOCK_SKEW_MS: u64 = 30_000; // 30 seconds
 
@@ -27,11 +35,23 @@ impl ResumeToken {
         last_applied_state_id: u64,
         last_acked_input_seq: u64,
     ) -> Self {
-        let issued_at_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_millis() as u64)
-            .unwrap_or(0);
+        Self::issued_at(
+            session_id,
+            client_id,
+            last_applied_state_id,
+            last_acked_input_seq,
+            SystemClock.unix_ms(),
+        )
+    }
 
+    /// A token issued at `issued_at_ms`, as read from the session's clock
+    pub fn issued_at(
+        session_id: u64,
+        client_id: u64,
+        last_applied_state_id: u64,
+        last_acked_input_seq: u64,
+        issued_at_ms: u64,
+    ) -> Self {
         Self {
             session_id,
             client_id,
@@ -41,25 +61,43 @@ impl ResumeToken {
         }
     }
 
-    pub fn encode_signed(&self, secret: &[u8]) -> Vec<u8> {
-        let payload = self.encode_payload();
-        let signature = hmac_sha256(secret, &payload);
-        let mut result = Vec::with_capacity(SIGNED_TOKEN_SIZE);
-        result.extend_from_slice(&payload);
-        result.extend_from_slice(&signature);
+    /// Encrypt the token with XChaCha20-Poly1305 under a key derived from `secret`, so
+    /// clients and network observers can't read session/client/state ids out of it.
+    ///
+    /// Layout: `version (1) || nonce (24) || ciphertext (40) || tag (16)`.
+    pub fn seal(&self, secret: &[u8]) -> Vec<u8> {
+        let mut nonce = [0u8; XNONCE_SIZE];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let mut in_out = self.encode_payload();
+        xchacha20_poly1305_seal(
+            &sealing_key(secret),
+            &nonce,
+            &[SEALED_TOKEN_VERSION],
+            &mut in_out,
+        );
+
+        let mut result = Vec::with_capacity(SEALED_TOKEN_SIZE);
+        result.push(SEALED_TOKEN_VERSION);
+        result.extend_from_slice(&nonce);
+        result.extend_from_slice(&in_out);
         result
     }
 
-    pub fn decode_signed(bytes: &[u8], secret: &[u8]) -> Option<Self> {
-        if bytes.len() < SIGNED_TOKEN_SIZE {
+    /// Decrypt a token produced by [`ResumeToken::seal`].
+    pub fn open(bytes: &[u8], secret: &[u8]) -> Option<Self> {
+        if bytes.len() != SEALED_TOKEN_SIZE || bytes[0] != SEALED_TOKEN_VERSION {
             return None;
         }
-        let (payload, signature) = bytes.split_at(bytes.len() - SIGNATURE_SIZE);
-        let expected_sig = hmac_sha256(secret, payload);
-        if !constant_time_eq(signature, &expected_sig) {
-            return None;
-        }
-        Self::decode_payload(payload)
+        let (nonce, ciphertext) = bytes[1..].split_at(XNONCE_SIZE);
+        let mut in_out = ciphertext.to_vec();
+        xchacha20_poly1305_open(
+            &sealing_key(secret),
+            nonce.try_into().ok()?,
+            &[SEALED_TOKEN_VERSION],
+            &mut in_out,
+        )?;
+        Self::decode_payload(&in_out)
     }
 
     fn encode_payload(&self) -> Vec<u8> {
@@ -86,11 +124,7 @@ impl ResumeToken {
     }
 
     pub fn is_expired(&self, max_age_ms: u64) -> bool {
-        let current_time_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_millis() as u64)
-            .unwrap_or(0);
-        self.is_expired_at(max_age_ms, current_time_ms)
+        self.is_expired_at(max_age_ms, SystemClock.unix_ms())
     }
 
     pub fn is_expired_at(&self, max_age_ms: u64, current_time_ms: u64) -> bool {
@@ -124,7 +158,37 @@ fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
     mac.finalize().into_bytes().into()
 }
 
-fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+fn sealing_key(secret: &[u8]) -> [u8; 32] {
+    hmac_sha256(secret, SEALING_KEY_CONTEXT)
+}
+
+/// XChaCha20-Poly1305 (draft-irtf-cfrg-xchacha), whose 24-byte nonce is large enough
+/// to pick at random for every token. Appends the tag to `in_out`.
+pub(crate) fn xchacha20_poly1305_seal(
+    key: &[u8; 32],
+    nonce: &[u8; XNONCE_SIZE],
+    aad: &[u8],
+    in_out: &mut Vec<u8>,
+) {
+    XChaCha20Poly1305::new(key.into())
+        .encrypt_in_place(XNonce::from_slice(nonce), aad, in_out)
+        .expect("token payload is far below the XChaCha20 length limit");
+}
+
+/// Decrypt what [`xchacha20_poly1305_seal`] produced in place, dropping the tag.
+/// None if it doesn't authenticate under `key` and `aad`.
+pub(crate) fn xchacha20_poly1305_open(
+    key: &[u8; 32],
+    nonce: &[u8; XNONCE_SIZE],
+    aad: &[u8],
+    in_out: &mut Vec<u8>,
+) -> Option<()> {
+    XChaCha20Poly1305::new(key.into())
+        .decrypt_in_place(XNonce::from_slice(nonce), aad, in_out)
+        .ok()
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
     if a.len() != b.len() {
         return false;
     }
@@ -153,7 +217,7 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_encode_decode_signed() {
+    fn test_seal_open_roundtrip() {
         let secret = b"test_secret_key_12345678901234567890";
         let token = ResumeToken {
             session_id: 123,
@@ -163,14 +227,14 @@ mod tests {
             issued_at_ms: 1000000,
         };
 
-        let encoded = token.encode_signed(secret);
-        let decoded = ResumeToken::decode_signed(&encoded, secret).unwrap();
+        let encoded = token.seal(secret);
+        let decoded = ResumeToken::open(&encoded, secret).unwrap();
 
         assert_eq!(token, decoded);
     }
 
     #[test]
-    fn test_tampered_signature_rejected() {
+    fn test_tampered_tag_rejected() {
         let secret = b"test_secret_key_12345678901234567890";
         let token = ResumeToken {
             session_id: 123,
@@ -180,10 +244,10 @@ mod tests {
             issued_at_ms: 1000000,
         };
 
-        let mut encoded = token.encode_signed(secret);
-        encoded[PAYLOAD_SIZE] ^= 0xff;
+        let mut encoded = token.seal(secret);
+        encoded[SEALED_TOKEN_SIZE - 1] ^= 0xff;
 
-        assert!(ResumeToken::decode_signed(&encoded, secret).is_none());
+        assert!(ResumeToken::open(&encoded, secret).is_none());
     }
 
     #[test]
@@ -198,8 +262,8 @@ mod tests {
             issued_at_ms: 1000000,
         };
 
-        let encoded = token.encode_signed(secret1);
-        assert!(ResumeToken::decode_signed(&encoded, secret2).is_none());
+        let encoded = token.seal(secret1);
+        assert!(ResumeToken::open(&encoded, secret2).is_none());
     }
 
     #[test]
@@ -213,18 +277,18 @@ mod tests {
             issued_at_ms: 1000000,
         };
 
-        let mut encoded = token.encode_signed(secret);
+        let mut encoded = token.seal(secret);
         encoded[0] ^= 0xff;
 
-        assert!(ResumeToken::decode_signed(&encoded, secret).is_none());
+        assert!(ResumeToken::open(&encoded, secret).is_none());
     }
 
     #[test]
     fn test_short_token_rejected() {
         let secret = b"test_secret_key_12345678901234567890";
-        let short_data = vec![0u8; SIGNED_TOKEN_SIZE - 1];
+        let short_data = vec![SEALED_TOKEN_VERSION; SEALED_TOKEN_SIZE - 1];
 
-        assert!(ResumeToken::decode_signed(&short_data, secret).is_none());
+        assert!(ResumeToken::open(&short_data, secret).is_none());
     }
 
     #[test]
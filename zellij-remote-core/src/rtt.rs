@@ -25,6 +25,14 @@ const SAMPLE_WINDOW_SIZE: u64 = 128;
 
 const MIN_ELAPSED_MS: u64 = 10;
 
+// Latency histogram: exact below LATENCY_SUB_BUCKETS ms, then every power of two is
+// split into LATENCY_SUB_BUCKETS buckets, so a percentile is off by at most ~3%
+const LATENCY_SUB_BUCKET_BITS: u32 = 4;
+const LATENCY_SUB_BUCKETS: u32 = 1 << LATENCY_SUB_BUCKET_BITS;
+const LATENCY_MAX_MS: u32 = u16::MAX as u32;
+const LATENCY_BUCKETS: usize =
+    (LATENCY_SUB_BUCKETS * (u16::BITS - LATENCY_SUB_BUCKET_BITS + 1)) as usize;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum LinkState {
     Stable,
@@ -33,6 +41,94 @@ pub enum LinkState {
     Degraded,
 }
 
+/// Input latency at a glance, e.g. to put numbers on "it feels laggy"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LatencyPercentiles {
+    pub p50_ms: u32,
+    pub p95_ms: u32,
+    pub p99_ms: u32,
+    pub samples: u64,
+}
+
+/// Streaming HDR-style histogram of RTT samples: fixed memory, any percentile on demand.
+/// Samples above `LATENCY_MAX_MS` are counted as `LATENCY_MAX_MS`.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    buckets: [u64; LATENCY_BUCKETS],
+    count: u64,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: [0; LATENCY_BUCKETS],
+            count: 0,
+        }
+    }
+
+    pub fn record(&mut self, latency_ms: u32) {
+        self.buckets[bucket_index(latency_ms.min(LATENCY_MAX_MS))] += 1;
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The latency `percentile` (0-100) of samples were at or below, rounded to the
+    /// middle of its bucket. None until a sample has been recorded.
+    pub fn percentile(&self, percentile: f64) -> Option<u32> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((percentile.clamp(0.0, 100.0) / 100.0) * self.count as f64).ceil() as u64;
+        let rank = rank.max(1);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(bucket_value(index));
+            }
+        }
+        None
+    }
+
+    pub fn percentiles(&self) -> Option<LatencyPercentiles> {
+        Some(LatencyPercentiles {
+            p50_ms: self.percentile(50.0)?,
+            p95_ms: self.percentile(95.0)?,
+            p99_ms: self.percentile(99.0)?,
+            samples: self.count,
+        })
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn bucket_index(latency_ms: u32) -> usize {
+    if latency_ms < LATENCY_SUB_BUCKETS {
+        return latency_ms as usize;
+    }
+    let shift = (u32::BITS - 1 - latency_ms.leading_zeros()) - LATENCY_SUB_BUCKET_BITS;
+    let sub_bucket = (latency_ms >> shift) - LATENCY_SUB_BUCKETS;
+    ((shift + 1) * LATENCY_SUB_BUCKETS + sub_bucket) as usize
+}
+
+fn bucket_value(index: usize) -> u32 {
+    let index = index as u32;
+    if index < LATENCY_SUB_BUCKETS {
+        return index;
+    }
+    let shift = index / LATENCY_SUB_BUCKETS - 1;
+    let sub_bucket = index % LATENCY_SUB_BUCKETS;
+    let low = (LATENCY_SUB_BUCKETS + sub_bucket) << shift;
+    low + ((1 << shift) - 1) / 2
+}
+
 #[derive(Debug, Clone)]
 pub struct RttEstimator {
     srtt_ms: Option<f64>,
@@ -45,6 +141,7 @@ pub struct RttEstimator {
     state_candidate: LinkState,
     candidate_since_ms: u64,
     monotonic_time_ms: u64,
+    histogram: LatencyHistogram,
 }
 
 impl RttEstimator {
@@ -60,6 +157,7 @@ impl RttEstimator {
             state_candidate: LinkState::Normal,
             candidate_since_ms: 0,
             monotonic_time_ms: 0,
+            histogram: LatencyHistogram::new(),
         }
     }
 
@@ -72,6 +170,7 @@ impl RttEstimator {
 
         match rtt_ms {
             Some(rtt) => {
+                self.histogram.record(rtt);
                 let rtt_f = rtt as f64;
 
                 match self.srtt_ms {
@@ -203,6 +302,15 @@ impl RttEstimator {
     pub fn rttvar_ms(&self) -> f64 {
         self.rttvar_ms
     }
+
+    /// p50/p95/p99 over every sample recorded so far, None before the first one
+    pub fn latency_percentiles(&self) -> Option<LatencyPercentiles> {
+        self.histogram.percentiles()
+    }
+
+    pub fn latency_histogram(&self) -> &LatencyHistogram {
+        &self.histogram
+    }
 }
 
 impl Default for RttEstimator {
@@ -1,31 +1,40 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::Range;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::Duration;
 
-use rand::RngCore;
+use prost::Message;
 
+use crate::backpressure::WindowBounds;
 use crate::client_state::ClientRenderState;
-use crate::frame::FrameStore;
-use crate::input::{InputProcessResult, InputReceiver};
-use crate::lease::LeaseManager;
+use crate::clock::{SharedClock, SystemClock};
+use crate::delta::DeltaEngine;
+use crate::echo::RecentInput;
+use crate::frame::{FrameFingerprint, FrameStore, LineSize};
+use crate::input::{InputProcessResult, InputReceiver, ReorderWindow};
+use crate::lease::{LeaseEvent, LeaseManager};
+use crate::render_job::{DeltaInputs, Output, RenderJob, RenderOutput};
 use crate::resume_token::{ResumeResult, ResumeToken};
-use crate::rtt::RttEstimator;
+use crate::rtt::{LatencyPercentiles, RttEstimator};
 use crate::state_history::StateHistory;
 use crate::style_table::StyleTable;
+use crate::token_keys::TokenKeyring;
 use zellij_remote_protocol::{
-    ControllerPolicy, InputAck, InputEvent, ScreenDelta, ScreenSnapshot, StateAck,
+    stream_envelope, AttachMode, AttachRequest, AttachResponse, ColorTransform, ControllerPolicy,
+    Detach, FlashGuardSettings, InputAck, InputBatch, InputEvent, LinkStats, ScreenDelta,
+    ScreenSnapshot, StateAck, StreamEnvelope,
 };
 
-#[cfg(not(test))]
-use std::time::Duration;
-
-#[cfg(test)]
-use crate::lease::Duration;
-
 const DEFAULT_LEASE_DURATION_SECS: u64 = 30;
 const DEFAULT_HISTORY_SIZE: usize = 64;
 const DEFAULT_TOKEN_EXPIRY_MS: u64 = 300_000; // 5 minutes
 const DEFAULT_MAX_CLOCK_SKEW_MS: u64 = 30_000; // 30 seconds
+/// Events kept for [`RemoteSession::drain_events`]; past this the oldest are dropped
+pub const MAX_PENDING_EVENTS: usize = 1024;
+/// A preloaded snapshot is rebuilt once the screen is this many states past it, so
+/// it stays well inside the state history and catch-up deltas from it stay sparse
+pub const SNAPSHOT_PRELOAD_STATES: u64 = 32;
 
 static SESSION_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
 
@@ -35,11 +44,52 @@ pub enum RenderUpdate {
     Delta(ScreenDelta),
 }
 
+/// A snapshot encoded once and handed to attaching clients as their first frame,
+/// see [`RemoteSession::preload_snapshot`]
+#[derive(Debug, Clone)]
+pub struct CachedSnapshot {
+    pub state_id: u64,
+    /// The snapshot in a `StreamEnvelope`, length-delimited the way stream messages
+    /// are framed, ready to write as is
+    pub encoded: Arc<[u8]>,
+    fingerprint: FrameFingerprint,
+    /// Styles the snapshot defines, ids 0 up to this
+    style_count: usize,
+}
+
+/// Something that happened to the session that servers broadcast or audit, queued
+/// until the next [`RemoteSession::drain_events`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    /// The controller lease ended: it expired, was revoked, or its owner left
+    Lease(LeaseEvent),
+    ClientAdded {
+        client_id: u64,
+    },
+    /// A client came back with a resume token
+    ClientResumed {
+        client_id: u64,
+        baseline_state_id: u64,
+    },
+    ClientRemoved {
+        client_id: u64,
+    },
+    /// A client's baseline was dropped, so its next update is a snapshot
+    Resync {
+        client_id: u64,
+    },
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum InputError {
     ClientNotFound,
     NotController,
-    OutOfOrder { expected: u64, received: u64 },
+    OutOfOrder {
+        expected: u64,
+        received: u64,
+    },
+    /// Early, kept in the reorder window until the inputs before it arrive
+    Held,
     Duplicate,
 }
 
@@ -48,38 +98,68 @@ pub struct RemoteSession {
     pub style_table: StyleTable,
     pub lease_manager: LeaseManager,
     pub input_receivers: HashMap<u64, InputReceiver>,
+    /// Given to every client's input receiver
+    input_reorder_window: ReorderWindow,
+    /// Given to every client's render state
+    max_delta_bytes: Option<usize>,
     pub rtt_estimator: RttEstimator,
     pub clients: HashMap<u64, ClientRenderState>,
     pub state_history: StateHistory,
+    /// Text each client typed recently, for flagging its echo in deltas
+    recent_input: HashMap<u64, RecentInput>,
+    /// Latest link report from each client
+    link_stats: HashMap<u64, LinkStats>,
     pub session_id: u64,
     token_expiry_ms: u64,
     max_clock_skew_ms: u64,
-    token_secret: [u8; 32],
+    token_keys: TokenKeyring,
     /// Cached dirty_rows for current state_id (cleared on state advance)
     cached_dirty_rows: Option<(u64, HashSet<usize>)>,
+    /// Fingerprint of the current state, shared by every client's delta
+    cached_fingerprint: Option<(u64, FrameFingerprint)>,
+    /// When the session was rebuilt from one saved before a restart
+    restored_at_ms: Option<u64>,
+    /// Events since the last drain, oldest first
+    events: VecDeque<SessionEvent>,
+    /// Last preloaded snapshot, for attaching clients
+    cached_snapshot: Option<CachedSnapshot>,
+    /// Time for leases, input, pacing and resume tokens
+    clock: SharedClock,
 }
 
 impl RemoteSession {
     pub fn new(cols: usize, rows: usize) -> Self {
-        let mut token_secret = [0u8; 32];
-        rand::thread_rng().fill_bytes(&mut token_secret);
+        Self::with_clock(cols, rows, SystemClock::shared())
+    }
 
+    /// A session that reads the time from `clock` rather than the system
+    pub fn with_clock(cols: usize, rows: usize, clock: SharedClock) -> Self {
         Self {
             frame_store: FrameStore::new(cols, rows),
             style_table: StyleTable::new(),
             lease_manager: LeaseManager::new(
                 ControllerPolicy::LastWriterWins,
                 Duration::from_secs(DEFAULT_LEASE_DURATION_SECS),
-            ),
+            )
+            .with_clock(clock.clone()),
             input_receivers: HashMap::new(),
+            input_reorder_window: ReorderWindow::disabled(),
+            max_delta_bytes: None,
             rtt_estimator: RttEstimator::new(),
             clients: HashMap::new(),
             state_history: StateHistory::new(DEFAULT_HISTORY_SIZE),
+            recent_input: HashMap::new(),
+            link_stats: HashMap::new(),
             session_id: SESSION_ID_COUNTER.fetch_add(1, Ordering::Relaxed),
             token_expiry_ms: DEFAULT_TOKEN_EXPIRY_MS,
             max_clock_skew_ms: DEFAULT_MAX_CLOCK_SKEW_MS,
-            token_secret,
+            token_keys: TokenKeyring::generate(),
             cached_dirty_rows: None,
+            cached_fingerprint: None,
+            restored_at_ms: None,
+            events: VecDeque::new(),
+            cached_snapshot: None,
+            clock,
         }
     }
 
@@ -89,31 +169,385 @@ impl RemoteSession {
         session
     }
 
+    /// Rebuild a session saved before a restart: same id and token keys, with state
+    /// ids carrying on after `last_state_id`. Tokens issued before the restart open
+    /// again, but none of their states is in history, so their clients get a snapshot.
+    pub fn restore(
+        cols: usize,
+        rows: usize,
+        session_id: u64,
+        token_keys: TokenKeyring,
+        last_state_id: u64,
+        restored_at_ms: u64,
+    ) -> Self {
+        let mut session = Self::with_session_id(cols, rows, session_id);
+        session.token_keys = token_keys;
+        session.frame_store.continue_after_state(last_state_id);
+        session.restored_at_ms = Some(restored_at_ms);
+        session
+    }
+
+    /// Whether `token_bytes` is a valid token for this session issued before it was
+    /// restored, i.e. the client is reattaching to a resurrected session.
+    pub fn predates_restore(&self, token_bytes: &[u8]) -> bool {
+        let Some(restored_at_ms) = self.restored_at_ms else {
+            return false;
+        };
+        self.token_keys.open(token_bytes).is_some_and(|token| {
+            token.session_id == self.session_id && token.issued_at_ms <= restored_at_ms
+        })
+    }
+
     #[cfg(test)]
     pub fn with_token_secret(cols: usize, rows: usize, secret: [u8; 32]) -> Self {
         let mut session = Self::new(cols, rows);
-        session.token_secret = secret;
+        session.token_keys = TokenKeyring::new(secret);
         session
     }
 
     pub fn add_client(&mut self, client_id: u64, window_size: u32) {
         self.clients
-            .insert(client_id, ClientRenderState::new(window_size));
-        self.input_receivers.insert(client_id, InputReceiver::new());
+            .insert(client_id, self.new_client_state(window_size));
+        let receiver = InputReceiver::new()
+            .with_reorder_window(self.input_reorder_window)
+            .with_clock(self.clock.clone());
+        self.input_receivers.insert(client_id, receiver);
+        self.push_event(SessionEvent::ClientAdded { client_id });
+    }
+
+    fn new_client_state(&self, window_size: u32) -> ClientRenderState {
+        let mut client_state = ClientRenderState::with_clock(window_size, self.clock.clone());
+        client_state.set_max_delta_bytes(self.max_delta_bytes);
+        client_state
+    }
+
+    /// Let the client's render window be tuned within `bounds` (e.g. as negotiated in
+    /// the handshake). Returns false if the client is unknown.
+    pub fn set_window_bounds(&mut self, client_id: u64, bounds: WindowBounds) -> bool {
+        match self.clients.get_mut(&client_id) {
+            Some(client_state) => {
+                client_state.render_window_mut().set_bounds(bounds);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Let the client be switched to text mode when its link starves (it advertised
+    /// `supports_text_mode`). Returns false if the client is unknown.
+    pub fn set_text_mode_allowed(&mut self, client_id: u64, allowed: bool) -> bool {
+        match self.clients.get_mut(&client_id) {
+            Some(client_state) => {
+                client_state.text_mode_mut().set_allowed(allowed);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Put `frame_hash` on the client's snapshots and deltas (it advertised
+    /// `supports_frame_hash`). Returns false if the client is unknown.
+    pub fn set_frame_hash_enabled(&mut self, client_id: u64, enabled: bool) -> bool {
+        match self.clients.get_mut(&client_id) {
+            Some(client_state) => {
+                client_state.set_frame_hash_enabled(enabled);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// The client asked for a snapshot with `REASON_DECODE_ERROR`. In integrity mode
+    /// that means its screen didn't match `frame_hash`: returns the client's mismatch
+    /// count so far, or None if it isn't using integrity mode.
+    pub fn record_frame_hash_mismatch(&mut self, client_id: u64) -> Option<u64> {
+        let client_state = self.clients.get_mut(&client_id)?;
+        if !client_state.frame_hash_enabled() {
+            return None;
+        }
+        Some(client_state.record_frame_hash_mismatch())
+    }
+
+    /// Deltas for the client that went out as snapshots because they were too large
+    pub fn delta_fallbacks(&self, client_id: u64) -> u64 {
+        self.clients
+            .get(&client_id)
+            .map_or(0, |c| c.delta_fallbacks())
+    }
+
+    pub fn frame_hash_mismatches(&self, client_id: u64) -> u64 {
+        self.clients
+            .get(&client_id)
+            .map_or(0, |c| c.frame_hash_mismatches())
+    }
+
+    /// Attach `DebugFrameInfo` to the client's snapshots and deltas (it set
+    /// `debug_frame_info` in its hello). Only builds with the `debug-frame-info`
+    /// feature honour it. Returns false if the client is unknown.
+    pub fn set_debug_frame_info_enabled(&mut self, client_id: u64, enabled: bool) -> bool {
+        match self.clients.get_mut(&client_id) {
+            Some(client_state) => {
+                client_state.set_debug_frame_info_enabled(enabled);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Put `row_crc32` on the client's row patches (it advertised
+    /// `supports_row_checksums`). Returns false if the client is unknown.
+    pub fn set_row_checksums_enabled(&mut self, client_id: u64, enabled: bool) -> bool {
+        match self.clients.get_mut(&client_id) {
+            Some(client_state) => {
+                client_state.set_row_checksums_enabled(enabled);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// The client asked for a snapshot with `REASON_BASE_MISMATCH`. If it checks row
+    /// checksums that may be a patched row that didn't match `row_crc32`: returns the
+    /// client's divergence count so far, or None if it doesn't check them.
+    pub fn record_row_checksum_mismatch(&mut self, client_id: u64) -> Option<u64> {
+        let client_state = self.clients.get_mut(&client_id)?;
+        if !client_state.row_checksums_enabled() {
+            return None;
+        }
+        Some(client_state.record_row_checksum_mismatch())
+    }
+
+    pub fn row_checksum_mismatches(&self, client_id: u64) -> u64 {
+        self.clients
+            .get(&client_id)
+            .map_or(0, |c| c.row_checksum_mismatches())
+    }
+
+    /// Let snapshots extend the style table the client already holds instead of
+    /// resetting it (it advertised `supports_style_retention`). Returns false if the
+    /// client is unknown.
+    pub fn set_style_retention_enabled(&mut self, client_id: u64, enabled: bool) -> bool {
+        match self.clients.get_mut(&client_id) {
+            Some(client_state) => {
+                client_state.set_style_retention_enabled(enabled);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Send the client underline styles and colors as they are (it advertised
+    /// `supports_styled_underlines`); otherwise they are reduced to a single underline.
+    /// Returns false if the client is unknown.
+    pub fn set_styled_underlines(&mut self, client_id: u64, enabled: bool) -> bool {
+        match self.clients.get_mut(&client_id) {
+            Some(client_state) => {
+                client_state.set_styled_underlines(enabled);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Send the client DEC line sizes (it advertised `supports_line_size`); otherwise
+    /// its rows all go out single. Returns false if the client is unknown.
+    pub fn set_line_sizes(&mut self, client_id: u64, enabled: bool) -> bool {
+        match self.clients.get_mut(&client_id) {
+            Some(client_state) => {
+                client_state.set_line_sizes(enabled);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Recolor the styles sent to the client as it asked in its `ClientHello`, leaving
+    /// other clients alone. Returns false if the client is unknown.
+    pub fn set_color_transform(&mut self, client_id: u64, transform: ColorTransform) -> bool {
+        match self.clients.get_mut(&client_id) {
+            Some(client_state) => {
+                client_state.set_color_transform(transform);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Limit flashing content on the client as it asked in its `ClientHello`. Returns
+    /// false if the client is unknown.
+    pub fn set_flash_guard(&mut self, client_id: u64, settings: &FlashGuardSettings) -> bool {
+        match self.clients.get_mut(&client_id) {
+            Some(client_state) => {
+                client_state.set_flash_guard(settings);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Make the client's next snapshot reset its style table, e.g. after it reported
+    /// a decode error.
+    pub fn forget_client_styles(&mut self, client_id: u64) {
+        if let Some(client_state) = self.clients.get_mut(&client_id) {
+            client_state.forget_styles();
+        }
+    }
+
+    /// Make every client's next update resend the styles it uses, after the style
+    /// table's ids were handed out afresh.
+    pub fn forget_all_client_styles(&mut self) {
+        for client_state in self.clients.values_mut() {
+            client_state.forget_styles();
+        }
+    }
+
+    /// Stop preparing frames for a backgrounded client, or start again once it is
+    /// visible. Its baseline is kept, so it resumes with a delta against the last frame
+    /// it acked, or a snapshot if it has none. Returns whether the visibility changed.
+    pub fn set_client_visible(&mut self, client_id: u64, visible: bool) -> bool {
+        self.clients
+            .get_mut(&client_id)
+            .is_some_and(|c| c.set_visible(visible))
+    }
+
+    pub fn is_client_visible(&self, client_id: u64) -> bool {
+        self.clients.get(&client_id).is_some_and(|c| c.is_visible())
+    }
+
+    /// Send the client only the rows in `region`, or the whole screen for None; its
+    /// next frame is a snapshot of them. Returns whether the region changed.
+    pub fn set_client_region(&mut self, client_id: u64, region: Option<Range<usize>>) -> bool {
+        self.clients
+            .get_mut(&client_id)
+            .is_some_and(|c| c.set_region(region))
+    }
+
+    pub fn is_text_mode(&self, client_id: u64) -> bool {
+        self.clients
+            .get(&client_id)
+            .is_some_and(|c| c.text_mode().is_active())
+    }
+
+    /// Cap the client's frame rate to one frame per `interval_ms`; 0 lifts the cap.
+    /// Frames held back coalesce into the next one that is due. Returns whether the
+    /// cap changed.
+    pub fn set_min_frame_interval(&mut self, client_id: u64, interval_ms: u64) -> bool {
+        self.clients
+            .get_mut(&client_id)
+            .is_some_and(|c| c.set_min_frame_interval(interval_ms))
+    }
+
+    /// Whether the client's frames can be held back, by text mode or a frame rate cap,
+    /// so that a held frame needs flushing later.
+    pub fn is_frame_paced(&self, client_id: u64) -> bool {
+        self.clients.get(&client_id).is_some_and(|c| c.is_paced())
+    }
+
+    /// Whether the client hasn't been sent the current state yet, e.g. because text
+    /// mode or a frame rate cap held the frame back, or the flash guard is due to
+    /// bring a held row up to date.
+    pub fn has_unsent_state(&self, client_id: u64) -> bool {
+        self.clients.get(&client_id).is_some_and(|c| {
+            !c.has_baseline()
+                || c.pending_state_id() < self.frame_store.current_state_id()
+                || c.flash_release_due()
+        })
     }
 
     pub fn remove_client(&mut self, client_id: u64) {
-        self.clients.remove(&client_id);
+        let known = self.clients.remove(&client_id).is_some();
         self.input_receivers.remove(&client_id);
-        self.lease_manager.remove_client(client_id);
+        self.recent_input.remove(&client_id);
+        self.link_stats.remove(&client_id);
+        if let Some(event) = self.lease_manager.remove_client(client_id) {
+            self.push_event(SessionEvent::Lease(event));
+        }
+        if known {
+            self.push_event(SessionEvent::ClientRemoved { client_id });
+        }
+    }
+
+    /// Handle a client's `Detach` ahead of its removal: release its lease with reason
+    /// "detach" and, unless it asked to keep them, revoke its resume tokens. Returns
+    /// the lease event if it held control.
+    pub fn detach_client(&mut self, client_id: u64, detach: &Detach) -> Option<LeaseEvent> {
+        if !detach.keep_resume_state {
+            self.revoke_client_resume_tokens(client_id);
+        }
+        let event = self
+            .lease_manager
+            .remove_client_with_reason(client_id, "detach")?;
+        self.push_event(SessionEvent::Lease(event.clone()));
+        Some(event)
+    }
+
+    /// Expire the lease if it's due (see [`LeaseManager::tick`]), queueing the event.
+    pub fn tick_lease(&mut self) -> Option<LeaseEvent> {
+        let event = self.lease_manager.tick()?;
+        self.push_event(SessionEvent::Lease(event.clone()));
+        Some(event)
+    }
+
+    /// Revoke the current lease with `reason`, queueing the event.
+    pub fn revoke_lease(&mut self, reason: &str) -> Option<LeaseEvent> {
+        let event = self.lease_manager.revoke(reason)?;
+        self.push_event(SessionEvent::Lease(event.clone()));
+        Some(event)
+    }
+
+    /// Queue the takeover the last granted control request caused, if any (see
+    /// [`LeaseManager::take_takeover`]).
+    pub fn queue_takeover(&mut self) -> Option<LeaseEvent> {
+        let event = self.lease_manager.take_takeover()?;
+        self.push_event(SessionEvent::Lease(event.clone()));
+        Some(event)
     }
 
+    /// Everything queued since the last call, oldest first. Lease events from
+    /// `lease_manager` itself are only queued when they go through the session.
+    pub fn drain_events(&mut self) -> Vec<SessionEvent> {
+        self.events.drain(..).collect()
+    }
+
+    fn push_event(&mut self, event: SessionEvent) {
+        if self.events.len() == MAX_PENDING_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Keep the client's latest `LinkStats` report. Returns false if the client is unknown.
+    pub fn record_link_stats(&mut self, client_id: u64, stats: LinkStats) -> bool {
+        if !self.clients.contains_key(&client_id) {
+            return false;
+        }
+        self.link_stats.insert(client_id, stats);
+        true
+    }
+
+    /// Input latency percentiles the client last reported, if it has sent any
+    pub fn input_latency(&self, client_id: u64) -> Option<LatencyPercentiles> {
+        let stats = self.link_stats.get(&client_id)?;
+        if stats.input_latency_samples == 0 {
+            return None;
+        }
+        Some(LatencyPercentiles {
+            p50_ms: stats.input_latency_p50_ms,
+            p95_ms: stats.input_latency_p95_ms,
+            p99_ms: stats.input_latency_p99_ms,
+            samples: stats.input_latency_samples,
+        })
+    }
+
+    /// Apply one input from the controller and ack it. Held inputs this releases are
+    /// recorded but not returned, so a session with a reorder window set should take
+    /// input through [`Self::process_input_batch`].
     pub fn process_input(
         &mut self,
         client_id: u64,
         input: &InputEvent,
     ) -> Result<InputAck, InputError> {
-        if !self.lease_manager.is_controller(client_id) {
+        if !self.lease_manager.accepts_input(client_id) {
             return Err(InputError::NotController);
         }
 
@@ -123,7 +557,17 @@ impl RemoteSession {
             .ok_or(InputError::ClientNotFound)?;
 
         match receiver.process_input(input) {
-            InputProcessResult::Processed => Ok(receiver.generate_ack()),
+            InputProcessResult::Processed => {
+                let released = receiver.take_released();
+                let ack = receiver.generate_ack();
+                self.lease_manager.record_input(client_id);
+                let recent = self.recent_input.entry(client_id).or_default();
+                for event in std::iter::once(input).chain(&released) {
+                    recent.record(event);
+                }
+                Ok(ack)
+            },
+            InputProcessResult::Held => Err(InputError::Held),
             InputProcessResult::Duplicate => Err(InputError::Duplicate),
             InputProcessResult::OutOfOrder { expected, received } => {
                 Err(InputError::OutOfOrder { expected, received })
@@ -131,6 +575,41 @@ impl RemoteSession {
         }
     }
 
+    /// Like [`Self::process_input`] for a batch; returns the ack along with the events
+    /// to apply, in order: those that weren't already processed, then any held inputs
+    /// the batch released.
+    pub fn process_input_batch(
+        &mut self,
+        client_id: u64,
+        batch: &InputBatch,
+    ) -> Result<(InputAck, Vec<InputEvent>), InputError> {
+        if !self.lease_manager.accepts_input(client_id) {
+            return Err(InputError::NotController);
+        }
+
+        let receiver = self
+            .input_receivers
+            .get_mut(&client_id)
+            .ok_or(InputError::ClientNotFound)?;
+
+        match receiver.process_batch(batch) {
+            Ok(events) => {
+                let ack = receiver.generate_ack();
+                self.lease_manager.record_input(client_id);
+                let recent = self.recent_input.entry(client_id).or_default();
+                for event in &events {
+                    recent.record(event);
+                }
+                Ok((ack, events))
+            },
+            Err(InputProcessResult::OutOfOrder { expected, received }) => {
+                Err(InputError::OutOfOrder { expected, received })
+            },
+            Err(InputProcessResult::Held) => Err(InputError::Held),
+            Err(_) => Err(InputError::Duplicate),
+        }
+    }
+
     pub fn process_state_ack(&mut self, client_id: u64, ack: &StateAck) {
         if let Some(client_state) = self.clients.get_mut(&client_id) {
             client_state.process_state_ack(ack);
@@ -141,42 +620,152 @@ impl RemoteSession {
 
             let pending_state_id = client_state.pending_state_id();
             if ack.last_applied_state_id >= pending_state_id {
-                if let Some(pending_frame) = client_state.pending_frame().cloned() {
-                    client_state.advance_baseline(ack.last_applied_state_id, pending_frame);
+                if let Some(pending) = client_state.pending_frame().cloned() {
+                    client_state.advance_baseline(ack.last_applied_state_id, pending);
                 }
             }
         }
     }
 
     pub fn get_render_update(&mut self, client_id: u64) -> Option<RenderUpdate> {
+        let job = self.begin_render_update(client_id)?;
+        self.finish_render_update(job.compute())
+    }
+
+    /// First half of [`Self::get_render_update`]: decide what the client gets and
+    /// capture what computing it needs. Snapshots are prepared here; deltas are
+    /// diffed by [`RenderJob::compute`], which doesn't need the session and may run
+    /// on another thread, before going back through [`Self::finish_render_update`].
+    pub fn begin_render_update(&mut self, client_id: u64) -> Option<RenderJob> {
         // Get cached dirty_rows for current state (captures from FrameStore on first call)
         // Clone to avoid borrow conflict with frame_store
         let dirty_rows = self.get_dirty_rows_for_current_state().clone();
-        let current_frame = self.frame_store.current_frame().clone();
+        let mut current_frame = self.frame_store.current_frame().clone();
         let current_state_id = self.frame_store.current_state_id();
 
+        if !self.clients.contains_key(&client_id) {
+            return None;
+        }
+        // Clients keep only fingerprints of their baselines; the frames behind them
+        // live in the shared history
+        self.record_state_snapshot();
+        let mut current_fingerprint = self.fingerprint_for_current_state().clone();
+
         let client_state = self.clients.get_mut(&client_id)?;
+        if !client_state.is_visible() {
+            return None;
+        }
+        if let Some(change) = client_state.observe_link() {
+            log::info!("Client {} text mode: {:?}", client_id, change);
+        }
+        if client_state.observe_size(current_frame.cols, current_frame.rows.len()) {
+            log::debug!(
+                "Client {} needs a snapshot after resize to {}x{}",
+                client_id,
+                current_frame.cols,
+                current_frame.rows.len()
+            );
+        }
+        if !client_state.frame_due() {
+            return None;
+        }
+        client_state.flatten_line_sizes(&mut current_frame, &mut current_fingerprint);
+        let held = client_state.hold_flashing_rows(&mut current_frame, &mut current_fingerprint);
 
+        let watermark = self
+            .input_receivers
+            .get(&client_id)
+            .map_or(0, |receiver| receiver.last_acked_seq());
         if client_state.should_send_snapshot() {
-            let snapshot = client_state.prepare_snapshot(
+            client_state.set_frame_held(held);
+            let mut snapshot = client_state.prepare_snapshot(
                 &current_frame,
                 current_state_id,
                 &mut self.style_table,
             );
-            Some(RenderUpdate::Snapshot(snapshot))
+            snapshot.delivered_input_watermark = watermark;
+            Some(RenderJob::ready(
+                client_id,
+                RenderUpdate::Snapshot(snapshot),
+            ))
         } else if client_state.can_send() {
-            let delta = client_state.prepare_delta(
-                &current_frame,
-                current_state_id,
-                &mut self.style_table,
-                Some(&dirty_rows),
-            );
-            delta.map(RenderUpdate::Delta)
+            let mut dirty_rows = if client_state.full_diff_due() {
+                (0..current_frame.rows.len()).collect()
+            } else {
+                dirty_rows
+            };
+            // Held rows differ from the frames the dirty rows were tracked between
+            let baseline_held = client_state.baseline_held();
+            dirty_rows.extend(held.keys().chain(baseline_held.keys()));
+            let baseline = client_state.baseline()?.clone();
+            let base_state_id = client_state.baseline_state_id();
+            let mut baseline_frame = self.state_history.get(base_state_id).cloned();
+            if let Some(frame) = &mut baseline_frame {
+                for (idx, row) in baseline_held {
+                    if let Some(baseline_row) = frame.rows.get_mut(*idx) {
+                        *baseline_row = row.clone();
+                    }
+                }
+            }
+            let inputs = DeltaInputs {
+                baseline,
+                baseline_frame,
+                current: current_frame,
+                current_fingerprint,
+                base_state_id,
+                state_id: current_state_id,
+                dirty_rows: Arc::new(dirty_rows),
+                delivered_input_watermark: watermark,
+                frame_hash: client_state.frame_hash_due(),
+                row_checksums: client_state.row_checksums_due(),
+                debug_frame_info: client_state.debug_frame_info_enabled(),
+                held_rows: held,
+            };
+            Some(RenderJob::delta(client_id, inputs))
         } else {
             None
         }
     }
 
+    /// Second half of [`Self::get_render_update`]: record a computed update as sent.
+    /// A delta whose baseline moved on while it was being computed (an ack came in)
+    /// is dropped; the next frame is diffed against the new baseline.
+    pub fn finish_render_update(&mut self, output: RenderOutput) -> Option<RenderUpdate> {
+        let client_id = output.client_id();
+        let (mut delta, fingerprint, frame, held_rows) = match output.into_result() {
+            Output::Ready(update) => return Some(update),
+            Output::Delta {
+                delta,
+                fingerprint,
+                frame,
+                held_rows,
+            } => (delta, fingerprint, frame, held_rows),
+        };
+        let client_state = self.clients.get_mut(&client_id)?;
+        if !client_state.has_baseline() || client_state.baseline_state_id() != delta.base_state_id {
+            log::trace!(
+                "Client {} baseline moved past {}, dropping delta",
+                client_id,
+                delta.base_state_id
+            );
+            return None;
+        }
+        if let Some(recent) = self.recent_input.get(&client_id) {
+            let watermark = delta.delivered_input_watermark;
+            recent.mark_echo(&mut delta, frame.cursor.row, watermark);
+        }
+        client_state.set_frame_held(held_rows);
+        let update = client_state.finish_update(delta, &frame, &fingerprint, &mut self.style_table);
+        if matches!(update, RenderUpdate::Snapshot(_)) {
+            log::debug!(
+                "Client {} delta too large, sent a snapshot instead ({} so far)",
+                client_id,
+                client_state.delta_fallbacks()
+            );
+        }
+        Some(update)
+    }
+
     pub fn client_count(&self) -> usize {
         self.clients.len()
     }
@@ -188,11 +777,110 @@ impl RemoteSession {
     pub fn force_client_snapshot(&mut self, client_id: u64) {
         if let Some(client_state) = self.clients.get_mut(&client_id) {
             client_state.reset_baseline();
+            self.push_event(SessionEvent::Resync { client_id });
+        }
+    }
+
+    /// The current frame with the whole style table, for a one-shot capture by
+    /// someone who isn't a client: no client state is touched or created.
+    pub fn capture_snapshot(&mut self) -> ScreenSnapshot {
+        let mut snapshot = DeltaEngine::compute_snapshot(
+            self.frame_store.current_frame(),
+            &mut self.style_table,
+            self.frame_store.current_state_id(),
+        );
+        snapshot.server_time_ms = self.clock.unix_ms();
+        snapshot
+    }
+
+    /// Encode a snapshot of the current state for clients that attach later, unless
+    /// the one kept is recent enough: no more than [`SNAPSHOT_PRELOAD_STATES`] behind
+    /// and the current size. A client handed it by [`Self::take_cached_snapshot`] sees
+    /// the screen as soon as it attaches, and catches up with a delta computed like
+    /// any other. Returns whether a new snapshot was encoded.
+    pub fn preload_snapshot(&mut self) -> bool {
+        let current_state_id = self.frame_store.current_state_id();
+        let frame = self.frame_store.current_frame();
+        let fresh = self.cached_snapshot.as_ref().is_some_and(|cached| {
+            current_state_id.saturating_sub(cached.state_id) < SNAPSHOT_PRELOAD_STATES
+                && cached.fingerprint.cols == frame.cols
+                && cached.fingerprint.rows.len() == frame.rows.len()
+        });
+        if fresh {
+            return false;
+        }
+
+        // Catch-up deltas are diffed against the frame in history
+        self.record_state_snapshot();
+        let mut snapshot = DeltaEngine::compute_snapshot(
+            self.frame_store.current_frame(),
+            &mut self.style_table,
+            current_state_id,
+        );
+        // Only ever a client's first frame
+        snapshot.frame_sequence = 1;
+        snapshot.server_time_ms = self.clock.unix_ms();
+        let envelope = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ScreenSnapshot(snapshot)),
+            envelope_seq: 0,
+        };
+        let cached = CachedSnapshot {
+            state_id: current_state_id,
+            encoded: envelope.encode_length_delimited_to_vec().into(),
+            fingerprint: self.fingerprint_for_current_state().clone(),
+            style_count: self.style_table.current_count(),
+        };
+        log::debug!(
+            "Preloaded snapshot of state {} ({} bytes)",
+            cached.state_id,
+            cached.encoded.len()
+        );
+        self.cached_snapshot = Some(cached);
+        true
+    }
+
+    pub fn cached_snapshot(&self) -> Option<&CachedSnapshot> {
+        self.cached_snapshot.as_ref()
+    }
+
+    /// The preloaded snapshot as the client's first frame, recorded as sent, if it
+    /// can be: the client has been sent nothing, wants the whole screen, has no newer
+    /// baseline (a resumed client may) and takes messages of its size (`max_bytes`,
+    /// None for any). Its next update is a delta from the snapshot's state.
+    pub fn take_cached_snapshot(
+        &mut self,
+        client_id: u64,
+        max_bytes: Option<usize>,
+    ) -> Option<CachedSnapshot> {
+        let cached = self.cached_snapshot.as_ref()?;
+        let client_state = self.clients.get_mut(&client_id)?;
+        let usable = client_state.is_visible()
+            && client_state.can_take_shared_snapshot()
+            && (client_state.line_sizes()
+                || cached
+                    .fingerprint
+                    .rows
+                    .iter()
+                    .all(|row| row.line_size == LineSize::Single))
+            && (!client_state.has_baseline() || client_state.baseline_state_id() < cached.state_id)
+            && max_bytes.is_none_or(|max| cached.encoded.len() <= max);
+        if !usable {
+            return None;
         }
+        client_state.record_shared_snapshot(
+            cached.state_id,
+            &cached.fingerprint,
+            cached.style_count,
+            cached.encoded.len(),
+        );
+        Some(cached.clone())
     }
 
     pub fn record_state_snapshot(&mut self) {
         let state_id = self.frame_store.current_state_id();
+        if self.state_history.newest_state_id() == Some(state_id) {
+            return;
+        }
         let frame = self.frame_store.current_frame().clone();
         self.state_history.push(state_id, frame);
     }
@@ -210,25 +898,23 @@ impl RemoteSession {
             .map(|r| r.last_acked_seq())
             .unwrap_or(0);
 
-        let token = ResumeToken::new(
+        let token = ResumeToken::issued_at(
             self.session_id,
             client_id,
             last_applied_state_id,
             last_acked_input_seq,
+            self.clock.unix_ms(),
         );
-        token.encode_signed(&self.token_secret)
+        self.token_keys.seal(&token)
     }
 
     pub fn try_resume(&mut self, token_bytes: &[u8], window_size: u32) -> ResumeResult {
-        let token = match ResumeToken::decode_signed(token_bytes, &self.token_secret) {
+        let token = match self.token_keys.open(token_bytes) {
             Some(t) => t,
             None => return ResumeResult::InvalidToken,
         };
 
-        let current_time_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_millis() as u64)
-            .unwrap_or(0);
+        let current_time_ms = self.clock.unix_ms();
 
         if !token.is_valid_timestamp(
             self.token_expiry_ms,
@@ -257,24 +943,112 @@ impl RemoteSession {
         }
 
         self.clients
-            .insert(token.client_id, ClientRenderState::new(window_size));
+            .insert(token.client_id, self.new_client_state(window_size));
         self.input_receivers.insert(
             token.client_id,
-            InputReceiver::new_from_seq(token.last_acked_input_seq),
+            InputReceiver::new_from_seq(token.last_acked_input_seq)
+                .with_reorder_window(self.input_reorder_window)
+                .with_clock(self.clock.clone()),
         );
 
         if let Some(baseline_frame) = self.state_history.get(token.last_applied_state_id) {
             if let Some(client_state) = self.clients.get_mut(&token.client_id) {
-                client_state.advance_baseline(token.last_applied_state_id, baseline_frame.clone());
+                client_state.advance_baseline(
+                    token.last_applied_state_id,
+                    FrameFingerprint::of(baseline_frame),
+                );
             }
         }
 
+        self.push_event(SessionEvent::ClientResumed {
+            client_id: token.client_id,
+            baseline_state_id: token.last_applied_state_id,
+        });
         ResumeResult::Resumed {
             client_id: token.client_id,
             baseline_state_id: token.last_applied_state_id,
         }
     }
 
+    /// Apply an `AttachRequest` from a connected client and describe the result.
+    ///
+    /// `Fresh` drops everything a resume could build on: the client's resume tokens,
+    /// its input sequence and its baseline. `Resume` (and an unspecified mode) seeds
+    /// the baseline from `last_applied_state_id` if nothing has been sent to the client
+    /// yet and that state is still in history. `force_snapshot` discards the baseline
+    /// whatever the mode.
+    pub fn apply_attach_request(
+        &mut self,
+        client_id: u64,
+        request: &AttachRequest,
+    ) -> AttachResponse {
+        let current_state_id = self.frame_store.current_state_id();
+        let Some(client_state) = self.clients.get_mut(&client_id) else {
+            return AttachResponse {
+                ok: false,
+                error_message: format!("client {} is not connected", client_id),
+                lease: None,
+                current_state_id,
+                will_send_snapshot: false,
+            };
+        };
+
+        let had_baseline = client_state.has_baseline();
+        match request.mode() {
+            AttachMode::Fresh => {
+                client_state.reset_baseline();
+                client_state.forget_styles();
+                let receiver = InputReceiver::new()
+                    .with_reorder_window(self.input_reorder_window)
+                    .with_clock(self.clock.clone());
+                self.input_receivers.insert(client_id, receiver);
+                self.recent_input.remove(&client_id);
+                self.token_keys.bump_client_epoch(client_id);
+            },
+            AttachMode::Resume | AttachMode::Unspecified => {
+                // Once a frame is in flight the client's screen is that frame, not
+                // whatever it reported having before
+                let untouched = !client_state.has_baseline() && client_state.frame_sequence() == 0;
+                if untouched && !request.force_snapshot {
+                    if let Some(frame) = self.state_history.get(request.last_applied_state_id) {
+                        client_state.advance_baseline(
+                            request.last_applied_state_id,
+                            FrameFingerprint::of(frame),
+                        );
+                    }
+                }
+                let window = self.input_reorder_window;
+                let clock = self.clock.clone();
+                let receiver = self.input_receivers.entry(client_id).or_insert_with(|| {
+                    InputReceiver::new()
+                        .with_reorder_window(window)
+                        .with_clock(clock.clone())
+                });
+                if untouched && receiver.last_acked_seq() == 0 {
+                    *receiver = InputReceiver::new_from_seq(request.last_acked_input_seq)
+                        .with_reorder_window(window)
+                        .with_clock(clock);
+                }
+            },
+        }
+        if request.force_snapshot {
+            client_state.reset_baseline();
+        }
+
+        let resync = had_baseline && !client_state.has_baseline();
+        let response = AttachResponse {
+            ok: true,
+            error_message: String::new(),
+            lease: self.lease_manager.get_current_lease(),
+            current_state_id,
+            will_send_snapshot: client_state.should_send_snapshot(),
+        };
+        if resync {
+            self.push_event(SessionEvent::Resync { client_id });
+        }
+        response
+    }
+
     pub fn set_token_expiry(&mut self, expiry_ms: u64) {
         self.token_expiry_ms = expiry_ms;
     }
@@ -283,13 +1057,63 @@ impl RemoteSession {
         self.max_clock_skew_ms = skew_ms;
     }
 
+    /// Hold inputs that arrive a little early instead of rejecting them as out of order;
+    /// off by default. Applies to clients already attached too.
+    pub fn set_input_reorder_window(&mut self, window: ReorderWindow) {
+        self.input_reorder_window = window;
+        for receiver in self.input_receivers.values_mut() {
+            receiver.set_reorder_window(window);
+        }
+    }
+
+    /// Name the client in lease denials and `ControllerLease.owner_name`, e.g. with
+    /// its `ClientHello.client_name`; forgotten when the client is removed
+    pub fn set_client_name(&mut self, client_id: u64, name: &str) {
+        self.lease_manager.set_client_name(client_id, name);
+    }
+
+    pub fn client_name(&self, client_id: u64) -> Option<&str> {
+        self.lease_manager.client_name(client_id)
+    }
+
+    /// Send deltas larger than `max_bytes` as snapshots (None: only those larger than
+    /// the snapshot). Applies to clients already attached too.
+    pub fn set_max_delta_bytes(&mut self, max_bytes: Option<usize>) {
+        self.max_delta_bytes = max_bytes;
+        for client_state in self.clients.values_mut() {
+            client_state.set_max_delta_bytes(max_bytes);
+        }
+    }
+
+    /// Whether a new client may take control without asking (`LastWriterWins`) or has
+    /// to force it (`ExplicitOnly`)
+    pub fn set_controller_policy(&mut self, policy: ControllerPolicy) {
+        self.lease_manager.set_policy(policy);
+    }
+
+    /// Demote the controller to viewer after `timeout_ms` without input (None disables).
+    pub fn set_controller_idle_timeout(&mut self, timeout_ms: Option<u64>) {
+        self.lease_manager
+            .set_idle_timeout(timeout_ms.map(Duration::from_millis));
+    }
+
     pub fn can_resume_from_state(&self, state_id: u64) -> bool {
         self.state_history.can_resume_from(state_id)
     }
 
-    #[cfg(test)]
-    pub fn token_secret(&self) -> &[u8; 32] {
-        &self.token_secret
+    /// Invalidate every resume token issued so far, e.g. after a suspected leak.
+    pub fn revoke_resume_tokens(&mut self) {
+        let epoch = self.token_keys.bump_epoch();
+        log::info!("Resume tokens revoked, token epoch is now {}", epoch);
+    }
+
+    /// Invalidate the resume tokens issued to one client.
+    pub fn revoke_client_resume_tokens(&mut self, client_id: u64) {
+        self.token_keys.bump_client_epoch(client_id);
+    }
+
+    pub(crate) fn token_keys(&self) -> &TokenKeyring {
+        &self.token_keys
     }
 
     /// Get dirty_rows for current state, capturing from FrameStore on first call per state.
@@ -309,6 +1133,19 @@ impl RemoteSession {
         &self.cached_dirty_rows.as_ref().unwrap().1
     }
 
+    fn fingerprint_for_current_state(&mut self) -> &FrameFingerprint {
+        let current_state_id = self.frame_store.current_state_id();
+        let stale = self
+            .cached_fingerprint
+            .as_ref()
+            .is_none_or(|(cached_id, _)| *cached_id != current_state_id);
+        if stale {
+            let fingerprint = FrameFingerprint::of(self.frame_store.current_frame());
+            self.cached_fingerprint = Some((current_state_id, fingerprint));
+        }
+        &self.cached_fingerprint.as_ref().unwrap().1
+    }
+
     /// Clear dirty_rows cache (call when advancing to new state)
     pub fn clear_dirty_rows_cache(&mut self) {
         self.cached_dirty_rows = None;
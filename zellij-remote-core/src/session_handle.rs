@@ -0,0 +1,279 @@
+//! An actor around [`RemoteSession`] for async servers.
+//!
+//! The session lives on its own task and every call is a message to it, so connection
+//! handlers never hold a lock across an await and all servers get the same ordering:
+//! requests from one handle are applied in the order they were sent.
+
+use tokio::sync::{mpsc, oneshot};
+use zellij_remote_protocol::{
+    ControllerLease, DisplaySize, InputAck, InputEvent, StateAck, DEFAULT_RENDER_WINDOW,
+};
+
+use crate::backpressure::WindowBounds;
+use crate::lease::LeaseResult;
+use crate::resume_token::ResumeResult;
+use crate::session::{InputError, RemoteSession, RenderUpdate};
+
+/// Commands queued to the session task before callers start waiting
+const COMMAND_QUEUE_SIZE: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionHandleError {
+    /// The session task has stopped
+    Closed,
+    Input(InputError),
+}
+
+impl std::fmt::Display for SessionHandleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Closed => write!(f, "the session task has stopped"),
+            Self::Input(e) => write!(f, "input rejected: {:?}", e),
+        }
+    }
+}
+
+impl std::error::Error for SessionHandleError {}
+
+/// What to attach a connecting client with.
+#[derive(Debug, Clone, Default)]
+pub struct AttachRequest {
+    /// Token from a previous connection; empty for a new client
+    pub resume_token: Vec<u8>,
+    pub window_bounds: WindowBounds,
+    /// Size to ask for if the client ends up with control
+    pub desired_size: Option<DisplaySize>,
+}
+
+/// A client attached by [`RemoteSessionHandle::attach`].
+#[derive(Debug, Clone)]
+pub struct Attached {
+    pub client_id: u64,
+    /// Whether `resume_token` was accepted; if not the client was added as new
+    pub resumed: bool,
+    /// Why a presented token was turned down
+    pub resume_rejected: Option<ResumeResult>,
+    /// The client's lease if it was granted control, else the current controller's
+    pub lease: Option<ControllerLease>,
+    /// Token for the client's next reconnect
+    pub resume_token: Vec<u8>,
+}
+
+type SessionFn = Box<dyn FnOnce(&mut RemoteSession) + Send>;
+
+enum Command {
+    Attach {
+        request: AttachRequest,
+        reply: oneshot::Sender<Attached>,
+    },
+    Detach {
+        client_id: u64,
+    },
+    Input {
+        client_id: u64,
+        input: InputEvent,
+        reply: oneshot::Sender<Result<InputAck, InputError>>,
+    },
+    Ack {
+        client_id: u64,
+        ack: StateAck,
+    },
+    GetUpdate {
+        client_id: u64,
+        reply: oneshot::Sender<Option<RenderUpdate>>,
+    },
+    RequestControl {
+        client_id: u64,
+        desired_size: Option<DisplaySize>,
+        force: bool,
+        reply: oneshot::Sender<LeaseResult>,
+    },
+    Run(SessionFn),
+}
+
+/// Cheap to clone; the session task ends once every handle is dropped.
+#[derive(Debug, Clone)]
+pub struct RemoteSessionHandle {
+    tx: mpsc::Sender<Command>,
+}
+
+impl RemoteSessionHandle {
+    /// Move `session` onto a task on the current tokio runtime.
+    pub fn spawn(session: RemoteSession) -> Self {
+        let (tx, rx) = mpsc::channel(COMMAND_QUEUE_SIZE);
+        tokio::spawn(run_session(session, rx));
+        Self { tx }
+    }
+
+    /// Resume the client if its token is still good, otherwise add it as a new client;
+    /// then ask for control on its behalf without forcing a takeover.
+    pub async fn attach(&self, request: AttachRequest) -> Result<Attached, SessionHandleError> {
+        self.call(|reply| Command::Attach { request, reply }).await
+    }
+
+    /// Drop the client; its resume token stays valid.
+    pub async fn detach(&self, client_id: u64) -> Result<(), SessionHandleError> {
+        self.send(Command::Detach { client_id }).await
+    }
+
+    pub async fn input(
+        &self,
+        client_id: u64,
+        input: InputEvent,
+    ) -> Result<InputAck, SessionHandleError> {
+        self.call(|reply| Command::Input {
+            client_id,
+            input,
+            reply,
+        })
+        .await?
+        .map_err(SessionHandleError::Input)
+    }
+
+    pub async fn ack(&self, client_id: u64, ack: StateAck) -> Result<(), SessionHandleError> {
+        self.send(Command::Ack { client_id, ack }).await
+    }
+
+    /// The snapshot or delta the client should be sent next, if any.
+    pub async fn get_update(
+        &self,
+        client_id: u64,
+    ) -> Result<Option<RenderUpdate>, SessionHandleError> {
+        self.call(|reply| Command::GetUpdate { client_id, reply })
+            .await
+    }
+
+    pub async fn request_control(
+        &self,
+        client_id: u64,
+        desired_size: Option<DisplaySize>,
+        force: bool,
+    ) -> Result<LeaseResult, SessionHandleError> {
+        self.call(|reply| Command::RequestControl {
+            client_id,
+            desired_size,
+            force,
+            reply,
+        })
+        .await
+    }
+
+    /// Run `f` on the session task, e.g. to draw into `frame_store`.
+    pub async fn with_session<F, R>(&self, f: F) -> Result<R, SessionHandleError>
+    where
+        F: FnOnce(&mut RemoteSession) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.call(|reply| {
+            Command::Run(Box::new(move |session| {
+                let _ = reply.send(f(session));
+            }))
+        })
+        .await
+    }
+
+    async fn send(&self, command: Command) -> Result<(), SessionHandleError> {
+        self.tx
+            .send(command)
+            .await
+            .map_err(|_| SessionHandleError::Closed)
+    }
+
+    async fn call<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> Command,
+    ) -> Result<T, SessionHandleError> {
+        let (reply, response) = oneshot::channel();
+        self.send(command(reply)).await?;
+        response.await.map_err(|_| SessionHandleError::Closed)
+    }
+}
+
+async fn run_session(mut session: RemoteSession, mut rx: mpsc::Receiver<Command>) {
+    let mut next_client_id = 1;
+    while let Some(command) = rx.recv().await {
+        match command {
+            Command::Attach { request, reply } => {
+                let attached = attach(&mut session, &mut next_client_id, request);
+                let _ = reply.send(attached);
+            },
+            Command::Detach { client_id } => session.remove_client(client_id),
+            Command::Input {
+                client_id,
+                input,
+                reply,
+            } => {
+                let _ = reply.send(session.process_input(client_id, &input));
+            },
+            Command::Ack { client_id, ack } => session.process_state_ack(client_id, &ack),
+            Command::GetUpdate { client_id, reply } => {
+                let _ = reply.send(session.get_render_update(client_id));
+            },
+            Command::RequestControl {
+                client_id,
+                desired_size,
+                force,
+                reply,
+            } => {
+                let result = session
+                    .lease_manager
+                    .request_control(client_id, desired_size, force);
+                let _ = reply.send(result);
+            },
+            Command::Run(f) => f(&mut session),
+        }
+    }
+}
+
+fn attach(
+    session: &mut RemoteSession,
+    next_client_id: &mut u64,
+    request: AttachRequest,
+) -> Attached {
+    let mut resume_rejected = None;
+    let resumed_id = if request.resume_token.is_empty() {
+        None
+    } else {
+        match session.try_resume(&request.resume_token, DEFAULT_RENDER_WINDOW) {
+            ResumeResult::Resumed { client_id, .. } => Some(client_id),
+            reason => {
+                resume_rejected = Some(reason);
+                None
+            },
+        }
+    };
+
+    let client_id = match resumed_id {
+        Some(client_id) => {
+            *next_client_id = (*next_client_id).max(client_id + 1);
+            client_id
+        },
+        None => {
+            // Skip ids still held by clients resumed from tokens
+            while session.has_client(*next_client_id) {
+                *next_client_id += 1;
+            }
+            let client_id = *next_client_id;
+            *next_client_id += 1;
+            session.add_client(client_id, DEFAULT_RENDER_WINDOW);
+            client_id
+        },
+    };
+    session.set_window_bounds(client_id, request.window_bounds);
+
+    let lease = match session
+        .lease_manager
+        .request_control(client_id, request.desired_size, false)
+    {
+        LeaseResult::Granted(lease) => Some(lease),
+        LeaseResult::Denied { .. } => session.lease_manager.get_current_lease(),
+    };
+
+    Attached {
+        client_id,
+        resumed: resumed_id.is_some(),
+        resume_rejected,
+        lease,
+        resume_token: session.generate_resume_token(client_id),
+    }
+}
@@ -0,0 +1,89 @@
+//! Bounds on the terminal sizes clients may ask for in `AttachRequest`,
+//! `RequestControl`, `ControlHandoffRequest` and `SetControllerSize`, so a hostile or
+//! buggy client can't make the server allocate a frame of `u32::MAX` columns.
+
+use zellij_remote_protocol::DisplaySize;
+
+pub const DEFAULT_MIN_COLS: u32 = 20;
+pub const DEFAULT_MAX_COLS: u32 = 1000;
+pub const DEFAULT_MIN_ROWS: u32 = 5;
+pub const DEFAULT_MAX_ROWS: u32 = 500;
+
+/// Sizes above the maximum (or with a zero dimension) are rejected outright; sizes
+/// merely below the minimum, e.g. from a phone in portrait, are raised to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimits {
+    pub min_cols: u32,
+    pub max_cols: u32,
+    pub min_rows: u32,
+    pub max_rows: u32,
+}
+
+impl Default for SizeLimits {
+    fn default() -> Self {
+        Self {
+            min_cols: DEFAULT_MIN_COLS,
+            max_cols: DEFAULT_MAX_COLS,
+            min_rows: DEFAULT_MIN_ROWS,
+            max_rows: DEFAULT_MAX_ROWS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SizeError {
+    Empty {
+        cols: u32,
+        rows: u32,
+    },
+    TooLarge {
+        cols: u32,
+        rows: u32,
+        max_cols: u32,
+        max_rows: u32,
+    },
+}
+
+impl std::fmt::Display for SizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty { cols, rows } => write!(f, "size {}x{} has no cells", cols, rows),
+            Self::TooLarge {
+                cols,
+                rows,
+                max_cols,
+                max_rows,
+            } => write!(
+                f,
+                "size {}x{} is larger than {}x{}",
+                cols, rows, max_cols, max_rows
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SizeError {}
+
+impl SizeLimits {
+    /// `size` raised to the minimum, or why it can't be used at all
+    pub fn check(&self, size: &DisplaySize) -> Result<DisplaySize, SizeError> {
+        if size.cols == 0 || size.rows == 0 {
+            return Err(SizeError::Empty {
+                cols: size.cols,
+                rows: size.rows,
+            });
+        }
+        if size.cols > self.max_cols || size.rows > self.max_rows {
+            return Err(SizeError::TooLarge {
+                cols: size.cols,
+                rows: size.rows,
+                max_cols: self.max_cols,
+                max_rows: self.max_rows,
+            });
+        }
+        Ok(DisplaySize {
+            cols: size.cols.max(self.min_cols),
+            rows: size.rows.max(self.min_rows),
+        })
+    }
+}
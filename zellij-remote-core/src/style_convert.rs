@@ -0,0 +1,258 @@
+//! Conversions between the protocol `Style` and terminal SGR parameters.
+//!
+//! Servers use the color constructors when building styles from their own
+//! terminal model; clients use [`style_to_sgr`] to render a style and
+//! [`apply_sgr_params`] to go the other way.
+
+use zellij_remote_protocol::{color, Color, DefaultColor, Rgb, Style, UnderlineStyle};
+
+pub fn default_color() -> Color {
+    Color {
+        value: Some(color::Value::DefaultColor(DefaultColor {})),
+    }
+}
+
+pub fn ansi256_color(index: u32) -> Color {
+    Color {
+        value: Some(color::Value::Ansi256(index)),
+    }
+}
+
+pub fn rgb_color(r: u8, g: u8, b: u8) -> Color {
+    Color {
+        value: Some(color::Value::Rgb(Rgb {
+            r: r as u32,
+            g: g as u32,
+            b: b as u32,
+        })),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ColorTarget {
+    Foreground,
+    Background,
+    Underline,
+}
+
+impl ColorTarget {
+    /// (base for indices 0-7 and 8-15, if the target has short codes; extended selector;
+    /// default color reset)
+    fn codes(self) -> (Option<(u32, u32)>, u32, u32) {
+        match self {
+            ColorTarget::Foreground => (Some((30, 90)), 38, 39),
+            ColorTarget::Background => (Some((40, 100)), 48, 49),
+            ColorTarget::Underline => (None, 58, 59),
+        }
+    }
+}
+
+fn color_params(color: &Color, target: ColorTarget) -> Option<String> {
+    let (short_bases, extended, reset) = target.codes();
+    match (color.value.as_ref()?, short_bases) {
+        (color::Value::DefaultColor(_), _) => Some(reset.to_string()),
+        (color::Value::Ansi256(idx @ 0..=7), Some((base, _))) => Some((base + idx).to_string()),
+        (color::Value::Ansi256(idx @ 8..=15), Some((_, bright_base))) => {
+            Some((bright_base + idx - 8).to_string())
+        },
+        (color::Value::Ansi256(idx), _) => Some(format!("{};5;{}", extended, (*idx).min(255))),
+        (color::Value::Rgb(rgb), _) => Some(format!(
+            "{};2;{};{};{}",
+            extended,
+            rgb.r.min(255),
+            rgb.g.min(255),
+            rgb.b.min(255)
+        )),
+    }
+}
+
+fn underline_param(underline: UnderlineStyle) -> Option<&'static str> {
+    match underline {
+        UnderlineStyle::Unspecified | UnderlineStyle::None => None,
+        UnderlineStyle::Single => Some("4"),
+        UnderlineStyle::Double => Some("4:2"),
+        UnderlineStyle::Curly => Some("4:3"),
+        UnderlineStyle::Dotted => Some("4:4"),
+        UnderlineStyle::Dashed => Some("4:5"),
+    }
+}
+
+/// Reduce `style` to what a terminal without styled underlines (SGR `4:x`, `58`) can
+/// show: any underline becomes a single one and the underline color is dropped.
+pub fn downgrade_underline(style: &mut Style) {
+    if underline_param(style.underline()).is_some() {
+        style.set_underline(UnderlineStyle::Single);
+    }
+    style.underline_color = None;
+}
+
+/// SGR parameters that fully describe `style`, starting with a reset so the
+/// result doesn't depend on whatever attributes were active before.
+pub fn style_to_sgr_params(style: &Style) -> Vec<String> {
+    let mut params = vec!["0".to_string()];
+    let flags = [
+        (style.bold, "1"),
+        (style.dim, "2"),
+        (style.italic, "3"),
+        (style.blink_slow, "5"),
+        (style.blink_fast, "6"),
+        (style.reverse, "7"),
+        (style.hidden, "8"),
+        (style.strike, "9"),
+    ];
+    params.extend(
+        flags
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, code)| code.to_string()),
+    );
+    if let Some(underline) = underline_param(style.underline()) {
+        params.push(underline.to_string());
+    }
+    let colors = [
+        (&style.fg, ColorTarget::Foreground),
+        (&style.bg, ColorTarget::Background),
+        (&style.underline_color, ColorTarget::Underline),
+    ];
+    for (color, target) in colors {
+        if let Some(param) = color.as_ref().and_then(|c| color_params(c, target)) {
+            params.push(param);
+        }
+    }
+    params
+}
+
+/// Full escape sequence (`ESC [ ... m`) selecting `style`.
+pub fn style_to_sgr(style: &Style) -> String {
+    format!("\x1b[{}m", style_to_sgr_params(style).join(";"))
+}
+
+/// Parse an SGR parameter string (the part between `ESC [` and `m`) into a style,
+/// starting from the default style.
+pub fn sgr_params_to_style(params: &str) -> Style {
+    let mut style = Style::default();
+    apply_sgr_params(&mut style, params);
+    style
+}
+
+/// Apply an SGR parameter string on top of `style`, as a terminal would.
+///
+/// Both `;` and `:` separated extended colors are accepted; unknown parameters are ignored.
+pub fn apply_sgr_params(style: &mut Style, params: &str) {
+    let mut groups = params.split(';');
+    while let Some(group) = groups.next() {
+        let mut sub = group.split(':');
+        let code = match sub.next().unwrap_or("") {
+            "" => 0,
+            s => match s.parse::<u32>() {
+                Ok(code) => code,
+                Err(_) => continue,
+            },
+        };
+        let subparams: Vec<&str> = sub.collect();
+
+        match code {
+            0 => *style = Style::default(),
+            1 => style.bold = true,
+            2 => style.dim = true,
+            3 => style.italic = true,
+            4 => style.set_underline(parse_underline_subparam(subparams.first().copied())),
+            5 => style.blink_slow = true,
+            6 => style.blink_fast = true,
+            7 => style.reverse = true,
+            8 => style.hidden = true,
+            9 => style.strike = true,
+            21 => style.set_underline(UnderlineStyle::Double),
+            22 => {
+                style.bold = false;
+                style.dim = false;
+            },
+            23 => style.italic = false,
+            24 => style.set_underline(UnderlineStyle::None),
+            25 => {
+                style.blink_slow = false;
+                style.blink_fast = false;
+            },
+            27 => style.reverse = false,
+            28 => style.hidden = false,
+            29 => style.strike = false,
+            30..=37 => style.fg = Some(ansi256_color(code - 30)),
+            38 => replace_if_parsed(&mut style.fg, parse_extended_color(&subparams, &mut groups)),
+            39 => style.fg = Some(default_color()),
+            40..=47 => style.bg = Some(ansi256_color(code - 40)),
+            48 => replace_if_parsed(&mut style.bg, parse_extended_color(&subparams, &mut groups)),
+            49 => style.bg = Some(default_color()),
+            58 => replace_if_parsed(
+                &mut style.underline_color,
+                parse_extended_color(&subparams, &mut groups),
+            ),
+            59 => style.underline_color = Some(default_color()),
+            90..=97 => style.fg = Some(ansi256_color(code - 90 + 8)),
+            100..=107 => style.bg = Some(ansi256_color(code - 100 + 8)),
+            _ => {},
+        }
+    }
+}
+
+/// Malformed extended colors are ignored rather than clearing the current color.
+fn replace_if_parsed(slot: &mut Option<Color>, parsed: Option<Color>) {
+    if parsed.is_some() {
+        *slot = parsed;
+    }
+}
+
+fn parse_underline_subparam(sub: Option<&str>) -> UnderlineStyle {
+    match sub.and_then(|s| s.parse::<u32>().ok()) {
+        None | Some(1) => UnderlineStyle::Single,
+        Some(0) => UnderlineStyle::None,
+        Some(2) => UnderlineStyle::Double,
+        Some(3) => UnderlineStyle::Curly,
+        Some(4) => UnderlineStyle::Dotted,
+        Some(5) => UnderlineStyle::Dashed,
+        Some(_) => UnderlineStyle::Single,
+    }
+}
+
+/// Parse the arguments of 38/48/58 from either colon subparameters or the
+/// following `;` separated parameters.
+fn parse_extended_color<'a>(
+    subparams: &[&str],
+    groups: &mut impl Iterator<Item = &'a str>,
+) -> Option<Color> {
+    let mut args: Vec<u32> = Vec::new();
+    if subparams.is_empty() {
+        let mode = groups.next()?.parse::<u32>().ok()?;
+        args.push(mode);
+        let count = match mode {
+            2 => 3,
+            5 => 1,
+            _ => return None,
+        };
+        for _ in 0..count {
+            args.push(groups.next()?.parse::<u32>().ok()?);
+        }
+    } else {
+        // `38:2::r:g:b` carries an (empty) colorspace id before the components
+        let mode = subparams[0].parse::<u32>().ok()?;
+        args.push(mode);
+        let rest = &subparams[1..];
+        let rest = if mode == 2 && rest.len() == 4 {
+            &rest[1..]
+        } else {
+            rest
+        };
+        for s in rest {
+            args.push(s.parse::<u32>().ok()?);
+        }
+    }
+
+    match args.as_slice() {
+        [5, idx] => Some(ansi256_color((*idx).min(255))),
+        [2, r, g, b] => Some(rgb_color(
+            (*r).min(255) as u8,
+            (*g).min(255) as u8,
+            (*b).min(255) as u8,
+        )),
+        _ => None,
+    }
+}
@@ -15,49 +15,108 @@ impl StyleKey {
     }
 }
 
+/// Most styles a table holds, the default included. A session that keeps minting
+/// RGB styles stops growing here instead of exhausting memory.
+pub const MAX_STYLES: usize = 1 << 20;
+
 #[derive(Clone, Debug)]
 pub struct StyleTable {
     styles: Vec<Style>,
-    style_to_id: HashMap<StyleKey, u16>,
+    style_to_id: HashMap<StyleKey, u32>,
+    max_styles: usize,
+    /// Lookups of new styles answered with the default because the table was full
+    overflowed: u64,
+    /// Bumped each time ids are handed out afresh, so holders of older ids know to
+    /// drop them
+    generation: u64,
 }
 
 impl StyleTable {
     pub fn new() -> Self {
+        Self::with_max_styles(MAX_STYLES)
+    }
+
+    /// A table that holds at most `max_styles` styles (at least the default, and no
+    /// more than u32 ids can number)
+    pub fn with_max_styles(max_styles: usize) -> Self {
         let mut table = Self {
             styles: Vec::new(),
             style_to_id: HashMap::new(),
+            max_styles: max_styles.clamp(1, u32::MAX as usize),
+            overflowed: 0,
+            generation: 0,
         };
         table.styles.push(Style::default());
         table
     }
 
-    pub fn get_or_insert(&mut self, style: &Style) -> u16 {
+    /// The id of `style`, adding it if it's new. Once the table is full new styles
+    /// get the default style's id 0: ids are never reused or wrapped onto a style
+    /// clients already hold.
+    pub fn get_or_insert(&mut self, style: &Style) -> u32 {
         let key = StyleKey::from_style(style);
 
         if let Some(&id) = self.style_to_id.get(&key) {
             return id;
         }
 
-        let id = self.styles.len() as u16;
+        if self.styles.len() >= self.max_styles {
+            if self.overflowed == 0 {
+                log::warn!(
+                    "Style table full at {} styles, new styles render with the default",
+                    self.styles.len()
+                );
+            }
+            self.overflowed += 1;
+            return 0;
+        }
+
+        // max_styles keeps the length within u32
+        let id = self.styles.len() as u32;
         self.styles.push(style.clone());
         self.style_to_id.insert(key, id);
         id
     }
 
-    pub fn get(&self, id: u16) -> Option<&Style> {
+    /// An empty table of the same capacity in the next generation. Its ids start
+    /// over, so they must not be read against this table's.
+    pub fn renumbered(&self) -> Self {
+        let mut table = Self::with_max_styles(self.max_styles);
+        table.generation = self.generation + 1;
+        table
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn max_styles(&self) -> usize {
+        self.max_styles
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Style> {
         self.styles.get(id as usize)
     }
 
+    pub fn is_full(&self) -> bool {
+        self.styles.len() >= self.max_styles
+    }
+
+    /// New styles given the default style because the table was full
+    pub fn overflowed(&self) -> u64 {
+        self.overflowed
+    }
+
     pub fn current_count(&self) -> usize {
         self.styles.len()
     }
 
-    pub fn styles_since(&self, baseline: usize) -> Vec<(u16, &Style)> {
+    pub fn styles_since(&self, baseline: usize) -> Vec<(u32, &Style)> {
         self.styles
             .iter()
             .enumerate()
             .skip(baseline)
-            .map(|(id, style)| (id as u16, style))
+            .map(|(id, style)| (id as u32, style))
             .collect()
     }
 
@@ -66,11 +125,11 @@ impl StyleTable {
         self.style_to_id.clear();
     }
 
-    pub fn all_styles(&self) -> impl Iterator<Item = (u16, &Style)> {
+    pub fn all_styles(&self) -> impl Iterator<Item = (u32, &Style)> {
         self.styles
             .iter()
             .enumerate()
-            .map(|(id, style)| (id as u16, style))
+            .map(|(id, style)| (id as u32, style))
     }
 }
 
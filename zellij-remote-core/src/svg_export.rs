@@ -0,0 +1,414 @@
+//! Rendering a frame to a standalone SVG image, e.g. to answer a one-shot capture
+//! with a picture or to compare a frame against a golden image in tests.
+//!
+//! Every glyph is placed on its cell with an explicit x position, so the picture
+//! keeps the grid whatever monospace font the viewer substitutes.
+
+use std::fmt::Write;
+
+use zellij_remote_protocol::{color, Color, Style, UnderlineStyle};
+
+use crate::frame::{CursorShape, FrameData, LineSize, Row};
+use crate::style_table::StyleTable;
+
+/// How a frame is drawn: cell metrics, font and the colors terminals leave to the
+/// user's theme.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgTheme {
+    pub cell_width: u32,
+    pub cell_height: u32,
+    pub font_size: u32,
+    pub font_family: String,
+    pub foreground: (u8, u8, u8),
+    pub background: (u8, u8, u8),
+    /// Used when the program hasn't set a cursor color
+    pub cursor: (u8, u8, u8),
+    /// The 16 ANSI colors; the rest of the 256-color palette is fixed
+    pub ansi: [(u8, u8, u8); 16],
+}
+
+impl Default for SvgTheme {
+    fn default() -> Self {
+        Self {
+            cell_width: 9,
+            cell_height: 18,
+            font_size: 15,
+            font_family: "Menlo, Consolas, 'DejaVu Sans Mono', monospace".to_string(),
+            foreground: (0xe5, 0xe5, 0xe5),
+            background: (0x1e, 0x1e, 0x1e),
+            cursor: (0xe5, 0xe5, 0xe5),
+            // xterm's defaults
+            ansi: [
+                (0x00, 0x00, 0x00),
+                (0xcd, 0x00, 0x00),
+                (0x00, 0xcd, 0x00),
+                (0xcd, 0xcd, 0x00),
+                (0x00, 0x00, 0xee),
+                (0xcd, 0x00, 0xcd),
+                (0x00, 0xcd, 0xcd),
+                (0xe5, 0xe5, 0xe5),
+                (0x7f, 0x7f, 0x7f),
+                (0xff, 0x00, 0x00),
+                (0x00, 0xff, 0x00),
+                (0xff, 0xff, 0x00),
+                (0x5c, 0x5c, 0xff),
+                (0xff, 0x00, 0xff),
+                (0x00, 0xff, 0xff),
+                (0xff, 0xff, 0xff),
+            ],
+        }
+    }
+}
+
+impl SvgTheme {
+    /// The RGB value of `color`, or `default` for the terminal's default color.
+    pub fn resolve(&self, color: Option<&Color>, default: (u8, u8, u8)) -> (u8, u8, u8) {
+        match color.and_then(|c| c.value.as_ref()) {
+            Some(color::Value::Ansi256(index)) => self.ansi256(*index),
+            Some(color::Value::Rgb(rgb)) => (rgb.r as u8, rgb.g as u8, rgb.b as u8),
+            Some(color::Value::DefaultColor(_)) | None => default,
+        }
+    }
+
+    fn ansi256(&self, index: u32) -> (u8, u8, u8) {
+        match index {
+            0..=15 => self.ansi[index as usize],
+            16..=231 => {
+                let level = |n: u32| if n == 0 { 0 } else { (55 + n * 40) as u8 };
+                let i = index - 16;
+                (level(i / 36), level(i / 6 % 6), level(i % 6))
+            },
+            232..=255 => {
+                let gray = (8 + (index - 232) * 10) as u8;
+                (gray, gray, gray)
+            },
+            _ => self.foreground,
+        }
+    }
+}
+
+/// The resolved look of one cell
+#[derive(Debug, Clone, PartialEq)]
+struct CellLook {
+    fg: (u8, u8, u8),
+    bg: (u8, u8, u8),
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    hidden: bool,
+    strike: bool,
+    underline: UnderlineStyle,
+    underline_color: (u8, u8, u8),
+}
+
+impl CellLook {
+    fn new(style: Option<&Style>, theme: &SvgTheme) -> Self {
+        let Some(style) = style else {
+            return Self {
+                fg: theme.foreground,
+                bg: theme.background,
+                bold: false,
+                dim: false,
+                italic: false,
+                hidden: false,
+                strike: false,
+                underline: UnderlineStyle::None,
+                underline_color: theme.foreground,
+            };
+        };
+        let mut fg = theme.resolve(style.fg.as_ref(), theme.foreground);
+        let mut bg = theme.resolve(style.bg.as_ref(), theme.background);
+        if style.reverse {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        let underline = match style.underline() {
+            UnderlineStyle::Unspecified => UnderlineStyle::None,
+            underline => underline,
+        };
+        Self {
+            fg,
+            bg,
+            bold: style.bold,
+            dim: style.dim,
+            italic: style.italic,
+            hidden: style.hidden,
+            strike: style.strike,
+            underline,
+            underline_color: theme.resolve(style.underline_color.as_ref(), fg),
+        }
+    }
+}
+
+/// Render `frame` as an SVG document, looking its styles up in `style_table`.
+pub fn frame_to_svg(frame: &FrameData, style_table: &StyleTable, theme: &SvgTheme) -> String {
+    let cw = theme.cell_width;
+    let ch = theme.cell_height;
+    let width = frame.cols as u32 * cw;
+    let height = frame.rows.len() as u32 * ch;
+
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">"
+    );
+    let _ = write!(
+        svg,
+        "<rect width=\"{width}\" height=\"{height}\" fill=\"{}\"/>",
+        hex(theme.background)
+    );
+    let _ = write!(
+        svg,
+        "<g font-family=\"{}\" font-size=\"{}\">",
+        escape(&theme.font_family),
+        theme.font_size
+    );
+
+    for (row_idx, row) in frame.rows.iter().enumerate() {
+        let looks: Vec<CellLook> = row
+            .0
+            .cells
+            .iter()
+            .map(|cell| CellLook::new(style_table.get(cell.style_id), theme))
+            .collect();
+        let top = row_idx as u32 * ch;
+        let mut content = String::new();
+        draw_backgrounds(&mut content, &looks, theme);
+        draw_text(&mut content, row, &looks, theme);
+        if frame.cursor.visible && frame.cursor.row as usize == row_idx {
+            draw_cursor(&mut content, frame, row, &looks, theme);
+        }
+
+        // Rows are drawn at the origin, then scaled into place for DEC line sizes
+        match row.line_size() {
+            LineSize::Single => {
+                let _ = write!(svg, "<g transform=\"translate(0,{top})\">{content}</g>");
+            },
+            line_size => {
+                let (scale_y, shift) = match line_size {
+                    LineSize::DoubleWidth => (1, 0),
+                    LineSize::DoubleHeightTop => (2, 0),
+                    _ => (2, ch),
+                };
+                let _ = write!(
+                    svg,
+                    "<clipPath id=\"row{row_idx}\">\
+                     <rect y=\"{top}\" width=\"{width}\" height=\"{ch}\"/></clipPath>"
+                );
+                let _ = write!(
+                    svg,
+                    "<g clip-path=\"url(#row{row_idx})\">\
+                     <g transform=\"translate(0,{}) scale(2,{scale_y})\">{content}</g></g>",
+                    top as i64 - shift as i64
+                );
+            },
+        }
+    }
+
+    svg.push_str("</g></svg>");
+    svg
+}
+
+/// One rect per run of cells sharing a background other than the theme's
+fn draw_backgrounds(out: &mut String, looks: &[CellLook], theme: &SvgTheme) {
+    let cw = theme.cell_width;
+    let mut col = 0;
+    while col < looks.len() {
+        let bg = looks[col].bg;
+        let run = looks[col..].iter().take_while(|look| look.bg == bg).count();
+        if bg != theme.background {
+            let _ = write!(
+                out,
+                "<rect x=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                col as u32 * cw,
+                run as u32 * cw,
+                theme.cell_height,
+                hex(bg)
+            );
+        }
+        col += run;
+    }
+}
+
+/// One text element per run of cells sharing a look, each glyph pinned to its cell,
+/// followed by the run's underline and strikethrough
+fn draw_text(out: &mut String, row: &Row, looks: &[CellLook], theme: &SvgTheme) {
+    let cw = theme.cell_width;
+    let cells = &row.0.cells;
+    let baseline = baseline(theme);
+    let mut col = 0;
+    while col < cells.len() {
+        let look = &looks[col];
+        let run = looks[col..].iter().take_while(|l| *l == look).count();
+        let mut xs = Vec::new();
+        let mut text = String::new();
+        for (offset, cell) in cells[col..col + run].iter().enumerate() {
+            // Continuation cells of wide characters and blanks draw nothing
+            if cell.width == 0 || cell.codepoint == ' ' as u32 || look.hidden {
+                continue;
+            }
+            let Some(c) = char::from_u32(cell.codepoint).filter(|c| !c.is_control()) else {
+                continue;
+            };
+            xs.push(((col + offset) as u32 * cw).to_string());
+            push_escaped(&mut text, c);
+        }
+        if !text.is_empty() {
+            let _ = write!(
+                out,
+                "<text x=\"{}\" y=\"{baseline}\" fill=\"{}\"{}{}{}>{text}</text>",
+                xs.join(" "),
+                hex(look.fg),
+                if look.bold {
+                    " font-weight=\"bold\""
+                } else {
+                    ""
+                },
+                if look.italic {
+                    " font-style=\"italic\""
+                } else {
+                    ""
+                },
+                if look.dim {
+                    " fill-opacity=\"0.5\""
+                } else {
+                    ""
+                },
+            );
+        }
+
+        if look.hidden {
+            col += run;
+            continue;
+        }
+        let x1 = col as u32 * cw;
+        let x2 = (col + run) as u32 * cw;
+        draw_underline(out, look, x1, x2, theme);
+        if look.strike {
+            let y = theme.cell_height / 2;
+            let _ = write!(
+                out,
+                "<line x1=\"{x1}\" y1=\"{y}\" x2=\"{x2}\" y2=\"{y}\" stroke=\"{}\"/>",
+                hex(look.fg)
+            );
+        }
+        col += run;
+    }
+}
+
+fn draw_underline(out: &mut String, look: &CellLook, x1: u32, x2: u32, theme: &SvgTheme) {
+    let y = baseline(theme) + 2;
+    let stroke = hex(look.underline_color);
+    let line = |out: &mut String, y: u32, dash: &str| {
+        let _ = write!(
+            out,
+            "<line x1=\"{x1}\" y1=\"{y}\" x2=\"{x2}\" y2=\"{y}\" stroke=\"{stroke}\"{dash}/>"
+        );
+    };
+    match look.underline {
+        UnderlineStyle::Single => line(out, y, ""),
+        UnderlineStyle::Double => {
+            line(out, y, "");
+            line(out, y + 2, "");
+        },
+        UnderlineStyle::Dotted => line(out, y, " stroke-dasharray=\"1 2\""),
+        UnderlineStyle::Dashed => line(out, y, " stroke-dasharray=\"4 2\""),
+        UnderlineStyle::Curly => {
+            let mut points = String::new();
+            let mut x = x1;
+            let mut up = true;
+            while x <= x2 {
+                let _ = write!(points, "{},{} ", x, if up { y - 1 } else { y + 1 });
+                x += 2;
+                up = !up;
+            }
+            let _ = write!(
+                out,
+                "<polyline points=\"{}\" fill=\"none\" stroke=\"{stroke}\"/>",
+                points.trim_end()
+            );
+        },
+        UnderlineStyle::None | UnderlineStyle::Unspecified => {},
+    }
+}
+
+fn draw_cursor(
+    out: &mut String,
+    frame: &FrameData,
+    row: &Row,
+    looks: &[CellLook],
+    theme: &SvgTheme,
+) {
+    let col = frame.cursor.col as usize;
+    let Some(cell) = row.get_cell(col) else {
+        return;
+    };
+    let color = frame.cursor.color.map_or(theme.cursor, |c| (c.r, c.g, c.b));
+    let x = col as u32 * theme.cell_width;
+    let cell_width = theme.cell_width * u32::from(cell.width.max(1));
+    let ch = theme.cell_height;
+    match frame.cursor.shape {
+        CursorShape::Block => {
+            let _ = write!(
+                out,
+                "<rect x=\"{x}\" width=\"{cell_width}\" height=\"{ch}\" fill=\"{}\"/>",
+                hex(color)
+            );
+            // The glyph under a block cursor shows in the cell's background color
+            if let Some(c) = char::from_u32(cell.codepoint)
+                .filter(|c| *c != ' ' && !c.is_control() && cell.width > 0)
+            {
+                let mut text = String::new();
+                push_escaped(&mut text, c);
+                let _ = write!(
+                    out,
+                    "<text x=\"{x}\" y=\"{}\" fill=\"{}\">{text}</text>",
+                    baseline(theme),
+                    hex(looks[col].bg)
+                );
+            }
+        },
+        CursorShape::Bar => {
+            let _ = write!(
+                out,
+                "<rect x=\"{x}\" width=\"2\" height=\"{ch}\" fill=\"{}\"/>",
+                hex(color)
+            );
+        },
+        CursorShape::Underline => {
+            let _ = write!(
+                out,
+                "<rect x=\"{x}\" y=\"{}\" width=\"{cell_width}\" height=\"2\" fill=\"{}\"/>",
+                ch - 2,
+                hex(color)
+            );
+        },
+    }
+}
+
+/// Where glyphs sit in a cell: about four fifths of the way down
+fn baseline(theme: &SvgTheme) -> u32 {
+    theme.cell_height * 4 / 5
+}
+
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn push_escaped(out: &mut String, c: char) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        c => out.push(c),
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        push_escaped(&mut out, c);
+    }
+    out
+}
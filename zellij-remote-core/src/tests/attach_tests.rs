@@ -0,0 +1,173 @@
+use crate::frame::Cell;
+use crate::resume_token::ResumeResult;
+use crate::session::{RemoteSession, RenderUpdate};
+use zellij_remote_protocol::{AttachMode, AttachRequest, InputEvent, StateAck};
+
+fn draw(session: &mut RemoteSession, c: char) -> u64 {
+    session.frame_store.update_row(0, |r| {
+        r.set_cell(
+            0,
+            Cell {
+                codepoint: c as u32,
+                width: 1,
+                style_id: 0,
+            },
+        )
+    });
+    session.frame_store.advance_state();
+    session.record_state_snapshot();
+    session.frame_store.current_state_id()
+}
+
+fn attach(mode: AttachMode, last_applied_state_id: u64, force_snapshot: bool) -> AttachRequest {
+    AttachRequest {
+        mode: mode as i32,
+        last_applied_state_id,
+        force_snapshot,
+        ..Default::default()
+    }
+}
+
+fn input(seq: u64) -> InputEvent {
+    InputEvent {
+        input_seq: seq,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_resume_picks_delta_baseline() {
+    let mut session = RemoteSession::new(10, 4);
+    let known = draw(&mut session, 'a');
+    let current = draw(&mut session, 'b');
+    session.add_client(1, 4);
+
+    let response = session.apply_attach_request(1, &attach(AttachMode::Resume, known, false));
+    assert!(response.ok);
+    assert!(!response.will_send_snapshot);
+    assert_eq!(response.current_state_id, current);
+
+    match session.get_render_update(1) {
+        Some(RenderUpdate::Delta(delta)) => {
+            assert_eq!(delta.base_state_id, known);
+            assert_eq!(delta.state_id, current);
+        },
+        other => panic!("Expected a delta, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_resume_from_unknown_state_sends_snapshot() {
+    let mut session = RemoteSession::new(10, 4);
+    draw(&mut session, 'a');
+    session.add_client(1, 4);
+
+    let response = session.apply_attach_request(1, &attach(AttachMode::Resume, 999, false));
+    assert!(response.ok);
+    assert!(response.will_send_snapshot);
+    assert!(matches!(
+        session.get_render_update(1),
+        Some(RenderUpdate::Snapshot(_))
+    ));
+}
+
+#[test]
+fn test_resume_ignored_once_frames_are_in_flight() {
+    let mut session = RemoteSession::new(10, 4);
+    let known = draw(&mut session, 'a');
+    session.add_client(1, 4);
+    assert!(matches!(
+        session.get_render_update(1),
+        Some(RenderUpdate::Snapshot(_))
+    ));
+
+    // The client is about to apply that snapshot, so `known` is no longer its screen
+    draw(&mut session, 'b');
+    let response = session.apply_attach_request(1, &attach(AttachMode::Resume, known, false));
+    assert!(response.will_send_snapshot);
+}
+
+#[test]
+fn test_force_snapshot_bypasses_resume() {
+    let mut session = RemoteSession::new(10, 4);
+    let known = draw(&mut session, 'a');
+    session.add_client(1, 4);
+
+    let response = session.apply_attach_request(1, &attach(AttachMode::Resume, known, true));
+    assert!(response.will_send_snapshot);
+    assert!(matches!(
+        session.get_render_update(1),
+        Some(RenderUpdate::Snapshot(_))
+    ));
+
+    // Also drops a baseline the client already acked
+    let state_id = draw(&mut session, 'b');
+    let _ = session.get_render_update(1);
+    session.process_state_ack(
+        1,
+        &StateAck {
+            last_applied_state_id: state_id,
+            ..Default::default()
+        },
+    );
+    let response = session.apply_attach_request(1, &attach(AttachMode::Unspecified, 0, true));
+    assert!(response.will_send_snapshot);
+}
+
+#[test]
+fn test_fresh_discards_resumable_state() {
+    let mut session = RemoteSession::new(10, 4);
+    let state_id = draw(&mut session, 'a');
+    session.add_client(1, 4);
+    let _ = session.get_render_update(1);
+    session.process_state_ack(
+        1,
+        &StateAck {
+            last_applied_state_id: state_id,
+            ..Default::default()
+        },
+    );
+    session.lease_manager.request_control(1, None, false);
+    session.process_input(1, &input(1)).unwrap();
+    let old_token = session.generate_resume_token(1);
+
+    let response = session.apply_attach_request(1, &attach(AttachMode::Fresh, state_id, false));
+    assert!(response.ok);
+    assert!(response.will_send_snapshot);
+    assert_eq!(response.lease.map(|l| l.owner_client_id), Some(1));
+
+    // Input numbering starts over
+    assert!(session.process_input(1, &input(1)).is_ok());
+
+    session.remove_client(1);
+    assert!(matches!(
+        session.try_resume(&old_token, 4),
+        ResumeResult::InvalidToken
+    ));
+}
+
+#[test]
+fn test_resume_continues_input_sequence() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+    session.lease_manager.request_control(1, None, false);
+
+    session.apply_attach_request(
+        1,
+        &AttachRequest {
+            mode: AttachMode::Resume as i32,
+            last_acked_input_seq: 7,
+            ..Default::default()
+        },
+    );
+    assert!(session.process_input(1, &input(7)).is_err());
+    assert!(session.process_input(1, &input(8)).is_ok());
+}
+
+#[test]
+fn test_attach_unknown_client_rejected() {
+    let mut session = RemoteSession::new(10, 4);
+    let response = session.apply_attach_request(5, &attach(AttachMode::Fresh, 0, false));
+    assert!(!response.ok);
+    assert!(!response.error_message.is_empty());
+}
@@ -0,0 +1,144 @@
+use crate::auth::{
+    AuthOutcome, SharedTokenRegistry, TokenEntry, TokenHash, TokenProvider, TokenRegistry,
+};
+use zellij_remote_protocol::ClientRole;
+
+#[test]
+fn test_token_hash_verifies_original_token() {
+    let hash = TokenHash::from_plaintext(b"s3cret-token");
+
+    assert!(hash.verify(b"s3cret-token"));
+    assert!(!hash.verify(b"s3cret-tokem"));
+    assert!(!hash.verify(b""));
+}
+
+#[test]
+fn test_token_hash_encode_parse_roundtrip() {
+    let hash = TokenHash::with_salt(b"token", b"fixed-salt".to_vec());
+    let encoded = hash.encode();
+    assert!(encoded.starts_with("sha256:"));
+    assert!(!encoded.contains("token"));
+
+    let parsed = TokenHash::parse(&encoded).expect("encoded hash should parse");
+    assert_eq!(parsed, hash);
+    assert!(parsed.verify(b"token"));
+}
+
+#[test]
+fn test_token_hash_salts_differ() {
+    let a = TokenHash::from_plaintext(b"token");
+    let b = TokenHash::from_plaintext(b"token");
+    assert_ne!(a.encode(), b.encode());
+}
+
+#[test]
+fn test_token_hash_parse_rejects_malformed() {
+    assert!(TokenHash::parse("").is_none());
+    assert!(TokenHash::parse("md5:00:00").is_none());
+    assert!(TokenHash::parse("sha256:zz:00").is_none());
+    assert!(TokenHash::parse("sha256:0011:abcd").is_none());
+    assert!(TokenHash::parse("sha256::").is_none());
+}
+
+#[test]
+fn test_token_hash_debug_redacts_material() {
+    let hash = TokenHash::with_salt(b"token", b"salt".to_vec());
+    let debug = format!("{:?}", hash);
+    assert!(!debug.contains(&hash.encode()));
+}
+
+#[test]
+fn test_closure_token_provider() {
+    let provider = || Some(TokenHash::with_salt(b"rotated", b"salt".to_vec()));
+    let current = provider.current_token().unwrap();
+    assert!(current.verify(b"rotated"));
+
+    let disabled = || -> Option<TokenHash> { None };
+    assert!(disabled.current_token().is_none());
+}
+
+fn entry(id: &str, token: &[u8], role: ClientRole, expires_at_ms: Option<u64>) -> TokenEntry {
+    TokenEntry {
+        id: id.to_string(),
+        hash: TokenHash::from_plaintext(token),
+        role,
+        expires_at_ms,
+    }
+}
+
+#[test]
+fn test_registry_attributes_matching_token() {
+    let registry = TokenRegistry::new(vec![
+        entry("alice", b"alice-token", ClientRole::Controller, None),
+        entry("kiosk", b"kiosk-token", ClientRole::Viewer, None),
+    ]);
+
+    assert_eq!(
+        registry.authenticate_at(b"kiosk-token", 0),
+        AuthOutcome::Accepted {
+            token_id: "kiosk".to_string(),
+            role: ClientRole::Viewer,
+        }
+    );
+    assert_eq!(
+        registry.authenticate_at(b"alice-token", 0),
+        AuthOutcome::Accepted {
+            token_id: "alice".to_string(),
+            role: ClientRole::Controller,
+        }
+    );
+    assert_eq!(
+        registry.authenticate_at(b"mallory", 0),
+        AuthOutcome::Rejected
+    );
+}
+
+#[test]
+fn test_registry_rejects_expired_token() {
+    let registry = TokenRegistry::new(vec![entry(
+        "temp",
+        b"temp-token",
+        ClientRole::Controller,
+        Some(1_000),
+    )]);
+
+    assert!(matches!(
+        registry.authenticate_at(b"temp-token", 999),
+        AuthOutcome::Accepted { .. }
+    ));
+    assert_eq!(
+        registry.authenticate_at(b"temp-token", 1_000),
+        AuthOutcome::Expired {
+            token_id: "temp".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_shared_registry_live_replace() {
+    let shared = SharedTokenRegistry::new(TokenRegistry::new(vec![entry(
+        "old",
+        b"old-token",
+        ClientRole::Controller,
+        None,
+    )]));
+    let handle = shared.clone();
+
+    handle.replace(TokenRegistry::new(vec![entry(
+        "new",
+        b"new-token",
+        ClientRole::Controller,
+        None,
+    )]));
+
+    let registry = shared.snapshot();
+    assert_eq!(registry.len(), 1);
+    assert_eq!(
+        registry.authenticate_at(b"old-token", 0),
+        AuthOutcome::Rejected
+    );
+    assert!(matches!(
+        registry.authenticate_at(b"new-token", 0),
+        AuthOutcome::Accepted { .. }
+    ));
+}
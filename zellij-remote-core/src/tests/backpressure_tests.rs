@@ -1,6 +1,7 @@
-use crate::backpressure::RenderWindow;
+use crate::backpressure::{RenderWindow, WindowBounds};
 use crate::client_state::ClientRenderState;
-use crate::frame::FrameData;
+use crate::frame::{FrameData, FrameFingerprint};
+use crate::session::RenderUpdate;
 use crate::style_table::StyleTable;
 use proptest::prelude::*;
 use zellij_remote_protocol::StateAck;
@@ -107,6 +108,130 @@ fn test_default_window_size() {
     assert_eq!(window.window_size(), 4);
 }
 
+#[test]
+fn test_window_bounds_negotiation() {
+    let server = WindowBounds { min: 2, max: 32 };
+
+    assert_eq!(WindowBounds::negotiate(server, 0, 0), server);
+    assert_eq!(
+        WindowBounds::negotiate(server, 4, 8),
+        WindowBounds { min: 4, max: 8 }
+    );
+    // Requests are clamped to what the server allows
+    assert_eq!(
+        WindowBounds::negotiate(server, 1, 100),
+        WindowBounds { min: 2, max: 32 }
+    );
+    // An inverted request collapses to the max
+    assert_eq!(
+        WindowBounds::negotiate(server, 16, 8),
+        WindowBounds { min: 8, max: 8 }
+    );
+}
+
+/// Send `count` frames of `bytes` at `sent_at`, then ack them all at `acked_at`.
+fn send_round(
+    window: &mut RenderWindow,
+    next_state_id: &mut u64,
+    count: u32,
+    bytes: usize,
+    sent_at: u64,
+    acked_at: u64,
+    loss_ppm: u32,
+) {
+    for _ in 0..count {
+        window.mark_sent_at(*next_state_id, bytes, sent_at);
+        *next_state_id += 1;
+    }
+    window.ack_received_at(*next_state_id - 1, acked_at, loss_ppm);
+}
+
+#[test]
+fn test_window_grows_towards_bdp_on_clean_acks() {
+    let mut window = RenderWindow::with_bounds(4, WindowBounds { min: 2, max: 8 });
+    let mut next = 1;
+
+    // A full window of 100-byte frames acked after 100ms: 4 B/ms x 100ms = 4 frames
+    send_round(&mut window, &mut next, 4, 100, 0, 100, 0);
+    assert_eq!(window.srtt_ms(), Some(100.0));
+    assert_eq!(window.bdp_target(), Some(5));
+    assert_eq!(window.window_size(), 5);
+
+    for round in 1..10 {
+        let size = window.window_size();
+        send_round(
+            &mut window,
+            &mut next,
+            size,
+            100,
+            round * 100,
+            round * 100 + 100,
+            0,
+        );
+    }
+    assert_eq!(window.window_size(), 8);
+    assert_eq!(window.unacked_count(), 0);
+}
+
+#[test]
+fn test_window_stays_small_when_app_limited() {
+    let mut window = RenderWindow::with_bounds(4, WindowBounds { min: 2, max: 32 });
+    let mut next = 1;
+
+    // One frame per round trip: the link is idle, so there is nothing to grow into
+    for round in 0..10 {
+        send_round(
+            &mut window,
+            &mut next,
+            1,
+            100,
+            round * 200,
+            round * 200 + 100,
+            0,
+        );
+    }
+    assert_eq!(window.bdp_target(), Some(2));
+    assert_eq!(window.window_size(), 4);
+}
+
+#[test]
+fn test_window_halves_on_loss_once_per_srtt() {
+    let mut window = RenderWindow::with_bounds(16, WindowBounds { min: 3, max: 32 });
+    let mut next = 1;
+
+    send_round(&mut window, &mut next, 4, 100, 0, 100, 50_000);
+    assert_eq!(window.window_size(), 8);
+
+    // Still within one srtt of the last reduction
+    send_round(&mut window, &mut next, 1, 100, 100, 150, 50_000);
+    assert_eq!(window.window_size(), 8);
+
+    send_round(&mut window, &mut next, 1, 100, 150, 250, 50_000);
+    assert_eq!(window.window_size(), 4);
+
+    send_round(&mut window, &mut next, 1, 100, 250, 400, 50_000);
+    assert_eq!(window.window_size(), 3);
+}
+
+#[test]
+fn test_fixed_window_is_not_tuned() {
+    let mut window = RenderWindow::new(4);
+    let mut next = 1;
+
+    send_round(&mut window, &mut next, 4, 100, 0, 100, 0);
+    send_round(&mut window, &mut next, 4, 100, 100, 200, 50_000);
+    assert_eq!(window.window_size(), 4);
+    assert!(window.srtt_ms().is_some());
+}
+
+#[test]
+fn test_set_bounds_clamps_window() {
+    let mut window = RenderWindow::new(4);
+    window.set_bounds(WindowBounds { min: 6, max: 12 });
+    assert_eq!(window.window_size(), 6);
+    assert_eq!(window.bounds(), WindowBounds { min: 6, max: 12 });
+}
+
 #[test]
 fn test_client_state_process_ack() {
     let mut state = ClientRenderState::new(4);
@@ -153,7 +278,14 @@ fn test_client_state_prepare_delta_requires_baseline() {
     let mut style_table = StyleTable::new();
     let frame = FrameData::new(80, 24);
 
-    let delta = state.prepare_delta(&frame, 1, &mut style_table, None);
+    let delta = state.prepare_delta(
+        &frame,
+        &FrameFingerprint::of(&frame),
+        1,
+        &mut style_table,
+        None,
+        None,
+    );
     assert!(delta.is_none());
 }
 
@@ -166,13 +298,59 @@ fn test_client_state_prepare_delta_after_snapshot() {
 
     let _ = state.prepare_snapshot(&frame1, 1, &mut style_table);
 
-    let delta = state.prepare_delta(&frame2, 2, &mut style_table, None);
-    assert!(delta.is_some());
-    let delta = delta.unwrap();
+    let Some(RenderUpdate::Delta(delta)) = state.prepare_delta(
+        &frame2,
+        &FrameFingerprint::of(&frame2),
+        2,
+        &mut style_table,
+        None,
+        None,
+    ) else {
+        panic!("expected a delta");
+    };
     assert_eq!(delta.base_state_id, 1);
     assert_eq!(delta.state_id, 2);
 }
 
+#[test]
+fn test_client_state_stamps_frame_sequence_and_time() {
+    let mut state = ClientRenderState::new(4);
+    let mut style_table = StyleTable::new();
+    let frame = FrameData::new(80, 24);
+
+    let snapshot = state.prepare_snapshot(&frame, 1, &mut style_table);
+    let Some(RenderUpdate::Delta(delta)) = state.prepare_delta(
+        &frame,
+        &FrameFingerprint::of(&frame),
+        2,
+        &mut style_table,
+        None,
+        None,
+    ) else {
+        panic!("expected a delta");
+    };
+
+    assert_eq!(snapshot.frame_sequence, 1);
+    assert_eq!(delta.frame_sequence, 2);
+    assert_eq!(state.frame_sequence(), 2);
+    assert!(snapshot.server_time_ms > 0);
+    assert!(delta.server_time_ms >= snapshot.server_time_ms);
+
+    // A refused delta doesn't consume a sequence number
+    let mut blocked = ClientRenderState::new(4);
+    assert!(blocked
+        .prepare_delta(
+            &frame,
+            &FrameFingerprint::of(&frame),
+            1,
+            &mut style_table,
+            None,
+            None
+        )
+        .is_none());
+    assert_eq!(blocked.frame_sequence(), 0);
+}
+
 #[test]
 fn test_client_state_blocks_delta_when_exhausted() {
     let mut state = ClientRenderState::new(2);
@@ -180,10 +358,24 @@ fn test_client_state_blocks_delta_when_exhausted() {
     let frame = FrameData::new(80, 24);
 
     let _ = state.prepare_snapshot(&frame, 1, &mut style_table);
-    let _ = state.prepare_delta(&frame, 2, &mut style_table, None);
+    let _ = state.prepare_delta(
+        &frame,
+        &FrameFingerprint::of(&frame),
+        2,
+        &mut style_table,
+        None,
+        None,
+    );
 
     assert!(!state.can_send());
-    let delta = state.prepare_delta(&frame, 3, &mut style_table, None);
+    let delta = state.prepare_delta(
+        &frame,
+        &FrameFingerprint::of(&frame),
+        3,
+        &mut style_table,
+        None,
+        None,
+    );
     assert!(delta.is_none());
 }
 
@@ -0,0 +1,82 @@
+use crate::bell::{BellCoalescer, BELL_COALESCE_MS};
+use zellij_remote_protocol::{BellEvent, BellUrgency};
+
+fn bell(pane_id: u32, urgency: BellUrgency, count: u32) -> BellEvent {
+    BellEvent {
+        pane_id,
+        urgency: urgency as i32,
+        count,
+    }
+}
+
+#[test]
+fn test_first_bell_sent_immediately() {
+    let mut bells = BellCoalescer::new();
+    assert_eq!(
+        bells.ring(1, BellUrgency::Normal, 0),
+        Some(bell(1, BellUrgency::Normal, 1))
+    );
+    assert!(!bells.has_held());
+}
+
+#[test]
+fn test_bell_storm_coalesced() {
+    let mut bells = BellCoalescer::new();
+    assert!(bells.ring(1, BellUrgency::Normal, 0).is_some());
+    for ms in 1..=100 {
+        assert!(bells.ring(1, BellUrgency::Normal, ms).is_none());
+    }
+    assert!(bells.has_held());
+
+    assert!(bells.flush(BELL_COALESCE_MS - 1).is_empty());
+    assert_eq!(
+        bells.flush(BELL_COALESCE_MS),
+        vec![bell(1, BellUrgency::Normal, 100)]
+    );
+    assert!(!bells.has_held());
+
+    // The flush started a new window
+    assert!(bells
+        .ring(1, BellUrgency::Normal, BELL_COALESCE_MS + 1)
+        .is_none());
+}
+
+#[test]
+fn test_panes_coalesced_separately() {
+    let mut bells = BellCoalescer::new();
+    assert!(bells.ring(1, BellUrgency::Normal, 0).is_some());
+    assert!(bells.ring(2, BellUrgency::High, 10).is_some());
+    assert!(bells.ring(1, BellUrgency::Normal, 20).is_none());
+    assert!(bells.ring(2, BellUrgency::High, 30).is_none());
+
+    assert_eq!(
+        bells.flush(2 * BELL_COALESCE_MS),
+        vec![
+            bell(1, BellUrgency::Normal, 1),
+            bell(2, BellUrgency::High, 1)
+        ]
+    );
+}
+
+#[test]
+fn test_held_bells_keep_most_urgent() {
+    let mut bells = BellCoalescer::new();
+    bells.ring(1, BellUrgency::Normal, 0);
+    bells.ring(1, BellUrgency::High, 10);
+    bells.ring(1, BellUrgency::Normal, 20);
+
+    assert_eq!(
+        bells.flush(BELL_COALESCE_MS),
+        vec![bell(1, BellUrgency::High, 2)]
+    );
+}
+
+#[test]
+fn test_quiet_pane_rings_again_at_once() {
+    let mut bells = BellCoalescer::new();
+    bells.ring(1, BellUrgency::Normal, 0);
+    assert!(bells.flush(BELL_COALESCE_MS).is_empty());
+    assert!(bells
+        .ring(1, BellUrgency::Normal, BELL_COALESCE_MS + 1)
+        .is_some());
+}
@@ -0,0 +1,179 @@
+use prost::Message;
+
+use crate::chunking::{chunk_delta, chunk_snapshot, ChunkAssembler, MIN_MESSAGE_BYTES};
+use crate::conformance::ClientScreen;
+use crate::delta::DeltaEngine;
+use crate::frame::{Cell, FrameStore};
+use crate::style_table::StyleTable;
+use zellij_remote_protocol::{ScreenSnapshot, Style};
+
+const COLS: usize = 2_000;
+const ROWS: usize = 3;
+
+/// Every row of `store` filled with a mix of styled narrow and wide characters
+fn fill(store: &mut FrameStore, style_table: &mut StyleTable, seed: u32) {
+    let bold = style_table.get_or_insert(&Style {
+        bold: true,
+        ..Default::default()
+    });
+    for row in 0..ROWS {
+        store.update_row(row, |r| {
+            let mut col = 0;
+            while col < COLS {
+                if (col as u32 + seed) % 7 == 3 && col + 1 < COLS {
+                    r.set_cell(
+                        col,
+                        Cell {
+                            codepoint: '中' as u32,
+                            width: 2,
+                            style_id: bold,
+                        },
+                    );
+                    r.set_cell(
+                        col + 1,
+                        Cell {
+                            codepoint: 0,
+                            width: 0,
+                            style_id: bold,
+                        },
+                    );
+                    col += 2;
+                } else {
+                    let c = char::from_u32('a' as u32 + (col as u32 + seed) % 26).unwrap();
+                    r.set_cell(
+                        col,
+                        Cell {
+                            codepoint: c as u32,
+                            width: 1,
+                            style_id: if col % 5 == 0 { bold } else { 0 },
+                        },
+                    );
+                    col += 1;
+                }
+            }
+        });
+    }
+}
+
+fn wide_snapshot() -> ScreenSnapshot {
+    let mut store = FrameStore::new(COLS, ROWS);
+    let mut style_table = StyleTable::new();
+    fill(&mut store, &mut style_table, 0);
+    store.advance_state();
+    let frame = store.snapshot();
+    DeltaEngine::compute_snapshot(&frame.data, &mut style_table, frame.state_id)
+}
+
+#[test]
+fn test_messages_that_fit_not_chunked() {
+    let snapshot = DeltaEngine::compute_snapshot(
+        &FrameStore::new(80, 24).snapshot().data,
+        &mut StyleTable::new(),
+        1,
+    );
+    let chunks = chunk_snapshot(snapshot.clone(), MIN_MESSAGE_BYTES);
+    assert_eq!(chunks, vec![snapshot.clone()]);
+    assert_eq!(
+        ChunkAssembler::new().push_snapshot(snapshot.clone()),
+        Some(snapshot)
+    );
+}
+
+#[test]
+fn test_wide_snapshot_chunked_and_reassembled() {
+    let snapshot = wide_snapshot();
+    let chunks = chunk_snapshot(snapshot.clone(), MIN_MESSAGE_BYTES);
+    assert!(chunks.len() > 1);
+    for (index, chunk) in chunks.iter().enumerate() {
+        assert!(chunk.encoded_len() <= MIN_MESSAGE_BYTES);
+        assert_eq!(chunk.state_id, snapshot.state_id);
+        assert_eq!(chunk.chunk_index, index as u32);
+        assert_eq!(chunk.chunk_count, chunks.len() as u32);
+        assert_eq!(chunk.styles.is_empty(), index > 0);
+        for row in &chunk.rows {
+            assert_ne!(row.widths.first(), Some(&0), "row split inside a wide char");
+        }
+    }
+
+    // Pushed snapshots can arrive in any order
+    let mut assembler = ChunkAssembler::new();
+    let mut whole = None;
+    for chunk in chunks.into_iter().rev() {
+        assert!(whole.is_none());
+        whole = assembler.push_snapshot(chunk);
+    }
+    assert_eq!(whole, Some(snapshot));
+    assert!(!assembler.is_gathering());
+}
+
+#[test]
+fn test_chunked_delta_applies_like_whole() {
+    let mut store = FrameStore::new(COLS, ROWS);
+    let mut style_table = StyleTable::new();
+    let baseline = store.snapshot();
+    fill(&mut store, &mut style_table, 1);
+    store.advance_state();
+    let current = store.snapshot();
+    let delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        &current.data,
+        &mut style_table,
+        baseline.state_id,
+        current.state_id,
+        None,
+    );
+
+    let chunks = chunk_delta(delta.clone(), MIN_MESSAGE_BYTES);
+    assert!(chunks.len() > 1);
+    let mut assembler = ChunkAssembler::new();
+    let mut whole = None;
+    for chunk in chunks {
+        assert!(chunk.encoded_len() <= MIN_MESSAGE_BYTES);
+        for run in chunk.row_patches.iter().flat_map(|p| &p.runs) {
+            assert_ne!(run.widths.first(), Some(&0), "run split inside a wide char");
+        }
+        assert!(whole.is_none());
+        whole = assembler.push_delta(chunk);
+    }
+    let whole = whole.unwrap();
+    assert_eq!(whole.chunk_count, 0);
+    assert_eq!(whole.row_patches.len(), delta.row_patches.len());
+
+    let mut screen = ClientScreen::new();
+    screen
+        .apply_snapshot(&DeltaEngine::compute_snapshot(
+            &baseline.data,
+            &mut style_table,
+            baseline.state_id,
+        ))
+        .unwrap();
+    screen.apply_delta(&whole).unwrap();
+    let expected = DeltaEngine::compute_snapshot(&current.data, &mut style_table, current.state_id);
+    assert_eq!(screen.to_snapshot().rows, expected.rows);
+}
+
+#[test]
+fn test_incomplete_set_dropped_for_newer_state() {
+    let older = chunk_snapshot(wide_snapshot(), MIN_MESSAGE_BYTES);
+    let newer_snapshot = ScreenSnapshot {
+        state_id: older[0].state_id + 1,
+        ..wide_snapshot()
+    };
+    let newer = chunk_snapshot(newer_snapshot.clone(), MIN_MESSAGE_BYTES);
+
+    let mut assembler = ChunkAssembler::new();
+    assert!(assembler.push_snapshot(older[0].clone()).is_none());
+    assert!(assembler.is_gathering());
+
+    let mut whole = None;
+    for chunk in newer {
+        whole = assembler.push_snapshot(chunk);
+    }
+    assert_eq!(whole, Some(newer_snapshot));
+
+    // What's left of the older set can't start it over
+    for chunk in older.into_iter().skip(1) {
+        assert!(assembler.push_snapshot(chunk).is_none());
+    }
+    assert!(!assembler.is_gathering());
+}
@@ -0,0 +1,188 @@
+use crate::color_transform::{contrast_ratio, transform_style_defs, MIN_CONTRAST_RATIO};
+use crate::conformance::ClientScreen;
+use crate::frame::Cell;
+use crate::session::{RemoteSession, RenderUpdate};
+use crate::style_convert::{ansi256_color, default_color, rgb_color};
+use zellij_remote_protocol::{color, Color, ColorTransform, StateAck, Style, StyleDef};
+
+fn transformed(transform: &ColorTransform, style: &Style) -> Style {
+    let mut defs = vec![StyleDef {
+        style_id: 1,
+        style: Some(style.clone()),
+    }];
+    transform_style_defs(transform, &mut defs);
+    defs.remove(0).style.unwrap()
+}
+
+fn rgb(color: Option<&Color>) -> (u8, u8, u8) {
+    match color.and_then(|c| c.value.as_ref()) {
+        Some(color::Value::Rgb(rgb)) => (rgb.r as u8, rgb.g as u8, rgb.b as u8),
+        other => panic!("Expected an RGB color, got {:?}", other),
+    }
+}
+
+fn colored(fg: Color, bg: Color) -> Style {
+    Style {
+        fg: Some(fg),
+        bg: Some(bg),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_no_transform_leaves_styles_alone() {
+    let style = Style {
+        dim: true,
+        ..colored(ansi256_color(238), rgb_color(250, 250, 250))
+    };
+    assert_eq!(transformed(&ColorTransform::default(), &style), style);
+}
+
+#[test]
+fn test_high_contrast_fixes_unreadable_text() {
+    let high_contrast = ColorTransform {
+        high_contrast: true,
+        ..Default::default()
+    };
+
+    // Dark gray on the (dark) default background
+    let style = Style {
+        dim: true,
+        ..colored(ansi256_color(238), default_color())
+    };
+    let style = transformed(&high_contrast, &style);
+    assert_eq!(rgb(style.fg.as_ref()), (0xff, 0xff, 0xff));
+    assert!(!style.dim);
+
+    // Default text on a light background
+    let style = Style {
+        bg: Some(rgb_color(0xee, 0xee, 0xee)),
+        ..Default::default()
+    };
+    assert_eq!(
+        rgb(transformed(&high_contrast, &style).fg.as_ref()),
+        (0, 0, 0)
+    );
+
+    // Already readable: kept in the client's own palette
+    let readable = colored(ansi256_color(15), ansi256_color(0));
+    assert_eq!(transformed(&high_contrast, &readable), readable);
+}
+
+#[test]
+fn test_dark_background_forced() {
+    let force_dark = ColorTransform {
+        force_dark_background: true,
+        ..Default::default()
+    };
+    let style = transformed(
+        &force_dark,
+        &colored(rgb_color(0x20, 0x20, 0x60), rgb_color(0xfa, 0xf0, 0xe6)),
+    );
+    let (fg, bg) = (rgb(style.fg.as_ref()), rgb(style.bg.as_ref()));
+    assert!(bg.0 < 0x20 && bg.1 < 0x20 && bg.2 < 0x20, "{:?}", bg);
+    assert!(fg.2 > 0xc0, "{:?}", fg);
+    assert!(contrast_ratio(fg, bg) >= MIN_CONTRAST_RATIO);
+
+    // Reverse video: the fg slot is what's seen as the background
+    let reversed = Style {
+        reverse: true,
+        ..colored(rgb_color(0xff, 0xff, 0xff), default_color())
+    };
+    let style = transformed(&force_dark, &reversed);
+    assert_eq!(rgb(style.fg.as_ref()), (0, 0, 0));
+    assert_eq!(style.bg, Some(default_color()));
+}
+
+#[test]
+fn test_protanopia_safe_separates_red_and_green() {
+    let protanopia_safe = ColorTransform {
+        protanopia_safe: true,
+        ..Default::default()
+    };
+    let red = transformed(
+        &protanopia_safe,
+        &colored(ansi256_color(9), default_color()),
+    );
+    let green = transformed(
+        &protanopia_safe,
+        &colored(ansi256_color(10), default_color()),
+    );
+    let (red, green) = (rgb(red.fg.as_ref()), rgb(green.fg.as_ref()));
+    assert!(red.2 > 0x80, "{:?}", red);
+    assert!(green.2 < 0x20, "{:?}", green);
+
+    // Neutral colors look the same either way
+    let white = colored(ansi256_color(15), default_color());
+    assert_eq!(transformed(&protanopia_safe, &white), white);
+}
+
+#[test]
+fn test_transform_applies_to_one_client_only() {
+    let style = colored(ansi256_color(238), default_color());
+    let mut session = RemoteSession::new(10, 2);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    assert!(session.set_color_transform(
+        1,
+        ColorTransform {
+            high_contrast: true,
+            ..Default::default()
+        }
+    ));
+    assert!(!session.set_color_transform(3, ColorTransform::default()));
+
+    let style_id = session.style_table.get_or_insert(&style);
+    session.frame_store.update_row(0, |r| {
+        r.set_cell(
+            0,
+            Cell {
+                codepoint: 'a' as u32,
+                width: 1,
+                style_id,
+            },
+        )
+    });
+    session.frame_store.advance_state();
+
+    let mut screens = [ClientScreen::new(), ClientScreen::new()];
+    for (client_id, screen) in [1, 2].into_iter().zip(screens.iter_mut()) {
+        match session.get_render_update(client_id) {
+            Some(RenderUpdate::Snapshot(snapshot)) => {
+                screen.apply_snapshot(&snapshot).unwrap();
+                session.process_state_ack(
+                    client_id,
+                    &StateAck {
+                        last_applied_state_id: snapshot.state_id,
+                        ..Default::default()
+                    },
+                );
+            },
+            other => panic!("Expected a snapshot, got {:?}", other),
+        }
+    }
+    let seen = screens[0].style_at(0, 0).unwrap();
+    assert_eq!(rgb(seen.fg.as_ref()), (0xff, 0xff, 0xff));
+    assert_eq!(screens[1].style_at(0, 0), Some(style.clone()));
+    assert_eq!(session.style_table.get(style_id), Some(&style));
+
+    // Turning it off resends the styles the client holds, as they are
+    session.set_color_transform(1, ColorTransform::default());
+    session.frame_store.update_row(1, |r| {
+        r.set_cell(
+            0,
+            Cell {
+                codepoint: 'b' as u32,
+                width: 1,
+                style_id: 0,
+            },
+        )
+    });
+    session.frame_store.advance_state();
+    match session.get_render_update(1) {
+        Some(RenderUpdate::Delta(delta)) => screens[0].apply_delta(&delta).unwrap(),
+        Some(RenderUpdate::Snapshot(snapshot)) => screens[0].apply_snapshot(&snapshot).unwrap(),
+        other => panic!("Expected an update, got {:?}", other),
+    }
+    assert_eq!(screens[0].style_at(0, 0), Some(style));
+}
@@ -0,0 +1,265 @@
+use std::collections::HashSet;
+
+use prost::Message;
+use zellij_remote_protocol::{
+    conformance_step, CellRun, ConformanceSuite, DisplaySize, RowPatch, ScreenDelta, ScreenSnapshot,
+};
+
+use crate::conformance::{
+    generate_suite, run_vector, ClientScreen, ConformanceError, ConformanceFailure,
+};
+
+const VECTORS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/conformance/vectors.pb");
+/// Set to rewrite the checked-in vectors after an intentional change to the delta engine
+const UPDATE_ENV: &str = "ZELLIJ_REMOTE_UPDATE_VECTORS";
+
+fn blank_snapshot(state_id: u64, cols: u32, rows: u32) -> ScreenSnapshot {
+    ScreenSnapshot {
+        state_id,
+        size: Some(DisplaySize { cols, rows }),
+        style_table_reset: true,
+        ..Default::default()
+    }
+}
+
+fn patch(row: u32, col_start: u32, text: &str, style_id: u32) -> RowPatch {
+    RowPatch {
+        row,
+        runs: vec![CellRun {
+            col_start,
+            codepoints: text.chars().map(|c| c as u32).collect(),
+            widths: vec![1; text.chars().count()],
+            style_ids: vec![style_id; text.chars().count()],
+        }],
+        line_size: 0,
+        echo: false,
+        row_crc32: 0,
+    }
+}
+
+#[test]
+fn test_generated_vectors_pass_reference_client() {
+    let suite = generate_suite();
+    assert!(!suite.vectors.is_empty());
+    for vector in &suite.vectors {
+        assert_eq!(run_vector(vector), Ok(()), "vector {}", vector.name);
+    }
+}
+
+#[test]
+fn test_vector_names_are_unique() {
+    let suite = generate_suite();
+    let names: HashSet<_> = suite.vectors.iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(names.len(), suite.vectors.len());
+}
+
+#[test]
+fn test_generator_is_deterministic() {
+    assert_eq!(
+        generate_suite().encode_to_vec(),
+        generate_suite().encode_to_vec()
+    );
+}
+
+#[test]
+fn test_checked_in_vectors_are_current() {
+    let generated = generate_suite().encode_to_vec();
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        std::fs::write(VECTORS_PATH, &generated).unwrap();
+        return;
+    }
+
+    let checked_in = std::fs::read(VECTORS_PATH).unwrap();
+    assert!(
+        checked_in == generated,
+        "conformance vectors are stale; rerun with {}=1 if the change is intended",
+        UPDATE_ENV
+    );
+
+    let suite = ConformanceSuite::decode(&checked_in[..]).unwrap();
+    for vector in &suite.vectors {
+        assert_eq!(run_vector(vector), Ok(()), "vector {}", vector.name);
+    }
+}
+
+#[test]
+fn test_vectors_exercise_sparse_runs_and_styles() {
+    let suite = generate_suite();
+    let deltas: Vec<&ScreenDelta> = suite
+        .vectors
+        .iter()
+        .flat_map(|v| &v.steps)
+        .filter_map(|step| match &step.frame {
+            Some(conformance_step::Frame::Delta(delta)) => Some(delta),
+            _ => None,
+        })
+        .collect();
+
+    assert!(deltas
+        .iter()
+        .any(|d| d.row_patches.iter().any(|p| p.runs.len() > 1)));
+    assert!(deltas.iter().any(|d| !d.styles_added.is_empty()));
+    assert!(deltas
+        .iter()
+        .any(|d| d.row_patches.is_empty() && d.cursor.is_some()));
+}
+
+#[test]
+fn test_delta_before_snapshot_rejected() {
+    let mut screen = ClientScreen::new();
+    let delta = ScreenDelta {
+        base_state_id: 0,
+        state_id: 1,
+        ..Default::default()
+    };
+    assert_eq!(
+        screen.apply_delta(&delta),
+        Err(ConformanceError::NoSnapshot)
+    );
+}
+
+#[test]
+fn test_base_mismatch_rejected() {
+    let mut screen = ClientScreen::new();
+    screen.apply_snapshot(&blank_snapshot(5, 4, 1)).unwrap();
+
+    let delta = ScreenDelta {
+        base_state_id: 4,
+        state_id: 6,
+        row_patches: vec![patch(0, 0, "a", 0)],
+        ..Default::default()
+    };
+    assert_eq!(
+        screen.apply_delta(&delta),
+        Err(ConformanceError::BaseMismatch {
+            expected: 5,
+            received: 4
+        })
+    );
+    assert_eq!(screen.state_id(), Some(5));
+}
+
+#[test]
+fn test_invalid_patches_rejected_without_partial_apply() {
+    let mut screen = ClientScreen::new();
+    screen.apply_snapshot(&blank_snapshot(1, 4, 2)).unwrap();
+    let before = screen.to_snapshot();
+
+    let cases = vec![
+        (
+            patch(2, 0, "a", 0),
+            ConformanceError::RowOutOfRange { row: 2 },
+        ),
+        (
+            patch(1, 2, "abc", 0),
+            ConformanceError::ColumnOutOfRange { row: 1, col: 4 },
+        ),
+        (
+            patch(1, 0, "a", 3),
+            ConformanceError::UnknownStyle { style_id: 3 },
+        ),
+    ];
+    for (bad_patch, expected) in cases {
+        let delta = ScreenDelta {
+            base_state_id: 1,
+            state_id: 2,
+            // A valid patch first, which must not stick when the next one fails
+            row_patches: vec![patch(0, 0, "ok", 0), bad_patch],
+            ..Default::default()
+        };
+        assert_eq!(screen.apply_delta(&delta), Err(expected));
+        assert_eq!(screen.to_snapshot(), before);
+    }
+}
+
+#[test]
+fn test_mismatch_reported() {
+    let mut vector = generate_suite().vectors.remove(0);
+    let expected = vector.expected.as_mut().unwrap();
+    expected.rows[0].codepoints[0] = 'H' as u32;
+
+    match run_vector(&vector) {
+        Err(ConformanceFailure::Mismatch { expected, actual }) => {
+            assert_eq!(expected.rows[0].codepoints[0], 'H' as u32);
+            assert_eq!(actual.rows[0].codepoints[0], 'h' as u32);
+        },
+        other => panic!("expected a mismatch, got {:?}", other),
+    }
+}
+
+fn wide_run(col_start: u32, codepoints: &[u32], widths: &[u32]) -> RowPatch {
+    RowPatch {
+        row: 0,
+        runs: vec![CellRun {
+            col_start,
+            codepoints: codepoints.to_vec(),
+            widths: widths.to_vec(),
+            style_ids: vec![0; codepoints.len()],
+        }],
+        ..Default::default()
+    }
+}
+
+fn delta(base_state_id: u64, row_patches: Vec<RowPatch>) -> ScreenDelta {
+    ScreenDelta {
+        base_state_id,
+        state_id: base_state_id + 1,
+        row_patches,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_runs_splitting_wide_chars_rejected() {
+    let mut screen = ClientScreen::new();
+    screen.apply_snapshot(&blank_snapshot(1, 6, 1)).unwrap();
+    screen
+        .apply_delta(&delta(1, vec![wide_run(0, &['中' as u32, 0], &[2, 0])]))
+        .unwrap();
+    let before = screen.to_snapshot();
+
+    // Starts on the continuation of 中
+    assert_eq!(
+        screen.apply_delta(&delta(2, vec![wide_run(1, &[0, 'a' as u32], &[0, 1])])),
+        Err(ConformanceError::SplitWideChar { row: 0, col: 1 })
+    );
+    // Ends on 文 without its continuation
+    assert_eq!(
+        screen.apply_delta(&delta(2, vec![wide_run(2, &['文' as u32], &[2])])),
+        Err(ConformanceError::SplitWideChar { row: 0, col: 3 })
+    );
+    assert_eq!(screen.to_snapshot(), before);
+
+    // In the last column there is no room for a continuation
+    screen
+        .apply_delta(&delta(2, vec![wide_run(5, &['字' as u32], &[2])]))
+        .unwrap();
+}
+
+#[test]
+fn test_overwritten_halves_of_wide_chars_blanked() {
+    let mut screen = ClientScreen::new();
+    screen.apply_snapshot(&blank_snapshot(1, 6, 1)).unwrap();
+    let cjk = ['中' as u32, 0, '文' as u32, 0, '字' as u32, 0];
+    screen
+        .apply_delta(&delta(1, vec![wide_run(0, &cjk, &[2, 0, 2, 0, 2, 0])]))
+        .unwrap();
+
+    // "ab" lands on the continuation of 中 and the leading half of 文
+    screen
+        .apply_delta(&delta(2, vec![patch(0, 1, "ab", 0)]))
+        .unwrap();
+    let row = &screen.to_snapshot().rows[0];
+    assert_eq!(
+        row.codepoints,
+        [
+            ' ' as u32,
+            'a' as u32,
+            'b' as u32,
+            ' ' as u32,
+            '字' as u32,
+            0
+        ]
+    );
+    assert_eq!(row.widths, [1, 1, 1, 1, 2, 0]);
+}
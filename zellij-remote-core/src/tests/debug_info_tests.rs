@@ -0,0 +1,82 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::debug_info::{self, DEBUG_FRAME_INFO_AVAILABLE};
+use crate::delta::DeltaEngine;
+use crate::frame::{Cell, FrameStore};
+use crate::session::{RemoteSession, RenderUpdate};
+use crate::style_table::StyleTable;
+
+#[test]
+fn test_rows_diffed_counts_dirty_rows_on_screen() {
+    assert_eq!(debug_info::rows_diffed(None, 24), 24);
+    let dirty: HashSet<usize> = [0, 5, 30].into_iter().collect();
+    assert_eq!(debug_info::rows_diffed(Some(&dirty), 24), 2);
+}
+
+#[test]
+fn test_info_counts_sent_cells() {
+    let mut store = FrameStore::new(80, 24);
+    let mut style_table = StyleTable::new();
+    let baseline = store.snapshot();
+    let snapshot = DeltaEngine::compute_snapshot(&baseline.data, &mut style_table, 1);
+    let info = debug_info::for_snapshot(&snapshot, 100, Duration::from_micros(42));
+    assert_eq!(info.rows_diffed, 24);
+    assert_eq!(info.cells_changed, 80 * 24);
+    assert_eq!(info.encoded_bytes, 100);
+    assert_eq!(info.compute_micros, 42);
+
+    store.update_row(3, |row| {
+        for col in 0..5 {
+            row.set_cell(
+                col,
+                Cell {
+                    codepoint: 'x' as u32,
+                    width: 1,
+                    style_id: 0,
+                },
+            );
+        }
+    });
+    store.advance_state();
+    let current = store.snapshot();
+    let delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        &current.data,
+        &mut style_table,
+        baseline.state_id,
+        current.state_id,
+        None,
+    );
+    let info = debug_info::for_delta(&delta, 1, Duration::ZERO);
+    assert_eq!(info.rows_diffed, 1);
+    assert_eq!(info.cells_changed, 5);
+}
+
+#[test]
+fn test_debug_info_only_in_builds_that_allow_it() {
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    assert!(session.set_debug_frame_info_enabled(1, true));
+    assert!(!session.set_debug_frame_info_enabled(3, true));
+
+    let Some(RenderUpdate::Snapshot(snapshot)) = session.get_render_update(1) else {
+        panic!("expected a snapshot");
+    };
+    assert_eq!(snapshot.debug_info.is_some(), DEBUG_FRAME_INFO_AVAILABLE);
+    if let Some(info) = snapshot.debug_info {
+        let mut without = snapshot.clone();
+        without.debug_info = None;
+        assert_eq!(
+            info.encoded_bytes as usize,
+            prost::Message::encoded_len(&without)
+        );
+    }
+
+    // Never for clients that didn't ask
+    let Some(RenderUpdate::Snapshot(snapshot)) = session.get_render_update(2) else {
+        panic!("expected a snapshot");
+    };
+    assert!(snapshot.debug_info.is_none());
+}
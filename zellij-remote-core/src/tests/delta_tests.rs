@@ -1,6 +1,12 @@
+use crate::conformance::ClientScreen;
 use crate::delta::DeltaEngine;
-use crate::frame::{Cell, Cursor, CursorShape, FrameStore};
+use crate::frame::{
+    Cell, Cursor, CursorColor, CursorShape, Frame, FrameFingerprint, FrameStore, LineSize,
+};
 use crate::style_table::StyleTable;
+use crate::tests::put;
+use prost::Message;
+use zellij_remote_protocol::{CellRun, RowPatch, ScreenDelta};
 
 #[test]
 fn test_delta_detects_changed_rows() {
@@ -79,6 +85,7 @@ fn test_delta_includes_cursor_change() {
         visible: true,
         blink: false,
         shape: CursorShape::Underline,
+        color: None,
     });
     store.advance_state();
 
@@ -100,6 +107,59 @@ fn test_delta_includes_cursor_change() {
     assert_eq!(cursor.col, 20);
 }
 
+#[test]
+fn test_cursor_color_and_blink_round_trip() {
+    let mut store = FrameStore::new(80, 24);
+    let baseline = store.snapshot();
+
+    let cursor = Cursor {
+        row: 3,
+        col: 7,
+        visible: true,
+        blink: false,
+        shape: CursorShape::Bar,
+        color: Some(CursorColor {
+            r: 255,
+            g: 128,
+            b: 0,
+        }),
+    };
+    store.set_cursor(cursor);
+    store.advance_state();
+    let colored = store.snapshot();
+
+    let delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        &colored.data,
+        &mut StyleTable::new(),
+        baseline.state_id,
+        colored.state_id,
+        None,
+    );
+    assert!(delta.row_patches.is_empty());
+    assert_eq!(DeltaEngine::decode_cursor(&delta.cursor.unwrap()), cursor);
+
+    // Only the color changes: still a cursor update, and unset again on the client
+    store.set_cursor(Cursor {
+        color: None,
+        ..cursor
+    });
+    store.advance_state();
+    let current = store.snapshot();
+    let delta = DeltaEngine::compute_delta(
+        &colored.data,
+        &current.data,
+        &mut StyleTable::new(),
+        colored.state_id,
+        current.state_id,
+        None,
+    );
+    let state = delta.cursor.expect("a color change is a cursor change");
+    assert!(state.color.is_none());
+    assert_eq!(DeltaEngine::decode_cursor(&state).color, None);
+    assert!(!DeltaEngine::decode_cursor(&state).blink);
+}
+
 #[test]
 fn test_snapshot_includes_all_rows() {
     let mut store = FrameStore::new(80, 24);
@@ -319,6 +379,7 @@ fn test_cursor_shape_bar_maps_to_beam() {
         visible: true,
         blink: false,
         shape: CursorShape::Bar,
+        color: None,
     });
     store.advance_state();
 
@@ -430,6 +491,133 @@ fn test_intra_row_diff_non_contiguous_changes() {
     assert_eq!(delta.row_patches[0].runs[1].codepoints[0], 'B' as u32);
 }
 
+/// Delta for `edits` (col, char) made on row 0 of a blank 40-column frame
+fn delta_for_edits(edits: &[(usize, char)], style_id: u32) -> ScreenDelta {
+    let mut store = FrameStore::new(40, 1);
+    let baseline = store.snapshot();
+    for &(col, c) in edits {
+        store.update_row(0, |row| {
+            row.set_cell(
+                col,
+                Cell {
+                    codepoint: c as u32,
+                    width: 1,
+                    style_id,
+                },
+            )
+        });
+    }
+    store.advance_state();
+    let delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        store.current_frame(),
+        &mut StyleTable::new(),
+        baseline.state_id,
+        store.current_state_id(),
+        None,
+    );
+    let mut screen = ClientScreen::new();
+    screen
+        .apply_snapshot(&DeltaEngine::compute_snapshot(
+            &baseline.data,
+            &mut StyleTable::new(),
+            baseline.state_id,
+        ))
+        .unwrap();
+    screen.apply_delta(&delta).unwrap();
+    let expected = DeltaEngine::compute_snapshot(
+        store.current_frame(),
+        &mut StyleTable::new(),
+        store.current_state_id(),
+    );
+    assert_eq!(screen.to_snapshot().rows, expected.rows);
+    delta
+}
+
+/// `patch` with each run split at its unchanged cells
+fn split_runs(patch: &RowPatch, changed: &[usize]) -> RowPatch {
+    let runs = patch
+        .runs
+        .iter()
+        .flat_map(|run| {
+            (0..run.codepoints.len())
+                .filter(|i| changed.contains(&(run.col_start as usize + i)))
+                .map(|i| CellRun {
+                    col_start: run.col_start + i as u32,
+                    codepoints: vec![run.codepoints[i]],
+                    widths: vec![run.widths[i]],
+                    style_ids: vec![run.style_ids[i]],
+                })
+        })
+        .collect();
+    RowPatch {
+        runs,
+        ..patch.clone()
+    }
+}
+
+#[test]
+fn test_runs_merge_across_small_gaps() {
+    // "a b c d": four changes a cell apart go out as one run
+    let delta = delta_for_edits(&[(0, 'a'), (2, 'b'), (4, 'c'), (6, 'd')], 0);
+    let patch = &delta.row_patches[0];
+    assert_eq!(patch.runs.len(), 1);
+    assert_eq!(patch.runs[0].col_start, 0);
+    assert_eq!(
+        patch.runs[0].codepoints,
+        "a b c d".chars().map(|c| c as u32).collect::<Vec<_>>()
+    );
+    assert!(patch.encoded_len() < split_runs(patch, &[0, 2, 4, 6]).encoded_len());
+
+    // Up to three plain cells between changes still pay off
+    let delta = delta_for_edits(&[(10, 'x'), (14, 'y')], 0);
+    let patch = &delta.row_patches[0];
+    assert_eq!(patch.runs.len(), 1);
+    assert!(patch.encoded_len() < split_runs(patch, &[10, 14]).encoded_len());
+}
+
+#[test]
+fn test_runs_kept_apart_when_merging_costs_more() {
+    // Four cells apart, resending the gap is bigger than another run
+    let delta = delta_for_edits(&[(10, 'x'), (15, 'y')], 0);
+    assert_eq!(delta.row_patches[0].runs.len(), 2);
+
+    // Wider cells tip the balance sooner: here the changes carry a style id that
+    // takes two bytes, but the gap is plain, so three cells still merge...
+    let delta = delta_for_edits(&[(10, 'x'), (14, 'y')], 300);
+    assert_eq!(delta.row_patches[0].runs.len(), 1);
+
+    // ...while a gap of multi-byte codepoints doesn't
+    let mut store = FrameStore::new(40, 1);
+    for col in 11..14 {
+        put(&mut store, 0, col, 'é', 1, 0);
+    }
+    store.advance_state();
+    let baseline = store.snapshot();
+    put(&mut store, 0, 10, 'x', 1, 0);
+    put(&mut store, 0, 14, 'y', 1, 0);
+    let delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        store.current_frame(),
+        &mut StyleTable::new(),
+        baseline.state_id,
+        baseline.state_id + 1,
+        None,
+    );
+    let patch = &delta.row_patches[0];
+    assert_eq!(patch.runs.len(), 2);
+    let merged = RowPatch {
+        runs: vec![CellRun {
+            col_start: 10,
+            codepoints: "xéééy".chars().map(|c| c as u32).collect(),
+            widths: vec![1; 5],
+            style_ids: vec![0; 5],
+        }],
+        ..patch.clone()
+    };
+    assert!(patch.encoded_len() <= merged.encoded_len());
+}
+
 #[test]
 fn test_dirty_row_false_positive_produces_no_patch() {
     let store = FrameStore::new(80, 24);
@@ -660,3 +848,310 @@ fn test_new_rows_not_duplicated_when_dirty_rows_provided() {
     assert_eq!(delta.row_patches[0].row, 10);
     assert_eq!(delta.row_patches[1].row, 11);
 }
+
+#[test]
+fn test_line_size_change_produces_patch_without_runs() {
+    let mut store = FrameStore::new(10, 4);
+    let baseline = store.snapshot();
+
+    store.update_row(2, |row| row.set_line_size(LineSize::DoubleWidth));
+    store.advance_state();
+    let current = store.snapshot();
+    let dirty = store.take_dirty_rows();
+    let mut style_table = StyleTable::new();
+
+    let delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        &current.data,
+        &mut style_table,
+        baseline.state_id,
+        current.state_id,
+        Some(&dirty),
+    );
+
+    assert_eq!(delta.row_patches.len(), 1);
+    assert_eq!(delta.row_patches[0].row, 2);
+    assert!(delta.row_patches[0].runs.is_empty());
+    assert_eq!(
+        delta.row_patches[0].line_size(),
+        zellij_remote_protocol::LineSize::DoubleWidth
+    );
+}
+
+#[test]
+fn test_unchanged_line_size_is_unspecified_in_patch() {
+    let mut store = FrameStore::new(10, 4);
+    store.update_row(1, |row| row.set_line_size(LineSize::DoubleHeightTop));
+    store.advance_state();
+    let baseline = store.snapshot();
+
+    store.update_row(1, |row| {
+        row.set_cell(
+            0,
+            Cell {
+                codepoint: 'X' as u32,
+                width: 1,
+                style_id: 0,
+            },
+        )
+    });
+    store.advance_state();
+    let current = store.snapshot();
+    let mut style_table = StyleTable::new();
+
+    let delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        &current.data,
+        &mut style_table,
+        baseline.state_id,
+        current.state_id,
+        None,
+    );
+
+    assert_eq!(delta.row_patches.len(), 1);
+    assert_eq!(
+        delta.row_patches[0].line_size(),
+        zellij_remote_protocol::LineSize::Unspecified
+    );
+
+    let snapshot = DeltaEngine::compute_snapshot(&current.data, &mut style_table, 2);
+    assert_eq!(
+        snapshot.rows[1].line_size(),
+        zellij_remote_protocol::LineSize::DoubleHeightTop
+    );
+    assert_eq!(
+        snapshot.rows[0].line_size(),
+        zellij_remote_protocol::LineSize::Unspecified
+    );
+}
+
+#[test]
+fn test_incremental_delta_matches_full_delta_with_baseline_frame() {
+    let mut store = FrameStore::new(20, 6);
+    put(&mut store, 0, 3, 'a', 1, 0);
+    store.advance_state();
+    let baseline = store.snapshot();
+    store.take_dirty_rows();
+
+    put(&mut store, 0, 3, 'b', 1, 0);
+    put(&mut store, 2, 0, 'c', 1, 0);
+    put(&mut store, 2, 10, 'd', 1, 0);
+    store.update_row(4, |row| row.set_line_size(LineSize::DoubleWidth));
+    store.advance_state();
+    let current = store.snapshot();
+    let dirty = store.take_dirty_rows();
+
+    let mut style_table = StyleTable::new();
+    let full = DeltaEngine::compute_delta(
+        &baseline.data,
+        &current.data,
+        &mut style_table,
+        baseline.state_id,
+        current.state_id,
+        Some(&dirty),
+    );
+    let incremental = DeltaEngine::compute_delta_incremental(
+        &FrameFingerprint::of(&baseline.data),
+        Some(&baseline.data),
+        &current.data,
+        &FrameFingerprint::of(&current.data),
+        &mut style_table,
+        baseline.state_id,
+        current.state_id,
+        Some(&dirty),
+    );
+
+    assert_eq!(incremental, full);
+    assert_eq!(full.row_patches[1].runs.len(), 2);
+}
+
+#[test]
+fn test_incremental_delta_without_baseline_frame_sends_whole_rows() {
+    let mut store = FrameStore::new(8, 3);
+    put(&mut store, 1, 0, 'x', 1, 0);
+    store.update_row(2, |row| row.set_line_size(LineSize::DoubleHeightTop));
+    store.advance_state();
+    let baseline = store.snapshot();
+
+    let mut style_table = StyleTable::new();
+    let mut screen = ClientScreen::new();
+    screen
+        .apply_snapshot(&DeltaEngine::compute_snapshot(
+            &baseline.data,
+            &mut style_table,
+            baseline.state_id,
+        ))
+        .unwrap();
+
+    put(&mut store, 1, 5, 'y', 1, 0);
+    put(&mut store, 2, 1, 'z', 1, 0);
+    store.set_cursor(Cursor {
+        row: 2,
+        col: 4,
+        ..Default::default()
+    });
+    store.advance_state();
+    let current = store.snapshot();
+
+    let delta = DeltaEngine::compute_delta_incremental(
+        &FrameFingerprint::of(&baseline.data),
+        None,
+        &current.data,
+        &FrameFingerprint::of(&current.data),
+        &mut style_table,
+        baseline.state_id,
+        current.state_id,
+        None,
+    );
+
+    let rows: Vec<u32> = delta.row_patches.iter().map(|p| p.row).collect();
+    assert_eq!(rows, vec![1, 2]);
+    assert!(delta
+        .row_patches
+        .iter()
+        .all(|p| p.runs.len() == 1 && p.runs[0].col_start == 0 && p.runs[0].codepoints.len() == 8));
+    // Line size is only sent when it changed, even without the baseline cells
+    assert_eq!(
+        delta.row_patches[1].line_size(),
+        zellij_remote_protocol::LineSize::Unspecified
+    );
+    assert!(delta.cursor.is_some());
+
+    screen.apply_delta(&delta).unwrap();
+    let expected = DeltaEngine::compute_snapshot(&current.data, &mut style_table, current.state_id);
+    assert_eq!(screen.to_snapshot().rows, expected.rows);
+}
+
+fn put_wide(store: &mut FrameStore, col: usize, c: char, continuation_style: u32) {
+    store.update_row(0, |r| {
+        r.set_cell(
+            col,
+            Cell {
+                codepoint: c as u32,
+                width: 2,
+                style_id: 0,
+            },
+        );
+        r.set_cell(
+            col + 1,
+            Cell {
+                codepoint: 0,
+                width: 0,
+                style_id: continuation_style,
+            },
+        );
+    });
+}
+
+/// Apply the delta from `baseline` to `store`'s frame on top of a snapshot of `baseline`
+fn check_delta_applies(baseline: &Frame, store: &mut FrameStore) {
+    let mut style_table = StyleTable::new();
+    let mut screen = ClientScreen::new();
+    screen
+        .apply_snapshot(&DeltaEngine::compute_snapshot(
+            &baseline.data,
+            &mut style_table,
+            baseline.state_id,
+        ))
+        .unwrap();
+    store.advance_state();
+    let current = store.snapshot();
+    let delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        &current.data,
+        &mut style_table,
+        baseline.state_id,
+        current.state_id,
+        None,
+    );
+    for run in delta.row_patches.iter().flat_map(|p| &p.runs) {
+        assert_ne!(run.widths.first(), Some(&0), "run starts on a continuation");
+    }
+    screen.apply_delta(&delta).unwrap();
+    let expected = DeltaEngine::compute_snapshot(&current.data, &mut style_table, current.state_id);
+    assert_eq!(screen.to_snapshot().rows, expected.rows);
+}
+
+#[test]
+fn test_runs_take_in_whole_wide_chars() {
+    let mut store = FrameStore::new(6, 1);
+    put_wide(&mut store, 0, '中', 0);
+    put_wide(&mut store, 2, '文', 0);
+    store.advance_state();
+    let baseline = store.snapshot();
+
+    // Only the leading cell differs; the run still carries the continuation
+    put_wide(&mut store, 2, '字', 0);
+    let delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        store.current_frame(),
+        &mut StyleTable::new(),
+        baseline.state_id,
+        baseline.state_id + 1,
+        None,
+    );
+    let run = &delta.row_patches[0].runs[0];
+    assert_eq!(run.col_start, 2);
+    assert_eq!(run.widths, [2, 0]);
+    check_delta_applies(&baseline, &mut store);
+}
+
+#[test]
+fn test_runs_starting_on_continuation_back_up() {
+    let mut store = FrameStore::new(6, 1);
+    put_wide(&mut store, 0, '中', 0);
+    put_wide(&mut store, 2, '文', 0);
+    store.advance_state();
+    let baseline = store.snapshot();
+
+    // Only the continuation of 文 differs
+    put_wide(&mut store, 2, '文', 1);
+    let delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        store.current_frame(),
+        &mut StyleTable::new(),
+        baseline.state_id,
+        baseline.state_id + 1,
+        None,
+    );
+    let run = &delta.row_patches[0].runs[0];
+    assert_eq!(run.col_start, 2);
+    assert_eq!(run.codepoints, ['文' as u32, 0]);
+}
+
+#[test]
+fn test_echo_suppression_carried_on_every_frame() {
+    let mut store = FrameStore::new(20, 2);
+    let baseline = store.snapshot();
+    put(&mut store, 0, 0, 'P', 1, 0);
+    store.set_echo_suppressed(true);
+    store.advance_state();
+    let current = store.snapshot();
+    let mut style_table = StyleTable::new();
+
+    let delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        &current.data,
+        &mut style_table,
+        baseline.state_id,
+        current.state_id,
+        None,
+    );
+    assert!(delta.echo_suppressed);
+    let snapshot = DeltaEngine::compute_snapshot(&current.data, &mut style_table, current.state_id);
+    assert!(snapshot.echo_suppressed);
+
+    // Not a diff: a delta off a suppressed baseline says echo is back on
+    store.set_echo_suppressed(false);
+    store.advance_state();
+    let echoing = store.snapshot();
+    let delta = DeltaEngine::compute_delta(
+        &current.data,
+        &echoing.data,
+        &mut style_table,
+        current.state_id,
+        echoing.state_id,
+        None,
+    );
+    assert!(!delta.echo_suppressed);
+}
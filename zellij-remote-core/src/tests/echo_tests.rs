@@ -0,0 +1,131 @@
+use crate::echo::RecentInput;
+use crate::frame::Cell;
+use crate::session::{RemoteSession, RenderUpdate};
+use zellij_remote_protocol::{
+    input_event, key_event, CellRun, InputEvent, KeyEvent, KeyModifiers, RowPatch, ScreenDelta,
+    StateAck,
+};
+
+fn text(seq: u64, text: &str) -> InputEvent {
+    InputEvent {
+        input_seq: seq,
+        client_time_ms: 0,
+        payload: Some(input_event::Payload::TextUtf8(text.as_bytes().to_vec())),
+    }
+}
+
+fn key(seq: u64, ch: char, modifiers: u32) -> InputEvent {
+    InputEvent {
+        input_seq: seq,
+        client_time_ms: 0,
+        payload: Some(input_event::Payload::Key(KeyEvent {
+            modifiers: Some(KeyModifiers { bits: modifiers }),
+            key: Some(key_event::Key::UnicodeScalar(ch as u32)),
+        })),
+    }
+}
+
+fn patch(row: u32, col_start: u32, text: &str) -> RowPatch {
+    RowPatch {
+        row,
+        runs: vec![CellRun {
+            col_start,
+            codepoints: text.chars().map(|c| c as u32).collect(),
+            widths: vec![1; text.chars().count()],
+            style_ids: vec![0; text.chars().count()],
+        }],
+        line_size: 0,
+        echo: false,
+        row_crc32: 0,
+    }
+}
+
+fn delta(row_patches: Vec<RowPatch>) -> ScreenDelta {
+    ScreenDelta {
+        row_patches,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_typed_text_on_cursor_row_is_echo() {
+    let mut recent = RecentInput::new();
+    recent.record(&text(1, "ls"));
+    recent.record(&key(2, ' ', 0));
+    recent.record(&key(3, '-', 0));
+
+    let mut update = delta(vec![patch(4, 2, "s -"), patch(5, 0, "s -")]);
+    recent.mark_echo(&mut update, 4, 3);
+    assert!(update.row_patches[0].echo);
+    // Off the cursor row it's output, whatever it looks like
+    assert!(!update.row_patches[1].echo);
+}
+
+#[test]
+fn test_only_delivered_input_counts_as_echo() {
+    let mut recent = RecentInput::new();
+    recent.record(&text(1, "a"));
+    recent.record(&text(2, "b"));
+
+    let mut update = delta(vec![patch(0, 0, "ab")]);
+    recent.mark_echo(&mut update, 0, 1);
+    assert!(!update.row_patches[0].echo);
+    recent.mark_echo(&mut update, 0, 2);
+    assert!(update.row_patches[0].echo);
+}
+
+#[test]
+fn test_untyped_content_and_shortcuts_are_not_echo() {
+    let mut recent = RecentInput::new();
+    recent.record(&key(1, 'c', 4)); // Ctrl+C
+    recent.record(&text(2, "x"));
+
+    let mut update = delta(vec![patch(0, 0, "^C"), patch(1, 0, "cx")]);
+    recent.mark_echo(&mut update, 0, 2);
+    assert!(!update.row_patches[0].echo);
+    recent.mark_echo(&mut update, 1, 2);
+    assert!(!update.row_patches[1].echo);
+}
+
+#[test]
+fn test_session_delta_carries_watermark_and_echo() {
+    let mut session = RemoteSession::new(20, 4);
+    session.add_client(1, 4);
+    session.lease_manager.request_control(1, None, false);
+
+    let first = match session.get_render_update(1) {
+        Some(RenderUpdate::Snapshot(snapshot)) => snapshot,
+        other => panic!("Expected a snapshot, got {:?}", other),
+    };
+    session.process_state_ack(
+        1,
+        &StateAck {
+            last_applied_state_id: first.state_id,
+            ..Default::default()
+        },
+    );
+
+    session.process_input(1, &text(1, "hi")).unwrap();
+    for (col, ch) in "hi".chars().enumerate() {
+        session.frame_store.update_row(0, |r| {
+            r.set_cell(
+                col,
+                Cell {
+                    codepoint: ch as u32,
+                    width: 1,
+                    style_id: 0,
+                },
+            )
+        });
+    }
+    session.frame_store.advance_state();
+
+    match session.get_render_update(1) {
+        Some(RenderUpdate::Delta(delta)) => {
+            assert_eq!(delta.delivered_input_watermark, 1);
+            assert_eq!(delta.row_patches.len(), 1);
+            assert!(delta.row_patches[0].echo);
+        },
+        other => panic!("Expected a delta, got {:?}", other),
+    }
+}
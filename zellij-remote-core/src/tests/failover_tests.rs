@@ -0,0 +1,103 @@
+use crate::failover::{format_endpoint, parse_endpoint, EndpointFailover};
+use zellij_remote_protocol::Endpoint;
+
+fn endpoint(host: &str, port: u32) -> Endpoint {
+    Endpoint {
+        host: host.to_string(),
+        port,
+    }
+}
+
+#[test]
+fn test_parse_endpoint() {
+    assert_eq!(
+        parse_endpoint("192.168.1.5:4433"),
+        Some(endpoint("192.168.1.5", 4433))
+    );
+    assert_eq!(
+        parse_endpoint(" box.tailnet.ts.net:443 "),
+        Some(endpoint("box.tailnet.ts.net", 443))
+    );
+    assert_eq!(
+        parse_endpoint("[fd7a::1]:4433"),
+        Some(endpoint("fd7a::1", 4433))
+    );
+
+    for bad in [
+        "",
+        "host",
+        "host:",
+        ":4433",
+        "host:0",
+        "host:70000",
+        "fd7a::1:4433",
+    ] {
+        assert_eq!(parse_endpoint(bad), None, "{:?}", bad);
+    }
+}
+
+#[test]
+fn test_format_endpoint_roundtrips() {
+    for s in ["10.0.0.2:4433", "[fd7a::1]:4433", "example.com:443"] {
+        assert_eq!(format_endpoint(&parse_endpoint(s).unwrap()), s);
+    }
+}
+
+#[test]
+fn test_fail_over_cycles_through_alternates() {
+    let primary = endpoint("192.168.1.5", 4433);
+    let mut failover = EndpointFailover::new(primary.clone());
+    failover.set_alternates(&[endpoint("100.64.0.2", 4433), endpoint("10.8.0.1", 4433)]);
+    assert_eq!(failover.current(), &primary);
+
+    assert!(failover.fail_over());
+    assert_eq!(failover.current().host, "100.64.0.2");
+    assert!(failover.fail_over());
+    assert_eq!(failover.current().host, "10.8.0.1");
+
+    // Everything failed: back to the primary, and the caller should back off
+    assert!(!failover.fail_over());
+    assert_eq!(failover.current(), &primary);
+    assert!(failover.fail_over());
+}
+
+#[test]
+fn test_connecting_resets_failures() {
+    let mut failover = EndpointFailover::new(endpoint("192.168.1.5", 4433));
+    failover.set_alternates(&[endpoint("100.64.0.2", 4433)]);
+
+    assert!(failover.fail_over());
+    failover.record_connected();
+    // The alternate's path dies later; the primary is worth another try
+    assert!(failover.fail_over());
+    assert_eq!(failover.current().host, "192.168.1.5");
+}
+
+#[test]
+fn test_no_alternates_never_fails_over() {
+    let mut failover = EndpointFailover::new(endpoint("192.168.1.5", 4433));
+    assert!(!failover.fail_over());
+    assert_eq!(failover.current().host, "192.168.1.5");
+}
+
+#[test]
+fn test_set_alternates_dedupes_and_keeps_current() {
+    let primary = endpoint("192.168.1.5", 4433);
+    let tailnet = endpoint("100.64.0.2", 4433);
+    let mut failover = EndpointFailover::new(primary.clone());
+    failover.set_alternates(&[
+        primary.clone(),
+        tailnet.clone(),
+        tailnet.clone(),
+        endpoint("bad", 0),
+    ]);
+    assert_eq!(failover.alternates(), std::slice::from_ref(&tailnet));
+
+    failover.fail_over();
+    failover.set_alternates(&[endpoint("10.8.0.1", 4433), tailnet.clone()]);
+    assert_eq!(failover.current(), &tailnet);
+
+    // The current alternate is no longer listed
+    failover.set_alternates(&[endpoint("10.8.0.1", 4433)]);
+    assert_eq!(failover.current(), &primary);
+}
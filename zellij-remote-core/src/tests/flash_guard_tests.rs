@@ -0,0 +1,240 @@
+use crate::conformance::ClientScreen;
+use crate::flash_guard::{FlashGuard, HeldRows};
+use crate::frame::{Cell, FrameData, FrameFingerprint, Row};
+use crate::frame_hash::hash_frame;
+use crate::session::{RemoteSession, RenderUpdate};
+use crate::tests::put_text;
+use zellij_remote_protocol::{FlashGuardSettings, StateAck};
+
+fn cell(c: char) -> Cell {
+    Cell {
+        codepoint: c as u32,
+        width: 1,
+        style_id: 0,
+    }
+}
+
+fn frame_with(rows: &[&str]) -> FrameData {
+    let mut frame = FrameData::new(8, rows.len());
+    for (idx, text) in rows.iter().enumerate() {
+        for (col, c) in text.chars().enumerate() {
+            frame.rows[idx].set_cell(col, cell(c));
+        }
+    }
+    frame
+}
+
+fn text(frame: &FrameData, row: usize) -> String {
+    frame.rows[row]
+        .0
+        .cells
+        .iter()
+        .filter_map(|cell| char::from_u32(cell.codepoint))
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+fn enabled_guard() -> FlashGuard {
+    let mut guard = FlashGuard::default();
+    guard.configure(&FlashGuardSettings::default());
+    guard
+}
+
+/// Pass a frame of `rows` through the guard at `now_ms`, returning it as the client
+/// gets it
+fn show(guard: &mut FlashGuard, rows: &[&str], now_ms: u64) -> (FrameData, HeldRows) {
+    let mut frame = frame_with(rows);
+    let mut fingerprint = FrameFingerprint::of(&frame);
+    let held = guard.hold(&mut frame, &mut fingerprint, now_ms);
+    assert_eq!(fingerprint, FrameFingerprint::of(&frame));
+    (frame, held)
+}
+
+fn blink(i: u64) -> &'static str {
+    if i % 2 == 1 {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+#[test]
+fn test_fast_toggling_row_held_until_it_settles() {
+    let mut guard = enabled_guard();
+    show(&mut guard, &["title", ""], 0);
+
+    // 10 Hz: the fourth flip back to recent content is held as the client had it
+    let mut shown = vec![];
+    for i in 1..=6 {
+        let (frame, held) = show(&mut guard, &["title", blink(i)], i * 50);
+        shown.push((text(&frame, 1), held.len()));
+    }
+    let expected: Vec<_> = ["on", "off", "on", "off", "on", "on"]
+        .iter()
+        .map(|text| text.to_string())
+        .zip([0, 0, 0, 0, 0, 1])
+        .collect();
+    assert_eq!(shown, expected);
+    assert!(guard.is_holding());
+
+    // Held rows change at most once per update interval
+    for i in 7..=25 {
+        let (frame, held) = show(&mut guard, &["title", blink(i)], i * 50);
+        assert_eq!(text(&frame, 1), "on", "at {}ms", i * 50);
+        assert_eq!(text(&frame, 0), "title");
+        assert!(held.contains_key(&1) && !held.contains_key(&0));
+    }
+    let (frame, _) = show(&mut guard, &["title", blink(26)], 1300);
+    assert_eq!(text(&frame, 1), "off");
+    let (frame, _) = show(&mut guard, &["title", blink(27)], 1350);
+    assert_eq!(text(&frame, 1), "off");
+
+    // Once it has stopped flipping, the row goes out as it is
+    assert!(!guard.release_due(1400));
+    assert!(guard.release_due(2400));
+    let (frame, held) = show(&mut guard, &["title", "on"], 2400);
+    assert_eq!(text(&frame, 1), "on");
+    assert!(held.is_empty());
+    assert!(!guard.is_holding());
+}
+
+#[test]
+fn test_changes_that_dont_flash_pass_through() {
+    let mut guard = enabled_guard();
+    show(&mut guard, &["", ""], 0);
+
+    // Typing changes a row often, but never back to what it was
+    let mut line = String::new();
+    for (i, c) in "hello!".chars().enumerate() {
+        line.push(c);
+        let (frame, held) = show(&mut guard, &[&line, ""], i as u64 * 20);
+        assert_eq!(text(&frame, 0), line);
+        assert!(held.is_empty());
+    }
+
+    // Blinking at 2.5 Hz stays under the limit
+    for i in 1..=10 {
+        let (frame, held) = show(&mut guard, &["hello!", blink(i)], i * 400);
+        assert_eq!(text(&frame, 1), blink(i));
+        assert!(held.is_empty());
+    }
+}
+
+#[test]
+fn test_disabled_guard_and_custom_limits() {
+    let mut guard = FlashGuard::default();
+    assert!(!guard.is_enabled());
+    for i in 0..20 {
+        let (frame, held) = show(&mut guard, &[blink(i)], i * 10);
+        assert_eq!(text(&frame, 0), blink(i));
+        assert!(held.is_empty());
+    }
+
+    guard.configure(&FlashGuardSettings {
+        max_flashes_per_second: 1,
+        ..Default::default()
+    });
+    show(&mut guard, &[""], 0);
+    let shown: Vec<_> = (1..=5)
+        .map(|i| text(&show(&mut guard, &[blink(i)], i * 100).0, 0))
+        .collect();
+    assert_eq!(shown, vec!["on", "off", "on", "on", "on"]);
+
+    // Opting out lets the row through at once
+    guard.configure(&FlashGuardSettings {
+        disabled: true,
+        ..Default::default()
+    });
+    assert!(!guard.is_holding());
+    assert_eq!(text(&show(&mut guard, &["off"], 600).0, 0), "off");
+}
+
+/// Send the client its next update and ack it
+fn send(session: &mut RemoteSession, screen: &mut ClientScreen) {
+    let state_id = match session.get_render_update(1) {
+        Some(RenderUpdate::Snapshot(snapshot)) => {
+            screen.apply_snapshot(&snapshot).unwrap();
+            snapshot.state_id
+        },
+        Some(RenderUpdate::Delta(delta)) => {
+            screen.apply_delta(&delta).unwrap();
+            delta.state_id
+        },
+        None => panic!("Expected an update"),
+    };
+    session.process_state_ack(
+        1,
+        &StateAck {
+            last_applied_state_id: state_id,
+            ..Default::default()
+        },
+    );
+}
+
+/// The session's current frame with `row` as `held`
+fn with_held_row(session: &RemoteSession, row: usize, held: &Row) -> FrameData {
+    let mut frame = session.frame_store.current_frame().clone();
+    frame.rows[row] = held.clone();
+    frame
+}
+
+#[test]
+fn test_session_holds_flashing_rows_and_keeps_client_in_sync() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+    assert!(session.set_flash_guard(1, &FlashGuardSettings::default()));
+    let mut screen = ClientScreen::new();
+    put_text(&mut session, 0, 0, "calm", 0);
+    send(&mut session, &mut screen);
+
+    for i in 1..=5 {
+        put_text(
+            &mut session,
+            1,
+            0,
+            if i % 2 == 1 { "on " } else { "off" },
+            0,
+        );
+        send(&mut session, &mut screen);
+        assert_eq!(
+            screen.frame_hash(),
+            hash_frame(session.frame_store.current_frame())
+        );
+    }
+    let on = session.frame_store.current_frame().rows[1].clone();
+
+    // Held: the client keeps "on ", while other rows still change
+    put_text(&mut session, 1, 0, "off", 0);
+    put_text(&mut session, 0, 0, "busy", 0);
+    send(&mut session, &mut screen);
+    assert_eq!(
+        screen.frame_hash(),
+        hash_frame(&with_held_row(&session, 1, &on))
+    );
+    assert!(session.is_frame_paced(1));
+    put_text(&mut session, 1, 0, "on ", 0);
+    put_text(&mut session, 1, 0, "off", 0);
+    put_text(&mut session, 2, 0, "more", 0);
+    send(&mut session, &mut screen);
+    assert_eq!(
+        screen.frame_hash(),
+        hash_frame(&with_held_row(&session, 1, &on))
+    );
+
+    // Released rows are diffed against what the client held, not the session's past
+    session.set_flash_guard(
+        1,
+        &FlashGuardSettings {
+            disabled: true,
+            ..Default::default()
+        },
+    );
+    put_text(&mut session, 3, 0, "done", 0);
+    send(&mut session, &mut screen);
+    assert_eq!(
+        screen.frame_hash(),
+        hash_frame(session.frame_store.current_frame())
+    );
+    assert!(!session.is_frame_paced(1));
+}
@@ -0,0 +1,129 @@
+use crate::conformance::ClientScreen;
+use crate::delta::DeltaEngine;
+use crate::frame::{Cell, FrameStore, LineSize};
+use crate::frame_hash::{hash_frame, FrameHasher};
+use crate::session::{RemoteSession, RenderUpdate};
+use crate::style_table::StyleTable;
+use crate::tests::put;
+use zellij_remote_protocol::{ScreenDelta, ScreenSnapshot, Style};
+
+#[test]
+fn test_client_screen_hash_matches_server_frame() {
+    let mut store = FrameStore::new(10, 4);
+    let mut style_table = StyleTable::new();
+    let bold = style_table.get_or_insert(&Style {
+        bold: true,
+        ..Default::default()
+    });
+    put(&mut store, 0, 0, 'h', 1, 0);
+    put(&mut store, 0, 1, 'i', 1, bold);
+    store.advance_state();
+    let baseline = store.snapshot();
+
+    let mut screen = ClientScreen::new();
+    screen
+        .apply_snapshot(&DeltaEngine::compute_snapshot(
+            &baseline.data,
+            &mut style_table,
+            baseline.state_id,
+        ))
+        .unwrap();
+    assert_eq!(screen.frame_hash(), hash_frame(&baseline.data));
+
+    put(&mut store, 2, 3, '中', 2, bold);
+    put(&mut store, 2, 4, '\0', 0, bold);
+    store.update_row(3, |r| r.set_line_size(LineSize::DoubleWidth));
+    let mut cursor = store.current_frame().cursor;
+    cursor.row = 2;
+    cursor.col = 5;
+    store.set_cursor(cursor);
+    store.advance_state();
+    let current = store.snapshot();
+
+    screen
+        .apply_delta(&DeltaEngine::compute_delta(
+            &baseline.data,
+            &current.data,
+            &mut style_table,
+            baseline.state_id,
+            current.state_id,
+            None,
+        ))
+        .unwrap();
+    assert_eq!(screen.frame_hash(), hash_frame(&current.data));
+    assert_ne!(hash_frame(&baseline.data), hash_frame(&current.data));
+}
+
+#[test]
+fn test_hash_covers_cells_line_size_and_cursor() {
+    let store = FrameStore::new(4, 2);
+    let blank = hash_frame(store.current_frame());
+
+    let mut changed = store.current_frame().clone();
+    changed.rows[1].set_cell(
+        0,
+        Cell {
+            style_id: 3,
+            ..Cell::default()
+        },
+    );
+    assert_ne!(hash_frame(&changed), blank);
+
+    let mut changed = store.current_frame().clone();
+    changed.rows[0].set_line_size(LineSize::DoubleHeightTop);
+    assert_ne!(hash_frame(&changed), blank);
+
+    let mut changed = store.current_frame().clone();
+    changed.cursor.visible = false;
+    assert_ne!(hash_frame(&changed), blank);
+
+    // Same cells, different shape
+    assert_ne!(hash_frame(&FrameStore::new(8, 1).snapshot().data), blank);
+}
+
+#[test]
+fn test_unspecified_line_size_hashes_as_single() {
+    let mut single = FrameHasher::new(1, 1);
+    single.row(zellij_remote_protocol::LineSize::Single as i32);
+    let mut unspecified = FrameHasher::new(1, 1);
+    unspecified.row(zellij_remote_protocol::LineSize::Unspecified as i32);
+    assert_eq!(single.finish(0, 0, true), unspecified.finish(0, 0, true));
+}
+
+fn render(session: &mut RemoteSession, client_id: u64) -> (u64, u64) {
+    match session.get_render_update(client_id) {
+        Some(RenderUpdate::Snapshot(ScreenSnapshot { frame_hash, .. })) => (frame_hash, 0),
+        Some(RenderUpdate::Delta(ScreenDelta { frame_hash, .. })) => (0, frame_hash),
+        None => panic!("Expected a render update"),
+    }
+}
+
+#[test]
+fn test_frame_hash_only_sent_in_integrity_mode() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    assert!(session.set_frame_hash_enabled(1, true));
+    session.frame_store.advance_state();
+
+    let expected = hash_frame(session.frame_store.current_frame());
+    assert_eq!(render(&mut session, 1), (expected, 0));
+    assert_eq!(render(&mut session, 2), (0, 0));
+}
+
+#[test]
+fn test_frame_hash_mismatches_counted_per_client() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    session.set_frame_hash_enabled(1, true);
+
+    assert_eq!(session.record_frame_hash_mismatch(1), Some(1));
+    assert_eq!(session.record_frame_hash_mismatch(1), Some(2));
+    // Without integrity mode a decode error isn't a hash mismatch
+    assert_eq!(session.record_frame_hash_mismatch(2), None);
+    assert_eq!(session.record_frame_hash_mismatch(3), None);
+
+    assert_eq!(session.frame_hash_mismatches(1), 2);
+    assert_eq!(session.frame_hash_mismatches(2), 0);
+}
@@ -0,0 +1,80 @@
+use crate::frame_stats::{FrameArrival, FrameStats};
+
+#[test]
+fn test_in_order_frames() {
+    let mut stats = FrameStats::new();
+
+    assert_eq!(stats.record_frame(1, 1_000, 1_020), FrameArrival::InOrder);
+    assert_eq!(stats.record_frame(2, 1_016, 1_036), FrameArrival::InOrder);
+
+    assert_eq!(stats.frames_received(), 2);
+    assert_eq!(stats.frames_missed(), 0);
+    assert_eq!(stats.last_sequence(), 2);
+    assert_eq!(stats.latency_ms(), Some(20));
+}
+
+#[test]
+fn test_gap_and_stale_frames() {
+    let mut stats = FrameStats::new();
+    stats.record_frame(1, 1_000, 1_000);
+
+    assert_eq!(
+        stats.record_frame(4, 1_030, 1_030),
+        FrameArrival::Gap { missed: 2 }
+    );
+    assert_eq!(stats.record_frame(3, 1_020, 1_040), FrameArrival::Stale);
+
+    assert_eq!(stats.frames_missed(), 2);
+    assert_eq!(stats.frames_stale(), 1);
+    assert_eq!(stats.frames_received(), 2);
+    assert_eq!(stats.last_sequence(), 4);
+}
+
+#[test]
+fn test_untracked_frames_ignored() {
+    let mut stats = FrameStats::new();
+    assert_eq!(stats.record_frame(0, 0, 1_000), FrameArrival::Untracked);
+    assert_eq!(stats.frames_received(), 0);
+    assert!(!stats.is_stalled(10_000, 100));
+}
+
+#[test]
+fn test_queuing_delay_independent_of_clock_offset() {
+    let mut stats = FrameStats::new();
+    // Client clock runs 5s behind the server
+    stats.record_frame(1, 10_000, 5_030);
+    stats.record_frame(2, 10_100, 5_180);
+
+    assert_eq!(stats.latency_ms(), Some(-4_920));
+    assert_eq!(stats.queuing_delay_ms(), Some(50));
+}
+
+#[test]
+fn test_frame_interval_and_stall() {
+    let mut stats = FrameStats::new();
+    stats.record_frame(1, 0, 1_000);
+    stats.record_frame(2, 0, 1_016);
+    stats.record_frame(3, 0, 1_116);
+
+    assert_eq!(stats.max_frame_interval_ms(), 100);
+    let interval = stats.frame_interval_ms().unwrap();
+    assert!(interval > 16.0 && interval < 100.0);
+    // Frames without a server timestamp still count towards pacing
+    assert_eq!(stats.latency_ms(), None);
+
+    assert!(!stats.is_stalled(1_200, 500));
+    assert!(stats.is_stalled(1_616, 500));
+}
+
+#[test]
+fn test_reset_connection_restarts_sequence() {
+    let mut stats = FrameStats::new();
+    stats.record_frame(1, 1_000, 1_010);
+    stats.record_frame(2, 1_010, 1_020);
+
+    stats.reset_connection();
+
+    assert_eq!(stats.record_frame(1, 2_000, 2_050), FrameArrival::InOrder);
+    assert_eq!(stats.frames_received(), 3);
+    assert_eq!(stats.queuing_delay_ms(), Some(0));
+}
@@ -1,4 +1,4 @@
-use crate::frame::{Cell, FrameStore, Row};
+use crate::frame::{Cell, Cursor, CursorShape, FrameStore, Row};
 use std::sync::Arc;
 
 #[test]
@@ -195,3 +195,22 @@ fn test_get_cell_out_of_bounds_returns_none() {
     assert!(row.get_cell(10).is_none());
     assert!(row.get_cell(100).is_none());
 }
+
+#[test]
+fn test_cursor_same_except_blink() {
+    let cursor = Cursor {
+        row: 2,
+        col: 7,
+        ..Default::default()
+    };
+    let blinked = Cursor {
+        blink: !cursor.blink,
+        ..cursor
+    };
+    assert!(cursor.same_except_blink(&blinked));
+    assert!(!cursor.same_except_blink(&Cursor { col: 8, ..blinked }));
+    assert!(!cursor.same_except_blink(&Cursor {
+        shape: CursorShape::Bar,
+        ..cursor
+    }));
+}
@@ -1,6 +1,11 @@
-use crate::input::{AckResult, InputProcessResult, InputReceiver, InputSender};
-use crate::lease::{Duration, TestClock};
-use zellij_remote_protocol::InputEvent;
+use std::time::Duration;
+
+use crate::clock::ManualClock;
+use crate::input::{
+    AckResult, InputProcessResult, InputReceiver, InputSender, ReorderWindow,
+    MAX_INPUT_BATCH_EVENTS, MAX_INPUT_REORDER_EVENTS,
+};
+use zellij_remote_protocol::{InputBatch, InputEvent};
 
 fn make_input(seq: u64, client_time_ms: u32) -> InputEvent {
     InputEvent {
@@ -10,6 +15,24 @@ fn make_input(seq: u64, client_time_ms: u32) -> InputEvent {
     }
 }
 
+fn make_batch(seqs: std::ops::RangeInclusive<u64>, client_time_ms: u32) -> InputBatch {
+    InputBatch {
+        client_time_ms,
+        events: seqs.map(|seq| make_input(seq, 0)).collect(),
+    }
+}
+
+fn reordering_receiver(max_events: u64) -> InputReceiver {
+    InputReceiver::new().with_reorder_window(ReorderWindow {
+        max_events,
+        hold_ms: 250,
+    })
+}
+
+fn seqs(events: &[InputEvent]) -> Vec<u64> {
+    events.iter().map(|e| e.input_seq).collect()
+}
+
 #[test]
 fn test_sequential_input_processed() {
     let mut receiver = InputReceiver::new();
@@ -89,9 +112,9 @@ fn test_cumulative_ack_semantics() {
 
 #[test]
 fn test_inflight_window_limits() {
-    TestClock::reset();
+    let clock = ManualClock::new();
 
-    let mut sender = InputSender::new(3);
+    let mut sender = InputSender::new(3).with_clock(clock.shared());
 
     assert!(sender.can_send());
     assert_eq!(sender.inflight_count(), 0);
@@ -116,16 +139,16 @@ fn test_inflight_window_limits() {
 fn test_ack_clears_inflight() {
     use zellij_remote_protocol::InputAck;
 
-    TestClock::reset();
+    let clock = ManualClock::new();
 
-    let mut sender = InputSender::new(5);
+    let mut sender = InputSender::new(5).with_clock(clock.shared());
 
     sender.mark_sent(1, 100);
     sender.mark_sent(2, 200);
     sender.mark_sent(3, 300);
     assert_eq!(sender.inflight_count(), 3);
 
-    TestClock::advance(Duration::from_millis(50));
+    clock.advance(Duration::from_millis(50));
 
     let ack = InputAck {
         acked_seq: 2,
@@ -157,9 +180,9 @@ fn test_ack_clears_inflight() {
 
 #[test]
 fn test_sender_next_seq_increments() {
-    TestClock::reset();
+    let clock = ManualClock::new();
 
-    let mut sender = InputSender::new(10);
+    let mut sender = InputSender::new(10).with_clock(clock.shared());
 
     assert_eq!(sender.next_seq(), 1);
     sender.mark_sent(1, 100);
@@ -172,9 +195,9 @@ fn test_sender_next_seq_increments() {
 fn test_ack_without_rtt_sample() {
     use zellij_remote_protocol::InputAck;
 
-    TestClock::reset();
+    let clock = ManualClock::new();
 
-    let mut sender = InputSender::new(5);
+    let mut sender = InputSender::new(5).with_clock(clock.shared());
     sender.mark_sent(1, 100);
     sender.mark_sent(2, 200);
 
@@ -193,9 +216,9 @@ fn test_ack_without_rtt_sample() {
 fn test_stale_ack() {
     use zellij_remote_protocol::InputAck;
 
-    TestClock::reset();
+    let clock = ManualClock::new();
 
-    let mut sender = InputSender::new(5);
+    let mut sender = InputSender::new(5).with_clock(clock.shared());
 
     let ack = InputAck {
         acked_seq: 0,
@@ -222,3 +245,336 @@ fn test_oldest_inflight_age() {
     assert!(age.is_some());
     assert!(age.unwrap() < 1000); // Should be very recent
 }
+
+#[test]
+fn test_batch_processed_and_acked_once() {
+    let mut receiver = InputReceiver::new();
+    receiver.process_input(&make_input(1, 100));
+    receiver.generate_ack();
+
+    let batch = make_batch(2..=5, 500);
+    let events = receiver.process_batch(&batch).unwrap();
+    assert_eq!(events.len(), 4);
+    assert_eq!(receiver.last_acked_seq(), 5);
+
+    let ack = receiver.generate_ack();
+    assert_eq!(ack.acked_seq, 5);
+    assert_eq!(ack.rtt_sample_seq, 5);
+    assert_eq!(ack.echoed_client_time_ms, 500);
+}
+
+#[test]
+fn test_batch_rejected_atomically() {
+    let mut receiver = InputReceiver::new();
+
+    // Gap inside the batch
+    let mut batch = make_batch(1..=3, 100);
+    batch.events[2].input_seq = 4;
+    assert_eq!(
+        receiver.process_batch(&batch),
+        Err(InputProcessResult::OutOfOrder {
+            expected: 3,
+            received: 4
+        })
+    );
+    assert_eq!(receiver.last_acked_seq(), 0);
+
+    // Batch starting past the next expected seq
+    assert_eq!(
+        receiver.process_batch(&make_batch(2..=3, 100)),
+        Err(InputProcessResult::OutOfOrder {
+            expected: 1,
+            received: 2
+        })
+    );
+    assert_eq!(receiver.last_acked_seq(), 0);
+
+    assert_eq!(
+        receiver.process_batch(&InputBatch::default()),
+        Err(InputProcessResult::Duplicate)
+    );
+}
+
+#[test]
+fn test_resent_batch_skips_processed_events() {
+    let mut receiver = InputReceiver::new();
+    receiver.process_batch(&make_batch(1..=3, 100)).unwrap();
+
+    assert_eq!(
+        receiver.process_batch(&make_batch(1..=3, 100)),
+        Err(InputProcessResult::Duplicate)
+    );
+
+    let resent = make_batch(2..=5, 200);
+    let events = receiver.process_batch(&resent).unwrap();
+    let seqs: Vec<u64> = events.iter().map(|e| e.input_seq).collect();
+    assert_eq!(seqs, vec![4, 5]);
+    assert_eq!(receiver.last_acked_seq(), 5);
+}
+
+#[test]
+fn test_sender_coalesces_queued_inputs() {
+    let clock = ManualClock::new();
+
+    let mut sender = InputSender::new(1).with_clock(clock.shared());
+    sender.mark_sent(1, 100);
+    assert!(!sender.can_send());
+
+    assert_eq!(sender.queue(make_input(0, 0)), 2);
+    assert_eq!(sender.queue(make_input(0, 0)), 3);
+    assert_eq!(sender.queued_count(), 2);
+    assert!(sender.take_batch(200).is_none());
+
+    clock.advance(Duration::from_millis(30));
+    sender.process_ack(&zellij_remote_protocol::InputAck {
+        acked_seq: 1,
+        rtt_sample_seq: 1,
+        echoed_client_time_ms: 100,
+    });
+
+    let batch = sender.take_batch(200).unwrap();
+    let seqs: Vec<u64> = batch.events.iter().map(|e| e.input_seq).collect();
+    assert_eq!(seqs, vec![2, 3]);
+    assert_eq!(batch.client_time_ms, 200);
+    assert_eq!(sender.queued_count(), 0);
+    assert_eq!(sender.next_seq(), 4);
+
+    // The receiver's single ack for the batch clears it and yields an RTT sample
+    let mut receiver = InputReceiver::new_from_seq(1);
+    receiver.process_batch(&batch).unwrap();
+    clock.advance(Duration::from_millis(20));
+    match sender.process_ack(&receiver.generate_ack()) {
+        AckResult::Ok { rtt_sample } => assert_eq!(rtt_sample.unwrap().seq, 3),
+        other => panic!("expected Ok, got {:?}", other),
+    }
+    assert_eq!(sender.inflight_count(), 0);
+}
+
+#[test]
+fn test_batches_capped() {
+    let mut sender = InputSender::new(1);
+    sender.mark_sent(1, 0);
+    for _ in 0..MAX_INPUT_BATCH_EVENTS + 1 {
+        sender.queue(make_input(0, 0));
+    }
+    sender.process_ack(&zellij_remote_protocol::InputAck {
+        acked_seq: 1,
+        rtt_sample_seq: 0,
+        echoed_client_time_ms: 0,
+    });
+
+    let batch = sender.take_batch(0).unwrap();
+    assert_eq!(batch.events.len(), MAX_INPUT_BATCH_EVENTS);
+    assert_eq!(sender.queued_count(), 1);
+}
+
+#[test]
+fn test_early_inputs_held_and_delivered_in_order() {
+    let mut receiver = reordering_receiver(4);
+
+    assert_eq!(
+        receiver.process_input(&make_input(3, 300)),
+        InputProcessResult::Held
+    );
+    assert_eq!(
+        receiver.process_input(&make_input(2, 200)),
+        InputProcessResult::Held
+    );
+    assert_eq!(receiver.last_acked_seq(), 0);
+    assert_eq!(receiver.held_count(), 2);
+
+    assert_eq!(
+        receiver.process_input(&make_input(1, 100)),
+        InputProcessResult::Processed
+    );
+    assert_eq!(seqs(&receiver.take_released()), vec![2, 3]);
+    assert_eq!(receiver.held_count(), 0);
+
+    let ack = receiver.generate_ack();
+    assert_eq!(ack.acked_seq, 3);
+    assert_eq!(ack.rtt_sample_seq, 3);
+    assert_eq!(ack.echoed_client_time_ms, 300);
+}
+
+#[test]
+fn test_held_inputs_deduplicated() {
+    let mut receiver = reordering_receiver(4);
+
+    for seq in [4, 3, 2] {
+        assert_eq!(
+            receiver.process_input(&make_input(seq, 0)),
+            InputProcessResult::Held
+        );
+    }
+    assert_eq!(
+        receiver.process_input(&make_input(3, 0)),
+        InputProcessResult::Duplicate
+    );
+    assert_eq!(receiver.held_count(), 3);
+
+    receiver.process_input(&make_input(1, 0));
+    assert_eq!(seqs(&receiver.take_released()), vec![2, 3, 4]);
+    assert_eq!(
+        receiver.process_input(&make_input(2, 0)),
+        InputProcessResult::Duplicate
+    );
+    assert_eq!(
+        receiver.process_input(&make_input(4, 0)),
+        InputProcessResult::Duplicate
+    );
+    assert!(receiver.take_released().is_empty());
+    assert_eq!(receiver.last_acked_seq(), 4);
+}
+
+#[test]
+fn test_gap_beyond_reorder_window_rejected() {
+    let mut receiver = reordering_receiver(4);
+
+    assert_eq!(
+        receiver.process_input(&make_input(6, 0)),
+        InputProcessResult::OutOfOrder {
+            expected: 1,
+            received: 6
+        }
+    );
+    assert_eq!(
+        receiver.process_input(&make_input(5, 0)),
+        InputProcessResult::Held
+    );
+
+    // Off by default
+    let mut strict = InputReceiver::new();
+    assert!(matches!(
+        strict.process_input(&make_input(2, 0)),
+        InputProcessResult::OutOfOrder { .. }
+    ));
+}
+
+#[test]
+fn test_held_inputs_expire() {
+    let clock = ManualClock::new();
+    let mut receiver = reordering_receiver(4).with_clock(clock.shared());
+
+    receiver.process_input(&make_input(3, 0));
+    clock.advance(Duration::from_millis(250));
+    receiver.process_input(&make_input(1, 0));
+    assert!(receiver.take_released().is_empty());
+    assert_eq!(receiver.last_acked_seq(), 1);
+    assert_eq!(receiver.held_count(), 0);
+
+    // The client resends what was dropped
+    receiver.process_input(&make_input(2, 0));
+    assert_eq!(
+        receiver.process_input(&make_input(3, 0)),
+        InputProcessResult::Processed
+    );
+}
+
+#[test]
+fn test_early_batches_held_whole() {
+    let mut receiver = reordering_receiver(8);
+
+    assert_eq!(
+        receiver.process_batch(&make_batch(4..=6, 600)),
+        Err(InputProcessResult::Held)
+    );
+    assert_eq!(
+        receiver.process_batch(&make_batch(4..=6, 600)),
+        Err(InputProcessResult::Duplicate)
+    );
+    // Ends past the window, so none of it is kept
+    assert_eq!(
+        receiver.process_batch(&make_batch(7..=9, 900)),
+        Err(InputProcessResult::OutOfOrder {
+            expected: 1,
+            received: 7
+        })
+    );
+    assert_eq!(receiver.held_count(), 3);
+
+    // Overlaps what's held and closes the gap
+    let events = receiver.process_batch(&make_batch(1..=5, 500)).unwrap();
+    assert_eq!(seqs(&events), vec![1, 2, 3, 4, 5, 6]);
+    let ack = receiver.generate_ack();
+    assert_eq!(ack.acked_seq, 6);
+    assert_eq!(ack.rtt_sample_seq, 6);
+    assert_eq!(ack.echoed_client_time_ms, 600);
+}
+
+#[test]
+fn test_shuffled_inputs_delivered_once_in_order() {
+    let mut receiver = reordering_receiver(8);
+    let mut delivered = Vec::new();
+
+    for seq in [5, 3, 8, 3, 2, 7, 1, 5, 6, 4, 2, 8] {
+        if receiver.process_input(&make_input(seq, 0)) == InputProcessResult::Processed {
+            delivered.push(seq);
+            delivered.extend(seqs(&receiver.take_released()));
+        }
+    }
+    assert_eq!(delivered, (1..=8).collect::<Vec<_>>());
+    assert_eq!(receiver.held_count(), 0);
+}
+
+#[test]
+fn test_reorder_window_capped() {
+    let mut receiver = reordering_receiver(u64::MAX);
+    assert_eq!(
+        receiver.reorder_window().max_events,
+        MAX_INPUT_REORDER_EVENTS
+    );
+
+    // However far ahead a flood of early inputs reaches, only the window is held
+    for seq in 2..=MAX_INPUT_REORDER_EVENTS * 4 {
+        receiver.process_input(&make_input(seq, 0));
+    }
+    assert_eq!(receiver.held_count(), MAX_INPUT_REORDER_EVENTS as usize - 1);
+    assert_eq!(
+        receiver.process_input(&make_input(1, 0)),
+        InputProcessResult::Processed
+    );
+    assert_eq!(
+        receiver.take_released().len(),
+        MAX_INPUT_REORDER_EVENTS as usize - 1
+    );
+    assert_eq!(receiver.held_count(), 0);
+}
+
+#[test]
+fn test_seqs_at_u64_max_do_not_wrap() {
+    let mut receiver =
+        InputReceiver::new_from_seq(u64::MAX - 3).with_reorder_window(ReorderWindow {
+            max_events: 4,
+            hold_ms: 250,
+        });
+
+    // The last seqs are held and released like any others
+    assert_eq!(
+        receiver.process_input(&make_input(u64::MAX, 0)),
+        InputProcessResult::Held
+    );
+    let events = receiver
+        .process_batch(&make_batch(u64::MAX - 2..=u64::MAX - 1, 0))
+        .unwrap();
+    assert_eq!(seqs(&events), vec![u64::MAX - 2, u64::MAX - 1, u64::MAX]);
+    assert_eq!(receiver.last_acked_seq(), u64::MAX);
+    assert_eq!(receiver.held_count(), 0);
+
+    // A client whose counter wrapped replays nothing
+    assert_eq!(
+        receiver.process_input(&make_input(1, 0)),
+        InputProcessResult::Duplicate
+    );
+    assert_eq!(
+        receiver.process_input(&make_input(0, 0)),
+        InputProcessResult::OutOfOrder {
+            expected: u64::MAX,
+            received: 0
+        }
+    );
+    assert_eq!(
+        receiver.process_batch(&make_batch(1..=2, 0)),
+        Err(InputProcessResult::Duplicate)
+    );
+    assert_eq!(receiver.generate_ack().acked_seq, u64::MAX);
+}
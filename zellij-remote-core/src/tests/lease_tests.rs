@@ -1,14 +1,17 @@
-use crate::lease::{Duration, LeaseEvent, LeaseManager, LeaseResult, TestClock};
-use zellij_remote_protocol::{ControllerPolicy, DisplaySize};
+use std::time::Duration;
 
-fn setup() {
-    TestClock::reset();
-}
+use crate::clock::ManualClock;
+use crate::lease::{
+    HandoffOutcome, HandoffRequestResult, LeaseEvent, LeaseManager, LeaseResult, LeaseReturn,
+    DEFAULT_HANDOFF_TIMEOUT_MS, DEFAULT_TAKEOVER_GRACE_MS,
+};
+use zellij_remote_protocol::{ControllerPolicy, DisplaySize};
 
 #[test]
 fn test_initial_request_granted() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let result = mgr.request_control(
         1,
@@ -35,8 +38,9 @@ fn test_initial_request_granted() {
 
 #[test]
 fn test_second_client_denied() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let _ = mgr.request_control(1, Some(DisplaySize { cols: 80, rows: 24 }), false);
 
@@ -66,10 +70,47 @@ fn test_second_client_denied() {
     assert!(!mgr.is_controller(2));
 }
 
+#[test]
+fn test_denial_and_lease_name_the_owner() {
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
+    mgr.set_client_name(1, "Alice's iPad");
+    let _ = mgr.request_control(1, Some(DisplaySize { cols: 80, rows: 24 }), false);
+
+    match mgr.request_control(2, None, false) {
+        LeaseResult::Denied {
+            reason,
+            current_lease,
+        } => {
+            assert!(reason.contains("Alice's iPad (client 1)"), "{}", reason);
+            assert_eq!(current_lease.unwrap().owner_name, "Alice's iPad");
+        },
+        other => panic!("Expected Denied, got {:?}", other),
+    }
+    assert_eq!(mgr.get_current_lease().unwrap().owner_name, "Alice's iPad");
+
+    // Forgotten with the client; an unnamed owner is just its id
+    mgr.remove_client(1);
+    assert_eq!(mgr.client_name(1), None);
+    let _ = mgr.request_control(3, None, false);
+    match mgr.request_control(2, None, false) {
+        LeaseResult::Denied {
+            reason,
+            current_lease,
+        } => {
+            assert!(reason.contains("held by client 3"), "{}", reason);
+            assert_eq!(current_lease.unwrap().owner_name, "");
+        },
+        other => panic!("Expected Denied, got {:?}", other),
+    }
+}
+
 #[test]
 fn test_last_writer_wins_takeover() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::LastWriterWins, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::LastWriterWins, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let result1 = mgr.request_control(1, Some(DisplaySize { cols: 80, rows: 24 }), false);
     assert!(matches!(result1, LeaseResult::Granted(_)));
@@ -99,8 +140,9 @@ fn test_last_writer_wins_takeover() {
 
 #[test]
 fn test_keepalive_extends_lease() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let result = mgr.request_control(1, None, false);
     let lease_id = match result {
@@ -108,11 +150,11 @@ fn test_keepalive_extends_lease() {
         _ => panic!("Expected Granted"),
     };
 
-    TestClock::advance(Duration::from_secs(30));
+    clock.advance(Duration::from_secs(30));
 
     assert!(mgr.keepalive(1, lease_id));
 
-    TestClock::advance(Duration::from_secs(40));
+    clock.advance(Duration::from_secs(40));
 
     let event = mgr.tick();
     assert!(event.is_none(), "Lease should not expire after keepalive");
@@ -122,8 +164,9 @@ fn test_keepalive_extends_lease() {
 
 #[test]
 fn test_lease_expires_without_keepalive() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let result = mgr.request_control(1, None, false);
     let lease_id = match result {
@@ -131,7 +174,7 @@ fn test_lease_expires_without_keepalive() {
         _ => panic!("Expected Granted"),
     };
 
-    TestClock::advance(Duration::from_secs(61));
+    clock.advance(Duration::from_secs(61));
 
     let event = mgr.tick();
     match event {
@@ -150,8 +193,9 @@ fn test_lease_expires_without_keepalive() {
 
 #[test]
 fn test_release_frees_lease() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let result = mgr.request_control(1, None, false);
     let lease_id = match result {
@@ -173,8 +217,9 @@ fn test_release_frees_lease() {
 
 #[test]
 fn test_size_change_by_controller() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let result = mgr.request_control(1, Some(DisplaySize { cols: 80, rows: 24 }), false);
     let lease_id = match result {
@@ -198,8 +243,9 @@ fn test_size_change_by_controller() {
 
 #[test]
 fn test_size_change_by_non_controller_rejected() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let result = mgr.request_control(1, Some(DisplaySize { cols: 80, rows: 24 }), false);
     let lease_id = match result {
@@ -231,8 +277,9 @@ fn test_size_change_by_non_controller_rejected() {
 
 #[test]
 fn test_viewer_mode_receives_updates() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let _ = mgr.request_control(1, Some(DisplaySize { cols: 80, rows: 24 }), false);
 
@@ -252,8 +299,9 @@ fn test_viewer_mode_receives_updates() {
 
 #[test]
 fn test_remove_controller_frees_lease() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let result = mgr.request_control(1, None, false);
     let lease_id = match result {
@@ -280,8 +328,9 @@ fn test_remove_controller_frees_lease() {
 
 #[test]
 fn test_remove_viewer_no_event() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let _ = mgr.request_control(1, None, false);
     mgr.add_viewer(2);
@@ -294,8 +343,9 @@ fn test_remove_viewer_no_event() {
 
 #[test]
 fn test_force_takeover_explicit_only() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let _ = mgr.request_control(1, None, false);
 
@@ -309,10 +359,67 @@ fn test_force_takeover_explicit_only() {
     }
 }
 
+#[test]
+fn test_force_takeover_keeps_displaced_input_for_grace() {
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
+    let _ = mgr.request_control(1, None, false);
+    assert_eq!(mgr.take_takeover(), None);
+
+    let _ = mgr.request_control(2, None, true);
+    let grace = Duration::from_millis(DEFAULT_TAKEOVER_GRACE_MS);
+    assert_eq!(
+        mgr.take_takeover(),
+        Some(LeaseEvent::TakenOver {
+            lease_id: 1,
+            owner: 1,
+            new_owner: 2,
+            grace: Some(grace),
+        })
+    );
+    assert_eq!(mgr.take_takeover(), None);
+    assert!(!mgr.is_controller(1));
+    assert!(mgr.accepts_input(1));
+    assert!(mgr.accepts_input(2));
+
+    clock.advance(grace);
+    assert!(!mgr.accepts_input(1));
+    assert!(mgr.accepts_input(2));
+}
+
+#[test]
+fn test_last_writer_wins_takeover_has_no_grace() {
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::LastWriterWins, Duration::from_secs(60))
+        .with_clock(clock.shared());
+    let _ = mgr.request_control(1, None, false);
+    let _ = mgr.request_control(2, None, false);
+    assert_eq!(
+        mgr.take_takeover(),
+        Some(LeaseEvent::TakenOver {
+            lease_id: 1,
+            owner: 1,
+            new_owner: 2,
+            grace: None,
+        })
+    );
+    assert!(!mgr.accepts_input(1));
+
+    // Nor does a disconnected controller's grace outlive it
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
+    let _ = mgr.request_control(1, None, false);
+    let _ = mgr.request_control(2, None, true);
+    mgr.remove_client(1);
+    assert!(!mgr.accepts_input(1));
+}
+
 #[test]
 fn test_keepalive_wrong_lease_id_fails() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let _ = mgr.request_control(1, None, false);
 
@@ -322,8 +429,9 @@ fn test_keepalive_wrong_lease_id_fails() {
 
 #[test]
 fn test_release_wrong_credentials_fails() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let result = mgr.request_control(1, None, false);
     let lease_id = match result {
@@ -338,8 +446,9 @@ fn test_release_wrong_credentials_fails() {
 
 #[test]
 fn test_get_current_lease() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     assert!(mgr.get_current_lease().is_none());
 
@@ -353,8 +462,9 @@ fn test_get_current_lease() {
 
 #[test]
 fn test_same_client_re_request_returns_existing() {
-    setup();
-    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60));
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
 
     let result1 = mgr.request_control(1, None, false);
     let lease_id = match result1 {
@@ -371,3 +481,455 @@ fn test_same_client_re_request_returns_existing() {
         _ => panic!("Expected same lease returned"),
     }
 }
+
+#[test]
+fn test_idle_controller_demoted_to_viewer() {
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::LastWriterWins, Duration::from_secs(60))
+        .with_clock(clock.shared());
+    mgr.set_idle_timeout(Some(Duration::from_secs(10)));
+
+    let _ = mgr.request_control(1, None, false);
+    clock.advance(Duration::from_secs(6));
+    mgr.record_input(1);
+    clock.advance(Duration::from_secs(6));
+    assert!(mgr.tick().is_none());
+    assert!(mgr.is_controller(1));
+
+    clock.advance(Duration::from_secs(5));
+    match mgr.tick() {
+        Some(LeaseEvent::Revoked { owner, reason, .. }) => {
+            assert_eq!(owner, 1);
+            assert_eq!(reason, "idle");
+        },
+        other => panic!("Expected idle revocation, got {:?}", other),
+    }
+    assert!(!mgr.is_controller(1));
+    assert!(mgr.is_viewer(1));
+    assert!(mgr.tick().is_none());
+}
+
+#[test]
+fn test_input_renews_lease_past_its_duration() {
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::LastWriterWins, Duration::from_secs(30))
+        .with_clock(clock.shared());
+    mgr.set_idle_timeout(Some(Duration::from_secs(10)));
+
+    let _ = mgr.request_control(1, None, false);
+    for _ in 0..12 {
+        clock.advance(Duration::from_secs(5));
+        mgr.record_input(1);
+        assert!(mgr.tick().is_none());
+    }
+    assert!(mgr.is_controller(1));
+
+    // Once the typing stops the idle timeout still applies
+    clock.advance(Duration::from_secs(10));
+    assert!(matches!(
+        mgr.tick(),
+        Some(LeaseEvent::Revoked { owner: 1, .. })
+    ));
+}
+
+#[test]
+fn test_idle_timeout_disabled_by_default() {
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::LastWriterWins, Duration::from_secs(600))
+        .with_clock(clock.shared());
+    assert!(mgr.idle_timeout().is_none());
+
+    let _ = mgr.request_control(1, None, false);
+    clock.advance(Duration::from_secs(300));
+    assert!(mgr.tick().is_none());
+    assert!(mgr.is_controller(1));
+}
+
+#[test]
+fn test_record_input_ignored_for_non_controller() {
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::LastWriterWins, Duration::from_secs(60))
+        .with_clock(clock.shared());
+    mgr.set_idle_timeout(Some(Duration::from_secs(10)));
+
+    let _ = mgr.request_control(1, None, false);
+    clock.advance(Duration::from_secs(8));
+    mgr.record_input(2);
+    clock.advance(Duration::from_secs(3));
+
+    assert!(matches!(
+        mgr.tick(),
+        Some(LeaseEvent::Revoked { owner: 1, .. })
+    ));
+}
+
+#[test]
+fn test_revoke_demotes_controller_to_viewer() {
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
+    assert!(mgr.revoke("local").is_none());
+
+    let _ = mgr.request_control(1, None, false);
+    match mgr.revoke("local") {
+        Some(LeaseEvent::Revoked { owner, reason, .. }) => {
+            assert_eq!(owner, 1);
+            assert_eq!(reason, "local");
+        },
+        other => panic!("Expected revocation, got {:?}", other),
+    }
+    assert!(!mgr.is_controller(1));
+    assert!(mgr.is_viewer(1));
+    assert!(mgr.get_current_lease().is_none());
+
+    // Anyone may take the free lease afterwards
+    assert!(matches!(
+        mgr.request_control(2, None, false),
+        LeaseResult::Granted(_)
+    ));
+}
+
+fn manager_with_controller(clock: &ManualClock, controller: u64) -> LeaseManager {
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
+    mgr.request_control(controller, None, false);
+    mgr
+}
+
+fn pending_handoff_id(result: HandoffRequestResult) -> u64 {
+    match result {
+        HandoffRequestResult::Pending { handoff_id, .. } => handoff_id,
+        other => panic!("Expected Pending, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_handoff_granted_without_controller() {
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::ExplicitOnly, Duration::from_secs(60))
+        .with_clock(clock.shared());
+
+    match mgr.request_handoff(2, None) {
+        HandoffRequestResult::Granted(lease) => assert_eq!(lease.owner_client_id, 2),
+        other => panic!("Expected Granted, got {:?}", other),
+    }
+    assert!(!mgr.has_pending_handoff());
+}
+
+#[test]
+fn test_handoff_asks_current_controller() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+
+    match mgr.request_handoff(2, None) {
+        HandoffRequestResult::Pending {
+            controller,
+            timeout_ms,
+            ..
+        } => {
+            assert_eq!(controller, 1);
+            assert_eq!(timeout_ms as u64, DEFAULT_HANDOFF_TIMEOUT_MS);
+        },
+        other => panic!("Expected Pending, got {:?}", other),
+    }
+    // Asking doesn't move the lease
+    assert!(mgr.is_controller(1));
+}
+
+#[test]
+fn test_approved_handoff_transfers_lease() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    let handoff_id = pending_handoff_id(mgr.request_handoff(
+        2,
+        Some(DisplaySize {
+            cols: 100,
+            rows: 30,
+        }),
+    ));
+
+    match mgr.respond_to_handoff(1, handoff_id, true) {
+        Some(HandoffOutcome::Approved {
+            requester,
+            previous_owner,
+            previous_lease_id,
+            lease,
+            ..
+        }) => {
+            assert_eq!(requester, 2);
+            assert_eq!(previous_owner, 1);
+            assert_eq!(previous_lease_id, 1);
+            assert_eq!(lease.owner_client_id, 2);
+            assert_eq!(lease.lease_id, 2);
+            assert_eq!(lease.current_size.unwrap().cols, 100);
+        },
+        other => panic!("Expected Approved, got {:?}", other),
+    }
+    assert!(mgr.is_controller(2));
+    assert!(mgr.is_viewer(1));
+    assert!(!mgr.has_pending_handoff());
+}
+
+#[test]
+fn test_declined_handoff_keeps_lease() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    let handoff_id = pending_handoff_id(mgr.request_handoff(2, None));
+
+    match mgr.respond_to_handoff(1, handoff_id, false) {
+        Some(HandoffOutcome::Denied {
+            requester,
+            current_lease,
+            ..
+        }) => {
+            assert_eq!(requester, 2);
+            assert_eq!(current_lease.unwrap().owner_client_id, 1);
+        },
+        other => panic!("Expected Denied, got {:?}", other),
+    }
+    assert!(mgr.is_controller(1));
+}
+
+#[test]
+fn test_handoff_response_only_from_asked_controller() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    let handoff_id = pending_handoff_id(mgr.request_handoff(2, None));
+
+    assert_eq!(mgr.respond_to_handoff(3, handoff_id, true), None);
+    assert_eq!(mgr.respond_to_handoff(1, handoff_id + 1, true), None);
+    assert!(mgr.has_pending_handoff());
+    assert!(mgr.is_controller(1));
+}
+
+#[test]
+fn test_second_handoff_denied_while_pending() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    pending_handoff_id(mgr.request_handoff(2, None));
+
+    assert!(matches!(
+        mgr.request_handoff(3, None),
+        HandoffRequestResult::Denied { .. }
+    ));
+}
+
+#[test]
+fn test_unanswered_handoff_times_out() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    let handoff_id = pending_handoff_id(mgr.request_handoff(2, None));
+
+    clock.advance(Duration::from_millis(DEFAULT_HANDOFF_TIMEOUT_MS - 1));
+    assert_eq!(mgr.poll_handoff(), None);
+    clock.advance(Duration::from_millis(1));
+    match mgr.poll_handoff() {
+        Some(HandoffOutcome::Denied {
+            handoff_id: id,
+            requester,
+            ..
+        }) => {
+            assert_eq!(id, handoff_id);
+            assert_eq!(requester, 2);
+        },
+        other => panic!("Expected Denied, got {:?}", other),
+    }
+    // A late answer is ignored
+    assert_eq!(mgr.respond_to_handoff(1, handoff_id, true), None);
+    assert!(mgr.is_controller(1));
+}
+
+#[test]
+fn test_handoff_dropped_when_lease_changes_hands() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    let handoff_id = pending_handoff_id(mgr.request_handoff(2, None));
+
+    mgr.release_control(1, 1);
+    mgr.request_control(3, None, false);
+    assert!(matches!(
+        mgr.poll_handoff(),
+        Some(HandoffOutcome::Denied { handoff_id: id, .. }) if id == handoff_id
+    ));
+    assert_eq!(mgr.respond_to_handoff(3, handoff_id, true), None);
+}
+
+#[test]
+fn test_handoff_cleared_when_requester_leaves() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    let handoff_id = pending_handoff_id(mgr.request_handoff(2, None));
+
+    mgr.remove_client(2);
+    assert!(!mgr.has_pending_handoff());
+    assert_eq!(mgr.respond_to_handoff(1, handoff_id, true), None);
+}
+
+fn borrow_control(mgr: &mut LeaseManager, client_id: u64, secs: u64) -> u64 {
+    match mgr.request_time_boxed_control(client_id, None, true, Some(Duration::from_secs(secs))) {
+        LeaseResult::Granted(lease) => lease.lease_id,
+        other => panic!("Expected Granted, got {:?}", other),
+    }
+}
+
+fn returned_to(result: Option<LeaseReturn>, owner: u64) -> LeaseReturn {
+    match result {
+        Some(returned) if returned.lease.owner_client_id == owner => returned,
+        other => panic!("Expected control back with {}, got {:?}", owner, other),
+    }
+}
+
+#[test]
+fn test_time_boxed_lease_returns_to_previous_owner() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    let borrowed = borrow_control(&mut mgr, 2, 30);
+    assert!(mgr.is_controller(2));
+    assert!(mgr.is_viewer(1));
+
+    clock.advance(Duration::from_millis(29_999));
+    assert_eq!(mgr.poll_time_box(), None);
+    clock.advance(Duration::from_millis(1));
+    let returned = returned_to(mgr.poll_time_box(), 1);
+    assert_eq!(returned.ended_lease_id, Some(borrowed));
+    assert!(mgr.is_controller(1));
+    assert!(mgr.is_viewer(2));
+    assert_eq!(mgr.time_box_remaining(), None);
+}
+
+#[test]
+fn test_nested_time_boxed_leases_unwind_in_order() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    borrow_control(&mut mgr, 2, 60);
+    clock.advance(Duration::from_secs(10));
+    let inner = borrow_control(&mut mgr, 3, 20);
+
+    clock.advance(Duration::from_secs(20));
+    let returned = returned_to(mgr.poll_time_box(), 2);
+    assert_eq!(returned.ended_lease_id, Some(inner));
+    // Client 2 only gets the rest of its own time box back
+    assert_eq!(mgr.time_box_remaining(), Some(Duration::from_secs(30)));
+
+    clock.advance(Duration::from_secs(30));
+    returned_to(mgr.poll_time_box(), 1);
+    assert_eq!(mgr.poll_time_box(), None);
+    assert!(mgr.is_controller(1));
+}
+
+#[test]
+fn test_nested_lease_outlasting_outer_returns_to_original_owner() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    borrow_control(&mut mgr, 2, 20);
+    clock.advance(Duration::from_secs(10));
+    borrow_control(&mut mgr, 3, 60);
+
+    clock.advance(Duration::from_secs(60));
+    returned_to(mgr.poll_time_box(), 1);
+    assert!(!mgr.is_controller(2));
+}
+
+#[test]
+fn test_time_boxed_lease_kept_when_original_owner_disconnects() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    borrow_control(&mut mgr, 2, 30);
+    assert_eq!(mgr.remove_client(1), None);
+
+    clock.advance(Duration::from_secs(30));
+    assert_eq!(mgr.poll_time_box(), None);
+    assert!(mgr.is_controller(2));
+    assert_eq!(mgr.time_box_remaining(), None);
+}
+
+#[test]
+fn test_nested_handoff_skips_disconnected_original_owner() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    borrow_control(&mut mgr, 2, 60);
+    borrow_control(&mut mgr, 3, 20);
+    mgr.remove_client(1);
+
+    clock.advance(Duration::from_secs(20));
+    returned_to(mgr.poll_time_box(), 2);
+    clock.advance(Duration::from_secs(40));
+    assert_eq!(mgr.poll_time_box(), None);
+    assert!(mgr.is_controller(2));
+}
+
+#[test]
+fn test_borrower_disconnect_returns_control_early() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    let borrowed = borrow_control(&mut mgr, 2, 30);
+
+    assert!(matches!(
+        mgr.remove_client(2),
+        Some(LeaseEvent::Revoked { lease_id, .. }) if lease_id == borrowed
+    ));
+    let returned = returned_to(mgr.poll_time_box(), 1);
+    // The borrowed lease was already revoked on disconnect
+    assert_eq!(returned.ended_lease_id, None);
+    assert!(!mgr.is_viewer(2));
+}
+
+#[test]
+fn test_open_ended_takeover_drops_lease_stack() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    borrow_control(&mut mgr, 2, 30);
+    mgr.request_control(3, None, true);
+
+    clock.advance(Duration::from_secs(30));
+    assert_eq!(mgr.poll_time_box(), None);
+    assert!(mgr.is_controller(3));
+}
+
+#[test]
+fn test_lease_status_counts_down() {
+    let clock = ManualClock::new();
+    let mut mgr = LeaseManager::new(ControllerPolicy::LastWriterWins, Duration::from_secs(30))
+        .with_clock(clock.shared());
+    mgr.set_idle_timeout(Some(Duration::from_secs(10)));
+    assert_eq!(mgr.lease_status(true), None);
+
+    let lease_id = match mgr.request_control(1, None, false) {
+        LeaseResult::Granted(lease) => lease.lease_id,
+        other => panic!("Expected Granted, got {:?}", other),
+    };
+    clock.advance(Duration::from_secs(4));
+    let status = mgr.lease_status(true).unwrap();
+    assert_eq!(status.lease.as_ref().unwrap().remaining_ms, 26_000);
+    assert_eq!(status.idle_remaining_ms, 6_000);
+    assert_eq!(status.time_box_remaining_ms, 0);
+    assert!(status.expires);
+
+    // A keepalive refills the lease; input refills both it and the idle budget
+    assert!(mgr.keepalive(1, lease_id));
+    clock.advance(Duration::from_secs(1));
+    let status = mgr.lease_status(true).unwrap();
+    assert_eq!(status.lease.unwrap().remaining_ms, 29_000);
+    assert_eq!(status.idle_remaining_ms, 5_000);
+    mgr.record_input(1);
+    let status = mgr.lease_status(true).unwrap();
+    assert_eq!(status.lease.unwrap().remaining_ms, 30_000);
+    assert_eq!(status.idle_remaining_ms, 10_000);
+
+    // Without tick nothing lapses, so there is no idle countdown to show
+    let status = mgr.lease_status(false).unwrap();
+    assert_eq!(status.idle_remaining_ms, 0);
+    assert!(!status.expires);
+}
+
+#[test]
+fn test_lease_status_reports_time_box() {
+    let clock = ManualClock::new();
+    let mut mgr = manager_with_controller(&clock, 1);
+    borrow_control(&mut mgr, 2, 30);
+    clock.advance(Duration::from_secs(12));
+
+    let status = mgr.lease_status(false).unwrap();
+    assert_eq!(status.lease.unwrap().owner_client_id, 2);
+    assert_eq!(status.time_box_remaining_ms, 18_000);
+}
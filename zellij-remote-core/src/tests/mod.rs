@@ -1,12 +1,103 @@
+mod attach_tests;
+mod auth_tests;
 mod backpressure_tests;
+mod bell_tests;
+mod chunking_tests;
+mod color_transform_tests;
+mod conformance_tests;
+mod debug_info_tests;
 mod delta_tests;
+mod echo_tests;
+mod failover_tests;
+mod flash_guard_tests;
+mod frame_hash_tests;
+mod frame_stats_tests;
 mod frame_tests;
 mod input_tests;
 mod lease_tests;
+mod persist_tests;
 mod proptest_tests;
+mod render_job_tests;
 mod render_seq_tests;
 mod resume_token_tests;
+mod row_checksum_tests;
 mod rtt_tests;
+mod session_handle_tests;
 mod session_tests;
+mod simulation_tests;
+mod size_limits_tests;
 mod state_history_tests;
+mod style_convert_tests;
+mod style_retention_tests;
 mod style_table_tests;
+mod styled_underline_tests;
+mod svg_export_tests;
+mod text_mode_tests;
+mod token_keys_tests;
+
+use crate::frame::{Cell, FrameStore};
+use crate::session::{RemoteSession, RenderUpdate};
+use zellij_remote_protocol::{ScreenDelta, ScreenSnapshot, StateAck, Style};
+
+/// Write one cell of `store`, for building frames cell by cell.
+fn put(store: &mut FrameStore, row: usize, col: usize, c: char, width: u8, style_id: u32) {
+    store.update_row(row, |r| {
+        r.set_cell(
+            col,
+            Cell {
+                codepoint: c as u32,
+                width,
+                style_id,
+            },
+        )
+    });
+}
+
+/// Write `text` into `row` of the session's screen from `col` on and advance the
+/// state, so it goes out to clients as one update.
+fn put_text(session: &mut RemoteSession, row: usize, col: usize, text: &str, style_id: u32) {
+    session.frame_store.update_row(row, |r| {
+        for (offset, c) in text.chars().enumerate() {
+            r.set_cell(
+                col + offset,
+                Cell {
+                    codepoint: c as u32,
+                    width: 1,
+                    style_id,
+                },
+            );
+        }
+    });
+    session.frame_store.advance_state();
+}
+
+/// [`put_text`] in `style`, interning it first.
+fn put_styled(session: &mut RemoteSession, row: usize, col: usize, text: &str, style: &Style) {
+    let style_id = session.style_table.get_or_insert(style);
+    put_text(session, row, col, text, style_id);
+}
+
+/// Ack `state_id` for the client, as if it applied that update.
+fn ack(session: &mut RemoteSession, client_id: u64, state_id: u64) {
+    session.process_state_ack(
+        client_id,
+        &StateAck {
+            last_applied_state_id: state_id,
+            ..Default::default()
+        },
+    );
+}
+
+fn snapshot(session: &mut RemoteSession, client_id: u64) -> ScreenSnapshot {
+    match session.get_render_update(client_id) {
+        Some(RenderUpdate::Snapshot(snapshot)) => snapshot,
+        other => panic!("Expected a snapshot, got {:?}", other),
+    }
+}
+
+fn delta(session: &mut RemoteSession, client_id: u64) -> ScreenDelta {
+    match session.get_render_update(client_id) {
+        Some(RenderUpdate::Delta(delta)) => delta,
+        other => panic!("Expected a delta, got {:?}", other),
+    }
+}
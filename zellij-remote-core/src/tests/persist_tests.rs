@@ -0,0 +1,228 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use prost::Message;
+
+use crate::frame::{Cell, Cursor, CursorShape, FrameStore};
+use crate::persist::{persist_session, restore_session, RestoreError, PERSISTED_SESSION_VERSION};
+use crate::session::RemoteSession;
+use crate::style_convert::rgb_color;
+use crate::style_table::StyleTable;
+use zellij_remote_protocol::{AttachMode, AttachRequest, PersistedSession, Style};
+
+const MAX_AGE_MS: u64 = 300_000;
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn roundtrip(persisted: &PersistedSession) -> PersistedSession {
+    PersistedSession::decode(persisted.encode_to_vec().as_slice()).unwrap()
+}
+
+fn session_with_token() -> (RemoteSession, Vec<u8>) {
+    let mut session = RemoteSession::with_session_id(80, 24, 42);
+    session.add_client(1, 4);
+    session.frame_store.advance_state();
+    session.record_state_snapshot();
+    let _ = session.get_render_update(1);
+    let token = session.generate_resume_token(1);
+    (session, token)
+}
+
+#[test]
+fn test_restored_session_opens_old_tokens() {
+    let (session, token) = session_with_token();
+    let saved_at = now_ms();
+    let persisted = roundtrip(&persist_session(&session, None, 2, saved_at));
+
+    let restored = restore_session(&persisted, 80, 24, MAX_AGE_MS, saved_at + 1).unwrap();
+    assert_eq!(restored.session.session_id, 42);
+    assert_eq!(restored.next_client_id, 2);
+    assert!(restored.last_frame.is_none());
+    assert!(restored.session.predates_restore(&token));
+
+    // Another session's keys don't open it
+    assert!(!RemoteSession::with_session_id(80, 24, 42).predates_restore(&token));
+}
+
+#[test]
+fn test_tokens_issued_after_restore_are_not_resurrected() {
+    let (session, _) = session_with_token();
+    let persisted = persist_session(&session, None, 2, now_ms());
+    let mut restored = restore_session(&persisted, 80, 24, MAX_AGE_MS, 0)
+        .unwrap()
+        .session;
+
+    // Restored "at" the epoch, so every token issued now comes after it
+    restored.add_client(2, 4);
+    let token = restored.generate_resume_token(2);
+    assert!(restored.token_keys().open(&token).is_some());
+    assert!(!restored.predates_restore(&token));
+}
+
+#[test]
+fn test_revoked_tokens_stay_revoked() {
+    let (mut session, token) = session_with_token();
+    session.revoke_client_resume_tokens(1);
+    let saved_at = now_ms();
+    let persisted = roundtrip(&persist_session(&session, None, 2, saved_at));
+
+    let restored = restore_session(&persisted, 80, 24, MAX_AGE_MS, saved_at + 1).unwrap();
+    assert!(!restored.session.predates_restore(&token));
+}
+
+#[test]
+fn test_returning_client_gets_snapshot() {
+    let (session, _) = session_with_token();
+    let last_state_id = session.frame_store.current_state_id();
+    let persisted = persist_session(
+        &session,
+        Some((&FrameStore::new(80, 24), &StyleTable::new())),
+        2,
+        now_ms(),
+    );
+
+    let mut restored = restore_session(&persisted, 80, 24, MAX_AGE_MS, now_ms())
+        .unwrap()
+        .session;
+    assert!(restored.frame_store.current_state_id() > last_state_id);
+
+    restored.add_client(1, 4);
+    let response = restored.apply_attach_request(
+        1,
+        &AttachRequest {
+            mode: AttachMode::Resume as i32,
+            last_applied_state_id: last_state_id,
+            ..Default::default()
+        },
+    );
+    assert!(response.will_send_snapshot);
+}
+
+#[test]
+fn test_last_frame_restored_with_styles() {
+    let (session, _) = session_with_token();
+    let mut style_table = StyleTable::new();
+    let bold = Style {
+        bold: true,
+        ..Default::default()
+    };
+    let style_id = style_table.get_or_insert(&bold);
+    let mut frame_store = FrameStore::new(4, 2);
+    frame_store.update_row(1, |row| {
+        row.set_cell(
+            2,
+            Cell {
+                codepoint: 'x' as u32,
+                width: 1,
+                style_id,
+            },
+        )
+    });
+    let cursor = Cursor {
+        row: 1,
+        col: 3,
+        visible: true,
+        blink: false,
+        shape: CursorShape::Bar,
+        color: None,
+    };
+    frame_store.set_cursor(cursor);
+
+    let persisted = roundtrip(&persist_session(
+        &session,
+        Some((&frame_store, &style_table)),
+        2,
+        now_ms(),
+    ));
+    let (restored_frame, restored_styles) = restore_session(&persisted, 4, 2, MAX_AGE_MS, now_ms())
+        .unwrap()
+        .last_frame
+        .unwrap();
+
+    let frame = restored_frame.current_frame();
+    assert_eq!(frame.cols, 4);
+    assert_eq!(frame.rows.len(), 2);
+    assert_eq!(frame.cursor, cursor);
+    let cell = frame.rows[1].get_cell(2).unwrap();
+    assert_eq!(cell.codepoint, 'x' as u32);
+    assert_eq!(restored_styles.get(cell.style_id), Some(&bold));
+    assert_eq!(frame.rows[0].get_cell(0), Some(&Cell::default()));
+}
+
+#[test]
+fn test_restored_style_ids_past_u16_range() {
+    let (session, _) = session_with_token();
+    let mut style_table = StyleTable::new();
+    let mut style_id = 0;
+    for i in 0..70_000u32 {
+        let style = Style {
+            fg: Some(rgb_color((i >> 16) as u8, (i >> 8) as u8, i as u8)),
+            ..Default::default()
+        };
+        style_id = style_table.get_or_insert(&style);
+    }
+    assert!(style_id > u16::MAX as u32);
+    let mut frame_store = FrameStore::new(4, 2);
+    frame_store.update_row(0, |row| {
+        row.set_cell(
+            1,
+            Cell {
+                codepoint: 'y' as u32,
+                width: 1,
+                style_id,
+            },
+        )
+    });
+
+    let persisted = roundtrip(&persist_session(
+        &session,
+        Some((&frame_store, &style_table)),
+        2,
+        now_ms(),
+    ));
+    let (restored_frame, restored_styles) = restore_session(&persisted, 4, 2, MAX_AGE_MS, now_ms())
+        .unwrap()
+        .last_frame
+        .unwrap();
+
+    let cell = *restored_frame.current_frame().rows[0].get_cell(1).unwrap();
+    assert_eq!(
+        restored_styles.get(cell.style_id),
+        style_table.get(style_id)
+    );
+}
+
+#[test]
+fn test_stale_or_unknown_sessions_rejected() {
+    let (session, _) = session_with_token();
+    let persisted = persist_session(&session, None, 2, 1_000);
+
+    assert_eq!(
+        restore_session(&persisted, 80, 24, MAX_AGE_MS, 1_000 + MAX_AGE_MS + 1).err(),
+        Some(RestoreError::Stale {
+            age_ms: MAX_AGE_MS + 1
+        })
+    );
+
+    let future = PersistedSession {
+        version: PERSISTED_SESSION_VERSION + 1,
+        ..persisted.clone()
+    };
+    assert_eq!(
+        restore_session(&future, 80, 24, MAX_AGE_MS, 1_000).err(),
+        Some(RestoreError::UnknownVersion(PERSISTED_SESSION_VERSION + 1))
+    );
+
+    let truncated = PersistedSession {
+        token_secret: vec![0; 16],
+        ..persisted
+    };
+    assert_eq!(
+        restore_session(&truncated, 80, 24, MAX_AGE_MS, 1_000).err(),
+        Some(RestoreError::BadSecret)
+    );
+}
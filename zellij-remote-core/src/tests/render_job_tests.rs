@@ -0,0 +1,107 @@
+use crate::render_job::RenderJob;
+use crate::session::{RemoteSession, RenderUpdate};
+use crate::tests::{ack, put_text, snapshot};
+
+/// Sends each client its first snapshot and acks it
+fn attach(session: &mut RemoteSession, client_ids: &[u64]) {
+    for &client_id in client_ids {
+        session.add_client(client_id, 4);
+        let first = snapshot(session, client_id);
+        ack(session, client_id, first.state_id);
+    }
+}
+
+#[test]
+fn test_split_update_matches_get_render_update() {
+    let mut split = RemoteSession::new(10, 4);
+    let mut whole = RemoteSession::new(10, 4);
+    attach(&mut split, &[1]);
+    attach(&mut whole, &[1]);
+    put_text(&mut split, 2, 3, "x", 0);
+    put_text(&mut whole, 2, 3, "x", 0);
+
+    let job = split.begin_render_update(1).unwrap();
+    assert!(job.is_delta());
+    assert_eq!(job.rows_to_diff(), 1);
+    let from_split = split.finish_render_update(job.compute());
+    let from_whole = whole.get_render_update(1);
+    match (from_split, from_whole) {
+        (Some(RenderUpdate::Delta(a)), Some(RenderUpdate::Delta(b))) => {
+            assert_eq!(a.row_patches, b.row_patches);
+            assert_eq!(a.state_id, b.state_id);
+            assert_eq!(a.frame_sequence, b.frame_sequence);
+        },
+        other => panic!("Expected two deltas, got {:?}", other),
+    }
+    assert_eq!(
+        split.clients[&1].pending_state_id(),
+        split.frame_store.current_state_id()
+    );
+}
+
+#[test]
+fn test_snapshot_job_is_ready() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+
+    let job = session.begin_render_update(1).unwrap();
+    assert!(!job.is_delta());
+    assert_eq!(job.rows_to_diff(), 0);
+    assert!(matches!(
+        session.finish_render_update(job.compute()),
+        Some(RenderUpdate::Snapshot(_))
+    ));
+}
+
+#[test]
+fn test_delta_dropped_when_baseline_moves() {
+    let mut session = RemoteSession::new(10, 4);
+    attach(&mut session, &[1]);
+    put_text(&mut session, 0, 0, "a", 0);
+    let first = match session.get_render_update(1) {
+        Some(RenderUpdate::Delta(delta)) => delta,
+        other => panic!("Expected a delta, got {:?}", other),
+    };
+
+    put_text(&mut session, 1, 0, "b", 0);
+    let job = session.begin_render_update(1).unwrap();
+    // The ack lands while the job is being computed
+    ack(&mut session, 1, first.state_id);
+    assert!(session.finish_render_update(job.compute()).is_none());
+
+    assert!(matches!(
+        session.get_render_update(1),
+        Some(RenderUpdate::Delta(delta)) if delta.base_state_id == first.state_id
+    ));
+}
+
+#[test]
+fn test_jobs_computed_in_parallel() {
+    let mut session = RemoteSession::new(10, 4);
+    let client_ids = [1, 2, 3, 4];
+    attach(&mut session, &client_ids);
+    put_text(&mut session, 3, 9, "z", 0);
+
+    let jobs: Vec<RenderJob> = client_ids
+        .iter()
+        .filter_map(|&client_id| session.begin_render_update(client_id))
+        .collect();
+    let outputs: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = jobs
+            .into_iter()
+            .map(|job| scope.spawn(move || job.compute()))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    for output in outputs {
+        let client_id = output.client_id();
+        match session.finish_render_update(output) {
+            Some(RenderUpdate::Delta(delta)) => {
+                assert_eq!(delta.row_patches.len(), 1, "client {}", client_id);
+                assert_eq!(delta.row_patches[0].row, 3);
+            },
+            other => panic!("Expected a delta for {}, got {:?}", client_id, other),
+        }
+    }
+}
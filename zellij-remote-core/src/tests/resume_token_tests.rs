@@ -1,83 +1,144 @@
-use crate::resume_token::ResumeToken;
+use crate::resume_token::{xchacha20_poly1305_open, xchacha20_poly1305_seal, ResumeToken};
 
 const TEST_SECRET: &[u8] = b"test_secret_key_12345678901234567890";
 
-#[test]
-fn test_encode_decode_signed_roundtrip() {
-    let token = ResumeToken {
+fn test_token() -> ResumeToken {
+    ResumeToken {
         session_id: 123456789,
         client_id: 42,
         last_applied_state_id: 100,
         last_acked_input_seq: 50,
         issued_at_ms: 1704067200000, // 2024-01-01 00:00:00 UTC
-    };
+    }
+}
 
-    let encoded = token.encode_signed(TEST_SECRET);
-    assert_eq!(encoded.len(), 72); // 40 byte payload + 32 byte signature
+fn decode_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
 
-    let decoded = ResumeToken::decode_signed(&encoded, TEST_SECRET).expect("decode should succeed");
+#[test]
+fn test_seal_open_roundtrip() {
+    let token = test_token();
 
-    assert_eq!(decoded.session_id, token.session_id);
-    assert_eq!(decoded.client_id, token.client_id);
-    assert_eq!(decoded.last_applied_state_id, token.last_applied_state_id);
-    assert_eq!(decoded.last_acked_input_seq, token.last_acked_input_seq);
-    assert_eq!(decoded.issued_at_ms, token.issued_at_ms);
+    let sealed = token.seal(TEST_SECRET);
+    assert_eq!(sealed.len(), 81); // version + 24 byte nonce + 40 byte payload + 16 byte tag
+    assert_eq!(sealed[0], 2);
+
+    let decoded = ResumeToken::open(&sealed, TEST_SECRET).expect("open should succeed");
+    assert_eq!(decoded, token);
+}
+
+#[test]
+fn test_sealed_token_hides_ids() {
+    let token = test_token();
+    let sealed = token.seal(TEST_SECRET);
+
+    for id in [
+        token.session_id,
+        token.client_id,
+        token.last_applied_state_id,
+        token.last_acked_input_seq,
+        token.issued_at_ms,
+    ] {
+        let needle = id.to_le_bytes();
+        assert!(!sealed.windows(8).any(|window| window == needle));
+    }
+}
+
+#[test]
+fn test_sealing_uses_fresh_nonce() {
+    let token = test_token();
+    assert_ne!(token.seal(TEST_SECRET), token.seal(TEST_SECRET));
 }
 
 #[test]
 fn test_decode_invalid_length() {
-    assert!(ResumeToken::decode_signed(&[], TEST_SECRET).is_none());
-    assert!(ResumeToken::decode_signed(&[0u8; 16], TEST_SECRET).is_none());
-    assert!(ResumeToken::decode_signed(&[0u8; 71], TEST_SECRET).is_none());
+    assert!(ResumeToken::open(&[], TEST_SECRET).is_none());
+    assert!(ResumeToken::open(&[0u8; 16], TEST_SECRET).is_none());
+    assert!(ResumeToken::open(&[0u8; 71], TEST_SECRET).is_none());
+    assert!(ResumeToken::open(&[2u8; 80], TEST_SECRET).is_none());
+
+    let mut sealed = test_token().seal(TEST_SECRET);
+    sealed.push(0);
+    assert!(ResumeToken::open(&sealed, TEST_SECRET).is_none());
 }
 
 #[test]
 fn test_decode_wrong_secret_fails() {
-    let token = ResumeToken {
-        session_id: 1,
-        client_id: 1,
-        last_applied_state_id: 1,
-        last_acked_input_seq: 0,
-        issued_at_ms: 1000,
-    };
-
-    let encoded = token.encode_signed(TEST_SECRET);
+    let sealed = test_token().seal(TEST_SECRET);
     let wrong_secret = b"wrong_secret_key_12345678901234567890";
 
-    assert!(ResumeToken::decode_signed(&encoded, wrong_secret).is_none());
+    assert!(ResumeToken::open(&sealed, wrong_secret).is_none());
 }
 
 #[test]
-fn test_tampered_payload_fails() {
-    let token = ResumeToken {
-        session_id: 1,
-        client_id: 1,
-        last_applied_state_id: 1,
-        last_acked_input_seq: 0,
-        issued_at_ms: 1000,
-    };
-
-    let mut encoded = token.encode_signed(TEST_SECRET);
-    encoded[0] ^= 0xff;
-
-    assert!(ResumeToken::decode_signed(&encoded, TEST_SECRET).is_none());
+fn test_any_tampered_byte_fails() {
+    let sealed = test_token().seal(TEST_SECRET);
+
+    // Covers the version, nonce, ciphertext and tag
+    for i in 0..sealed.len() {
+        let mut tampered = sealed.clone();
+        tampered[i] ^= 0x01;
+        assert!(
+            ResumeToken::open(&tampered, TEST_SECRET).is_none(),
+            "flipping byte {} should be rejected",
+            i
+        );
+    }
 }
 
 #[test]
-fn test_tampered_signature_fails() {
-    let token = ResumeToken {
-        session_id: 1,
-        client_id: 1,
-        last_applied_state_id: 1,
-        last_acked_input_seq: 0,
-        issued_at_ms: 1000,
-    };
+fn test_truncated_token_fails() {
+    let sealed = test_token().seal(TEST_SECRET);
+    for len in 0..sealed.len() {
+        assert!(ResumeToken::open(&sealed[..len], TEST_SECRET).is_none());
+    }
+}
 
-    let mut encoded = token.encode_signed(TEST_SECRET);
-    let last_idx = encoded.len() - 1;
-    encoded[last_idx] ^= 0xff;
+#[test]
+fn test_swapped_ciphertext_fails() {
+    let first = test_token().seal(TEST_SECRET);
+    let second = ResumeToken {
+        client_id: 7,
+        ..test_token()
+    }
+    .seal(TEST_SECRET);
+
+    // Nonce from one token with the ciphertext and tag of another
+    let mut spliced = first[..25].to_vec();
+    spliced.extend_from_slice(&second[25..]);
+    assert!(ResumeToken::open(&spliced, TEST_SECRET).is_none());
+}
 
-    assert!(ResumeToken::decode_signed(&encoded, TEST_SECRET).is_none());
+#[test]
+fn test_xchacha20_poly1305_vector() {
+    // draft-irtf-cfrg-xchacha-03, appendix A.3.1
+    let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+    let aad = decode_hex("50515253c0c1c2c3c4c5c6c7");
+    let key: [u8; 32] =
+        decode_hex("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f")
+            .try_into()
+            .unwrap();
+    let nonce: [u8; 24] = decode_hex("404142434445464748494a4b4c4d4e4f5051525354555657")
+        .try_into()
+        .unwrap();
+    let expected = decode_hex(concat!(
+        "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb",
+        "731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452",
+        "2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9",
+        "21f9664c97637da9768812f615c68b13b52e",
+        "c0875924c1c7987947deafd8780acf49",
+    ));
+
+    let mut in_out = plaintext.to_vec();
+    xchacha20_poly1305_seal(&key, &nonce, &aad, &mut in_out);
+    assert_eq!(in_out, expected);
+
+    xchacha20_poly1305_open(&key, &nonce, &aad, &mut in_out).unwrap();
+    assert_eq!(in_out, &plaintext[..]);
 }
 
 #[test]
@@ -0,0 +1,210 @@
+use crate::conformance::ClientScreen;
+use crate::delta::DeltaEngine;
+use crate::frame::{FrameStore, LineSize};
+use crate::frame_hash::{row_crc32, stamp_row_checksums, RowChecksum};
+use crate::session::{RemoteSession, RenderUpdate};
+use crate::style_table::StyleTable;
+use crate::tests::put;
+use zellij_remote_protocol::{ScreenDelta, StateAck, Style};
+
+#[test]
+fn test_client_screen_rows_match_server_checksums() {
+    let mut store = FrameStore::new(10, 4);
+    let mut style_table = StyleTable::new();
+    let bold = style_table.get_or_insert(&Style {
+        bold: true,
+        ..Default::default()
+    });
+    put(&mut store, 0, 0, 'h', 1, 0);
+    store.advance_state();
+    let baseline = store.snapshot();
+
+    let mut screen = ClientScreen::new();
+    screen
+        .apply_snapshot(&DeltaEngine::compute_snapshot(
+            &baseline.data,
+            &mut style_table,
+            baseline.state_id,
+        ))
+        .unwrap();
+
+    put(&mut store, 0, 1, 'i', 1, bold);
+    put(&mut store, 2, 3, '中', 2, bold);
+    put(&mut store, 2, 4, '\0', 0, bold);
+    store.update_row(3, |r| r.set_line_size(LineSize::DoubleWidth));
+    store.advance_state();
+    let current = store.snapshot();
+
+    let mut delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        &current.data,
+        &mut style_table,
+        baseline.state_id,
+        current.state_id,
+        None,
+    );
+    stamp_row_checksums(&mut delta, &current.data);
+    assert!(delta.row_patches.iter().all(|patch| patch.row_crc32 != 0));
+    screen.apply_delta(&delta).unwrap();
+
+    assert!(screen.mismatched_rows(&delta).is_empty());
+    for row in 0..4 {
+        assert_eq!(
+            screen.row_crc32(row),
+            Some(row_crc32(&current.data.rows[row]))
+        );
+    }
+}
+
+#[test]
+fn test_corrupted_row_is_reported() {
+    let mut store = FrameStore::new(6, 3);
+    let mut style_table = StyleTable::new();
+    store.advance_state();
+    let baseline = store.snapshot();
+
+    let mut screen = ClientScreen::new();
+    screen
+        .apply_snapshot(&DeltaEngine::compute_snapshot(
+            &baseline.data,
+            &mut style_table,
+            baseline.state_id,
+        ))
+        .unwrap();
+
+    put(&mut store, 1, 0, 'x', 1, 0);
+    put(&mut store, 2, 0, 'y', 1, 0);
+    store.advance_state();
+    let current = store.snapshot();
+    let mut delta = DeltaEngine::compute_delta(
+        &baseline.data,
+        &current.data,
+        &mut style_table,
+        baseline.state_id,
+        current.state_id,
+        None,
+    );
+    stamp_row_checksums(&mut delta, &current.data);
+
+    // A bit flipped in transit, past QUIC's own integrity checks
+    let mut corrupted = delta.clone();
+    let patch = corrupted
+        .row_patches
+        .iter_mut()
+        .find(|patch| patch.row == 2)
+        .unwrap();
+    patch.runs[0].codepoints[0] = 'z' as u32;
+    screen.apply_delta(&corrupted).unwrap();
+
+    assert_eq!(screen.mismatched_rows(&corrupted), vec![2]);
+}
+
+#[test]
+fn test_patches_without_checksum_are_not_checked() {
+    let screen = ClientScreen::new();
+    let delta = ScreenDelta {
+        row_patches: vec![zellij_remote_protocol::RowPatch {
+            row: 5,
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    assert!(screen.mismatched_rows(&delta).is_empty());
+}
+
+#[test]
+fn test_checksum_is_crc32() {
+    // CRC-32 (IEEE) of the little-endian words 1 (single line size) then 'a', 1, 0
+    let mut checksum = RowChecksum::new(zellij_remote_protocol::LineSize::Single as i32);
+    checksum.cell('a' as u32, 1, 0);
+    let mut bytes = Vec::new();
+    for word in [1u32, 'a' as u32, 1, 0] {
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+    assert_eq!(checksum.finish(), crc32_reference(&bytes));
+
+    // The standard check value
+    assert_eq!(crc32_reference(b"123456789"), 0xCBF4_3926);
+
+    let mut unspecified = RowChecksum::new(zellij_remote_protocol::LineSize::Unspecified as i32);
+    unspecified.cell('a' as u32, 1, 0);
+    assert_eq!(unspecified.finish(), crc32_reference(&bytes));
+}
+
+/// Bitwise CRC-32, to check the table-driven one against
+fn crc32_reference(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Send `client_id` its first snapshot and ack it, so the next render is a delta.
+fn sync(session: &mut RemoteSession, client_id: u64) {
+    let state_id = match session.get_render_update(client_id) {
+        Some(RenderUpdate::Snapshot(snapshot)) => snapshot.state_id,
+        _ => panic!("Expected a snapshot"),
+    };
+    session.process_state_ack(
+        client_id,
+        &StateAck {
+            last_applied_state_id: state_id,
+            ..Default::default()
+        },
+    );
+}
+
+fn delta_checksums(session: &mut RemoteSession, client_id: u64) -> Vec<u32> {
+    match session.get_render_update(client_id) {
+        Some(RenderUpdate::Delta(delta)) => delta
+            .row_patches
+            .iter()
+            .map(|patch| patch.row_crc32)
+            .collect(),
+        _ => panic!("Expected a delta"),
+    }
+}
+
+#[test]
+fn test_row_checksums_only_sent_when_enabled() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    assert!(session.set_row_checksums_enabled(1, true));
+    assert!(!session.set_row_checksums_enabled(3, true));
+    session.frame_store.advance_state();
+    sync(&mut session, 1);
+    sync(&mut session, 2);
+
+    put(&mut session.frame_store, 1, 0, 'a', 1, 0);
+    session.frame_store.advance_state();
+    let expected = row_crc32(&session.frame_store.current_frame().rows[1]);
+
+    assert_eq!(delta_checksums(&mut session, 1), vec![expected]);
+    assert_eq!(delta_checksums(&mut session, 2), vec![0]);
+}
+
+#[test]
+fn test_row_checksum_mismatches_counted_per_client() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    session.set_row_checksums_enabled(1, true);
+
+    assert_eq!(session.record_row_checksum_mismatch(1), Some(1));
+    assert_eq!(session.record_row_checksum_mismatch(1), Some(2));
+    // A client that doesn't check rows lost its baseline some other way
+    assert_eq!(session.record_row_checksum_mismatch(2), None);
+    assert_eq!(session.record_row_checksum_mismatch(3), None);
+
+    assert_eq!(session.row_checksum_mismatches(1), 2);
+    assert_eq!(session.row_checksum_mismatches(2), 0);
+}
@@ -1,4 +1,4 @@
-use crate::rtt::{LinkState, RttEstimator};
+use crate::rtt::{LatencyHistogram, LinkState, RttEstimator};
 
 #[test]
 fn test_initial_sample_sets_srtt() {
@@ -396,3 +396,62 @@ fn test_record_packet_api() {
     estimator.record_packet(None);
     assert!(estimator.loss_rate() > 0.0);
 }
+
+#[test]
+fn test_latency_percentiles_empty_until_first_sample() {
+    let mut estimator = RttEstimator::new();
+    assert_eq!(estimator.latency_percentiles(), None);
+
+    // Losses carry no latency
+    estimator.record_loss();
+    assert_eq!(estimator.latency_percentiles(), None);
+
+    estimator.record_sample(40);
+    let percentiles = estimator.latency_percentiles().unwrap();
+    assert_eq!(percentiles.p50_ms, 40);
+    assert_eq!(percentiles.p99_ms, 40);
+    assert_eq!(percentiles.samples, 1);
+}
+
+#[test]
+fn test_latency_percentiles_within_bucket_precision() {
+    let mut histogram = LatencyHistogram::new();
+    for latency_ms in 1..=1000 {
+        histogram.record(latency_ms);
+    }
+
+    for (percentile, expected) in [(50.0, 500.0), (95.0, 950.0), (99.0, 990.0)] {
+        let value = histogram.percentile(percentile).unwrap() as f64;
+        assert!(
+            (value - expected).abs() / expected < 0.04,
+            "p{} = {}, expected about {}",
+            percentile,
+            value,
+            expected
+        );
+    }
+    assert_eq!(histogram.count(), 1000);
+}
+
+#[test]
+fn test_latency_tail_shows_up_in_p99_not_p50() {
+    let mut estimator = RttEstimator::new();
+    for _ in 0..97 {
+        estimator.record_sample(20);
+    }
+    for _ in 0..3 {
+        estimator.record_sample(800);
+    }
+
+    let percentiles = estimator.latency_percentiles().unwrap();
+    assert_eq!(percentiles.p50_ms, 20);
+    assert_eq!(percentiles.p95_ms, 20);
+    assert!(percentiles.p99_ms >= 780, "p99 = {}", percentiles.p99_ms);
+}
+
+#[test]
+fn test_latency_histogram_clamps_huge_samples() {
+    let mut histogram = LatencyHistogram::new();
+    histogram.record(u32::MAX);
+    assert!(histogram.percentile(50.0).unwrap() > 60_000);
+}
@@ -0,0 +1,116 @@
+use crate::session::{InputError, RemoteSession, RenderUpdate};
+use crate::session_handle::{AttachRequest, RemoteSessionHandle, SessionHandleError};
+use zellij_remote_protocol::{InputEvent, StateAck};
+
+fn make_input(seq: u64) -> InputEvent {
+    InputEvent {
+        input_seq: seq,
+        client_time_ms: 0,
+        payload: None,
+    }
+}
+
+#[tokio::test]
+async fn test_attach_grants_control_to_first_client() {
+    let handle = RemoteSessionHandle::spawn(RemoteSession::new(80, 24));
+
+    let first = handle.attach(AttachRequest::default()).await.unwrap();
+    let second = handle.attach(AttachRequest::default()).await.unwrap();
+
+    assert_ne!(first.client_id, second.client_id);
+    assert!(!first.resumed);
+    let lease = second.lease.expect("Viewer should see the current lease");
+    assert_eq!(lease.owner_client_id, first.client_id);
+    assert!(!first.resume_token.is_empty());
+
+    assert!(handle.input(first.client_id, make_input(1)).await.is_ok());
+    assert_eq!(
+        handle.input(second.client_id, make_input(1)).await,
+        Err(SessionHandleError::Input(InputError::NotController))
+    );
+}
+
+#[tokio::test]
+async fn test_updates_follow_acks() {
+    let handle = RemoteSessionHandle::spawn(RemoteSession::new(10, 4));
+    let client_id = handle
+        .attach(AttachRequest::default())
+        .await
+        .unwrap()
+        .client_id;
+
+    let state_id = match handle.get_update(client_id).await.unwrap() {
+        Some(RenderUpdate::Snapshot(snapshot)) => snapshot.state_id,
+        other => panic!("Expected a snapshot, got {:?}", other),
+    };
+    handle
+        .ack(
+            client_id,
+            StateAck {
+                last_applied_state_id: state_id,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    handle
+        .with_session(|session| session.frame_store.advance_state())
+        .await
+        .unwrap();
+    assert!(matches!(
+        handle.get_update(client_id).await.unwrap(),
+        Some(RenderUpdate::Delta(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_reattach_with_resume_token() {
+    let handle = RemoteSessionHandle::spawn(RemoteSession::new(10, 4));
+    let first = handle.attach(AttachRequest::default()).await.unwrap();
+    let client_id = first.client_id;
+    let state_id = match handle.get_update(client_id).await.unwrap() {
+        Some(RenderUpdate::Snapshot(snapshot)) => snapshot.state_id,
+        other => panic!("Expected a snapshot, got {:?}", other),
+    };
+    handle
+        .ack(
+            client_id,
+            StateAck {
+                last_applied_state_id: state_id,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    // A token naming the acked state, as servers hand out while connected
+    let resume_token = handle
+        .with_session(move |session| session.generate_resume_token(client_id))
+        .await
+        .unwrap();
+    handle.detach(client_id).await.unwrap();
+
+    let again = handle
+        .attach(AttachRequest {
+            resume_token,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(again.resumed);
+    assert_eq!(again.client_id, first.client_id);
+
+    // New clients never reuse a resumed id
+    let other = handle.attach(AttachRequest::default()).await.unwrap();
+    assert_ne!(other.client_id, first.client_id);
+
+    let rejected = handle
+        .attach(AttachRequest {
+            resume_token: b"garbage".to_vec(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(!rejected.resumed);
+    assert!(rejected.resume_rejected.is_some());
+}
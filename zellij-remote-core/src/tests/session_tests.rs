@@ -1,7 +1,19 @@
-use crate::frame::FrameData;
+use std::time::Duration;
+
+use crate::backpressure::WindowBounds;
+use crate::clock::{Clock, ManualClock};
+use crate::frame::{Cell, FrameData, FrameFingerprint, LineSize};
+use crate::lease::LeaseEvent;
 use crate::resume_token::{ResumeResult, ResumeToken};
-use crate::session::{InputError, RemoteSession};
-use zellij_remote_protocol::{DisplaySize, InputEvent, StateAck};
+use crate::session::{
+    InputError, RemoteSession, RenderUpdate, SessionEvent, MAX_PENDING_EVENTS,
+    SNAPSHOT_PRELOAD_STATES,
+};
+use prost::Message;
+use zellij_remote_protocol::{
+    stream_envelope, Detach, DetachReason, DisplaySize, InputEvent, LineSize as ProtoLineSize,
+    LinkStats, ScreenSnapshot, StateAck, StreamEnvelope, Style,
+};
 
 fn make_input(seq: u64, client_time_ms: u32) -> InputEvent {
     InputEvent {
@@ -29,6 +41,61 @@ fn test_input_rejected_from_non_controller() {
     assert!(result.is_ok());
 }
 
+#[test]
+fn test_input_batch_requires_controller_and_acks_once() {
+    use zellij_remote_protocol::InputBatch;
+
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    session
+        .lease_manager
+        .request_control(1, Some(DisplaySize { cols: 80, rows: 24 }), false);
+
+    let batch = InputBatch {
+        client_time_ms: 300,
+        events: (1..=3).map(|seq| make_input(seq, 0)).collect(),
+    };
+    assert_eq!(
+        session.process_input_batch(2, &batch),
+        Err(InputError::NotController)
+    );
+
+    let (ack, events) = session.process_input_batch(1, &batch).unwrap();
+    assert_eq!(events.len(), 3);
+    assert_eq!(ack.acked_seq, 3);
+    assert_eq!(ack.echoed_client_time_ms, 300);
+    assert_eq!(
+        session.process_input_batch(1, &batch),
+        Err(InputError::Duplicate)
+    );
+}
+
+#[test]
+fn test_input_reorder_window_applies_to_attached_clients() {
+    use crate::input::ReorderWindow;
+    use zellij_remote_protocol::InputBatch;
+
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    session
+        .lease_manager
+        .request_control(1, Some(DisplaySize { cols: 80, rows: 24 }), false);
+    session.set_input_reorder_window(ReorderWindow::default());
+
+    let batch = |seqs: std::ops::RangeInclusive<u64>| InputBatch {
+        client_time_ms: 0,
+        events: seqs.map(|seq| make_input(seq, 0)).collect(),
+    };
+    assert_eq!(
+        session.process_input_batch(1, &batch(2..=3)),
+        Err(InputError::Held)
+    );
+    let (ack, events) = session.process_input_batch(1, &batch(1..=1)).unwrap();
+    assert_eq!(events.len(), 3);
+    assert_eq!(ack.acked_seq, 3);
+}
+
 #[test]
 fn test_delta_only_uses_acked_baseline() {
     use crate::client_state::ClientRenderState;
@@ -42,15 +109,29 @@ fn test_delta_only_uses_acked_baseline() {
 
     let _ = state.prepare_snapshot(&frame1, 1, &mut style_table);
 
-    let delta1 = state.prepare_delta(&frame2, 2, &mut style_table, None);
-    assert!(delta1.is_some());
-    let delta1 = delta1.unwrap();
+    let Some(RenderUpdate::Delta(delta1)) = state.prepare_delta(
+        &frame2,
+        &FrameFingerprint::of(&frame2),
+        2,
+        &mut style_table,
+        None,
+        None,
+    ) else {
+        panic!("expected a delta");
+    };
     assert_eq!(delta1.base_state_id, 1);
     assert_eq!(delta1.state_id, 2);
 
-    let delta2 = state.prepare_delta(&frame3, 3, &mut style_table, None);
-    assert!(delta2.is_some());
-    let delta2 = delta2.unwrap();
+    let Some(RenderUpdate::Delta(delta2)) = state.prepare_delta(
+        &frame3,
+        &FrameFingerprint::of(&frame3),
+        3,
+        &mut style_table,
+        None,
+        None,
+    ) else {
+        panic!("expected a delta");
+    };
     assert_eq!(delta2.base_state_id, 1);
     assert_eq!(delta2.state_id, 3);
 
@@ -62,11 +143,18 @@ fn test_delta_only_uses_acked_baseline() {
         srtt_ms: 0,
     };
     state.process_state_ack(&ack);
-    state.advance_baseline(2, frame2.clone());
-
-    let delta3 = state.prepare_delta(&frame3, 4, &mut style_table, None);
-    assert!(delta3.is_some());
-    let delta3 = delta3.unwrap();
+    state.advance_baseline(2, FrameFingerprint::of(&frame2));
+
+    let Some(RenderUpdate::Delta(delta3)) = state.prepare_delta(
+        &frame3,
+        &FrameFingerprint::of(&frame3),
+        4,
+        &mut style_table,
+        None,
+        None,
+    ) else {
+        panic!("expected a delta");
+    };
     assert_eq!(delta3.base_state_id, 2);
     assert_eq!(delta3.state_id, 4);
 }
@@ -114,6 +202,35 @@ fn test_process_state_ack_records_rtt() {
     assert_eq!(session.rtt_estimator.srtt_ms(), Some(50));
 }
 
+#[test]
+fn test_link_stats_tracked_per_client() {
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+
+    let stats = LinkStats {
+        srtt_ms: 30,
+        input_latency_p50_ms: 25,
+        input_latency_p95_ms: 90,
+        input_latency_p99_ms: 210,
+        input_latency_samples: 400,
+    };
+    assert!(session.record_link_stats(1, stats));
+    assert!(!session.record_link_stats(3, LinkStats::default()));
+    // A report without samples has nothing to show yet
+    assert!(session.record_link_stats(2, LinkStats::default()));
+
+    let latency = session.input_latency(1).unwrap();
+    assert_eq!(latency.p50_ms, 25);
+    assert_eq!(latency.p95_ms, 90);
+    assert_eq!(latency.p99_ms, 210);
+    assert_eq!(latency.samples, 400);
+    assert_eq!(session.input_latency(2), None);
+
+    session.remove_client(1);
+    assert_eq!(session.input_latency(1), None);
+}
+
 #[test]
 fn test_per_client_input_receivers() {
     let mut session = RemoteSession::new(80, 24);
@@ -154,7 +271,9 @@ fn test_resume_token_generation_and_validation() {
     let token_bytes = session.generate_resume_token(1);
     assert!(!token_bytes.is_empty());
 
-    let token = ResumeToken::decode_signed(&token_bytes, session.token_secret())
+    let token = session
+        .token_keys()
+        .open(&token_bytes)
         .expect("token should decode");
     assert_eq!(token.session_id, 42);
     assert_eq!(token.client_id, 1);
@@ -197,7 +316,7 @@ fn test_resume_with_session_mismatch() {
     session.record_state_snapshot();
 
     let token = ResumeToken::new(99, 1, 1, 0);
-    let token_bytes = token.encode_signed(session.token_secret());
+    let token_bytes = session.token_keys().seal(&token);
 
     let result = session.try_resume(&token_bytes, 4);
     assert!(matches!(result, ResumeResult::SessionMismatch));
@@ -214,12 +333,86 @@ fn test_resume_with_state_not_found() {
     session.remove_client(1);
 
     let token = ResumeToken::new(42, 1, 999, 0);
-    let token_bytes = token.encode_signed(session.token_secret());
+    let token_bytes = session.token_keys().seal(&token);
 
     let result = session.try_resume(&token_bytes, 4);
     assert!(matches!(result, ResumeResult::StateNotFound));
 }
 
+#[test]
+fn test_revoked_resume_tokens_rejected() {
+    let mut session = RemoteSession::with_session_id(80, 24, 42);
+
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    session.frame_store.advance_state();
+    session.record_state_snapshot();
+    let _ = session.get_render_update(1);
+    let _ = session.get_render_update(2);
+
+    let first = session.generate_resume_token(1);
+    let second = session.generate_resume_token(2);
+    session.remove_client(1);
+    session.remove_client(2);
+
+    session.revoke_client_resume_tokens(1);
+    assert_eq!(session.try_resume(&first, 4), ResumeResult::InvalidToken);
+    assert!(matches!(
+        session.try_resume(&second, 4),
+        ResumeResult::Resumed { client_id: 2, .. }
+    ));
+
+    session.remove_client(2);
+    session.revoke_resume_tokens();
+    assert_eq!(session.try_resume(&second, 4), ResumeResult::InvalidToken);
+}
+
+#[test]
+fn test_detach_discards_or_keeps_resume_state() {
+    let mut session = RemoteSession::with_session_id(80, 24, 42);
+
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    session
+        .lease_manager
+        .request_control(1, Some(DisplaySize { cols: 80, rows: 24 }), false);
+    session.frame_store.advance_state();
+    session.record_state_snapshot();
+    let _ = session.get_render_update(1);
+    let _ = session.get_render_update(2);
+
+    let first = session.generate_resume_token(1);
+    let second = session.generate_resume_token(2);
+
+    let event = session.detach_client(
+        1,
+        &Detach {
+            reason: DetachReason::User as i32,
+            keep_resume_state: false,
+        },
+    );
+    assert!(matches!(
+        event,
+        Some(LeaseEvent::Revoked { owner: 1, ref reason, .. }) if reason == "detach"
+    ));
+    session.remove_client(1);
+    assert_eq!(session.try_resume(&first, 4), ResumeResult::InvalidToken);
+
+    let event = session.detach_client(
+        2,
+        &Detach {
+            reason: DetachReason::Reconnect as i32,
+            keep_resume_state: true,
+        },
+    );
+    assert!(event.is_none());
+    session.remove_client(2);
+    assert!(matches!(
+        session.try_resume(&second, 4),
+        ResumeResult::Resumed { client_id: 2, .. }
+    ));
+}
+
 #[test]
 fn test_resume_with_client_id_in_use() {
     let mut session = RemoteSession::with_session_id(80, 24, 42);
@@ -295,3 +488,587 @@ fn test_resume_restores_input_seq() {
     let result = session.process_input(1, &make_input(5, 100));
     assert!(matches!(result, Err(InputError::Duplicate)));
 }
+
+#[test]
+fn test_controller_idle_timeout_reset_by_input() {
+    let clock = ManualClock::new();
+    let mut session = RemoteSession::with_clock(80, 24, clock.shared());
+    session.set_controller_idle_timeout(Some(1_000));
+
+    session.add_client(1, 4);
+    session
+        .lease_manager
+        .request_control(1, Some(DisplaySize { cols: 80, rows: 24 }), false);
+
+    clock.advance(Duration::from_millis(800));
+    assert!(session.process_input(1, &make_input(1, 100)).is_ok());
+    clock.advance(Duration::from_millis(800));
+    assert!(session.lease_manager.tick().is_none());
+
+    clock.advance(Duration::from_millis(300));
+    assert!(session.lease_manager.tick().is_some());
+    assert_eq!(
+        session.process_input(1, &make_input(2, 100)),
+        Err(InputError::NotController)
+    );
+}
+
+#[test]
+fn test_resume_token_expiry_follows_session_clock() {
+    let clock = ManualClock::new();
+    let mut session = RemoteSession::with_clock(80, 24, clock.shared());
+    session.add_client(1, 4);
+    session.frame_store.advance_state();
+    session.record_state_snapshot();
+    let _ = session.get_render_update(1);
+
+    let token_bytes = session.generate_resume_token(1);
+    let token = session.token_keys().open(&token_bytes).unwrap();
+    assert_eq!(token.issued_at_ms, clock.unix_ms());
+
+    session.remove_client(1);
+    clock.advance(Duration::from_millis(ResumeToken::default_expiry_ms()));
+    assert!(matches!(
+        session.try_resume(&token_bytes, 4),
+        ResumeResult::Resumed { client_id: 1, .. }
+    ));
+
+    session.remove_client(1);
+    clock.advance(Duration::from_millis(1));
+    assert!(matches!(
+        session.try_resume(&token_bytes, 4),
+        ResumeResult::ExpiredToken
+    ));
+}
+
+#[test]
+fn test_resume_token_from_the_future_rejected() {
+    let clock = ManualClock::new();
+    let mut session = RemoteSession::with_clock(80, 24, clock.shared());
+    let skew_ms = ResumeToken::default_max_clock_skew_ms();
+
+    let ahead = ResumeToken::issued_at(session.session_id, 1, 0, 0, clock.unix_ms() + skew_ms + 1);
+    let token_bytes = session.token_keys().seal(&ahead);
+    assert!(matches!(
+        session.try_resume(&token_bytes, 4),
+        ResumeResult::FutureDatedToken
+    ));
+
+    // Once the clock is within the allowed skew of it, it's no longer from the future
+    clock.advance(Duration::from_millis(1));
+    assert!(!matches!(
+        session.try_resume(&token_bytes, 4),
+        ResumeResult::FutureDatedToken
+    ));
+}
+
+#[test]
+fn test_set_window_bounds() {
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    let bounds = WindowBounds { min: 2, max: 16 };
+
+    assert!(session.set_window_bounds(1, bounds));
+    assert!(!session.set_window_bounds(2, bounds));
+    assert_eq!(session.clients[&1].render_window().bounds(), bounds);
+    assert_eq!(session.clients[&1].render_window().window_size(), 4);
+}
+
+fn next_snapshot(session: &mut RemoteSession, client_id: u64) -> ScreenSnapshot {
+    match session.get_render_update(client_id) {
+        Some(RenderUpdate::Snapshot(snapshot)) => snapshot,
+        other => panic!("Expected a snapshot, got {:?}", other),
+    }
+}
+
+fn ack_current(session: &mut RemoteSession, client_id: u64) {
+    let state_id = session.frame_store.current_state_id();
+    session.process_state_ack(
+        client_id,
+        &StateAck {
+            last_applied_state_id: state_id,
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn test_resize_sends_snapshot_with_size_change() {
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    assert!(next_snapshot(&mut session, 1).size_changed.is_none());
+    ack_current(&mut session, 1);
+
+    // Rows only: content stays where it was
+    session.frame_store.resize(80, 30);
+    session.frame_store.advance_state();
+    let size_changed = next_snapshot(&mut session, 1)
+        .size_changed
+        .expect("Snapshot after a resize should say so");
+    assert_eq!(
+        size_changed.old_size,
+        Some(DisplaySize { cols: 80, rows: 24 })
+    );
+    assert_eq!(
+        size_changed.new_size,
+        Some(DisplaySize { cols: 80, rows: 30 })
+    );
+    assert!(!size_changed.reflowed);
+    ack_current(&mut session, 1);
+
+    session.frame_store.resize(100, 30);
+    session.frame_store.advance_state();
+    assert!(
+        next_snapshot(&mut session, 1)
+            .size_changed
+            .unwrap()
+            .reflowed
+    );
+    ack_current(&mut session, 1);
+
+    // Back to deltas once the client has the new size
+    session.frame_store.advance_state();
+    assert!(matches!(
+        session.get_render_update(1),
+        Some(RenderUpdate::Delta(_))
+    ));
+}
+
+#[test]
+fn test_line_sizes_only_for_clients_that_support_them() {
+    let mut session = RemoteSession::new(10, 3);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    assert!(session.set_line_sizes(1, true));
+    session
+        .frame_store
+        .update_row(0, |r| r.set_line_size(LineSize::DoubleWidth));
+    session.frame_store.advance_state();
+
+    let line_sizes = |snapshot: ScreenSnapshot| -> Vec<i32> {
+        snapshot.rows.iter().map(|row| row.line_size).collect()
+    };
+    assert_eq!(
+        line_sizes(next_snapshot(&mut session, 1))[0],
+        ProtoLineSize::DoubleWidth as i32
+    );
+    assert!(line_sizes(next_snapshot(&mut session, 2))
+        .iter()
+        .all(|line_size| *line_size == ProtoLineSize::Unspecified as i32));
+    ack_current(&mut session, 1);
+    ack_current(&mut session, 2);
+
+    // A change of line size alone is no change at all without support
+    session
+        .frame_store
+        .update_row(1, |r| r.set_line_size(LineSize::DoubleHeightTop));
+    session.frame_store.advance_state();
+    match session.get_render_update(1) {
+        Some(RenderUpdate::Delta(delta)) => {
+            assert_eq!(delta.row_patches.len(), 1);
+            assert_eq!(
+                delta.row_patches[0].line_size,
+                ProtoLineSize::DoubleHeightTop as i32
+            );
+        },
+        other => panic!("Expected a delta, got {:?}", other),
+    }
+    match session.get_render_update(2) {
+        Some(RenderUpdate::Delta(delta)) => assert!(delta.row_patches.is_empty()),
+        other => panic!("Expected a delta, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_delta_larger_than_snapshot_sent_as_snapshot() {
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    next_snapshot(&mut session, 1);
+    ack_current(&mut session, 1);
+
+    // Every cell changes, so each row patch is a whole row plus its run framing
+    for row in 0..24 {
+        session.frame_store.update_row(row, |r| {
+            for col in 0..80 {
+                r.set_cell(
+                    col,
+                    Cell {
+                        codepoint: 'a' as u32 + ((row + col) % 26) as u32,
+                        width: 1,
+                        style_id: 0,
+                    },
+                );
+            }
+        });
+    }
+    session.frame_store.advance_state();
+    let snapshot = next_snapshot(&mut session, 1);
+    assert_eq!(snapshot.state_id, session.frame_store.current_state_id());
+    assert_eq!(session.delta_fallbacks(1), 1);
+    ack_current(&mut session, 1);
+
+    // A small change stays a delta
+    session.frame_store.update_row(0, |r| {
+        r.set_cell(
+            0,
+            Cell {
+                codepoint: 'x' as u32,
+                width: 1,
+                style_id: 0,
+            },
+        )
+    });
+    session.frame_store.advance_state();
+    assert!(matches!(
+        session.get_render_update(1),
+        Some(RenderUpdate::Delta(_))
+    ));
+    assert_eq!(session.delta_fallbacks(1), 1);
+}
+
+#[test]
+fn test_max_delta_bytes_caps_deltas() {
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    session.set_max_delta_bytes(Some(8));
+    next_snapshot(&mut session, 1);
+    ack_current(&mut session, 1);
+
+    session.frame_store.update_row(5, |r| {
+        r.set_cell(
+            0,
+            Cell {
+                codepoint: 'x' as u32,
+                width: 1,
+                style_id: 0,
+            },
+        )
+    });
+    session.frame_store.advance_state();
+    next_snapshot(&mut session, 1);
+    assert_eq!(session.delta_fallbacks(1), 1);
+    assert_eq!(session.delta_fallbacks(2), 0);
+}
+
+#[test]
+fn test_max_delta_bytes_counts_new_styles() {
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    session.set_max_delta_bytes(Some(512));
+    next_snapshot(&mut session, 1);
+    ack_current(&mut session, 1);
+
+    // One cell changes, but the delta has to carry every style interned since
+    let mut style_id = 0;
+    for blue in 0..200u32 {
+        style_id = session.style_table.get_or_insert(&Style {
+            fg: Some(zellij_remote_protocol::Color {
+                value: Some(zellij_remote_protocol::color::Value::Rgb(
+                    zellij_remote_protocol::Rgb {
+                        r: 0,
+                        g: 0,
+                        b: blue,
+                    },
+                )),
+            }),
+            ..Default::default()
+        });
+    }
+    session.frame_store.update_row(5, |r| {
+        r.set_cell(
+            0,
+            Cell {
+                codepoint: 'x' as u32,
+                width: 1,
+                style_id,
+            },
+        )
+    });
+    session.frame_store.advance_state();
+    next_snapshot(&mut session, 1);
+    assert_eq!(session.delta_fallbacks(1), 1);
+}
+
+#[test]
+fn test_resize_and_back_before_sending_needs_no_hint() {
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    next_snapshot(&mut session, 1);
+    ack_current(&mut session, 1);
+
+    // Seen at 100x30, but no frame went out before the size went back
+    let client_state = session.clients.get_mut(&1).unwrap();
+    assert!(client_state.observe_size(100, 30));
+    assert!(!client_state.observe_size(80, 24));
+    session.frame_store.advance_state();
+    assert!(next_snapshot(&mut session, 1).size_changed.is_none());
+}
+
+#[test]
+fn test_hidden_client_gets_no_frames() {
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    next_snapshot(&mut session, 1);
+    ack_current(&mut session, 1);
+
+    assert!(session.set_client_visible(1, false));
+    assert!(!session.set_client_visible(1, false));
+    assert!(!session.is_client_visible(1));
+    for _ in 0..3 {
+        session.frame_store.advance_state();
+        assert!(session.get_render_update(1).is_none());
+    }
+
+    // Picks up from the last acked frame
+    assert!(session.set_client_visible(1, true));
+    match session.get_render_update(1) {
+        Some(RenderUpdate::Delta(delta)) => {
+            assert_eq!(delta.state_id, session.frame_store.current_state_id());
+        },
+        other => panic!("Expected a delta, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_hidden_client_without_baseline_resumes_with_snapshot() {
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    session.set_client_visible(1, false);
+    assert!(session.get_render_update(1).is_none());
+
+    session.set_client_visible(1, true);
+    next_snapshot(&mut session, 1);
+
+    assert!(!session.set_client_visible(2, false));
+}
+
+#[test]
+fn test_region_client_gets_only_its_rows() {
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    session.set_frame_hash_enabled(1, true);
+    next_snapshot(&mut session, 1);
+    ack_current(&mut session, 1);
+
+    // A status-bar widget watching the last row
+    assert!(session.set_client_region(1, Some(23..24)));
+    assert!(!session.set_client_region(1, Some(23..24)));
+    let snapshot = next_snapshot(&mut session, 1);
+    let rows: Vec<u32> = snapshot.rows.iter().map(|row| row.row).collect();
+    assert_eq!(rows, vec![23]);
+    assert_eq!(snapshot.size.unwrap().rows, 24);
+    assert_eq!(snapshot.frame_hash, 0);
+    ack_current(&mut session, 1);
+
+    for row in [0, 23] {
+        session.frame_store.update_row(row, |r| {
+            r.set_cell(
+                0,
+                Cell {
+                    codepoint: 'x' as u32,
+                    width: 1,
+                    style_id: 0,
+                },
+            )
+        });
+    }
+    session.frame_store.advance_state();
+    match session.get_render_update(1) {
+        Some(RenderUpdate::Delta(delta)) => {
+            let rows: Vec<u32> = delta.row_patches.iter().map(|patch| patch.row).collect();
+            assert_eq!(rows, vec![23]);
+            assert_eq!(delta.frame_hash, 0);
+        },
+        other => panic!("Expected a delta, got {:?}", other),
+    }
+    ack_current(&mut session, 1);
+
+    // Back to the whole screen, starting with the rows it never got
+    assert!(session.set_client_region(1, None));
+    assert_eq!(next_snapshot(&mut session, 1).rows.len(), 24);
+    assert!(!session.set_client_region(2, Some(0..1)));
+}
+
+#[test]
+fn test_frame_rate_cap_holds_frames_back() {
+    let mut session = RemoteSession::new(80, 24);
+    session.add_client(1, 4);
+    assert!(!session.is_frame_paced(1));
+    next_snapshot(&mut session, 1);
+    ack_current(&mut session, 1);
+
+    assert!(session.set_min_frame_interval(1, 60_000));
+    assert!(!session.set_min_frame_interval(1, 60_000));
+    assert!(session.is_frame_paced(1));
+    session.frame_store.advance_state();
+    assert!(session.get_render_update(1).is_none());
+    assert!(session.has_unsent_state(1));
+
+    // The held frame goes out once the cap allows
+    session.set_min_frame_interval(1, 0);
+    match session.get_render_update(1) {
+        Some(RenderUpdate::Delta(delta)) => {
+            assert_eq!(delta.state_id, session.frame_store.current_state_id());
+        },
+        other => panic!("Expected a delta, got {:?}", other),
+    }
+    assert!(!session.set_min_frame_interval(2, 100));
+}
+
+#[test]
+fn test_capture_snapshot_attaches_nobody() {
+    let mut session = RemoteSession::new(80, 24);
+    let bold = session.style_table.get_or_insert(&Style {
+        bold: true,
+        ..Default::default()
+    });
+    session.frame_store.advance_state();
+
+    let snapshot = session.capture_snapshot();
+    assert_eq!(snapshot.state_id, session.frame_store.current_state_id());
+    assert_eq!(snapshot.rows.len(), 24);
+    assert!(snapshot.style_table_reset);
+    assert!(snapshot.styles.iter().any(|def| def.style_id == bold));
+    assert_eq!(session.client_count(), 0);
+}
+
+fn mark_row(session: &mut RemoteSession, row: usize) {
+    session.frame_store.update_row(row, |r| {
+        r.set_cell(
+            0,
+            Cell {
+                codepoint: 'x' as u32,
+                width: 1,
+                style_id: 0,
+            },
+        )
+    });
+    session.frame_store.advance_state();
+}
+
+#[test]
+fn test_preloaded_snapshot_is_first_frame_then_delta() {
+    let mut session = RemoteSession::new(80, 24);
+    session.frame_store.advance_state();
+    assert!(session.preload_snapshot());
+    assert!(!session.preload_snapshot());
+    let cached_state_id = session.frame_store.current_state_id();
+
+    // Another client's frame takes the dirty rows of the first change
+    session.add_client(2, 4);
+    next_snapshot(&mut session, 2);
+    mark_row(&mut session, 1);
+    let _ = session.get_render_update(2);
+    mark_row(&mut session, 0);
+
+    session.add_client(1, 4);
+    let cached = session.take_cached_snapshot(1, None).unwrap();
+    assert_eq!(cached.state_id, cached_state_id);
+    let envelope = StreamEnvelope::decode_length_delimited(&cached.encoded[..]).unwrap();
+    match envelope.msg {
+        Some(stream_envelope::Msg::ScreenSnapshot(snapshot)) => {
+            assert_eq!(snapshot.state_id, cached_state_id);
+            assert_eq!(snapshot.frame_sequence, 1);
+            assert_eq!(snapshot.rows.len(), 24);
+        },
+        other => panic!("Expected a snapshot, got {:?}", other),
+    }
+    assert!(session.take_cached_snapshot(1, None).is_none());
+
+    // The catch-up delta covers both changes since the snapshot
+    let Some(RenderUpdate::Delta(delta)) = session.get_render_update(1) else {
+        panic!("expected a delta");
+    };
+    assert_eq!(delta.base_state_id, cached_state_id);
+    assert_eq!(delta.state_id, session.frame_store.current_state_id());
+    let rows: Vec<u32> = delta.row_patches.iter().map(|patch| patch.row).collect();
+    assert_eq!(rows, vec![0, 1]);
+}
+
+#[test]
+fn test_preloaded_snapshot_only_when_it_fits() {
+    let mut session = RemoteSession::new(80, 24);
+    assert!(session.take_cached_snapshot(1, None).is_none());
+    session.preload_snapshot();
+    let encoded_len = session.cached_snapshot().unwrap().encoded.len();
+
+    // Already sent a frame, watching a region, or too large for the client
+    session.add_client(1, 4);
+    next_snapshot(&mut session, 1);
+    assert!(session.take_cached_snapshot(1, None).is_none());
+    session.add_client(2, 4);
+    session.set_client_region(2, Some(0..1));
+    assert!(session.take_cached_snapshot(2, None).is_none());
+    session.add_client(3, 4);
+    assert!(session
+        .take_cached_snapshot(3, Some(encoded_len - 1))
+        .is_none());
+    assert!(session.take_cached_snapshot(3, Some(encoded_len)).is_some());
+
+    // Rebuilt once it falls behind, or the screen is resized
+    for _ in 1..SNAPSHOT_PRELOAD_STATES {
+        session.frame_store.advance_state();
+        assert!(!session.preload_snapshot());
+    }
+    session.frame_store.advance_state();
+    assert!(session.preload_snapshot());
+    session.frame_store.resize(100, 24);
+    assert!(session.preload_snapshot());
+}
+
+#[test]
+fn test_session_events_queued_until_drained() {
+    let mut session = RemoteSession::with_session_id(80, 24, 42);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    session
+        .lease_manager
+        .request_control(1, Some(DisplaySize { cols: 80, rows: 24 }), false);
+    session.frame_store.advance_state();
+    session.record_state_snapshot();
+    let _ = session.get_render_update(2);
+    ack_current(&mut session, 2);
+    let token = session.generate_resume_token(2);
+
+    // The controller leaving ends its lease
+    session.remove_client(1);
+    session.force_client_snapshot(2);
+    session.remove_client(2);
+    session.remove_client(3);
+    assert!(matches!(
+        session.try_resume(&token, 4),
+        ResumeResult::Resumed { client_id: 2, .. }
+    ));
+
+    let events = session.drain_events();
+    assert_eq!(events[0], SessionEvent::ClientAdded { client_id: 1 });
+    assert_eq!(events[1], SessionEvent::ClientAdded { client_id: 2 });
+    assert!(matches!(
+        &events[2],
+        SessionEvent::Lease(LeaseEvent::Revoked { owner: 1, reason, .. }) if reason == "disconnect"
+    ));
+    assert_eq!(
+        events[3..],
+        [
+            SessionEvent::ClientRemoved { client_id: 1 },
+            SessionEvent::Resync { client_id: 2 },
+            SessionEvent::ClientRemoved { client_id: 2 },
+            SessionEvent::ClientResumed {
+                client_id: 2,
+                baseline_state_id: session.frame_store.current_state_id(),
+            },
+        ]
+    );
+    assert!(session.drain_events().is_empty());
+}
+
+#[test]
+fn test_session_events_bounded() {
+    let mut session = RemoteSession::new(80, 24);
+    for client_id in 0..MAX_PENDING_EVENTS as u64 + 10 {
+        session.add_client(client_id, 4);
+    }
+    let events = session.drain_events();
+    assert_eq!(events.len(), MAX_PENDING_EVENTS);
+    assert_eq!(events[0], SessionEvent::ClientAdded { client_id: 10 });
+}
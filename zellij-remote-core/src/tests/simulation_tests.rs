@@ -0,0 +1,886 @@
+//! Deterministic simulation of the whole protocol: one `RemoteSession` and simulated
+//! clients exchanging messages over jittery, lossy links on a logical clock.
+//!
+//! Everything runs on one thread and every random choice comes from a seeded RNG, so
+//! a seed replays the same run exactly and a failing seed is a reproducible bug.
+//! Clients behave like a careful real client: they keep the screens they acked so a
+//! delta can apply against any of them, resend unacked input after a resume, and ask
+//! for a snapshot when a delta's base is gone.
+//!
+//! After every tick the run checks that
+//! - every screen a client applies hashes like the server's frame at that state
+//! - the server's baseline for a client is a state the client applied, or a snapshot
+//!   still on its way
+//! - input watermarks and acks only move forward, and never past what the server took
+//! - the lease is where the script put it
+//!
+//! and once links are clean again, every client catches up with the server and no
+//! render window is left holding frames.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::clock::ManualClock;
+use crate::conformance::ClientScreen;
+use crate::frame::{Cell, Cursor};
+use crate::frame_hash::hash_frame;
+use crate::lease::LeaseResult;
+use crate::resume_token::ResumeResult;
+use crate::session::{InputError, RemoteSession, RenderUpdate};
+use zellij_remote_protocol::{input_event, InputAck, InputBatch, InputEvent, StateAck};
+
+const TICK_MS: u64 = 10;
+const WINDOW_SIZE: u32 = 8;
+const COLS: usize = 40;
+const ROWS: usize = 10;
+const SEEDS: u64 = 16;
+
+#[derive(Debug, Clone, Copy)]
+struct Link {
+    loss_percent: u32,
+    min_latency_ms: u64,
+    max_latency_ms: u64,
+}
+
+impl Link {
+    fn clean() -> Self {
+        Self {
+            loss_percent: 0,
+            min_latency_ms: 20,
+            max_latency_ms: 20,
+        }
+    }
+
+    fn jittery() -> Self {
+        Self {
+            loss_percent: 0,
+            min_latency_ms: 10,
+            max_latency_ms: 60,
+        }
+    }
+
+    fn lossy(loss_percent: u32) -> Self {
+        Self {
+            loss_percent,
+            ..Self::jittery()
+        }
+    }
+}
+
+/// Client to server. State acks travel as datagrams; the rest on the stream.
+#[derive(Debug)]
+enum Up {
+    StateAck(StateAck),
+    Input(InputBatch),
+    RequestSnapshot,
+}
+
+/// Server to client. Deltas travel as datagrams; the rest on the stream.
+#[derive(Debug)]
+enum Down {
+    Frame(RenderUpdate),
+    InputAck(InputAck),
+    LeaseRevoked,
+}
+
+struct Packet<T> {
+    deliver_at: u64,
+    order: u64,
+    client_id: u64,
+    msg: T,
+}
+
+struct SimClient {
+    link: Link,
+    connected: bool,
+    controller: bool,
+    /// Screens by state id, from the oldest a delta may still be based on
+    screens: BTreeMap<u64, ClientScreen>,
+    applied: HashSet<u64>,
+    snapshot_requested: bool,
+    watermark: u64,
+    next_input_seq: u64,
+    acked_input_seq: u64,
+    unacked: Vec<InputEvent>,
+    resume_token: Option<Vec<u8>>,
+}
+
+impl SimClient {
+    fn new(link: Link) -> Self {
+        Self {
+            link,
+            connected: true,
+            controller: false,
+            screens: BTreeMap::new(),
+            applied: HashSet::new(),
+            snapshot_requested: false,
+            watermark: 0,
+            next_input_seq: 1,
+            acked_input_seq: 0,
+            unacked: Vec::new(),
+            resume_token: None,
+        }
+    }
+
+    /// Start over as a fresh client, as after a resume the server turned down
+    fn forget(&mut self) {
+        self.screens.clear();
+        self.applied.clear();
+        self.snapshot_requested = false;
+        self.watermark = 0;
+        self.next_input_seq = 1;
+        self.acked_input_seq = 0;
+        self.unacked.clear();
+    }
+
+    fn newest_screen(&self) -> Option<(u64, &ClientScreen)> {
+        self.screens.iter().next_back().map(|(id, s)| (*id, s))
+    }
+}
+
+struct Sim {
+    rng: StdRng,
+    now_ms: u64,
+    clock: ManualClock,
+    session: RemoteSession,
+    clients: BTreeMap<u64, SimClient>,
+    controller: Option<u64>,
+    up: Vec<Packet<Up>>,
+    down: Vec<Packet<Down>>,
+    next_order: u64,
+    /// Latest delivery time on each client's stream, per direction, so stream
+    /// messages arrive in order
+    stream_tail: HashMap<(u64, bool), u64>,
+    state_hashes: HashMap<u64, u64>,
+    cursor: (usize, usize),
+    changed: bool,
+    rejected_inputs: usize,
+    trace: Vec<String>,
+}
+
+impl Sim {
+    fn new(seed: u64) -> Self {
+        let clock = ManualClock::new();
+        let mut sim = Self {
+            rng: StdRng::seed_from_u64(seed),
+            now_ms: 0,
+            session: RemoteSession::with_clock(COLS, ROWS, clock.shared()),
+            clock,
+            clients: BTreeMap::new(),
+            controller: None,
+            up: Vec::new(),
+            down: Vec::new(),
+            next_order: 0,
+            stream_tail: HashMap::new(),
+            state_hashes: HashMap::new(),
+            cursor: (0, 0),
+            changed: false,
+            rejected_inputs: 0,
+            trace: Vec::new(),
+        };
+        sim.session.frame_store.advance_state();
+        sim.record_state();
+        sim
+    }
+
+    fn log(&mut self, event: String) {
+        self.trace.push(format!("{:>6} {}", self.now_ms, event));
+    }
+
+    fn record_state(&mut self) {
+        let frame_store = &self.session.frame_store;
+        self.state_hashes.insert(
+            frame_store.current_state_id(),
+            hash_frame(frame_store.current_frame()),
+        );
+    }
+
+    fn cols(&self) -> usize {
+        self.session.frame_store.current_frame().cols
+    }
+
+    fn rows(&self) -> usize {
+        self.session.frame_store.current_frame().rows.len()
+    }
+
+    /// Text on a row of the server's current frame, without trailing blanks
+    fn server_text(&self, row: usize) -> String {
+        let frame = self.session.frame_store.current_frame();
+        let text: String = (0..frame.cols)
+            .filter_map(|col| frame.rows[row].get_cell(col))
+            .map(|cell| char::from_u32(cell.codepoint).unwrap_or(' '))
+            .map(|ch| if ch == '\0' { ' ' } else { ch })
+            .collect();
+        text.trim_end().to_string()
+    }
+
+    /// When a message sent now arrives, or `None` if the link drops it
+    fn delivery_time(&mut self, client_id: u64, upstream: bool, stream: bool) -> Option<u64> {
+        let link = self.clients[&client_id].link;
+        if !stream && self.rng.gen_range(0..100) < link.loss_percent {
+            return None;
+        }
+        let latency = self
+            .rng
+            .gen_range(link.min_latency_ms..=link.max_latency_ms);
+        let mut deliver_at = self.now_ms + latency;
+        if stream {
+            let tail = self.stream_tail.entry((client_id, upstream)).or_insert(0);
+            deliver_at = deliver_at.max(*tail);
+            *tail = deliver_at;
+        }
+        Some(deliver_at)
+    }
+
+    fn send_up(&mut self, client_id: u64, msg: Up) {
+        let stream = !matches!(msg, Up::StateAck(_));
+        let Some(deliver_at) = self.delivery_time(client_id, true, stream) else {
+            self.log(format!("c{} -> lost {:?}", client_id, msg));
+            return;
+        };
+        self.next_order += 1;
+        self.up.push(Packet {
+            deliver_at,
+            order: self.next_order,
+            client_id,
+            msg,
+        });
+    }
+
+    fn send_down(&mut self, client_id: u64, msg: Down) {
+        let stream = !matches!(msg, Down::Frame(RenderUpdate::Delta(_)));
+        let Some(deliver_at) = self.delivery_time(client_id, false, stream) else {
+            if let Down::Frame(RenderUpdate::Delta(delta)) = &msg {
+                self.log(format!("c{} <- lost delta {}", client_id, delta.state_id));
+            }
+            return;
+        };
+        self.next_order += 1;
+        self.down.push(Packet {
+            deliver_at,
+            order: self.next_order,
+            client_id,
+            msg,
+        });
+    }
+
+    fn render(&mut self, client_id: u64) {
+        let Some(update) = self.session.get_render_update(client_id) else {
+            return;
+        };
+        match &update {
+            RenderUpdate::Snapshot(s) => {
+                self.log(format!("c{} <- snapshot {}", client_id, s.state_id))
+            },
+            RenderUpdate::Delta(d) => self.log(format!(
+                "c{} <- delta {} on {}",
+                client_id, d.state_id, d.base_state_id
+            )),
+        }
+        self.send_down(client_id, Down::Frame(update));
+    }
+
+    fn connected_ids(&self) -> Vec<u64> {
+        self.clients
+            .iter()
+            .filter(|(_, c)| c.connected)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    // Script actions
+
+    fn attach(&mut self, client_id: u64, link: Link) {
+        self.session.add_client(client_id, WINDOW_SIZE);
+        self.clients.insert(client_id, SimClient::new(link));
+        self.log(format!("c{} attached", client_id));
+        self.render(client_id);
+    }
+
+    fn set_link(&mut self, client_id: u64, link: Link) {
+        self.clients.get_mut(&client_id).unwrap().link = link;
+    }
+
+    fn take_control(&mut self, client_id: u64) {
+        if let LeaseResult::Denied { reason, .. } = self
+            .session
+            .lease_manager
+            .request_control(client_id, None, true)
+        {
+            panic!("c{} was denied control: {}", client_id, reason);
+        }
+        if let Some(previous) = self.controller.filter(|&id| id != client_id) {
+            self.send_down(previous, Down::LeaseRevoked);
+        }
+        self.controller = Some(client_id);
+        self.log(format!("c{} took control", client_id));
+
+        // Input sent under an earlier lease, or lost with a connection, goes again
+        let client = self.clients.get_mut(&client_id).unwrap();
+        client.controller = true;
+        if !client.unacked.is_empty() {
+            let batch = InputBatch {
+                client_time_ms: self.now_ms as u32,
+                events: client.unacked.clone(),
+            };
+            self.send_up(client_id, Up::Input(batch));
+        }
+    }
+
+    fn type_char(&mut self, client_id: u64, ch: char) {
+        let now_ms = self.now_ms as u32;
+        let client = self.clients.get_mut(&client_id).unwrap();
+        if !client.connected || !client.controller {
+            return;
+        }
+        let event = InputEvent {
+            input_seq: client.next_input_seq,
+            client_time_ms: now_ms,
+            payload: Some(input_event::Payload::TextUtf8(ch.to_string().into_bytes())),
+        };
+        client.next_input_seq += 1;
+        client.unacked.push(event.clone());
+        self.send_up(
+            client_id,
+            Up::Input(InputBatch {
+                client_time_ms: now_ms,
+                events: vec![event],
+            }),
+        );
+    }
+
+    /// Program output on the bottom row, which typing never reaches
+    fn output(&mut self, text: &str) {
+        let row = self.rows() - 1;
+        let cols = self.cols();
+        self.session.frame_store.update_row(row, |r| {
+            for col in 0..cols {
+                let ch = text.chars().nth(col).unwrap_or(' ');
+                r.set_cell(col, cell(ch));
+            }
+        });
+        self.changed = true;
+    }
+
+    fn resize(&mut self, cols: usize, rows: usize) {
+        self.session.frame_store.resize(cols, rows);
+        let (row, col) = self.cursor;
+        self.cursor = (row.min(rows.saturating_sub(2)), col.min(cols - 1));
+        self.changed = true;
+        self.log(format!("resized to {}x{}", cols, rows));
+    }
+
+    fn disconnect(&mut self, client_id: u64) {
+        let token = self.session.generate_resume_token(client_id);
+        self.session.remove_client(client_id);
+        self.up.retain(|p| p.client_id != client_id);
+        self.down.retain(|p| p.client_id != client_id);
+        self.stream_tail.retain(|(id, _), _| *id != client_id);
+        if self.controller == Some(client_id) {
+            self.controller = None;
+        }
+        let client = self.clients.get_mut(&client_id).unwrap();
+        client.connected = false;
+        client.controller = false;
+        client.snapshot_requested = false;
+        client.resume_token = Some(token);
+        self.log(format!("c{} disconnected", client_id));
+    }
+
+    /// Reconnect with the resume token, or as a fresh client if the server refuses it.
+    /// Returns whether the session was resumed.
+    fn resume(&mut self, client_id: u64) -> bool {
+        let token = self.clients[&client_id]
+            .resume_token
+            .clone()
+            .expect("resume without a token");
+        let resumed = match self.session.try_resume(&token, WINDOW_SIZE) {
+            ResumeResult::Resumed {
+                client_id: resumed_id,
+                baseline_state_id,
+            } => {
+                assert_eq!(resumed_id, client_id);
+                self.log(format!("c{} resumed at {}", client_id, baseline_state_id));
+                true
+            },
+            other => {
+                self.session.add_client(client_id, WINDOW_SIZE);
+                self.clients.get_mut(&client_id).unwrap().forget();
+                self.log(format!("c{} resume refused ({:?})", client_id, other));
+                false
+            },
+        };
+        let client = self.clients.get_mut(&client_id).unwrap();
+        client.connected = true;
+        client.resume_token = None;
+        self.render(client_id);
+        resumed
+    }
+
+    fn run(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            self.tick();
+        }
+    }
+
+    fn type_text(&mut self, client_id: u64, text: &str) {
+        for ch in text.chars() {
+            self.type_char(client_id, ch);
+            self.tick();
+        }
+    }
+
+    // The clock
+
+    fn tick(&mut self) {
+        self.now_ms += TICK_MS;
+        self.clock.advance(Duration::from_millis(TICK_MS));
+
+        for packet in take_due(&mut self.up, self.now_ms) {
+            self.server_receive(packet.client_id, packet.msg);
+        }
+        for packet in take_due(&mut self.down, self.now_ms) {
+            self.client_receive(packet.client_id, packet.msg);
+        }
+        if std::mem::take(&mut self.changed) {
+            self.session.frame_store.advance_state();
+            self.record_state();
+            for client_id in self.connected_ids() {
+                self.render(client_id);
+            }
+        }
+        self.check_invariants();
+    }
+
+    fn server_receive(&mut self, client_id: u64, msg: Up) {
+        match msg {
+            Up::StateAck(ack) => self.session.process_state_ack(client_id, &ack),
+            Up::Input(batch) => match self.session.process_input_batch(client_id, &batch) {
+                Ok((ack, events)) => {
+                    let text: String = events.iter().filter_map(typed_char).collect();
+                    for ch in text.chars() {
+                        self.write_typed(ch);
+                    }
+                    self.log(format!(
+                        "server took {:?} from c{}, acked {}",
+                        text, client_id, ack.acked_seq
+                    ));
+                    self.send_down(client_id, Down::InputAck(ack));
+                },
+                Err(InputError::NotController) => {
+                    self.rejected_inputs += 1;
+                    self.log(format!("server rejected input from c{}", client_id));
+                },
+                Err(InputError::Duplicate) => {
+                    self.log(format!("server dropped resent input from c{}", client_id));
+                },
+                Err(e) => panic!(
+                    "c{} input at {}ms: {:?} (batch {:?})",
+                    client_id, self.now_ms, e, batch
+                ),
+            },
+            Up::RequestSnapshot => {
+                self.log(format!("c{} asked for a snapshot", client_id));
+                self.session.force_client_snapshot(client_id);
+            },
+        }
+    }
+
+    /// The shell echoing a typed char at the cursor, above the bottom row
+    fn write_typed(&mut self, ch: char) {
+        let (row, col) = self.cursor;
+        self.session
+            .frame_store
+            .update_row(row, |r| r.set_cell(col, cell(ch)));
+        let (cols, rows) = (self.cols(), self.rows());
+        self.cursor = if col + 1 < cols {
+            (row, col + 1)
+        } else {
+            ((row + 1).min(rows.saturating_sub(2)), 0)
+        };
+        self.session.frame_store.set_cursor(Cursor {
+            row: self.cursor.0 as u32,
+            col: self.cursor.1 as u32,
+            ..Default::default()
+        });
+        self.changed = true;
+    }
+
+    fn client_receive(&mut self, client_id: u64, msg: Down) {
+        match msg {
+            Down::Frame(RenderUpdate::Snapshot(snapshot)) => {
+                let mut screen = ClientScreen::new();
+                screen
+                    .apply_snapshot(&snapshot)
+                    .expect("snapshot should apply");
+                self.check_screen(client_id, snapshot.state_id, &screen);
+                let client = self.clients.get_mut(&client_id).unwrap();
+                client.screens = BTreeMap::from([(snapshot.state_id, screen)]);
+                client.snapshot_requested = false;
+                self.applied(
+                    client_id,
+                    snapshot.state_id,
+                    snapshot.delivered_input_watermark,
+                );
+            },
+            Down::Frame(RenderUpdate::Delta(delta)) => {
+                let client = self.clients.get_mut(&client_id).unwrap();
+                if client
+                    .newest_screen()
+                    .is_some_and(|(newest, _)| delta.state_id <= newest)
+                {
+                    self.log(format!(
+                        "c{} dropped stale delta {}",
+                        client_id, delta.state_id
+                    ));
+                    return;
+                }
+                let Some(base) = client.screens.get(&delta.base_state_id) else {
+                    let ask = !client.snapshot_requested;
+                    client.snapshot_requested = true;
+                    self.log(format!(
+                        "c{} has no base {} for delta {}",
+                        client_id, delta.base_state_id, delta.state_id
+                    ));
+                    if ask {
+                        self.send_up(client_id, Up::RequestSnapshot);
+                    }
+                    return;
+                };
+                let mut screen = base.clone();
+                if let Err(e) = screen.apply_delta(&delta) {
+                    panic!(
+                        "c{} failed to apply delta {} on {}: {:?}",
+                        client_id, delta.state_id, delta.base_state_id, e
+                    );
+                }
+                self.check_screen(client_id, delta.state_id, &screen);
+                let client = self.clients.get_mut(&client_id).unwrap();
+                // The server never goes back past a base it diffed against
+                client.screens = client.screens.split_off(&delta.base_state_id);
+                client.screens.insert(delta.state_id, screen);
+                self.applied(client_id, delta.state_id, delta.delivered_input_watermark);
+            },
+            Down::InputAck(ack) => {
+                let client = self.clients.get_mut(&client_id).unwrap();
+                assert!(
+                    ack.acked_seq >= client.acked_input_seq,
+                    "c{} input ack went back from {} to {}",
+                    client_id,
+                    client.acked_input_seq,
+                    ack.acked_seq
+                );
+                client.acked_input_seq = ack.acked_seq;
+                client.unacked.retain(|e| e.input_seq > ack.acked_seq);
+            },
+            Down::LeaseRevoked => {
+                self.clients.get_mut(&client_id).unwrap().controller = false;
+                self.log(format!("c{} lost control", client_id));
+            },
+        }
+    }
+
+    fn applied(&mut self, client_id: u64, state_id: u64, watermark: u64) {
+        let client = self.clients.get_mut(&client_id).unwrap();
+        assert!(
+            watermark >= client.watermark,
+            "c{} watermark went back from {} to {} at state {}",
+            client_id,
+            client.watermark,
+            watermark,
+            state_id
+        );
+        client.watermark = watermark;
+        client.applied.insert(state_id);
+        self.log(format!("c{} applied {}", client_id, state_id));
+        self.send_up(
+            client_id,
+            Up::StateAck(StateAck {
+                last_applied_state_id: state_id,
+                ..Default::default()
+            }),
+        );
+    }
+
+    fn check_screen(&self, client_id: u64, state_id: u64, screen: &ClientScreen) {
+        assert_eq!(
+            Some(&screen.frame_hash()),
+            self.state_hashes.get(&state_id),
+            "c{} screen at state {} differs from the server's frame ({}ms)",
+            client_id,
+            state_id,
+            self.now_ms
+        );
+    }
+
+    fn check_invariants(&self) {
+        for (&client_id, client) in self.clients.iter().filter(|(_, c)| c.connected) {
+            let state = &self.session.clients[&client_id];
+            if state.has_baseline() {
+                let baseline = state.baseline_state_id();
+                let snapshot_in_flight = self.down.iter().any(|p| {
+                    p.client_id == client_id
+                        && matches!(&p.msg, Down::Frame(RenderUpdate::Snapshot(s)) if s.state_id == baseline)
+                });
+                assert!(
+                    client.applied.contains(&baseline) || snapshot_in_flight,
+                    "c{} baseline {} was never applied ({}ms)",
+                    client_id,
+                    baseline,
+                    self.now_ms
+                );
+            }
+            let taken = self.session.input_receivers[&client_id].last_acked_seq();
+            assert!(
+                client.acked_input_seq <= taken && client.watermark <= taken,
+                "c{} saw input acked past {} ({}ms)",
+                client_id,
+                taken,
+                self.now_ms
+            );
+        }
+        let owner = self
+            .session
+            .lease_manager
+            .get_current_lease()
+            .map(|lease| lease.owner_client_id);
+        assert_eq!(owner, self.controller, "lease owner ({}ms)", self.now_ms);
+    }
+
+    /// Clean links, some output, then quiet until everything in flight has landed.
+    /// Every connected client should end on the server's current frame.
+    fn settle(&mut self) {
+        for client in self.clients.values_mut() {
+            client.link = Link::clean();
+        }
+        for i in 0..30 {
+            self.output(&format!("settling {}", i));
+            self.tick();
+        }
+        self.run(20);
+        assert!(self.up.is_empty() && self.down.is_empty());
+
+        let state_id = self.session.frame_store.current_state_id();
+        for client_id in self.connected_ids() {
+            let client = &self.clients[&client_id];
+            let (newest, screen) = client.newest_screen().expect("client has a screen");
+            assert_eq!(
+                newest, state_id,
+                "c{} is stuck behind the server",
+                client_id
+            );
+            self.check_screen(client_id, newest, screen);
+            let window = self.session.clients[&client_id].render_window();
+            assert_eq!(
+                window.unacked_count(),
+                0,
+                "c{} render window still holds frames",
+                client_id
+            );
+            assert!(client.unacked.is_empty() || !client.controller);
+        }
+    }
+}
+
+fn cell(ch: char) -> Cell {
+    Cell {
+        codepoint: ch as u32,
+        width: 1,
+        style_id: 0,
+    }
+}
+
+fn typed_char(event: &InputEvent) -> Option<char> {
+    match &event.payload {
+        Some(input_event::Payload::TextUtf8(bytes)) => {
+            String::from_utf8_lossy(bytes).chars().next()
+        },
+        _ => None,
+    }
+}
+
+fn take_due<T>(queue: &mut Vec<Packet<T>>, now_ms: u64) -> Vec<Packet<T>> {
+    let (mut due, rest): (Vec<_>, Vec<_>) = queue.drain(..).partition(|p| p.deliver_at <= now_ms);
+    *queue = rest;
+    due.sort_by_key(|p| (p.deliver_at, p.order));
+    due
+}
+
+fn two_clients(seed: u64, link: Link) -> Sim {
+    let mut sim = Sim::new(seed);
+    sim.attach(1, link);
+    sim.attach(2, link);
+    sim.run(5);
+    sim.take_control(1);
+    sim
+}
+
+/// A scripted run where every step is picked by the seed
+fn random_run(seed: u64) -> Sim {
+    let mut sim = Sim::new(seed);
+    let mut script = StdRng::seed_from_u64(seed ^ 0x5eed);
+    sim.attach(1, Link::jittery());
+    sim.attach(2, Link::jittery());
+    sim.take_control(1);
+    let mut last_takeover = 0;
+    for tick in 0..400 {
+        match script.gen_range(0..100) {
+            0..=39 => {
+                if let Some(controller) = sim.controller {
+                    let ch = script.gen_range(b'a'..=b'z') as char;
+                    sim.type_char(controller, ch);
+                }
+            },
+            40..=59 => sim.output(&format!("tick {}", tick)),
+            60..=64 => {
+                let id = script.gen_range(1..=2);
+                if sim.clients[&id].connected {
+                    sim.set_link(id, Link::lossy(script.gen_range(0..50)));
+                }
+            },
+            65..=67 if tick - last_takeover >= 20 => {
+                let id = script.gen_range(1..=2);
+                if sim.clients[&id].connected {
+                    sim.take_control(id);
+                    last_takeover = tick;
+                }
+            },
+            68..=69 => {
+                let id = script.gen_range(1..=2);
+                if sim.clients[&id].connected {
+                    sim.disconnect(id);
+                } else {
+                    sim.resume(id);
+                }
+            },
+            70 => {
+                let cols = script.gen_range(10..=60);
+                let rows = script.gen_range(3..=20);
+                sim.resize(cols, rows);
+            },
+            _ => {},
+        }
+        sim.tick();
+    }
+    for id in [1, 2] {
+        if !sim.clients[&id].connected {
+            sim.resume(id);
+        }
+    }
+    sim.settle();
+    sim
+}
+
+#[test]
+fn test_sim_attach_and_type() {
+    for seed in 0..SEEDS {
+        let mut sim = two_clients(seed, Link::jittery());
+        sim.type_text(1, "echo hello");
+        sim.settle();
+        assert_eq!(sim.server_text(0), "echo hello", "seed {}", seed);
+    }
+}
+
+#[test]
+fn test_sim_typing_under_loss() {
+    for seed in 0..SEEDS {
+        let mut sim = two_clients(seed, Link::lossy(30));
+        sim.type_text(1, "the quick brown fox jumps");
+        sim.settle();
+        assert_eq!(
+            sim.server_text(0),
+            "the quick brown fox jumps",
+            "seed {}",
+            seed
+        );
+    }
+}
+
+#[test]
+fn test_sim_resume_resends_lost_input() {
+    for seed in 0..SEEDS {
+        let mut sim = two_clients(seed, Link::lossy(20));
+        sim.type_text(1, "abc");
+        // Whatever is still in flight goes down with the connection
+        sim.type_char(1, 'd');
+        sim.disconnect(1);
+        for i in 0..10 {
+            sim.output(&format!("while away {}", i));
+            sim.tick();
+        }
+        assert!(sim.resume(1), "seed {}", seed);
+        sim.take_control(1);
+        sim.run(10);
+        sim.type_text(1, "ef");
+        sim.settle();
+        assert_eq!(sim.server_text(0), "abcdef", "seed {}", seed);
+    }
+}
+
+#[test]
+fn test_sim_resume_after_history_is_gone() {
+    let mut sim = two_clients(3, Link::jittery());
+    sim.type_text(1, "abc");
+    sim.run(10);
+    sim.disconnect(1);
+    for i in 0..100 {
+        sim.output(&format!("while away {}", i));
+        sim.tick();
+    }
+    assert!(!sim.resume(1));
+    sim.take_control(1);
+    sim.type_text(1, "d");
+    sim.settle();
+    assert_eq!(sim.server_text(0), "abcd");
+}
+
+#[test]
+fn test_sim_lease_takeover() {
+    for seed in 0..SEEDS {
+        let mut sim = two_clients(seed, Link::jittery());
+        sim.type_text(1, "one");
+        sim.run(10);
+        sim.take_control(2);
+        // Sent before the revoke reaches client 1
+        sim.type_char(1, 'x');
+        sim.tick();
+        sim.type_text(2, "two");
+        sim.type_text(1, "y");
+        sim.settle();
+        assert!(sim.rejected_inputs >= 1, "seed {}", seed);
+        assert!(!sim.clients[&1].controller);
+        assert_eq!(sim.server_text(0), "onetwo", "seed {}", seed);
+    }
+}
+
+#[test]
+fn test_sim_resize() {
+    for seed in 0..SEEDS {
+        let mut sim = two_clients(seed, Link::lossy(20));
+        sim.type_text(1, "before");
+        sim.resize(60, 15);
+        sim.type_text(1, " during");
+        sim.run(3);
+        sim.resize(30, 6);
+        sim.type_text(1, " after");
+        sim.settle();
+        assert_eq!(sim.server_text(0), "before during after", "seed {}", seed);
+    }
+}
+
+#[test]
+fn test_sim_random_schedules() {
+    for seed in 0..SEEDS {
+        let sim = random_run(seed);
+        assert!(sim.trace.len() > 400, "seed {}", seed);
+    }
+}
+
+#[test]
+fn test_sim_same_seed_same_run() {
+    assert_eq!(random_run(7).trace, random_run(7).trace);
+    assert_ne!(random_run(7).trace, random_run(8).trace);
+}
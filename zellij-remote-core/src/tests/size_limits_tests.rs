@@ -0,0 +1,52 @@
+use crate::size_limits::{SizeError, SizeLimits, DEFAULT_MAX_COLS, DEFAULT_MAX_ROWS};
+use zellij_remote_protocol::DisplaySize;
+
+fn size(cols: u32, rows: u32) -> DisplaySize {
+    DisplaySize { cols, rows }
+}
+
+#[test]
+fn test_sizes_in_range_pass_unchanged() {
+    let limits = SizeLimits::default();
+    assert_eq!(limits.check(&size(80, 24)), Ok(size(80, 24)));
+    assert_eq!(
+        limits.check(&size(DEFAULT_MAX_COLS, DEFAULT_MAX_ROWS)),
+        Ok(size(DEFAULT_MAX_COLS, DEFAULT_MAX_ROWS))
+    );
+}
+
+#[test]
+fn test_small_sizes_raised_to_minimum() {
+    let limits = SizeLimits::default();
+    assert_eq!(limits.check(&size(1, 1)), Ok(size(20, 5)));
+    assert_eq!(limits.check(&size(12, 40)), Ok(size(20, 40)));
+}
+
+#[test]
+fn test_empty_and_huge_sizes_rejected() {
+    let limits = SizeLimits::default();
+    assert_eq!(
+        limits.check(&size(0, 24)),
+        Err(SizeError::Empty { cols: 0, rows: 24 })
+    );
+    assert!(matches!(
+        limits.check(&size(u32::MAX, 24)),
+        Err(SizeError::TooLarge { .. })
+    ));
+    assert!(matches!(
+        limits.check(&size(80, DEFAULT_MAX_ROWS + 1)),
+        Err(SizeError::TooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_custom_limits() {
+    let limits = SizeLimits {
+        min_cols: 40,
+        max_cols: 200,
+        min_rows: 10,
+        max_rows: 60,
+    };
+    assert_eq!(limits.check(&size(30, 8)), Ok(size(40, 10)));
+    assert!(limits.check(&size(201, 60)).is_err());
+}
@@ -0,0 +1,111 @@
+use crate::style_convert::{
+    ansi256_color, apply_sgr_params, default_color, downgrade_underline, rgb_color,
+    sgr_params_to_style, style_to_sgr, style_to_sgr_params,
+};
+use zellij_remote_protocol::{Style, UnderlineStyle};
+
+fn styled() -> Style {
+    let mut style = Style {
+        fg: Some(ansi256_color(1)),
+        bg: Some(rgb_color(10, 20, 30)),
+        bold: true,
+        italic: true,
+        strike: true,
+        underline_color: Some(ansi256_color(200)),
+        ..Default::default()
+    };
+    style.set_underline(UnderlineStyle::Curly);
+    style
+}
+
+#[test]
+fn test_default_style_is_plain_reset() {
+    assert_eq!(style_to_sgr(&Style::default()), "\x1b[0m");
+}
+
+#[test]
+fn test_style_to_sgr_params() {
+    assert_eq!(
+        style_to_sgr_params(&styled()),
+        vec!["0", "1", "3", "9", "4:3", "31", "48;2;10;20;30", "58;5;200"]
+    );
+}
+
+#[test]
+fn test_named_colors_use_short_codes() {
+    let style = Style {
+        fg: Some(ansi256_color(12)),
+        bg: Some(ansi256_color(0)),
+        ..Default::default()
+    };
+    assert_eq!(style_to_sgr_params(&style), vec!["0", "94", "40"]);
+
+    let style = Style {
+        fg: Some(default_color()),
+        bg: Some(ansi256_color(100)),
+        ..Default::default()
+    };
+    assert_eq!(style_to_sgr_params(&style), vec!["0", "39", "48;5;100"]);
+}
+
+#[test]
+fn test_sgr_roundtrip() {
+    let style = styled();
+    let params = style_to_sgr_params(&style).join(";");
+    assert_eq!(sgr_params_to_style(&params), style);
+}
+
+#[test]
+fn test_parse_colon_and_semicolon_extended_colors() {
+    let semicolon = sgr_params_to_style("38;2;1;2;3;48;5;17");
+    let colon = sgr_params_to_style("38:2::1:2:3;48:5:17");
+    assert_eq!(semicolon, colon);
+    assert_eq!(semicolon.fg, Some(rgb_color(1, 2, 3)));
+    assert_eq!(semicolon.bg, Some(ansi256_color(17)));
+    assert_eq!(
+        sgr_params_to_style("38:2:1:2:3").fg,
+        Some(rgb_color(1, 2, 3))
+    );
+}
+
+#[test]
+fn test_apply_sgr_params_is_incremental() {
+    let mut style = styled();
+    apply_sgr_params(&mut style, "22;23;24;39");
+    assert!(!style.bold);
+    assert!(!style.italic);
+    assert!(style.strike);
+    assert_eq!(style.underline(), UnderlineStyle::None);
+    assert_eq!(style.fg, Some(default_color()));
+
+    apply_sgr_params(&mut style, "");
+    assert_eq!(style, Style::default());
+}
+
+#[test]
+fn test_malformed_params_are_ignored() {
+    let mut style = styled();
+    apply_sgr_params(&mut style, "x;38;7;2");
+    assert_eq!(style.fg, Some(ansi256_color(1)));
+    assert!(style.bold);
+}
+
+#[test]
+fn test_downgrade_underline() {
+    let mut style = styled();
+    downgrade_underline(&mut style);
+    assert_eq!(style.underline(), UnderlineStyle::Single);
+    assert!(style.underline_color.is_none());
+    assert_eq!(
+        style_to_sgr_params(&style),
+        vec!["0", "1", "3", "9", "4", "31", "48;2;10;20;30"]
+    );
+
+    // No underline stays none; only the stray color goes
+    let mut plain = Style {
+        underline_color: Some(ansi256_color(3)),
+        ..Default::default()
+    };
+    downgrade_underline(&mut plain);
+    assert_eq!(plain, Style::default());
+}
@@ -0,0 +1,160 @@
+use crate::conformance::ClientScreen;
+use crate::frame_hash::hash_frame;
+use crate::session::RemoteSession;
+use crate::tests::{ack, delta, put_styled, snapshot};
+use zellij_remote_protocol::Style;
+
+fn style(bold: bool, italic: bool) -> Style {
+    Style {
+        bold,
+        italic,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_delta_carries_styles_interned_before_it() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+    put_styled(&mut session, 0, 0, "a", &style(true, false));
+
+    let mut screen = ClientScreen::new();
+    let first = snapshot(&mut session, 1);
+    screen.apply_snapshot(&first).unwrap();
+    ack(&mut session, 1, first.state_id);
+
+    put_styled(&mut session, 1, 0, "b", &style(false, true));
+    let update = delta(&mut session, 1);
+    assert_eq!(update.styles_added.len(), 1);
+    screen.apply_delta(&update).unwrap();
+    assert_eq!(
+        screen.frame_hash(),
+        hash_frame(session.frame_store.current_frame())
+    );
+}
+
+#[test]
+fn test_unacked_styles_resent_in_next_delta() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+    let first = snapshot(&mut session, 1);
+    ack(&mut session, 1, first.state_id);
+
+    put_styled(&mut session, 0, 0, "a", &style(true, false));
+    let lost = delta(&mut session, 1);
+    assert_eq!(lost.styles_added.len(), 1);
+
+    // The first delta was never acked, so its style goes out again
+    put_styled(&mut session, 1, 0, "b", &style(true, false));
+    let next = delta(&mut session, 1);
+    assert_eq!(next.styles_added, lost.styles_added);
+
+    ack(&mut session, 1, next.state_id);
+    put_styled(&mut session, 2, 0, "c", &style(true, false));
+    assert!(delta(&mut session, 1).styles_added.is_empty());
+}
+
+#[test]
+fn test_ack_of_older_delta_moves_style_watermark() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+    let first = snapshot(&mut session, 1);
+    ack(&mut session, 1, first.state_id);
+
+    put_styled(&mut session, 0, 0, "a", &style(true, false));
+    let bold = delta(&mut session, 1);
+    put_styled(&mut session, 1, 0, "b", &style(false, true));
+    let italic = delta(&mut session, 1);
+    assert_eq!(italic.styles_added.len(), 2);
+
+    // Output keeps coming, so acks trail the newest frame sent
+    ack(&mut session, 1, bold.state_id);
+    put_styled(&mut session, 2, 0, "c", &style(true, true));
+    let next = delta(&mut session, 1);
+    let ids: Vec<u32> = next.styles_added.iter().map(|def| def.style_id).collect();
+    assert_eq!(ids, vec![2, 3]);
+}
+
+#[test]
+fn test_snapshot_extends_retained_style_table() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+    assert!(session.set_style_retention_enabled(1, true));
+    put_styled(&mut session, 0, 0, "a", &style(true, false));
+
+    let mut screen = ClientScreen::new();
+    let first = snapshot(&mut session, 1);
+    assert!(first.style_table_reset);
+    screen.apply_snapshot(&first).unwrap();
+    ack(&mut session, 1, first.state_id);
+
+    put_styled(&mut session, 1, 0, "b", &style(false, true));
+    session.force_client_snapshot(1);
+    let second = snapshot(&mut session, 1);
+    assert!(!second.style_table_reset);
+    assert_eq!(second.styles.len(), 1);
+    assert_eq!(second.styles[0].style_id, 2);
+
+    screen.apply_snapshot(&second).unwrap();
+    assert_eq!(
+        screen.frame_hash(),
+        hash_frame(session.frame_store.current_frame())
+    );
+}
+
+#[test]
+fn test_snapshot_resets_styles_without_watermark() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    session.set_style_retention_enabled(1, true);
+    put_styled(&mut session, 0, 0, "a", &style(true, false));
+
+    // Nothing acked yet
+    snapshot(&mut session, 1);
+    session.force_client_snapshot(1);
+    assert!(snapshot(&mut session, 1).style_table_reset);
+
+    // Acked, but the client reported a decode error
+    let state_id = session.frame_store.current_state_id();
+    ack(&mut session, 1, state_id);
+    session.forget_client_styles(1);
+    session.force_client_snapshot(1);
+    let reset = snapshot(&mut session, 1);
+    assert!(reset.style_table_reset);
+    assert_eq!(reset.styles.len(), 2);
+
+    // Without the capability every snapshot resets
+    let first = snapshot(&mut session, 2);
+    ack(&mut session, 2, first.state_id);
+    session.force_client_snapshot(2);
+    assert!(snapshot(&mut session, 2).style_table_reset);
+}
+
+#[test]
+fn test_renumbered_style_table_resent_to_every_client() {
+    let mut session = RemoteSession::new(10, 4);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    put_styled(&mut session, 0, 0, "a", &style(true, false));
+
+    let mut screens = [ClientScreen::new(), ClientScreen::new()];
+    for (client_id, screen) in [1, 2].into_iter().zip(screens.iter_mut()) {
+        let first = snapshot(&mut session, client_id);
+        screen.apply_snapshot(&first).unwrap();
+        ack(&mut session, client_id, first.state_id);
+    }
+
+    // Id 1 now names a different style than the one the clients hold
+    session.style_table = session.style_table.renumbered();
+    session.forget_all_client_styles();
+    put_styled(&mut session, 0, 0, "a", &style(false, true));
+
+    for (client_id, screen) in [1, 2].into_iter().zip(screens.iter_mut()) {
+        let update = delta(&mut session, client_id);
+        assert_eq!(update.styles_added.len(), 2);
+        screen.apply_delta(&update).unwrap();
+        assert_eq!(screen.style_at(0, 0).map(|style| style.italic), Some(true));
+        assert_eq!(screen.style_at(0, 0).map(|style| style.bold), Some(false));
+    }
+}
@@ -1,5 +1,5 @@
-use crate::style_table::StyleTable;
-use zellij_remote_protocol::{Color, Rgb, Style};
+use crate::style_table::{StyleTable, MAX_STYLES};
+use zellij_remote_protocol::{Color, Rgb, Style, UnderlineStyle};
 
 fn make_style(fg_r: u8, fg_g: u8, fg_b: u8) -> Style {
     Style {
@@ -100,3 +100,77 @@ fn test_styles_since_baseline() {
     let new_styles = table.styles_since(baseline);
     assert_eq!(new_styles.len(), 2);
 }
+
+#[test]
+fn test_underline_style_and_color_are_part_of_the_key() {
+    let mut table = StyleTable::new();
+
+    let mut curly = make_style(255, 0, 0);
+    curly.set_underline(UnderlineStyle::Curly);
+    let mut dotted = curly.clone();
+    dotted.set_underline(UnderlineStyle::Dotted);
+    let mut colored = curly.clone();
+    colored.underline_color = Some(make_style(0, 0, 255).fg.unwrap());
+
+    let ids = [
+        table.get_or_insert(&make_style(255, 0, 0)),
+        table.get_or_insert(&curly),
+        table.get_or_insert(&dotted),
+        table.get_or_insert(&colored),
+    ];
+    for (i, a) in ids.iter().enumerate() {
+        for b in &ids[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+    assert_eq!(table.get_or_insert(&colored.clone()), ids[3]);
+}
+
+#[test]
+fn test_ids_past_u16_range_do_not_wrap() {
+    let mut table = StyleTable::new();
+    let styles: Vec<Style> = (0..70_000u32)
+        .map(|i| make_style((i >> 16) as u8, (i >> 8) as u8, i as u8))
+        .collect();
+    for (i, style) in styles.iter().enumerate() {
+        assert_eq!(table.get_or_insert(style), i as u32 + 1);
+    }
+
+    let high_id = u16::MAX as u32 + 2;
+    assert_eq!(table.get(high_id), Some(&styles[high_id as usize - 1]));
+    assert_eq!(table.get_or_insert(&styles[0]), 1);
+    assert!(!table.is_full());
+    assert!(MAX_STYLES > 70_001);
+}
+
+#[test]
+fn test_full_table_gives_new_styles_the_default_id() {
+    let mut table = StyleTable::with_max_styles(3);
+    let red = table.get_or_insert(&make_style(255, 0, 0));
+    let green = table.get_or_insert(&make_style(0, 255, 0));
+    assert_eq!((red, green), (1, 2));
+    assert!(table.is_full());
+
+    assert_eq!(table.get_or_insert(&make_style(0, 0, 255)), 0);
+    assert_eq!(table.get_or_insert(&make_style(0, 0, 128)), 0);
+    assert_eq!(table.overflowed(), 2);
+    assert_eq!(table.current_count(), 3);
+
+    // Styles already in the table keep their ids
+    assert_eq!(table.get_or_insert(&make_style(0, 255, 0)), 2);
+    assert_eq!(table.overflowed(), 2);
+}
+
+#[test]
+fn test_renumbered_table_starts_over_in_the_next_generation() {
+    let mut table = StyleTable::with_max_styles(3);
+    table.get_or_insert(&make_style(255, 0, 0));
+    table.get_or_insert(&make_style(0, 255, 0));
+    assert!(table.is_full());
+
+    let mut renumbered = table.renumbered();
+    assert_eq!(renumbered.generation(), table.generation() + 1);
+    assert_eq!(renumbered.max_styles(), 3);
+    assert_eq!(renumbered.current_count(), 1);
+    assert_eq!(renumbered.get_or_insert(&make_style(0, 0, 255)), 1);
+}
@@ -0,0 +1,99 @@
+use crate::conformance::ClientScreen;
+use crate::session::RemoteSession;
+use crate::style_convert::{ansi256_color, rgb_color, style_to_sgr};
+use crate::tests::{ack, delta, put_styled, snapshot};
+use zellij_remote_protocol::{Color, Style, UnderlineStyle};
+
+fn underlined(underline: UnderlineStyle, color: Option<Color>) -> Style {
+    let mut style = Style {
+        underline_color: color,
+        ..Default::default()
+    };
+    style.set_underline(underline);
+    style
+}
+
+#[test]
+fn test_styled_underlines_reach_capable_client() {
+    let curly = underlined(UnderlineStyle::Curly, Some(rgb_color(255, 128, 0)));
+    let mut session = RemoteSession::new(10, 2);
+    session.add_client(1, 4);
+    assert!(session.set_styled_underlines(1, true));
+    put_styled(&mut session, 0, 0, "a", &curly);
+
+    let mut screen = ClientScreen::new();
+    let first = snapshot(&mut session, 1);
+    screen.apply_snapshot(&first).unwrap();
+    ack(&mut session, 1, first.state_id);
+    assert_eq!(screen.style_at(0, 0), Some(curly.clone()));
+
+    let dotted = underlined(UnderlineStyle::Dotted, Some(ansi256_color(33)));
+    put_styled(&mut session, 0, 1, "b", &dotted);
+    screen.apply_delta(&delta(&mut session, 1)).unwrap();
+    assert_eq!(screen.style_at(0, 1), Some(dotted));
+    assert_eq!(screen.style_at(0, 0), Some(curly.clone()));
+    assert_eq!(
+        style_to_sgr(&screen.style_at(0, 0).unwrap()),
+        "\x1b[0;4:3;58;2;255;128;0m"
+    );
+}
+
+#[test]
+fn test_underline_color_change_alone_sends_new_style() {
+    let mut session = RemoteSession::new(10, 2);
+    session.add_client(1, 4);
+    session.set_styled_underlines(1, true);
+    let red = underlined(UnderlineStyle::Double, Some(ansi256_color(1)));
+    put_styled(&mut session, 0, 0, "a", &red);
+
+    let mut screen = ClientScreen::new();
+    let first = snapshot(&mut session, 1);
+    screen.apply_snapshot(&first).unwrap();
+    ack(&mut session, 1, first.state_id);
+
+    // Same text, same underline style; only the underline color differs
+    let blue = underlined(UnderlineStyle::Double, Some(ansi256_color(4)));
+    put_styled(&mut session, 0, 0, "a", &blue);
+    let update = delta(&mut session, 1);
+    assert_eq!(update.row_patches.len(), 1);
+    assert_eq!(update.styles_added.len(), 1);
+    assert_eq!(update.styles_added[0].style.as_ref(), Some(&blue));
+
+    screen.apply_delta(&update).unwrap();
+    assert_eq!(screen.style_at(0, 0), Some(blue));
+}
+
+#[test]
+fn test_styled_underlines_downgraded_for_other_clients() {
+    let curly = underlined(UnderlineStyle::Curly, Some(ansi256_color(208)));
+    let single = underlined(UnderlineStyle::Single, None);
+    let mut session = RemoteSession::new(10, 2);
+    session.add_client(1, 4);
+    session.add_client(2, 4);
+    session.set_styled_underlines(1, true);
+    put_styled(&mut session, 0, 0, "a", &curly);
+
+    let mut capable = ClientScreen::new();
+    let mut plain = ClientScreen::new();
+    let first = snapshot(&mut session, 1);
+    capable.apply_snapshot(&first).unwrap();
+    ack(&mut session, 1, first.state_id);
+    let first = snapshot(&mut session, 2);
+    plain.apply_snapshot(&first).unwrap();
+    ack(&mut session, 2, first.state_id);
+    assert_eq!(plain.style_at(0, 0), Some(single.clone()));
+
+    let dashed = underlined(UnderlineStyle::Dashed, Some(ansi256_color(1)));
+    put_styled(&mut session, 0, 1, "b", &dashed);
+    capable.apply_delta(&delta(&mut session, 1)).unwrap();
+    plain.apply_delta(&delta(&mut session, 2)).unwrap();
+
+    assert_eq!(capable.style_at(0, 0), Some(curly));
+    assert_eq!(capable.style_at(0, 1), Some(dashed));
+    assert_eq!(plain.style_at(0, 1), Some(single));
+    // The session's own table keeps full fidelity
+    assert_eq!(
+        session.style_table.get(2).map(|s| s.underline()),
+        Some(UnderlineStyle::Dashed)
+    );
+}
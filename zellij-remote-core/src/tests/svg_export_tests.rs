@@ -0,0 +1,99 @@
+use crate::frame::{CursorColor, CursorShape, FrameStore, LineSize};
+use crate::style_convert::{ansi256_color, rgb_color};
+use crate::style_table::StyleTable;
+use crate::svg_export::{frame_to_svg, SvgTheme};
+use crate::tests::put;
+use zellij_remote_protocol::{Style, UnderlineStyle};
+
+#[test]
+fn test_svg_has_frame_size_and_theme_background() {
+    let store = FrameStore::new(10, 2);
+    let theme = SvgTheme::default();
+    let svg = frame_to_svg(store.current_frame(), &StyleTable::new(), &theme);
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"90\" height=\"36\""));
+    assert!(svg.contains("fill=\"#1e1e1e\""));
+    assert!(svg.ends_with("</svg>"));
+    // A blank screen draws no text
+    assert!(!svg.contains("<text"));
+}
+
+#[test]
+fn test_svg_styles_glyphs_and_escapes_them() {
+    let mut store = FrameStore::new(10, 1);
+    let mut style_table = StyleTable::new();
+    let red_bold = style_table.get_or_insert(&Style {
+        fg: Some(ansi256_color(196)),
+        bg: Some(rgb_color(0, 0, 0x80)),
+        bold: true,
+        italic: true,
+        underline: UnderlineStyle::Double as i32,
+        ..Default::default()
+    });
+    put(&mut store, 0, 0, '<', 1, red_bold);
+    put(&mut store, 0, 1, '&', 1, red_bold);
+    put(&mut store, 0, 3, 'x', 1, 0);
+
+    let svg = frame_to_svg(store.current_frame(), &style_table, &SvgTheme::default());
+    assert!(svg.contains(
+        "<text x=\"0 9\" y=\"14\" fill=\"#ff0000\" font-weight=\"bold\" font-style=\"italic\">&lt;&amp;</text>"
+    ));
+    assert!(svg.contains("<rect x=\"0\" width=\"18\" height=\"18\" fill=\"#000080\"/>"));
+    // Double underline under the styled run only
+    assert_eq!(svg.matches("<line x1=\"0\"").count(), 2);
+    assert!(svg.contains("<text x=\"27\" y=\"14\" fill=\"#e5e5e5\">x</text>"));
+}
+
+#[test]
+fn test_svg_wide_char_and_reverse() {
+    let mut store = FrameStore::new(4, 1);
+    let mut style_table = StyleTable::new();
+    let reverse = style_table.get_or_insert(&Style {
+        reverse: true,
+        ..Default::default()
+    });
+    put(&mut store, 0, 1, '中', 2, reverse);
+    put(&mut store, 0, 2, '\0', 0, reverse);
+    let theme = SvgTheme::default();
+
+    let svg = frame_to_svg(store.current_frame(), &style_table, &theme);
+    // Reverse video: theme foreground behind, theme background glyph
+    assert!(svg.contains("<rect x=\"9\" width=\"18\" height=\"18\" fill=\"#e5e5e5\"/>"));
+    assert!(svg.contains("<text x=\"9\" y=\"14\" fill=\"#1e1e1e\">中</text>"));
+}
+
+#[test]
+fn test_svg_cursor_and_line_size() {
+    let mut store = FrameStore::new(4, 2);
+    put(&mut store, 1, 2, 'a', 1, 0);
+    store.update_row(0, |r| r.set_line_size(LineSize::DoubleWidth));
+    let mut cursor = store.current_frame().cursor;
+    cursor.row = 1;
+    cursor.col = 2;
+    cursor.shape = CursorShape::Block;
+    cursor.color = Some(CursorColor {
+        r: 0xff,
+        g: 0x80,
+        b: 0,
+    });
+    store.set_cursor(cursor);
+
+    let svg = frame_to_svg(
+        store.current_frame(),
+        &StyleTable::new(),
+        &SvgTheme::default(),
+    );
+    assert!(svg.contains("<rect x=\"18\" width=\"9\" height=\"18\" fill=\"#ff8000\"/>"));
+    // The glyph under the block shows through in the cell's background
+    assert!(svg.contains("<text x=\"18\" y=\"14\" fill=\"#1e1e1e\">a</text>"));
+    assert!(svg.contains("scale(2,1)"));
+
+    cursor.visible = false;
+    store.set_cursor(cursor);
+    let svg = frame_to_svg(
+        store.current_frame(),
+        &StyleTable::new(),
+        &SvgTheme::default(),
+    );
+    assert!(!svg.contains("#ff8000"));
+}
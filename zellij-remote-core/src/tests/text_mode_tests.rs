@@ -0,0 +1,224 @@
+use crate::backpressure::RenderWindow;
+use crate::client_state::ClientRenderState;
+use crate::frame::{Cell, FrameStore};
+use crate::style_table::StyleTable;
+use crate::text_mode::{
+    TextModeChange, TextModeController, TEXT_MODE_FRAME_INTERVAL_MS, TEXT_MODE_RECOVERY_MAX_MS,
+    TEXT_MODE_RECOVERY_MS, TEXT_MODE_STARVATION_WINDOW_MS, TEXT_MODE_STARVED_FRAMES,
+};
+use zellij_remote_protocol::Style;
+
+/// A full window on a link that delivered 1000 bytes in 10s (0.1 bytes/ms)
+fn starved_window() -> RenderWindow {
+    let mut window = RenderWindow::new(2);
+    window.mark_sent_at(1, 1_000, 0);
+    window.ack_received_at(1, 10_000, 0);
+    window.mark_sent_at(2, 100, 10_000);
+    window.mark_sent_at(3, 100, 10_000);
+    assert!(window.is_window_exhausted());
+    window
+}
+
+/// A full window on a link that delivered 1000 bytes in 10ms
+fn busy_fast_window() -> RenderWindow {
+    let mut window = RenderWindow::new(2);
+    window.mark_sent_at(1, 1_000, 0);
+    window.ack_received_at(1, 10, 0);
+    window.mark_sent_at(2, 100, 10);
+    window.mark_sent_at(3, 100, 10);
+    window
+}
+
+fn allowed_controller() -> TextModeController {
+    let mut controller = TextModeController::default();
+    controller.set_allowed(true);
+    controller
+}
+
+/// Feed starved frames until the controller switches; returns the time it did.
+fn enter_text_mode(controller: &mut TextModeController, start_ms: u64) -> u64 {
+    let window = starved_window();
+    for i in 0..TEXT_MODE_STARVED_FRAMES as u64 {
+        let now = start_ms + i * 100;
+        if let Some(change) = controller.observe(now, &window) {
+            assert_eq!(change, TextModeChange::Entered);
+            return now;
+        }
+    }
+    panic!("text mode not entered");
+}
+
+#[test]
+fn test_sustained_starvation_enters_text_mode() {
+    let mut controller = allowed_controller();
+    let window = starved_window();
+
+    for i in 0..TEXT_MODE_STARVED_FRAMES - 1 {
+        assert_eq!(controller.observe(i as u64 * 100, &window), None);
+    }
+    assert_eq!(
+        controller.observe(1_000, &window),
+        Some(TextModeChange::Entered)
+    );
+    assert!(controller.is_active());
+}
+
+#[test]
+fn test_not_entered_without_starvation() {
+    let mut controller = allowed_controller();
+
+    // Full window, but the link is fast: the session is just producing a lot
+    let fast = busy_fast_window();
+    // Slow link that isn't backed up
+    let mut idle = RenderWindow::new(4);
+    idle.mark_sent_at(1, 1_000, 0);
+    idle.ack_received_at(1, 10_000, 0);
+
+    for i in 0..10 {
+        assert_eq!(controller.observe(i * 100, &fast), None);
+        assert_eq!(controller.observe(i * 100, &idle), None);
+    }
+    assert!(!controller.is_active());
+}
+
+#[test]
+fn test_not_entered_unless_allowed() {
+    let mut controller = TextModeController::default();
+    let window = starved_window();
+    for i in 0..10 {
+        assert_eq!(controller.observe(i * 100, &window), None);
+    }
+    assert!(!controller.is_active());
+}
+
+#[test]
+fn test_starved_frames_outside_window_do_not_accumulate() {
+    let mut controller = allowed_controller();
+    let window = starved_window();
+
+    for i in 0..TEXT_MODE_STARVED_FRAMES as u64 {
+        assert_eq!(
+            controller.observe(i * TEXT_MODE_STARVATION_WINDOW_MS, &window),
+            None
+        );
+    }
+}
+
+#[test]
+fn test_recovery_exits_text_mode_and_escalates() {
+    let mut controller = allowed_controller();
+    let clear = RenderWindow::new(2);
+
+    let entered = enter_text_mode(&mut controller, 0);
+    assert_eq!(controller.observe(entered + 1, &clear), None);
+    assert_eq!(
+        controller.observe(entered + TEXT_MODE_RECOVERY_MS, &clear),
+        None
+    );
+    assert_eq!(
+        controller.observe(entered + 1 + TEXT_MODE_RECOVERY_MS, &clear),
+        Some(TextModeChange::Exited)
+    );
+
+    // Falling back in makes the next recovery take twice as long
+    let entered = enter_text_mode(&mut controller, 100_000);
+    assert_eq!(controller.observe(entered + 1, &clear), None);
+    assert_eq!(
+        controller.observe(entered + 1 + TEXT_MODE_RECOVERY_MS, &clear),
+        None
+    );
+    assert_eq!(
+        controller.observe(entered + 1 + 2 * TEXT_MODE_RECOVERY_MS, &clear),
+        Some(TextModeChange::Exited)
+    );
+}
+
+#[test]
+fn test_backed_up_frame_restarts_recovery() {
+    let mut controller = allowed_controller();
+    let clear = RenderWindow::new(2);
+    let starved = starved_window();
+
+    let entered = enter_text_mode(&mut controller, 0);
+    controller.observe(entered + 1, &clear);
+    controller.observe(entered + TEXT_MODE_RECOVERY_MS / 2, &starved);
+    assert_eq!(
+        controller.observe(entered + 1 + TEXT_MODE_RECOVERY_MS, &clear),
+        None
+    );
+    assert!(controller.is_active());
+}
+
+#[test]
+fn test_recovery_is_capped() {
+    let mut controller = allowed_controller();
+    let clear = RenderWindow::new(2);
+    let mut now = 0;
+    for _ in 0..10 {
+        now = enter_text_mode(&mut controller, now) + 1;
+        controller.observe(now, &clear);
+        now += TEXT_MODE_RECOVERY_MAX_MS;
+        assert_eq!(
+            controller.observe(now, &clear),
+            Some(TextModeChange::Exited)
+        );
+        now += 1;
+    }
+}
+
+#[test]
+fn test_frames_coalesced_in_text_mode() {
+    let mut controller = allowed_controller();
+    controller.record_frame(0);
+    assert!(controller.frame_due(1));
+
+    let entered = enter_text_mode(&mut controller, 0);
+    controller.record_frame(entered);
+    assert!(!controller.frame_due(entered + TEXT_MODE_FRAME_INTERVAL_MS - 1));
+    assert!(controller.frame_due(entered + TEXT_MODE_FRAME_INTERVAL_MS));
+}
+
+#[test]
+fn test_disallowing_leaves_text_mode() {
+    let mut controller = allowed_controller();
+    enter_text_mode(&mut controller, 0);
+    controller.set_allowed(false);
+    assert!(!controller.is_active());
+    assert!(controller.frame_due(0));
+}
+
+#[test]
+fn test_text_mode_frames_are_monochrome() {
+    let mut styles = StyleTable::new();
+    let bold = styles.get_or_insert(&Style {
+        bold: true,
+        ..Default::default()
+    });
+    let mut store = FrameStore::new(4, 1);
+    store.update_row(0, |row| {
+        row.set_cell(
+            0,
+            Cell {
+                codepoint: 'a' as u32,
+                width: 1,
+                style_id: bold,
+            },
+        )
+    });
+    store.advance_state();
+
+    let mut state = ClientRenderState::new(2);
+    state.text_mode_mut().set_allowed(true);
+    *state.render_window_mut() = starved_window();
+    for _ in 0..TEXT_MODE_STARVED_FRAMES - 1 {
+        assert_eq!(state.observe_link(), None);
+    }
+    assert_eq!(state.observe_link(), Some(TextModeChange::Entered));
+
+    let snapshot = state.prepare_snapshot(store.current_frame(), 1, &mut styles);
+    assert_eq!(snapshot.styles.len(), 1);
+    assert_eq!(snapshot.styles[0].style_id, 0);
+    assert_eq!(snapshot.rows[0].codepoints[0], 'a' as u32);
+    assert!(snapshot.rows[0].style_ids.iter().all(|&id| id == 0));
+    assert!(!state.frame_due());
+}
@@ -0,0 +1,143 @@
+use crate::resume_token::ResumeToken;
+use crate::token_keys::TokenKeyring;
+
+const MASTER: [u8; 32] = [7u8; 32];
+
+fn token_for(client_id: u64) -> ResumeToken {
+    ResumeToken {
+        session_id: 1,
+        client_id,
+        last_applied_state_id: 10,
+        last_acked_input_seq: 5,
+        issued_at_ms: 1_000,
+    }
+}
+
+#[test]
+fn test_seal_open_roundtrip() {
+    let keys = TokenKeyring::new(MASTER);
+    let token = token_for(3);
+
+    let sealed = keys.seal(&token);
+    assert_eq!(sealed.len(), 133); // 52 byte key id + 81 byte sealed token
+    assert_eq!(keys.open(&sealed), Some(token));
+}
+
+#[test]
+fn test_sealed_tokens_are_opaque() {
+    let keys = TokenKeyring::new(MASTER);
+    let client_id = 0x0123_4567_89ab_cdefu64.to_le_bytes();
+    let token = token_for(u64::from_le_bytes(client_id));
+
+    let first = keys.seal(&token);
+    let second = keys.seal(&token);
+    assert!(!first.windows(8).any(|window| window == client_id));
+    // Fresh nonces, so two tokens of one client can't be linked by their key ids
+    assert_ne!(first[..52], second[..52]);
+}
+
+#[test]
+fn test_client_secrets_are_distinct() {
+    let keys = TokenKeyring::new(MASTER);
+    assert_ne!(keys.client_secret(1, 0), keys.client_secret(2, 0));
+    assert_ne!(keys.client_secret(1, 0), keys.client_secret(1, 1));
+    assert_ne!(
+        keys.client_secret(1, 0),
+        TokenKeyring::new([8u8; 32]).client_secret(1, 0)
+    );
+    // Deterministic, so tokens survive as long as the master secret does
+    assert_eq!(
+        keys.client_secret(1, 0),
+        TokenKeyring::new(MASTER).client_secret(1, 0)
+    );
+}
+
+#[test]
+fn test_token_does_not_validate_for_other_client() {
+    let keys = TokenKeyring::new(MASTER);
+    let sealed = keys.seal(&token_for(1));
+
+    // Relabel the key id as client 2
+    let mut relabeled = keys.seal_key_id(2, 0);
+    relabeled.extend_from_slice(&sealed[52..]);
+    assert!(keys.open(&relabeled).is_none());
+
+    // Tampering with the encrypted key id fails to open it
+    let mut tampered = sealed.clone();
+    tampered[30] ^= 1;
+    assert!(keys.open(&tampered).is_none());
+
+    // A token sealed with client 1's secret but claiming to be client 2's
+    let forged = ResumeToken {
+        client_id: 2,
+        ..token_for(1)
+    }
+    .seal(&keys.client_secret(1, 0));
+    let mut key_id = keys.seal_key_id(1, 0);
+    key_id.extend_from_slice(&forged);
+    assert!(keys.open(&key_id).is_none());
+}
+
+#[test]
+fn test_leaked_client_secret_cannot_forge_other_clients() {
+    let keys = TokenKeyring::new(MASTER);
+    let leaked = keys.client_secret(1, 0);
+
+    let forged = token_for(2).seal(&leaked);
+    let mut bytes = keys.seal_key_id(2, 0);
+    bytes.extend_from_slice(&forged);
+    assert!(keys.open(&bytes).is_none());
+}
+
+#[test]
+fn test_bump_epoch_revokes_all_tokens() {
+    let mut keys = TokenKeyring::new(MASTER);
+    let first = keys.seal(&token_for(1));
+    let second = keys.seal(&token_for(2));
+
+    assert_eq!(keys.bump_epoch(), 1);
+    assert!(keys.open(&first).is_none());
+    assert!(keys.open(&second).is_none());
+
+    // Relabeling an old token with the new epoch doesn't help
+    let mut relabeled = keys.seal_key_id(1, 1);
+    relabeled.extend_from_slice(&first[52..]);
+    assert!(keys.open(&relabeled).is_none());
+
+    let fresh = keys.seal(&token_for(1));
+    assert_eq!(keys.open(&fresh), Some(token_for(1)));
+}
+
+#[test]
+fn test_bump_client_epoch_revokes_only_that_client() {
+    let mut keys = TokenKeyring::new(MASTER);
+    let first = keys.seal(&token_for(1));
+    let second = keys.seal(&token_for(2));
+
+    assert_eq!(keys.bump_client_epoch(1), 1);
+    assert!(keys.open(&first).is_none());
+    assert!(keys.open(&second).is_some());
+    assert!(keys.open(&keys.seal(&token_for(1))).is_some());
+}
+
+#[test]
+fn test_session_bump_overtakes_client_epochs() {
+    let mut keys = TokenKeyring::new(MASTER);
+    keys.bump_client_epoch(1);
+    keys.bump_client_epoch(1);
+    let revoked = keys.seal(&token_for(1));
+
+    assert_eq!(keys.bump_epoch(), 3);
+    assert_eq!(keys.epoch_for(1), 3);
+    assert_eq!(keys.epoch_for(2), 3);
+    assert!(keys.open(&revoked).is_none());
+}
+
+#[test]
+fn test_truncated_and_empty_tokens_rejected() {
+    let keys = TokenKeyring::new(MASTER);
+    let sealed = keys.seal(&token_for(1));
+    for len in 0..sealed.len() {
+        assert!(keys.open(&sealed[..len]).is_none());
+    }
+}
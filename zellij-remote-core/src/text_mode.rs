@@ -0,0 +1,149 @@
+//! Text-only fallback for clients on very constrained links (satellite, 2G).
+//!
+//! While a client is in text mode its frames carry no styles and go out at most once
+//! per [`TEXT_MODE_FRAME_INTERVAL_MS`]. The server switches a client in when the render
+//! window keeps filling up on a link delivering less than
+//! [`TEXT_MODE_STARVED_BYTES_PER_MS`], and back out once frames have stopped backing
+//! up for a while. Clients opt in with `Capabilities.supports_text_mode`.
+
+use crate::backpressure::RenderWindow;
+use zellij_remote_protocol::{ScreenDelta, ScreenSnapshot, StyleDef};
+
+/// Delivery rate below which a full render window counts as starvation (~16 kbit/s)
+pub const TEXT_MODE_STARVED_BYTES_PER_MS: f64 = 2.0;
+/// Starved frames within `TEXT_MODE_STARVATION_WINDOW_MS` that switch a client to text mode
+pub const TEXT_MODE_STARVED_FRAMES: u32 = 3;
+pub const TEXT_MODE_STARVATION_WINDOW_MS: u64 = 10_000;
+/// How long frames must go out without backing up before styles come back; doubles
+/// each time the client falls back into text mode, up to the max
+pub const TEXT_MODE_RECOVERY_MS: u64 = 15_000;
+pub const TEXT_MODE_RECOVERY_MAX_MS: u64 = 120_000;
+/// Minimum gap between frames to a client in text mode
+pub const TEXT_MODE_FRAME_INTERVAL_MS: u64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextModeChange {
+    Entered,
+    Exited,
+}
+
+/// Per-client text mode state, fed with the render window each time a frame is ready.
+#[derive(Debug, Default)]
+pub struct TextModeController {
+    allowed: bool,
+    active: bool,
+    starved_frames: u32,
+    starvation_window_start: Option<u64>,
+    /// Start of the current run of frames that didn't back up, while active
+    clear_since: Option<u64>,
+    /// Times the client has been switched to text mode
+    entries: u32,
+    last_frame_ms: Option<u64>,
+}
+
+impl TextModeController {
+    /// Allow text mode for a client that advertised support. Disallowing it leaves text
+    /// mode immediately, without reporting a change.
+    pub fn set_allowed(&mut self, allowed: bool) {
+        self.allowed = allowed;
+        if !allowed {
+            self.active = false;
+            self.starved_frames = 0;
+            self.starvation_window_start = None;
+            self.clear_since = None;
+        }
+    }
+
+    pub fn is_allowed(&self) -> bool {
+        self.allowed
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Look at the link as a frame becomes ready. A frame is starved when the window is
+    /// already full (the link, not the session, is limiting) and the measured delivery
+    /// rate is below [`TEXT_MODE_STARVED_BYTES_PER_MS`].
+    pub fn observe(&mut self, now_ms: u64, window: &RenderWindow) -> Option<TextModeChange> {
+        if !self.allowed {
+            return None;
+        }
+        let backed_up = window.is_window_exhausted();
+        let slow = window
+            .throughput_bytes_per_ms()
+            .is_some_and(|rate| rate < TEXT_MODE_STARVED_BYTES_PER_MS);
+
+        if self.active {
+            if backed_up {
+                self.clear_since = None;
+                return None;
+            }
+            let since = *self.clear_since.get_or_insert(now_ms);
+            if now_ms.saturating_sub(since) < self.recovery_ms() {
+                return None;
+            }
+            self.active = false;
+            self.clear_since = None;
+            return Some(TextModeChange::Exited);
+        }
+
+        if !(backed_up && slow) {
+            return None;
+        }
+        let window_expired = self
+            .starvation_window_start
+            .is_none_or(|start| now_ms.saturating_sub(start) >= TEXT_MODE_STARVATION_WINDOW_MS);
+        if window_expired {
+            self.starved_frames = 0;
+            self.starvation_window_start = Some(now_ms);
+        }
+        self.starved_frames += 1;
+        if self.starved_frames < TEXT_MODE_STARVED_FRAMES {
+            return None;
+        }
+
+        self.active = true;
+        self.entries += 1;
+        self.starved_frames = 0;
+        self.starvation_window_start = None;
+        Some(TextModeChange::Entered)
+    }
+
+    /// Outside text mode frames are always due.
+    pub fn frame_due(&self, now_ms: u64) -> bool {
+        !self.active
+            || self
+                .last_frame_ms
+                .is_none_or(|last| now_ms.saturating_sub(last) >= TEXT_MODE_FRAME_INTERVAL_MS)
+    }
+
+    pub fn record_frame(&mut self, now_ms: u64) {
+        self.last_frame_ms = Some(now_ms);
+    }
+
+    fn recovery_ms(&self) -> u64 {
+        TEXT_MODE_RECOVERY_MS
+            .saturating_mul(1 << self.entries.saturating_sub(1).min(16))
+            .min(TEXT_MODE_RECOVERY_MAX_MS)
+    }
+}
+
+/// Point every cell at the default style and drop style definitions.
+pub fn strip_delta_styles(delta: &mut ScreenDelta) {
+    delta.styles_added.clear();
+    for patch in &mut delta.row_patches {
+        for run in &mut patch.runs {
+            run.style_ids.iter_mut().for_each(|id| *id = 0);
+        }
+    }
+}
+
+/// Like [`strip_delta_styles`]; the snapshot still resets the client's style table,
+/// leaving only the default style.
+pub fn strip_snapshot_styles(snapshot: &mut ScreenSnapshot) {
+    snapshot.styles.retain(|def: &StyleDef| def.style_id == 0);
+    for row in &mut snapshot.rows {
+        row.style_ids.iter_mut().for_each(|id| *id = 0);
+    }
+}
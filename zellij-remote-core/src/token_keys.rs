@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use rand::RngCore;
+use ring::hkdf::{Salt, HKDF_SHA256};
+
+use crate::resume_token::{
+    xchacha20_poly1305_open, xchacha20_poly1305_seal, ResumeToken, TAG_SIZE, XNONCE_SIZE,
+};
+
+const MASTER_SECRET_SIZE: usize = 32;
+const KEY_ID_PLAINTEXT_SIZE: usize = 12;
+/// `nonce (24) || sealed(client_id (8) || epoch (4)) || tag (16)`. The server needs the
+/// client id and epoch to derive the token key, so they travel with the token, but
+/// encrypted under a session-wide key with a fresh nonce: clients and observers can't
+/// read them or link two tokens of the same client.
+const KEY_ID_SIZE: usize = XNONCE_SIZE + KEY_ID_PLAINTEXT_SIZE + TAG_SIZE;
+const KEY_SALT: &[u8] = b"zellij-remote token keys v1";
+const KEY_INFO: &[u8] = b"zellij-remote resume token";
+const KEY_ID_INFO: &[u8] = b"zellij-remote token key id";
+
+/// Resume token keys for one session.
+///
+/// Each client's tokens are sealed under its own secret, derived with HKDF-SHA256 from
+/// the session master secret, the client id and an epoch, so a leaked client secret
+/// can't forge or read another client's tokens. Bumping an epoch revokes every token
+/// issued under the old one.
+pub struct TokenKeyring {
+    master: [u8; MASTER_SECRET_SIZE],
+    epoch: u32,
+    /// Clients revoked individually since the last session-wide bump
+    client_epochs: HashMap<u64, u32>,
+}
+
+impl TokenKeyring {
+    pub fn new(master: [u8; MASTER_SECRET_SIZE]) -> Self {
+        Self {
+            master,
+            epoch: 0,
+            client_epochs: HashMap::new(),
+        }
+    }
+
+    pub fn generate() -> Self {
+        let mut master = [0u8; MASTER_SECRET_SIZE];
+        rand::thread_rng().fill_bytes(&mut master);
+        Self::new(master)
+    }
+
+    /// A keyring saved before a restart, with the epochs it had reached.
+    pub fn restore(
+        master: [u8; MASTER_SECRET_SIZE],
+        epoch: u32,
+        client_epochs: HashMap<u64, u32>,
+    ) -> Self {
+        Self {
+            master,
+            epoch,
+            client_epochs,
+        }
+    }
+
+    /// What [`TokenKeyring::restore`] needs: master secret, session epoch and the
+    /// epochs of clients revoked individually.
+    pub(crate) fn parts(&self) -> (&[u8; MASTER_SECRET_SIZE], u32, &HashMap<u64, u32>) {
+        (&self.master, self.epoch, &self.client_epochs)
+    }
+
+    /// Epoch the client's tokens are currently issued under.
+    pub fn epoch_for(&self, client_id: u64) -> u32 {
+        self.client_epochs
+            .get(&client_id)
+            .copied()
+            .unwrap_or(self.epoch)
+    }
+
+    /// Revoke every outstanding token. Returns the new session epoch.
+    pub fn bump_epoch(&mut self) -> u32 {
+        // Clients revoked individually may already be ahead of the session epoch
+        let highest = self
+            .client_epochs
+            .values()
+            .copied()
+            .fold(self.epoch, u32::max);
+        self.epoch = highest.wrapping_add(1);
+        self.client_epochs.clear();
+        self.epoch
+    }
+
+    /// Revoke one client's outstanding tokens. Returns the client's new epoch.
+    pub fn bump_client_epoch(&mut self, client_id: u64) -> u32 {
+        let epoch = self.epoch_for(client_id).wrapping_add(1);
+        self.client_epochs.insert(client_id, epoch);
+        epoch
+    }
+
+    pub fn client_secret(&self, client_id: u64, epoch: u32) -> [u8; 32] {
+        self.derive(&[KEY_INFO, &client_id.to_le_bytes(), &epoch.to_le_bytes()])
+    }
+
+    fn derive(&self, info: &[&[u8]]) -> [u8; 32] {
+        let prk = Salt::new(HKDF_SHA256, KEY_SALT).extract(&self.master);
+        let mut secret = [0u8; 32];
+        prk.expand(info, HKDF_SHA256)
+            .and_then(|okm| okm.fill(&mut secret))
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        secret
+    }
+
+    /// Seal `token` under its client's current secret, prefixed with the encrypted
+    /// key id.
+    pub fn seal(&self, token: &ResumeToken) -> Vec<u8> {
+        let epoch = self.epoch_for(token.client_id);
+        let sealed = token.seal(&self.client_secret(token.client_id, epoch));
+
+        let mut result = self.seal_key_id(token.client_id, epoch);
+        result.reserve(sealed.len());
+        result.extend_from_slice(&sealed);
+        result
+    }
+
+    /// The encrypted `client_id || epoch` a sealed token starts with.
+    pub(crate) fn seal_key_id(&self, client_id: u64, epoch: u32) -> Vec<u8> {
+        let mut nonce = [0u8; XNONCE_SIZE];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let mut key_id = Vec::with_capacity(KEY_ID_PLAINTEXT_SIZE + TAG_SIZE);
+        key_id.extend_from_slice(&client_id.to_le_bytes());
+        key_id.extend_from_slice(&epoch.to_le_bytes());
+        xchacha20_poly1305_seal(&self.derive(&[KEY_ID_INFO]), &nonce, &[], &mut key_id);
+
+        let mut result = Vec::with_capacity(KEY_ID_SIZE);
+        result.extend_from_slice(&nonce);
+        result.extend_from_slice(&key_id);
+        result
+    }
+
+    /// Open a token sealed by [`TokenKeyring::seal`] under the client's current epoch.
+    pub fn open(&self, bytes: &[u8]) -> Option<ResumeToken> {
+        if bytes.len() < KEY_ID_SIZE {
+            return None;
+        }
+        let (key_id, sealed) = bytes.split_at(KEY_ID_SIZE);
+        let (nonce, key_id) = key_id.split_at(XNONCE_SIZE);
+        let mut key_id = key_id.to_vec();
+        xchacha20_poly1305_open(
+            &self.derive(&[KEY_ID_INFO]),
+            nonce.try_into().ok()?,
+            &[],
+            &mut key_id,
+        )?;
+        let client_id = u64::from_le_bytes(key_id[..8].try_into().ok()?);
+        let epoch = u32::from_le_bytes(key_id[8..].try_into().ok()?);
+        if epoch != self.epoch_for(client_id) {
+            return None;
+        }
+
+        let token = ResumeToken::open(sealed, &self.client_secret(client_id, epoch))?;
+        (token.client_id == client_id).then_some(token)
+    }
+}
+
+impl std::fmt::Debug for TokenKeyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenKeyring")
+            .field("epoch", &self.epoch)
+            .field("client_epochs", &self.client_epochs)
+            .finish_non_exhaustive()
+    }
+}
@@ -1,8 +1,18 @@
 use std::io::Result;
 
 fn main() -> Result<()> {
-    // prost-build outputs to OUT_DIR, file named after proto package
-    // For package "zellij.remote.v1", generates "zellij.remote.v1.rs"
-    prost_build::compile_protos(&["proto/zellij_remote.proto"], &["proto/"])?;
+    // prost-build outputs to OUT_DIR, one file per proto package: "zellij.remote.control.v1"
+    // generates "zellij.remote.control.v1.rs". The render package imports the control one,
+    // so compiling it generates both.
+    let proto = if std::env::var_os("CARGO_FEATURE_RENDER").is_some() {
+        "proto/zellij_remote_render.proto"
+    } else {
+        "proto/zellij_remote_control.proto"
+    };
+    let mut config = prost_build::Config::new();
+    if std::env::var_os("CARGO_FEATURE_SERDE").is_some() {
+        config.type_attribute(".", "#[derive(serde::Serialize)]");
+    }
+    config.compile_protos(&[proto], &["proto/"])?;
     Ok(())
 }
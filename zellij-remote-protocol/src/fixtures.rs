@@ -0,0 +1,382 @@
+//! Rendering fixtures for client self-tests.
+//!
+//! Each fixture is a short sequence of snapshots and deltas exercising one area of
+//! rendering: every style attribute and color kind, wide characters, rows as wide as
+//! a server allows, line sizes. A server in conformance echo mode replays them to a
+//! client so a third-party implementation can check its output by eye or against its
+//! own golden images, without a live session.
+
+use crate::{
+    color, stream_envelope, CellRun, Color, CursorShape, CursorState, DefaultColor, DisplaySize,
+    LineSize, Rgb, RowData, RowPatch, ScreenDelta, ScreenSnapshot, Style, StyleDef, UnderlineStyle,
+};
+
+/// Widest row a server accepts by default, for the max-size rows fixture
+pub const MAX_ROW_COLS: u32 = 1000;
+
+pub struct Fixture {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The fixture's messages, its first snapshot at `first_state_id` and every later
+    /// message one state past the one before
+    pub build: fn(first_state_id: u64) -> Vec<stream_envelope::Msg>,
+}
+
+pub const FIXTURES: &[Fixture] = &[
+    Fixture {
+        name: "styles",
+        description: "Every attribute, underline style, ANSI, 256-color and RGB color, \
+                      one group per row",
+        build: styles_fixture,
+    },
+    Fixture {
+        name: "wide_chars",
+        description: "CJK and emoji, a wide character in the last column, styled wide \
+                      characters, then a delta replacing a wide character with two \
+                      narrow ones",
+        build: wide_chars_fixture,
+    },
+    Fixture {
+        name: "max_row",
+        description: "Rows MAX_ROW_COLS wide, of narrow and of wide characters",
+        build: max_row_fixture,
+    },
+    Fixture {
+        name: "line_sizes",
+        description: "Double-width and double-height rows next to a single one",
+        build: line_sizes_fixture,
+    },
+];
+
+pub fn fixture(name: &str) -> Option<&'static Fixture> {
+    FIXTURES.iter().find(|fixture| fixture.name == name)
+}
+
+/// Styles in id order, id 0 being the default
+struct StyleBook {
+    styles: Vec<Style>,
+}
+
+impl StyleBook {
+    fn new() -> Self {
+        Self {
+            styles: vec![Style::default()],
+        }
+    }
+
+    fn id(&mut self, style: Style) -> u32 {
+        match self.styles.iter().position(|known| *known == style) {
+            Some(id) => id as u32,
+            None => {
+                self.styles.push(style);
+                self.styles.len() as u32 - 1
+            },
+        }
+    }
+
+    fn defs(&self) -> Vec<StyleDef> {
+        self.styles
+            .iter()
+            .enumerate()
+            .map(|(id, style)| StyleDef {
+                style_id: id as u32,
+                style: Some(style.clone()),
+            })
+            .collect()
+    }
+}
+
+fn ansi(index: u32) -> Option<Color> {
+    Some(Color {
+        value: Some(color::Value::Ansi256(index)),
+    })
+}
+
+fn rgb(r: u32, g: u32, b: u32) -> Option<Color> {
+    Some(Color {
+        value: Some(color::Value::Rgb(Rgb { r, g, b })),
+    })
+}
+
+fn default_color() -> Option<Color> {
+    Some(Color {
+        value: Some(color::Value::DefaultColor(DefaultColor {})),
+    })
+}
+
+fn new_row(row: u32) -> RowData {
+    RowData {
+        row,
+        ..Default::default()
+    }
+}
+
+fn push_text(row: &mut RowData, text: &str, style_id: u32) {
+    for c in text.chars() {
+        row.codepoints.push(c as u32);
+        row.widths.push(1);
+        row.style_ids.push(style_id);
+    }
+}
+
+/// A wide character and its continuation cell, both in `style_id`
+fn push_wide(row: &mut RowData, c: char, style_id: u32) {
+    row.codepoints.extend([c as u32, 0]);
+    row.widths.extend([2, 0]);
+    row.style_ids.extend([style_id, style_id]);
+}
+
+/// Blank the rest of the row so every row is exactly `cols` cells
+fn pad(row: &mut RowData, cols: u32) {
+    while (row.codepoints.len() as u32) < cols {
+        push_text(row, " ", 0);
+    }
+}
+
+fn snapshot(
+    state_id: u64,
+    cols: u32,
+    rows: u32,
+    book: &StyleBook,
+    mut row_data: Vec<RowData>,
+) -> stream_envelope::Msg {
+    let mut present: Vec<u32> = row_data.iter().map(|row| row.row).collect();
+    for row in 0..rows {
+        if !present.contains(&row) {
+            row_data.push(new_row(row));
+            present.push(row);
+        }
+    }
+    row_data.sort_by_key(|row| row.row);
+    for row in row_data.iter_mut() {
+        pad(row, cols);
+    }
+    stream_envelope::Msg::ScreenSnapshot(ScreenSnapshot {
+        state_id,
+        size: Some(DisplaySize { cols, rows }),
+        style_table_reset: true,
+        styles: book.defs(),
+        rows: row_data,
+        cursor: Some(CursorState {
+            row: 0,
+            col: 0,
+            visible: false,
+            blink: false,
+            shape: CursorShape::Block as i32,
+            color: None,
+        }),
+        ..Default::default()
+    })
+}
+
+fn styles_fixture(first_state_id: u64) -> Vec<stream_envelope::Msg> {
+    let mut book = StyleBook::new();
+    let mut rows = Vec::new();
+
+    let attributes: [(&str, fn(&mut Style)); 8] = [
+        ("bold", |s| s.bold = true),
+        ("dim", |s| s.dim = true),
+        ("italic", |s| s.italic = true),
+        ("reverse", |s| s.reverse = true),
+        ("hidden", |s| s.hidden = true),
+        ("strike", |s| s.strike = true),
+        ("blink", |s| s.blink_slow = true),
+        ("blink-fast", |s| s.blink_fast = true),
+    ];
+    let mut row = new_row(0);
+    for (label, set) in attributes {
+        let mut style = Style::default();
+        set(&mut style);
+        push_text(&mut row, label, book.id(style));
+        push_text(&mut row, " ", 0);
+    }
+    rows.push(row);
+
+    let underlines = [
+        ("single", UnderlineStyle::Single),
+        ("double", UnderlineStyle::Double),
+        ("dotted", UnderlineStyle::Dotted),
+        ("dashed", UnderlineStyle::Dashed),
+        ("curly", UnderlineStyle::Curly),
+    ];
+    let mut row = new_row(1);
+    for (label, underline) in underlines {
+        let mut style = Style::default();
+        style.set_underline(underline);
+        push_text(&mut row, label, book.id(style));
+        push_text(&mut row, " ", 0);
+    }
+    let mut colored = Style {
+        underline_color: rgb(255, 0, 0),
+        ..Default::default()
+    };
+    colored.set_underline(UnderlineStyle::Curly);
+    push_text(&mut row, "red-curly", book.id(colored));
+    rows.push(row);
+
+    // The 16 ANSI colors as foreground, then as background
+    let mut fg_row = new_row(2);
+    let mut bg_row = new_row(3);
+    for index in 0..16 {
+        let label = format!("{:>3}", index);
+        let fg = book.id(Style {
+            fg: ansi(index),
+            ..Default::default()
+        });
+        let bg = book.id(Style {
+            bg: ansi(index),
+            ..Default::default()
+        });
+        push_text(&mut fg_row, &label, fg);
+        push_text(&mut bg_row, &label, bg);
+    }
+    rows.push(fg_row);
+    rows.push(bg_row);
+
+    // The 240 remaining 256-color entries, one background cell each
+    for (offset, chunk) in (16..256).collect::<Vec<u32>>().chunks(80).enumerate() {
+        let mut row = new_row(4 + offset as u32);
+        for &index in chunk {
+            let id = book.id(Style {
+                bg: ansi(index),
+                ..Default::default()
+            });
+            push_text(&mut row, " ", id);
+        }
+        rows.push(row);
+    }
+
+    let mut row = new_row(7);
+    for col in 0..80 {
+        let id = book.id(Style {
+            bg: rgb(col * 3, 255 - col * 3, 128),
+            ..Default::default()
+        });
+        push_text(&mut row, " ", id);
+    }
+    rows.push(row);
+
+    // Explicit default colors, and everything at once
+    let mut row = new_row(8);
+    let explicit_default = book.id(Style {
+        fg: default_color(),
+        bg: default_color(),
+        ..Default::default()
+    });
+    push_text(&mut row, "default ", explicit_default);
+    let mut combined = Style {
+        fg: rgb(255, 200, 0),
+        bg: ansi(4),
+        bold: true,
+        italic: true,
+        strike: true,
+        underline_color: ansi(9),
+        ..Default::default()
+    };
+    combined.set_underline(UnderlineStyle::Double);
+    push_text(&mut row, "combined", book.id(combined));
+    rows.push(row);
+
+    vec![snapshot(first_state_id, 80, 10, &book, rows)]
+}
+
+fn wide_chars_fixture(first_state_id: u64) -> Vec<stream_envelope::Msg> {
+    let mut book = StyleBook::new();
+    let highlighted = book.id(Style {
+        fg: ansi(0),
+        bg: ansi(11),
+        ..Default::default()
+    });
+
+    let mut cjk = new_row(0);
+    for c in "中文字符".chars() {
+        push_wide(&mut cjk, c, 0);
+    }
+    let mut emoji = new_row(1);
+    for c in ['😀', '👍', '🚀'] {
+        push_wide(&mut emoji, c, 0);
+    }
+    let mut last_column = new_row(2);
+    push_text(&mut last_column, &" ".repeat(78), 0);
+    push_wide(&mut last_column, '末', 0);
+    let mut styled = new_row(3);
+    push_text(&mut styled, "a", 0);
+    push_wide(&mut styled, '宽', highlighted);
+    push_text(&mut styled, "b", highlighted);
+    push_wide(&mut styled, '字', 0);
+
+    let mut messages = vec![snapshot(
+        first_state_id,
+        80,
+        4,
+        &book,
+        vec![cjk, emoji, last_column, styled],
+    )];
+    messages.push(stream_envelope::Msg::ScreenDeltaStream(ScreenDelta {
+        base_state_id: first_state_id,
+        state_id: first_state_id + 1,
+        row_patches: vec![RowPatch {
+            row: 0,
+            runs: vec![CellRun {
+                col_start: 0,
+                codepoints: vec!['a' as u32, 'b' as u32],
+                widths: vec![1, 1],
+                style_ids: vec![0, 0],
+            }],
+            ..Default::default()
+        }],
+        ..Default::default()
+    }));
+    messages
+}
+
+fn max_row_fixture(first_state_id: u64) -> Vec<stream_envelope::Msg> {
+    let mut book = StyleBook::new();
+    let marker = book.id(Style {
+        fg: ansi(2),
+        bold: true,
+        ..Default::default()
+    });
+
+    let mut narrow = new_row(0);
+    for col in 0..MAX_ROW_COLS {
+        // Every tenth column stands out, so a clipped or shifted row is easy to spot
+        let style_id = if col % 10 == 0 { marker } else { 0 };
+        let digit = char::from_digit(col % 10, 10).unwrap_or('?');
+        push_text(&mut narrow, &digit.to_string(), style_id);
+    }
+    let mut wide = new_row(1);
+    for _ in 0..MAX_ROW_COLS / 2 {
+        push_wide(&mut wide, '宽', 0);
+    }
+
+    vec![snapshot(
+        first_state_id,
+        MAX_ROW_COLS,
+        2,
+        &book,
+        vec![narrow, wide],
+    )]
+}
+
+fn line_sizes_fixture(first_state_id: u64) -> Vec<stream_envelope::Msg> {
+    let book = StyleBook::new();
+    let sizes = [
+        LineSize::Unspecified,
+        LineSize::DoubleWidth,
+        LineSize::DoubleHeightTop,
+        LineSize::DoubleHeightBottom,
+    ];
+    let rows = sizes
+        .iter()
+        .enumerate()
+        .map(|(index, line_size)| {
+            let mut row = new_row(index as u32);
+            push_text(&mut row, "Line size", 0);
+            row.set_line_size(*line_size);
+            row
+        })
+        .collect();
+
+    vec![snapshot(first_state_id, 40, 4, &book, rows)]
+}
@@ -1,15 +1,36 @@
 // Include generated code from OUT_DIR (set by cargo during build)
-// prost generates filename based on proto package name
+// prost generates filename based on proto package name. Modules mirror the packages
+// below `zellij.remote`, which is where prost's cross-package paths start from.
 pub mod proto {
-    include!(concat!(env!("OUT_DIR"), "/zellij.remote.v1.rs"));
+    #[cfg(feature = "control")]
+    pub mod control {
+        pub mod v1 {
+            include!(concat!(env!("OUT_DIR"), "/zellij.remote.control.v1.rs"));
+        }
+    }
+
+    #[cfg(feature = "render")]
+    pub mod render {
+        pub mod v1 {
+            include!(concat!(env!("OUT_DIR"), "/zellij.remote.render.v1.rs"));
+        }
+    }
 }
 
-pub use proto::*;
+#[cfg(feature = "control")]
+pub use proto::control::v1::*;
+#[cfg(feature = "render")]
+pub use proto::render::v1::*;
+
+#[cfg(feature = "render")]
+pub mod fixtures;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "render"))]
 mod tests;
 
 pub const ZRP_VERSION_MAJOR: u32 = 1;
 pub const ZRP_VERSION_MINOR: u32 = 0;
 pub const DEFAULT_MAX_DATAGRAM_BYTES: u32 = 1200;
 pub const DEFAULT_RENDER_WINDOW: u32 = 4;
+pub const DEFAULT_MIN_RENDER_WINDOW: u32 = 2;
+pub const DEFAULT_MAX_RENDER_WINDOW: u32 = 32;
@@ -1,6 +1,6 @@
 use prost::Message;
 
-use crate::proto::*;
+use crate::*;
 
 // =============================================================================
 // HANDSHAKE ROUNDTRIPS
@@ -26,6 +26,18 @@ fn test_capabilities_roundtrip() {
         supports_images: true,
         supports_clipboard: true,
         supports_hyperlinks: false,
+        supports_line_size: false,
+        supports_text_mode: false,
+        supports_frame_hash: false,
+        supports_style_retention: false,
+        supports_priority_lanes: false,
+        supports_row_checksums: false,
+        supports_push_streams: false,
+        supports_bell: false,
+        supports_lease_status: false,
+        max_message_bytes: 0,
+        supports_pane_layout: false,
+        supports_keybinding_info: false,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -44,6 +56,18 @@ fn test_capabilities_all_false() {
         supports_images: false,
         supports_clipboard: false,
         supports_hyperlinks: false,
+        supports_line_size: false,
+        supports_text_mode: false,
+        supports_frame_hash: false,
+        supports_style_retention: false,
+        supports_priority_lanes: false,
+        supports_row_checksums: false,
+        supports_push_streams: false,
+        supports_bell: false,
+        supports_lease_status: false,
+        max_message_bytes: 0,
+        supports_pane_layout: false,
+        supports_keybinding_info: false,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -62,6 +86,18 @@ fn test_capabilities_all_true() {
         supports_images: true,
         supports_clipboard: true,
         supports_hyperlinks: true,
+        supports_line_size: true,
+        supports_text_mode: true,
+        supports_frame_hash: false,
+        supports_style_retention: true,
+        supports_priority_lanes: false,
+        supports_row_checksums: false,
+        supports_push_streams: false,
+        supports_bell: false,
+        supports_lease_status: false,
+        max_message_bytes: 0,
+        supports_pane_layout: false,
+        supports_keybinding_info: false,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -82,10 +118,53 @@ fn test_client_hello_roundtrip() {
             supports_images: false,
             supports_clipboard: true,
             supports_hyperlinks: false,
+            supports_line_size: false,
+            supports_text_mode: false,
+            supports_frame_hash: false,
+            supports_style_retention: false,
+            supports_priority_lanes: false,
+            supports_row_checksums: false,
+            supports_push_streams: false,
+            supports_bell: false,
+            supports_lease_status: false,
+            max_message_bytes: 0,
+            supports_pane_layout: false,
+            supports_keybinding_info: false,
         }),
         client_name: "ios".to_string(),
         bearer_token: vec![0x01, 0x02, 0x03, 0x04],
         resume_token: vec![0xAA, 0xBB],
+        min_render_window: 2,
+        max_render_window: 16,
+        supported_codecs: vec![],
+        debug_frame_info: false,
+        locale: Some(ClientLocale {
+            timezone: "Europe/Berlin".to_string(),
+            locale: "de-DE".to_string(),
+            utc_offset_minutes: 120,
+            has_utc_offset: true,
+        }),
+        color_transform: Some(ColorTransform {
+            force_dark_background: true,
+            high_contrast: false,
+            protanopia_safe: true,
+        }),
+        extensions: vec![Extension {
+            name: "x-compression-trial".to_string(),
+            payload: vec![0x01],
+        }],
+        terminal_answers: Some(TerminalAnswers {
+            device_attributes: vec![22, 52],
+            termcap: [("TN", "xterm-256color"), ("RGB", "8")]
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }),
+        flash_guard: Some(FlashGuardSettings {
+            disabled: false,
+            max_flashes_per_second: 2,
+            min_update_interval_ms: 1500,
+        }),
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -101,6 +180,15 @@ fn test_client_hello_empty_fields() {
         client_name: String::new(),
         bearer_token: vec![],
         resume_token: vec![],
+        min_render_window: 0,
+        max_render_window: 0,
+        supported_codecs: vec![],
+        debug_frame_info: false,
+        locale: None,
+        color_transform: None,
+        extensions: vec![],
+        terminal_answers: None,
+        flash_guard: None,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -121,6 +209,18 @@ fn test_server_hello_roundtrip() {
             supports_images: false,
             supports_clipboard: false,
             supports_hyperlinks: false,
+            supports_line_size: false,
+            supports_text_mode: false,
+            supports_frame_hash: false,
+            supports_style_retention: false,
+            supports_priority_lanes: false,
+            supports_row_checksums: false,
+            supports_push_streams: false,
+            supports_bell: false,
+            supports_lease_status: false,
+            max_message_bytes: 0,
+            supports_pane_layout: false,
+            supports_keybinding_info: false,
         }),
         client_id: 12345,
         session_name: "my-session".to_string(),
@@ -132,11 +232,30 @@ fn test_server_hello_roundtrip() {
             current_size: Some(DisplaySize { cols: 80, rows: 24 }),
             remaining_ms: 30000,
             duration_ms: 60000,
+            owner_name: String::new(),
         }),
         resume_token: vec![0x11, 0x22, 0x33],
         snapshot_interval_ms: 5000,
         max_inflight_inputs: 16,
         render_window: 4,
+        min_render_window: 2,
+        max_render_window: 16,
+        keepalive_interval_ms: 15000,
+        alternate_endpoints: vec![
+            Endpoint {
+                host: "192.168.1.5".to_string(),
+                port: 4433,
+            },
+            Endpoint {
+                host: "fd7a:115c:a1e0::1".to_string(),
+                port: 4433,
+            },
+        ],
+        compression: None,
+        extensions: vec![Extension {
+            name: "x-compression-trial".to_string(),
+            payload: vec![],
+        }],
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -151,6 +270,7 @@ fn test_server_hello_all_session_states() {
         SessionState::Running,
         SessionState::Created,
         SessionState::Resurrected,
+        SessionState::Degraded,
     ] {
         let original = ServerHello {
             negotiated_version: None,
@@ -163,6 +283,12 @@ fn test_server_hello_all_session_states() {
             snapshot_interval_ms: 0,
             max_inflight_inputs: 0,
             render_window: 0,
+            min_render_window: 0,
+            max_render_window: 0,
+            keepalive_interval_ms: 0,
+            alternate_endpoints: vec![],
+            compression: None,
+            extensions: vec![],
         };
         let mut buf = Vec::new();
         original.encode(&mut buf).unwrap();
@@ -230,6 +356,7 @@ fn test_attach_response_roundtrip() {
             current_size: Some(DisplaySize { cols: 80, rows: 24 }),
             remaining_ms: 10000,
             duration_ms: 30000,
+            owner_name: String::new(),
         }),
         current_state_id: 999,
         will_send_snapshot: true,
@@ -271,6 +398,7 @@ fn test_controller_lease_roundtrip() {
         }),
         remaining_ms: u32::MAX,
         duration_ms: u32::MAX,
+        owner_name: "Alice's iPad".to_string(),
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -287,6 +415,7 @@ fn test_request_control_roundtrip() {
             rows: 50,
         }),
         force: true,
+        max_duration_ms: 60_000,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -304,6 +433,7 @@ fn test_grant_control_roundtrip() {
             current_size: Some(DisplaySize { cols: 80, rows: 24 }),
             remaining_ms: 5000,
             duration_ms: 10000,
+            owner_name: String::new(),
         }),
     };
     let mut buf = Vec::new();
@@ -323,6 +453,7 @@ fn test_deny_control_roundtrip() {
             current_size: Some(DisplaySize { cols: 80, rows: 24 }),
             remaining_ms: 1000,
             duration_ms: 30000,
+            owner_name: String::new(),
         }),
     };
     let mut buf = Vec::new();
@@ -372,6 +503,24 @@ fn test_lease_revoked_roundtrip() {
     let original = LeaseRevoked {
         lease_id: 42,
         reason: "timeout".to_string(),
+        takeover: None,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = LeaseRevoked::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_lease_revoked_takeover_roundtrip() {
+    let original = LeaseRevoked {
+        lease_id: 42,
+        reason: "takeover".to_string(),
+        takeover: Some(Takeover {
+            new_owner_client_id: 7,
+            new_owner_name: "ipad".to_string(),
+            grace_ms: 2000,
+        }),
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -437,6 +586,13 @@ fn test_key_event_all_special_keys() {
         SpecialKey::F10,
         SpecialKey::F11,
         SpecialKey::F12,
+        SpecialKey::F13,
+        SpecialKey::F24,
+        SpecialKey::Kp0,
+        SpecialKey::Kp9,
+        SpecialKey::KpEnter,
+        SpecialKey::Menu,
+        SpecialKey::PrintScreen,
     ];
     for key in special_keys {
         let original = KeyEvent {
@@ -559,6 +715,27 @@ fn test_input_ack_roundtrip() {
     assert_eq!(original, decoded);
 }
 
+#[test]
+fn test_input_batch_roundtrip() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::InputBatch(InputBatch {
+            client_time_ms: 4321,
+            events: (7..10)
+                .map(|seq| InputEvent {
+                    input_seq: seq,
+                    client_time_ms: 0,
+                    payload: Some(input_event::Payload::TextUtf8(b"a".to_vec())),
+                })
+                .collect(),
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
 // =============================================================================
 // RENDER ROUNDTRIPS
 // =============================================================================
@@ -706,6 +883,13 @@ fn test_cursor_state_roundtrip() {
         visible: true,
         blink: true,
         shape: CursorShape::Beam as i32,
+        color: Some(Color {
+            value: Some(color::Value::Rgb(Rgb {
+                r: 255,
+                g: 128,
+                b: 0,
+            })),
+        }),
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -727,6 +911,7 @@ fn test_cursor_state_all_shapes() {
             visible: false,
             blink: false,
             shape: shape as i32,
+            color: None,
         };
         let mut buf = Vec::new();
         original.encode(&mut buf).unwrap();
@@ -742,6 +927,8 @@ fn test_row_data_roundtrip() {
         codepoints: vec!['H' as u32, 'e' as u32, 'l' as u32, 'l' as u32, 'o' as u32],
         widths: vec![1, 1, 1, 1, 1],
         style_ids: vec![0, 0, 1, 1, 0],
+        line_size: 0,
+        col_start: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -749,6 +936,35 @@ fn test_row_data_roundtrip() {
     assert_eq!(original, decoded);
 }
 
+#[test]
+fn test_row_line_size_roundtrip() {
+    let row = RowData {
+        row: 2,
+        codepoints: vec!['W' as u32],
+        widths: vec![1],
+        style_ids: vec![0],
+        line_size: LineSize::DoubleHeightTop as i32,
+        col_start: 0,
+    };
+    let mut buf = Vec::new();
+    row.encode(&mut buf).unwrap();
+    assert_eq!(RowData::decode(&buf[..]).unwrap(), row);
+
+    let patch = RowPatch {
+        row: 2,
+        runs: vec![],
+        line_size: LineSize::DoubleWidth as i32,
+        echo: false,
+        row_crc32: 0,
+    };
+    let mut buf = Vec::new();
+    patch.encode(&mut buf).unwrap();
+    assert_eq!(
+        RowPatch::decode(&buf[..]).unwrap().line_size(),
+        LineSize::DoubleWidth
+    );
+}
+
 #[test]
 fn test_row_data_large_vectors() {
     let size = 1000;
@@ -757,6 +973,8 @@ fn test_row_data_large_vectors() {
         codepoints: (0..size).map(|i| ('A' as u32) + (i % 26)).collect(),
         widths: vec![1; size as usize],
         style_ids: (0..size).collect(),
+        line_size: 0,
+        col_start: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -771,6 +989,8 @@ fn test_row_data_empty_vectors() {
         codepoints: vec![],
         widths: vec![],
         style_ids: vec![],
+        line_size: 0,
+        col_start: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -810,10 +1030,50 @@ fn test_row_patch_roundtrip() {
                 style_ids: vec![2],
             },
         ],
+        line_size: 0,
+        echo: false,
+        row_crc32: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = RowPatch::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_row_patch_echo_roundtrip() {
+    let original = RowPatch {
+        row: 7,
+        runs: vec![CellRun {
+            col_start: 4,
+            codepoints: vec!['l' as u32, 's' as u32],
+            widths: vec![1, 1],
+            style_ids: vec![0, 0],
+        }],
+        line_size: 0,
+        echo: true,
+        row_crc32: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
     let decoded = RowPatch::decode(&buf[..]).unwrap();
+    assert!(decoded.echo);
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_row_patch_crc32_roundtrip() {
+    let original = RowPatch {
+        row: 2,
+        runs: vec![],
+        line_size: 0,
+        echo: false,
+        row_crc32: 0xCBF4_3926,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = RowPatch::decode(&buf[..]).unwrap();
+    assert_eq!(decoded.row_crc32, 0xCBF4_3926);
     assert_eq!(original, decoded);
 }
 
@@ -849,6 +1109,9 @@ fn test_screen_delta_roundtrip() {
                 widths: vec![1],
                 style_ids: vec![5],
             }],
+            line_size: 0,
+            echo: false,
+            row_crc32: 0,
         }],
         cursor: Some(CursorState {
             row: 0,
@@ -856,8 +1119,16 @@ fn test_screen_delta_roundtrip() {
             visible: true,
             blink: false,
             shape: CursorShape::Block as i32,
+            color: None,
         }),
         delivered_input_watermark: 50,
+        server_time_ms: 1_767_225_600_123,
+        frame_sequence: 42,
+        frame_hash: 0,
+        chunk_index: 0,
+        chunk_count: 0,
+        echo_suppressed: false,
+        debug_info: None,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -874,6 +1145,13 @@ fn test_screen_delta_empty() {
         row_patches: vec![],
         cursor: None,
         delivered_input_watermark: 0,
+        server_time_ms: 0,
+        frame_sequence: 0,
+        frame_hash: 0,
+        chunk_index: 0,
+        chunk_count: 0,
+        echo_suppressed: false,
+        debug_info: None,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -913,6 +1191,8 @@ fn test_screen_snapshot_roundtrip() {
             codepoints: vec![' ' as u32; 80],
             widths: vec![1; 80],
             style_ids: vec![0; 80],
+            line_size: 0,
+            col_start: 0,
         }],
         cursor: Some(CursorState {
             row: 0,
@@ -920,8 +1200,24 @@ fn test_screen_snapshot_roundtrip() {
             visible: true,
             blink: true,
             shape: CursorShape::Block as i32,
+            color: None,
         }),
         delivered_input_watermark: 100,
+        server_time_ms: 1_767_225_600_456,
+        frame_sequence: 7,
+        frame_hash: 0,
+        size_changed: Some(SizeChanged {
+            old_size: Some(DisplaySize {
+                cols: 100,
+                rows: 30,
+            }),
+            new_size: Some(DisplaySize { cols: 80, rows: 24 }),
+            reflowed: true,
+        }),
+        chunk_index: 0,
+        chunk_count: 0,
+        echo_suppressed: false,
+        debug_info: None,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -967,6 +1263,8 @@ fn test_screen_snapshot_large() {
                 codepoints: vec!['.' as u32; cols as usize],
                 widths: vec![1; cols as usize],
                 style_ids: vec![0; cols as usize],
+                line_size: 0,
+                col_start: 0,
             })
             .collect(),
         cursor: Some(CursorState {
@@ -975,8 +1273,17 @@ fn test_screen_snapshot_large() {
             visible: true,
             blink: false,
             shape: CursorShape::Underline as i32,
+            color: None,
         }),
         delivered_input_watermark: 999,
+        server_time_ms: 0,
+        frame_sequence: 0,
+        frame_hash: 0,
+        size_changed: None,
+        chunk_index: 0,
+        chunk_count: 0,
+        echo_suppressed: false,
+        debug_info: None,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1041,6 +1348,7 @@ fn test_protocol_error_roundtrip() {
         code: protocol_error::Code::Unauthorized as i32,
         message: "Invalid token".to_string(),
         fatal: true,
+        retry_after_ms: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1059,11 +1367,14 @@ fn test_protocol_error_all_codes() {
         protocol_error::Code::SessionNotFound,
         protocol_error::Code::LeaseDenied,
         protocol_error::Code::Internal,
+        protocol_error::Code::SessionDegraded,
+        protocol_error::Code::RateLimited,
     ] {
         let original = ProtocolError {
             code: code as i32,
             message: String::new(),
             fatal: false,
+            retry_after_ms: 0,
         };
         let mut buf = Vec::new();
         original.encode(&mut buf).unwrap();
@@ -1126,7 +1437,17 @@ fn test_stream_envelope_client_hello() {
             client_name: "test".to_string(),
             bearer_token: vec![],
             resume_token: vec![],
+            min_render_window: 0,
+            max_render_window: 0,
+            supported_codecs: vec![],
+            debug_frame_info: false,
+            locale: None,
+            color_transform: None,
+            extensions: vec![],
+            terminal_answers: None,
+            flash_guard: None,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1148,7 +1469,14 @@ fn test_stream_envelope_server_hello() {
             snapshot_interval_ms: 5000,
             max_inflight_inputs: 16,
             render_window: 4,
+            min_render_window: 0,
+            max_render_window: 0,
+            keepalive_interval_ms: 0,
+            alternate_endpoints: vec![],
+            compression: None,
+            extensions: vec![],
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1168,6 +1496,7 @@ fn test_stream_envelope_attach_request() {
             read_only: false,
             force_snapshot: true,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1185,6 +1514,7 @@ fn test_stream_envelope_attach_response() {
             current_state_id: 100,
             will_send_snapshot: true,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1192,6 +1522,96 @@ fn test_stream_envelope_attach_response() {
     assert_eq!(original, decoded);
 }
 
+#[test]
+fn test_stream_envelope_detach() {
+    for reason in [
+        DetachReason::User,
+        DetachReason::Reconnect,
+        DetachReason::ClientError,
+        DetachReason::SwitchSession,
+    ] {
+        let original = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::Detach(Detach {
+                reason: reason as i32,
+                keep_resume_state: reason != DetachReason::User,
+            })),
+            envelope_seq: 0,
+        };
+        let mut buf = Vec::new();
+        original.encode(&mut buf).unwrap();
+        let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+        assert_eq!(original, decoded);
+    }
+}
+
+#[test]
+fn test_session_switch_roundtrip() {
+    let redirect = SessionRedirect {
+        session_name: "work".to_string(),
+        endpoint: Some(Endpoint {
+            host: String::new(),
+            port: 4434,
+        }),
+    };
+    for msg in [
+        stream_envelope::Msg::SwitchSession(SwitchSession {
+            session_name: "work".to_string(),
+        }),
+        stream_envelope::Msg::SessionRedirect(redirect.clone()),
+    ] {
+        let original = StreamEnvelope {
+            msg: Some(msg),
+            envelope_seq: 0,
+        };
+        let mut buf = Vec::new();
+        original.encode(&mut buf).unwrap();
+        let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+        assert_eq!(original, decoded);
+    }
+
+    // Control-plane readers see the redirect too
+    let envelope = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::SessionRedirect(redirect.clone())),
+        envelope_seq: 0,
+    };
+    let decoded = ControlEnvelope::decode(envelope.encode_to_vec().as_slice()).unwrap();
+    assert_eq!(
+        decoded.msg,
+        Some(control_envelope::Msg::SessionRedirect(redirect))
+    );
+}
+
+#[test]
+fn test_stream_envelope_snapshot_request() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::SnapshotRequest(SnapshotRequest {})),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_screen_delta_debug_info_roundtrip() {
+    let original = ScreenDelta {
+        base_state_id: 1,
+        state_id: 2,
+        debug_info: Some(DebugFrameInfo {
+            rows_diffed: 3,
+            cells_changed: 17,
+            encoded_bytes: 212,
+            compute_micros: 48,
+        }),
+        ..Default::default()
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = ScreenDelta::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
 #[test]
 fn test_stream_envelope_request_control() {
     let original = StreamEnvelope {
@@ -1202,7 +1622,9 @@ fn test_stream_envelope_request_control() {
                 rows: 40,
             }),
             force: false,
+            max_duration_ms: 0,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1221,8 +1643,10 @@ fn test_stream_envelope_grant_control() {
                 current_size: Some(DisplaySize { cols: 80, rows: 24 }),
                 remaining_ms: 30000,
                 duration_ms: 60000,
+                owner_name: String::new(),
             }),
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1237,6 +1661,7 @@ fn test_stream_envelope_deny_control() {
             reason: "already controlled".to_string(),
             lease: None,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1250,6 +1675,7 @@ fn test_stream_envelope_release_control() {
         msg: Some(stream_envelope::Msg::ReleaseControl(ReleaseControl {
             lease_id: 42,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1267,6 +1693,7 @@ fn test_stream_envelope_set_controller_size() {
             }),
             request_snapshot: false,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1281,6 +1708,7 @@ fn test_stream_envelope_keep_alive_lease() {
             lease_id: 1,
             client_time_ms: 50000,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1295,11 +1723,415 @@ fn test_stream_envelope_lease_revoked() {
             lease_id: 1,
             reason: "takeover".to_string(),
         })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_stream_envelope_session_state_changed() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::SessionStateChanged(
+            SessionStateChanged {
+                state: SessionState::Degraded as i32,
+                reason: "screen thread stalled".to_string(),
+            },
+        )),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_stream_envelope_render_mode_changed() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::RenderModeChanged(RenderModeChanged {
+            mode: RenderMode::TextOnly as i32,
+            frame_interval_ms: 500,
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_stream_envelope_stream_paused() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::StreamPaused(StreamPaused {
+            paused: true,
+            placeholder: "Streaming paused by the host".to_string(),
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_stream_envelope_session_metadata() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::SessionMetadata(SessionMetadata {
+            session_name: "work".to_string(),
+            pane_title: "vim".to_string(),
+            cwd: "/home/user/src".to_string(),
+            running_command: "vim main.rs".to_string(),
+            server_locale: Some(ClientLocale {
+                timezone: "America/New_York".to_string(),
+                locale: "en-US".to_string(),
+                utc_offset_minutes: -240,
+                has_utc_offset: true,
+            }),
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_stream_envelope_link_stats() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::LinkStats(LinkStats {
+            srtt_ms: 42,
+            input_latency_p50_ms: 38,
+            input_latency_p95_ms: 120,
+            input_latency_p99_ms: 310,
+            input_latency_samples: 1_024,
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_stream_envelope_lane_open() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::LaneOpen(LaneOpen {
+            lane: Lane::Bulk as i32,
+        })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
     let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
     assert_eq!(original, decoded);
+    match decoded.msg {
+        Some(stream_envelope::Msg::LaneOpen(open)) => assert_eq!(open.lane(), Lane::Bulk),
+        other => panic!("Expected LaneOpen, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_stream_envelope_client_visibility() {
+    for visible in [false, true] {
+        let original = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ClientVisibility(ClientVisibility {
+                visible,
+            })),
+            envelope_seq: 0,
+        };
+        let mut buf = Vec::new();
+        original.encode(&mut buf).unwrap();
+        let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+        assert_eq!(original, decoded);
+    }
+}
+
+#[test]
+fn test_stream_envelope_subscribe_region() {
+    for rows in [Some(RowRange { start: 23, end: 24 }), None] {
+        let original = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::SubscribeRegion(SubscribeRegion {
+                rows,
+            })),
+            envelope_seq: 0,
+        };
+        let mut buf = Vec::new();
+        original.encode(&mut buf).unwrap();
+        let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+        assert_eq!(original, decoded);
+    }
+}
+
+#[test]
+fn test_stream_envelope_capability_update() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::CapabilityUpdate(CapabilityUpdate {
+            capabilities: Some(Capabilities {
+                supports_datagrams: false,
+                ..Default::default()
+            }),
+            supported_codecs: vec![],
+            compression: Some(CompressionConfig {
+                snapshots: Codec::None as i32,
+                deltas: Codec::None as i32,
+            }),
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_stream_envelope_bell() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::Bell(BellEvent {
+            pane_id: 3,
+            urgency: BellUrgency::High as i32,
+            count: 12,
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_stream_envelope_pane_layout() {
+    let rect = |col, cols| PaneRect {
+        col,
+        row: 0,
+        cols,
+        rows: 23,
+    };
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::PaneLayout(PaneLayout {
+            panes: vec![
+                PaneBoundary {
+                    pane_id: 1,
+                    is_plugin: false,
+                    rect: Some(rect(0, 40)),
+                    content: Some(PaneRect {
+                        col: 1,
+                        row: 1,
+                        cols: 38,
+                        rows: 21,
+                    }),
+                    title: "vim".to_string(),
+                    focused: true,
+                    floating: false,
+                    selectable: true,
+                },
+                PaneBoundary {
+                    pane_id: 0,
+                    is_plugin: true,
+                    rect: Some(rect(40, 40)),
+                    content: Some(rect(40, 40)),
+                    title: String::new(),
+                    focused: false,
+                    floating: true,
+                    selectable: false,
+                },
+            ],
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_stream_envelope_focus_pane() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::FocusPane(FocusPane {
+            pane_id: 4,
+            is_plugin: true,
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_stream_envelope_keybinding_info() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::KeybindingInfo(KeybindingInfo {
+            modes: vec![ModeKeybindings {
+                mode: "normal".to_string(),
+                bindings: vec![
+                    Keybinding {
+                        key: Some(KeyEvent {
+                            modifiers: Some(KeyModifiers { bits: 4 }),
+                            key: Some(key_event::Key::UnicodeScalar('g' as u32)),
+                        }),
+                        label: "Ctrl g".to_string(),
+                        actions: vec![r#"SwitchToMode "locked""#.to_string()],
+                    },
+                    Keybinding {
+                        key: None,
+                        label: "CAPSlOCK".to_string(),
+                        actions: vec!["Quit".to_string()],
+                    },
+                ],
+            }],
+            default_mode: "normal".to_string(),
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_stream_envelope_compressed() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::Compressed(CompressedEnvelope {
+            codec: Codec::Zstd as i32,
+            uncompressed_len: 4096,
+            payload: vec![0x28, 0xb5, 0x2f, 0xfd, 0x00],
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_compression_config_defaults_to_none() {
+    let config = CompressionConfig::default();
+    assert_eq!(config.snapshots(), Codec::None);
+    assert_eq!(config.deltas(), Codec::None);
+
+    let original = CompressionConfig {
+        snapshots: Codec::Zstd as i32,
+        deltas: Codec::None as i32,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    assert_eq!(CompressionConfig::decode(&buf[..]).unwrap(), original);
+}
+
+#[test]
+fn test_stream_envelope_control_handoff() {
+    let request = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::ControlHandoffRequest(
+            ControlHandoffRequest {
+                handoff_id: 3,
+                requester_client_id: 7,
+                reason: "my turn".to_string(),
+                desired_size: Some(DisplaySize {
+                    cols: 100,
+                    rows: 30,
+                }),
+                timeout_ms: 10_000,
+            },
+        )),
+        envelope_seq: 0,
+    };
+    let response = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::ControlHandoffResponse(
+            ControlHandoffResponse {
+                handoff_id: 3,
+                approved: true,
+            },
+        )),
+        envelope_seq: 0,
+    };
+    for original in [request, response] {
+        let mut buf = Vec::new();
+        original.encode(&mut buf).unwrap();
+        let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+        assert_eq!(original, decoded);
+    }
+}
+
+#[test]
+fn test_stream_envelope_lease_status() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::LeaseStatus(LeaseStatus {
+            lease: Some(ControllerLease {
+                lease_id: 4,
+                owner_client_id: 2,
+                policy: ControllerPolicy::LastWriterWins as i32,
+                current_size: Some(DisplaySize { cols: 80, rows: 24 }),
+                remaining_ms: 12_500,
+                duration_ms: 30_000,
+                owner_name: String::new(),
+            }),
+            idle_remaining_ms: 4_000,
+            time_box_remaining_ms: 0,
+            expires: true,
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_screen_snapshot_chunk_roundtrip() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::ScreenSnapshot(ScreenSnapshot {
+            state_id: 9,
+            size: Some(DisplaySize {
+                cols: 4_000,
+                rows: 2,
+            }),
+            rows: vec![RowData {
+                row: 1,
+                col_start: 2_048,
+                codepoints: vec!['a' as u32, 0x4E2D, 0],
+                widths: vec![1, 2, 0],
+                style_ids: vec![0, 3, 3],
+                ..Default::default()
+            }],
+            chunk_index: 3,
+            chunk_count: 5,
+            ..Default::default()
+        })),
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_protocol_error_retry_hint_roundtrip() {
+    let original = ProtocolError {
+        code: protocol_error::Code::SessionDegraded as i32,
+        message: "session degraded".to_string(),
+        fatal: true,
+        retry_after_ms: 2_000,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = ProtocolError::decode(&buf[..]).unwrap();
+    assert_eq!(decoded.retry_after_ms, 2_000);
+    assert_eq!(original, decoded);
 }
 
 #[test]
@@ -1309,6 +2141,7 @@ fn test_stream_envelope_request_snapshot() {
             reason: request_snapshot::Reason::BaseMismatch as i32,
             known_state_id: 50,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1323,6 +2156,7 @@ fn test_stream_envelope_ping() {
             ping_id: 123,
             client_time_ms: 10000,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1338,6 +2172,7 @@ fn test_stream_envelope_pong() {
             echoed_client_time_ms: 10000,
             server_time_ms: 10005,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1352,7 +2187,9 @@ fn test_stream_envelope_protocol_error() {
             code: protocol_error::Code::BadMessage as i32,
             message: "Invalid field".to_string(),
             fatal: false,
+            retry_after_ms: 0,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1369,6 +2206,7 @@ fn test_stream_envelope_unsupported_notice() {
                 behavior: "stripped".to_string(),
             },
         )),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1387,7 +2225,16 @@ fn test_stream_envelope_screen_snapshot() {
             rows: vec![],
             cursor: None,
             delivered_input_watermark: 0,
+            server_time_ms: 0,
+            frame_sequence: 0,
+            frame_hash: 0,
+            size_changed: None,
+            chunk_index: 0,
+            chunk_count: 0,
+            echo_suppressed: false,
+            debug_info: None,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1405,7 +2252,15 @@ fn test_stream_envelope_screen_delta_stream() {
             row_patches: vec![],
             cursor: None,
             delivered_input_watermark: 0,
+            server_time_ms: 0,
+            frame_sequence: 0,
+            frame_hash: 0,
+            chunk_index: 0,
+            chunk_count: 0,
+            echo_suppressed: false,
+            debug_info: None,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1421,6 +2276,7 @@ fn test_stream_envelope_input_event() {
             client_time_ms: 1000,
             payload: Some(input_event::Payload::TextUtf8(b"hello".to_vec())),
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1436,6 +2292,7 @@ fn test_stream_envelope_input_ack() {
             rtt_sample_seq: 9,
             echoed_client_time_ms: 5000,
         })),
+        envelope_seq: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1445,11 +2302,105 @@ fn test_stream_envelope_input_ack() {
 
 #[test]
 fn test_stream_envelope_empty() {
-    let original = StreamEnvelope { msg: None };
+    let original = StreamEnvelope {
+        msg: None,
+        envelope_seq: 0,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_stream_envelope_seq_roundtrip() {
+    let original = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::Ping(Ping {
+            ping_id: 3,
+            client_time_ms: 0,
+        })),
+        envelope_seq: 42,
+    };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
     let decoded = StreamEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(decoded.envelope_seq, 42);
     assert_eq!(original, decoded);
+
+    // Unstamped envelopes cost nothing on the wire
+    let unstamped = StreamEnvelope {
+        envelope_seq: 0,
+        ..original
+    };
+    assert!(unstamped.encoded_len() < buf.len());
+}
+
+// =============================================================================
+// CONTROL ENVELOPE (control-plane view of a StreamEnvelope)
+// =============================================================================
+
+#[test]
+fn test_control_envelope_reads_control_messages() {
+    let hello = ClientHello {
+        client_name: "auth-proxy".to_string(),
+        bearer_token: b"secret".to_vec(),
+        ..Default::default()
+    };
+    let error = ProtocolError {
+        code: protocol_error::Code::Unauthorized as i32,
+        message: "bad token".to_string(),
+        fatal: true,
+        retry_after_ms: 0,
+    };
+
+    let envelope = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::ClientHello(hello.clone())),
+        envelope_seq: 0,
+    };
+    let decoded = ControlEnvelope::decode(envelope.encode_to_vec().as_slice()).unwrap();
+    assert_eq!(decoded.msg, Some(control_envelope::Msg::ClientHello(hello)));
+
+    let envelope = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::ProtocolError(error.clone())),
+        envelope_seq: 0,
+    };
+    let decoded = ControlEnvelope::decode(envelope.encode_to_vec().as_slice()).unwrap();
+    assert_eq!(
+        decoded.msg,
+        Some(control_envelope::Msg::ProtocolError(error))
+    );
+}
+
+#[test]
+fn test_control_envelope_skips_render_messages() {
+    let envelope = StreamEnvelope {
+        msg: Some(stream_envelope::Msg::ScreenSnapshot(ScreenSnapshot {
+            state_id: 5,
+            size: Some(DisplaySize { cols: 80, rows: 24 }),
+            ..Default::default()
+        })),
+        envelope_seq: 0,
+    };
+    let decoded = ControlEnvelope::decode(envelope.encode_to_vec().as_slice()).unwrap();
+    assert_eq!(decoded.msg, None);
+}
+
+#[test]
+fn test_control_envelope_readable_as_stream_envelope() {
+    let original = ControlEnvelope {
+        msg: Some(control_envelope::Msg::Ping(Ping {
+            ping_id: 9,
+            client_time_ms: 100,
+        })),
+    };
+    let decoded = StreamEnvelope::decode(original.encode_to_vec().as_slice()).unwrap();
+    assert_eq!(
+        decoded.msg,
+        Some(stream_envelope::Msg::Ping(Ping {
+            ping_id: 9,
+            client_time_ms: 100,
+        }))
+    );
 }
 
 // =============================================================================
@@ -1471,6 +2422,9 @@ fn test_datagram_envelope_screen_delta() {
                     widths: vec![1],
                     style_ids: vec![0],
                 }],
+                line_size: 0,
+                echo: false,
+                row_crc32: 0,
             }],
             cursor: Some(CursorState {
                 row: 5,
@@ -1478,8 +2432,16 @@ fn test_datagram_envelope_screen_delta() {
                 visible: true,
                 blink: false,
                 shape: CursorShape::Block as i32,
+                color: None,
             }),
             delivered_input_watermark: 50,
+            server_time_ms: 0,
+            frame_sequence: 0,
+            frame_hash: 0,
+            chunk_index: 0,
+            chunk_count: 0,
+            echo_suppressed: false,
+            debug_info: None,
         })),
     };
     let mut buf = Vec::new();
@@ -1543,6 +2505,200 @@ fn test_datagram_envelope_empty() {
     assert_eq!(original, decoded);
 }
 
+// =============================================================================
+// RELAY ENVELOPE ONEOF TESTS
+// =============================================================================
+
+#[test]
+fn test_relay_envelope_register_roundtrip() {
+    let original = RelayEnvelope {
+        msg: Some(relay_envelope::Msg::Register(RelayRegister {
+            session_name: "build-box".to_string(),
+            relay_token: b"relay-secret".to_vec(),
+        })),
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = RelayEnvelope::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_relay_envelope_accept_and_error_roundtrip() {
+    let messages = vec![
+        relay_envelope::Msg::Incoming(RelayIncoming {
+            relay_client_id: 7,
+            client_addr: "203.0.113.5:50000".to_string(),
+        }),
+        relay_envelope::Msg::Accept(RelayAccept {
+            relay_client_id: 7,
+            host_key: vec![0xAB; 16],
+        }),
+        relay_envelope::Msg::Error(RelayError {
+            code: relay_error::Code::SessionTaken as i32,
+            message: "session already registered".to_string(),
+        }),
+    ];
+    for msg in messages {
+        let original = RelayEnvelope { msg: Some(msg) };
+        let mut buf = Vec::new();
+        original.encode(&mut buf).unwrap();
+        let decoded = RelayEnvelope::decode(&buf[..]).unwrap();
+        assert_eq!(original, decoded);
+    }
+}
+
+// =============================================================================
+// CONFORMANCE VECTORS
+// =============================================================================
+
+#[test]
+fn test_conformance_suite_roundtrip() {
+    let original = ConformanceSuite {
+        version: 1,
+        vectors: vec![ConformanceVector {
+            name: "cursor_only".to_string(),
+            description: "A delta that moves the cursor and touches no rows".to_string(),
+            steps: vec![
+                ConformanceStep {
+                    frame: Some(conformance_step::Frame::Snapshot(ScreenSnapshot {
+                        state_id: 1,
+                        size: Some(DisplaySize { cols: 4, rows: 1 }),
+                        style_table_reset: true,
+                        ..Default::default()
+                    })),
+                },
+                ConformanceStep {
+                    frame: Some(conformance_step::Frame::Delta(ScreenDelta {
+                        base_state_id: 1,
+                        state_id: 2,
+                        cursor: Some(CursorState {
+                            row: 0,
+                            col: 3,
+                            visible: true,
+                            blink: false,
+                            shape: CursorShape::Beam as i32,
+                            color: None,
+                        }),
+                        ..Default::default()
+                    })),
+                },
+            ],
+            expected: Some(ScreenSnapshot {
+                state_id: 2,
+                ..Default::default()
+            }),
+        }],
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = ConformanceSuite::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+#[test]
+fn test_persisted_session_roundtrip() {
+    let original = PersistedSession {
+        version: 1,
+        session_id: 42,
+        token_secret: vec![7; 32],
+        token_epoch: 2,
+        client_token_epochs: [(3, 4)].into_iter().collect(),
+        next_client_id: 9,
+        snapshot: Some(ScreenSnapshot {
+            state_id: 17,
+            size: Some(DisplaySize { cols: 4, rows: 1 }),
+            style_table_reset: true,
+            ..Default::default()
+        }),
+        saved_at_ms: 1_700_000_000_000,
+    };
+    let mut buf = Vec::new();
+    original.encode(&mut buf).unwrap();
+    let decoded = PersistedSession::decode(&buf[..]).unwrap();
+    assert_eq!(original, decoded);
+}
+
+// =============================================================================
+// RENDERING FIXTURES
+// =============================================================================
+
+#[test]
+fn test_fixtures_are_well_formed() {
+    use crate::fixtures::{fixture, FIXTURES};
+    use std::collections::HashSet;
+
+    let names: HashSet<&str> = FIXTURES.iter().map(|fixture| fixture.name).collect();
+    assert_eq!(names.len(), FIXTURES.len());
+    assert!(fixture("styles").is_some());
+    assert!(fixture("unknown").is_none());
+
+    for fixture in FIXTURES {
+        let messages = (fixture.build)(10);
+        let Some(stream_envelope::Msg::ScreenSnapshot(first)) = messages.first() else {
+            panic!("{} doesn't start with a snapshot", fixture.name);
+        };
+        assert_eq!(first.state_id, 10, "{}", fixture.name);
+        assert!(first.style_table_reset, "{}", fixture.name);
+        let size = first.size.clone().unwrap();
+        let defined: HashSet<u32> = first.styles.iter().map(|def| def.style_id).collect();
+
+        let mut state_id = 10;
+        for message in &messages {
+            let rows: Vec<(Vec<u32>, Vec<u32>, Vec<u32>)> = match message {
+                stream_envelope::Msg::ScreenSnapshot(snapshot) => {
+                    assert_eq!(snapshot.state_id, state_id);
+                    assert_eq!(snapshot.rows.len() as u32, size.rows, "{}", fixture.name);
+                    snapshot
+                        .rows
+                        .iter()
+                        .map(|row| {
+                            assert_eq!(row.codepoints.len() as u32, size.cols, "{}", fixture.name);
+                            (
+                                row.codepoints.clone(),
+                                row.widths.clone(),
+                                row.style_ids.clone(),
+                            )
+                        })
+                        .collect()
+                },
+                stream_envelope::Msg::ScreenDeltaStream(delta) => {
+                    assert_eq!(
+                        (delta.base_state_id, delta.state_id),
+                        (state_id - 1, state_id)
+                    );
+                    delta
+                        .row_patches
+                        .iter()
+                        .flat_map(|patch| &patch.runs)
+                        .map(|run| {
+                            (
+                                run.codepoints.clone(),
+                                run.widths.clone(),
+                                run.style_ids.clone(),
+                            )
+                        })
+                        .collect()
+                },
+                other => panic!("{} sends {:?}", fixture.name, other),
+            };
+            for (codepoints, widths, style_ids) in rows {
+                assert_eq!(codepoints.len(), widths.len());
+                assert_eq!(codepoints.len(), style_ids.len());
+                assert!(style_ids.iter().all(|id| defined.contains(id)));
+                // Every wide character is followed by its continuation cell
+                for (col, width) in widths.iter().enumerate() {
+                    if *width == 2 {
+                        assert_eq!(widths.get(col + 1), Some(&0), "{}", fixture.name);
+                        assert_eq!(codepoints[col + 1], 0);
+                    }
+                }
+            }
+            state_id += 1;
+        }
+    }
+}
+
 // =============================================================================
 // EDGE CASES
 // =============================================================================
@@ -1556,6 +2712,13 @@ fn test_max_u64_values() {
         row_patches: vec![],
         cursor: None,
         delivered_input_watermark: u64::MAX,
+        server_time_ms: 0,
+        frame_sequence: 0,
+        frame_hash: 0,
+        chunk_index: 0,
+        chunk_count: 0,
+        echo_suppressed: false,
+        debug_info: None,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1598,6 +2761,15 @@ fn test_unicode_strings() {
         client_name: "客户端-العميل-クライアント".to_string(),
         bearer_token: "🔐🔑🗝️".as_bytes().to_vec(),
         resume_token: vec![],
+        min_render_window: 0,
+        max_render_window: 0,
+        supported_codecs: vec![],
+        debug_frame_info: false,
+        locale: None,
+        color_transform: None,
+        extensions: vec![],
+        terminal_answers: None,
+        flash_guard: None,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1613,6 +2785,15 @@ fn test_large_bearer_token() {
         client_name: String::new(),
         bearer_token: vec![0xAB; 10000],
         resume_token: vec![0xCD; 10000],
+        min_render_window: 0,
+        max_render_window: 0,
+        supported_codecs: vec![],
+        debug_frame_info: false,
+        locale: None,
+        color_transform: None,
+        extensions: vec![],
+        terminal_answers: None,
+        flash_guard: None,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
@@ -1643,6 +2824,8 @@ fn test_wide_character_widths() {
         codepoints: vec![0x4E2D, 0x6587, 0x5B57], // 中文字
         widths: vec![2, 2, 2],
         style_ids: vec![0, 0, 0],
+        line_size: 0,
+        col_start: 0,
     };
     let mut buf = Vec::new();
     original.encode(&mut buf).unwrap();
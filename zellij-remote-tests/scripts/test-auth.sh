@@ -87,8 +87,15 @@ run_client_test() {
         fi
     else
         if [ "$expect_success" = "false" ]; then
-            echo "  ✓ PASS: Connection rejected (no ServerHello)"
-            TESTS_PASSED=$((TESTS_PASSED + 1))
+            # The server says why instead of just closing: CODE_UNAUTHORIZED
+            if echo "$output" | grep -q "Authentication failed"; then
+                echo "  ✓ PASS: Connection rejected with UNAUTHORIZED"
+                TESTS_PASSED=$((TESTS_PASSED + 1))
+            else
+                echo "  ✗ FAIL: Connection rejected without an UNAUTHORIZED error"
+                echo "  Output: $output"
+                TESTS_FAILED=$((TESTS_FAILED + 1))
+            fi
         else
             echo "  ✗ FAIL: No ServerHello but expected success"
             echo "  Output: $output"
@@ -52,7 +52,10 @@ use crate::{
 };
 
 #[cfg(feature = "remote")]
-use crate::remote::{remote_thread_main, RemoteConfig, RemoteInstruction};
+use crate::remote::{
+    remote_thread_main, RemoteConfig, RemoteInstruction, ScreenEvents, SecurityProfile,
+    DEFAULT_KEEPALIVE_INTERVAL_MS,
+};
 use route::{route_thread_main, NotificationEnd};
 use zellij_utils::{
     channels::{self, ChannelWithContext, SenderWithContext},
@@ -1930,28 +1933,194 @@ fn init_session(
                 "127.0.0.1:4433".parse().unwrap()
             });
 
-        let bearer_token = std::env::var("ZELLIJ_REMOTE_TOKEN")
-            .ok()
-            .map(|s| {
+        // Only a salted hash of the token is kept; ZELLIJ_REMOTE_TOKEN_HASH lets deployments
+        // avoid putting the plaintext in the environment at all. A malformed one keeps the
+        // server from starting rather than serving without authentication.
+        let mut auth_error = None;
+        let bearer_token = match std::env::var("ZELLIJ_REMOTE_TOKEN_HASH") {
+            Ok(encoded) => {
+                let hash = zellij_remote_core::TokenHash::parse(&encoded);
+                if hash.is_none() {
+                    log::error!(
+                        "ZELLIJ_REMOTE_TOKEN_HASH is malformed (expected sha256:<salt>:<digest>), not serving remote clients"
+                    );
+                    auth_error = Some(
+                        "serving with a malformed ZELLIJ_REMOTE_TOKEN_HASH (expected sha256:<salt>:<digest>)"
+                            .to_string(),
+                    );
+                }
+                hash
+            },
+            Err(_) => std::env::var("ZELLIJ_REMOTE_TOKEN").ok().and_then(|s| {
                 if s.is_empty() {
                     log::error!(
                         "ZELLIJ_REMOTE_TOKEN cannot be empty, treating as no authentication"
                     );
                     None
                 } else {
-                    Some(s.into_bytes())
+                    Some(zellij_remote_core::TokenHash::from_plaintext(s.as_bytes()))
                 }
+            }),
+        };
+
+        // A second token for tools that only take one-shot captures (e.g. dashboards)
+        let snapshot_token = std::env::var("ZELLIJ_REMOTE_SNAPSHOT_TOKEN")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(|s| zellij_remote_core::TokenHash::from_plaintext(s.as_bytes()));
+
+        let controller_idle_timeout_ms = std::env::var("ZELLIJ_REMOTE_CONTROLLER_IDLE_TIMEOUT_MS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|ms: &u64| *ms > 0);
+
+        // 0 turns keep-alive pings off
+        let keepalive_interval_ms = Some(
+            std::env::var("ZELLIJ_REMOTE_KEEPALIVE_INTERVAL_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_KEEPALIVE_INTERVAL_MS),
+        )
+        .filter(|ms: &u64| *ms > 0);
+
+        // e.g. "192.168.1.5:4433,[fd7a:115c:a1e0::1]:4433" for a LAN and a tailnet path
+        let alternate_endpoints = std::env::var("ZELLIJ_REMOTE_ALTERNATE_ENDPOINTS")
+            .map(|list| {
+                list.split(',')
+                    .filter(|s| !s.trim().is_empty())
+                    .filter_map(|s| {
+                        let endpoint = zellij_remote_core::failover::parse_endpoint(s);
+                        if endpoint.is_none() {
+                            log::error!("Ignoring malformed alternate endpoint {:?}", s);
+                        }
+                        endpoint
+                    })
+                    .collect()
             })
-            .flatten();
+            .unwrap_or_default();
+
+        // Unset or 0 leaves that role uncapped
+        let max_fps = |var: &str| {
+            std::env::var(var)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|fps: &u32| *fps > 0)
+        };
+        let frame_rate_caps = zellij_remote_bridge::FrameRateCaps {
+            controller_max_fps: max_fps("ZELLIJ_REMOTE_CONTROLLER_MAX_FPS"),
+            viewer_max_fps: max_fps("ZELLIJ_REMOTE_VIEWER_MAX_FPS"),
+        };
+
+        // Unset or 0 lets remote clients force a takeover without asking
+        let approval_timeout_ms = std::env::var("ZELLIJ_REMOTE_APPROVAL_TIMEOUT_MS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|ms: &u64| *ms > 0);
 
         let session_name = envs::get_session_name().unwrap_or_else(|_| "zellij".to_string());
 
+        // Opt-in: the saved state includes the resume token secret
+        let persist_dir = std::env::var("ZELLIJ_REMOTE_PERSIST")
+            .is_ok()
+            .then(|| zellij_utils::consts::session_info_folder_for_session(&session_name));
+
+        // How long the screen may go without answering before clients are told the
+        // session is unresponsive
+        let screen_stall_timeout_ms = std::env::var("ZELLIJ_REMOTE_SCREEN_STALL_TIMEOUT_MS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|ms: &u64| *ms > 0);
+
+        // How long a write may wait for a client to read before it is closed
+        let writer_stall_timeout_ms = std::env::var("ZELLIJ_REMOTE_WRITER_STALL_TIMEOUT_MS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|ms: &u64| *ms > 0);
+
+        // How far ahead of the next input an input may arrive and still be held (0
+        // rejects every gap), and for how long; capped at MAX_INPUT_REORDER_EVENTS
+        let default_reorder = zellij_remote_core::ReorderWindow::default();
+        let input_reorder_window = zellij_remote_core::ReorderWindow {
+            max_events: std::env::var("ZELLIJ_REMOTE_INPUT_REORDER_EVENTS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default_reorder.max_events),
+            hold_ms: std::env::var("ZELLIJ_REMOTE_INPUT_REORDER_HOLD_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default_reorder.hold_ms),
+        };
+
+        // Deltas larger than this go out as snapshots
+        let max_delta_bytes = std::env::var("ZELLIJ_REMOTE_MAX_DELTA_BYTES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|bytes: &usize| *bytes > 0);
+
+        let worker_threads = std::env::var("ZELLIJ_REMOTE_WORKER_THREADS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|threads: &usize| *threads > 0);
+
+        // strict, default or open; a profile that can't be read is taken as strict
+        let security_profile = match std::env::var("ZELLIJ_REMOTE_SECURITY_PROFILE") {
+            Ok(name) => SecurityProfile::parse(&name).unwrap_or_else(|| {
+                log::error!(
+                    "Unknown ZELLIJ_REMOTE_SECURITY_PROFILE {:?} (expected strict, default or open), using strict",
+                    name
+                );
+                SecurityProfile::Strict
+            }),
+            Err(_) => SecurityProfile::default(),
+        };
+
+        // Unset leaves it to the security profile
+        let controller_policy = std::env::var("ZELLIJ_REMOTE_CONTROLLER_POLICY")
+            .ok()
+            .and_then(|name| match name.as_str() {
+                "explicit-only" => Some(zellij_remote_protocol::ControllerPolicy::ExplicitOnly),
+                "last-writer-wins" => {
+                    Some(zellij_remote_protocol::ControllerPolicy::LastWriterWins)
+                },
+                _ => {
+                    log::error!(
+                        "Ignoring unknown ZELLIJ_REMOTE_CONTROLLER_POLICY {:?} (expected explicit-only or last-writer-wins)",
+                        name
+                    );
+                    None
+                },
+            });
+        let audit_log = std::env::var("ZELLIJ_REMOTE_AUDIT")
+            .ok()
+            .map(|value| value != "0");
+        let i_know_what_im_doing = envs::get_remote_i_know_what_im_doing();
+
         let config = RemoteConfig {
             listen_addr,
             session_name,
             initial_size: Size { cols: 80, rows: 24 },
-            to_screen: to_screen_bounded.clone(),
+            events: Arc::new(ScreenEvents::new(to_screen_bounded.clone())),
             bearer_token,
+            token_provider: None,
+            snapshot_token,
+            controller_idle_timeout_ms,
+            keepalive_interval_ms,
+            alternate_endpoints,
+            frame_rate_caps,
+            approval_timeout_ms,
+            persist_dir,
+            screen_stall_timeout_ms,
+            size_limits: zellij_remote_core::SizeLimits::default(),
+            input_reorder_window,
+            writer_stall_timeout_ms,
+            extensions: zellij_remote_bridge::ExtensionRegistry::default(),
+            max_delta_bytes,
+            worker_threads,
+            security_profile,
+            controller_policy,
+            audit_log,
+            i_know_what_im_doing,
+            auth_error,
         };
 
         let _remote_thread = thread::Builder::new()
@@ -496,6 +496,12 @@ pub trait ServerOsApi: Send + Sync {
     fn write_to_tty_stdin(&self, terminal_id: u32, buf: &[u8]) -> Result<usize>;
     /// Wait until all output written to the object referred to by `fd` has been transmitted.
     fn tcdrain(&self, terminal_id: u32) -> Result<()>;
+    /// Whether the terminal is at a password prompt: echo off in canonical mode, as
+    /// `getpass` leaves it. Raw-mode programs turn echo off too but draw what is typed
+    /// themselves, so they don't count. `None` if that can't be told.
+    fn hides_typed_input(&self, _terminal_id: u32) -> Option<bool> {
+        None
+    }
     /// Terminate the process with process ID `pid`. (SIGTERM)
     fn kill(&self, pid: Pid) -> Result<()>;
     /// Terminate the process with process ID `pid`. (SIGKILL)
@@ -688,6 +694,17 @@ impl ServerOsApi for ServerOsInputOutput {
             _ => Err(anyhow!("could not find raw file descriptor")).with_context(err_context),
         }
     }
+    fn hides_typed_input(&self, terminal_id: u32) -> Option<bool> {
+        let fd = match self.terminal_id_to_raw_fd.lock().ok()?.get(&terminal_id) {
+            Some(Some(fd)) => *fd,
+            _ => return None,
+        };
+        // The master side reports the line discipline settings of the pane's tty
+        termios::tcgetattr(fd).ok().map(|attrs| {
+            !attrs.local_flags.contains(termios::LocalFlags::ECHO)
+                && attrs.local_flags.contains(termios::LocalFlags::ICANON)
+        })
+    }
     fn box_clone(&self) -> Box<dyn ServerOsApi> {
         Box::new((*self).clone())
     }
@@ -1,6 +1,6 @@
 use std::collections::VecDeque;
 
-use crate::panes::Row;
+use crate::panes::{LineSize, Row};
 
 use crate::panes::Selection;
 use crate::{
@@ -299,6 +299,7 @@ pub struct Output {
     styled_underlines: bool,
     pane_render_report: PaneRenderReport,
     cursor_coordinates: Option<(usize, usize)>,
+    bells: Vec<PaneId>, // panes that rang the bell since their last render
 }
 
 impl Output {
@@ -605,6 +606,14 @@ impl Output {
         let empty_pane_render_report = PaneRenderReport::default();
         std::mem::replace(&mut self.pane_render_report, empty_pane_render_report)
     }
+    pub fn add_bell(&mut self, pane_id: PaneId) {
+        if !self.bells.contains(&pane_id) {
+            self.bells.push(pane_id);
+        }
+    }
+    pub fn bells(&self) -> &[PaneId] {
+        &self.bells
+    }
 }
 
 // this struct represents the geometry of a group of floating panes
@@ -729,7 +738,8 @@ impl FloatingPanesStack {
                 let left_chunk_x = c_chunk_left_side;
                 let right_chunk_x = pane_right_edge + 1;
                 let mut left_chunk =
-                    CharacterChunk::new(left_chunk_characters, left_chunk_x, c_chunk.y);
+                    CharacterChunk::new(left_chunk_characters, left_chunk_x, c_chunk.y)
+                        .with_line_size(c_chunk.line_size);
                 if !c_chunk.selection_and_colors.is_empty() {
                     left_chunk.selection_and_colors = c_chunk.selection_and_colors.clone();
                 }
@@ -920,12 +930,21 @@ impl FloatingPanesStack {
     }
 }
 
+fn line_size_in_viewport(line_index: usize, viewport: &[Row]) -> LineSize {
+    viewport
+        .get(line_index)
+        .map(|row| row.line_size)
+        .unwrap_or_default()
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct CharacterChunk {
     pub terminal_characters: Vec<TerminalCharacter>,
     pub x: usize,
     pub y: usize,
     pub changed_colors: Option<[Option<AnsiCode>; 256]>,
+    /// DEC line size of the pane line this chunk was taken from
+    pub line_size: LineSize,
     selection_and_colors: Vec<(Selection, AnsiCode, Option<AnsiCode>)>, // Selection, background color, optional foreground color
 }
 
@@ -949,6 +968,10 @@ impl CharacterChunk {
             ..Default::default()
         }
     }
+    pub fn with_line_size(mut self, line_size: LineSize) -> Self {
+        self.line_size = line_size;
+        self
+    }
     pub fn add_selection_and_colors(
         &mut self,
         selection: Selection,
@@ -1115,7 +1138,10 @@ impl OutputBuffer {
 
                 let x = x_offset; // right now we only buffer full lines as this doesn't seem to have a huge impact on performance, but the infra is here if we want to change this
                 let y = line_index + y_offset;
-                changed_chunks.push(CharacterChunk::new(terminal_characters, x, y));
+                changed_chunks.push(
+                    CharacterChunk::new(terminal_characters, x, y)
+                        .with_line_size(line_size_in_viewport(line_index, viewport)),
+                );
             }
             changed_chunks
         } else {
@@ -1132,7 +1158,10 @@ impl OutputBuffer {
                     self.extract_line_from_viewport(line_index, viewport, viewport_width);
                 let x = x_offset;
                 let y = line_index + y_offset;
-                changed_chunks.push(CharacterChunk::new(terminal_characters, x, y));
+                changed_chunks.push(
+                    CharacterChunk::new(terminal_characters, x, y)
+                        .with_line_size(line_size_in_viewport(line_index, viewport)),
+                );
             }
             changed_chunks
         }
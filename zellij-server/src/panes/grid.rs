@@ -33,6 +33,7 @@ use crate::panes::alacritty_functions::{parse_number, xparse_color};
 use crate::panes::hyperlink_tracker::HyperlinkTracker;
 use crate::panes::link_handler::LinkHandler;
 use crate::panes::search::SearchResult;
+use crate::panes::terminal_answers::{primary_device_attributes, termcap_replies};
 use crate::panes::terminal_character::{
     AnsiCode, CharsetIndex, Cursor, CursorShape, RcCharacterStyles, StandardCharset,
     TerminalCharacter, EMPTY_TERMINAL_CHARACTER,
@@ -330,6 +331,7 @@ pub struct Grid {
     character_cell_size: Rc<RefCell<Option<SizeInPixels>>>,
     sixel_grid: SixelGrid,
     pub changed_colors: Option<[Option<AnsiCode>; 256]>,
+    cursor_color: Option<AnsiCode>, // OSC 12; only remote clients draw it for now
     pub should_render: bool,
     pub lock_renders: bool,
     pub cursor_key_mode: bool, // DECCKM - when set, cursor keys should send ANSI direction codes (eg. "OD") instead of the arrow keys (eg. "[D")
@@ -355,6 +357,8 @@ pub struct Grid {
     pub search_results: SearchResult,
     pub pending_clipboard_update: Option<String>,
     ui_component_bytes: Option<Vec<u8>>,
+    /// Capability names of an XTGETTCAP query being read
+    termcap_query: Option<Vec<u8>>,
     style: Style,
     debug: bool,
     arrow_fonts: bool,
@@ -531,6 +535,7 @@ impl Grid {
             title_stack: vec![],
             title: None,
             changed_colors: None,
+            cursor_color: None,
             is_scrolled: false,
             link_handler,
             ring_bell: false,
@@ -543,6 +548,7 @@ impl Grid {
             sixel_grid,
             pending_clipboard_update: None,
             ui_component_bytes: None,
+            termcap_query: None,
             style,
             debug,
             arrow_fonts,
@@ -598,6 +604,9 @@ impl Grid {
     pub fn cursor_shape(&self) -> CursorShape {
         self.cursor.get_shape()
     }
+    pub fn cursor_color(&self) -> Option<AnsiCode> {
+        self.cursor_color
+    }
 
     pub fn viewport(&self) -> &[Row] {
         &self.viewport
@@ -1304,6 +1313,12 @@ impl Grid {
         }
         self.output_buffer.update_all_lines();
     }
+    pub fn set_line_size_at_cursor(&mut self, line_size: LineSize) {
+        if let Some(row) = self.viewport.get_mut(self.cursor.y) {
+            row.line_size = line_size;
+            self.output_buffer.update_line(self.cursor.y);
+        }
+    }
     pub fn add_canonical_line(&mut self) {
         let (scroll_region_top, scroll_region_bottom) = self.scroll_region;
         self.hyperlink_tracker.update(
@@ -1753,6 +1768,7 @@ impl Grid {
         self.cursor.change_shape(CursorShape::Initial);
         self.output_buffer.update_all_lines();
         self.changed_colors = None;
+        self.cursor_color = None;
         self.scrollback_buffer_lines = 0;
         self.search_results = Default::default();
         self.sixel_scrolling = false;
@@ -2533,7 +2549,10 @@ impl Perform for Grid {
     }
 
     fn hook(&mut self, params: &Params, intermediates: &[u8], _ignore: bool, c: char) {
-        if c == 'q' {
+        if c == 'q' && intermediates == [b'+'] {
+            // XTGETTCAP - request termcap/terminfo string
+            self.termcap_query = Some(vec![]);
+        } else if c == 'q' {
             // we only process sixel images if we know the pixel size of each character cell,
             // otherwise we can't reliably display them
             if self.current_cursor_pixel_coordinates().is_some() {
@@ -2563,6 +2582,8 @@ impl Perform for Grid {
             self.should_render = false;
         } else if let Some(ui_component_bytes) = self.ui_component_bytes.as_mut() {
             ui_component_bytes.push(byte);
+        } else if let Some(termcap_query) = self.termcap_query.as_mut() {
+            termcap_query.push(byte);
         }
     }
 
@@ -2576,6 +2597,10 @@ impl Perform for Grid {
             UiComponentParser::new(self, style, arrow_fonts)
                 .parse(component_bytes.collect())
                 .non_fatal();
+        } else if let Some(termcap_query) = self.termcap_query.take() {
+            for reply in termcap_replies(&termcap_query) {
+                self.pending_messages_to_pty.push(reply.into_bytes());
+            }
         }
         self.mark_for_rerender();
     }
@@ -2679,8 +2704,11 @@ impl Perform for Grid {
                 }
             },
 
+            // Set cursor color; querying it is still unsupported
             b"12" => {
-                // get/set cursor color currently unimplemented
+                if let Some(color) = params.get(1).and_then(|color| xparse_color(color)) {
+                    self.cursor_color = Some(color);
+                }
             },
 
             // Set cursor style.
@@ -2765,7 +2793,7 @@ impl Perform for Grid {
 
             // Reset text cursor color.
             b"112" => {
-                // TBD - reset text cursor color - currently unimplemented
+                self.cursor_color = None;
             },
 
             _ => {
@@ -3279,10 +3307,10 @@ impl Perform for Grid {
             // https://vt100.net/docs/vt510-rm/DA1.html
             match intermediates.get(0) {
                 None | Some(0) => {
-                    // primary device attributes - VT220 with sixel
-                    let terminal_capabilities = "\u{1b}[?62;4c";
+                    // primary device attributes - VT220 with sixel, or whatever the
+                    // controlling remote client's terminal is
                     self.pending_messages_to_pty
-                        .push(terminal_capabilities.as_bytes().to_vec());
+                        .push(primary_device_attributes().into_bytes());
                 },
                 Some(b'>') => {
                     // secondary device attributes
@@ -3452,6 +3480,18 @@ impl Perform for Grid {
             (b'8', None) => {
                 self.restore_cursor_position();
             },
+            (b'3', Some(b'#')) => {
+                self.set_line_size_at_cursor(LineSize::DoubleHeightTop);
+            },
+            (b'4', Some(b'#')) => {
+                self.set_line_size_at_cursor(LineSize::DoubleHeightBottom);
+            },
+            (b'5', Some(b'#')) => {
+                self.set_line_size_at_cursor(LineSize::Single);
+            },
+            (b'6', Some(b'#')) => {
+                self.set_line_size_at_cursor(LineSize::DoubleWidth);
+            },
             (b'8', Some(b'#')) => {
                 let mut fill_character = EMPTY_TERMINAL_CHARACTER;
                 fill_character.character = 'E';
@@ -3509,10 +3549,21 @@ impl AlternateScreenState {
     }
 }
 
+/// DEC line size, set per line by ESC # 3/4/5/6
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineSize {
+    #[default]
+    Single,
+    DoubleWidth,
+    DoubleHeightTop,
+    DoubleHeightBottom,
+}
+
 #[derive(Clone)]
 pub struct Row {
     pub columns: VecDeque<TerminalCharacter>,
     pub is_canonical: bool,
+    pub line_size: LineSize,
     width: Option<usize>,
 }
 
@@ -3530,6 +3581,7 @@ impl Row {
         Row {
             columns: VecDeque::new(),
             is_canonical: false,
+            line_size: LineSize::Single,
             width: None,
         }
     }
@@ -3537,6 +3589,7 @@ impl Row {
         Row {
             columns,
             is_canonical: false,
+            line_size: LineSize::Single,
             width: None,
         }
     }
@@ -0,0 +1,99 @@
+//! What panes tell applications asking about the terminal.
+//!
+//! Applications find out what they're drawing on with queries such as DA1 (`CSI c`)
+//! and XTGETTCAP (`DCS + q`). Panes answer them for the terminal the session runs in,
+//! unless a remote client is in control: its output is read on that client's terminal,
+//! so the answers that terminal gave at attach are used instead. They're kept for the
+//! screen thread, which owns every grid, rather than handed to each pane.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// DA1 reply while no remote client is in control: VT220 with sixel
+const LOCAL_DEVICE_ATTRIBUTES: &str = "\u{1b}[?62;4c";
+
+/// How the controlling remote client's terminal answers queries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TerminalAnswers {
+    /// DA1 attributes after the conformance level
+    pub device_attributes: Vec<u32>,
+    /// XTGETTCAP capability values by name
+    pub termcap: BTreeMap<String, String>,
+}
+
+thread_local! {
+    static CONTROLLER_ANSWERS: RefCell<Option<TerminalAnswers>> = const { RefCell::new(None) };
+}
+
+/// Answer for the controlling remote client's terminal from now on, or for the local
+/// one again with `None`
+pub fn set_controller_answers(answers: Option<TerminalAnswers>) {
+    CONTROLLER_ANSWERS.with(|current| *current.borrow_mut() = answers);
+}
+
+/// The reply to a DA1 query
+pub fn primary_device_attributes() -> String {
+    CONTROLLER_ANSWERS.with(|current| match &*current.borrow() {
+        Some(answers) => {
+            let mut reply = String::from("\u{1b}[?62");
+            for attribute in &answers.device_attributes {
+                let _ = write!(reply, ";{}", attribute);
+            }
+            reply.push('c');
+            reply
+        },
+        None => LOCAL_DEVICE_ATTRIBUTES.to_string(),
+    })
+}
+
+/// The replies to an XTGETTCAP query for `query`, hex encoded capability names
+/// separated by `;`, one reply per name. There are none while no remote client is in
+/// control, as the local terminal's capabilities aren't known.
+pub fn termcap_replies(query: &[u8]) -> Vec<String> {
+    CONTROLLER_ANSWERS.with(|current| {
+        let current = current.borrow();
+        let Some(answers) = current.as_ref() else {
+            return vec![];
+        };
+        query
+            .split(|byte| *byte == b';')
+            .filter(|encoded_name| !encoded_name.is_empty())
+            .map(|encoded_name| {
+                let name = hex_decode(encoded_name);
+                match name
+                    .as_ref()
+                    .and_then(|name| Some((name, answers.termcap.get(name)?)))
+                {
+                    Some((name, value)) => format!(
+                        "\u{1b}P1+r{}={}\u{1b}\\",
+                        hex_encode(name),
+                        hex_encode(value)
+                    ),
+                    None => format!(
+                        "\u{1b}P0+r{}\u{1b}\\",
+                        name.as_deref().map(hex_encode).unwrap_or_default()
+                    ),
+                }
+            })
+            .collect()
+    })
+}
+
+fn hex_encode(text: &str) -> String {
+    text.bytes().fold(String::new(), |mut encoded, byte| {
+        let _ = write!(encoded, "{:02X}", byte);
+        encoded
+    })
+}
+
+fn hex_decode(encoded: &[u8]) -> Option<String> {
+    if encoded.len() % 2 != 0 {
+        return None;
+    }
+    let bytes = encoded
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
@@ -3,7 +3,9 @@ use crate::panes::sixel::SixelImageStore;
 use crate::panes::LinkHandler;
 use crate::panes::{
     grid::Grid,
-    terminal_character::{render_first_run_banner, TerminalCharacter, EMPTY_TERMINAL_CHARACTER},
+    terminal_character::{
+        render_first_run_banner, AnsiCode, CursorShape, TerminalCharacter, EMPTY_TERMINAL_CHARACTER,
+    },
 };
 use crate::pty::VteBytes;
 use crate::route::NotificationEnd;
@@ -557,6 +559,12 @@ impl Pane for TerminalPane {
     fn cursor_shape_csi(&self) -> String {
         self.grid.cursor_shape().get_csi_str().to_string()
     }
+    fn cursor_shape(&self) -> CursorShape {
+        self.grid.cursor_shape()
+    }
+    fn cursor_color(&self) -> Option<AnsiCode> {
+        self.grid.cursor_color()
+    }
     fn drain_messages_to_pty(&mut self) -> Vec<Vec<u8>> {
         self.grid.pending_messages_to_pty.drain(..).collect()
     }
@@ -1,6 +1,7 @@
 use super::super::Grid;
-use crate::panes::grid::SixelImageStore;
+use crate::panes::grid::{LineSize, SixelImageStore};
 use crate::panes::link_handler::LinkHandler;
+use crate::panes::terminal_character::AnsiCode;
 use ::insta::assert_snapshot;
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -3475,6 +3476,39 @@ pub fn osc_4_color_query() {
     assert_eq!(message_string, "\u{1b}]4;222;rgb:ffff/d7d7/8787\u{1b}\\");
 }
 
+#[test]
+pub fn osc_12_sets_and_resets_cursor_color() {
+    let mut vte_parser = vte::Parser::new();
+    let sixel_image_store = Rc::new(RefCell::new(SixelImageStore::default()));
+    let terminal_emulator_color_codes = Rc::new(RefCell::new(HashMap::new()));
+    let debug = false;
+    let arrow_fonts = true;
+    let styled_underlines = true;
+    let explicitly_disable_kitty_keyboard_protocol = false;
+    let mut grid = Grid::new(
+        51,
+        97,
+        Rc::new(RefCell::new(Palette::default())),
+        terminal_emulator_color_codes,
+        Rc::new(RefCell::new(LinkHandler::new())),
+        Rc::new(RefCell::new(None)),
+        sixel_image_store,
+        Style::default(),
+        debug,
+        arrow_fonts,
+        styled_underlines,
+        explicitly_disable_kitty_keyboard_protocol,
+    );
+    for byte in "\u{1b}]12;rgb:ff/80/00\u{7}".as_bytes() {
+        vte_parser.advance(&mut grid, *byte);
+    }
+    assert_eq!(grid.cursor_color(), Some(AnsiCode::RgbCode((255, 128, 0))));
+    for byte in "\u{1b}]112\u{7}".as_bytes() {
+        vte_parser.advance(&mut grid, *byte);
+    }
+    assert_eq!(grid.cursor_color(), None);
+}
+
 #[test]
 pub fn xtsmgraphics_color_register_count() {
     let mut vte_parser = vte::Parser::new();
@@ -3900,3 +3934,101 @@ fn cannot_escape_scroll_region() {
     }
     assert_snapshot!(format!("{:?}", grid));
 }
+
+#[test]
+fn dec_line_size_applies_to_cursor_line() {
+    let mut vte_parser = vte::Parser::new();
+    let sixel_image_store = Rc::new(RefCell::new(SixelImageStore::default()));
+    let terminal_emulator_color_codes = Rc::new(RefCell::new(HashMap::new()));
+    let debug = false;
+    let arrow_fonts = true;
+    let styled_underlines = true;
+    let explicitly_disable_kitty_keyboard_protocol = false;
+    let mut grid = Grid::new(
+        10,
+        40,
+        Rc::new(RefCell::new(Palette::default())),
+        terminal_emulator_color_codes,
+        Rc::new(RefCell::new(LinkHandler::new())),
+        Rc::new(RefCell::new(None)),
+        sixel_image_store,
+        Style::default(),
+        debug,
+        arrow_fonts,
+        styled_underlines,
+        explicitly_disable_kitty_keyboard_protocol,
+    );
+    let content =
+        "\u{1b}#6Wide\n\r\u{1b}#3Tall\n\r\u{1b}#4Tall\n\r\u{1b}#6\u{1b}#5Normal".as_bytes();
+    for byte in content {
+        vte_parser.advance(&mut grid, *byte);
+    }
+    let line_sizes: Vec<LineSize> = grid.viewport.iter().map(|row| row.line_size).collect();
+    assert_eq!(
+        line_sizes,
+        vec![
+            LineSize::DoubleWidth,
+            LineSize::DoubleHeightTop,
+            LineSize::DoubleHeightBottom,
+            LineSize::Single,
+        ]
+    );
+}
+
+#[test]
+fn terminal_queries_answered_for_remote_controller() {
+    use crate::panes::terminal_answers::{set_controller_answers, TerminalAnswers};
+
+    let mut vte_parser = vte::Parser::new();
+    let sixel_image_store = Rc::new(RefCell::new(SixelImageStore::default()));
+    let terminal_emulator_color_codes = Rc::new(RefCell::new(HashMap::new()));
+    let debug = false;
+    let arrow_fonts = true;
+    let styled_underlines = true;
+    let explicitly_disable_kitty_keyboard_protocol = false;
+    let mut grid = Grid::new(
+        51,
+        97,
+        Rc::new(RefCell::new(Palette::default())),
+        terminal_emulator_color_codes,
+        Rc::new(RefCell::new(LinkHandler::new())),
+        Rc::new(RefCell::new(None)),
+        sixel_image_store,
+        Style::default(),
+        debug,
+        arrow_fonts,
+        styled_underlines,
+        explicitly_disable_kitty_keyboard_protocol,
+    );
+    // DA1, then XTGETTCAP for "TN" and "Ms"
+    let queries = "\x1b[c\x1bP+q544E;4D73\x1b\\";
+    let mut replies = |grid: &mut Grid| {
+        for byte in queries.as_bytes() {
+            vte_parser.advance(grid, *byte);
+        }
+        grid.pending_messages_to_pty
+            .drain(..)
+            .map(|bytes| String::from_utf8(bytes).unwrap())
+            .collect::<Vec<String>>()
+    };
+
+    assert_eq!(replies(&mut grid), vec!["\x1b[?62;4c"]);
+
+    set_controller_answers(Some(TerminalAnswers {
+        device_attributes: vec![22, 52],
+        termcap: [("TN".to_string(), "xterm-kitty".to_string())]
+            .into_iter()
+            .collect(),
+    }));
+    assert_eq!(
+        replies(&mut grid),
+        vec![
+            "\x1b[?62;22;52c",
+            "\x1bP1+r544E=787465726D2D6B69747479\x1b\\",
+            "\x1bP0+r4D73\x1b\\",
+        ]
+    );
+
+    set_controller_answers(None);
+    assert_eq!(replies(&mut grid), vec!["\x1b[?62;4c"]);
+}
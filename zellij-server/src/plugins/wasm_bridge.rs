@@ -1902,6 +1902,11 @@ fn check_event_permission(
         | Event::FailedToWriteConfigToDisk(..)
         | Event::CommandPaneReRun(..)
         | Event::CwdChanged(..)
+        | Event::RemoteClientAttached(..)
+        | Event::RemoteClientDetached(..)
+        | Event::RemoteLeaseChanged(..)
+        | Event::RemoteApprovalRequested(..)
+        | Event::RemoteApprovalResolved(..)
         | Event::InputReceived => PermissionType::ReadApplicationState,
         Event::WebServerStatus(..) => PermissionType::StartWebServer,
         Event::PaneRenderReport(..) => PermissionType::ReadPaneContents,
@@ -35,6 +35,9 @@ use zellij_utils::web_server_commands::shutdown_all_webserver_instances;
 
 use crate::{panes::PaneId, screen::ScreenInstruction};
 
+#[cfg(feature = "remote")]
+use crate::remote::RemoteInstruction;
+
 use prost::Message;
 use zellij_utils::{
     consts::{VERSION, ZELLIJ_SESSION_INFO_CACHE_DIR, ZELLIJ_SOCK_DIR},
@@ -441,6 +444,16 @@ fn host_run_plugin_command(mut caller: Caller<'_, PluginEnv>) {
                         write_config_to_disk,
                     } => rebind_keys(env, keys_to_rebind, keys_to_unbind, write_config_to_disk)?,
                     PluginCommand::ListClients => list_clients(env),
+                    PluginCommand::ListRemoteClients => list_remote_clients(env),
+                    PluginCommand::RevokeRemoteLease => revoke_remote_lease(env),
+                    PluginCommand::StartRemoteServing => start_remote_serving(env),
+                    PluginCommand::StopRemoteServing => stop_remote_serving(env),
+                    PluginCommand::RespondToRemoteApproval(request_id, approved) => {
+                        respond_to_remote_approval(env, request_id, approved)
+                    },
+                    PluginCommand::SetRemoteClientLatency(remote_id, delay_ms) => {
+                        set_remote_client_latency(env, remote_id, delay_ms)
+                    },
                     PluginCommand::ChangeHostFolder(new_host_folder) => {
                         change_host_folder(env, new_host_folder)
                     },
@@ -2212,6 +2225,87 @@ fn list_clients(env: &PluginEnv) {
     });
 }
 
+fn list_remote_clients(env: &PluginEnv) {
+    let _ = env.senders.to_screen.as_ref().map(|sender| {
+        sender.send(ScreenInstruction::ListRemoteClientsToPlugin(
+            env.plugin_id,
+            env.client_id,
+        ))
+    });
+}
+
+#[cfg(feature = "remote")]
+fn revoke_remote_lease(env: &PluginEnv) {
+    if let Err(e) = env.senders.send_to_remote(RemoteInstruction::RevokeLease) {
+        log::error!("Failed to revoke remote lease: {:?}", e);
+    }
+}
+
+#[cfg(not(feature = "remote"))]
+fn revoke_remote_lease(_env: &PluginEnv) {
+    log::error!("This version of Zellij was compiled without remote access support!");
+}
+
+#[cfg(feature = "remote")]
+fn start_remote_serving(env: &PluginEnv) {
+    if let Err(e) = env.senders.send_to_remote(RemoteInstruction::Start) {
+        log::error!("Failed to start remote serving: {:?}", e);
+    }
+}
+
+#[cfg(not(feature = "remote"))]
+fn start_remote_serving(_env: &PluginEnv) {
+    log::error!("This version of Zellij was compiled without remote access support!");
+}
+
+#[cfg(feature = "remote")]
+fn stop_remote_serving(env: &PluginEnv) {
+    if let Err(e) = env.senders.send_to_remote(RemoteInstruction::Stop) {
+        log::error!("Failed to stop remote serving: {:?}", e);
+    }
+}
+
+#[cfg(not(feature = "remote"))]
+fn stop_remote_serving(_env: &PluginEnv) {
+    log::error!("This version of Zellij was compiled without remote access support!");
+}
+
+#[cfg(feature = "remote")]
+fn respond_to_remote_approval(env: &PluginEnv, request_id: u64, approved: bool) {
+    if let Err(e) = env
+        .senders
+        .send_to_remote(RemoteInstruction::AnswerApproval {
+            request_id,
+            approved,
+        })
+    {
+        log::error!("Failed to answer remote approval request: {:?}", e);
+    }
+}
+
+#[cfg(not(feature = "remote"))]
+fn respond_to_remote_approval(_env: &PluginEnv, _request_id: u64, _approved: bool) {
+    log::error!("This version of Zellij was compiled without remote access support!");
+}
+
+#[cfg(feature = "remote")]
+fn set_remote_client_latency(env: &PluginEnv, remote_id: u64, delay_ms: u64) {
+    if let Err(e) = env
+        .senders
+        .send_to_remote(RemoteInstruction::SetClientLatency {
+            remote_id,
+            delay_ms,
+        })
+    {
+        log::error!("Failed to set remote client latency: {:?}", e);
+    }
+}
+
+#[cfg(not(feature = "remote"))]
+fn set_remote_client_latency(_env: &PluginEnv, _remote_id: u64, _delay_ms: u64) {
+    log::error!("This version of Zellij was compiled without remote access support!");
+}
+
 fn change_host_folder(env: &PluginEnv, new_host_folder: PathBuf) {
     let _ = env.senders.to_plugin.as_ref().map(|sender| {
         sender.send(PluginInstruction::ChangePluginHostDir(
@@ -3105,12 +3199,18 @@ fn check_command_permission(
         | PluginCommand::ReplacePaneWithExistingPane(..)
         | PluginCommand::KillSessions(..)
         | PluginCommand::SendSigintToPaneId(..)
-        | PluginCommand::SendSigkillToPaneId(..) => PermissionType::ChangeApplicationState,
+        | PluginCommand::SendSigkillToPaneId(..)
+        | PluginCommand::RevokeRemoteLease
+        | PluginCommand::StartRemoteServing
+        | PluginCommand::StopRemoteServing
+        | PluginCommand::RespondToRemoteApproval(..)
+        | PluginCommand::SetRemoteClientLatency(..) => PermissionType::ChangeApplicationState,
         PluginCommand::UnblockCliPipeInput(..)
         | PluginCommand::BlockCliPipeInput(..)
         | PluginCommand::CliPipeOutput(..) => PermissionType::ReadCliPipes,
         PluginCommand::MessageToPlugin(..) => PermissionType::MessageAndLaunchOtherPlugins,
         PluginCommand::ListClients
+        | PluginCommand::ListRemoteClients
         | PluginCommand::DumpSessionLayout
         | PluginCommand::GetPanePid { .. } => PermissionType::ReadApplicationState,
         PluginCommand::RebindKeys { .. } | PluginCommand::Reconfigure(..) => {
@@ -141,6 +141,9 @@ pub enum PtyInstruction {
         response_channel: crossbeam::channel::Sender<GetPanePidResponse>,
     },
     UpdateAndReportCwds,
+    /// Follow the cwd and command of this pane for remote clients (None to stop)
+    #[cfg(feature = "remote")]
+    ReportFocusToRemote(Option<PaneId>),
     Exit,
 }
 
@@ -170,6 +173,8 @@ impl From<&PtyInstruction> for PtyContext {
             PtyInstruction::SendSigkillToPaneId(..) => PtyContext::SendSigkillToPaneId,
             PtyInstruction::GetPanePid { .. } => PtyContext::GetPanePid,
             PtyInstruction::UpdateAndReportCwds => PtyContext::UpdateAndReportCwds,
+            #[cfg(feature = "remote")]
+            PtyInstruction::ReportFocusToRemote(..) => PtyContext::ReportFocusToRemote,
             PtyInstruction::Exit => PtyContext::Exit,
         }
     }
@@ -186,6 +191,11 @@ pub(crate) struct Pty {
     post_command_discovery_hook: Option<String>,
     plugin_cwds: HashMap<u32, PathBuf>,   // plugin_id -> cwd
     terminal_cwds: HashMap<u32, PathBuf>, // terminal_id -> cwd
+    #[cfg(feature = "remote")]
+    remote_focus: Option<PaneId>,
+    /// cwd and command last reported for `remote_focus`
+    #[cfg(feature = "remote")]
+    remote_focus_process: Option<(Option<PathBuf>, Option<String>)>,
 }
 
 pub(crate) fn pty_thread_main(mut pty: Pty, layout: Box<Layout>) -> Result<()> {
@@ -788,6 +798,14 @@ pub(crate) fn pty_thread_main(mut pty: Pty, layout: Box<Layout>) -> Result<()> {
             },
             PtyInstruction::UpdateAndReportCwds => {
                 pty.update_and_report_cwds();
+                #[cfg(feature = "remote")]
+                pty.report_focus_process_to_remote();
+            },
+            #[cfg(feature = "remote")]
+            PtyInstruction::ReportFocusToRemote(pane_id) => {
+                pty.remote_focus = pane_id;
+                pty.remote_focus_process = None;
+                pty.report_focus_process_to_remote();
             },
             PtyInstruction::Exit => break,
         }
@@ -813,6 +831,10 @@ impl Pty {
             post_command_discovery_hook,
             plugin_cwds: HashMap::new(),
             terminal_cwds: HashMap::new(),
+            #[cfg(feature = "remote")]
+            remote_focus: None,
+            #[cfg(feature = "remote")]
+            remote_focus_process: None,
         }
     }
     pub fn get_default_terminal(
@@ -1950,6 +1972,43 @@ impl Pty {
         }
     }
 
+    /// Send the remote thread the cwd and foreground command of the pane remote clients
+    /// are focused on, if they changed. Plugin panes have neither.
+    #[cfg(feature = "remote")]
+    pub fn report_focus_process_to_remote(&mut self) {
+        let Some(pane_id) = self.remote_focus else {
+            return;
+        };
+        let child_pid = match pane_id {
+            PaneId::Terminal(terminal_id) => self.id_to_child_pid.get(&terminal_id).copied(),
+            PaneId::Plugin(_) => None,
+        };
+        let process = match (child_pid, self.bus.os_input.as_ref()) {
+            (Some(child_pid), Some(os_input)) => {
+                let pid = Pid::from_raw(child_pid);
+                let (pids_to_cwds, pids_to_cmds) = os_input.get_cwds(vec![pid]);
+                let ppids_to_cmds =
+                    os_input.get_all_cmds_by_ppid(&self.post_command_discovery_hook);
+                // Prefer what's running in the shell over the shell itself
+                let command = ppids_to_cmds
+                    .get(&format!("{}", child_pid))
+                    .or_else(|| pids_to_cmds.get(&pid))
+                    .map(|cmd| cmd.join(" "));
+                (pids_to_cwds.get(&pid).cloned(), command)
+            },
+            _ => (None, None),
+        };
+        if self.remote_focus_process.as_ref() == Some(&process) {
+            return;
+        }
+        let (cwd, command) = process.clone();
+        self.remote_focus_process = Some(process);
+        let _ = self
+            .bus
+            .senders
+            .send_to_remote(crate::remote::RemoteInstruction::FocusedPaneProcess { cwd, command });
+    }
+
     pub fn reconfigure(
         &mut self,
         default_editor: Option<PathBuf>,
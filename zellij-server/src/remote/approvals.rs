@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use zellij_remote_protocol::RequestControl;
+use zellij_utils::data::RemoteApprovalAction;
+
+/// What a remote client asked for, kept until the local user answers
+#[derive(Debug, Clone, PartialEq)]
+pub enum PendingAction {
+    /// A `RequestControl` with `force` set while another client held the lease
+    ForceTakeover(RequestControl),
+}
+
+impl PendingAction {
+    pub fn kind(&self) -> RemoteApprovalAction {
+        match self {
+            PendingAction::ForceTakeover(_) => RemoteApprovalAction::ForceTakeover,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingApproval {
+    pub request_id: u64,
+    pub remote_id: u64,
+    pub action: PendingAction,
+    pub deadline: Instant,
+}
+
+/// Sensitive requests from remote clients waiting for the local user to approve them.
+///
+/// Each client has at most one request of a kind waiting, so a client repeating a
+/// request doesn't stack up prompts. Requests not answered by their deadline are
+/// denied.
+#[derive(Debug)]
+pub struct PendingApprovals {
+    timeout: Duration,
+    next_request_id: u64,
+    pending: BTreeMap<u64, PendingApproval>,
+}
+
+impl PendingApprovals {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            next_request_id: 1,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns None when the client is already waiting on a request of this kind.
+    pub fn request(
+        &mut self,
+        remote_id: u64,
+        action: PendingAction,
+        now: Instant,
+    ) -> Option<&PendingApproval> {
+        let waiting = self
+            .pending
+            .values()
+            .any(|p| p.remote_id == remote_id && p.action.kind() == action.kind());
+        if waiting {
+            return None;
+        }
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        self.pending.insert(
+            request_id,
+            PendingApproval {
+                request_id,
+                remote_id,
+                action,
+                deadline: now + self.timeout,
+            },
+        );
+        self.pending.get(&request_id)
+    }
+
+    /// The local user answered; None if the request already timed out or was dropped.
+    pub fn resolve(&mut self, request_id: u64) -> Option<PendingApproval> {
+        self.pending.remove(&request_id)
+    }
+
+    /// Remove and return the requests whose deadline has passed.
+    pub fn expire(&mut self, now: Instant) -> Vec<PendingApproval> {
+        let expired: Vec<u64> = self
+            .pending
+            .values()
+            .filter(|p| p.deadline <= now)
+            .map(|p| p.request_id)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|id| self.pending.remove(&id))
+            .collect()
+    }
+
+    /// Drop a departing client's requests, returning their ids.
+    pub fn remove_client(&mut self, remote_id: u64) -> Vec<u64> {
+        let ids: Vec<u64> = self
+            .pending
+            .values()
+            .filter(|p| p.remote_id == remote_id)
+            .map(|p| p.request_id)
+            .collect();
+        for id in &ids {
+            self.pending.remove(id);
+        }
+        ids
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn takeover() -> PendingAction {
+        PendingAction::ForceTakeover(RequestControl {
+            force: true,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_one_pending_request_per_client_and_kind() {
+        let now = Instant::now();
+        let mut approvals = PendingApprovals::new(Duration::from_secs(10));
+
+        let first = approvals.request(1, takeover(), now).unwrap().request_id;
+        assert!(approvals.request(1, takeover(), now).is_none());
+        let other = approvals.request(2, takeover(), now).unwrap().request_id;
+        assert_ne!(first, other);
+
+        assert_eq!(approvals.resolve(first).unwrap().remote_id, 1);
+        assert!(approvals.resolve(first).is_none());
+        assert!(approvals.request(1, takeover(), now).is_some());
+    }
+
+    #[test]
+    fn test_unanswered_requests_expire() {
+        let now = Instant::now();
+        let mut approvals = PendingApprovals::new(Duration::from_secs(10));
+        let request_id = approvals.request(1, takeover(), now).unwrap().request_id;
+
+        assert!(approvals.expire(now + Duration::from_secs(9)).is_empty());
+        let expired = approvals.expire(now + Duration::from_secs(10));
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].request_id, request_id);
+        assert!(approvals.is_empty());
+    }
+
+    #[test]
+    fn test_departing_client_drops_its_requests() {
+        let now = Instant::now();
+        let mut approvals = PendingApprovals::new(Duration::from_secs(10));
+        let request_id = approvals.request(1, takeover(), now).unwrap().request_id;
+        approvals.request(2, takeover(), now);
+
+        assert_eq!(approvals.remove_client(1), vec![request_id]);
+        assert!(approvals.resolve(request_id).is_none());
+        assert!(!approvals.is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Control requests arriving faster than this from one client are dropped without a reply
+pub const CONTROL_REQUEST_MIN_INTERVAL_MS: u64 = 250;
+/// Denials within `CONTROL_DENIAL_WINDOW_MS` that get a client muted
+pub const CONTROL_DENIALS_BEFORE_MUTE: u32 = 3;
+pub const CONTROL_DENIAL_WINDOW_MS: u64 = 10_000;
+/// Length of the first mute; each further mute doubles it, up to the max
+pub const CONTROL_MUTE_BASE_MS: u64 = 5_000;
+pub const CONTROL_MUTE_MAX_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlRequestDecision {
+    /// Pass the request on to the lease manager
+    Allow,
+    /// Too soon after the previous request; drop it silently
+    Debounced,
+    /// The client is muted for repeated denials
+    Muted { remaining: Duration },
+}
+
+#[derive(Debug, Default)]
+struct ControlHistory {
+    last_request: Option<Instant>,
+    denials: u32,
+    denial_window_start: Option<Instant>,
+    mute_count: u32,
+    muted_until: Option<Instant>,
+}
+
+/// Protects the lease manager (and other clients, who see every lease change) from
+/// clients flooding `RequestControl`, e.g. with a key bound to it.
+#[derive(Debug)]
+pub struct ControlRequestThrottle {
+    min_interval: Duration,
+    denial_window: Duration,
+    clients: HashMap<u64, ControlHistory>,
+}
+
+impl ControlRequestThrottle {
+    pub fn new(min_interval: Duration, denial_window: Duration) -> Self {
+        Self {
+            min_interval,
+            denial_window,
+            clients: HashMap::new(),
+        }
+    }
+
+    /// Decide what to do with a control request; every request that isn't debounced
+    /// restarts the debounce interval.
+    pub fn check(&mut self, client_id: u64, now: Instant) -> ControlRequestDecision {
+        let history = self.clients.entry(client_id).or_default();
+        if let Some(last) = history.last_request {
+            if now.saturating_duration_since(last) < self.min_interval {
+                return ControlRequestDecision::Debounced;
+            }
+        }
+        history.last_request = Some(now);
+
+        match history.muted_until {
+            Some(until) if now < until => ControlRequestDecision::Muted {
+                remaining: until - now,
+            },
+            _ => ControlRequestDecision::Allow,
+        }
+    }
+
+    /// A granted request clears the client's penalty.
+    pub fn record_grant(&mut self, client_id: u64) {
+        if let Some(history) = self.clients.get_mut(&client_id) {
+            history.denials = 0;
+            history.denial_window_start = None;
+            history.mute_count = 0;
+            history.muted_until = None;
+        }
+    }
+
+    /// Returns the mute duration when this denial gets the client muted.
+    pub fn record_denial(&mut self, client_id: u64, now: Instant) -> Option<Duration> {
+        let history = self.clients.entry(client_id).or_default();
+        let window_expired = history
+            .denial_window_start
+            .is_none_or(|start| now.saturating_duration_since(start) >= self.denial_window);
+        if window_expired {
+            history.denials = 0;
+            history.denial_window_start = Some(now);
+        }
+        history.denials += 1;
+        if history.denials < CONTROL_DENIALS_BEFORE_MUTE {
+            return None;
+        }
+
+        let mute_ms = CONTROL_MUTE_BASE_MS
+            .saturating_mul(1 << history.mute_count.min(16))
+            .min(CONTROL_MUTE_MAX_MS);
+        let mute = Duration::from_millis(mute_ms);
+        history.mute_count += 1;
+        history.muted_until = Some(now + mute);
+        history.denials = 0;
+        history.denial_window_start = None;
+        Some(mute)
+    }
+
+    pub fn remove_client(&mut self, client_id: u64) {
+        self.clients.remove(&client_id);
+    }
+}
+
+impl Default for ControlRequestThrottle {
+    fn default() -> Self {
+        Self::new(
+            Duration::from_millis(CONTROL_REQUEST_MIN_INTERVAL_MS),
+            Duration::from_millis(CONTROL_DENIAL_WINDOW_MS),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_requests_within_interval_are_debounced() {
+        let mut throttle = ControlRequestThrottle::default();
+        let start = Instant::now();
+
+        assert_eq!(throttle.check(1, start), ControlRequestDecision::Allow);
+        assert_eq!(
+            throttle.check(1, start + ms(100)),
+            ControlRequestDecision::Debounced
+        );
+        // Other clients are tracked separately
+        assert_eq!(
+            throttle.check(2, start + ms(100)),
+            ControlRequestDecision::Allow
+        );
+        assert_eq!(
+            throttle.check(1, start + ms(CONTROL_REQUEST_MIN_INTERVAL_MS)),
+            ControlRequestDecision::Allow
+        );
+    }
+
+    #[test]
+    fn test_repeated_denials_mute_client() {
+        let mut throttle = ControlRequestThrottle::default();
+        let start = Instant::now();
+
+        assert_eq!(throttle.record_denial(1, start), None);
+        assert_eq!(throttle.record_denial(1, start + ms(1_000)), None);
+        assert_eq!(
+            throttle.record_denial(1, start + ms(2_000)),
+            Some(ms(CONTROL_MUTE_BASE_MS))
+        );
+
+        assert_eq!(
+            throttle.check(1, start + ms(3_000)),
+            ControlRequestDecision::Muted {
+                remaining: ms(CONTROL_MUTE_BASE_MS - 1_000)
+            }
+        );
+        assert_eq!(
+            throttle.check(1, start + ms(2_000 + CONTROL_MUTE_BASE_MS)),
+            ControlRequestDecision::Allow
+        );
+    }
+
+    #[test]
+    fn test_denials_outside_window_do_not_accumulate() {
+        let mut throttle = ControlRequestThrottle::default();
+        let start = Instant::now();
+
+        throttle.record_denial(1, start);
+        throttle.record_denial(1, start + ms(1_000));
+        assert_eq!(
+            throttle.record_denial(1, start + ms(CONTROL_DENIAL_WINDOW_MS)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_mute_escalates_and_grant_resets() {
+        let mut throttle = ControlRequestThrottle::default();
+        let mut now = Instant::now();
+        let mut mutes = Vec::new();
+        for _ in 0..6 {
+            for _ in 0..CONTROL_DENIALS_BEFORE_MUTE {
+                if let Some(mute) = throttle.record_denial(1, now) {
+                    mutes.push(mute.as_millis() as u64);
+                }
+            }
+            now += ms(CONTROL_MUTE_MAX_MS);
+        }
+        assert_eq!(mutes, vec![5_000, 10_000, 20_000, 40_000, 60_000, 60_000]);
+
+        throttle.record_grant(1);
+        assert_eq!(throttle.check(1, now), ControlRequestDecision::Allow);
+        throttle.record_denial(1, now);
+        throttle.record_denial(1, now);
+        assert_eq!(
+            throttle.record_denial(1, now),
+            Some(ms(CONTROL_MUTE_BASE_MS))
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Where the time goes getting a frame to remote clients.
+//!
+//! A frame is painted from the screen's output (grid convert), taken into the frame
+//! store (ingest), diffed for each client (delta compute), then encoded and written to
+//! the client's stream by its sender task. Each stage keeps a moving average and its
+//! worst run against a share of a 60 Hz frame, so jank on a large terminal can be
+//! pinned on one of them rather than guessed at.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use zellij_utils::data::RemoteFrameStage;
+
+/// Weight of the newest run in a stage's moving average
+const AVERAGE_ALPHA: f64 = 0.125;
+/// A stage running over budget is warned about at most this often
+pub const OVER_BUDGET_WARN_INTERVAL: Duration = Duration::from_secs(10);
+/// How often the timings are passed on for `zellij remote status --verbose`
+pub const STAGE_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameStage {
+    /// Painting the screen's output into a `FrameStore`, on the screen thread
+    GridConvert,
+    /// Taking a new frame into the session's frame store
+    Ingest,
+    /// Working out every client's snapshot or delta
+    DeltaCompute,
+    /// Encoding and compressing one update for one client
+    Encode,
+    /// Writing one update to a client's stream
+    SocketWrite,
+}
+
+impl FrameStage {
+    pub const ALL: [FrameStage; 5] = [
+        FrameStage::GridConvert,
+        FrameStage::Ingest,
+        FrameStage::DeltaCompute,
+        FrameStage::Encode,
+        FrameStage::SocketWrite,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FrameStage::GridConvert => "grid_convert",
+            FrameStage::Ingest => "ingest",
+            FrameStage::DeltaCompute => "delta_compute",
+            FrameStage::Encode => "encode",
+            FrameStage::SocketWrite => "socket_write",
+        }
+    }
+
+    /// How long one run may take; together they leave room in a 16ms frame
+    pub fn budget(self) -> Duration {
+        Duration::from_millis(match self {
+            FrameStage::GridConvert => 4,
+            FrameStage::Ingest => 2,
+            FrameStage::DeltaCompute => 4,
+            FrameStage::Encode => 2,
+            FrameStage::SocketWrite => 4,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct StageStats {
+    average_ns: Option<f64>,
+    worst: Duration,
+    over_budget: u64,
+    last_warned: Option<Instant>,
+}
+
+/// Moving averages, worst runs and budget overruns of each [`FrameStage`]
+#[derive(Debug, Default)]
+pub struct FrameStageTimings {
+    stages: [StageStats; FrameStage::ALL.len()],
+}
+
+impl FrameStageTimings {
+    /// Record one run of `stage`. True when it ran over budget and that stage hasn't
+    /// been warned about in the last `OVER_BUDGET_WARN_INTERVAL`.
+    pub fn record(&mut self, stage: FrameStage, elapsed: Duration, now: Instant) -> bool {
+        let stats = &mut self.stages[stage as usize];
+        let ns = elapsed.as_nanos() as f64;
+        stats.average_ns = Some(match stats.average_ns {
+            Some(average) => average + AVERAGE_ALPHA * (ns - average),
+            None => ns,
+        });
+        stats.worst = stats.worst.max(elapsed);
+        if elapsed <= stage.budget() {
+            return false;
+        }
+        stats.over_budget += 1;
+        let warn = stats.last_warned.is_none_or(|warned| {
+            now.saturating_duration_since(warned) >= OVER_BUDGET_WARN_INTERVAL
+        });
+        if warn {
+            stats.last_warned = Some(now);
+        }
+        warn
+    }
+
+    pub fn average(&self, stage: FrameStage) -> Option<Duration> {
+        self.stages[stage as usize]
+            .average_ns
+            .map(|ns| Duration::from_nanos(ns.round() as u64))
+    }
+
+    pub fn worst(&self, stage: FrameStage) -> Duration {
+        self.stages[stage as usize].worst
+    }
+
+    /// Runs of `stage` that took longer than its budget
+    pub fn over_budget(&self, stage: FrameStage) -> u64 {
+        self.stages[stage as usize].over_budget
+    }
+
+    /// The stages that have run, in pipeline order
+    pub fn summary(&self) -> Vec<RemoteFrameStage> {
+        FrameStage::ALL
+            .iter()
+            .filter_map(|&stage| {
+                Some(RemoteFrameStage {
+                    name: stage.name().to_string(),
+                    average_us: self.average(stage)?.as_micros() as u64,
+                    worst_us: self.worst(stage).as_micros() as u64,
+                    budget_us: stage.budget().as_micros() as u64,
+                    over_budget: self.over_budget(stage),
+                })
+            })
+            .collect()
+    }
+}
+
+/// [`FrameStageTimings`] shared by the main loop and the clients' sender tasks.
+/// Cloning shares the timings.
+#[derive(Debug, Clone, Default)]
+pub struct StageRecorder {
+    timings: Arc<Mutex<FrameStageTimings>>,
+}
+
+impl StageRecorder {
+    /// Record one run of `stage`, warning if it's over budget
+    pub fn record(&self, stage: FrameStage, elapsed: Duration) {
+        let mut timings = self.timings.lock().unwrap_or_else(|e| e.into_inner());
+        if timings.record(stage, elapsed, Instant::now()) {
+            log::warn!(
+                "Remote frame stage {} took {}us, over its {}us budget (average {}us, {} runs over budget)",
+                stage.name(),
+                elapsed.as_micros(),
+                stage.budget().as_micros(),
+                timings.average(stage).unwrap_or_default().as_micros(),
+                timings.over_budget(stage)
+            );
+        }
+    }
+
+    /// Run `f` as one run of `stage`
+    pub fn time<T>(&self, stage: FrameStage, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        self.record(stage, started.elapsed());
+        result
+    }
+
+    pub fn summary(&self) -> Vec<RemoteFrameStage> {
+        self.timings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .summary()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages_averaged_and_overruns_warned_once_per_interval() {
+        let mut timings = FrameStageTimings::default();
+        let now = Instant::now();
+        let budget = FrameStage::DeltaCompute.budget();
+
+        assert!(!timings.record(FrameStage::DeltaCompute, budget / 2, now));
+        assert_eq!(timings.average(FrameStage::DeltaCompute), Some(budget / 2));
+        assert!(timings.record(FrameStage::DeltaCompute, budget * 3, now));
+        // Over again, but warned about a moment ago
+        assert!(!timings.record(
+            FrameStage::DeltaCompute,
+            budget * 2,
+            now + Duration::from_secs(1)
+        ));
+        assert!(timings.record(
+            FrameStage::DeltaCompute,
+            budget * 2,
+            now + OVER_BUDGET_WARN_INTERVAL
+        ));
+
+        assert_eq!(timings.over_budget(FrameStage::DeltaCompute), 3);
+        assert_eq!(timings.worst(FrameStage::DeltaCompute), budget * 3);
+        let average = timings.average(FrameStage::DeltaCompute).unwrap();
+        assert!(average > budget / 2 && average < budget * 3);
+        // Other stages are kept apart
+        assert_eq!(timings.average(FrameStage::Encode), None);
+        assert_eq!(timings.over_budget(FrameStage::Encode), 0);
+    }
+
+    #[test]
+    fn test_summary_lists_stages_that_ran_in_pipeline_order() {
+        let recorder = StageRecorder::default();
+        assert!(recorder.summary().is_empty());
+
+        recorder.record(FrameStage::SocketWrite, Duration::from_micros(300));
+        recorder
+            .clone()
+            .record(FrameStage::Ingest, Duration::from_micros(50));
+
+        let summary = recorder.summary();
+        assert_eq!(
+            summary,
+            vec![
+                RemoteFrameStage {
+                    name: "ingest".to_string(),
+                    average_us: 50,
+                    worst_us: 50,
+                    budget_us: 2_000,
+                    over_budget: 0,
+                },
+                RemoteFrameStage {
+                    name: "socket_write".to_string(),
+                    average_us: 300,
+                    worst_us: 300,
+                    budget_us: 4_000,
+                    over_budget: 0,
+                },
+            ]
+        );
+    }
+}
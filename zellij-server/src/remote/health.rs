@@ -0,0 +1,317 @@
+use std::time::{Duration, Instant};
+
+use zellij_remote_protocol::SessionState;
+
+/// How long the screen thread may leave a heartbeat unanswered, with no frame either,
+/// before it is considered stalled
+pub const SCREEN_STALL_TIMEOUT_MS: u64 = 10_000;
+/// Backoff hint sent to clients refused while the session is degraded
+pub const DEGRADED_RETRY_AFTER_MS: u32 = 2_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthIssue {
+    /// Sending to the screen thread failed; it has most likely exited
+    ScreenChannelClosed,
+    /// The channel delivering frames from the screen thread was closed
+    FrameChannelClosed,
+    /// A heartbeat was asked for and neither it nor a frame came back within the stall
+    /// timeout
+    ScreenStalled,
+}
+
+impl HealthIssue {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            HealthIssue::ScreenChannelClosed => "screen thread is not accepting input",
+            HealthIssue::FrameChannelClosed => "screen thread stopped sending frames",
+            HealthIssue::ScreenStalled => "screen thread is not responding",
+        }
+    }
+}
+
+/// Tracks whether the screen thread is still servicing the remote session.
+///
+/// Stalls are judged by heartbeat round trips alone, as plenty of input (typing at a
+/// password prompt, keys an app ignores) draws nothing. The screen is asked for a
+/// heartbeat once it has been quiet for half the stall timeout, and straight away
+/// after input is forwarded, so the heartbeat queues up behind the input. Nothing
+/// from it a full timeout after it was last heard from, with a heartbeat outstanding,
+/// counts as a stall. Channel closures are permanent; a stall clears as soon as a
+/// frame or heartbeat arrives.
+#[derive(Debug)]
+pub struct SessionHealth {
+    stall_timeout: Duration,
+    screen_channel_closed: bool,
+    frame_channel_closed: bool,
+    /// When the oldest input the screen hasn't yet shown it got past was forwarded
+    input_unconfirmed_since: Option<Instant>,
+    /// The last frame or heartbeat from the screen
+    last_heard_at: Option<Instant>,
+    /// When the heartbeat still unanswered was asked for
+    heartbeat_sent_at: Option<Instant>,
+    reported_state: SessionState,
+}
+
+impl SessionHealth {
+    pub fn new(stall_timeout: Duration) -> Self {
+        Self {
+            stall_timeout,
+            screen_channel_closed: false,
+            frame_channel_closed: false,
+            input_unconfirmed_since: None,
+            last_heard_at: None,
+            heartbeat_sent_at: None,
+            reported_state: SessionState::Running,
+        }
+    }
+
+    pub fn record_screen_send(&mut self, ok: bool, now: Instant) {
+        if ok {
+            self.input_unconfirmed_since.get_or_insert(now);
+        } else {
+            self.screen_channel_closed = true;
+        }
+    }
+
+    pub fn record_frame(&mut self, now: Instant) {
+        self.input_unconfirmed_since = None;
+        self.record_heartbeat(now);
+    }
+
+    /// Whether to ask the screen for a heartbeat: it has input to get past, or has
+    /// been quiet long enough.
+    pub fn heartbeat_due(&self, now: Instant) -> bool {
+        self.heartbeat_sent_at.is_none()
+            && !self.screen_channel_closed
+            && (self.input_unconfirmed_since.is_some()
+                || self
+                    .last_heard_at
+                    .is_none_or(|at| now.saturating_duration_since(at) >= self.stall_timeout / 2))
+    }
+
+    pub fn record_heartbeat_sent(&mut self, ok: bool, now: Instant) {
+        if ok {
+            self.heartbeat_sent_at = Some(now);
+        } else {
+            self.screen_channel_closed = true;
+        }
+    }
+
+    pub fn record_heartbeat(&mut self, now: Instant) {
+        // The screen handles what it is sent in order, so a heartbeat asked for after
+        // the pending input was answered after it: the input was dealt with, even if
+        // it drew nothing (like typing at a password prompt)
+        if let (Some(sent_at), Some(since)) = (self.heartbeat_sent_at, self.input_unconfirmed_since)
+        {
+            if sent_at >= since {
+                self.input_unconfirmed_since = None;
+            }
+        }
+        self.last_heard_at = Some(now);
+        self.heartbeat_sent_at = None;
+    }
+
+    pub fn record_frame_channel_closed(&mut self) {
+        self.frame_channel_closed = true;
+    }
+
+    pub fn issue(&self, now: Instant) -> Option<HealthIssue> {
+        if self.screen_channel_closed {
+            return Some(HealthIssue::ScreenChannelClosed);
+        }
+        if self.frame_channel_closed {
+            return Some(HealthIssue::FrameChannelClosed);
+        }
+        self.heartbeat_sent_at
+            .map(|sent_at| self.last_heard_at.unwrap_or(sent_at))
+            .is_some_and(|since| now.saturating_duration_since(since) >= self.stall_timeout)
+            .then_some(HealthIssue::ScreenStalled)
+    }
+
+    pub fn state(&self, now: Instant) -> SessionState {
+        if self.issue(now).is_some() {
+            SessionState::Degraded
+        } else {
+            SessionState::Running
+        }
+    }
+
+    /// Returns the new state (and the issue behind it, if any) when it differs from the
+    /// last state returned, so callers only notify clients on transitions.
+    pub fn poll_transition(&mut self, now: Instant) -> Option<(SessionState, Option<HealthIssue>)> {
+        let issue = self.issue(now);
+        let state = self.state(now);
+        if state == self.reported_state {
+            return None;
+        }
+        self.reported_state = state;
+        Some((state, issue))
+    }
+}
+
+impl Default for SessionHealth {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(SCREEN_STALL_TIMEOUT_MS))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health() -> SessionHealth {
+        SessionHealth::new(Duration::from_millis(100))
+    }
+
+    #[test]
+    fn test_healthy_until_heartbeat_behind_input_goes_unanswered() {
+        let mut health = health();
+        let start = Instant::now();
+        assert_eq!(health.state(start), SessionState::Running);
+
+        health.record_screen_send(true, start);
+        assert!(health.heartbeat_due(start));
+        health.record_heartbeat_sent(true, start);
+        assert_eq!(
+            health.state(start + Duration::from_millis(50)),
+            SessionState::Running
+        );
+        assert_eq!(
+            health.issue(start + Duration::from_millis(100)),
+            Some(HealthIssue::ScreenStalled)
+        );
+    }
+
+    #[test]
+    fn test_frame_clears_stall() {
+        let mut health = health();
+        let start = Instant::now();
+        health.record_screen_send(true, start);
+        health.record_heartbeat_sent(true, start);
+        let later = start + Duration::from_millis(200);
+        assert_eq!(health.state(later), SessionState::Degraded);
+
+        health.record_frame(later);
+        assert_eq!(health.state(later), SessionState::Running);
+    }
+
+    #[test]
+    fn test_input_without_output_is_not_a_stall() {
+        let mut health = health();
+        let start = Instant::now();
+        health.record_frame(start);
+        health.record_screen_send(true, start);
+        health.record_screen_send(true, start + Duration::from_millis(90));
+        assert_eq!(
+            health.state(start + Duration::from_millis(500)),
+            SessionState::Running
+        );
+
+        // Once the heartbeat behind it is answered, no more are asked for
+        let asked = start + Duration::from_millis(90);
+        health.record_heartbeat_sent(true, asked);
+        health.record_heartbeat(asked);
+        assert!(!health.heartbeat_due(asked + Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn test_channel_closure_is_permanent() {
+        let mut health = health();
+        let now = Instant::now();
+        health.record_screen_send(false, now);
+        health.record_frame(now);
+        assert_eq!(health.issue(now), Some(HealthIssue::ScreenChannelClosed));
+
+        let mut health = SessionHealth::default();
+        health.record_frame_channel_closed();
+        assert_eq!(health.issue(now), Some(HealthIssue::FrameChannelClosed));
+    }
+
+    #[test]
+    fn test_poll_transition_reports_changes_once() {
+        let mut health = health();
+        let start = Instant::now();
+        assert_eq!(health.poll_transition(start), None);
+
+        health.record_screen_send(true, start);
+        health.record_heartbeat_sent(true, start);
+        let stalled = start + Duration::from_millis(150);
+        assert_eq!(
+            health.poll_transition(stalled),
+            Some((SessionState::Degraded, Some(HealthIssue::ScreenStalled)))
+        );
+        assert_eq!(health.poll_transition(stalled), None);
+
+        health.record_frame(stalled);
+        assert_eq!(
+            health.poll_transition(stalled),
+            Some((SessionState::Running, None))
+        );
+    }
+
+    #[test]
+    fn test_quiet_screen_asked_for_heartbeat() {
+        let mut health = health();
+        let start = Instant::now();
+        health.record_frame(start);
+        assert!(!health.heartbeat_due(start + Duration::from_millis(49)));
+        let asked = start + Duration::from_millis(50);
+        assert!(health.heartbeat_due(asked));
+        health.record_heartbeat_sent(true, asked);
+        assert!(!health.heartbeat_due(asked + Duration::from_millis(10)));
+
+        // Idle but answering is healthy
+        let answered = asked + Duration::from_millis(10);
+        health.record_heartbeat(answered);
+        assert_eq!(
+            health.state(answered + Duration::from_millis(200)),
+            SessionState::Running
+        );
+        assert!(health.heartbeat_due(answered + Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn test_heartbeat_after_input_without_output_clears_stall() {
+        let mut health = health();
+        let start = Instant::now();
+        health.record_frame(start);
+
+        // A heartbeat asked for before the input says nothing about it
+        health.record_heartbeat_sent(true, start);
+        let typed = start + Duration::from_millis(10);
+        health.record_screen_send(true, typed);
+        health.record_heartbeat(typed + Duration::from_millis(5));
+
+        // The input draws nothing, but the screen answers the next heartbeat
+        let asked = typed + Duration::from_millis(60);
+        assert!(health.heartbeat_due(asked));
+        health.record_heartbeat_sent(true, asked);
+        health.record_heartbeat(asked + Duration::from_millis(5));
+        assert_eq!(
+            health.state(typed + Duration::from_millis(150)),
+            SessionState::Running
+        );
+    }
+
+    #[test]
+    fn test_unanswered_heartbeat_is_a_stall() {
+        let mut health = health();
+        let start = Instant::now();
+        health.record_frame(start);
+        let asked = start + Duration::from_millis(50);
+        health.record_heartbeat_sent(true, asked);
+        // Measured from when the screen was last heard from, not from the ask
+        assert_eq!(
+            health.state(start + Duration::from_millis(99)),
+            SessionState::Running
+        );
+        assert_eq!(
+            health.issue(start + Duration::from_millis(100)),
+            Some(HealthIssue::ScreenStalled)
+        );
+
+        let mut health = SessionHealth::default();
+        health.record_heartbeat_sent(false, start);
+        assert_eq!(health.issue(start), Some(HealthIssue::ScreenChannelClosed));
+    }
+}
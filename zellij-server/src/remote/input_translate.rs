@@ -105,52 +105,178 @@ fn translate_special_key(special: i32) -> Option<BareKey> {
         x if x == SpecialKey::F10 as i32 => Some(BareKey::F(10)),
         x if x == SpecialKey::F11 as i32 => Some(BareKey::F(11)),
         x if x == SpecialKey::F12 as i32 => Some(BareKey::F(12)),
+        x if (SpecialKey::F13 as i32..=SpecialKey::F24 as i32).contains(&x) => {
+            Some(BareKey::F((x - SpecialKey::F13 as i32 + 13) as u8))
+        },
+        x if (SpecialKey::Kp0 as i32..=SpecialKey::Kp9 as i32).contains(&x) => {
+            char::from_digit((x - SpecialKey::Kp0 as i32) as u32, 10).map(BareKey::Char)
+        },
+        x if x == SpecialKey::KpEnter as i32 => Some(BareKey::Enter),
+        x if x == SpecialKey::Menu as i32 => Some(BareKey::Menu),
+        x if x == SpecialKey::PrintScreen as i32 => Some(BareKey::PrintScreen),
         _ => None,
     }
 }
 
+/// The protocol's name for `key`, the reverse of what `translate_input` accepts, or
+/// None for keys it has no name for (Caps Lock, Scroll Lock, Num Lock, Pause).
+pub fn protocol_key(key: &KeyWithModifier) -> Option<zellij_remote_protocol::KeyEvent> {
+    let key_event_key = match key.bare_key {
+        BareKey::Char(ch) => key_event::Key::UnicodeScalar(ch as u32),
+        bare_key => key_event::Key::Special(special_key(bare_key)? as i32),
+    };
+    let bits = key
+        .key_modifiers
+        .iter()
+        .map(|modifier| match modifier {
+            KeyModifier::Shift => 1,
+            KeyModifier::Alt => 2,
+            KeyModifier::Ctrl => 4,
+            KeyModifier::Super => 8,
+        })
+        .sum();
+    Some(zellij_remote_protocol::KeyEvent {
+        modifiers: (bits != 0).then_some(KeyModifiers { bits }),
+        key: Some(key_event_key),
+    })
+}
+
+fn special_key(bare_key: BareKey) -> Option<SpecialKey> {
+    let special = match bare_key {
+        BareKey::Enter => SpecialKey::Enter,
+        BareKey::Esc => SpecialKey::Escape,
+        BareKey::Backspace => SpecialKey::Backspace,
+        BareKey::Tab => SpecialKey::Tab,
+        BareKey::Left => SpecialKey::Left,
+        BareKey::Right => SpecialKey::Right,
+        BareKey::Up => SpecialKey::Up,
+        BareKey::Down => SpecialKey::Down,
+        BareKey::Home => SpecialKey::Home,
+        BareKey::End => SpecialKey::End,
+        BareKey::PageUp => SpecialKey::PageUp,
+        BareKey::PageDown => SpecialKey::PageDown,
+        BareKey::Insert => SpecialKey::Insert,
+        BareKey::Delete => SpecialKey::Delete,
+        BareKey::F(n @ 1..=12) => SpecialKey::from_i32(SpecialKey::F1 as i32 + n as i32 - 1)?,
+        BareKey::F(n @ 13..=24) => SpecialKey::from_i32(SpecialKey::F13 as i32 + n as i32 - 13)?,
+        BareKey::Menu => SpecialKey::Menu,
+        BareKey::PrintScreen => SpecialKey::PrintScreen,
+        _ => return None,
+    };
+    Some(special)
+}
+
+/// xterm modifier parameter for CSI sequences: 1 + (Shift=1 | Alt=2 | Ctrl=4 | Super=8).
+/// Returns None when no modifiers are held so the unmodified sequence can be used.
+fn xterm_modifier_param(key: &KeyWithModifier) -> Option<u8> {
+    let mut param = 1;
+    if key.key_modifiers.contains(&KeyModifier::Shift) {
+        param += 1;
+    }
+    if key.key_modifiers.contains(&KeyModifier::Alt) {
+        param += 2;
+    }
+    if key.key_modifiers.contains(&KeyModifier::Ctrl) {
+        param += 4;
+    }
+    if key.key_modifiers.contains(&KeyModifier::Super) {
+        param += 8;
+    }
+    if param == 1 {
+        None
+    } else {
+        Some(param)
+    }
+}
+
+/// Cursor-style keys: `CSI <final>` unmodified, `CSI 1;<mod> <final>` with modifiers.
+fn csi_letter(final_byte: char, modifier: Option<u8>) -> Vec<u8> {
+    match modifier {
+        Some(m) => format!("\x1b[1;{}{}", m, final_byte).into_bytes(),
+        None => format!("\x1b[{}", final_byte).into_bytes(),
+    }
+}
+
+/// SS3-style keys (F1-F4): `SS3 <final>` unmodified, `CSI 1;<mod> <final>` with modifiers.
+fn ss3_letter(final_byte: char, modifier: Option<u8>) -> Vec<u8> {
+    match modifier {
+        Some(m) => format!("\x1b[1;{}{}", m, final_byte).into_bytes(),
+        None => format!("\x1bO{}", final_byte).into_bytes(),
+    }
+}
+
+/// Tilde-style keys: `CSI <code> ~` unmodified, `CSI <code>;<mod> ~` with modifiers.
+fn csi_tilde(code: u8, modifier: Option<u8>) -> Vec<u8> {
+    match modifier {
+        Some(m) => format!("\x1b[{};{}~", code, m).into_bytes(),
+        None => format!("\x1b[{}~", code).into_bytes(),
+    }
+}
+
+fn with_alt_prefix(has_alt: bool, mut bytes: Vec<u8>) -> Vec<u8> {
+    if has_alt && !bytes.is_empty() {
+        bytes.insert(0, 0x1b);
+    }
+    bytes
+}
+
 fn key_to_bytes(key: &KeyWithModifier) -> Vec<u8> {
     let has_ctrl = key.key_modifiers.contains(&KeyModifier::Ctrl);
+    let has_alt = key.key_modifiers.contains(&KeyModifier::Alt);
+    let has_shift = key.key_modifiers.contains(&KeyModifier::Shift);
+    let modifier = xterm_modifier_param(key);
 
     match &key.bare_key {
         BareKey::Char(c) => {
-            if has_ctrl && c.is_ascii_alphabetic() {
+            let bytes = if has_ctrl && c.is_ascii_alphabetic() {
                 vec![(c.to_ascii_lowercase() as u8) - b'a' + 1]
+            } else if has_ctrl && *c == ' ' {
+                vec![0x00]
             } else {
                 let mut s = String::new();
                 s.push(*c);
                 s.into_bytes()
-            }
+            };
+            with_alt_prefix(has_alt, bytes)
         },
-        BareKey::Enter => vec![b'\r'],
-        BareKey::Tab => vec![b'\t'],
-        BareKey::Backspace => vec![0x7f],
-        BareKey::Esc => vec![0x1b],
-        BareKey::Left => b"\x1b[D".to_vec(),
-        BareKey::Right => b"\x1b[C".to_vec(),
-        BareKey::Up => b"\x1b[A".to_vec(),
-        BareKey::Down => b"\x1b[B".to_vec(),
-        BareKey::Home => b"\x1b[H".to_vec(),
-        BareKey::End => b"\x1b[F".to_vec(),
-        BareKey::PageUp => b"\x1b[5~".to_vec(),
-        BareKey::PageDown => b"\x1b[6~".to_vec(),
-        BareKey::Insert => b"\x1b[2~".to_vec(),
-        BareKey::Delete => b"\x1b[3~".to_vec(),
+        BareKey::Enter => with_alt_prefix(has_alt, vec![b'\r']),
+        BareKey::Tab if has_shift => b"\x1b[Z".to_vec(),
+        BareKey::Tab => with_alt_prefix(has_alt, vec![b'\t']),
+        BareKey::Backspace => with_alt_prefix(has_alt, vec![0x7f]),
+        BareKey::Esc => with_alt_prefix(has_alt, vec![0x1b]),
+        BareKey::Left => csi_letter('D', modifier),
+        BareKey::Right => csi_letter('C', modifier),
+        BareKey::Up => csi_letter('A', modifier),
+        BareKey::Down => csi_letter('B', modifier),
+        BareKey::Home => csi_letter('H', modifier),
+        BareKey::End => csi_letter('F', modifier),
+        BareKey::PageUp => csi_tilde(5, modifier),
+        BareKey::PageDown => csi_tilde(6, modifier),
+        BareKey::Insert => csi_tilde(2, modifier),
+        BareKey::Delete => csi_tilde(3, modifier),
         BareKey::F(n) => match n {
-            1 => b"\x1bOP".to_vec(),
-            2 => b"\x1bOQ".to_vec(),
-            3 => b"\x1bOR".to_vec(),
-            4 => b"\x1bOS".to_vec(),
-            5 => b"\x1b[15~".to_vec(),
-            6 => b"\x1b[17~".to_vec(),
-            7 => b"\x1b[18~".to_vec(),
-            8 => b"\x1b[19~".to_vec(),
-            9 => b"\x1b[20~".to_vec(),
-            10 => b"\x1b[21~".to_vec(),
-            11 => b"\x1b[23~".to_vec(),
-            12 => b"\x1b[24~".to_vec(),
+            1 => ss3_letter('P', modifier),
+            2 => ss3_letter('Q', modifier),
+            3 => ss3_letter('R', modifier),
+            4 => ss3_letter('S', modifier),
+            5 => csi_tilde(15, modifier),
+            6 => csi_tilde(17, modifier),
+            7 => csi_tilde(18, modifier),
+            8 => csi_tilde(19, modifier),
+            9 => csi_tilde(20, modifier),
+            10 => csi_tilde(21, modifier),
+            11 => csi_tilde(23, modifier),
+            12 => csi_tilde(24, modifier),
+            // xterm reports F13-F24 as Shift+F1..F12
+            13..=24 => {
+                let mut shifted = key.clone();
+                shifted.bare_key = BareKey::F(n - 12);
+                shifted.key_modifiers.insert(KeyModifier::Shift);
+                key_to_bytes(&shifted)
+            },
             _ => vec![],
         },
+        BareKey::Menu => csi_tilde(29, modifier),
         _ => vec![],
     }
 }
@@ -241,4 +367,112 @@ mod tests {
             _ => panic!("Expected Write action"),
         }
     }
+
+    fn key_bytes(modifier_bits: u32, key: key_event::Key) -> Vec<u8> {
+        let event = InputEvent {
+            input_seq: 1,
+            client_time_ms: 0,
+            payload: Some(input_event::Payload::Key(KeyEvent {
+                modifiers: Some(KeyModifiers {
+                    bits: modifier_bits,
+                }),
+                key: Some(key),
+            })),
+        };
+        match translate_input(&event).unwrap() {
+            Action::Write { bytes, .. } => bytes,
+            _ => panic!("Expected Write action"),
+        }
+    }
+
+    #[test]
+    fn test_translate_chorded_keys() {
+        const SHIFT: u32 = 1;
+        const ALT: u32 = 2;
+        const CTRL: u32 = 4;
+
+        let special = |k: SpecialKey| key_event::Key::Special(k as i32);
+        let unicode = |c: char| key_event::Key::UnicodeScalar(c as u32);
+
+        let cases: Vec<(u32, key_event::Key, &[u8])> = vec![
+            (0, special(SpecialKey::Left), b"\x1b[D"),
+            (ALT, special(SpecialKey::Left), b"\x1b[1;3D"),
+            (ALT, special(SpecialKey::Right), b"\x1b[1;3C"),
+            (CTRL, special(SpecialKey::Right), b"\x1b[1;5C"),
+            (SHIFT, special(SpecialKey::Up), b"\x1b[1;2A"),
+            (CTRL | SHIFT, special(SpecialKey::Down), b"\x1b[1;6B"),
+            (SHIFT, special(SpecialKey::Home), b"\x1b[1;2H"),
+            (CTRL, special(SpecialKey::End), b"\x1b[1;5F"),
+            (CTRL, special(SpecialKey::PageUp), b"\x1b[5;5~"),
+            (ALT, special(SpecialKey::Delete), b"\x1b[3;3~"),
+            (0, special(SpecialKey::F1), b"\x1bOP"),
+            (SHIFT, special(SpecialKey::F1), b"\x1b[1;2P"),
+            (CTRL, special(SpecialKey::F4), b"\x1b[1;5S"),
+            (SHIFT, special(SpecialKey::F5), b"\x1b[15;2~"),
+            (ALT | SHIFT, special(SpecialKey::F12), b"\x1b[24;4~"),
+            (SHIFT, special(SpecialKey::Tab), b"\x1b[Z"),
+            (ALT, special(SpecialKey::Backspace), b"\x1b\x7f"),
+            (ALT, special(SpecialKey::Enter), b"\x1b\r"),
+            (ALT, unicode('b'), b"\x1bb"),
+            (ALT | CTRL, unicode('x'), b"\x1b\x18"),
+            (CTRL, unicode(' '), b"\x00"),
+            (0, special(SpecialKey::F13), b"\x1b[1;2P"),
+            (0, special(SpecialKey::F24), b"\x1b[24;2~"),
+            (0, special(SpecialKey::Kp0), b"0"),
+            (0, special(SpecialKey::Kp7), b"7"),
+            (0, special(SpecialKey::KpEnter), b"\r"),
+            (0, special(SpecialKey::Menu), b"\x1b[29~"),
+        ];
+
+        for (bits, key, expected) in cases {
+            let description = format!("{:?} with modifier bits {}", key, bits);
+            assert_eq!(key_bytes(bits, key), expected.to_vec(), "{}", description);
+        }
+    }
+
+    #[test]
+    fn test_protocol_key_roundtrips_through_translate() {
+        let keys = [
+            KeyWithModifier::new(BareKey::Char('g')).with_ctrl_modifier(),
+            KeyWithModifier::new(BareKey::Left).with_alt_modifier(),
+            KeyWithModifier::new(BareKey::F(5)),
+            KeyWithModifier::new(BareKey::F(20)).with_shift_modifier(),
+            KeyWithModifier::new(BareKey::Esc),
+        ];
+        for key in keys {
+            let event = InputEvent {
+                payload: Some(input_event::Payload::Key(protocol_key(&key).unwrap())),
+                ..Default::default()
+            };
+            match translate_input(&event) {
+                Some(Action::Write {
+                    key_with_modifier: Some(translated),
+                    ..
+                }) => assert_eq!(translated, key),
+                other => panic!("{:?} translated to {:?}", key, other),
+            }
+        }
+        assert_eq!(protocol_key(&KeyWithModifier::new(BareKey::CapsLock)), None);
+    }
+
+    #[test]
+    fn test_translate_print_screen_keeps_key_identity() {
+        let event = InputEvent {
+            input_seq: 1,
+            client_time_ms: 0,
+            payload: Some(input_event::Payload::Key(KeyEvent {
+                modifiers: None,
+                key: Some(key_event::Key::Special(SpecialKey::PrintScreen as i32)),
+            })),
+        };
+
+        match translate_input(&event).unwrap() {
+            Action::Write {
+                key_with_modifier, ..
+            } => {
+                assert_eq!(key_with_modifier.unwrap().bare_key, BareKey::PrintScreen);
+            },
+            _ => panic!("Expected Write action"),
+        }
+    }
 }
@@ -1,7 +1,18 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::RemoteConfig;
 use crate::ClientId;
 use zellij_remote_core::{FrameStore, StyleTable};
+use zellij_remote_protocol::{KeybindingInfo, PaneLayout};
 use zellij_utils::pane_size::Size;
 
+/// The screen client remote controllers act through: the screen attaches it once a
+/// remote client connects, so remote input reaches a pane even with no local client
+/// attached, and the focus it moves stays put between remote sessions. Local client
+/// ids count up from 1, so they don't reach it.
+pub const REMOTE_CLIENT_ID: ClientId = ClientId::MAX;
+
 /// Instructions sent TO the remote thread
 #[derive(Debug, Clone)]
 pub enum RemoteInstruction {
@@ -9,7 +20,10 @@ pub enum RemoteInstruction {
     FrameReady {
         client_id: ClientId,
         frame_store: FrameStore,
-        style_table: StyleTable,
+        /// None when unchanged since the last frame
+        style_table: Option<StyleTable>,
+        /// How long painting it from the screen's output took
+        convert_time: Duration,
     },
     /// Client resized their viewport
     ClientResize { client_id: ClientId, size: Size },
@@ -17,6 +31,37 @@ pub enum RemoteInstruction {
     ClientConnected { client_id: ClientId, size: Size },
     /// Remote client disconnected
     ClientDisconnected { client_id: ClientId },
+    /// Title of the pane remote clients are looking at changed (from the screen)
+    FocusedPaneTitle { title: String },
+    /// Working directory or foreground command of that pane changed (from the pty)
+    FocusedPaneProcess {
+        cwd: Option<PathBuf>,
+        command: Option<String>,
+    },
+    /// Panes of the tab remote clients are looking at moved, were retitled or the focus
+    /// changed (from the screen)
+    PaneLayout(PaneLayout),
+    /// The keybindings in effect changed, e.g. on config reload (from the screen)
+    Keybindings(KeybindingInfo),
+    /// A terminal pane rang the bell (from the screen)
+    Bell { pane_id: u32, focused: bool },
+    /// The screen answering a heartbeat request
+    ScreenHeartbeat,
+    /// Take control away from the remote client holding it (e.g. requested by a plugin)
+    RevokeLease,
+    /// The local user approved or denied a remote client's request (from a plugin)
+    AnswerApproval { request_id: u64, approved: bool },
+    /// Delay everything sent to one remote client by `delay_ms`, 0 to stop; for testing
+    /// how the client copes with latency (from a plugin)
+    SetClientLatency { remote_id: u64, delay_ms: u64 },
+    /// Pause streaming to remote clients, or resume it if paused (local user's action)
+    ToggleStreamingPaused,
+    /// Close all remote clients and listen again with this config
+    Reconfigure(RemoteConfig),
+    /// Close all remote clients and stop listening until `Start` or `Shutdown`
+    Stop,
+    /// Listen again after `Stop`, with the config from the last `Reconfigure`
+    Start,
     /// Session is shutting down
     Shutdown,
 }
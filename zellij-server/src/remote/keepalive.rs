@@ -0,0 +1,214 @@
+use std::time::{Duration, Instant};
+
+use zellij_remote_protocol::{Ping, Pong};
+
+/// Default gap without frames after which a client is pinged
+pub const DEFAULT_KEEPALIVE_INTERVAL_MS: u64 = 15_000;
+/// Intervals a ping may go unanswered before the client is closed
+pub const KEEPALIVE_MISSED_INTERVALS: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeepAliveAction {
+    Ping(Ping),
+    /// No `Pong` for `KEEPALIVE_MISSED_INTERVALS` intervals from a client that has
+    /// answered before; close it
+    TimedOut,
+}
+
+/// Keeps a quiet client's connection (and any NAT mapping in front of it) alive.
+///
+/// A client that hasn't been sent anything for an interval gets a `Ping`, repeated
+/// every interval until a `Pong` comes back. Frames count as traffic, so a client
+/// watching a busy session is never pinged.
+///
+/// Only clients that have answered a ping are timed out: a client built before pongs
+/// existed just ignores the pings, and closing it would cut off a working connection.
+#[derive(Debug)]
+pub struct KeepAlive {
+    interval: Duration,
+    /// Pings carry ms since this instant, echoed back to time the round trip
+    epoch: Instant,
+    last_sent: Instant,
+    last_ping_id: u64,
+    /// When the oldest unanswered ping went out
+    awaiting_pong_since: Option<Instant>,
+    /// Set by the first `Pong`; arms the timeout
+    answers_pings: bool,
+}
+
+impl KeepAlive {
+    pub fn new(interval: Duration, now: Instant) -> Self {
+        Self {
+            interval,
+            epoch: now,
+            last_sent: now,
+            last_ping_id: 0,
+            awaiting_pong_since: None,
+            answers_pings: false,
+        }
+    }
+
+    pub fn record_frame_sent(&mut self, now: Instant) {
+        self.last_sent = self.last_sent.max(now);
+    }
+
+    pub fn poll(&mut self, now: Instant) -> Option<KeepAliveAction> {
+        if let Some(since) = self.awaiting_pong_since.filter(|_| self.answers_pings) {
+            if now.saturating_duration_since(since) >= self.interval * KEEPALIVE_MISSED_INTERVALS {
+                return Some(KeepAliveAction::TimedOut);
+            }
+        }
+        if now.saturating_duration_since(self.last_sent) < self.interval {
+            return None;
+        }
+        self.last_ping_id += 1;
+        self.last_sent = now;
+        self.awaiting_pong_since.get_or_insert(now);
+        Some(KeepAliveAction::Ping(Ping {
+            ping_id: self.last_ping_id,
+            client_time_ms: self.clock_ms(now),
+        }))
+    }
+
+    /// Returns the round trip time, or None for a pong to a ping that was never sent.
+    pub fn record_pong(&mut self, pong: &Pong, now: Instant) -> Option<Duration> {
+        if pong.ping_id == 0 || pong.ping_id > self.last_ping_id {
+            return None;
+        }
+        self.awaiting_pong_since = None;
+        self.answers_pings = true;
+        let rtt_ms = self.clock_ms(now).wrapping_sub(pong.echoed_client_time_ms);
+        Some(Duration::from_millis(rtt_ms as u64))
+    }
+
+    fn clock_ms(&self, now: Instant) -> u32 {
+        now.saturating_duration_since(self.epoch).as_millis() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(DEFAULT_KEEPALIVE_INTERVAL_MS);
+
+    fn ping_id(action: Option<KeepAliveAction>) -> Option<u64> {
+        match action {
+            Some(KeepAliveAction::Ping(ping)) => Some(ping.ping_id),
+            _ => None,
+        }
+    }
+
+    fn answer(ping: &Ping) -> Pong {
+        Pong {
+            ping_id: ping.ping_id,
+            echoed_client_time_ms: ping.client_time_ms,
+            server_time_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_ping_sent_after_quiet_interval() {
+        let start = Instant::now();
+        let mut keepalive = KeepAlive::new(INTERVAL, start);
+
+        assert_eq!(keepalive.poll(start + INTERVAL / 2), None);
+        assert_eq!(ping_id(keepalive.poll(start + INTERVAL)), Some(1));
+        // Not again until another interval has passed
+        assert_eq!(keepalive.poll(start + INTERVAL + INTERVAL / 2), None);
+    }
+
+    #[test]
+    fn test_frames_postpone_ping() {
+        let start = Instant::now();
+        let mut keepalive = KeepAlive::new(INTERVAL, start);
+
+        keepalive.record_frame_sent(start + INTERVAL / 2);
+        assert_eq!(keepalive.poll(start + INTERVAL), None);
+        assert_eq!(
+            ping_id(keepalive.poll(start + INTERVAL + INTERVAL / 2)),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_pong_clears_outstanding_ping() {
+        let start = Instant::now();
+        let mut keepalive = KeepAlive::new(INTERVAL, start);
+
+        let Some(KeepAliveAction::Ping(ping)) = keepalive.poll(start + INTERVAL) else {
+            panic!("expected a ping");
+        };
+        let unknown = Pong {
+            ping_id: 2,
+            ..answer(&ping)
+        };
+        assert_eq!(keepalive.record_pong(&unknown, start + INTERVAL), None);
+
+        let rtt = Duration::from_millis(40);
+        assert_eq!(
+            keepalive.record_pong(&answer(&ping), start + INTERVAL + rtt),
+            Some(rtt)
+        );
+
+        let later = start + INTERVAL * (KEEPALIVE_MISSED_INTERVALS + 1);
+        assert_eq!(ping_id(keepalive.poll(later)), Some(2));
+    }
+
+    /// Answer the first ping so the timeout is armed; returns when it was answered.
+    fn answer_first_ping(keepalive: &mut KeepAlive, start: Instant) -> Instant {
+        let Some(KeepAliveAction::Ping(ping)) = keepalive.poll(start + INTERVAL) else {
+            panic!("expected a ping");
+        };
+        keepalive.record_pong(&answer(&ping), start + INTERVAL);
+        start + INTERVAL
+    }
+
+    #[test]
+    fn test_unanswered_pings_time_out() {
+        let start = Instant::now();
+        let mut keepalive = KeepAlive::new(INTERVAL, start);
+        let answered = answer_first_ping(&mut keepalive, start);
+
+        let first_ping = answered + INTERVAL;
+        assert_eq!(ping_id(keepalive.poll(first_ping)), Some(2));
+        for i in 1..KEEPALIVE_MISSED_INTERVALS {
+            assert_eq!(
+                ping_id(keepalive.poll(first_ping + INTERVAL * i)),
+                Some(i as u64 + 2)
+            );
+        }
+        assert_eq!(
+            keepalive.poll(first_ping + INTERVAL * KEEPALIVE_MISSED_INTERVALS),
+            Some(KeepAliveAction::TimedOut)
+        );
+    }
+
+    #[test]
+    fn test_client_that_never_pongs_is_not_timed_out() {
+        let start = Instant::now();
+        let mut keepalive = KeepAlive::new(INTERVAL, start);
+
+        for i in 1..=KEEPALIVE_MISSED_INTERVALS * 3 {
+            assert_eq!(
+                ping_id(keepalive.poll(start + INTERVAL * i)),
+                Some(i as u64)
+            );
+        }
+    }
+
+    #[test]
+    fn test_frames_do_not_excuse_missing_pong() {
+        let start = Instant::now();
+        let mut keepalive = KeepAlive::new(INTERVAL, start);
+        let answered = answer_first_ping(&mut keepalive, start);
+
+        let first_ping = answered + INTERVAL;
+        keepalive.poll(first_ping);
+        keepalive.record_frame_sent(first_ping + INTERVAL * 2);
+        assert_eq!(
+            keepalive.poll(first_ping + INTERVAL * KEEPALIVE_MISSED_INTERVALS),
+            Some(KeepAliveAction::TimedOut)
+        );
+    }
+}
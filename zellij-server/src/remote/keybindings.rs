@@ -0,0 +1,96 @@
+use zellij_remote_protocol::{Keybinding, KeybindingInfo, ModeKeybindings};
+use zellij_utils::data::{InputMode, KeybindsVec};
+
+use super::input_translate::protocol_key;
+
+/// The session's keybindings as remote clients are told them. Modes and keys keep the
+/// order the config gives them; bindings with no action that can be written in config
+/// syntax are left out.
+pub fn keybinding_info(keybinds: &KeybindsVec, default_mode: InputMode) -> KeybindingInfo {
+    KeybindingInfo {
+        modes: keybinds
+            .iter()
+            .map(|(mode, bindings)| ModeKeybindings {
+                mode: mode_name(*mode),
+                bindings: bindings
+                    .iter()
+                    .filter_map(|(key, actions)| {
+                        let actions: Vec<String> = actions
+                            .iter()
+                            .filter_map(|action| action.to_kdl())
+                            .map(|node| node.to_string().trim().to_owned())
+                            .collect();
+                        (!actions.is_empty()).then(|| Keybinding {
+                            key: protocol_key(key),
+                            label: key.to_string(),
+                            actions,
+                        })
+                    })
+                    .collect(),
+            })
+            .collect(),
+        default_mode: mode_name(default_mode),
+    }
+}
+
+/// "renametab" for `InputMode::RenameTab`, as modes are named in the config
+fn mode_name(mode: InputMode) -> String {
+    format!("{:?}", mode).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zellij_utils::data::{BareKey, KeyWithModifier};
+    use zellij_utils::input::actions::Action;
+
+    #[test]
+    fn test_keybindings_in_config_order() {
+        let keybinds: KeybindsVec = vec![
+            (
+                InputMode::Normal,
+                vec![
+                    (
+                        KeyWithModifier::new(BareKey::Char('g')).with_ctrl_modifier(),
+                        vec![Action::SwitchToMode {
+                            input_mode: InputMode::Locked,
+                        }],
+                    ),
+                    (
+                        KeyWithModifier::new(BareKey::Char('q')).with_ctrl_modifier(),
+                        vec![Action::Quit],
+                    ),
+                ],
+            ),
+            (
+                InputMode::RenameTab,
+                vec![(
+                    KeyWithModifier::new(BareKey::CapsLock),
+                    vec![Action::SwitchToMode {
+                        input_mode: InputMode::Normal,
+                    }],
+                )],
+            ),
+        ];
+
+        let info = keybinding_info(&keybinds, InputMode::Locked);
+        assert_eq!(info.default_mode, "locked");
+        assert_eq!(info.modes.len(), 2);
+        assert_eq!(info.modes[0].mode, "normal");
+        assert_eq!(info.modes[1].mode, "renametab");
+
+        let lock = &info.modes[0].bindings[0];
+        assert_eq!(lock.label, "Ctrl g");
+        assert_eq!(lock.actions, vec![r#"SwitchToMode "locked""#.to_owned()]);
+        assert_eq!(
+            lock.key,
+            protocol_key(&KeyWithModifier::new(BareKey::Char('g')).with_ctrl_modifier())
+        );
+        assert_eq!(info.modes[0].bindings[1].actions, vec!["Quit".to_owned()]);
+
+        // Still listed, just without a key the protocol can name
+        let caps_lock = &info.modes[1].bindings[0];
+        assert_eq!(caps_lock.key, None);
+        assert!(!caps_lock.label.is_empty());
+    }
+}
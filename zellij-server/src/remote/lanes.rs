@@ -0,0 +1,296 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::time::Instant;
+use zellij_remote_bridge::{encode_envelope, encode_lane_prologue};
+use zellij_remote_protocol::{stream_envelope, Lane, LaneOpen, StreamEnvelope};
+
+/// The lane a message is written to once priority lanes are negotiated.
+///
+/// Snapshots can run to megabytes; on their own stream they no longer hold up the
+/// input acks and small deltas that make typing feel responsive.
+pub fn lane_for(msg: &StreamEnvelope) -> Lane {
+    match msg.msg {
+        Some(stream_envelope::Msg::ScreenSnapshot(_)) => Lane::Bulk,
+        Some(stream_envelope::Msg::ScreenDeltaStream(_))
+        | Some(stream_envelope::Msg::InputAck(_)) => Lane::Realtime,
+        _ => Lane::Control,
+    }
+}
+
+/// The server-opened streams behind the render lanes.
+pub struct LaneStreams {
+    pub realtime: wtransport::SendStream,
+    pub bulk: wtransport::SendStream,
+}
+
+/// Open the render lanes, announcing each with a `LaneOpen`. Clients with push streams
+/// also expect the stream-type prologue byte in front of it.
+pub async fn open_lanes(
+    connection: &wtransport::Connection,
+    prologue: bool,
+) -> Result<LaneStreams> {
+    Ok(LaneStreams {
+        realtime: open_lane(connection, Lane::Realtime, prologue).await?,
+        bulk: open_lane(connection, Lane::Bulk, prologue).await?,
+    })
+}
+
+async fn open_lane(
+    connection: &wtransport::Connection,
+    lane: Lane,
+    prologue: bool,
+) -> Result<wtransport::SendStream> {
+    let mut stream = connection.open_uni().await?.await?;
+    let encoded = if prologue {
+        encode_lane_prologue(lane)?
+    } else {
+        encode_envelope(&StreamEnvelope {
+            msg: Some(stream_envelope::Msg::LaneOpen(LaneOpen {
+                lane: lane as i32,
+            })),
+            envelope_seq: 0,
+        })?
+    };
+    stream.write_all(&encoded).await?;
+    Ok(stream)
+}
+
+/// A client's outgoing queues, one per lane.
+///
+/// Without lanes every message shares the control queue, keeping the single ordered
+/// stream older clients expect. With push streams, snapshots skip the bulk lane and
+/// each go out on a stream of their own.
+pub struct LaneSender {
+    lanes: Lanes,
+    /// Set once a delay has been injected. Everything goes through it from then on,
+    /// even at no delay, so nothing overtakes what it still holds back.
+    delay: Option<DelayStage>,
+}
+
+#[derive(Clone)]
+struct Lanes {
+    control: mpsc::Sender<StreamEnvelope>,
+    realtime: Option<mpsc::Sender<StreamEnvelope>>,
+    bulk: Option<mpsc::Sender<StreamEnvelope>>,
+    push: Option<mpsc::Sender<StreamEnvelope>>,
+}
+
+impl Lanes {
+    fn queue_for(&self, msg: &StreamEnvelope) -> &mpsc::Sender<StreamEnvelope> {
+        let lane = match lane_for(msg) {
+            Lane::Realtime => self.realtime.as_ref(),
+            Lane::Bulk => self.push.as_ref().or(self.bulk.as_ref()),
+            _ => None,
+        };
+        lane.unwrap_or(&self.control)
+    }
+}
+
+/// Holds each message back until its delay is up, then queues it on its lane, in the
+/// order they came.
+struct DelayStage {
+    delay: Duration,
+    held: mpsc::UnboundedSender<(Instant, StreamEnvelope)>,
+}
+
+impl DelayStage {
+    fn spawn(lanes: Lanes, delay: Duration) -> Self {
+        let (held, mut receiver) = mpsc::unbounded_channel::<(Instant, StreamEnvelope)>();
+        tokio::spawn(async move {
+            while let Some((due, msg)) = receiver.recv().await {
+                tokio::time::sleep_until(due).await;
+                if lanes.queue_for(&msg).send(msg).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Self { delay, held }
+    }
+}
+
+impl LaneSender {
+    pub fn single(control: mpsc::Sender<StreamEnvelope>) -> Self {
+        Self {
+            lanes: Lanes {
+                control,
+                realtime: None,
+                bulk: None,
+                push: None,
+            },
+            delay: None,
+        }
+    }
+
+    pub fn with_lanes(
+        control: mpsc::Sender<StreamEnvelope>,
+        realtime: mpsc::Sender<StreamEnvelope>,
+        bulk: mpsc::Sender<StreamEnvelope>,
+    ) -> Self {
+        Self {
+            lanes: Lanes {
+                control,
+                realtime: Some(realtime),
+                bulk: Some(bulk),
+                push: None,
+            },
+            delay: None,
+        }
+    }
+
+    /// Send snapshots through `push` rather than a lane
+    pub fn with_push(self, push: mpsc::Sender<StreamEnvelope>) -> Self {
+        Self {
+            lanes: Lanes {
+                push: Some(push),
+                ..self.lanes
+            },
+            ..self
+        }
+    }
+
+    /// Hold every message back for `delay` before queueing it, to test a client under
+    /// latency. Held messages don't count against the lanes, so a client that falls
+    /// behind isn't resynced while delayed. Must be called within a tokio runtime.
+    pub fn set_delay(&mut self, delay: Duration) {
+        match self.delay.as_mut() {
+            Some(stage) => stage.delay = delay,
+            None if delay.is_zero() => {},
+            None => self.delay = Some(DelayStage::spawn(self.lanes.clone(), delay)),
+        }
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+            .as_ref()
+            .map_or(Duration::ZERO, |stage| stage.delay)
+    }
+
+    /// Queue `msg` on its lane; a full lane doesn't block the others.
+    pub fn try_send(&self, msg: StreamEnvelope) -> Result<(), TrySendError<StreamEnvelope>> {
+        match self.delay.as_ref() {
+            Some(stage) => stage
+                .held
+                .send((Instant::now() + stage.delay, msg))
+                .map_err(|e| TrySendError::Closed(e.0 .1)),
+            None => self.lanes.queue_for(&msg).try_send(msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zellij_remote_protocol::{InputAck, Ping, ScreenDelta, ScreenSnapshot};
+
+    fn envelope(msg: stream_envelope::Msg) -> StreamEnvelope {
+        StreamEnvelope {
+            msg: Some(msg),
+            envelope_seq: 0,
+        }
+    }
+
+    #[test]
+    fn test_messages_routed_by_type() {
+        let snapshot = envelope(stream_envelope::Msg::ScreenSnapshot(
+            ScreenSnapshot::default(),
+        ));
+        let delta = envelope(stream_envelope::Msg::ScreenDeltaStream(
+            ScreenDelta::default(),
+        ));
+        let ack = envelope(stream_envelope::Msg::InputAck(InputAck::default()));
+        let ping = envelope(stream_envelope::Msg::Ping(Ping::default()));
+
+        assert_eq!(lane_for(&snapshot), Lane::Bulk);
+        assert_eq!(lane_for(&delta), Lane::Realtime);
+        assert_eq!(lane_for(&ack), Lane::Realtime);
+        assert_eq!(lane_for(&ping), Lane::Control);
+    }
+
+    #[test]
+    fn test_full_bulk_lane_does_not_block_realtime() {
+        let (control_tx, mut control_rx) = mpsc::channel(1);
+        let (realtime_tx, mut realtime_rx) = mpsc::channel(1);
+        let (bulk_tx, _bulk_rx) = mpsc::channel(1);
+        let sender = LaneSender::with_lanes(control_tx, realtime_tx, bulk_tx);
+
+        let snapshot = || {
+            envelope(stream_envelope::Msg::ScreenSnapshot(
+                ScreenSnapshot::default(),
+            ))
+        };
+        sender.try_send(snapshot()).unwrap();
+        assert!(matches!(
+            sender.try_send(snapshot()),
+            Err(TrySendError::Full(_))
+        ));
+
+        let ack = envelope(stream_envelope::Msg::InputAck(InputAck::default()));
+        sender.try_send(ack.clone()).unwrap();
+        assert_eq!(realtime_rx.try_recv().unwrap(), ack);
+        assert!(control_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_snapshots_prefer_push() {
+        let (control_tx, mut control_rx) = mpsc::channel(4);
+        let (push_tx, mut push_rx) = mpsc::channel(4);
+        let sender = LaneSender::single(control_tx).with_push(push_tx);
+
+        let snapshot = envelope(stream_envelope::Msg::ScreenSnapshot(
+            ScreenSnapshot::default(),
+        ));
+        let delta = envelope(stream_envelope::Msg::ScreenDeltaStream(
+            ScreenDelta::default(),
+        ));
+        sender.try_send(snapshot.clone()).unwrap();
+        sender.try_send(delta.clone()).unwrap();
+        assert_eq!(push_rx.try_recv().unwrap(), snapshot);
+        assert_eq!(control_rx.try_recv().unwrap(), delta);
+        assert!(control_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_single_stream_uses_control_queue() {
+        let (control_tx, mut control_rx) = mpsc::channel(4);
+        let sender = LaneSender::single(control_tx);
+
+        let snapshot = envelope(stream_envelope::Msg::ScreenSnapshot(
+            ScreenSnapshot::default(),
+        ));
+        sender.try_send(snapshot.clone()).unwrap();
+        assert_eq!(control_rx.try_recv().unwrap(), snapshot);
+    }
+
+    #[tokio::test]
+    async fn test_delay_holds_messages_back_in_order() {
+        let (control_tx, mut control_rx) = mpsc::channel(4);
+        let (realtime_tx, mut realtime_rx) = mpsc::channel(4);
+        let (bulk_tx, _bulk_rx) = mpsc::channel(4);
+        let mut sender = LaneSender::with_lanes(control_tx, realtime_tx, bulk_tx);
+        sender.set_delay(Duration::from_millis(50));
+        assert_eq!(sender.delay(), Duration::from_millis(50));
+
+        let sent_at = Instant::now();
+        let ack = |sequence| {
+            envelope(stream_envelope::Msg::InputAck(InputAck {
+                acked_seq: sequence,
+                ..Default::default()
+            }))
+        };
+        sender.try_send(ack(1)).unwrap();
+        sender.try_send(ack(2)).unwrap();
+        assert!(realtime_rx.try_recv().is_err());
+
+        assert_eq!(realtime_rx.recv().await.unwrap(), ack(1));
+        assert!(sent_at.elapsed() >= Duration::from_millis(50));
+        assert_eq!(realtime_rx.recv().await.unwrap(), ack(2));
+
+        // Back to no delay, still behind anything held
+        sender.set_delay(Duration::ZERO);
+        let ping = envelope(stream_envelope::Msg::Ping(Ping::default()));
+        sender.try_send(ping.clone()).unwrap();
+        assert_eq!(control_rx.recv().await.unwrap(), ping);
+    }
+}
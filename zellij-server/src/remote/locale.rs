@@ -0,0 +1,119 @@
+use zellij_remote_protocol::ClientLocale;
+
+/// Longest timezone or locale tag kept from a ClientHello
+pub const MAX_LOCALE_FIELD_LEN: usize = 64;
+
+/// Real offsets stay within UTC-12..UTC+14; anything past a day either way is junk
+const MAX_UTC_OFFSET_MINUTES: i32 = 18 * 60;
+
+/// A client's locale as shown to plugins: fields that aren't a plausible IANA zone
+/// name, BCP 47 tag or UTC offset are dropped rather than passed on.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LocaleHint {
+    pub timezone: String,
+    pub locale: String,
+    pub utc_offset_minutes: Option<i32>,
+}
+
+impl LocaleHint {
+    pub fn from_client(locale: &ClientLocale) -> Self {
+        Self {
+            timezone: sanitize(&locale.timezone, |c| {
+                c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '+' | '-')
+            }),
+            locale: sanitize(&locale.locale, |c| c.is_ascii_alphanumeric() || c == '-'),
+            utc_offset_minutes: Some(locale.utc_offset_minutes)
+                .filter(|_| locale.has_utc_offset)
+                .filter(|offset| offset.abs() <= MAX_UTC_OFFSET_MINUTES),
+        }
+    }
+}
+
+fn sanitize(field: &str, allowed: impl Fn(char) -> bool) -> String {
+    if field.len() > MAX_LOCALE_FIELD_LEN || !field.chars().all(allowed) {
+        return String::new();
+    }
+    field.to_owned()
+}
+
+/// The server's own timezone and locale, for `SessionMetadata.server_locale`. The zone
+/// name comes from `TZ` or the `/etc/localtime` link and may be empty; the offset is
+/// always known.
+pub fn server_locale() -> ClientLocale {
+    let timezone = std::env::var("TZ")
+        .ok()
+        .map(|tz| tz.trim_start_matches(':').to_owned())
+        .filter(|tz| !tz.is_empty())
+        .or_else(localtime_zone)
+        .unwrap_or_default();
+    let locale = ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| posix_to_bcp47(&value))
+        .unwrap_or_default();
+    ClientLocale {
+        timezone,
+        locale,
+        utc_offset_minutes: chrono::Local::now().offset().local_minus_utc() / 60,
+        has_utc_offset: true,
+    }
+}
+
+/// "Europe/Berlin" from /etc/localtime -> /usr/share/zoneinfo/Europe/Berlin
+fn localtime_zone() -> Option<String> {
+    let target = std::fs::read_link("/etc/localtime").ok()?;
+    let target = target.to_str()?;
+    let (_, zone) = target.split_once("zoneinfo/")?;
+    Some(zone.to_owned())
+}
+
+/// "de_DE.UTF-8@euro" -> "de-DE"; "C" and "POSIX" say nothing about the user
+fn posix_to_bcp47(value: &str) -> String {
+    let tag = value.split(['.', '@']).next().unwrap_or_default();
+    if tag == "C" || tag == "POSIX" {
+        return String::new();
+    }
+    tag.replace('_', "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_locale_sanitized() {
+        let hint = LocaleHint::from_client(&ClientLocale {
+            timezone: "America/Argentina/Buenos_Aires".to_owned(),
+            locale: "es-AR".to_owned(),
+            utc_offset_minutes: -180,
+            has_utc_offset: true,
+        });
+        assert_eq!(hint.timezone, "America/Argentina/Buenos_Aires");
+        assert_eq!(hint.locale, "es-AR");
+        assert_eq!(hint.utc_offset_minutes, Some(-180));
+
+        let hint = LocaleHint::from_client(&ClientLocale {
+            timezone: "\u{1b}[2J".to_owned(),
+            locale: "x".repeat(200),
+            utc_offset_minutes: 100_000,
+            has_utc_offset: true,
+        });
+        assert_eq!(hint, LocaleHint::default());
+
+        let hint = LocaleHint::from_client(&ClientLocale {
+            utc_offset_minutes: 60,
+            has_utc_offset: false,
+            ..Default::default()
+        });
+        assert_eq!(hint.utc_offset_minutes, None);
+    }
+
+    #[test]
+    fn test_posix_locale_to_bcp47() {
+        assert_eq!(posix_to_bcp47("de_DE.UTF-8"), "de-DE");
+        assert_eq!(posix_to_bcp47("sr_RS@latin"), "sr-RS");
+        assert_eq!(posix_to_bcp47("C.UTF-8"), "");
+        assert_eq!(posix_to_bcp47("POSIX"), "");
+    }
+}
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
+use super::approvals::PendingApprovals;
 use crate::ClientId;
+use zellij_remote_bridge::FrameRateCaps;
 use zellij_remote_core::{RemoteSession, RenderUpdate, StyleTable};
 use zellij_utils::pane_size::Size;
 
@@ -17,6 +20,12 @@ pub struct RemoteManager {
     /// Current screen dimensions
     cols: usize,
     rows: usize,
+    /// The local user paused streaming to remote clients
+    streaming_paused: bool,
+    /// Frame rate limits for the controller and for viewers
+    frame_rate_caps: FrameRateCaps,
+    /// Sensitive requests waiting for the local user; None when they aren't prompted for
+    approvals: Option<PendingApprovals>,
 }
 
 impl RemoteManager {
@@ -28,9 +37,26 @@ impl RemoteManager {
             next_remote_id: 1,
             cols,
             rows,
+            streaming_paused: false,
+            frame_rate_caps: FrameRateCaps::default(),
+            approvals: None,
         }
     }
 
+    /// A manager around a session restored after a restart; remote ids carry on from
+    /// `next_remote_id` so they don't collide with ids in outstanding resume tokens.
+    pub fn restore(cols: usize, rows: usize, session: RemoteSession, next_remote_id: u64) -> Self {
+        let mut manager = Self::new(cols, rows);
+        manager.session = session;
+        manager.next_remote_id = next_remote_id.max(1);
+        manager
+    }
+
+    /// Remote ID the next client will get
+    pub fn next_remote_id(&self) -> u64 {
+        self.next_remote_id
+    }
+
     /// Register a new remote client, returns the remote client ID
     ///
     /// If the zellij_id is already registered, the old remote client is removed first.
@@ -120,6 +146,46 @@ impl RemoteManager {
         self.client_mapping.len()
     }
 
+    /// Pause or resume streaming to all remote clients, returns false if it already was
+    pub fn set_streaming_paused(&mut self, paused: bool) -> bool {
+        let changed = self.streaming_paused != paused;
+        self.streaming_paused = paused;
+        changed
+    }
+
+    /// Check if streaming to remote clients is paused
+    pub fn is_streaming_paused(&self) -> bool {
+        self.streaming_paused
+    }
+
+    pub fn set_frame_rate_caps(&mut self, caps: FrameRateCaps) {
+        self.frame_rate_caps = caps;
+    }
+
+    /// Cap each client's frame rate by its current role. Call before preparing frames,
+    /// so a client that takes or loses the lease is paced accordingly from then on.
+    pub fn pace_clients(&mut self, remote_ids: impl IntoIterator<Item = u64>) {
+        for remote_id in remote_ids {
+            let controller = self.session.lease_manager.is_controller(remote_id);
+            let interval_ms = self.frame_rate_caps.min_frame_interval_ms(controller);
+            self.session.set_min_frame_interval(remote_id, interval_ms);
+        }
+    }
+
+    /// Ask the local user before a force takeover, denying it after `timeout`; None
+    /// lets such requests through unprompted
+    pub fn set_approval_timeout(&mut self, timeout: Option<Duration>) {
+        self.approvals = timeout.map(PendingApprovals::new);
+    }
+
+    pub fn approvals_enabled(&self) -> bool {
+        self.approvals.is_some()
+    }
+
+    pub fn approvals_mut(&mut self) -> Option<&mut PendingApprovals> {
+        self.approvals.as_mut()
+    }
+
     /// Get current screen dimensions
     pub fn dimensions(&self) -> (usize, usize) {
         (self.cols, self.rows)
@@ -186,6 +252,52 @@ mod tests {
         assert_eq!(manager.get_remote_id(1), Some(2));
     }
 
+    #[test]
+    fn test_streaming_pause_is_session_wide() {
+        let mut manager = RemoteManager::new(80, 24);
+        manager.add_client(1, test_size());
+        assert!(!manager.is_streaming_paused());
+
+        assert!(manager.set_streaming_paused(true));
+        assert!(!manager.set_streaming_paused(true));
+        assert!(manager.is_streaming_paused());
+
+        // Clients attaching while paused don't unpause the session
+        manager.add_client(2, test_size());
+        assert!(manager.is_streaming_paused());
+
+        assert!(manager.set_streaming_paused(false));
+        assert!(!manager.is_streaming_paused());
+    }
+
+    #[test]
+    fn test_viewers_paced_slower_than_controller() {
+        let mut manager = RemoteManager::new(80, 24);
+        manager.set_frame_rate_caps(FrameRateCaps {
+            controller_max_fps: None,
+            viewer_max_fps: Some(10),
+        });
+        let controller = manager.add_client(1, test_size());
+        let viewer = manager.add_client(2, test_size());
+        manager
+            .session_mut()
+            .lease_manager
+            .request_control(controller, None, false);
+
+        manager.pace_clients([controller, viewer]);
+        assert!(!manager.session().is_frame_paced(controller));
+        assert!(manager.session().is_frame_paced(viewer));
+    }
+
+    #[test]
+    fn test_restored_manager_continues_remote_ids() {
+        let session = RemoteSession::with_session_id(80, 24, 42);
+        let mut manager = RemoteManager::restore(80, 24, session, 7);
+        assert_eq!(manager.session().session_id, 42);
+        assert_eq!(manager.add_client(1, test_size()), 7);
+        assert_eq!(manager.next_remote_id(), 8);
+    }
+
     #[test]
     fn test_resize_updates_frame_store() {
         let mut manager = RemoteManager::new(80, 24);
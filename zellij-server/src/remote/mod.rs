@@ -1,12 +1,37 @@
+mod approvals;
+mod control_throttle;
+mod frame_stages;
+mod health;
 mod input_translate;
 mod instruction;
+mod keepalive;
+mod keybindings;
+mod lanes;
+mod locale;
 mod manager;
+mod mouse;
 mod output_convert;
-mod style_convert;
+mod pane_layout;
+mod persist;
+mod presence;
+mod query_answers;
+mod runtime_probe;
+mod screen_events;
+mod security_profile;
+mod session_events;
+mod session_switch;
+pub(crate) mod style_convert;
 mod thread;
+mod write_watchdog;
 
 pub use input_translate::translate_input;
-pub use instruction::{RemoteInputInstruction, RemoteInstruction};
+pub use instruction::{RemoteInputInstruction, RemoteInstruction, REMOTE_CLIENT_ID};
+pub use keepalive::DEFAULT_KEEPALIVE_INTERVAL_MS;
+pub use keybindings::keybinding_info;
 pub use manager::RemoteManager;
-pub use output_convert::chunks_to_frame_store;
+pub use output_convert::{chunks_to_frame_store, set_frame_cursor, HostCursor, RemoteCanvas};
+pub use pane_layout::pane_layout;
+pub use screen_events::ScreenEvents;
+pub use security_profile::SecurityProfile;
+pub use session_events::OutboundSessionEvents;
 pub use thread::{remote_thread_main, RemoteConfig};
@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+use zellij_remote_protocol::{input_event, InputEvent, MouseKind};
+
+/// Minimum gap between two mouse moves routed for the same client, about a frame
+pub const MOUSE_MOVE_COALESCE_MS: u64 = 16;
+
+/// Mouse moves (and drags, which are moves with a button held) from one client,
+/// thinned to the latest per [`MOUSE_MOVE_COALESCE_MS`] so a client reporting every
+/// pixel of pointer motion doesn't flood the screen thread. Presses, releases and
+/// everything else are never held back, and go out after any move held before them.
+#[derive(Debug, Default)]
+pub struct MouseMoveCoalescer {
+    held: Option<InputEvent>,
+    last_sent_at: Option<Instant>,
+}
+
+impl MouseMoveCoalescer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The events to route now for `event`, in order.
+    pub fn push(&mut self, event: InputEvent, now: Instant) -> Vec<InputEvent> {
+        if !is_move(&event) {
+            return self.held.take().into_iter().chain([event]).collect();
+        }
+        let window = Duration::from_millis(MOUSE_MOVE_COALESCE_MS);
+        if self
+            .last_sent_at
+            .is_some_and(|sent_at| now.saturating_duration_since(sent_at) < window)
+        {
+            // Replaces any move already held: only the latest position matters
+            self.held = Some(event);
+            return vec![];
+        }
+        self.held = None;
+        self.last_sent_at = Some(now);
+        vec![event]
+    }
+
+    /// The move held back, once its window has passed. Call periodically.
+    pub fn flush(&mut self, now: Instant) -> Option<InputEvent> {
+        let sent_at = self.last_sent_at?;
+        if now.saturating_duration_since(sent_at) < Duration::from_millis(MOUSE_MOVE_COALESCE_MS) {
+            return None;
+        }
+        let event = self.held.take()?;
+        self.last_sent_at = Some(now);
+        Some(event)
+    }
+
+    pub fn has_held(&self) -> bool {
+        self.held.is_some()
+    }
+}
+
+fn is_move(event: &InputEvent) -> bool {
+    matches!(
+        &event.payload,
+        Some(input_event::Payload::Mouse(mouse)) if mouse.kind() == MouseKind::Move
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zellij_remote_protocol::MouseEvent;
+
+    fn mouse(input_seq: u64, kind: MouseKind, col: u32) -> InputEvent {
+        InputEvent {
+            input_seq,
+            payload: Some(input_event::Payload::Mouse(MouseEvent {
+                kind: kind as i32,
+                col,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn seqs(events: &[InputEvent]) -> Vec<u64> {
+        events.iter().map(|event| event.input_seq).collect()
+    }
+
+    #[test]
+    fn test_move_storm_keeps_latest() {
+        let start = Instant::now();
+        let mut mouse_moves = MouseMoveCoalescer::new();
+        assert_eq!(
+            seqs(&mouse_moves.push(mouse(1, MouseKind::Move, 1), start)),
+            [1]
+        );
+        for seq in 2..=10 {
+            let at = start + Duration::from_millis(seq);
+            assert!(mouse_moves
+                .push(mouse(seq, MouseKind::Move, 1), at)
+                .is_empty());
+        }
+        assert!(mouse_moves.has_held());
+
+        let window = Duration::from_millis(MOUSE_MOVE_COALESCE_MS);
+        assert_eq!(
+            mouse_moves.flush(start + window - Duration::from_millis(1)),
+            None
+        );
+        let flushed = mouse_moves.flush(start + window).unwrap();
+        assert_eq!(flushed.input_seq, 10);
+        assert!(!mouse_moves.has_held());
+        assert_eq!(mouse_moves.flush(start + window * 2), None);
+    }
+
+    #[test]
+    fn test_press_sends_held_move_first() {
+        let start = Instant::now();
+        let mut mouse_moves = MouseMoveCoalescer::new();
+        mouse_moves.push(mouse(1, MouseKind::Move, 1), start);
+        mouse_moves.push(mouse(2, MouseKind::Move, 2), start);
+
+        let at = start + Duration::from_millis(1);
+        let routed = mouse_moves.push(mouse(3, MouseKind::Down, 2), at);
+        assert_eq!(seqs(&routed), [2, 3]);
+        assert_eq!(seqs(&mouse_moves.push(mouse(4, MouseKind::Up, 2), at)), [4]);
+        assert!(!mouse_moves.has_held());
+    }
+
+    #[test]
+    fn test_slow_moves_not_held() {
+        let start = Instant::now();
+        let window = Duration::from_millis(MOUSE_MOVE_COALESCE_MS);
+        let mut mouse_moves = MouseMoveCoalescer::new();
+        for seq in 0..3 {
+            let at = start + window * seq as u32;
+            assert_eq!(
+                seqs(&mouse_moves.push(mouse(seq, MouseKind::Move, 1), at)),
+                [seq]
+            );
+        }
+        assert!(!mouse_moves.has_held());
+    }
+}
@@ -3,11 +3,22 @@
 //! This module converts Zellij's Output (CharacterChunks) to a FrameStore
 //! for transmission to remote clients. This captures the full composited
 //! screen including all panes, floating windows, and UI elements.
+//!
+//! A render only carries the chunks that changed since the last one, so the frame
+//! sent is kept in a [`RemoteCanvas`] and each render is painted over it, the same
+//! way the local terminal keeps whatever it isn't sent again.
 
 use crate::output::CharacterChunk;
-use crate::panes::terminal_character::{AnsiCode, CharacterStyles};
-use crate::panes::Selection;
-use zellij_remote_core::{Cell, FrameStore, StyleTable};
+use crate::panes::terminal_character::{
+    AnsiCode, CharacterStyles, CursorShape as ZellijCursorShape,
+};
+use crate::panes::{LineSize as ZellijLineSize, Selection};
+use crate::remote_bridge::{
+    zellij_cursor_color_to_zrp, zellij_cursor_shape_to_zrp, zellij_line_size_to_zrp,
+};
+use crate::ClientId;
+use std::collections::HashMap;
+use zellij_remote_core::{Cell, Cursor, CursorShape, FrameStore, Row, StyleTable};
 
 use super::style_convert::character_styles_to_cell;
 
@@ -46,7 +57,126 @@ pub fn chunks_to_frame_store(
     style_table: &mut StyleTable,
 ) -> FrameStore {
     let mut store = FrameStore::new(cols, rows);
+    paint_chunks(&mut store, chunks, style_table);
+    store.advance_state();
+    store
+}
+
+/// The screen remote clients see, kept across renders so the panes a render didn't
+/// touch stay as they were. Starts over on a blank screen when the size changes or
+/// frames start coming from another client's render.
+#[derive(Debug)]
+pub struct RemoteCanvas {
+    client_id: Option<ClientId>,
+    frame_store: FrameStore,
+    style_table: StyleTable,
+    /// Generation and length of the style table last handed out with a frame
+    styles_sent: Option<(u64, usize)>,
+}
+
+impl Default for RemoteCanvas {
+    fn default() -> Self {
+        Self {
+            client_id: None,
+            frame_store: FrameStore::new(0, 0),
+            style_table: StyleTable::new(),
+            styles_sent: None,
+        }
+    }
+}
+
+impl RemoteCanvas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Paint the chunks of `client_id`'s render and return the whole screen to send,
+    /// with the rows this render touched marked dirty, and the style table if it
+    /// changed since the last paint.
+    ///
+    /// The screen returned shares its rows with the canvas: a later paint copies
+    /// only the rows it touches.
+    pub fn paint(
+        &mut self,
+        client_id: ClientId,
+        chunks: &[CharacterChunk],
+        cols: usize,
+        rows: usize,
+    ) -> (FrameStore, Option<StyleTable>) {
+        let frame = self.frame_store.current_frame();
+        if self.client_id != Some(client_id) || frame.cols != cols || frame.rows.len() != rows {
+            self.client_id = Some(client_id);
+            self.frame_store = FrameStore::new(cols, rows);
+            self.style_table = self.style_table.renumbered();
+            // Nothing painted yet may still be on the remote screen from before
+            for row in 0..rows {
+                self.frame_store.update_row(row, |_| {});
+            }
+        } else if self.style_table.current_count() > self.style_table.max_styles() / 2 {
+            self.compact_styles();
+        }
+        paint_chunks(&mut self.frame_store, chunks, &mut self.style_table);
+        self.frame_store.advance_state();
+        let frame_store = self.frame_store.clone();
+        self.frame_store.take_dirty_rows();
+        let styles = (
+            self.style_table.generation(),
+            self.style_table.current_count(),
+        );
+        let style_table = (self.styles_sent != Some(styles)).then(|| {
+            self.styles_sent = Some(styles);
+            self.style_table.clone()
+        });
+        (frame_store, style_table)
+    }
+
+    /// Rebuild the style table from the styles still on screen, so the ones scrolled
+    /// away stop taking up room, and renumber the cells that use them.
+    fn compact_styles(&mut self) {
+        let mut compacted = self.style_table.renumbered();
+        let mut new_ids: HashMap<u32, u32> = HashMap::new();
+        let mut new_id = |style_id: u32| {
+            *new_ids.entry(style_id).or_insert_with(|| {
+                self.style_table
+                    .get(style_id)
+                    .map_or(0, |style| compacted.get_or_insert(style))
+            })
+        };
+        let mut renumbered_rows = Vec::new();
+        for (row_idx, row) in self.frame_store.current_frame().rows.iter().enumerate() {
+            let cells: Vec<(usize, Cell)> = row
+                .0
+                .cells
+                .iter()
+                .enumerate()
+                .filter_map(|(col, cell)| {
+                    let style_id = new_id(cell.style_id);
+                    (style_id != cell.style_id).then_some((col, Cell { style_id, ..*cell }))
+                })
+                .collect();
+            if !cells.is_empty() {
+                renumbered_rows.push((row_idx, cells));
+            }
+        }
+        for (row_idx, cells) in renumbered_rows {
+            self.frame_store.update_row(row_idx, |row| {
+                for (col, cell) in cells {
+                    row.set_cell(col, cell);
+                }
+            });
+        }
+        log::debug!(
+            "Compacted remote style table from {} to {} styles",
+            self.style_table.current_count(),
+            compacted.current_count()
+        );
+        self.style_table = compacted;
+    }
+}
 
+fn paint_chunks(store: &mut FrameStore, chunks: &[CharacterChunk], style_table: &mut StyleTable) {
+    let frame = store.current_frame();
+    let (cols, rows) = (frame.cols, frame.rows.len());
     for chunk in chunks {
         let chunk_y = chunk.y;
         if chunk_y >= rows {
@@ -55,6 +185,17 @@ pub fn chunks_to_frame_store(
 
         let selection_and_colors = chunk.selection_and_colors();
 
+        // A remote row is scaled as a whole, so only a chunk spanning the full
+        // screen width (a fullscreen, borderless pane) can set its line size
+        if chunk.x == 0 {
+            let chunk_width: usize = chunk.terminal_characters.iter().map(|tc| tc.width()).sum();
+            if chunk_width >= cols {
+                store.update_row(chunk_y, |row| {
+                    row.set_line_size(zellij_line_size_to_zrp(chunk.line_size));
+                });
+            }
+        }
+
         let mut col = chunk.x;
         for tc in &chunk.terminal_characters {
             if col >= cols {
@@ -68,6 +209,7 @@ pub fn chunks_to_frame_store(
             let width = tc.width();
 
             store.update_row(chunk_y, |row| {
+                clear_wide_remnants(row, col, width);
                 row.set_cell(col, cell.clone());
             });
 
@@ -88,15 +230,160 @@ pub fn chunks_to_frame_store(
             col += width;
         }
     }
+}
 
-    store.advance_state();
-    store
+/// Blank out what's left of a wide character on `row` once a `width`-wide one is
+/// painted over part of it at `col`, as a terminal would
+fn clear_wide_remnants(row: &mut Row, col: usize, width: usize) {
+    let continuation = |row: &Row, col: usize| row.get_cell(col).is_some_and(|c| c.width == 0);
+    if col > 0 && continuation(row, col) {
+        let style_id = row.get_cell(col - 1).map_or(0, |c| c.style_id);
+        row.set_cell(
+            col - 1,
+            Cell {
+                style_id,
+                ..Cell::default()
+            },
+        );
+    }
+    let mut after = col + width.max(1);
+    while continuation(row, after) {
+        let style_id = row.get_cell(after).map_or(0, |c| c.style_id);
+        row.set_cell(
+            after,
+            Cell {
+                style_id,
+                ..Cell::default()
+            },
+        );
+        after += 1;
+    }
+}
+
+/// The cursor the local terminal draws over the composited screen: the active
+/// pane's, in screen coordinates
+#[derive(Debug, Clone, Copy)]
+pub struct HostCursor {
+    pub x: usize,
+    pub y: usize,
+    pub shape: ZellijCursorShape,
+    pub color: Option<AnsiCode>,
+}
+
+/// Put the host cursor on a frame built by [`chunks_to_frame_store`], which only
+/// sees characters; `None` hides it.
+pub fn set_frame_cursor(store: &mut FrameStore, cursor: Option<HostCursor>) {
+    let frame = store.current_frame();
+    let (cols, rows) = (frame.cols, frame.rows.len());
+    let cursor = match cursor {
+        Some(cursor) if cursor.x < cols && cursor.y < rows => {
+            let (shape, blink) = zellij_cursor_shape_to_zrp(&cursor.shape);
+            Cursor {
+                row: cursor.y as u32,
+                col: cursor.x as u32,
+                visible: true,
+                blink,
+                shape,
+                color: zellij_cursor_color_to_zrp(cursor.color),
+            }
+        },
+        _ => Cursor {
+            row: 0,
+            col: 0,
+            visible: false,
+            blink: false,
+            shape: CursorShape::Block,
+            color: None,
+        },
+    };
+    store.set_cursor(cursor);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::output::{FloatingPanesStack, Output};
+    use crate::panes::sixel::SixelImageStore;
     use crate::panes::terminal_character::TerminalCharacter;
+    use crate::panes::{Grid, LinkHandler};
+    use crate::remote_bridge::grid_to_frame_store;
+    use std::cell::RefCell;
+    use std::collections::{HashMap, HashSet};
+    use std::rc::Rc;
+    use zellij_remote_core::LineSize;
+    use zellij_utils::data::{Palette, Style};
+    use zellij_utils::pane_size::{Dimension, PaneGeom};
+
+    fn text_chunk(text: &str, x: usize, y: usize) -> CharacterChunk {
+        CharacterChunk::new(text.chars().map(TerminalCharacter::new).collect(), x, y)
+    }
+
+    fn row_text(store: &FrameStore, row: usize) -> String {
+        store.current_frame().rows[row]
+            .0
+            .cells
+            .iter()
+            .filter(|cell| cell.width > 0)
+            .filter_map(|cell| char::from_u32(cell.codepoint))
+            .collect()
+    }
+
+    /// A terminal fed what the local client is sent, to check remote frames against
+    fn local_terminal(cols: usize, rows: usize) -> Grid {
+        Grid::new(
+            rows,
+            cols,
+            Rc::new(RefCell::new(Palette::default())),
+            Rc::new(RefCell::new(HashMap::new())),
+            Rc::new(RefCell::new(LinkHandler::new())),
+            Rc::new(RefCell::new(None)),
+            Rc::new(RefCell::new(SixelImageStore::default())),
+            Style::default(),
+            false,
+            true,
+            true,
+            false,
+        )
+    }
+
+    /// One render to client 1 with `floating` stacked over the tiled panes, the way
+    /// `Screen` renders: painted on `canvas` and sent to `local` as escape codes
+    fn render(
+        canvas: &mut RemoteCanvas,
+        local: &mut Grid,
+        floating: &[PaneGeom],
+        tiled_chunks: Vec<CharacterChunk>,
+        floating_chunks: Vec<CharacterChunk>,
+    ) -> FrameStore {
+        let mut output = Output::default();
+        output.add_clients(
+            &HashSet::from([1]),
+            Rc::new(RefCell::new(LinkHandler::new())),
+            Some(FloatingPanesStack {
+                layers: floating.to_vec(),
+            }),
+        );
+        output
+            .add_character_chunks_to_client(1, tiled_chunks, None)
+            .unwrap();
+        output
+            .add_character_chunks_to_client(1, floating_chunks, Some(1))
+            .unwrap();
+        let cols = local.width;
+        let rows = local.height;
+        let (frame_store, _) = canvas.paint(
+            1,
+            output.get_client_character_chunks(1).unwrap(),
+            cols,
+            rows,
+        );
+
+        let mut parser = vte::Parser::new();
+        for byte in output.serialize().unwrap()[&1].bytes() {
+            parser.advance(local, byte);
+        }
+        frame_store
+    }
 
     #[test]
     fn test_empty_chunks() {
@@ -204,6 +491,23 @@ mod tests {
         assert_eq!(frame.rows[3].get_cell(5).unwrap().codepoint, 'Y' as u32);
     }
 
+    #[test]
+    fn test_line_size_only_for_full_width_chunks() {
+        let mut style_table = StyleTable::new();
+        let full: Vec<TerminalCharacter> = "abcd".chars().map(TerminalCharacter::new).collect();
+        let partial: Vec<TerminalCharacter> = "ab".chars().map(TerminalCharacter::new).collect();
+        let chunks = [
+            CharacterChunk::new(full, 0, 0).with_line_size(ZellijLineSize::DoubleHeightTop),
+            CharacterChunk::new(partial, 2, 1).with_line_size(ZellijLineSize::DoubleWidth),
+        ];
+
+        let store = chunks_to_frame_store(&chunks, 4, 2, &mut style_table);
+
+        let frame = store.current_frame();
+        assert_eq!(frame.rows[0].line_size(), LineSize::DoubleHeightTop);
+        assert_eq!(frame.rows[1].line_size(), LineSize::Single);
+    }
+
     #[test]
     fn test_wide_char_at_edge_truncated() {
         let mut style_table = StyleTable::new();
@@ -216,4 +520,191 @@ mod tests {
         let cell = frame.rows[0].get_cell(79).unwrap();
         assert_eq!(cell.codepoint, '中' as u32);
     }
+
+    #[test]
+    fn test_canvas_keeps_what_a_render_left_out() {
+        let mut canvas = RemoteCanvas::new();
+        let (store, _) = canvas.paint(
+            1,
+            &[text_chunk("left", 0, 0), text_chunk("right", 10, 1)],
+            20,
+            2,
+        );
+        assert_eq!(row_text(&store, 1).trim_end(), "          right");
+
+        let (mut store, _) = canvas.paint(1, &[text_chunk("LEFT", 0, 0)], 20, 2);
+        assert_eq!(row_text(&store, 0).trim_end(), "LEFT");
+        assert_eq!(row_text(&store, 1).trim_end(), "          right");
+        assert_eq!(store.take_dirty_rows(), HashSet::from([0]));
+    }
+
+    #[test]
+    fn test_canvas_starts_over_for_new_size_or_client() {
+        let mut canvas = RemoteCanvas::new();
+        canvas.paint(1, &[text_chunk("old", 0, 0)], 20, 2);
+
+        let (mut store, _) = canvas.paint(1, &[], 30, 2);
+        assert_eq!(row_text(&store, 0).trim_end(), "");
+        assert_eq!(store.take_dirty_rows(), HashSet::from([0, 1]));
+
+        canvas.paint(1, &[text_chunk("old", 0, 0)], 30, 2);
+        let (store, _) = canvas.paint(2, &[], 30, 2);
+        assert_eq!(row_text(&store, 0).trim_end(), "");
+    }
+
+    #[test]
+    fn test_canvas_clears_half_overwritten_wide_chars() {
+        let mut canvas = RemoteCanvas::new();
+        canvas.paint(1, &[text_chunk("中中", 0, 0)], 6, 1);
+        let (store, _) = canvas.paint(1, &[text_chunk("a", 1, 0), text_chunk("b", 2, 0)], 6, 1);
+        let row = &store.current_frame().rows[0];
+        assert_eq!(row.get_cell(0).unwrap().codepoint, ' ' as u32);
+        assert_eq!(row.get_cell(0).unwrap().width, 1);
+        assert_eq!(row.get_cell(3).unwrap().codepoint, ' ' as u32);
+        assert_eq!(row.get_cell(3).unwrap().width, 1);
+    }
+
+    fn red_chunk(text: &str, red: u8, x: usize, y: usize) -> CharacterChunk {
+        let styles = CharacterStyles::default().foreground(Some(AnsiCode::RgbCode((red, 0, 0))));
+        let characters = text
+            .chars()
+            .map(|c| TerminalCharacter::new_styled(c, styles.into()))
+            .collect();
+        CharacterChunk::new(characters, x, y)
+    }
+
+    fn red_of(store: &FrameStore, style_table: &StyleTable, row: usize, col: usize) -> u8 {
+        let style_id = store.current_frame().rows[row]
+            .get_cell(col)
+            .unwrap()
+            .style_id;
+        assert_ne!(style_id, 0, "cell lost its style");
+        match style_table.get(style_id).and_then(|style| style.fg.clone()) {
+            Some(zellij_remote_protocol::Color {
+                value: Some(zellij_remote_protocol::color::Value::Rgb(rgb)),
+            }) => rgb.r as u8,
+            other => panic!("expected an RGB foreground, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_canvas_sends_style_table_only_when_it_changes() {
+        let mut canvas = RemoteCanvas::new();
+        let (_, style_table) = canvas.paint(1, &[red_chunk("a", 1, 0, 0)], 4, 1);
+        assert_eq!(style_table.map(|table| table.current_count()), Some(2));
+
+        let (_, style_table) = canvas.paint(1, &[red_chunk("b", 1, 1, 0)], 4, 1);
+        assert!(style_table.is_none());
+
+        let (_, style_table) = canvas.paint(1, &[red_chunk("c", 2, 2, 0)], 4, 1);
+        assert_eq!(style_table.map(|table| table.current_count()), Some(3));
+    }
+
+    #[test]
+    fn test_canvas_shares_untouched_rows_between_frames() {
+        let mut canvas = RemoteCanvas::new();
+        let (first, _) = canvas.paint(1, &[text_chunk("top", 0, 0)], 4, 2);
+        let (second, _) = canvas.paint(1, &[text_chunk("low", 0, 1)], 4, 2);
+        let (first, second) = (first.current_frame(), second.current_frame());
+        assert!(first.rows[0].ptr_eq(&second.rows[0]));
+        assert!(!first.rows[1].ptr_eq(&second.rows[1]));
+    }
+
+    #[test]
+    fn test_canvas_compacts_style_table_before_it_fills() {
+        let mut canvas = RemoteCanvas {
+            style_table: StyleTable::with_max_styles(8),
+            ..RemoteCanvas::new()
+        };
+        let (_, style_table) = canvas.paint(1, &[red_chunk("kept", 200, 0, 0)], 4, 2);
+        let mut style_table = style_table.unwrap();
+        let first_generation = style_table.generation();
+
+        // Each render repaints row 1 in a new color; the old ones leave the screen
+        for red in 1..=20 {
+            let (store, table) = canvas.paint(1, &[red_chunk("x", red, 0, 1)], 4, 2);
+            if let Some(table) = table {
+                style_table = table;
+            }
+            assert!(!style_table.is_full());
+            assert_eq!(red_of(&store, &style_table, 0, 0), 200);
+            assert_eq!(red_of(&store, &style_table, 1, 0), red);
+        }
+        assert!(style_table.generation() > first_generation);
+        assert_eq!(style_table.overflowed(), 0);
+    }
+
+    #[test]
+    fn test_canvas_matches_local_terminal_under_floating_pane() {
+        let (cols, rows) = (20, 4);
+        let floating = [PaneGeom {
+            x: 5,
+            y: 1,
+            cols: Dimension::fixed(8),
+            rows: Dimension::fixed(2),
+            ..Default::default()
+        }];
+        let tiled = |c: char| -> Vec<CharacterChunk> {
+            (0..rows)
+                .map(|y| text_chunk(&c.to_string().repeat(cols), 0, y))
+                .collect()
+        };
+        let floating_chunks = vec![text_chunk("FLOATING", 5, 1), text_chunk("floating", 5, 2)];
+
+        let mut canvas = RemoteCanvas::new();
+        let mut local = local_terminal(cols, rows);
+        render(
+            &mut canvas,
+            &mut local,
+            &floating,
+            tiled('a'),
+            floating_chunks,
+        );
+        // Only the tiled pane changes; the floating pane isn't rendered again
+        let remote = render(&mut canvas, &mut local, &floating, tiled('b'), vec![]);
+
+        let local = grid_to_frame_store(&local, &mut StyleTable::new());
+        for row in 0..rows {
+            assert_eq!(row_text(&remote, row), row_text(&local, row), "row {}", row);
+        }
+        assert_eq!(row_text(&remote, 1), "bbbbbFLOATINGbbbbbbb");
+    }
+
+    #[test]
+    fn test_host_cursor_keeps_shape_blink_and_color() {
+        let mut style_table = StyleTable::new();
+        let mut store = chunks_to_frame_store(&[], 80, 24, &mut style_table);
+
+        set_frame_cursor(
+            &mut store,
+            Some(HostCursor {
+                x: 12,
+                y: 4,
+                shape: ZellijCursorShape::BlinkingUnderline,
+                color: Some(AnsiCode::RgbCode((0, 255, 0))),
+            }),
+        );
+        let cursor = store.current_frame().cursor;
+        assert_eq!((cursor.row, cursor.col), (4, 12));
+        assert!(cursor.visible && cursor.blink);
+        assert_eq!(cursor.shape, CursorShape::Underline);
+        assert_eq!(cursor.color.map(|c| (c.r, c.g, c.b)), Some((0, 255, 0)));
+
+        set_frame_cursor(
+            &mut store,
+            Some(HostCursor {
+                x: 12,
+                y: 4,
+                shape: ZellijCursorShape::Beam,
+                color: None,
+            }),
+        );
+        let cursor = store.current_frame().cursor;
+        assert!(!cursor.blink);
+        assert_eq!(cursor.shape, CursorShape::Bar);
+        assert_eq!(cursor.color, None);
+
+        set_frame_cursor(&mut store, None);
+        assert!(!store.current_frame().cursor.visible);
+    }
 }
@@ -0,0 +1,162 @@
+use zellij_remote_protocol::{PaneBoundary, PaneLayout, PaneRect};
+use zellij_utils::data::PaneInfo;
+
+use crate::panes::PaneId;
+
+/// The panes a client sees of a tab, from its `pane_infos()`: suppressed panes are
+/// left out, as are tiled panes hidden behind a fullscreen one and floating panes while
+/// they're hidden. `focused` is the pane remote input goes to.
+pub fn pane_layout(
+    panes: Vec<PaneInfo>,
+    focused: Option<PaneId>,
+    floating_panes_visible: bool,
+) -> PaneLayout {
+    let fullscreen = panes.iter().any(|pane| pane.is_fullscreen);
+    let (floating, tiled): (Vec<_>, Vec<_>) = panes
+        .into_iter()
+        .filter(|pane| !pane.is_suppressed)
+        .partition(|pane| pane.is_floating);
+    let tiled = tiled
+        .into_iter()
+        .filter(|pane| !fullscreen || pane.is_fullscreen || !pane.is_selectable);
+    let floating = floating.into_iter().filter(|_| floating_panes_visible);
+    PaneLayout {
+        panes: tiled
+            .chain(floating)
+            .map(|pane| boundary(pane, focused))
+            .collect(),
+    }
+}
+
+/// The pane a `FocusPane` names
+pub fn focus_target(pane_id: u32, is_plugin: bool) -> PaneId {
+    if is_plugin {
+        PaneId::Plugin(pane_id)
+    } else {
+        PaneId::Terminal(pane_id)
+    }
+}
+
+fn boundary(pane: PaneInfo, focused: Option<PaneId>) -> PaneBoundary {
+    PaneBoundary {
+        pane_id: pane.id,
+        is_plugin: pane.is_plugin,
+        rect: Some(rect(
+            pane.pane_x,
+            pane.pane_y,
+            pane.pane_columns,
+            pane.pane_rows,
+        )),
+        content: Some(rect(
+            pane.pane_content_x,
+            pane.pane_content_y,
+            pane.pane_content_columns,
+            pane.pane_content_rows,
+        )),
+        focused: focused == Some(focus_target(pane.id, pane.is_plugin)),
+        floating: pane.is_floating,
+        selectable: pane.is_selectable,
+        title: pane.title,
+    }
+}
+
+fn rect(col: usize, row: usize, cols: usize, rows: usize) -> PaneRect {
+    PaneRect {
+        col: col as u32,
+        row: row as u32,
+        cols: cols as u32,
+        rows: rows as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane(id: u32, x: usize, is_floating: bool) -> PaneInfo {
+        PaneInfo {
+            id,
+            is_floating,
+            is_selectable: true,
+            title: format!("pane {}", id),
+            pane_x: x,
+            pane_content_x: x + 1,
+            pane_y: 1,
+            pane_content_y: 2,
+            pane_columns: 40,
+            pane_content_columns: 38,
+            pane_rows: 20,
+            pane_content_rows: 18,
+            ..Default::default()
+        }
+    }
+
+    fn ids(layout: &PaneLayout) -> Vec<u32> {
+        layout.panes.iter().map(|pane| pane.pane_id).collect()
+    }
+
+    #[test]
+    fn test_floating_panes_on_top() {
+        let status_bar = PaneInfo {
+            id: 0,
+            is_plugin: true,
+            pane_rows: 1,
+            pane_content_rows: 1,
+            pane_columns: 80,
+            pane_content_columns: 80,
+            ..Default::default()
+        };
+        let panes = vec![
+            pane(5, 10, true),
+            pane(1, 0, false),
+            pane(2, 40, false),
+            status_bar,
+        ];
+        let layout = pane_layout(panes.clone(), Some(PaneId::Terminal(2)), true);
+        assert_eq!(ids(&layout), [1, 2, 0, 5]);
+        assert!(layout.panes[3].floating);
+        assert!(!layout.panes[2].selectable);
+
+        let focused = &layout.panes[1];
+        assert!(focused.focused);
+        assert!(!layout.panes[0].focused);
+        assert_eq!(
+            focused.rect,
+            Some(PaneRect {
+                col: 40,
+                row: 1,
+                cols: 40,
+                rows: 20,
+            })
+        );
+        assert_eq!(
+            focused.content,
+            Some(PaneRect {
+                col: 41,
+                row: 2,
+                cols: 38,
+                rows: 18,
+            })
+        );
+        assert_eq!(focused.title, "pane 2");
+
+        // A plugin with the same id as the focused terminal isn't focused
+        let layout = pane_layout(panes, Some(PaneId::Terminal(0)), false);
+        assert_eq!(ids(&layout), [1, 2, 0]);
+        assert!(!layout.panes[2].focused);
+    }
+
+    #[test]
+    fn test_hidden_panes_left_out() {
+        let mut fullscreen = pane(2, 0, false);
+        fullscreen.is_fullscreen = true;
+        let mut suppressed = pane(3, 0, false);
+        suppressed.is_suppressed = true;
+        let layout = pane_layout(
+            vec![pane(1, 0, false), fullscreen, suppressed],
+            Some(PaneId::Terminal(2)),
+            true,
+        );
+        assert_eq!(ids(&layout), [2]);
+    }
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use prost::Message;
+use zellij_remote_protocol::PersistedSession;
+
+/// Kept next to the session's other cached metadata
+pub const PERSIST_FILE_NAME: &str = "remote-session.pb";
+/// Resume tokens expire after five minutes, so an older file can't honour any of them
+pub const PERSIST_MAX_AGE_MS: u64 = 300_000;
+/// How often the remote session is saved while serving
+pub const PERSIST_INTERVAL_MS: u64 = 10_000;
+
+/// Write `persisted` to `path`, readable by the owner only: it holds the resume token
+/// secret. The file is replaced in one step, so a crash mid-write keeps the old one.
+pub fn save(path: &Path, persisted: &PersistedSession) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp_path = path.with_extension("pb.tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp_path)?;
+    file.write_all(&persisted.encode_to_vec())?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+/// The session saved at `path`, if there is a readable one.
+pub fn load(path: &Path) -> Option<PersistedSession> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            log::warn!(
+                "Failed to read saved remote session {}: {}",
+                path.display(),
+                e
+            );
+            return None;
+        },
+    };
+    match PersistedSession::decode(bytes.as_slice()) {
+        Ok(persisted) => Some(persisted),
+        Err(e) => {
+            log::warn!(
+                "Ignoring corrupt saved remote session {}: {}",
+                path.display(),
+                e
+            );
+            None
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn persisted() -> PersistedSession {
+        PersistedSession {
+            version: 1,
+            session_id: 42,
+            token_secret: vec![7; 32],
+            next_client_id: 3,
+            saved_at_ms: 1_000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_save_load_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session").join(PERSIST_FILE_NAME);
+
+        assert!(load(&path).is_none());
+        save(&path, &persisted()).unwrap();
+        assert_eq!(load(&path), Some(persisted()));
+
+        let newer = PersistedSession {
+            saved_at_ms: 2_000,
+            ..persisted()
+        };
+        save(&path, &newer).unwrap();
+        assert_eq!(load(&path), Some(newer));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_saved_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PERSIST_FILE_NAME);
+        save(&path, &persisted()).unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_corrupt_file_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PERSIST_FILE_NAME);
+        fs::write(&path, [0xff, 0xff, 0xff]).unwrap();
+        assert!(load(&path).is_none());
+    }
+}
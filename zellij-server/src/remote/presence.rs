@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+
+use zellij_remote_protocol::ClientLocale;
+use zellij_utils::data::RemoteClientInfo;
+
+use super::locale::LocaleHint;
+
+/// Longest client name shown in the local UI
+pub const MAX_CLIENT_NAME_LEN: usize = 64;
+
+/// Which remote clients are attached and which one is in control, as shown in the local
+/// UI (status bar, plugins).
+///
+/// The screen thread is sent the whole list, and only when it changed since the last
+/// report, so callers can poll after anything that might attach, detach or move the lease.
+#[derive(Debug, Default)]
+pub struct RemotePresence {
+    names: BTreeMap<u64, String>,
+    locales: BTreeMap<u64, LocaleHint>,
+    /// Delay injected on what's sent to each client, for telling its numbers apart
+    latencies: BTreeMap<u64, u64>,
+    reported: Vec<RemoteClientInfo>,
+}
+
+impl RemotePresence {
+    pub fn add_client(&mut self, remote_id: u64, client_name: &str) {
+        self.names
+            .insert(remote_id, display_name(remote_id, client_name));
+    }
+
+    /// The locale from the client's ClientHello, if it sent one
+    pub fn set_locale(&mut self, remote_id: u64, locale: &ClientLocale) {
+        self.locales
+            .insert(remote_id, LocaleHint::from_client(locale));
+    }
+
+    /// Mark the client as delayed by `delay_ms`, or no longer delayed with 0
+    pub fn set_injected_latency(&mut self, remote_id: u64, delay_ms: u64) {
+        if delay_ms == 0 {
+            self.latencies.remove(&remote_id);
+        } else if self.names.contains_key(&remote_id) {
+            self.latencies.insert(remote_id, delay_ms);
+        }
+    }
+
+    /// The client's name as shown in the local UI
+    pub fn name(&self, remote_id: u64) -> Option<&str> {
+        self.names.get(&remote_id).map(String::as_str)
+    }
+
+    pub fn remove_client(&mut self, remote_id: u64) {
+        self.names.remove(&remote_id);
+        self.locales.remove(&remote_id);
+        self.latencies.remove(&remote_id);
+    }
+
+    pub fn clients(&self, controller: Option<u64>) -> Vec<RemoteClientInfo> {
+        self.names
+            .iter()
+            .map(|(remote_id, name)| {
+                let locale = self.locales.get(remote_id).cloned().unwrap_or_default();
+                RemoteClientInfo {
+                    remote_id: *remote_id,
+                    name: name.clone(),
+                    is_controller: controller == Some(*remote_id),
+                    timezone: locale.timezone,
+                    locale: locale.locale,
+                    utc_offset_minutes: locale.utc_offset_minutes,
+                    injected_latency_ms: self.latencies.get(remote_id).copied().unwrap_or(0),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the client list when it differs from the last list returned.
+    pub fn poll_change(&mut self, controller: Option<u64>) -> Option<Vec<RemoteClientInfo>> {
+        let clients = self.clients(controller);
+        if clients == self.reported {
+            return None;
+        }
+        self.reported = clients.clone();
+        Some(clients)
+    }
+}
+
+/// Client names come straight from the ClientHello, so strip anything that could mess
+/// with the local terminal and fall back to the remote id when nothing is left.
+fn display_name(remote_id: u64, client_name: &str) -> String {
+    let name: String = client_name
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_CLIENT_NAME_LEN)
+        .collect();
+    let name = name.trim();
+    if name.is_empty() {
+        format!("remote-{}", remote_id)
+    } else {
+        name.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_change_reports_attach_detach_and_controller() {
+        let mut presence = RemotePresence::default();
+        assert_eq!(presence.poll_change(None), None);
+
+        presence.add_client(1, "alice@ios");
+        presence.add_client(2, "bob@laptop");
+        let clients = presence.poll_change(Some(1)).unwrap();
+        assert_eq!(clients.len(), 2);
+        assert!(clients[0].is_controller);
+        assert!(!clients[1].is_controller);
+        assert_eq!(presence.poll_change(Some(1)), None);
+
+        let clients = presence.poll_change(Some(2)).unwrap();
+        assert!(clients[1].is_controller);
+
+        presence.remove_client(2);
+        let clients = presence.poll_change(None).unwrap();
+        assert_eq!(
+            clients,
+            vec![RemoteClientInfo {
+                remote_id: 1,
+                name: "alice@ios".to_owned(),
+                is_controller: false,
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_client_names_are_sanitized() {
+        let mut presence = RemotePresence::default();
+        presence.add_client(7, "\u{1b}[2J");
+        presence.add_client(8, &"x".repeat(200));
+        let clients = presence.clients(None);
+        assert_eq!(clients[0].name, "[2J");
+        assert_eq!(clients[1].name.len(), MAX_CLIENT_NAME_LEN);
+
+        presence.add_client(9, " \n ");
+        assert_eq!(presence.clients(None)[2].name, "remote-9");
+    }
+
+    #[test]
+    fn test_client_locale_reported() {
+        let mut presence = RemotePresence::default();
+        presence.add_client(1, "alice@ios");
+        presence.poll_change(None).unwrap();
+
+        presence.set_locale(
+            1,
+            &ClientLocale {
+                timezone: "Asia/Tokyo".to_owned(),
+                locale: "ja-JP".to_owned(),
+                utc_offset_minutes: 540,
+                has_utc_offset: true,
+            },
+        );
+        let clients = presence.poll_change(None).unwrap();
+        assert_eq!(clients[0].timezone, "Asia/Tokyo");
+        assert_eq!(clients[0].locale, "ja-JP");
+        assert_eq!(clients[0].utc_offset_minutes, Some(540));
+
+        presence.remove_client(1);
+        presence.add_client(1, "alice@ios");
+        assert_eq!(presence.clients(None)[0].timezone, "");
+    }
+
+    #[test]
+    fn test_injected_latency_reported() {
+        let mut presence = RemotePresence::default();
+        presence.add_client(1, "alice@ios");
+        presence.set_injected_latency(2, 300);
+        presence.poll_change(None).unwrap();
+
+        presence.set_injected_latency(1, 300);
+        let clients = presence.poll_change(None).unwrap();
+        assert_eq!(clients[0].injected_latency_ms, 300);
+
+        presence.set_injected_latency(1, 0);
+        assert_eq!(
+            presence.poll_change(None).unwrap()[0].injected_latency_ms,
+            0
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use zellij_remote_protocol::TerminalAnswers as ClientAnswers;
+
+use crate::panes::terminal_answers::TerminalAnswers;
+
+/// DA1 attribute for sixel graphics, which remote clients are never sent
+const SIXEL_ATTRIBUTE: u32 = 4;
+/// Most XTGETTCAP capabilities kept per client
+pub const MAX_TERMCAP_ENTRIES: usize = 256;
+
+/// The terminal answers each remote client sent in its `ClientHello`, and which ones
+/// panes were last told to give.
+///
+/// Only the controller's answers are used, so like presence the session is told only
+/// when they change, and callers can poll after anything that might move the lease.
+#[derive(Debug, Default)]
+pub struct ControllerAnswers {
+    by_client: HashMap<u64, TerminalAnswers>,
+    reported: Option<TerminalAnswers>,
+}
+
+impl ControllerAnswers {
+    pub fn add_client(&mut self, remote_id: u64, answers: &ClientAnswers) {
+        self.by_client.insert(remote_id, from_client(answers));
+    }
+
+    pub fn remove_client(&mut self, remote_id: u64) {
+        self.by_client.remove(&remote_id);
+    }
+
+    /// The answers panes should give with `controller` in control, when they differ
+    /// from the last ones returned. `Some(None)` means the local terminal's: there's no
+    /// controller, or it sent no answers.
+    pub fn poll_change(&mut self, controller: Option<u64>) -> Option<Option<TerminalAnswers>> {
+        let answers = controller.and_then(|remote_id| self.by_client.get(&remote_id).cloned());
+        if answers == self.reported {
+            return None;
+        }
+        self.reported = answers.clone();
+        Some(answers)
+    }
+}
+
+fn from_client(answers: &ClientAnswers) -> TerminalAnswers {
+    TerminalAnswers {
+        device_attributes: answers
+            .device_attributes
+            .iter()
+            .copied()
+            .filter(|attribute| *attribute != SIXEL_ATTRIBUTE)
+            .collect(),
+        termcap: answers
+            .termcap
+            .iter()
+            .take(MAX_TERMCAP_ENTRIES)
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client_answers(device_attributes: Vec<u32>) -> ClientAnswers {
+        ClientAnswers {
+            device_attributes,
+            termcap: [("TN".to_string(), "xterm-kitty".to_string())]
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_controllers_answers_reported_on_change() {
+        let mut answers = ControllerAnswers::default();
+        answers.add_client(1, &client_answers(vec![4, 22]));
+        answers.add_client(2, &client_answers(vec![22, 52]));
+
+        let Some(Some(first)) = answers.poll_change(Some(1)) else {
+            panic!("Expected client 1's answers");
+        };
+        // Sixel is dropped, as remote clients don't get images
+        assert_eq!(first.device_attributes, vec![22]);
+        assert_eq!(first.termcap["TN"], "xterm-kitty");
+        assert_eq!(answers.poll_change(Some(1)), None);
+
+        let Some(Some(second)) = answers.poll_change(Some(2)) else {
+            panic!("Expected client 2's answers");
+        };
+        assert_eq!(second.device_attributes, vec![22, 52]);
+
+        answers.remove_client(2);
+        assert_eq!(answers.poll_change(Some(2)), Some(None));
+        assert_eq!(answers.poll_change(None), None);
+    }
+}
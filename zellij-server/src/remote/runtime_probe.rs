@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+/// Worker threads the remote thread's runtime gets unless configured otherwise
+pub const DEFAULT_WORKER_THREADS: usize = 2;
+/// A probe task that waits this long for a worker means every task is waiting about
+/// as long: accepting connections, reading input, writing frames
+pub const SLOW_QUEUE_DELAY_MS: u64 = 100;
+
+/// How long tasks spawned on the remote runtime wait for a worker, measured by
+/// spawning a probe task now and then and timing how soon it runs. Saturated workers
+/// show up here before clients notice frames or input acks lagging.
+#[derive(Debug, Default)]
+pub struct RuntimeProbe {
+    worst: Duration,
+    /// Probes that waited at least `SLOW_QUEUE_DELAY_MS`
+    slow_probes: u32,
+}
+
+impl RuntimeProbe {
+    /// Record how long a probe waited to run; true if that was slow
+    pub fn record(&mut self, queue_delay: Duration) -> bool {
+        self.worst = self.worst.max(queue_delay);
+        let slow = queue_delay >= Duration::from_millis(SLOW_QUEUE_DELAY_MS);
+        if slow {
+            self.slow_probes = self.slow_probes.wrapping_add(1);
+        }
+        slow
+    }
+
+    pub fn worst(&self) -> Duration {
+        self.worst
+    }
+
+    pub fn slow_probes(&self) -> u32 {
+        self.slow_probes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_probes_counted_and_worst_kept() {
+        let mut probe = RuntimeProbe::default();
+        assert!(!probe.record(Duration::from_millis(2)));
+        assert!(probe.record(Duration::from_millis(SLOW_QUEUE_DELAY_MS + 50)));
+        assert!(!probe.record(Duration::from_millis(5)));
+
+        assert_eq!(
+            probe.worst(),
+            Duration::from_millis(SLOW_QUEUE_DELAY_MS + 50)
+        );
+        assert_eq!(probe.slow_probes(), 1);
+    }
+}
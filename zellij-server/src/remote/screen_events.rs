@@ -0,0 +1,94 @@
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use zellij_utils::channels::SenderWithContext;
+use zellij_utils::data::{
+    KeyWithModifier, RemoteApprovalRequest, RemoteClientInfo, RemoteFrameStage, RemoteInputLatency,
+};
+use zellij_utils::pane_size::Size;
+
+use super::session_events::OutboundSessionEvents;
+use crate::panes::terminal_answers::TerminalAnswers;
+use crate::panes::PaneId;
+use crate::screen::ScreenInstruction;
+use crate::ClientId;
+
+/// [`OutboundSessionEvents`] delivered to the screen thread.
+#[derive(Clone)]
+pub struct ScreenEvents {
+    to_screen: SenderWithContext<ScreenInstruction>,
+}
+
+impl ScreenEvents {
+    pub fn new(to_screen: SenderWithContext<ScreenInstruction>) -> Self {
+        Self { to_screen }
+    }
+
+    fn send(&self, instruction: ScreenInstruction) -> Result<()> {
+        self.to_screen
+            .send(instruction)
+            .map_err(|e| anyhow::anyhow!("screen thread is gone: {}", e))
+    }
+}
+
+impl OutboundSessionEvents for ScreenEvents {
+    fn write_to_pane(
+        &self,
+        client_id: ClientId,
+        key_with_modifier: Option<KeyWithModifier>,
+        bytes: Vec<u8>,
+        is_kitty_keyboard_protocol: bool,
+    ) -> Result<()> {
+        self.send(ScreenInstruction::WriteCharacter(
+            key_with_modifier,
+            bytes,
+            is_kitty_keyboard_protocol,
+            client_id,
+            None,
+        ))
+    }
+
+    fn focus_pane(&self, client_id: ClientId, pane_id: PaneId) -> Result<()> {
+        self.send(ScreenInstruction::FocusPaneWithId(
+            pane_id, false, false, client_id, None,
+        ))
+    }
+
+    fn resize(&self, size: Size) -> Result<()> {
+        self.send(ScreenInstruction::TerminalResize(size))
+    }
+
+    fn remote_clients_changed(&self, clients: Vec<RemoteClientInfo>) -> Result<()> {
+        self.send(ScreenInstruction::RemoteClientsChanged(clients))
+    }
+
+    fn terminal_answers_changed(&self, answers: Option<TerminalAnswers>) -> Result<()> {
+        self.send(ScreenInstruction::RemoteTerminalAnswers(answers))
+    }
+
+    fn frame_stages_changed(&self, stages: Vec<RemoteFrameStage>) -> Result<()> {
+        self.send(ScreenInstruction::RemoteFrameStages(stages))
+    }
+
+    fn input_latency_changed(&self, latency: Vec<RemoteInputLatency>) -> Result<()> {
+        self.send(ScreenInstruction::RemoteInputLatency(latency))
+    }
+
+    fn serving_changed(&self, listen_addr: Option<SocketAddr>) -> Result<()> {
+        self.send(ScreenInstruction::RemoteServingChanged(
+            listen_addr.map(|addr| addr.to_string()),
+        ))
+    }
+
+    fn approval_requested(&self, request: RemoteApprovalRequest) -> Result<()> {
+        self.send(ScreenInstruction::RemoteApprovalRequested(request))
+    }
+
+    fn approval_resolved(&self, request_id: u64) -> Result<()> {
+        self.send(ScreenInstruction::RemoteApprovalResolved(request_id))
+    }
+
+    fn heartbeat(&self) -> Result<()> {
+        self.send(ScreenInstruction::RemoteHeartbeat)
+    }
+}
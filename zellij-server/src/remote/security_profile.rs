@@ -0,0 +1,182 @@
+//! Whether the remote server may start with the configuration it was given.
+//!
+//! Every check runs here, once, before the listener binds. A [`SecurityProfile`] says
+//! which combinations are acceptable: `strict` needs a bearer token, a loopback
+//! address (there are no client certificates to fall back on, so no mTLS),
+//! explicit-only control and the audit log, and turns on the last two unless told
+//! otherwise; `default` refuses to put an unauthenticated session on the network;
+//! `open` refuses nothing. A refused configuration doesn't start unless the server
+//! was started with `--i-know-what-im-doing`, which logs each problem instead.
+
+use std::fmt;
+
+use zellij_remote_protocol::ControllerPolicy;
+
+use super::thread::RemoteConfig;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SecurityProfile {
+    Strict,
+    #[default]
+    Default,
+    Open,
+}
+
+impl SecurityProfile {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "strict" => Some(SecurityProfile::Strict),
+            "default" => Some(SecurityProfile::Default),
+            "open" => Some(SecurityProfile::Open),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SecurityProfile::Strict => "strict",
+            SecurityProfile::Default => "default",
+            SecurityProfile::Open => "open",
+        }
+    }
+
+    /// Control policy for configs that don't name one
+    fn controller_policy(self) -> ControllerPolicy {
+        match self {
+            SecurityProfile::Strict => ControllerPolicy::ExplicitOnly,
+            SecurityProfile::Default | SecurityProfile::Open => ControllerPolicy::LastWriterWins,
+        }
+    }
+
+    /// Whether the audit log is on for configs that don't say
+    fn audit_log(self) -> bool {
+        self == SecurityProfile::Strict
+    }
+}
+
+/// What the server runs with once its profile lets it start
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hardening {
+    pub controller_policy: ControllerPolicy,
+    pub audit_log: bool,
+    /// Problems the profile let through, to be logged as the server starts
+    pub warnings: Vec<String>,
+}
+
+/// Why a profile won't let the server start
+#[derive(Debug, Clone, PartialEq)]
+pub struct Refusal {
+    pub profile: SecurityProfile,
+    pub problems: Vec<String>,
+    /// Whether `--i-know-what-im-doing` would let the server start anyway
+    pub overridable: bool,
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the {} security profile does not allow {}; fix the configuration",
+            self.profile.name(),
+            self.problems.join(", ")
+        )?;
+        if self.overridable {
+            write!(f, " or start with --i-know-what-im-doing")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Refusal {}
+
+/// Check `config` against its security profile
+pub fn enforce(config: &RemoteConfig) -> Result<Hardening, Refusal> {
+    let profile = config.security_profile;
+    let strict = profile == SecurityProfile::Strict;
+    let authenticated = config.token_provider.is_some() || config.bearer_token.is_some();
+    let ip = config.listen_addr.ip();
+    let controller_policy = config
+        .controller_policy
+        .unwrap_or_else(|| profile.controller_policy());
+    let audit_log = config.audit_log.unwrap_or_else(|| profile.audit_log());
+
+    // No profile serves without the authentication the operator asked for
+    if let Some(error) = &config.auth_error {
+        return Err(Refusal {
+            profile,
+            problems: vec![error.clone()],
+            overridable: false,
+        });
+    }
+
+    // Each problem, and whether the profile refuses it
+    let mut problems = vec![];
+    if !authenticated && !ip.is_loopback() {
+        problems.push((
+            profile != SecurityProfile::Open,
+            format!(
+                "serving {} to the network without authentication (set ZELLIJ_REMOTE_TOKEN)",
+                ip
+            ),
+        ));
+    } else if !authenticated {
+        problems.push((
+            strict,
+            "serving without authentication, so any local user can connect (set ZELLIJ_REMOTE_TOKEN)"
+                .to_string(),
+        ));
+    }
+    if strict && !ip.is_loopback() {
+        problems.push((
+            true,
+            format!(
+                "listening on {}, which isn't a loopback address and can't require client certificates",
+                ip
+            ),
+        ));
+    }
+    if strict && controller_policy != ControllerPolicy::ExplicitOnly {
+        problems.push((
+            true,
+            "letting clients take control without asking (ZELLIJ_REMOTE_CONTROLLER_POLICY)"
+                .to_string(),
+        ));
+    }
+    if strict && !audit_log {
+        problems.push((
+            true,
+            "turning the audit log off (ZELLIJ_REMOTE_AUDIT)".to_string(),
+        ));
+    }
+
+    let mut refused = vec![];
+    let mut warnings = vec![];
+    for (refuse, problem) in problems {
+        if refuse {
+            refused.push(problem);
+        } else {
+            warnings.push(problem);
+        }
+    }
+    if !refused.is_empty() {
+        if !config.i_know_what_im_doing {
+            return Err(Refusal {
+                profile,
+                problems: refused,
+                overridable: true,
+            });
+        }
+        warnings.extend(refused.into_iter().map(|problem| {
+            format!(
+                "{} (refused by the {} security profile, allowed by --i-know-what-im-doing)",
+                problem,
+                profile.name()
+            )
+        }));
+    }
+    Ok(Hardening {
+        controller_policy,
+        audit_log,
+        warnings,
+    })
+}
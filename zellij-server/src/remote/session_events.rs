@@ -0,0 +1,63 @@
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use zellij_utils::data::{
+    KeyWithModifier, RemoteApprovalRequest, RemoteClientInfo, RemoteFrameStage, RemoteInputLatency,
+};
+use zellij_utils::pane_size::Size;
+
+use crate::panes::terminal_answers::TerminalAnswers;
+use crate::panes::PaneId;
+use crate::ClientId;
+
+/// Everything the remote subsystem asks of the rest of the server.
+///
+/// The server implements it with [`super::ScreenEvents`]; tests can drive the remote
+/// thread with a recording implementation instead of a screen thread.
+pub trait OutboundSessionEvents: Send + Sync {
+    /// Write a controller's keystroke or text to the pane `client_id` has focused.
+    fn write_to_pane(
+        &self,
+        client_id: ClientId,
+        key_with_modifier: Option<KeyWithModifier>,
+        bytes: Vec<u8>,
+        is_kitty_keyboard_protocol: bool,
+    ) -> Result<()>;
+
+    /// Focus `pane_id` for `client_id`, as a controller asked with `FocusPane`.
+    fn focus_pane(&self, client_id: ClientId, pane_id: PaneId) -> Result<()>;
+
+    /// Resize the session to `size`.
+    ///
+    /// Not sent yet: a controller's display size is only a viewport hint for now.
+    fn resize(&self, size: Size) -> Result<()>;
+
+    /// Remote clients attached, detached or the controller changed.
+    fn remote_clients_changed(&self, clients: Vec<RemoteClientInfo>) -> Result<()>;
+
+    /// Panes should answer terminal queries for the controller's terminal from now on,
+    /// or for the local one again (`None`).
+    fn terminal_answers_changed(&self, answers: Option<TerminalAnswers>) -> Result<()>;
+
+    /// How long each stage of getting frames to remote clients takes, for the session
+    /// info `zellij remote status --verbose` reads.
+    fn frame_stages_changed(&self, stages: Vec<RemoteFrameStage>) -> Result<()>;
+
+    /// Input latency percentiles of each connected client, for the same session info.
+    fn input_latency_changed(&self, latency: Vec<RemoteInputLatency>) -> Result<()>;
+
+    /// The listener started on `listen_addr`, or stopped (`None`).
+    fn serving_changed(&self, listen_addr: Option<SocketAddr>) -> Result<()>;
+
+    /// A remote client asked for something the local user has to approve.
+    fn approval_requested(&self, request: RemoteApprovalRequest) -> Result<()>;
+
+    /// The request was answered, timed out or its client went away.
+    fn approval_resolved(&self, request_id: u64) -> Result<()>;
+
+    /// Ask for a [`RemoteInstruction::ScreenHeartbeat`] back, showing the session
+    /// still handles instructions while it has nothing to render.
+    ///
+    /// [`RemoteInstruction::ScreenHeartbeat`]: super::RemoteInstruction::ScreenHeartbeat
+    fn heartbeat(&self) -> Result<()>;
+}
@@ -0,0 +1,118 @@
+//! Finding the session a `SwitchSession` names.
+//!
+//! Every zellij session is its own server process with its own remote listener, so a
+//! client can't be moved between them on one connection. Instead the session it asks
+//! for is looked up the way the session manager finds other sessions (a socket in the
+//! socket dir, and the session info it caches) and the client is redirected to the
+//! address that session publishes.
+
+use std::fs;
+use std::net::IpAddr;
+
+use zellij_remote_core::failover::parse_endpoint;
+use zellij_remote_protocol::Endpoint;
+use zellij_utils::consts::{session_info_cache_file_name, ZELLIJ_SOCK_DIR};
+use zellij_utils::data::SessionInfo;
+
+/// Why a session can't be switched to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SwitchError {
+    InvalidName,
+    CurrentSession,
+    NotRunning,
+    NotServingRemote,
+}
+
+impl std::fmt::Display for SwitchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SwitchError::InvalidName => write!(f, "invalid session name"),
+            SwitchError::CurrentSession => write!(f, "already attached to this session"),
+            SwitchError::NotRunning => write!(f, "no running session by that name"),
+            SwitchError::NotServingRemote => {
+                write!(f, "session doesn't serve remote clients")
+            },
+        }
+    }
+}
+
+/// Where the running session `name` takes remote clients. Reads the filesystem, so
+/// call it off the runtime's workers.
+pub fn session_endpoint(name: &str, current_session: &str) -> Result<Endpoint, SwitchError> {
+    if !valid_session_name(name) {
+        return Err(SwitchError::InvalidName);
+    }
+    if name == current_session {
+        return Err(SwitchError::CurrentSession);
+    }
+    if !ZELLIJ_SOCK_DIR.join(name).exists() {
+        return Err(SwitchError::NotRunning);
+    }
+    let raw_session_info = fs::read_to_string(session_info_cache_file_name(name))
+        .map_err(|_| SwitchError::NotRunning)?;
+    let session_info = SessionInfo::from_string(&raw_session_info, current_session)
+        .map_err(|_| SwitchError::NotRunning)?;
+    session_info
+        .remote_listen_address
+        .as_deref()
+        .and_then(redirect_endpoint)
+        .ok_or(SwitchError::NotServingRemote)
+}
+
+/// Session names become file names, so anything that could leave the socket dir is
+/// refused before touching the filesystem
+fn valid_session_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\', '\0'])
+}
+
+/// The endpoint to send a client for a published listen address. A wildcard address
+/// says nothing about how the client reaches this machine, so the host is left empty
+/// for it to keep the one it connected to.
+fn redirect_endpoint(listen_address: &str) -> Option<Endpoint> {
+    let mut endpoint = parse_endpoint(listen_address)?;
+    if endpoint
+        .host
+        .parse::<IpAddr>()
+        .is_ok_and(|ip| ip.is_unspecified())
+    {
+        endpoint.host.clear();
+    }
+    Some(endpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_that_leave_the_socket_dir_refused() {
+        assert!(valid_session_name("work"));
+        assert!(valid_session_name("my-session.2"));
+        for name in ["", ".", "..", "../work", "a/b", ".hidden"] {
+            assert_eq!(
+                session_endpoint(name, "current"),
+                Err(SwitchError::InvalidName),
+                "{:?}",
+                name
+            );
+        }
+        assert_eq!(
+            session_endpoint("current", "current"),
+            Err(SwitchError::CurrentSession)
+        );
+    }
+
+    #[test]
+    fn test_wildcard_listen_address_keeps_the_clients_host() {
+        let endpoint = redirect_endpoint("0.0.0.0:4434").unwrap();
+        assert_eq!(endpoint.host, "");
+        assert_eq!(endpoint.port, 4434);
+        assert_eq!(redirect_endpoint("[::]:4434").unwrap().host, "");
+        assert_eq!(
+            redirect_endpoint("127.0.0.1:4434").unwrap().host,
+            "127.0.0.1"
+        );
+        assert_eq!(redirect_endpoint("[::1]:4434").unwrap().host, "::1");
+        assert!(redirect_endpoint("not an address").is_none());
+    }
+}
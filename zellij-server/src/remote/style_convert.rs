@@ -1,7 +1,9 @@
 //! Style conversion utilities for the remote protocol.
 //!
 //! This module provides functions to convert Zellij's character styles
-//! to the remote protocol's Style format.
+//! to the remote protocol's Style format. The Zellij-independent half
+//! (protocol colors and SGR rendering) lives in `zellij_remote_core::style_convert`
+//! so clients can share it.
 
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -10,10 +12,11 @@ use crate::panes::terminal_character::NamedColor;
 use crate::panes::terminal_character::{
     AnsiCode, AnsiStyledUnderline, CharacterStyles, RcCharacterStyles, TerminalCharacter,
 };
+use zellij_remote_core::style_convert::{ansi256_color, default_color, rgb_color};
 use zellij_remote_core::{Cell, StyleTable};
-use zellij_remote_protocol::{color, Color, DefaultColor, Rgb, Style, UnderlineStyle};
+use zellij_remote_protocol::{Color, Style, UnderlineStyle};
 
-fn named_color_to_ansi256(color: NamedColor) -> u32 {
+pub(crate) fn named_color_to_ansi256(color: NamedColor) -> u32 {
     match color {
         NamedColor::Black => 0,
         NamedColor::Red => 1,
@@ -34,30 +37,18 @@ fn named_color_to_ansi256(color: NamedColor) -> u32 {
     }
 }
 
-fn ansi_code_to_color(code: &Option<AnsiCode>) -> Option<Color> {
+pub(crate) fn ansi_code_to_color(code: &Option<AnsiCode>) -> Option<Color> {
     match code {
         None => None,
-        Some(AnsiCode::Reset) => Some(Color {
-            value: Some(color::Value::DefaultColor(DefaultColor {})),
-        }),
-        Some(AnsiCode::NamedColor(named)) => Some(Color {
-            value: Some(color::Value::Ansi256(named_color_to_ansi256(*named))),
-        }),
-        Some(AnsiCode::RgbCode((r, g, b))) => Some(Color {
-            value: Some(color::Value::Rgb(Rgb {
-                r: *r as u32,
-                g: *g as u32,
-                b: *b as u32,
-            })),
-        }),
-        Some(AnsiCode::ColorIndex(idx)) => Some(Color {
-            value: Some(color::Value::Ansi256(*idx as u32)),
-        }),
+        Some(AnsiCode::Reset) => Some(default_color()),
+        Some(AnsiCode::NamedColor(named)) => Some(ansi256_color(named_color_to_ansi256(*named))),
+        Some(AnsiCode::RgbCode((r, g, b))) => Some(rgb_color(*r, *g, *b)),
+        Some(AnsiCode::ColorIndex(idx)) => Some(ansi256_color(*idx as u32)),
         Some(AnsiCode::On | AnsiCode::Underline(_)) => None,
     }
 }
 
-fn ansi_code_to_underline_style(code: &AnsiCode) -> UnderlineStyle {
+pub(crate) fn ansi_code_to_underline_style(code: &AnsiCode) -> UnderlineStyle {
     match code {
         AnsiCode::On => UnderlineStyle::Single,
         AnsiCode::Underline(Some(styled)) => match styled {
@@ -126,12 +117,11 @@ pub fn character_styles_to_style(styles: &CharacterStyles) -> Style {
 }
 
 /// Cache style IDs by RcCharacterStyles pointer to avoid re-encoding
-#[allow(dead_code)]
 pub fn get_cached_style_id(
     styles: &RcCharacterStyles,
     style_table: &mut StyleTable,
-    cache: &mut HashMap<usize, u16>,
-) -> u16 {
+    cache: &mut HashMap<usize, u32>,
+) -> u32 {
     let ptr = match styles {
         RcCharacterStyles::Reset => 0,
         RcCharacterStyles::Rc(rc) => Rc::as_ptr(rc) as usize,
@@ -148,7 +138,6 @@ pub fn get_cached_style_id(
 }
 
 /// Convert a TerminalCharacter to a Cell for the remote protocol
-#[allow(dead_code)]
 pub fn terminal_character_to_cell(tc: &TerminalCharacter, style_table: &mut StyleTable) -> Cell {
     character_styles_to_cell(tc.character, tc.width(), &tc.styles, style_table)
 }
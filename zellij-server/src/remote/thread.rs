@@ -1,29 +1,63 @@
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, OnceLock};
 
 use anyhow::{Context, Result};
 use bytes::BytesMut;
 use prost::Message;
-use subtle::ConstantTimeEq;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, watch, RwLock};
+use tokio_util::codec::Decoder;
 use wtransport::{Endpoint, Identity, ServerConfig};
-use zellij_remote_bridge::{decode_datagram_envelope, encode_datagram_envelope, encode_envelope};
-use zellij_remote_core::{FrameStore, LeaseResult, RenderUpdate};
+use zellij_remote_bridge::{
+    decode_datagram_envelope, encode_datagram_envelope, encode_envelope,
+    encode_envelope_compressed, encode_envelope_compressed_into, negotiate_compression, send_push,
+    AuditEvent, AuditLog, EncodeBuffer, EnvelopeSequenceChecker, EnvelopeSequencer,
+    ExtensionRegistry, FrameRateCaps, SequenceGap, ZrpCodec,
+};
+use zellij_remote_core::text_mode::TEXT_MODE_FRAME_INTERVAL_MS;
+use zellij_remote_core::{
+    chunk_delta, chunk_snapshot, persist_session, restore_session, BellCoalescer, FrameStore,
+    HandoffOutcome, HandoffRequestResult, InputError, LeaseEvent, LeaseResult, LeaseReturn,
+    RenderJob, RenderOutput, RenderUpdate, ReorderWindow, Row, SessionEvent, SizeError, SizeLimits,
+    StyleTable, TokenHash, TokenProvider, WindowBounds,
+};
 use zellij_remote_protocol::{
-    datagram_envelope, protocol_error, stream_envelope, Capabilities, ClientHello, ControllerLease,
-    DatagramEnvelope, DenyControl, DisplaySize, GrantControl, ProtocolError, ProtocolVersion,
-    ServerHello, SessionState, StreamEnvelope,
+    datagram_envelope, protocol_error, request_snapshot, stream_envelope, AttachRequest,
+    AttachResponse, BellEvent, BellUrgency, Capabilities, CapabilityUpdate, ClientHello,
+    ClientRole, CompressionConfig, ControlHandoffRequest, ControlHandoffResponse, ControllerLease,
+    ControllerPolicy, DatagramEnvelope, DenyControl, Detach, DisplaySize, FocusPane, GrantControl,
+    InputBatch, InputEvent, KeybindingInfo, LeaseRevoked, LeaseStatus, LineSize, LinkStats,
+    PaneLayout, Pong, ProtocolError, ProtocolVersion, PushHeader, PushKind, RenderMode,
+    RenderModeChanged, RowRange, ServerHello, SessionMetadata, SessionRedirect, SessionState,
+    SessionStateChanged, StreamEnvelope, StreamPaused, SwitchSession, Takeover,
 };
-use zellij_utils::channels::{Receiver, SenderWithContext};
+use zellij_utils::channels::Receiver;
+use zellij_utils::data::{RemoteApprovalRequest, RemoteFrameStage, RemoteInputLatency};
 use zellij_utils::errors::ErrorContext;
 use zellij_utils::pane_size::Size;
 
+use super::approvals::PendingAction;
+use super::control_throttle::{ControlRequestDecision, ControlRequestThrottle};
+use super::frame_stages::{FrameStage, StageRecorder, STAGE_REPORT_INTERVAL};
+use super::health::{SessionHealth, DEGRADED_RETRY_AFTER_MS, SCREEN_STALL_TIMEOUT_MS};
 use super::input_translate::translate_input;
-use super::instruction::RemoteInstruction;
+use super::instruction::{RemoteInstruction, REMOTE_CLIENT_ID};
+use super::keepalive::{KeepAlive, KeepAliveAction};
+use super::lanes::{open_lanes, LaneSender, LaneStreams};
+use super::locale::server_locale;
 use super::manager::RemoteManager;
-use crate::screen::ScreenInstruction;
+use super::mouse::{MouseMoveCoalescer, MOUSE_MOVE_COALESCE_MS};
+use super::pane_layout::focus_target;
+use super::persist::{self, PERSIST_FILE_NAME, PERSIST_INTERVAL_MS, PERSIST_MAX_AGE_MS};
+use super::presence::RemotePresence;
+use super::query_answers::ControllerAnswers;
+use super::runtime_probe::{RuntimeProbe, DEFAULT_WORKER_THREADS};
+use super::security_profile::{self, Hardening, SecurityProfile};
+use super::session_events::OutboundSessionEvents;
+use super::session_switch::{session_endpoint, SwitchError};
+use super::write_watchdog::{WriteTimer, WRITER_STALL_TIMEOUT_MS};
 use crate::ClientId;
 
 static REMOTE_CLIENT_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
@@ -90,14 +124,87 @@ impl TestKnobs {
 
 const MAX_FRAME_SIZE: usize = 1_048_576; // 1 MB
 const CLIENT_CHANNEL_SIZE: usize = 4;
+/// Fewest deltas in one frame worth spreading over the blocking pool
+const PARALLEL_RENDER_MIN_DELTAS: usize = 2;
+/// Fewest rows to diff in one frame worth moving off the runtime workers
+const BLOCKING_RENDER_MIN_ROWS: usize = 16;
+const LEASE_TICK_INTERVAL_MS: u64 = 250;
+const HEALTH_CHECK_INTERVAL_MS: u64 = 1_000;
+const KEEPALIVE_TICK_INTERVAL_MS: u64 = 1_000;
+const LEASE_STATUS_INTERVAL_MS: u64 = 1_000;
+/// How long closing clients get to receive their last messages
+const CLOSE_GRACE_MS: u64 = 500;
+/// Most latency a plugin can inject on a client
+const MAX_INJECTED_LATENCY_MS: u64 = 10_000;
+/// Shown by clients over their last frame while the local user has streaming paused
+const STREAM_PAUSED_PLACEHOLDER: &str = "Streaming paused by the host";
 
 /// Configuration for the remote server
+#[derive(Clone)]
 pub struct RemoteConfig {
     pub listen_addr: SocketAddr,
     pub session_name: String,
     pub initial_size: Size,
-    pub to_screen: SenderWithContext<ScreenInstruction>,
-    pub bearer_token: Option<Vec<u8>>,
+    /// Where input and presence changes go; the screen thread in a real server
+    pub events: Arc<dyn OutboundSessionEvents>,
+    /// Salted hash of the expected bearer token (the plaintext is never retained)
+    pub bearer_token: Option<TokenHash>,
+    /// Hook for fetching the expected token from a secret manager; takes precedence
+    /// over `bearer_token` when set
+    pub token_provider: Option<Arc<dyn TokenProvider>>,
+    /// Salted hash of a token that only allows one-shot captures
+    /// (`CLIENT_ROLE_SNAPSHOT`); ignored while the session itself is unauthenticated
+    pub snapshot_token: Option<TokenHash>,
+    /// Demote an idle controller to viewer after this many ms without input
+    pub controller_idle_timeout_ms: Option<u64>,
+    /// Ping clients after this many ms without frames, closing those that stop
+    /// answering; None turns keep-alive off
+    pub keepalive_interval_ms: Option<u64>,
+    /// Other addresses clients can reach this server on, advertised for failover
+    pub alternate_endpoints: Vec<zellij_remote_protocol::Endpoint>,
+    /// Most frames per second sent to the controller and to viewers
+    pub frame_rate_caps: FrameRateCaps,
+    /// Ask the local user before letting a client force a takeover, denying it after
+    /// this many ms; None lets takeovers through unprompted
+    pub approval_timeout_ms: Option<u64>,
+    /// Directory to save the session's resume state in, so resume tokens survive a
+    /// restart; None keeps it in memory only
+    pub persist_dir: Option<PathBuf>,
+    /// Mark the session degraded after the screen thread goes this many ms without
+    /// answering input or a heartbeat; None uses [`SCREEN_STALL_TIMEOUT_MS`]
+    pub screen_stall_timeout_ms: Option<u64>,
+    /// Terminal sizes clients may ask for; larger ones are rejected, smaller raised
+    pub size_limits: SizeLimits,
+    /// How far ahead of the controller's next input seq an input may arrive and still
+    /// be kept until the ones before it do
+    pub input_reorder_window: ReorderWindow,
+    /// Close a client once a write to it has waited this many ms for the peer to
+    /// read; None uses [`WRITER_STALL_TIMEOUT_MS`]
+    pub writer_stall_timeout_ms: Option<u64>,
+    /// Handlers for the handshake extensions clients may offer; none are accepted
+    /// by default
+    pub extensions: ExtensionRegistry,
+    /// Send deltas that encode larger than this as snapshots; None only replaces
+    /// deltas larger than the snapshot
+    pub max_delta_bytes: Option<usize>,
+    /// Worker threads for the remote thread's runtime; None uses
+    /// [`DEFAULT_WORKER_THREADS`]. Read once when the thread starts, so reconfiguring
+    /// doesn't change it
+    pub worker_threads: Option<usize>,
+    /// Which combinations of the settings above the server refuses to start with
+    pub security_profile: SecurityProfile,
+    /// Whether a new client may take control without asking; None uses the
+    /// profile's (explicit-only for strict, otherwise last-writer-wins)
+    pub controller_policy: Option<ControllerPolicy>,
+    /// Report clients authenticating, failing to and disconnecting to the audit log;
+    /// None uses the profile's (on for strict only)
+    pub audit_log: Option<bool>,
+    /// Start even when the security profile refuses the configuration
+    pub i_know_what_im_doing: bool,
+    /// Why authentication that was asked for couldn't be set up, e.g. a malformed
+    /// `ZELLIJ_REMOTE_TOKEN_HASH`. The server won't start while it is set, whatever the
+    /// profile, rather than serve without authentication
+    pub auth_error: Option<String>,
 }
 
 impl std::fmt::Debug for RemoteConfig {
@@ -110,13 +217,42 @@ impl std::fmt::Debug for RemoteConfig {
                 "bearer_token",
                 &self.bearer_token.as_ref().map(|_| "[REDACTED]"),
             )
+            .field(
+                "token_provider",
+                &self.token_provider.as_ref().map(|_| "[PROVIDER]"),
+            )
+            .field(
+                "snapshot_token",
+                &self.snapshot_token.as_ref().map(|_| "[REDACTED]"),
+            )
+            .field(
+                "controller_idle_timeout_ms",
+                &self.controller_idle_timeout_ms,
+            )
+            .field("keepalive_interval_ms", &self.keepalive_interval_ms)
+            .field("alternate_endpoints", &self.alternate_endpoints)
+            .field("frame_rate_caps", &self.frame_rate_caps)
+            .field("approval_timeout_ms", &self.approval_timeout_ms)
+            .field("persist_dir", &self.persist_dir)
+            .field("screen_stall_timeout_ms", &self.screen_stall_timeout_ms)
+            .field("size_limits", &self.size_limits)
+            .field("input_reorder_window", &self.input_reorder_window)
+            .field("writer_stall_timeout_ms", &self.writer_stall_timeout_ms)
+            .field("extensions", &self.extensions)
+            .field("max_delta_bytes", &self.max_delta_bytes)
+            .field("worker_threads", &self.worker_threads)
+            .field("security_profile", &self.security_profile)
+            .field("controller_policy", &self.controller_policy)
+            .field("audit_log", &self.audit_log)
+            .field("i_know_what_im_doing", &self.i_know_what_im_doing)
+            .field("auth_error", &self.auth_error)
             .finish()
     }
 }
 
 /// Per-client WebTransport connection state (M1: uses channel instead of raw stream)
 struct ClientConnection {
-    sender: mpsc::Sender<StreamEnvelope>,
+    sender: LaneSender,
     #[allow(dead_code)]
     remote_id: u64,
     /// Handle to the connection for sending datagrams
@@ -125,21 +261,87 @@ struct ClientConnection {
     max_datagram_size: Option<usize>,
     /// Whether datagrams are negotiated (transport AND client advertised AND server accepted)
     datagrams_negotiated: bool,
-    /// Handle to abort the datagram receive task on disconnect
+    /// Handle to abort the datagram receive task on disconnect; also set when
+    /// datagrams were negotiated at connect and later turned off
     datagram_task_handle: Option<tokio::task::JoinHandle<()>>,
+    /// What the sender tasks compress with
+    compression: watch::Sender<CompressionConfig>,
+    /// Whether the client was last told it is in text mode
+    text_mode: bool,
+    /// None when keep-alive is off
+    keepalive: Option<KeepAlive>,
+    /// Metadata the client was last sent
+    metadata_sent: Option<SessionMetadata>,
+    /// The client wants `PaneLayout` updates
+    pane_layout: bool,
+    /// Pane layout the client was last sent
+    pane_layout_sent: Option<PaneLayout>,
+    /// The client wants `KeybindingInfo` updates
+    keybindings: bool,
+    /// Keybindings the client was last sent
+    keybindings_sent: Option<KeybindingInfo>,
+    /// The client wants a `BellEvent` when a pane rings the bell
+    bells: bool,
+    /// The client wants periodic `LeaseStatus` updates
+    lease_status: bool,
+    /// Mouse moves from the client waiting to be routed
+    mouse_moves: MouseMoveCoalescer,
+    /// As shown in the local UI, for telling others who took control
+    client_name: String,
+    /// Render messages bigger than this are split into chunks; None when unlimited
+    max_message_bytes: Option<usize>,
+    /// Writes queued messages to the client's streams until `sender` is dropped
+    sender_task_handle: tokio::task::JoinHandle<()>,
+    /// One per sender task, for the stalled writer watchdog
+    write_timers: Vec<WriteTimer>,
 }
 
 /// Shared state between the main loop and connection handlers
 struct SharedState {
     manager: RemoteManager,
-    #[allow(dead_code)]
     current_frame: Option<FrameStore>,
     session_name: String,
-    to_screen: SenderWithContext<ScreenInstruction>,
+    events: Arc<dyn OutboundSessionEvents>,
     active_zellij_client: Option<ClientId>,
     frame_count: u32,
     delta_count: u32,
     dropped_delta_count: u32,
+    /// Frames skipped because nothing on screen changed
+    idle_frame_count: u32,
+    health: SessionHealth,
+    control_throttle: ControlRequestThrottle,
+    presence: RemotePresence,
+    /// What each client's terminal answers queries with, for panes to use while it's in control
+    terminal_answers: ControllerAnswers,
+    keepalive_interval_ms: Option<u64>,
+    alternate_endpoints: Vec<zellij_remote_protocol::Endpoint>,
+    metadata: SessionMetadata,
+    /// Panes of the tab remote clients see, as last reported by the screen
+    pane_layout: PaneLayout,
+    /// The session's keybindings, as last reported by the screen
+    keybindings: KeybindingInfo,
+    bells: BellCoalescer,
+    /// Zero point of the millisecond clock `bells` runs on
+    started_at: std::time::Instant,
+    /// When `LeaseStatus` last went out, on the `started_at` clock
+    lease_status_sent_ms: Option<u64>,
+    size_limits: SizeLimits,
+    /// How long a write may wait for a client to read before it is closed
+    writer_stall_timeout: std::time::Duration,
+    /// Clients closed because they stopped reading
+    stalled_writer_disconnects: u32,
+    /// Envelopes from clients whose `envelope_seq` wasn't the one after the last
+    envelope_seq_gaps: u32,
+    runtime_probe: RuntimeProbe,
+    /// How long each stage of getting a frame out takes, shared with the sender tasks
+    frame_stages: StageRecorder,
+    /// The stage timings last passed on to the session
+    frame_stages_reported: Vec<RemoteFrameStage>,
+    /// The client input latencies last passed on to the session
+    input_latency_reported: Vec<RemoteInputLatency>,
+    extensions: ExtensionRegistry,
+    /// Where clients authenticating and leaving are reported, when the audit log is on
+    audit: Option<AuditLog>,
 }
 
 /// Message from connection handlers to the main loop
@@ -147,21 +349,53 @@ enum ConnectionEvent {
     ClientConnected {
         remote_id: u64,
         send: wtransport::SendStream,
+        /// Render lane streams, if the client negotiated priority lanes
+        lanes: Option<LaneStreams>,
+        /// Whether snapshots go out on push streams
+        push_streams: bool,
+        compression: CompressionConfig,
         connection: wtransport::Connection,
         client_supports_datagrams: bool,
+        client_supports_bell: bool,
+        client_supports_lease_status: bool,
+        client_supports_pane_layout: bool,
+        client_supports_keybinding_info: bool,
+        /// Negotiated `max_message_bytes`, 0 when unlimited
+        max_message_bytes: u32,
         conn_event_tx: mpsc::Sender<ConnectionEvent>,
     },
     ClientDisconnected {
         remote_id: u64,
     },
+    /// A write to the client waited past the stall timeout for the peer to read
+    WriterStalled {
+        remote_id: u64,
+    },
+    /// The client announced it is leaving; its stream closes next
+    Detached {
+        remote_id: u64,
+        detach: Detach,
+    },
     InputReceived {
         remote_id: u64,
         input: zellij_remote_protocol::InputEvent,
     },
+    InputBatchReceived {
+        remote_id: u64,
+        batch: InputBatch,
+    },
     RequestControl {
         remote_id: u64,
         request: zellij_remote_protocol::RequestControl,
     },
+    ControlHandoffRequested {
+        remote_id: u64,
+        request: ControlHandoffRequest,
+    },
+    ControlHandoffAnswered {
+        remote_id: u64,
+        response: ControlHandoffResponse,
+    },
     RequestSnapshot {
         remote_id: u64,
         request: zellij_remote_protocol::RequestSnapshot,
@@ -174,6 +408,38 @@ enum ConnectionEvent {
         remote_id: u64,
         request: zellij_remote_protocol::SetControllerSize,
     },
+    FocusPaneRequested {
+        remote_id: u64,
+        request: FocusPane,
+    },
+    PongReceived {
+        remote_id: u64,
+        pong: Pong,
+    },
+    LinkStatsReceived {
+        remote_id: u64,
+        stats: LinkStats,
+    },
+    VisibilityChanged {
+        remote_id: u64,
+        visible: bool,
+    },
+    RegionSubscribed {
+        remote_id: u64,
+        rows: Option<RowRange>,
+    },
+    CapabilitiesUpdated {
+        remote_id: u64,
+        update: CapabilityUpdate,
+    },
+    AttachRequested {
+        remote_id: u64,
+        request: AttachRequest,
+    },
+    SwitchSessionRequested {
+        remote_id: u64,
+        request: SwitchSession,
+    },
 }
 
 /// Main entry point for the remote thread
@@ -188,7 +454,12 @@ pub fn remote_thread_main(
     );
 
     let rt = tokio::runtime::Builder::new_multi_thread()
-        .worker_threads(2)
+        .worker_threads(
+            config
+                .worker_threads
+                .unwrap_or(DEFAULT_WORKER_THREADS)
+                .max(1),
+        )
         .enable_all()
         .thread_name("remote-tokio")
         .build()
@@ -201,89 +472,370 @@ async fn run_remote_server(
     receiver: Receiver<(RemoteInstruction, ErrorContext)>,
     config: RemoteConfig,
 ) -> Result<()> {
-    let bearer_token = config.bearer_token.clone();
+    TestKnobs::get().log_active_knobs();
+
+    // M3: Spawn a dedicated task for blocking recv instead of spawning per-receive
+    let (instruction_tx, mut instruction_rx) = mpsc::channel::<RemoteInstruction>(64);
+    tokio::task::spawn_blocking({
+        let receiver = receiver.clone();
+        move || loop {
+            match receiver.recv() {
+                Ok((instruction, _err_ctx)) => {
+                    if instruction_tx.blocking_send(instruction).is_err() {
+                        break;
+                    }
+                },
+                Err(_) => {
+                    break;
+                },
+            }
+        }
+    });
+
+    let mut config = config;
+    let mut carry_over = SessionCarryOver::default();
+    loop {
+        let exit = match security_profile::enforce(&config) {
+            Ok(hardening) => match bind_endpoint(&config) {
+                Ok(server) => {
+                    report_serving(&config, Some(config.listen_addr));
+                    serve(
+                        server,
+                        &mut instruction_rx,
+                        &config,
+                        &hardening,
+                        &mut carry_over,
+                    )
+                    .await?
+                },
+                Err(e) => {
+                    log::error!(
+                        "Remote server failed to listen on {}: {:#}",
+                        config.listen_addr,
+                        e
+                    );
+                    ListenerExit::Stop
+                },
+            },
+            Err(refusal) => {
+                log::error!("Remote server refused to start: {}", refusal);
+                ListenerExit::Stop
+            },
+        };
+        match exit {
+            ListenerExit::Shutdown => break,
+            ListenerExit::Reconfigure(new_config) => {
+                log::info!("Remote server reconfigured, restarting");
+                config = *new_config;
+            },
+            ListenerExit::Stop => {
+                log::info!("Remote server stopped");
+                report_serving(&config, None);
+                if !wait_while_stopped(&mut instruction_rx, &mut config, &mut carry_over).await {
+                    break;
+                }
+                log::info!("Remote server starting again");
+            },
+        }
+    }
+
+    log::info!("Remote thread shutting down");
+    Ok(())
+}
+
+fn report_serving(config: &RemoteConfig, listen_addr: Option<SocketAddr>) {
+    if let Err(e) = config.events.serving_changed(listen_addr) {
+        log::warn!("Failed to report remote serving state to screen: {}", e);
+    }
+}
+
+/// Why a listener stopped serving
+enum ListenerExit {
+    Shutdown,
+    Stop,
+    Reconfigure(Box<RemoteConfig>),
+}
+
+/// What the screen and pty last reported, kept across listener restarts so a rebuilt
+/// listener can serve the current screen before the next render.
+#[derive(Default)]
+struct SessionCarryOver {
+    active_zellij_client: Option<ClientId>,
+    last_frame: Option<(FrameStore, StyleTable)>,
+    /// `session_name` is taken from the config instead
+    metadata: SessionMetadata,
+    pane_layout: PaneLayout,
+    keybindings: KeybindingInfo,
+    streaming_paused: bool,
+}
+
+/// The zellij client remote input goes to once `connected` attached: the remote
+/// screen client while the screen has it, otherwise the latest local client.
+fn input_client(current: Option<ClientId>, connected: ClientId) -> Option<ClientId> {
+    if current == Some(REMOTE_CLIENT_ID) {
+        current
+    } else {
+        Some(connected)
+    }
+}
+
+/// What to do with an instruction that arrived while the listener is stopped
+#[derive(Debug)]
+enum StoppedAction {
+    Stay,
+    Start,
+    Shutdown,
+}
+
+impl SessionCarryOver {
+    /// Keep up with the session while stopped; a `Reconfigure` replaces `config`
+    /// without starting.
+    fn apply_while_stopped(
+        &mut self,
+        config: &mut RemoteConfig,
+        instruction: RemoteInstruction,
+    ) -> StoppedAction {
+        match instruction {
+            RemoteInstruction::FrameReady {
+                frame_store,
+                style_table,
+                ..
+            } => {
+                let style_table = style_table
+                    .or_else(|| self.last_frame.take().map(|(_, style_table)| style_table))
+                    .unwrap_or_default();
+                self.last_frame = Some((frame_store, style_table));
+            },
+            RemoteInstruction::ClientConnected { client_id, .. } => {
+                self.active_zellij_client = input_client(self.active_zellij_client, client_id);
+            },
+            RemoteInstruction::ClientDisconnected { client_id } => {
+                if self.active_zellij_client == Some(client_id) {
+                    self.active_zellij_client = None;
+                }
+            },
+            RemoteInstruction::FocusedPaneTitle { title } => self.metadata.pane_title = title,
+            RemoteInstruction::FocusedPaneProcess { cwd, command } => {
+                set_focused_pane_process(&mut self.metadata, cwd, command);
+            },
+            RemoteInstruction::PaneLayout(pane_layout) => self.pane_layout = pane_layout,
+            RemoteInstruction::Keybindings(keybindings) => self.keybindings = keybindings,
+            RemoteInstruction::ToggleStreamingPaused => {
+                self.streaming_paused = !self.streaming_paused;
+            },
+            RemoteInstruction::Reconfigure(new_config) => *config = new_config,
+            RemoteInstruction::Start => return StoppedAction::Start,
+            RemoteInstruction::Shutdown => return StoppedAction::Shutdown,
+            RemoteInstruction::ClientResize { .. }
+            | RemoteInstruction::RevokeLease
+            | RemoteInstruction::AnswerApproval { .. }
+            | RemoteInstruction::SetClientLatency { .. }
+            | RemoteInstruction::Bell { .. }
+            | RemoteInstruction::ScreenHeartbeat
+            | RemoteInstruction::Stop => {},
+        }
+        StoppedAction::Stay
+    }
+}
 
-    if bearer_token.is_none() {
-        log::warn!("Remote server running WITHOUT authentication - any client can connect!");
+/// Returns false if the thread should exit rather than start again.
+async fn wait_while_stopped(
+    instruction_rx: &mut mpsc::Receiver<RemoteInstruction>,
+    config: &mut RemoteConfig,
+    carry_over: &mut SessionCarryOver,
+) -> bool {
+    while let Some(instruction) = instruction_rx.recv().await {
+        match carry_over.apply_while_stopped(config, instruction) {
+            StoppedAction::Stay => {},
+            StoppedAction::Start => return true,
+            StoppedAction::Shutdown => return false,
+        }
     }
+    // Nothing can start us again once the screen's channel is gone
+    false
+}
+
+fn bind_endpoint(
+    config: &RemoteConfig,
+) -> Result<Endpoint<wtransport::endpoint::endpoint_side::Server>> {
+    let identity = Identity::self_signed(["localhost", "zellij-remote"])
+        .map_err(|e| anyhow::anyhow!("failed to create self-signed identity: {}", e))?;
+
+    let server_config = ServerConfig::builder()
+        .with_bind_address(config.listen_addr)
+        .with_identity(identity)
+        .build();
+
+    Ok(Endpoint::server(server_config)?)
+}
+
+/// Serve remote clients on `server`, as `hardening` allows, until told to stop, restart
+/// or shut down; clients are then closed with `CODE_SESSION_CLOSING`.
+async fn serve(
+    server: Endpoint<wtransport::endpoint::endpoint_side::Server>,
+    instruction_rx: &mut mpsc::Receiver<RemoteInstruction>,
+    config: &RemoteConfig,
+    hardening: &Hardening,
+    carry_over: &mut SessionCarryOver,
+) -> Result<ListenerExit> {
+    let token_provider: Option<Arc<dyn TokenProvider>> =
+        config.token_provider.clone().or_else(|| {
+            config
+                .bearer_token
+                .clone()
+                .map(|hash| Arc::new(hash) as Arc<dyn TokenProvider>)
+        });
 
-    let is_loopback = config.listen_addr.ip().is_loopback();
-    if !is_loopback && bearer_token.is_none() {
-        log::error!(
-            "CRITICAL SECURITY WARNING: Remote server binding to non-loopback address {} \
-             without authentication! This exposes your session to the network without any protection. \
-             Set ZELLIJ_REMOTE_TOKEN environment variable to enable authentication.",
-            config.listen_addr.ip()
+    for warning in &hardening.warnings {
+        log::warn!(
+            "Remote server security ({} profile): {}",
+            config.security_profile.name(),
+            warning
         );
     }
 
-    TestKnobs::get().log_active_knobs();
+    let persist_path = config
+        .persist_dir
+        .as_ref()
+        .map(|dir| dir.join(PERSIST_FILE_NAME));
+    let mut manager = new_manager(config, persist_path.as_deref(), carry_over);
+    manager
+        .session_mut()
+        .set_controller_policy(hardening.controller_policy);
+    manager
+        .session_mut()
+        .set_controller_idle_timeout(config.controller_idle_timeout_ms);
+    manager
+        .session_mut()
+        .set_input_reorder_window(config.input_reorder_window);
+    manager
+        .session_mut()
+        .set_max_delta_bytes(config.max_delta_bytes);
+    manager.set_streaming_paused(carry_over.streaming_paused);
+    manager.set_frame_rate_caps(config.frame_rate_caps);
+    manager.set_approval_timeout(
+        config
+            .approval_timeout_ms
+            .map(std::time::Duration::from_millis),
+    );
+
+    // Clients don't send KeepAliveLease yet; the controller's input renews its lease
+    // instead. Leases are only ticked when an idle timeout is configured, so a quiet
+    // controller keeps control otherwise. The tick still runs to time out unanswered
+    // handoff requests.
+    let lease_expiry_enabled = config.controller_idle_timeout_ms.is_some();
+    let mut lease_tick =
+        tokio::time::interval(tokio::time::Duration::from_millis(LEASE_TICK_INTERVAL_MS));
+    let mut health_check =
+        tokio::time::interval(tokio::time::Duration::from_millis(HEALTH_CHECK_INTERVAL_MS));
+    let mut text_mode_flush = tokio::time::interval(tokio::time::Duration::from_millis(
+        TEXT_MODE_FRAME_INTERVAL_MS,
+    ));
+    // Frames a rate cap held back go out once the cap allows, at the tightest cap's pace
+    let frame_cap_ms = [true, false]
+        .into_iter()
+        .map(|controller| config.frame_rate_caps.min_frame_interval_ms(controller))
+        .filter(|ms| *ms > 0)
+        .min();
+    let mut frame_cap_flush = tokio::time::interval(tokio::time::Duration::from_millis(
+        frame_cap_ms.unwrap_or(TEXT_MODE_FRAME_INTERVAL_MS),
+    ));
+    let keepalive_enabled = config.keepalive_interval_ms.is_some();
+    let mut keepalive_tick = tokio::time::interval(tokio::time::Duration::from_millis(
+        KEEPALIVE_TICK_INTERVAL_MS,
+    ));
+    let mut persist_tick =
+        tokio::time::interval(tokio::time::Duration::from_millis(PERSIST_INTERVAL_MS));
+    let mut stage_report = tokio::time::interval(STAGE_REPORT_INTERVAL);
+    // Only polled while a move is held back, so don't catch up on the ticks in between
+    let mut mouse_move_flush =
+        tokio::time::interval(tokio::time::Duration::from_millis(MOUSE_MOVE_COALESCE_MS));
+    mouse_move_flush.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut frame_channel_open = true;
 
     let shared_state = Arc::new(RwLock::new(SharedState {
-        manager: RemoteManager::new(config.initial_size.cols, config.initial_size.rows),
+        manager,
         current_frame: None,
         session_name: config.session_name.clone(),
-        to_screen: config.to_screen,
-        active_zellij_client: None,
+        events: config.events.clone(),
+        active_zellij_client: carry_over.active_zellij_client,
         frame_count: 0,
         delta_count: 0,
         dropped_delta_count: 0,
+        idle_frame_count: 0,
+        health: SessionHealth::new(std::time::Duration::from_millis(
+            config
+                .screen_stall_timeout_ms
+                .unwrap_or(SCREEN_STALL_TIMEOUT_MS),
+        )),
+        control_throttle: ControlRequestThrottle::default(),
+        presence: RemotePresence::default(),
+        terminal_answers: ControllerAnswers::default(),
+        keepalive_interval_ms: config.keepalive_interval_ms,
+        alternate_endpoints: config.alternate_endpoints.clone(),
+        metadata: SessionMetadata {
+            session_name: config.session_name.clone(),
+            server_locale: Some(server_locale()),
+            ..carry_over.metadata.clone()
+        },
+        pane_layout: carry_over.pane_layout.clone(),
+        keybindings: carry_over.keybindings.clone(),
+        bells: BellCoalescer::new(),
+        started_at: std::time::Instant::now(),
+        lease_status_sent_ms: None,
+        size_limits: config.size_limits,
+        writer_stall_timeout: std::time::Duration::from_millis(
+            config
+                .writer_stall_timeout_ms
+                .unwrap_or(WRITER_STALL_TIMEOUT_MS),
+        ),
+        stalled_writer_disconnects: 0,
+        envelope_seq_gaps: 0,
+        runtime_probe: RuntimeProbe::default(),
+        frame_stages: StageRecorder::default(),
+        frame_stages_reported: vec![],
+        input_latency_reported: vec![],
+        extensions: config.extensions.clone(),
+        audit: hardening.audit_log.then(AuditLog::new),
     }));
+    if let Some((frame_store, style_table)) = carry_over.last_frame.take() {
+        apply_frame(
+            &mut *shared_state.write().await,
+            frame_store,
+            Some(style_table),
+        );
+    }
 
     let (conn_event_tx, mut conn_event_rx) = mpsc::channel::<ConnectionEvent>(64);
     let mut clients: HashMap<u64, ClientConnection> = HashMap::new();
 
-    let identity = Identity::self_signed(["localhost", "zellij-remote"])
-        .map_err(|e| anyhow::anyhow!("failed to create self-signed identity: {}", e))?;
-
-    let server_config = ServerConfig::builder()
-        .with_bind_address(config.listen_addr)
-        .with_identity(identity)
-        .build();
-
-    let server = Endpoint::server(server_config)?;
-
     log::info!(
         "WebTransport server listening on {}{}",
         config.listen_addr,
-        if bearer_token.is_some() {
+        if token_provider.is_some() {
             " (authenticated)"
         } else {
             " (UNAUTHENTICATED)"
         }
     );
 
-    // M3: Spawn a dedicated task for blocking recv instead of spawning per-receive
-    let (instruction_tx, mut instruction_rx) = mpsc::channel::<RemoteInstruction>(64);
-    tokio::task::spawn_blocking({
-        let receiver = receiver.clone();
-        move || loop {
-            match receiver.recv() {
-                Ok((instruction, _err_ctx)) => {
-                    if instruction_tx.blocking_send(instruction).is_err() {
-                        break;
-                    }
-                },
-                Err(_) => {
-                    break;
-                },
-            }
-        }
-    });
-
-    loop {
+    let exit = loop {
         tokio::select! {
             biased;
 
-            Some(instruction) = instruction_rx.recv() => {
-                let should_exit = handle_instruction(
+            instruction = instruction_rx.recv(), if frame_channel_open => {
+                let Some(instruction) = instruction else {
+                    log::error!("Frame channel from screen thread closed, marking session degraded");
+                    frame_channel_open = false;
+                    shared_state.write().await.health.record_frame_channel_closed();
+                    continue;
+                };
+                if let Some(exit) = handle_instruction(
                     &shared_state,
                     &mut clients,
                     instruction,
-                ).await?;
-                if should_exit {
-                    log::info!("Remote thread received shutdown signal");
-                    break;
+                ).await? {
+                    break exit;
                 }
             }
 
@@ -294,10 +846,11 @@ async fn run_remote_server(
                 let connection = session_request.accept().await?;
                 let shared_state = shared_state.clone();
                 let conn_event_tx = conn_event_tx.clone();
-                let bearer_token = bearer_token.clone();
+                let token_provider = token_provider.clone();
+                let snapshot_token = config.snapshot_token.clone();
 
                 tokio::spawn(async move {
-                    if let Err(e) = handle_connection(connection, shared_state, conn_event_tx, bearer_token).await {
+                    if let Err(e) = handle_connection(connection, shared_state, conn_event_tx, token_provider, snapshot_token).await {
                         log::error!("Connection error: {}", e);
                     }
                 });
@@ -306,88 +859,263 @@ async fn run_remote_server(
             Some(event) = conn_event_rx.recv() => {
                 handle_connection_event(&shared_state, &mut clients, event).await?;
             }
+
+            _ = lease_tick.tick() => {
+                handle_lease_tick(&shared_state, &clients, lease_expiry_enabled).await;
+            }
+
+            _ = health_check.tick() => {
+                handle_health_check(&shared_state, &clients).await;
+                check_stalled_writers(&shared_state, &mut clients).await;
+                probe_runtime(&shared_state);
+            }
+
+            _ = text_mode_flush.tick() => {
+                handle_paced_frame_flush(&shared_state, &mut clients).await;
+            }
+
+            _ = frame_cap_flush.tick(), if frame_cap_ms.is_some() => {
+                handle_paced_frame_flush(&shared_state, &mut clients).await;
+            }
+
+            _ = keepalive_tick.tick(), if keepalive_enabled => {
+                handle_keepalive_tick(&shared_state, &mut clients).await;
+            }
+
+            _ = mouse_move_flush.tick(), if clients.values().any(|client| client.mouse_moves.has_held()) => {
+                handle_mouse_move_flush(&shared_state, &mut clients).await;
+            }
+
+            _ = stage_report.tick() => {
+                report_frame_stages(&shared_state).await;
+                report_input_latency(&mut *shared_state.write().await);
+            }
+
+            _ = persist_tick.tick(), if persist_path.is_some() => {
+                if let Some(path) = &persist_path {
+                    save_session(&*shared_state.read().await, path);
+                }
+            }
         }
+    };
+
+    let reason = match exit {
+        ListenerExit::Shutdown => "session is shutting down",
+        ListenerExit::Stop => "remote access was stopped",
+        ListenerExit::Reconfigure(_) => "remote access is restarting",
+    };
+    close_all_clients(&shared_state, &mut clients, reason).await;
+
+    let state = shared_state.read().await;
+    if let Some(path) = &persist_path {
+        save_session(&state, path);
     }
+    carry_over.active_zellij_client = state.active_zellij_client;
+    carry_over.last_frame = state
+        .current_frame
+        .clone()
+        .map(|frame_store| (frame_store, state.manager.style_table().clone()));
+    carry_over.metadata = state.metadata.clone();
+    carry_over.pane_layout = state.pane_layout.clone();
+    carry_over.keybindings = state.keybindings.clone();
+    carry_over.streaming_paused = state.manager.is_streaming_paused();
+    Ok(exit)
+}
 
-    log::info!("Remote thread shutting down");
-    Ok(())
+/// A manager for a new listener, carrying on the session saved at `persist_path` if
+/// there's a recent enough one. Its last frame is served until the screen renders,
+/// unless the screen already sent a newer one.
+fn new_manager(
+    config: &RemoteConfig,
+    persist_path: Option<&Path>,
+    carry_over: &mut SessionCarryOver,
+) -> RemoteManager {
+    let Size { cols, rows } = config.initial_size;
+    let restored =
+        persist_path.and_then(persist::load).and_then(|persisted| {
+            match restore_session(&persisted, cols, rows, PERSIST_MAX_AGE_MS, unix_time_ms()) {
+                Ok(restored) => Some(restored),
+                Err(e) => {
+                    log::info!("Not restoring saved remote session: {:?}", e);
+                    None
+                },
+            }
+        });
+    let Some(restored) = restored else {
+        return RemoteManager::new(cols, rows);
+    };
+    log::info!(
+        "Restored remote session {}, resume tokens issued before the restart are honoured",
+        restored.session.session_id
+    );
+    if carry_over.last_frame.is_none() {
+        carry_over.last_frame = restored.last_frame;
+    }
+    RemoteManager::restore(cols, rows, restored.session, restored.next_client_id)
+}
+
+/// Save what resume tokens need to `path`; failures are logged, not fatal.
+fn save_session(state: &SharedState, path: &Path) {
+    let last_frame = state
+        .current_frame
+        .as_ref()
+        .map(|frame_store| (frame_store, state.manager.style_table()));
+    let persisted = persist_session(
+        state.manager.session(),
+        last_frame,
+        state.manager.next_remote_id(),
+        unix_time_ms(),
+    );
+    if let Err(e) = persist::save(path, &persisted) {
+        log::warn!("Failed to save remote session to {}: {}", path.display(), e);
+    }
+}
+
+fn unix_time_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Copy a frame from the screen into the session and advance its state.
+/// Bring the session up to `frame_store`. Returns false, without advancing the
+/// session's state, if the frame is identical to the last one, or differs only in
+/// whether the cursor blinks, so there's nothing to send anyone.
+fn apply_frame(
+    state: &mut SharedState,
+    mut frame_store: FrameStore,
+    style_table: Option<StyleTable>,
+) -> bool {
+    state.health.record_frame(std::time::Instant::now());
+    state.frame_count = state.frame_count.wrapping_add(1);
+    let is_first_frame = state.frame_count == 1;
+    if let Some(style_table) = style_table {
+        if style_table.generation() != state.manager.style_table().generation() {
+            // The ids were handed out afresh, so what clients hold under them is stale
+            state.manager.session_mut().forget_all_client_styles();
+        }
+        *state.manager.style_table_mut() = style_table;
+    }
+
+    // Extract info from incoming frame before mutating
+    let incoming_cols = frame_store.current_frame().cols;
+    let incoming_rows = frame_store.current_frame().rows.len();
+    let incoming_cursor = frame_store.current_frame().cursor;
+    let incoming_echo_suppressed = frame_store.current_frame().echo_suppressed;
+
+    // Take dirty_rows before borrowing session
+    let dirty_rows = frame_store.take_dirty_rows();
+
+    let session = state.manager.session_mut();
+
+    // Check for dimension changes - requires full redraw
+    let session_cols = session.frame_store.current_frame().cols;
+    let session_rows = session.frame_store.current_frame().rows.len();
+    let dimension_changed = session_cols != incoming_cols || session_rows != incoming_rows;
+
+    // Determine if we need full copy:
+    // 1. First frame - need complete initial state
+    // 2. Dimension changed - resize invalidates all rows
+    let needs_full_copy = is_first_frame || dimension_changed;
+
+    if dimension_changed {
+        session.frame_store.resize(incoming_cols, incoming_rows);
+    }
+
+    if needs_full_copy {
+        // Copy all rows for initial frame or after resize
+        for (row_idx, row) in frame_store.current_frame().rows.iter().enumerate() {
+            session.frame_store.set_row(row_idx, row.0.as_ref().clone());
+        }
+    }
+
+    // Normal case: only copy dirty rows (the optimization!), and of those only the
+    // ones that really changed; screens redraw rows they didn't touch
+    let mut changed = needs_full_copy;
+    if !needs_full_copy {
+        for row_idx in &dirty_rows {
+            let Some(row) = frame_store.current_frame().rows.get(*row_idx) else {
+                continue;
+            };
+            let unchanged = session
+                .frame_store
+                .current_frame()
+                .rows
+                .get(*row_idx)
+                .is_some_and(|current| same_row(current, row));
+            if !unchanged {
+                session
+                    .frame_store
+                    .set_row(*row_idx, row.0.as_ref().clone());
+                changed = true;
+            }
+        }
+    }
+    // Blink rides along with the next real change; on its own it would churn a
+    // delta out to every client each time the program toggles it
+    changed |= !session
+        .frame_store
+        .current_frame()
+        .cursor
+        .same_except_blink(&incoming_cursor);
+    changed |= session.frame_store.current_frame().echo_suppressed != incoming_echo_suppressed;
+
+    // Seeds the rebuilt session if the listener restarts
+    state.current_frame = Some(frame_store);
+    if !changed {
+        state.idle_frame_count = state.idle_frame_count.wrapping_add(1);
+        return false;
+    }
+
+    let session = state.manager.session_mut();
+    session.frame_store.set_cursor(incoming_cursor);
+    session
+        .frame_store
+        .set_echo_suppressed(incoming_echo_suppressed);
+    session.frame_store.advance_state();
+    session.record_state_snapshot();
+    session.clear_dirty_rows_cache();
+    true
+}
+
+fn same_row(a: &Row, b: &Row) -> bool {
+    Arc::ptr_eq(&a.0, &b.0) || a.0 == b.0
 }
 
 async fn handle_instruction(
     shared_state: &Arc<RwLock<SharedState>>,
     clients: &mut HashMap<u64, ClientConnection>,
     instruction: RemoteInstruction,
-) -> Result<bool> {
+) -> Result<Option<ListenerExit>> {
     match instruction {
         RemoteInstruction::FrameReady {
             client_id: _,
-            mut frame_store,
+            frame_store,
             style_table,
+            convert_time,
         } => {
             let knobs = TestKnobs::get();
 
-            // M2: Clone data needed for sending before releasing lock
-            let (updates_to_send, delay_ms): (Vec<(u64, RenderUpdate, usize)>, Option<u64>) = {
+            // M2: Capture what each client's update needs, then diff off the lock
+            let (jobs, stages) = {
                 let mut state = shared_state.write().await;
-                state.frame_count = state.frame_count.wrapping_add(1);
-                let is_first_frame = state.frame_count == 1;
-                *state.manager.style_table_mut() = style_table;
-
-                // Extract info from incoming frame before mutating
-                let incoming_cols = frame_store.current_frame().cols;
-                let incoming_rows = frame_store.current_frame().rows.len();
-                let incoming_cursor = frame_store.current_frame().cursor;
-
-                // Take dirty_rows before borrowing session
-                let dirty_rows = frame_store.take_dirty_rows();
-
-                let session = state.manager.session_mut();
-
-                // Check for dimension changes - requires full redraw
-                let session_cols = session.frame_store.current_frame().cols;
-                let session_rows = session.frame_store.current_frame().rows.len();
-                let dimension_changed =
-                    session_cols != incoming_cols || session_rows != incoming_rows;
-
-                // Determine if we need full copy:
-                // 1. First frame - need complete initial state
-                // 2. Dimension changed - resize invalidates all rows
-                let needs_full_copy = is_first_frame || dimension_changed;
-
-                if dimension_changed {
-                    session.frame_store.resize(incoming_cols, incoming_rows);
+                let stages = state.frame_stages.clone();
+                stages.record(FrameStage::GridConvert, convert_time);
+                let changed = stages.time(FrameStage::Ingest, || {
+                    apply_frame(&mut state, frame_store, style_table)
+                });
+                if !changed {
+                    log::trace!(
+                        "Frame unchanged, skipping clients ({} idle frames)",
+                        state.idle_frame_count
+                    );
+                    return Ok(None);
                 }
-
-                if needs_full_copy {
-                    // Copy all rows for initial frame or after resize
-                    for (row_idx, row) in frame_store.current_frame().rows.iter().enumerate() {
-                        session.frame_store.set_row(row_idx, row.0.as_ref().clone());
-                    }
-                } else if !dirty_rows.is_empty() {
-                    // Normal case: only copy dirty rows (the optimization!)
-                    for row_idx in &dirty_rows {
-                        if let Some(row) = frame_store.current_frame().rows.get(*row_idx) {
-                            session
-                                .frame_store
-                                .set_row(*row_idx, row.0.as_ref().clone());
-                        }
-                    }
+                // The session keeps up, so resuming sends what's on screen by then
+                if state.manager.is_streaming_paused() {
+                    return Ok(None);
                 }
-                // If dirty_rows is empty and not first frame/resize, only cursor updates
-                // (no row copying needed - this is a cursor-only frame)
-
-                session.frame_store.set_cursor(incoming_cursor);
-                session.frame_store.advance_state();
-                session.record_state_snapshot();
-                session.clear_dirty_rows_cache();
-
-                let _state_id = session.frame_store.current_state_id();
-
-                // Release session borrow before assigning to state
-                let _ = session;
-
-                // Store for debugging
-                state.current_frame = Some(frame_store);
 
                 let force_snapshot = knobs
                     .force_snapshot_every
@@ -399,160 +1127,41 @@ async fn handle_instruction(
                         state.manager.session_mut().force_client_snapshot(remote_id);
                     }
                 }
+                state.manager.pace_clients(clients.keys().copied());
 
-                let updates: Vec<_> = clients
+                let session = state.manager.session_mut();
+                let jobs = clients
                     .keys()
-                    .filter_map(|&remote_id| {
-                        state
-                            .manager
-                            .session_mut()
-                            .get_render_update(remote_id)
-                            .map(|update| {
-                                let frame_size = match &update {
-                                    RenderUpdate::Snapshot(snapshot) => snapshot.encoded_len(),
-                                    RenderUpdate::Delta(delta) => {
-                                        state.delta_count = state.delta_count.wrapping_add(1);
-                                        delta.encoded_len()
-                                    },
-                                };
-                                (remote_id, update, frame_size)
-                            })
-                    })
-                    .collect();
-
-                (updates, knobs.delay_send_ms)
+                    .filter_map(|&remote_id| session.begin_render_update(remote_id))
+                    .collect::<Vec<_>>();
+                (jobs, stages)
             };
             // Lock released here
 
+            let computing = (!jobs.is_empty()).then(std::time::Instant::now);
+            let outputs = compute_render_jobs(jobs).await;
+            if let Some(started) = computing {
+                stages.record(FrameStage::DeltaCompute, started.elapsed());
+            }
+            let updates_to_send = finish_render_outputs(shared_state, outputs).await;
+            let delay_ms = knobs.delay_send_ms;
+
             if let Some(ms) = delay_ms {
                 tokio::time::sleep(tokio::time::Duration::from_millis(ms)).await;
             }
 
-            // M1: Send to each client's channel (non-blocking)
-            // Try datagrams first for deltas, fall back to stream
-            const CONSERVATIVE_DATAGRAM_LIMIT: usize = 1200;
-
-            let mut clients_to_remove = Vec::new();
-            let mut clients_need_snapshot = Vec::new();
-            let client_count = clients.len();
-
-            for (remote_id, update, frame_size) in updates_to_send {
-                let is_delta = matches!(&update, RenderUpdate::Delta(_));
-
-                let should_drop = if is_delta {
-                    knobs
-                        .drop_delta_nth
-                        .map(|n| {
-                            if n > 0 {
-                                let mut state_guard = shared_state.blocking_write();
-                                let should_drop = state_guard.delta_count.is_multiple_of(n);
-                                if should_drop {
-                                    state_guard.dropped_delta_count =
-                                        state_guard.dropped_delta_count.wrapping_add(1);
-                                }
-                                should_drop
-                            } else {
-                                false
-                            }
-                        })
-                        .unwrap_or(false)
-                } else {
-                    false
-                };
-
-                if knobs.log_frame_stats {
-                    log::info!(
-                        "[FRAME_STATS] type={} size={} clients={} dropped={} drop_nth={:?} delay_ms={:?}",
-                        if is_delta { "delta" } else { "snapshot" },
-                        frame_size,
-                        client_count,
-                        should_drop,
-                        knobs.drop_delta_nth,
-                        knobs.delay_send_ms,
-                    );
-                }
-
-                if should_drop {
-                    log::debug!("Test knob: dropping delta for client {}", remote_id);
-                    continue;
-                }
-
-                if let Some(client) = clients.get(&remote_id) {
-                    let mut sent_via_datagram = false;
-
-                    if let RenderUpdate::Delta(ref delta) = update {
-                        if client.datagrams_negotiated {
-                            let datagram_envelope = DatagramEnvelope {
-                                msg: Some(datagram_envelope::Msg::ScreenDelta(delta.clone())),
-                            };
-                            let encoded = encode_datagram_envelope(&datagram_envelope);
-                            let max_size = client
-                                .max_datagram_size
-                                .unwrap_or(0)
-                                .min(CONSERVATIVE_DATAGRAM_LIMIT);
-
-                            if encoded.len() <= max_size {
-                                match client.connection.send_datagram(&encoded) {
-                                    Ok(()) => {
-                                        log::trace!(
-                                            "Sent delta via datagram ({} bytes) to client {}",
-                                            encoded.len(),
-                                            remote_id
-                                        );
-                                        sent_via_datagram = true;
-                                    },
-                                    Err(e) => {
-                                        log::debug!(
-                                            "Datagram send failed for client {}, using stream: {}",
-                                            remote_id,
-                                            e
-                                        );
-                                    },
-                                }
-                            }
-                        }
-                    }
-
-                    if !sent_via_datagram {
-                        let msg = match update {
-                            RenderUpdate::Snapshot(snapshot) => StreamEnvelope {
-                                msg: Some(stream_envelope::Msg::ScreenSnapshot(snapshot)),
-                            },
-                            RenderUpdate::Delta(delta) => StreamEnvelope {
-                                msg: Some(stream_envelope::Msg::ScreenDeltaStream(delta)),
-                            },
-                        };
-                        match client.sender.try_send(msg) {
-                            Err(mpsc::error::TrySendError::Full(_)) => {
-                                log::warn!(
-                                    "Client {} channel full, forcing snapshot resync",
-                                    remote_id
-                                );
-                                clients_need_snapshot.push(remote_id);
-                            },
-                            Err(mpsc::error::TrySendError::Closed(_)) => {
-                                clients_to_remove.push(remote_id);
-                            },
-                            Ok(()) => {},
-                        }
-                    }
-                }
-            }
-
-            for remote_id in &clients_need_snapshot {
-                let mut state = shared_state.write().await;
-                state
-                    .manager
-                    .session_mut()
-                    .force_client_snapshot(*remote_id);
-            }
-
-            for remote_id in clients_to_remove {
-                clients.remove(&remote_id);
-                let mut state = shared_state.write().await;
-                state.manager.session_mut().remove_client(remote_id);
-                log::info!("Removed client {} due to closed channel", remote_id);
-            }
+            send_render_updates(shared_state, clients, updates_to_send).await;
+            report_render_modes(shared_state, clients).await;
+            report_session_metadata(shared_state, clients).await;
+            report_pane_layout(shared_state, clients).await;
+            report_keybindings(shared_state, clients).await;
+            // Kept off the clients' critical path, for the next one to attach
+            shared_state
+                .write()
+                .await
+                .manager
+                .session_mut()
+                .preload_snapshot();
 
             log::trace!("Frame ready: clients={}", clients.len());
         },
@@ -569,7 +1178,7 @@ async fn handle_instruction(
         },
         RemoteInstruction::ClientConnected { client_id, size } => {
             let mut state = shared_state.write().await;
-            state.active_zellij_client = Some(client_id);
+            state.active_zellij_client = input_client(state.active_zellij_client, client_id);
             log::info!(
                 "Zellij client {} connected: {}x{}",
                 client_id,
@@ -584,715 +1193,4554 @@ async fn handle_instruction(
             }
             log::info!("Zellij client {} disconnected", client_id);
         },
+        RemoteInstruction::FocusedPaneTitle { title } => {
+            shared_state.write().await.metadata.pane_title = title;
+            report_session_metadata(shared_state, clients).await;
+        },
+        RemoteInstruction::FocusedPaneProcess { cwd, command } => {
+            set_focused_pane_process(&mut shared_state.write().await.metadata, cwd, command);
+            report_session_metadata(shared_state, clients).await;
+        },
+        RemoteInstruction::PaneLayout(pane_layout) => {
+            shared_state.write().await.pane_layout = pane_layout;
+            report_pane_layout(shared_state, clients).await;
+        },
+        RemoteInstruction::Keybindings(keybindings) => {
+            shared_state.write().await.keybindings = keybindings;
+            report_keybindings(shared_state, clients).await;
+        },
+        RemoteInstruction::RevokeLease => {
+            let events = {
+                let mut state = shared_state.write().await;
+                let session = state.manager.session_mut();
+                if session.revoke_lease("local").is_none() {
+                    log::debug!("No remote lease to revoke");
+                }
+                let events = session.drain_events();
+                report_presence(&mut state);
+                events
+            };
+            deliver_session_events(clients, events);
+        },
+        RemoteInstruction::Bell { pane_id, focused } => {
+            let bell = {
+                let mut state = shared_state.write().await;
+                ring_bell(&mut state, pane_id, focused)
+            };
+            broadcast_bells(clients, bell.into_iter().collect());
+        },
+        RemoteInstruction::ScreenHeartbeat => {
+            let now = std::time::Instant::now();
+            shared_state.write().await.health.record_heartbeat(now);
+        },
+        RemoteInstruction::AnswerApproval {
+            request_id,
+            approved,
+        } => {
+            let answer = {
+                let mut state = shared_state.write().await;
+                answer_approval(&mut state, request_id, approved, std::time::Instant::now())
+            };
+            match answer {
+                Some((remote_id, responses)) => {
+                    send_control_messages(clients, remote_id, responses)
+                },
+                None => log::debug!("Approval {} is no longer pending", request_id),
+            }
+        },
+        RemoteInstruction::SetClientLatency {
+            remote_id,
+            delay_ms,
+        } => {
+            let Some(client) = clients.get_mut(&remote_id) else {
+                log::debug!("No remote client {} to delay", remote_id);
+                return Ok(None);
+            };
+            let delay_ms = delay_ms.min(MAX_INJECTED_LATENCY_MS);
+            client
+                .sender
+                .set_delay(std::time::Duration::from_millis(delay_ms));
+            log::info!(
+                "Injected latency on remote client {}: {}ms",
+                remote_id,
+                delay_ms
+            );
+            let mut state = shared_state.write().await;
+            state.presence.set_injected_latency(remote_id, delay_ms);
+            report_presence(&mut state);
+        },
+        RemoteInstruction::ToggleStreamingPaused => {
+            toggle_streaming_paused(shared_state, clients).await;
+        },
+        RemoteInstruction::Reconfigure(config) => {
+            return Ok(Some(ListenerExit::Reconfigure(Box::new(config))));
+        },
+        RemoteInstruction::Stop => {
+            return Ok(Some(ListenerExit::Stop));
+        },
+        RemoteInstruction::Start => {
+            log::debug!("Remote server already running");
+        },
         RemoteInstruction::Shutdown => {
-            return Ok(true);
+            log::info!("Remote thread received shutdown signal");
+            return Ok(Some(ListenerExit::Shutdown));
         },
     }
-    Ok(false)
+    Ok(None)
 }
 
-struct ClientGuard {
-    remote_id: u64,
-    shared_state: Arc<RwLock<SharedState>>,
-    conn_event_tx: mpsc::Sender<ConnectionEvent>,
-    disarmed: bool,
+fn set_focused_pane_process(
+    metadata: &mut SessionMetadata,
+    cwd: Option<PathBuf>,
+    command: Option<String>,
+) {
+    metadata.cwd = cwd
+        .map(|cwd| cwd.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    metadata.running_command = command.unwrap_or_default();
 }
 
-impl ClientGuard {
-    fn new(
-        remote_id: u64,
-        shared_state: Arc<RwLock<SharedState>>,
-        conn_event_tx: mpsc::Sender<ConnectionEvent>,
-    ) -> Self {
-        Self {
-            remote_id,
-            shared_state,
-            conn_event_tx,
-            disarmed: false,
+/// Diff each client's frame. With several clients, or much of the screen changed, the
+/// jobs run on the blocking pool, in parallel and clear of the runtime workers the
+/// accept loop and connections share; a keystroke's delta to one client isn't worth
+/// the hop.
+async fn compute_render_jobs(jobs: Vec<RenderJob>) -> Vec<RenderOutput> {
+    let deltas = jobs.iter().filter(|job| job.is_delta()).count();
+    let rows: usize = jobs.iter().map(RenderJob::rows_to_diff).sum();
+    if deltas < PARALLEL_RENDER_MIN_DELTAS && rows < BLOCKING_RENDER_MIN_ROWS {
+        return jobs.into_iter().map(RenderJob::compute).collect();
+    }
+    let handles: Vec<_> = jobs
+        .into_iter()
+        .map(|job| {
+            let remote_id = job.client_id();
+            (
+                remote_id,
+                tokio::task::spawn_blocking(move || job.compute()),
+            )
+        })
+        .collect();
+    let mut outputs = Vec::with_capacity(handles.len());
+    for (remote_id, handle) in handles {
+        match handle.await {
+            Ok(output) => outputs.push(output),
+            Err(e) => log::error!("Render job for client {} failed: {}", remote_id, e),
         }
     }
+    outputs
+}
 
-    fn disarm(&mut self) {
-        self.disarmed = true;
+/// Record computed render updates as sent, back under the lock, with their sizes.
+async fn finish_render_outputs(
+    shared_state: &Arc<RwLock<SharedState>>,
+    outputs: Vec<RenderOutput>,
+) -> Vec<(u64, RenderUpdate, usize)> {
+    let mut state = shared_state.write().await;
+    let mut updates = Vec::with_capacity(outputs.len());
+    for output in outputs {
+        let remote_id = output.client_id();
+        let Some(update) = state.manager.session_mut().finish_render_update(output) else {
+            continue;
+        };
+        let frame_size = match &update {
+            RenderUpdate::Snapshot(snapshot) => snapshot.encoded_len(),
+            RenderUpdate::Delta(delta) => {
+                state.delta_count = state.delta_count.wrapping_add(1);
+                delta.encoded_len()
+            },
+        };
+        updates.push((remote_id, update, frame_size));
     }
+    updates
 }
 
-impl Drop for ClientGuard {
-    fn drop(&mut self) {
-        if self.disarmed {
+/// Bring a newly added client up to date if its first frame, a preloaded snapshot,
+/// is behind the screen, rather than leaving it there until the next frame.
+async fn send_catch_up(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+    remote_id: u64,
+) {
+    let job = {
+        let mut state = shared_state.write().await;
+        if state.manager.is_streaming_paused() {
             return;
         }
-        let remote_id = self.remote_id;
-        let shared_state = self.shared_state.clone();
-        let conn_event_tx = self.conn_event_tx.clone();
-        tokio::spawn(async move {
-            {
-                let mut state = shared_state.write().await;
-                state.manager.session_mut().remove_client(remote_id);
-                log::info!("ClientGuard cleanup: removed client {}", remote_id);
-            }
-            if let Err(e) = conn_event_tx
-                .send(ConnectionEvent::ClientDisconnected { remote_id })
-                .await
-            {
-                log::warn!(
-                    "Failed to send ClientDisconnected during guard cleanup: {}",
-                    e
-                );
-            }
-        });
-    }
+        let session = state.manager.session_mut();
+        if !session.has_unsent_state(remote_id) {
+            return;
+        }
+        session.begin_render_update(remote_id)
+    };
+    let Some(job) = job else {
+        return;
+    };
+    let outputs = compute_render_jobs(vec![job]).await;
+    let updates = finish_render_outputs(shared_state, outputs).await;
+    send_render_updates(shared_state, clients, updates).await;
 }
 
-async fn handle_connection(
-    connection: wtransport::Connection,
-    shared_state: Arc<RwLock<SharedState>>,
-    conn_event_tx: mpsc::Sender<ConnectionEvent>,
-    expected_token: Option<Vec<u8>>,
-) -> Result<()> {
-    let (mut send, mut recv) = connection.accept_bi().await?;
-    let remote_id = REMOTE_CLIENT_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
-
-    let client_hello = read_client_hello(&mut recv).await?;
+/// Pause streaming to every remote client, or resume it. Nothing is queued while
+/// paused; resuming brings each client up to date right away rather than on the next
+/// frame, which may be a while.
+async fn toggle_streaming_paused(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+) {
+    let (paused, jobs) = {
+        let mut state = shared_state.write().await;
+        let paused = !state.manager.is_streaming_paused();
+        state.manager.set_streaming_paused(paused);
+        let session = state.manager.session_mut();
+        let jobs: Vec<_> = if paused {
+            vec![]
+        } else {
+            clients
+                .keys()
+                .filter_map(|&remote_id| session.begin_render_update(remote_id))
+                .collect()
+        };
+        (paused, jobs)
+    };
     log::info!(
-        "Received ClientHello from {} (remote_id={})",
-        client_hello.client_name,
-        remote_id
+        "Remote streaming {} for {} clients",
+        if paused { "paused" } else { "resumed" },
+        clients.len()
     );
+    broadcast_stream_paused(clients, paused);
+    if jobs.is_empty() {
+        return;
+    }
+    let outputs = compute_render_jobs(jobs).await;
+    let updates = finish_render_outputs(shared_state, outputs).await;
+    send_render_updates(shared_state, clients, updates).await;
+    report_render_modes(shared_state, clients).await;
+    report_session_metadata(shared_state, clients).await;
+    report_pane_layout(shared_state, clients).await;
+    report_keybindings(shared_state, clients).await;
+}
 
-    if let Some(ref expected) = expected_token {
-        let auth_valid = client_hello.bearer_token.len() == expected.len()
-            && bool::from(client_hello.bearer_token.ct_eq(expected));
-        if !auth_valid {
-            log::warn!(
-                "Authentication failed for remote client {} ({}): invalid bearer token",
-                remote_id,
-                client_hello.client_name
-            );
-            let error = ProtocolError {
-                code: protocol_error::Code::Unauthorized as i32,
-                message: "Invalid bearer token".to_string(),
-                fatal: true,
-            };
-            let encoded = encode_envelope(&StreamEnvelope {
-                msg: Some(stream_envelope::Msg::ProtocolError(error)),
-            })?;
-            send.write_all(&encoded).await?;
-            send.finish().await.ok();
-            anyhow::bail!("authentication failed: invalid bearer token");
-        }
-        log::debug!("Remote client {} authenticated successfully", remote_id);
+fn stream_paused_notice(paused: bool) -> StreamPaused {
+    StreamPaused {
+        paused,
+        placeholder: if paused {
+            STREAM_PAUSED_PLACEHOLDER.to_string()
+        } else {
+            String::new()
+        },
     }
+}
 
-    let mut guard = ClientGuard::new(remote_id, shared_state.clone(), conn_event_tx.clone());
+fn broadcast_stream_paused(clients: &HashMap<u64, ClientConnection>, paused: bool) {
+    for (remote_id, client) in clients {
+        let msg = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::StreamPaused(stream_paused_notice(
+                paused,
+            ))),
+            envelope_seq: 0,
+        };
+        if let Err(mpsc::error::TrySendError::Full(_)) = client.sender.try_send(msg) {
+            log::warn!("Client {} channel full, dropping StreamPaused", remote_id);
+        }
+    }
+}
 
-    {
-        let mut state = shared_state.write().await;
-        state.manager.session_mut().add_client(remote_id, 4);
+/// `update` as stream messages, chunked when the client capped their size.
+fn render_messages(update: RenderUpdate, max_message_bytes: Option<usize>) -> Vec<StreamEnvelope> {
+    match (update, max_message_bytes) {
+        (RenderUpdate::Snapshot(snapshot), Some(max_bytes)) => chunk_snapshot(snapshot, max_bytes)
+            .into_iter()
+            .map(|chunk| StreamEnvelope {
+                msg: Some(stream_envelope::Msg::ScreenSnapshot(chunk)),
+                envelope_seq: 0,
+            })
+            .collect(),
+        (RenderUpdate::Delta(delta), Some(max_bytes)) => chunk_delta(delta, max_bytes)
+            .into_iter()
+            .map(|chunk| StreamEnvelope {
+                msg: Some(stream_envelope::Msg::ScreenDeltaStream(chunk)),
+                envelope_seq: 0,
+            })
+            .collect(),
+        (RenderUpdate::Snapshot(snapshot), None) => vec![StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ScreenSnapshot(snapshot)),
+            envelope_seq: 0,
+        }],
+        (RenderUpdate::Delta(delta), None) => vec![StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ScreenDeltaStream(delta)),
+            envelope_seq: 0,
+        }],
+    }
+}
 
-        let session = state.manager.session_mut();
-        let lease = session.lease_manager.request_control(
-            remote_id,
-            Some(DisplaySize { cols: 80, rows: 24 }),
-            false,
-        );
+/// Send prepared render updates, trying datagrams first for deltas and falling back to
+/// the client's stream.
+async fn send_render_updates(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+    updates_to_send: Vec<(u64, RenderUpdate, usize)>,
+) {
+    let knobs = TestKnobs::get();
+    // M1: Send to each client's channel (non-blocking)
+    // Try datagrams first for deltas, fall back to stream
+    const CONSERVATIVE_DATAGRAM_LIMIT: usize = 1200;
 
-        let lease_info = match lease {
-            LeaseResult::Granted(l) => Some(l),
-            LeaseResult::Denied { .. } => session.lease_manager.get_current_lease(),
-        };
+    let mut clients_to_remove = Vec::new();
+    let mut clients_need_snapshot = Vec::new();
+    let client_count = clients.len();
 
-        let resume_token = session.generate_resume_token(remote_id);
-        let session_name = state.session_name.clone();
+    for (remote_id, update, frame_size) in updates_to_send {
+        let is_delta = matches!(&update, RenderUpdate::Delta(_));
 
-        let server_hello = build_server_hello(
-            &client_hello,
-            remote_id,
-            lease_info,
-            resume_token,
-            &session_name,
-        );
-        let encoded = encode_envelope(&StreamEnvelope {
-            msg: Some(stream_envelope::Msg::ServerHello(server_hello)),
-        })?;
-        send.write_all(&encoded).await?;
-        log::info!("Sent ServerHello to remote client {}", remote_id);
+        let should_drop = if is_delta {
+            knobs
+                .drop_delta_nth
+                .map(|n| {
+                    if n > 0 {
+                        let mut state_guard = shared_state.blocking_write();
+                        let should_drop = state_guard.delta_count.is_multiple_of(n);
+                        if should_drop {
+                            state_guard.dropped_delta_count =
+                                state_guard.dropped_delta_count.wrapping_add(1);
+                        }
+                        should_drop
+                    } else {
+                        false
+                    }
+                })
+                .unwrap_or(false)
+        } else {
+            false
+        };
 
-        if let Some(RenderUpdate::Snapshot(snapshot)) =
-            state.manager.session_mut().get_render_update(remote_id)
-        {
-            let encoded = encode_envelope(&StreamEnvelope {
-                msg: Some(stream_envelope::Msg::ScreenSnapshot(snapshot)),
-            })?;
-            send.write_all(&encoded).await?;
-            log::info!("Sent initial ScreenSnapshot to remote client {}", remote_id);
+        if knobs.log_frame_stats {
+            log::info!(
+                "[FRAME_STATS] type={} size={} clients={} dropped={} drop_nth={:?} delay_ms={:?} client={} injected_delay_ms={}",
+                if is_delta { "delta" } else { "snapshot" },
+                frame_size,
+                client_count,
+                should_drop,
+                knobs.drop_delta_nth,
+                knobs.delay_send_ms,
+                remote_id,
+                clients
+                    .get(&remote_id)
+                    .map_or(0, |client| client.sender.delay().as_millis()),
+            );
         }
-    }
 
-    guard.disarm();
+        if should_drop {
+            log::debug!("Test knob: dropping delta for client {}", remote_id);
+            continue;
+        }
 
-    let client_supports_datagrams = client_hello
-        .capabilities
-        .as_ref()
-        .map(|c| c.supports_datagrams)
-        .unwrap_or(false);
+        if let Some(client) = clients.get_mut(&remote_id) {
+            let mut sent_via_datagram = false;
+            let mut sent_via_stream = false;
 
-    conn_event_tx
-        .send(ConnectionEvent::ClientConnected {
-            remote_id,
-            send,
-            connection: connection.clone(),
-            client_supports_datagrams,
-            conn_event_tx: conn_event_tx.clone(),
-        })
-        .await?;
+            if let RenderUpdate::Delta(ref delta) = update {
+                // Datagrams would skip the delay and overtake what it holds back
+                if client.datagrams_negotiated && client.sender.delay().is_zero() {
+                    let datagram_envelope = DatagramEnvelope {
+                        msg: Some(datagram_envelope::Msg::ScreenDelta(delta.clone())),
+                    };
+                    let encoded = encode_datagram_envelope(&datagram_envelope);
+                    let max_size = client
+                        .max_datagram_size
+                        .unwrap_or(0)
+                        .min(CONSERVATIVE_DATAGRAM_LIMIT);
 
-    let mut buffer = BytesMut::new();
-    loop {
-        let mut chunk = [0u8; 4096];
-        match recv.read(&mut chunk).await? {
-            Some(0) | None => {
-                log::info!("Remote client {} stream closed", remote_id);
-                break;
-            },
-            Some(n) => {
-                buffer.extend_from_slice(&chunk[..n]);
+                    if encoded.len() <= max_size {
+                        match client.connection.send_datagram(&encoded) {
+                            Ok(()) => {
+                                log::trace!(
+                                    "Sent delta via datagram ({} bytes) to client {}",
+                                    encoded.len(),
+                                    remote_id
+                                );
+                                sent_via_datagram = true;
+                            },
+                            Err(e) => {
+                                log::debug!(
+                                    "Datagram send failed for client {}, using stream: {}",
+                                    remote_id,
+                                    e
+                                );
+                            },
+                        }
+                    }
+                }
+            }
 
-                while let Some(envelope) = decode_envelope(&mut buffer)? {
-                    match envelope.msg {
-                        Some(stream_envelope::Msg::InputEvent(input)) => {
-                            conn_event_tx
-                                .send(ConnectionEvent::InputReceived { remote_id, input })
-                                .await?;
+            if !sent_via_datagram {
+                let msgs = render_messages(update, client.max_message_bytes);
+                // A set cut short is never completed: the client drops it once the
+                // resync snapshot arrives
+                for msg in msgs {
+                    match client.sender.try_send(msg) {
+                        Err(mpsc::error::TrySendError::Full(_)) => {
+                            log::warn!(
+                                "Client {} channel full, forcing snapshot resync",
+                                remote_id
+                            );
+                            clients_need_snapshot.push(remote_id);
+                            sent_via_stream = false;
+                            break;
                         },
-                        Some(stream_envelope::Msg::RequestControl(req)) => {
-                            conn_event_tx
-                                .send(ConnectionEvent::RequestControl {
-                                    remote_id,
-                                    request: req,
-                                })
-                                .await?;
+                        Err(mpsc::error::TrySendError::Closed(_)) => {
+                            clients_to_remove.push(remote_id);
+                            sent_via_stream = false;
+                            break;
                         },
-                        Some(stream_envelope::Msg::RequestSnapshot(request)) => {
-                            log::info!(
-                                "Client {} requested snapshot: reason={:?}",
-                                remote_id,
-                                request.reason
-                            );
-                            conn_event_tx
-                                .send(ConnectionEvent::RequestSnapshot { remote_id, request })
-                                .await?;
-                        },
-                        Some(stream_envelope::Msg::SetControllerSize(request)) => {
-                            log::info!(
-                                "Client {} set controller size: {:?}",
-                                remote_id,
-                                request.size
-                            );
-                            conn_event_tx
-                                .send(ConnectionEvent::SetControllerSize { remote_id, request })
-                                .await?;
-                        },
-
-                        _ => {
-                            log::debug!("Unhandled message from client {}", remote_id);
+                        Ok(()) => {
+                            sent_via_stream = true;
                         },
                     }
                 }
-            },
+            }
+            if sent_via_datagram || sent_via_stream {
+                if let Some(keepalive) = client.keepalive.as_mut() {
+                    keepalive.record_frame_sent(std::time::Instant::now());
+                }
+            }
         }
     }
 
-    conn_event_tx
-        .send(ConnectionEvent::ClientDisconnected { remote_id })
-        .await?;
-    Ok(())
+    for remote_id in &clients_need_snapshot {
+        let mut state = shared_state.write().await;
+        state
+            .manager
+            .session_mut()
+            .force_client_snapshot(*remote_id);
+    }
+
+    for remote_id in clients_to_remove {
+        clients.remove(&remote_id);
+        let mut state = shared_state.write().await;
+        state.manager.session_mut().remove_client(remote_id);
+        log::info!("Removed client {} due to closed channel", remote_id);
+    }
 }
 
-/// Spawns a per-client sender task that receives from the channel and writes to the stream (M1)
-fn spawn_client_sender_task(
-    remote_id: u64,
-    mut send_stream: wtransport::SendStream,
-    mut receiver: mpsc::Receiver<StreamEnvelope>,
+/// Clients in text mode get frames at most every `TEXT_MODE_FRAME_INTERVAL_MS`, and
+/// capped clients at most at their frame rate, so a frame held back to coalesce goes
+/// out here if nothing newer came along. So do rows the flash guard held, once they
+/// settle.
+async fn handle_paced_frame_flush(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
 ) {
-    tokio::spawn(async move {
-        while let Some(msg) = receiver.recv().await {
-            match encode_envelope(&msg) {
-                Ok(encoded) => {
-                    if let Err(e) = send_stream.write_all(&encoded).await {
-                        log::warn!("Client {} sender task: write failed: {}", remote_id, e);
-                        break;
-                    }
-                },
-                Err(e) => {
-                    log::error!("Client {} sender task: encode failed: {}", remote_id, e);
-                },
-            }
+    let updates: Vec<_> = {
+        let mut state = shared_state.write().await;
+        if state.manager.is_streaming_paused() {
+            return;
         }
-        log::debug!("Client {} sender task exiting", remote_id);
-    });
+        let session = state.manager.session_mut();
+        let held_back: Vec<u64> = clients
+            .keys()
+            .copied()
+            .filter(|&remote_id| {
+                session.is_frame_paced(remote_id) && session.has_unsent_state(remote_id)
+            })
+            .collect();
+        held_back
+            .into_iter()
+            .filter_map(|remote_id| {
+                session.get_render_update(remote_id).map(|update| {
+                    let frame_size = match &update {
+                        RenderUpdate::Snapshot(snapshot) => snapshot.encoded_len(),
+                        RenderUpdate::Delta(delta) => delta.encoded_len(),
+                    };
+                    (remote_id, update, frame_size)
+                })
+            })
+            .collect()
+    };
+    if updates.is_empty() {
+        return;
+    }
+    send_render_updates(shared_state, clients, updates).await;
+    report_render_modes(shared_state, clients).await;
 }
 
-fn spawn_datagram_receive_task(
-    remote_id: u64,
-    connection: wtransport::Connection,
-    conn_event_tx: mpsc::Sender<ConnectionEvent>,
-) -> tokio::task::JoinHandle<()> {
-    tokio::spawn(async move {
-        loop {
-            match connection.receive_datagram().await {
-                Ok(datagram) => match decode_datagram_envelope(&datagram) {
-                    Ok(envelope) => {
-                        if let Some(datagram_envelope::Msg::StateAck(ack)) = envelope.msg {
-                            log::trace!(
-                                "Received StateAck from client {}: last_applied={}",
-                                remote_id,
-                                ack.last_applied_state_id
-                            );
-                            if conn_event_tx
-                                .try_send(ConnectionEvent::StateAckReceived { remote_id, ack })
-                                .is_err()
-                            {
-                                log::debug!(
-                                    "Client {} StateAck channel full or closed, dropping ack",
-                                    remote_id,
-                                );
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        log::trace!("Failed to decode datagram from client {}: {}", remote_id, e);
-                    },
-                },
-                Err(e) => {
-                    log::debug!(
-                        "Datagram receive error for client {} (connection closed?): {}",
-                        remote_id,
-                        e
-                    );
-                    break;
-                },
+/// Tell clients the session moved in or out of text mode since they were last told.
+async fn report_render_modes(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+) {
+    let state = shared_state.read().await;
+    for (remote_id, client) in clients.iter_mut() {
+        let text_mode = state.manager.session().is_text_mode(*remote_id);
+        if text_mode == client.text_mode {
+            continue;
+        }
+        let change = if text_mode {
+            RenderModeChanged {
+                mode: RenderMode::TextOnly.into(),
+                frame_interval_ms: TEXT_MODE_FRAME_INTERVAL_MS as u32,
+            }
+        } else {
+            RenderModeChanged {
+                mode: RenderMode::Full.into(),
+                frame_interval_ms: 0,
             }
+        };
+        let msg = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::RenderModeChanged(change)),
+            envelope_seq: 0,
+        };
+        match client.sender.try_send(msg) {
+            Ok(()) => {
+                log::info!(
+                    "Client {} switched to {} mode",
+                    remote_id,
+                    if text_mode { "text-only" } else { "full" }
+                );
+                client.text_mode = text_mode;
+            },
+            // Retried on the next frame
+            Err(e) => log::debug!("Client {} RenderModeChanged not sent: {}", remote_id, e),
         }
-        log::debug!("Client {} datagram receive task exiting", remote_id);
-    })
+    }
 }
 
-async fn handle_connection_event(
+/// Send each client the session metadata if it changed since they were last told.
+async fn report_session_metadata(
     shared_state: &Arc<RwLock<SharedState>>,
     clients: &mut HashMap<u64, ClientConnection>,
-    event: ConnectionEvent,
-) -> Result<()> {
-    match event {
-        ConnectionEvent::ClientConnected {
-            remote_id,
-            send,
-            connection,
-            client_supports_datagrams,
-            conn_event_tx,
-        } => {
-            let max_datagram_size = connection.max_datagram_size();
-            let transport_supports = max_datagram_size.is_some();
-            let datagrams_negotiated = transport_supports && client_supports_datagrams;
+) {
+    let state = shared_state.read().await;
+    for (remote_id, client) in clients.iter_mut() {
+        if client.metadata_sent.as_ref() == Some(&state.metadata) {
+            continue;
+        }
+        let msg = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::SessionMetadata(
+                state.metadata.clone(),
+            )),
+            envelope_seq: 0,
+        };
+        match client.sender.try_send(msg) {
+            Ok(()) => client.metadata_sent = Some(state.metadata.clone()),
+            // Retried on the next frame
+            Err(e) => log::debug!("Client {} SessionMetadata not sent: {}", remote_id, e),
+        }
+    }
+}
 
-            if datagrams_negotiated {
-                log::info!(
-                    "Client {} datagrams negotiated, max_size={}",
-                    remote_id,
-                    max_datagram_size.unwrap()
-                );
-            } else {
-                log::info!(
-                    "Client {} datagrams not negotiated (transport={}, client_advertised={})",
-                    remote_id,
-                    transport_supports,
-                    client_supports_datagrams
-                );
-            }
+/// Send each client that asked for it the pane layout if it changed since they were
+/// last told.
+async fn report_pane_layout(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+) {
+    let state = shared_state.read().await;
+    for (remote_id, client) in clients.iter_mut().filter(|(_, client)| client.pane_layout) {
+        if client.pane_layout_sent.as_ref() == Some(&state.pane_layout) {
+            continue;
+        }
+        let msg = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::PaneLayout(state.pane_layout.clone())),
+            envelope_seq: 0,
+        };
+        match client.sender.try_send(msg) {
+            Ok(()) => client.pane_layout_sent = Some(state.pane_layout.clone()),
+            // Retried on the next frame
+            Err(e) => log::debug!("Client {} PaneLayout not sent: {}", remote_id, e),
+        }
+    }
+}
 
-            let datagram_task_handle = if datagrams_negotiated {
-                Some(spawn_datagram_receive_task(
-                    remote_id,
-                    connection.clone(),
-                    conn_event_tx,
-                ))
-            } else {
-                None
+/// Send each client that asked for them the keybindings if they changed since they
+/// were last told.
+async fn report_keybindings(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+) {
+    let state = shared_state.read().await;
+    for (remote_id, client) in clients.iter_mut().filter(|(_, client)| client.keybindings) {
+        if client.keybindings_sent.as_ref() == Some(&state.keybindings) {
+            continue;
+        }
+        let msg = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::KeybindingInfo(
+                state.keybindings.clone(),
+            )),
+            envelope_seq: 0,
+        };
+        match client.sender.try_send(msg) {
+            Ok(()) => client.keybindings_sent = Some(state.keybindings.clone()),
+            // Retried on the next frame
+            Err(e) => log::debug!("Client {} KeybindingInfo not sent: {}", remote_id, e),
+        }
+    }
+}
+
+async fn handle_lease_tick(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &HashMap<u64, ClientConnection>,
+    lease_expiry_enabled: bool,
+) {
+    let (events, handoff, returned, denied, bells, lease_status) = {
+        let mut state = shared_state.write().await;
+        let denied = expire_approvals(&mut state, std::time::Instant::now());
+        let now_ms = state.started_at.elapsed().as_millis() as u64;
+        let bells = state.bells.flush(now_ms);
+        if lease_expiry_enabled {
+            state.manager.session_mut().tick_lease();
+        }
+        let events = state.manager.session_mut().drain_events();
+        let lease_manager = &mut state.manager.session_mut().lease_manager;
+        let handoff = lease_manager.poll_handoff();
+        let returned = lease_manager.poll_time_box();
+        record_handoff_outcome(&mut state, handoff.as_ref());
+        if let Some(returned) = &returned {
+            state
+                .control_throttle
+                .record_grant(returned.lease.owner_client_id);
+        }
+        let lease_status = due_lease_status(&mut state, now_ms, lease_expiry_enabled);
+        report_presence(&mut state);
+        (events, handoff, returned, denied, bells, lease_status)
+    };
+    broadcast_bells(clients, bells);
+    if let Some(lease_status) = lease_status {
+        broadcast_lease_status(clients, lease_status);
+    }
+    for (remote_id, responses) in denied {
+        send_control_messages(clients, remote_id, responses);
+    }
+    if let Some(handoff) = handoff {
+        deliver_handoff_outcome(clients, handoff);
+    }
+    if let Some(returned) = returned {
+        deliver_lease_return(clients, returned);
+    }
+    deliver_session_events(clients, events);
+}
+
+/// Act on what the session queued since the last drain: an ended lease is broadcast
+/// as `LeaseRevoked`, naming the new controller after a takeover; everything else is
+/// only logged.
+fn deliver_session_events(clients: &HashMap<u64, ClientConnection>, events: Vec<SessionEvent>) {
+    for event in events {
+        let (lease_id, owner, reason, takeover) = match event {
+            SessionEvent::Lease(LeaseEvent::Revoked {
+                lease_id,
+                owner,
+                reason,
+            }) => (lease_id, owner, reason, None),
+            SessionEvent::Lease(LeaseEvent::Expired { lease_id, owner }) => {
+                (lease_id, owner, "timeout".to_string(), None)
+            },
+            SessionEvent::Lease(LeaseEvent::TakenOver {
+                lease_id,
+                owner,
+                new_owner,
+                grace,
+            }) => {
+                let takeover = Takeover {
+                    new_owner_client_id: new_owner,
+                    new_owner_name: clients
+                        .get(&new_owner)
+                        .map(|client| client.client_name.clone())
+                        .unwrap_or_default(),
+                    grace_ms: grace.map_or(0, |grace| grace.as_millis() as u32),
+                };
+                (lease_id, owner, "takeover".to_string(), Some(takeover))
+            },
+            SessionEvent::Resync { client_id } => {
+                log::debug!("Remote client {} resyncing from a snapshot", client_id);
+                continue;
+            },
+            other => {
+                log::debug!("Remote session event: {:?}", other);
+                continue;
+            },
+        };
+        log::info!(
+            "Lease {} of remote client {} ended: {}",
+            lease_id,
+            owner,
+            reason
+        );
+        broadcast_lease_revoked(clients, lease_id, &reason, takeover);
+    }
+}
+
+/// Coalesce a bell from the screen; returns the event to send right away, if any
+fn ring_bell(state: &mut SharedState, pane_id: u32, focused: bool) -> Option<BellEvent> {
+    let urgency = if focused {
+        BellUrgency::Normal
+    } else {
+        BellUrgency::High
+    };
+    let now_ms = state.started_at.elapsed().as_millis() as u64;
+    state.bells.ring(pane_id, urgency, now_ms)
+}
+
+fn broadcast_bells(clients: &HashMap<u64, ClientConnection>, bells: Vec<BellEvent>) {
+    for bell in bells {
+        for (remote_id, client) in clients.iter().filter(|(_, client)| client.bells) {
+            let msg = StreamEnvelope {
+                msg: Some(stream_envelope::Msg::Bell(bell.clone())),
+                envelope_seq: 0,
             };
+            if let Err(mpsc::error::TrySendError::Full(_)) = client.sender.try_send(msg) {
+                log::debug!("Client {} channel full, dropping BellEvent", remote_id);
+            }
+        }
+    }
+}
 
-            let (tx, rx) = mpsc::channel::<StreamEnvelope>(CLIENT_CHANNEL_SIZE);
-            spawn_client_sender_task(remote_id, send, rx);
-            clients.insert(
-                remote_id,
-                ClientConnection {
-                    sender: tx,
-                    remote_id,
-                    connection,
-                    max_datagram_size,
-                    datagrams_negotiated,
-                    datagram_task_handle,
+/// The lease status to send, if a controller holds the lease and the last one went
+/// out at least `LEASE_STATUS_INTERVAL_MS` ago
+fn due_lease_status(
+    state: &mut SharedState,
+    now_ms: u64,
+    lease_expiry_enabled: bool,
+) -> Option<LeaseStatus> {
+    if state
+        .lease_status_sent_ms
+        .is_some_and(|sent_ms| now_ms.saturating_sub(sent_ms) < LEASE_STATUS_INTERVAL_MS)
+    {
+        return None;
+    }
+    let status = state
+        .manager
+        .session()
+        .lease_manager
+        .lease_status(lease_expiry_enabled)?;
+    state.lease_status_sent_ms = Some(now_ms);
+    Some(status)
+}
+
+fn broadcast_lease_status(clients: &HashMap<u64, ClientConnection>, status: LeaseStatus) {
+    for (remote_id, client) in clients.iter().filter(|(_, client)| client.lease_status) {
+        let msg = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::LeaseStatus(status.clone())),
+            envelope_seq: 0,
+        };
+        if let Err(mpsc::error::TrySendError::Full(_)) = client.sender.try_send(msg) {
+            log::debug!("Client {} channel full, dropping LeaseStatus", remote_id);
+        }
+    }
+}
+
+fn broadcast_lease_revoked(
+    clients: &HashMap<u64, ClientConnection>,
+    lease_id: u64,
+    reason: &str,
+    takeover: Option<Takeover>,
+) {
+    for (remote_id, client) in clients {
+        let msg = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::LeaseRevoked(LeaseRevoked {
+                lease_id,
+                reason: reason.to_string(),
+                takeover: takeover.clone(),
+            })),
+            envelope_seq: 0,
+        };
+        if let Err(mpsc::error::TrySendError::Full(_)) = client.sender.try_send(msg) {
+            log::warn!("Client {} channel full, dropping LeaseRevoked", remote_id);
+        }
+    }
+}
+
+/// Tells the session about remote clients attaching, detaching or taking control,
+/// so the local UI can show who is watching and who is in control, and panes answer
+/// terminal queries for the controller's terminal.
+fn report_presence(state: &mut SharedState) {
+    let controller = state
+        .manager
+        .session()
+        .lease_manager
+        .get_current_lease()
+        .map(|lease| lease.owner_client_id);
+    if let Some(clients) = state.presence.poll_change(controller) {
+        if let Err(e) = state.events.remote_clients_changed(clients) {
+            log::warn!("Failed to report remote clients to screen: {}", e);
+        }
+    }
+    if let Some(answers) = state.terminal_answers.poll_change(controller) {
+        if let Err(e) = state.events.terminal_answers_changed(answers) {
+            log::warn!("Failed to report terminal answers to screen: {}", e);
+        }
+    }
+}
+
+/// Ask a screen that has been quiet for a while to show it is still there.
+fn send_heartbeat_if_due(state: &mut SharedState, now: std::time::Instant) {
+    if !state.health.heartbeat_due(now) {
+        return;
+    }
+    let sent = state.events.heartbeat();
+    if let Err(e) = &sent {
+        log::warn!("Failed to send heartbeat to screen: {}", e);
+    }
+    state.health.record_heartbeat_sent(sent.is_ok(), now);
+}
+
+async fn handle_health_check(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &HashMap<u64, ClientConnection>,
+) {
+    let transition = {
+        let mut state = shared_state.write().await;
+        let now = std::time::Instant::now();
+        send_heartbeat_if_due(&mut state, now);
+        state.health.poll_transition(now)
+    };
+    let Some((session_state, issue)) = transition else {
+        return;
+    };
+
+    let reason = issue.map(|i| i.describe()).unwrap_or("recovered");
+    match session_state {
+        SessionState::Degraded => log::error!("Remote session degraded: {}", reason),
+        _ => log::info!("Remote session healthy again"),
+    }
+
+    for (remote_id, client) in clients {
+        let msg = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::SessionStateChanged(
+                SessionStateChanged {
+                    state: session_state.into(),
+                    reason: reason.to_string(),
                 },
+            )),
+            envelope_seq: 0,
+        };
+        if let Err(mpsc::error::TrySendError::Full(_)) = client.sender.try_send(msg) {
+            log::warn!(
+                "Client {} channel full, dropping SessionStateChanged",
+                remote_id
             );
-            log::info!(
-                "Remote client {} added to active clients (total: {})",
-                remote_id,
-                clients.len()
-            );
-        },
-        ConnectionEvent::ClientDisconnected { remote_id } => {
-            if let Some(client) = clients.remove(&remote_id) {
-                if let Some(handle) = client.datagram_task_handle {
-                    handle.abort();
+        }
+    }
+}
+
+/// Ping clients that haven't been sent a frame for an interval, and close those whose
+/// pongs stopped arriving.
+async fn handle_keepalive_tick(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+) {
+    let now = std::time::Instant::now();
+    let mut timed_out = Vec::new();
+    for (remote_id, client) in clients.iter_mut() {
+        let Some(keepalive) = client.keepalive.as_mut() else {
+            continue;
+        };
+        match keepalive.poll(now) {
+            Some(KeepAliveAction::Ping(ping)) => {
+                let msg = StreamEnvelope {
+                    msg: Some(stream_envelope::Msg::Ping(ping)),
+                    envelope_seq: 0,
+                };
+                // A full channel means frames are queued, which keeps the link busy anyway
+                if let Err(e) = client.sender.try_send(msg) {
+                    log::debug!("Client {} ping not sent: {}", remote_id, e);
                 }
-            }
-            let mut state = shared_state.write().await;
-            state.manager.session_mut().remove_client(remote_id);
-            log::info!(
-                "Remote client {} removed (total: {})",
-                remote_id,
-                clients.len()
-            );
-        },
-        ConnectionEvent::InputReceived { remote_id, input } => {
-            // M2: Clone data needed, release lock before network I/O
-            let (is_controller, process_result, active_zellij_client, to_screen) = {
+            },
+            Some(KeepAliveAction::TimedOut) => timed_out.push(*remote_id),
+            None => {},
+        }
+    }
+
+    for remote_id in timed_out {
+        log::warn!(
+            "Remote client {} stopped answering pings, closing",
+            remote_id
+        );
+        if let Some(client) = clients.get(&remote_id) {
+            client
+                .connection
+                .close(wtransport::VarInt::from_u32(0), b"keep-alive timeout");
+        }
+        remove_client(shared_state, clients, remote_id).await;
+    }
+}
+
+/// Close clients with a write that has waited past the stall timeout, in case their
+/// sender task couldn't report it
+async fn check_stalled_writers(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+) {
+    let stall_timeout = shared_state.read().await.writer_stall_timeout;
+    let now = std::time::Instant::now();
+    let stalled: Vec<u64> = clients
+        .iter()
+        .filter(|(_, client)| {
+            client
+                .write_timers
+                .iter()
+                .any(|timer| timer.is_stalled(now, stall_timeout))
+        })
+        .map(|(remote_id, _)| *remote_id)
+        .collect();
+    for remote_id in stalled {
+        close_stalled_writer(shared_state, clients, remote_id).await;
+    }
+}
+
+/// Time how long a task spawned now waits for a runtime worker, as every connection
+/// and sender task is waiting about as long, and warn when it's long enough for
+/// clients to notice.
+fn probe_runtime(shared_state: &Arc<RwLock<SharedState>>) {
+    let shared_state = shared_state.clone();
+    let spawned_at = std::time::Instant::now();
+    tokio::spawn(async move {
+        let queue_delay = spawned_at.elapsed();
+        let mut state = shared_state.write().await;
+        if state.runtime_probe.record(queue_delay) {
+            log::warn!(
+                "Remote runtime workers are saturated: a task waited {}ms to run ({} slow probes, worst {}ms)",
+                queue_delay.as_millis(),
+                state.runtime_probe.slow_probes(),
+                state.runtime_probe.worst().as_millis()
+            );
+        } else {
+            log::trace!("Remote runtime queue delay {:?}", queue_delay);
+        }
+    });
+}
+
+/// Pass the frame stage timings on to the session when they changed, for `zellij
+/// remote status --verbose`, logging them with `ZELLIJ_REMOTE_LOG_FRAME_STATS`.
+async fn report_frame_stages(shared_state: &Arc<RwLock<SharedState>>) {
+    let mut state = shared_state.write().await;
+    let stages = state.frame_stages.summary();
+    if stages == state.frame_stages_reported {
+        return;
+    }
+    if TestKnobs::get().log_frame_stats {
+        let line = stages
+            .iter()
+            .map(|stage| {
+                format!(
+                    "{}={}us/{}us/{}",
+                    stage.name, stage.average_us, stage.worst_us, stage.over_budget
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        log::info!("[FRAME_STAGES] avg/worst/over_budget {}", line);
+    }
+    if let Err(e) = state.events.frame_stages_changed(stages.clone()) {
+        log::warn!("Failed to report frame stage timings to screen: {}", e);
+    }
+    state.frame_stages_reported = stages;
+}
+
+/// Pass on the input latency percentiles connected clients last reported in
+/// `LinkStats`, when they changed, for `zellij remote status --verbose`. Clients drop
+/// out of the list when they disconnect.
+fn report_input_latency(state: &mut SharedState) {
+    let latency: Vec<RemoteInputLatency> = state
+        .presence
+        .clients(None)
+        .into_iter()
+        .filter_map(|client| {
+            let percentiles = state.manager.session().input_latency(client.remote_id)?;
+            Some(RemoteInputLatency {
+                client: client.name,
+                p50_ms: percentiles.p50_ms,
+                p95_ms: percentiles.p95_ms,
+                p99_ms: percentiles.p99_ms,
+                samples: percentiles.samples,
+            })
+        })
+        .collect();
+    if latency == state.input_latency_reported {
+        return;
+    }
+    if let Err(e) = state.events.input_latency_changed(latency.clone()) {
+        log::warn!("Failed to report client input latency to screen: {}", e);
+    }
+    state.input_latency_reported = latency;
+}
+
+/// Count and log an envelope from a client that wasn't the one after the last
+async fn record_envelope_seq_gap(
+    shared_state: &Arc<RwLock<SharedState>>,
+    remote_id: u64,
+    gap: SequenceGap,
+) {
+    let total = {
+        let mut state = shared_state.write().await;
+        state.envelope_seq_gaps = state.envelope_seq_gaps.wrapping_add(1);
+        state.envelope_seq_gaps
+    };
+    log::warn!(
+        "Remote client {} sent envelope {} where {} was expected ({} envelope sequence gaps)",
+        remote_id,
+        gap.received,
+        gap.expected,
+        total
+    );
+}
+
+/// Close a client that stopped reading, which fails the writes its sender tasks are
+/// blocked in. Its resume state is kept, so it can reconnect and carry on.
+async fn close_stalled_writer(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+    remote_id: u64,
+) {
+    let Some(client) = clients.get(&remote_id) else {
+        return;
+    };
+    client
+        .connection
+        .close(wtransport::VarInt::from_u32(0), b"write stalled");
+    let (timeout, total) = {
+        let mut state = shared_state.write().await;
+        state.stalled_writer_disconnects = state.stalled_writer_disconnects.wrapping_add(1);
+        (state.writer_stall_timeout, state.stalled_writer_disconnects)
+    };
+    log::warn!(
+        "Remote client {} stopped reading for {}ms, closing ({} stalled writer disconnects)",
+        remote_id,
+        timeout.as_millis(),
+        total
+    );
+    remove_client(shared_state, clients, remote_id).await;
+}
+
+async fn remove_client(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+    remote_id: u64,
+) -> Option<ClientConnection> {
+    let mut client = clients.remove(&remote_id);
+    if let Some(handle) = client.as_mut().and_then(|c| c.datagram_task_handle.take()) {
+        handle.abort();
+    }
+    let mut state = shared_state.write().await;
+    if let Some(latency) = state.manager.session().input_latency(remote_id) {
+        log::info!(
+            "Remote client {} input latency p50={}ms p95={}ms p99={}ms ({} samples)",
+            remote_id,
+            latency.p50_ms,
+            latency.p95_ms,
+            latency.p99_ms,
+            latency.samples
+        );
+    }
+    let mismatches = state.manager.session().frame_hash_mismatches(remote_id);
+    if mismatches > 0 {
+        log::warn!(
+            "Remote client {} had {} frame hash mismatches",
+            remote_id,
+            mismatches
+        );
+    }
+    let divergences = state.manager.session().row_checksum_mismatches(remote_id);
+    if divergences > 0 {
+        log::warn!(
+            "Remote client {} diverged {} times (row checksums or lost baselines)",
+            remote_id,
+            divergences
+        );
+    }
+    let fallbacks = state.manager.session().delta_fallbacks(remote_id);
+    if fallbacks > 0 {
+        log::info!(
+            "Remote client {} had {} deltas sent as snapshots for size",
+            remote_id,
+            fallbacks
+        );
+    }
+    state.manager.session_mut().remove_client(remote_id);
+    state.control_throttle.remove_client(remote_id);
+    let dropped_approvals = state
+        .manager
+        .approvals_mut()
+        .map(|approvals| approvals.remove_client(remote_id))
+        .unwrap_or_default();
+    for request_id in dropped_approvals {
+        if let Err(e) = state.events.approval_resolved(request_id) {
+            log::warn!("Failed to report approval {} dropped: {}", request_id, e);
+        }
+    }
+    state.presence.remove_client(remote_id);
+    state.terminal_answers.remove_client(remote_id);
+    report_presence(&mut state);
+    // A controller leaving ends its lease; the others hear about it right away
+    let events = state.manager.session_mut().drain_events();
+    deliver_session_events(clients, events);
+    if let (Some(audit), Some(_)) = (&state.audit, &client) {
+        audit.emit(AuditEvent::ClientDisconnected {
+            client_id: remote_id,
+            token_id: None,
+        });
+    }
+    log::info!(
+        "Remote client {} removed (total: {})",
+        remote_id,
+        clients.len()
+    );
+    client
+}
+
+/// Tell every client the session is closing, give their queued messages a moment to
+/// go out, then close the connections.
+async fn close_all_clients(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+    reason: &str,
+) {
+    let remote_ids: Vec<u64> = clients.keys().copied().collect();
+    let mut closing = Vec::with_capacity(remote_ids.len());
+    for remote_id in remote_ids {
+        let Some(client) = remove_client(shared_state, clients, remote_id).await else {
+            continue;
+        };
+        let msg = StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ProtocolError(ProtocolError {
+                code: protocol_error::Code::SessionClosing as i32,
+                message: format!("Closing: {}", reason),
+                fatal: true,
+                retry_after_ms: 0,
+            })),
+            envelope_seq: 0,
+        };
+        if let Err(e) = client.sender.try_send(msg) {
+            log::debug!("Client {} SessionClosing not sent: {}", remote_id, e);
+        }
+        // Dropping the sender lets the sender task drain the queue and finish the stream
+        closing.push((client.connection, client.sender_task_handle));
+    }
+
+    let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_millis(CLOSE_GRACE_MS);
+    for (connection, sender_task) in closing {
+        let _ = tokio::time::timeout_at(deadline, sender_task).await;
+        connection.close(wtransport::VarInt::from_u32(0), b"session closing");
+    }
+}
+
+struct ClientGuard {
+    remote_id: u64,
+    shared_state: Arc<RwLock<SharedState>>,
+    conn_event_tx: mpsc::Sender<ConnectionEvent>,
+    disarmed: bool,
+}
+
+impl ClientGuard {
+    fn new(
+        remote_id: u64,
+        shared_state: Arc<RwLock<SharedState>>,
+        conn_event_tx: mpsc::Sender<ConnectionEvent>,
+    ) -> Self {
+        Self {
+            remote_id,
+            shared_state,
+            conn_event_tx,
+            disarmed: false,
+        }
+    }
+
+    fn disarm(&mut self) {
+        self.disarmed = true;
+    }
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        if self.disarmed {
+            return;
+        }
+        let remote_id = self.remote_id;
+        let shared_state = self.shared_state.clone();
+        let conn_event_tx = self.conn_event_tx.clone();
+        tokio::spawn(async move {
+            {
                 let mut state = shared_state.write().await;
-                let is_controller = state
-                    .manager
-                    .session_mut()
-                    .lease_manager
-                    .is_controller(remote_id);
-                if !is_controller {
-                    (false, None, None, None)
-                } else {
-                    let result = state.manager.session_mut().process_input(remote_id, &input);
-                    (
-                        true,
-                        Some(result),
-                        state.active_zellij_client,
-                        Some(state.to_screen.clone()),
-                    )
-                }
-            };
-            // Lock released here
+                state.manager.session_mut().remove_client(remote_id);
+                log::info!("ClientGuard cleanup: removed client {}", remote_id);
+            }
+            if let Err(e) = conn_event_tx
+                .send(ConnectionEvent::ClientDisconnected { remote_id })
+                .await
+            {
+                log::warn!(
+                    "Failed to send ClientDisconnected during guard cleanup: {}",
+                    e
+                );
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    connection: wtransport::Connection,
+    shared_state: Arc<RwLock<SharedState>>,
+    conn_event_tx: mpsc::Sender<ConnectionEvent>,
+    token_provider: Option<Arc<dyn TokenProvider>>,
+    snapshot_token: Option<TokenHash>,
+) -> Result<()> {
+    let (mut send, mut recv) = connection.accept_bi().await?;
+    let remote_id = REMOTE_CLIENT_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let (client_hello, mut buffer) = match read_client_hello(&mut recv).await? {
+        HelloRead::Hello(client_hello, buffer) => (client_hello, buffer),
+        HelloRead::Malformed(reason) => {
+            log::warn!("Refusing remote client {}: {}", remote_id, reason);
+            refuse_client(
+                &mut send,
+                false,
+                handshake_error(protocol_error::Code::BadMessage, reason.clone(), 0),
+            )
+            .await?;
+            anyhow::bail!("handshake failed: {}", reason);
+        },
+    };
+    log::info!(
+        "Received ClientHello from {} (remote_id={})",
+        client_hello.client_name,
+        remote_id
+    );
+    // Refusals answer an AttachRequest sent along with the hello before the error
+    let attach_requested = matches!(
+        decode_envelope(&mut buffer.clone()),
+        Ok(Some(StreamEnvelope {
+            msg: Some(stream_envelope::Msg::AttachRequest(_)),
+            ..
+        }))
+    );
+
+    if let Err(error) = check_client_version(&client_hello) {
+        log::warn!(
+            "Refusing remote client {} ({}): {}",
+            remote_id,
+            client_hello.client_name,
+            error.message
+        );
+        refuse_client(&mut send, attach_requested, error).await?;
+        anyhow::bail!("attach refused: unsupported protocol version");
+    }
+
+    let audit = shared_state.read().await.audit.clone();
+    let client_ip = Some(connection.remote_address().ip());
+    let Some(role) = authorize(
+        token_provider.as_deref(),
+        snapshot_token.as_ref(),
+        &client_hello.bearer_token,
+    ) else {
+        log::warn!(
+            "Authentication failed for remote client {} ({}): invalid bearer token",
+            remote_id,
+            client_hello.client_name
+        );
+        if let Some(audit) = &audit {
+            audit.emit(AuditEvent::AuthenticationFailed {
+                client_id: remote_id,
+                client_ip,
+                token_id: None,
+                reason: "invalid bearer token".to_string(),
+            });
+        }
+        let error = handshake_error(
+            protocol_error::Code::Unauthorized,
+            "Invalid bearer token".to_string(),
+            0,
+        );
+        refuse_client(&mut send, attach_requested, error).await?;
+        anyhow::bail!("authentication failed: invalid bearer token");
+    };
+    if token_provider.is_some() {
+        log::debug!(
+            "Remote client {} authenticated successfully as {:?}",
+            remote_id,
+            role
+        );
+    }
+    if let Some(audit) = &audit {
+        // The server has a single session token, so there's no token id to name
+        audit.emit(AuditEvent::ClientAuthenticated {
+            client_id: remote_id,
+            client_name: client_hello.client_name.clone(),
+            client_ip,
+            token_id: None,
+            role,
+        });
+    }
+
+    let health_issue = shared_state
+        .read()
+        .await
+        .health
+        .issue(std::time::Instant::now());
+    if let Some(issue) = health_issue {
+        log::warn!(
+            "Refusing remote client {} ({}): session degraded ({})",
+            remote_id,
+            client_hello.client_name,
+            issue.describe()
+        );
+        let error = handshake_error(
+            protocol_error::Code::SessionDegraded,
+            format!("Session degraded: {}", issue.describe()),
+            DEGRADED_RETRY_AFTER_MS,
+        );
+        refuse_client(&mut send, attach_requested, error).await?;
+        anyhow::bail!("attach refused: session degraded");
+    }
+
+    // Whatever followed the hello has to decode before the client is recorded
+    let next = match decode_envelope(&mut buffer.clone()) {
+        Ok(next) => next,
+        Err(e) => {
+            log::warn!(
+                "Refusing remote client {} ({}): {}",
+                remote_id,
+                client_hello.client_name,
+                e
+            );
+            let error = handshake_error(protocol_error::Code::BadMessage, e.to_string(), 0);
+            refuse_client(&mut send, false, error).await?;
+            return Err(e.context("handshake failed"));
+        },
+    };
+
+    // A capture is answered and closed before anything about the client is recorded
+    let snapshot_requested = matches!(
+        next,
+        Some(StreamEnvelope {
+            msg: Some(stream_envelope::Msg::SnapshotRequest(_)),
+            ..
+        })
+    );
+    if snapshot_requested || role == ClientRole::Snapshot {
+        return serve_capture(&mut send, &shared_state, &client_hello, remote_id).await;
+    }
+
+    // An AttachRequest sent along with the ClientHello decides the first frame
+    let mut attach_request = match next {
+        Some(StreamEnvelope {
+            msg: Some(stream_envelope::Msg::AttachRequest(request)),
+            ..
+        }) => {
+            decode_envelope(&mut buffer)?;
+            Some(request)
+        },
+        _ => None,
+    };
+    if let Some(request) = attach_request.as_mut() {
+        let size_limits = shared_state.read().await.size_limits;
+        if let Err(e) = check_attach_size(&size_limits, request) {
+            log::warn!(
+                "Refusing remote client {} ({}): {}",
+                remote_id,
+                client_hello.client_name,
+                e
+            );
+            let error = handshake_error(protocol_error::Code::BadMessage, e.to_string(), 0);
+            refuse_client(&mut send, true, error).await?;
+            anyhow::bail!("attach refused: {}", e);
+        }
+    }
+
+    let mut guard = ClientGuard::new(remote_id, shared_state.clone(), conn_event_tx.clone());
+    let compression = negotiate_compression(&client_hello.supported_codecs);
+
+    let max_message_bytes = client_hello
+        .capabilities
+        .as_ref()
+        .map_or(0, |c| negotiate_message_bytes(c.max_message_bytes));
+
+    {
+        let mut state = shared_state.write().await;
+        let window_bounds = WindowBounds::negotiate(
+            WindowBounds::default(),
+            client_hello.min_render_window,
+            client_hello.max_render_window,
+        );
+        state
+            .manager
+            .session_mut()
+            .add_client(remote_id, zellij_remote_protocol::DEFAULT_RENDER_WINDOW);
+        state
+            .manager
+            .session_mut()
+            .set_window_bounds(remote_id, window_bounds);
+        let client_supports_text_mode = client_hello
+            .capabilities
+            .as_ref()
+            .is_some_and(|c| c.supports_text_mode);
+        state
+            .manager
+            .session_mut()
+            .set_text_mode_allowed(remote_id, client_supports_text_mode);
+        let client_supports_frame_hash = client_hello
+            .capabilities
+            .as_ref()
+            .is_some_and(|c| c.supports_frame_hash);
+        state
+            .manager
+            .session_mut()
+            .set_frame_hash_enabled(remote_id, client_supports_frame_hash);
+        let client_supports_row_checksums = client_hello
+            .capabilities
+            .as_ref()
+            .is_some_and(|c| c.supports_row_checksums);
+        state
+            .manager
+            .session_mut()
+            .set_row_checksums_enabled(remote_id, client_supports_row_checksums);
+        let client_supports_style_retention = client_hello
+            .capabilities
+            .as_ref()
+            .is_some_and(|c| c.supports_style_retention);
+        state
+            .manager
+            .session_mut()
+            .set_style_retention_enabled(remote_id, client_supports_style_retention);
+        let client_supports_styled_underlines = client_hello
+            .capabilities
+            .as_ref()
+            .is_some_and(|c| c.supports_styled_underlines);
+        state
+            .manager
+            .session_mut()
+            .set_styled_underlines(remote_id, client_supports_styled_underlines);
+        let client_supports_line_size = client_hello
+            .capabilities
+            .as_ref()
+            .is_some_and(|c| c.supports_line_size);
+        state
+            .manager
+            .session_mut()
+            .set_line_sizes(remote_id, client_supports_line_size);
+        state.manager.session_mut().set_color_transform(
+            remote_id,
+            client_hello.color_transform.clone().unwrap_or_default(),
+        );
+        // On unless the client turns it off
+        state.manager.session_mut().set_flash_guard(
+            remote_id,
+            &client_hello.flash_guard.clone().unwrap_or_default(),
+        );
+        state
+            .manager
+            .session_mut()
+            .set_debug_frame_info_enabled(remote_id, client_hello.debug_frame_info);
+        // Named before requesting control, so the lease and denials carry the name
+        state
+            .presence
+            .add_client(remote_id, &client_hello.client_name);
+        let client_name = state
+            .presence
+            .name(remote_id)
+            .unwrap_or_default()
+            .to_string();
+        state
+            .manager
+            .session_mut()
+            .set_client_name(remote_id, &client_name);
+
+        let session = state.manager.session_mut();
+        let lease = session.lease_manager.request_control(
+            remote_id,
+            Some(DisplaySize { cols: 80, rows: 24 }),
+            false,
+        );
+        // Under LastWriterWins a new client takes control from the current one, who is
+        // told on the next lease tick
+        session.queue_takeover();
+
+        let lease_info = match lease {
+            LeaseResult::Granted(l) => Some(l),
+            LeaseResult::Denied { .. } => session.lease_manager.get_current_lease(),
+        };
+        if let Some(locale) = &client_hello.locale {
+            state.presence.set_locale(remote_id, locale);
+        }
+        if let Some(answers) = &client_hello.terminal_answers {
+            state.terminal_answers.add_client(remote_id, answers);
+        }
+        report_presence(&mut state);
+
+        // Applied before the resume token is issued, which a Fresh attach would revoke
+        let attach_response =
+            attach_request.map(|request| attach_from_hello(&mut state, remote_id, request));
+
+        let resume_token = state.manager.session_mut().generate_resume_token(remote_id);
+        let session_name = state.session_name.clone();
+        let keepalive_interval_ms = state.keepalive_interval_ms.unwrap_or(0);
+
+        let mut server_hello = build_server_hello(
+            &client_hello,
+            remote_id,
+            lease_info,
+            resume_token,
+            &session_name,
+            window_bounds,
+            keepalive_interval_ms,
+        );
+        server_hello.alternate_endpoints = state.alternate_endpoints.clone();
+        server_hello.extensions = state.extensions.negotiate(&client_hello.extensions);
+        if state
+            .manager
+            .session()
+            .predates_restore(&client_hello.resume_token)
+        {
+            log::info!(
+                "Remote client {} is reattaching after a server restart",
+                remote_id
+            );
+            server_hello.session_state = SessionState::Resurrected.into();
+        }
+        let encoded = encode_envelope(&StreamEnvelope {
+            msg: Some(stream_envelope::Msg::ServerHello(server_hello)),
+            envelope_seq: 0,
+        })?;
+        send.write_all(&encoded).await?;
+        log::info!("Sent ServerHello to remote client {}", remote_id);
+
+        if let Some(response) = attach_response {
+            let encoded = encode_envelope(&StreamEnvelope {
+                msg: Some(stream_envelope::Msg::AttachResponse(response)),
+                envelope_seq: 0,
+            })?;
+            send.write_all(&encoded).await?;
+        }
+
+        let initial_update = if state.manager.is_streaming_paused() {
+            // The first frame goes out when the local user resumes streaming
+            Some(stream_envelope::Msg::StreamPaused(stream_paused_notice(
+                true,
+            )))
+        } else if let Some(cached) = state.manager.session_mut().take_cached_snapshot(
+            remote_id,
+            (max_message_bytes > 0).then_some(max_message_bytes as usize),
+        ) {
+            // On screen right away; the listener sends the catch-up delta once the
+            // client is added
+            send.write_all(&cached.encoded).await?;
+            log::info!(
+                "Sent preloaded snapshot of state {} to remote client {}",
+                cached.state_id,
+                remote_id
+            );
+            None
+        } else {
+            match state.manager.session_mut().get_render_update(remote_id) {
+                Some(RenderUpdate::Snapshot(snapshot)) => {
+                    Some(stream_envelope::Msg::ScreenSnapshot(snapshot))
+                },
+                // A resumed client already has a baseline
+                Some(RenderUpdate::Delta(delta)) => {
+                    Some(stream_envelope::Msg::ScreenDeltaStream(delta))
+                },
+                None => None,
+            }
+        };
+        if let Some(msg) = initial_update {
+            let encoded = encode_envelope_compressed(
+                &StreamEnvelope {
+                    msg: Some(msg),
+                    envelope_seq: 0,
+                },
+                &compression,
+            )?;
+            send.write_all(&encoded).await?;
+            log::info!("Sent initial frame to remote client {}", remote_id);
+        }
+    }
+
+    let client_supports_lanes = client_hello
+        .capabilities
+        .as_ref()
+        .is_some_and(|c| c.supports_priority_lanes);
+    let push_streams = client_hello
+        .capabilities
+        .as_ref()
+        .is_some_and(|c| c.supports_push_streams);
+    let lanes = if client_supports_lanes {
+        let lanes = open_lanes(&connection, push_streams).await?;
+        log::info!("Opened priority lanes to remote client {}", remote_id);
+        Some(lanes)
+    } else {
+        None
+    };
+
+    guard.disarm();
+
+    let client_supports_datagrams = client_hello
+        .capabilities
+        .as_ref()
+        .map(|c| c.supports_datagrams)
+        .unwrap_or(false);
+    let client_supports_bell = client_hello
+        .capabilities
+        .as_ref()
+        .is_some_and(|c| c.supports_bell);
+    let client_supports_lease_status = client_hello
+        .capabilities
+        .as_ref()
+        .is_some_and(|c| c.supports_lease_status);
+    let client_supports_pane_layout = client_hello
+        .capabilities
+        .as_ref()
+        .is_some_and(|c| c.supports_pane_layout);
+    let client_supports_keybinding_info = client_hello
+        .capabilities
+        .as_ref()
+        .is_some_and(|c| c.supports_keybinding_info);
+    conn_event_tx
+        .send(ConnectionEvent::ClientConnected {
+            remote_id,
+            send,
+            lanes,
+            push_streams,
+            compression,
+            connection: connection.clone(),
+            client_supports_datagrams,
+            client_supports_bell,
+            client_supports_lease_status,
+            client_supports_pane_layout,
+            client_supports_keybinding_info,
+            max_message_bytes,
+            conn_event_tx: conn_event_tx.clone(),
+        })
+        .await?;
+
+    let mut sequence = EnvelopeSequenceChecker::new();
+    loop {
+        let mut chunk = [0u8; 4096];
+        match recv.read(&mut chunk).await? {
+            Some(0) | None => {
+                log::info!("Remote client {} stream closed", remote_id);
+                break;
+            },
+            Some(n) => {
+                buffer.extend_from_slice(&chunk[..n]);
+
+                while let Some(envelope) = decode_envelope(&mut buffer)? {
+                    if let Some(gap) = sequence.observe(&envelope) {
+                        record_envelope_seq_gap(&shared_state, remote_id, gap).await;
+                    }
+                    match envelope.msg {
+                        Some(stream_envelope::Msg::InputEvent(input)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::InputReceived { remote_id, input })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::InputBatch(batch)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::InputBatchReceived { remote_id, batch })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::RequestControl(req)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::RequestControl {
+                                    remote_id,
+                                    request: req,
+                                })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::ControlHandoffRequest(request)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::ControlHandoffRequested {
+                                    remote_id,
+                                    request,
+                                })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::ControlHandoffResponse(response)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::ControlHandoffAnswered {
+                                    remote_id,
+                                    response,
+                                })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::RequestSnapshot(request)) => {
+                            log::info!(
+                                "Client {} requested snapshot: reason={:?}",
+                                remote_id,
+                                request.reason
+                            );
+                            conn_event_tx
+                                .send(ConnectionEvent::RequestSnapshot { remote_id, request })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::SetControllerSize(request)) => {
+                            log::info!(
+                                "Client {} set controller size: {:?}",
+                                remote_id,
+                                request.size
+                            );
+                            conn_event_tx
+                                .send(ConnectionEvent::SetControllerSize { remote_id, request })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::FocusPane(request)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::FocusPaneRequested { remote_id, request })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::Pong(pong)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::PongReceived { remote_id, pong })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::LinkStats(stats)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::LinkStatsReceived { remote_id, stats })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::AttachRequest(request)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::AttachRequested { remote_id, request })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::CapabilityUpdate(update)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::CapabilitiesUpdated { remote_id, update })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::ClientVisibility(visibility)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::VisibilityChanged {
+                                    remote_id,
+                                    visible: visibility.visible,
+                                })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::SubscribeRegion(subscribe)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::RegionSubscribed {
+                                    remote_id,
+                                    rows: subscribe.rows,
+                                })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::SwitchSession(request)) => {
+                            conn_event_tx
+                                .send(ConnectionEvent::SwitchSessionRequested {
+                                    remote_id,
+                                    request,
+                                })
+                                .await?;
+                        },
+                        Some(stream_envelope::Msg::Detach(detach)) => {
+                            // Anything after a detach is ignored; the main loop removes
+                            // the client, so no ClientDisconnected follows
+                            conn_event_tx
+                                .send(ConnectionEvent::Detached { remote_id, detach })
+                                .await?;
+                            return Ok(());
+                        },
+
+                        _ => {
+                            log::debug!("Unhandled message from client {}", remote_id);
+                        },
+                    }
+                }
+            },
+        }
+    }
+
+    conn_event_tx
+        .send(ConnectionEvent::ClientDisconnected { remote_id })
+        .await?;
+    Ok(())
+}
+
+/// The role `bearer_token` grants: full control with the session token, one-shot
+/// captures with the snapshot token, and None if it matches neither. Without a
+/// session token every client gets full control.
+fn authorize(
+    token_provider: Option<&dyn TokenProvider>,
+    snapshot_token: Option<&TokenHash>,
+    bearer_token: &[u8],
+) -> Option<ClientRole> {
+    let provider = match token_provider {
+        Some(provider) => provider,
+        None => return Some(ClientRole::Controller),
+    };
+    let session_token_valid = match provider.current_token() {
+        Some(expected) => expected.verify(bearer_token),
+        None => {
+            log::error!("Token provider returned no token, rejecting remote client");
+            false
+        },
+    };
+    if session_token_valid {
+        Some(ClientRole::Controller)
+    } else if snapshot_token.is_some_and(|token| token.verify(bearer_token)) {
+        Some(ClientRole::Snapshot)
+    } else {
+        None
+    }
+}
+
+/// Answer a one-shot capture: the ServerHello, one snapshot of the screen with its
+/// whole style table, then the end of the stream. The client is never attached, so
+/// it takes no lease, gets no resume token and never shows up in presence.
+async fn serve_capture(
+    send: &mut wtransport::SendStream,
+    shared_state: &Arc<RwLock<SharedState>>,
+    client_hello: &ClientHello,
+    remote_id: u64,
+) -> Result<()> {
+    let compression = negotiate_compression(&client_hello.supported_codecs);
+    let max_message_bytes = client_hello
+        .capabilities
+        .as_ref()
+        .map_or(0, |c| negotiate_message_bytes(c.max_message_bytes));
+    let window_bounds = WindowBounds::negotiate(
+        WindowBounds::default(),
+        client_hello.min_render_window,
+        client_hello.max_render_window,
+    );
+
+    let (server_hello, render) = {
+        let mut state = shared_state.write().await;
+        let server_hello = build_server_hello(
+            client_hello,
+            remote_id,
+            None,
+            vec![],
+            &state.session_name,
+            window_bounds,
+            0,
+        );
+        let render = if state.manager.is_streaming_paused() {
+            // The host has hidden the screen from remote clients, captures included
+            vec![StreamEnvelope {
+                msg: Some(stream_envelope::Msg::StreamPaused(stream_paused_notice(
+                    true,
+                ))),
+                envelope_seq: 0,
+            }]
+        } else {
+            let mut snapshot = state.manager.session_mut().capture_snapshot();
+            if !client_hello
+                .capabilities
+                .as_ref()
+                .is_some_and(|c| c.supports_line_size)
+            {
+                for row in snapshot.rows.iter_mut() {
+                    row.line_size = LineSize::Unspecified as i32;
+                }
+            }
+            render_messages(
+                RenderUpdate::Snapshot(snapshot),
+                (max_message_bytes > 0).then_some(max_message_bytes as usize),
+            )
+        };
+        (server_hello, render)
+    };
+
+    send.write_all(&encode_envelope(&StreamEnvelope {
+        msg: Some(stream_envelope::Msg::ServerHello(server_hello)),
+        envelope_seq: 0,
+    })?)
+    .await?;
+    for envelope in &render {
+        send.write_all(&encode_envelope_compressed(envelope, &compression)?)
+            .await?;
+    }
+    send.finish().await.ok();
+    log::info!(
+        "Sent a one-shot capture to remote client {} ({})",
+        remote_id,
+        client_hello.client_name
+    );
+    Ok(())
+}
+
+/// Spawns a per-client sender task that receives from the channel and writes to the stream (M1)
+fn spawn_client_sender_task(
+    remote_id: u64,
+    mut send_stream: wtransport::SendStream,
+    mut receiver: mpsc::Receiver<StreamEnvelope>,
+    compression: watch::Receiver<CompressionConfig>,
+    stall: StallGuard,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut buf = EncodeBuffer::new();
+        // Stamped as written, so a gap the client sees is a write that never happened
+        let mut sequencer = EnvelopeSequencer::new();
+        while let Some(mut msg) = receiver.recv().await {
+            sequencer.stamp(&mut msg);
+            let compression = compression.borrow().clone();
+            let frame = is_frame_update(&msg);
+            let started = std::time::Instant::now();
+            match encode_for_send(msg, compression, &mut buf).await {
+                Ok(encoded) => {
+                    let encoded_at = std::time::Instant::now();
+                    stall.record_frame_stage(frame, FrameStage::Encode, started, encoded_at);
+                    match stall.write(send_stream.write_all(&encoded)).await {
+                        Some(Ok(())) => stall.record_frame_stage(
+                            frame,
+                            FrameStage::SocketWrite,
+                            encoded_at,
+                            std::time::Instant::now(),
+                        ),
+                        Some(Err(e)) => {
+                            log::warn!("Client {} sender task: write failed: {}", remote_id, e);
+                            break;
+                        },
+                        // The main loop closes the connection; finishing would block too
+                        None => return,
+                    }
+                },
+                Err(e) => {
+                    log::error!("Client {} sender task: encode failed: {}", remote_id, e);
+                },
+            }
+        }
+        let _ = tokio::time::timeout(stall.timeout, send_stream.finish()).await;
+        log::debug!("Client {} sender task exiting", remote_id);
+    })
+}
+
+/// Whether a message carries screen contents, whose encoding and writing count
+/// towards the frame stage timings
+fn is_frame_update(msg: &StreamEnvelope) -> bool {
+    matches!(
+        msg.msg,
+        Some(stream_envelope::Msg::ScreenSnapshot(_) | stream_envelope::Msg::ScreenDeltaStream(_))
+    )
+}
+
+/// Encode a message for a sender task. Snapshots, the only messages large enough to
+/// take a while to compress, are encoded on the blocking pool so a big one doesn't
+/// hold up a runtime worker.
+async fn encode_for_send(
+    msg: StreamEnvelope,
+    compression: CompressionConfig,
+    buf: &mut EncodeBuffer,
+) -> Result<std::borrow::Cow<'_, [u8]>> {
+    if matches!(msg.msg, Some(stream_envelope::Msg::ScreenSnapshot(_))) {
+        let encoded =
+            tokio::task::spawn_blocking(move || encode_envelope_compressed(&msg, &compression))
+                .await??;
+        return Ok(std::borrow::Cow::Owned(encoded));
+    }
+    encode_envelope_compressed_into(&msg, &compression, buf).map(std::borrow::Cow::Borrowed)
+}
+
+/// What a sender task needs to give up on a peer that stopped reading, and to time
+/// the frames it encodes and writes
+struct StallGuard {
+    remote_id: u64,
+    timeout: std::time::Duration,
+    timer: WriteTimer,
+    conn_event_tx: mpsc::Sender<ConnectionEvent>,
+    stages: StageRecorder,
+}
+
+impl StallGuard {
+    /// Record `stage` as running from `started` to `finished`, if it was for a frame
+    fn record_frame_stage(
+        &self,
+        frame: bool,
+        stage: FrameStage,
+        started: std::time::Instant,
+        finished: std::time::Instant,
+    ) {
+        if frame {
+            self.stages
+                .record(stage, finished.saturating_duration_since(started));
+        }
+    }
+
+    /// Run `write` on the watchdog's timer. One still waiting after the stall timeout is
+    /// abandoned and the client reported to the main loop, which closes it; returns
+    /// None then.
+    async fn write<E>(
+        &self,
+        write: impl std::future::Future<Output = std::result::Result<(), E>>,
+    ) -> Option<std::result::Result<(), E>> {
+        self.timer.start(std::time::Instant::now());
+        let result = tokio::time::timeout(self.timeout, write).await;
+        self.timer.finish();
+        match result {
+            Ok(result) => Some(result),
+            Err(_) => {
+                let _ = self
+                    .conn_event_tx
+                    .send(ConnectionEvent::WriterStalled {
+                        remote_id: self.remote_id,
+                    })
+                    .await;
+                None
+            },
+        }
+    }
+}
+
+/// Sends each queued message as a push on a new unidirectional stream, one at a time
+fn spawn_client_push_task(
+    remote_id: u64,
+    connection: wtransport::Connection,
+    mut receiver: mpsc::Receiver<StreamEnvelope>,
+    compression: watch::Receiver<CompressionConfig>,
+    stall: StallGuard,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut buf = EncodeBuffer::new();
+        let mut push_id = 0;
+        while let Some(msg) = receiver.recv().await {
+            let kind = match msg.msg {
+                Some(stream_envelope::Msg::ScreenSnapshot(_)) => PushKind::Snapshot,
+                _ => PushKind::Unspecified,
+            };
+            let compression = compression.borrow().clone();
+            let frame = is_frame_update(&msg);
+            let started = std::time::Instant::now();
+            match encode_envelope_compressed_into(&msg, &compression, &mut buf) {
+                Ok(encoded) => {
+                    let encoded_at = std::time::Instant::now();
+                    stall.record_frame_stage(frame, FrameStage::Encode, started, encoded_at);
+                    push_id += 1;
+                    let header = PushHeader {
+                        push_id,
+                        kind: kind as i32,
+                        ..Default::default()
+                    };
+                    match stall.write(send_push(&connection, header, encoded)).await {
+                        Some(Ok(())) => stall.record_frame_stage(
+                            frame,
+                            FrameStage::SocketWrite,
+                            encoded_at,
+                            std::time::Instant::now(),
+                        ),
+                        Some(Err(e)) => {
+                            log::warn!("Client {} push task: push failed: {}", remote_id, e);
+                            break;
+                        },
+                        None => return,
+                    }
+                },
+                Err(e) => {
+                    log::error!("Client {} push task: encode failed: {}", remote_id, e);
+                },
+            }
+        }
+        log::debug!("Client {} push task exiting", remote_id);
+    })
+}
+
+/// Spawns a sender task per lane, plus a push task when `push` is given; the returned
+/// handle finishes once all of them have. Each task gets a timer for the stalled
+/// writer watchdog, returned alongside.
+#[allow(clippy::too_many_arguments)]
+fn spawn_client_sender_tasks(
+    remote_id: u64,
+    send_stream: wtransport::SendStream,
+    lanes: Option<LaneStreams>,
+    push: Option<wtransport::Connection>,
+    compression: watch::Receiver<CompressionConfig>,
+    stall_timeout: std::time::Duration,
+    conn_event_tx: mpsc::Sender<ConnectionEvent>,
+    stages: StageRecorder,
+) -> (LaneSender, tokio::task::JoinHandle<()>, Vec<WriteTimer>) {
+    let origin = std::time::Instant::now();
+    let mut timers = Vec::new();
+    let mut stall_guard = || {
+        let timer = WriteTimer::new(origin);
+        timers.push(timer.clone());
+        StallGuard {
+            remote_id,
+            timeout: stall_timeout,
+            timer,
+            conn_event_tx: conn_event_tx.clone(),
+            stages: stages.clone(),
+        }
+    };
+    let (control_tx, control_rx) = mpsc::channel::<StreamEnvelope>(CLIENT_CHANNEL_SIZE);
+    let mut handles = vec![spawn_client_sender_task(
+        remote_id,
+        send_stream,
+        control_rx,
+        compression.clone(),
+        stall_guard(),
+    )];
+    let sender = match lanes {
+        Some(lanes) => {
+            let (realtime_tx, realtime_rx) = mpsc::channel(CLIENT_CHANNEL_SIZE);
+            let (bulk_tx, bulk_rx) = mpsc::channel(CLIENT_CHANNEL_SIZE);
+            handles.push(spawn_client_sender_task(
+                remote_id,
+                lanes.realtime,
+                realtime_rx,
+                compression.clone(),
+                stall_guard(),
+            ));
+            handles.push(spawn_client_sender_task(
+                remote_id,
+                lanes.bulk,
+                bulk_rx,
+                compression.clone(),
+                stall_guard(),
+            ));
+            LaneSender::with_lanes(control_tx, realtime_tx, bulk_tx)
+        },
+        None => LaneSender::single(control_tx),
+    };
+    let sender = match push {
+        Some(connection) => {
+            let (push_tx, push_rx) = mpsc::channel(CLIENT_CHANNEL_SIZE);
+            handles.push(spawn_client_push_task(
+                remote_id,
+                connection,
+                push_rx,
+                compression,
+                stall_guard(),
+            ));
+            sender.with_push(push_tx)
+        },
+        None => sender,
+    };
+    let handle = tokio::spawn(async move {
+        for handle in handles {
+            let _ = handle.await;
+        }
+    });
+    (sender, handle, timers)
+}
+
+fn spawn_datagram_receive_task(
+    remote_id: u64,
+    connection: wtransport::Connection,
+    conn_event_tx: mpsc::Sender<ConnectionEvent>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match connection.receive_datagram().await {
+                Ok(datagram) => match decode_datagram_envelope(&datagram) {
+                    Ok(envelope) => match envelope.msg {
+                        Some(datagram_envelope::Msg::StateAck(ack)) => {
+                            log::trace!(
+                                "Received StateAck from client {}: last_applied={}",
+                                remote_id,
+                                ack.last_applied_state_id
+                            );
+                            if conn_event_tx
+                                .try_send(ConnectionEvent::StateAckReceived { remote_id, ack })
+                                .is_err()
+                            {
+                                log::debug!(
+                                    "Client {} StateAck channel full or closed, dropping ack",
+                                    remote_id,
+                                );
+                            }
+                        },
+                        Some(datagram_envelope::Msg::Pong(pong)) => {
+                            // A lost pong is covered by the next ping
+                            let _ = conn_event_tx
+                                .try_send(ConnectionEvent::PongReceived { remote_id, pong });
+                        },
+                        _ => {},
+                    },
+                    Err(e) => {
+                        log::trace!("Failed to decode datagram from client {}: {}", remote_id, e);
+                    },
+                },
+                Err(e) => {
+                    log::debug!(
+                        "Datagram receive error for client {} (connection closed?): {}",
+                        remote_id,
+                        e
+                    );
+                    break;
+                },
+            }
+        }
+        log::debug!("Client {} datagram receive task exiting", remote_id);
+    })
+}
+
+async fn handle_connection_event(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+    mut event: ConnectionEvent,
+) -> Result<()> {
+    let attach_requested = matches!(event, ConnectionEvent::AttachRequested { .. });
+    if let Some((remote_id, size)) = requested_size(&mut event) {
+        let size_limits = shared_state.read().await.size_limits;
+        match size_limits.check(size) {
+            Ok(allowed) => *size = allowed,
+            Err(e) => {
+                log::warn!("Remote client {} asked for a bad size: {}", remote_id, e);
+                send_control_messages(clients, remote_id, bad_size_replies(attach_requested, &e));
+                return Ok(());
+            },
+        }
+    }
+    match event {
+        ConnectionEvent::ClientConnected {
+            remote_id,
+            send,
+            lanes,
+            push_streams,
+            compression,
+            connection,
+            client_supports_datagrams,
+            client_supports_bell,
+            client_supports_lease_status,
+            client_supports_pane_layout,
+            client_supports_keybinding_info,
+            max_message_bytes,
+            conn_event_tx,
+        } => {
+            let max_datagram_size = connection.max_datagram_size();
+            let transport_supports = max_datagram_size.is_some();
+            let datagrams_negotiated = transport_supports && client_supports_datagrams;
+
+            if datagrams_negotiated {
+                log::info!(
+                    "Client {} datagrams negotiated, max_size={}",
+                    remote_id,
+                    max_datagram_size.unwrap()
+                );
+            } else {
+                log::info!(
+                    "Client {} datagrams not negotiated (transport={}, client_advertised={})",
+                    remote_id,
+                    transport_supports,
+                    client_supports_datagrams
+                );
+            }
+
+            let datagram_task_handle = if datagrams_negotiated {
+                Some(spawn_datagram_receive_task(
+                    remote_id,
+                    connection.clone(),
+                    conn_event_tx.clone(),
+                ))
+            } else {
+                None
+            };
+
+            let keepalive = shared_state.read().await.keepalive_interval_ms.map(|ms| {
+                KeepAlive::new(
+                    std::time::Duration::from_millis(ms),
+                    std::time::Instant::now(),
+                )
+            });
+
+            let push = push_streams.then(|| connection.clone());
+            // Sender tasks pick up compression changes from a CapabilityUpdate
+            let (compression_tx, compression_rx) = watch::channel(compression);
+            let (stall_timeout, client_name, stages) = {
+                let state = shared_state.read().await;
+                let client_name = state.presence.name(remote_id).unwrap_or_default();
+                (
+                    state.writer_stall_timeout,
+                    client_name.to_string(),
+                    state.frame_stages.clone(),
+                )
+            };
+            let (sender, sender_task_handle, write_timers) = spawn_client_sender_tasks(
+                remote_id,
+                send,
+                lanes,
+                push,
+                compression_rx,
+                stall_timeout,
+                conn_event_tx,
+                stages,
+            );
+            clients.insert(
+                remote_id,
+                ClientConnection {
+                    sender,
+                    remote_id,
+                    connection,
+                    max_datagram_size,
+                    datagrams_negotiated,
+                    datagram_task_handle,
+                    compression: compression_tx,
+                    text_mode: false,
+                    keepalive,
+                    metadata_sent: None,
+                    pane_layout: client_supports_pane_layout,
+                    pane_layout_sent: None,
+                    keybindings: client_supports_keybinding_info,
+                    keybindings_sent: None,
+                    bells: client_supports_bell,
+                    lease_status: client_supports_lease_status,
+                    mouse_moves: MouseMoveCoalescer::new(),
+                    client_name,
+                    max_message_bytes: (max_message_bytes > 0)
+                        .then_some(max_message_bytes as usize),
+                    sender_task_handle,
+                    write_timers,
+                },
+            );
+            log::info!(
+                "Remote client {} added to active clients (total: {})",
+                remote_id,
+                clients.len()
+            );
+            send_catch_up(shared_state, clients, remote_id).await;
+            // Label the window right away rather than on the next change
+            report_session_metadata(shared_state, clients).await;
+            report_pane_layout(shared_state, clients).await;
+            report_keybindings(shared_state, clients).await;
+        },
+        ConnectionEvent::ClientDisconnected { remote_id } => {
+            remove_client(shared_state, clients, remote_id).await;
+        },
+        ConnectionEvent::WriterStalled { remote_id } => {
+            close_stalled_writer(shared_state, clients, remote_id).await;
+        },
+        ConnectionEvent::Detached { remote_id, detach } => {
+            log::info!(
+                "Remote client {} detached: reason={:?} keep_resume_state={}",
+                remote_id,
+                detach.reason(),
+                detach.keep_resume_state
+            );
+            let lease_event = shared_state
+                .write()
+                .await
+                .manager
+                .session_mut()
+                .detach_client(remote_id, &detach);
+            if let Some(event) = lease_event {
+                log::info!("Remote client {} released control: {:?}", remote_id, event);
+            }
+            remove_client(shared_state, clients, remote_id).await;
+        },
+        ConnectionEvent::SwitchSessionRequested { remote_id, request } => {
+            let current_session = shared_state.read().await.session_name.clone();
+            let session_name = request.session_name.clone();
+            let lookup = tokio::task::spawn_blocking(move || {
+                session_endpoint(&session_name, &current_session)
+            })
+            .await;
+            let found = match lookup {
+                Ok(found) => found,
+                Err(e) => {
+                    log::error!("Session lookup for client {} failed: {}", remote_id, e);
+                    Err(SwitchError::NotRunning)
+                },
+            };
+            match &found {
+                Ok(endpoint) => log::info!(
+                    "Redirecting remote client {} to session {:?} on port {}",
+                    remote_id,
+                    request.session_name,
+                    endpoint.port
+                ),
+                Err(e) => log::info!(
+                    "Remote client {} can't switch to session {:?}: {}",
+                    remote_id,
+                    request.session_name,
+                    e
+                ),
+            }
+            send_control_messages(
+                clients,
+                remote_id,
+                vec![session_switch_reply(request.session_name, found)],
+            );
+        },
+        ConnectionEvent::PongReceived { remote_id, pong } => {
+            let keepalive = clients
+                .get_mut(&remote_id)
+                .and_then(|client| client.keepalive.as_mut());
+            if let Some(keepalive) = keepalive {
+                match keepalive.record_pong(&pong, std::time::Instant::now()) {
+                    Some(rtt) => log::trace!("Client {} pong, rtt={:?}", remote_id, rtt),
+                    None => log::debug!(
+                        "Client {} sent pong for unknown ping {}",
+                        remote_id,
+                        pong.ping_id
+                    ),
+                }
+            }
+        },
+        ConnectionEvent::LinkStatsReceived { remote_id, stats } => {
+            log::debug!(
+                "Client {} input latency p50={}ms p95={}ms p99={}ms ({} samples), srtt={}ms",
+                remote_id,
+                stats.input_latency_p50_ms,
+                stats.input_latency_p95_ms,
+                stats.input_latency_p99_ms,
+                stats.input_latency_samples,
+                stats.srtt_ms
+            );
+            shared_state
+                .write()
+                .await
+                .manager
+                .session_mut()
+                .record_link_stats(remote_id, stats);
+        },
+        ConnectionEvent::VisibilityChanged { remote_id, visible } => {
+            let update = {
+                let mut state = shared_state.write().await;
+                let paused = state.manager.is_streaming_paused();
+                let session = state.manager.session_mut();
+                if !session.set_client_visible(remote_id, visible) {
+                    return Ok(());
+                }
+                log::info!(
+                    "Client {} is {}",
+                    remote_id,
+                    if visible { "visible" } else { "hidden" }
+                );
+                // Catch up now rather than on the next frame, which may be a while
+                if visible && !paused {
+                    session.get_render_update(remote_id)
+                } else {
+                    None
+                }
+            };
+            if let Some(update) = update {
+                let frame_size = match &update {
+                    RenderUpdate::Snapshot(snapshot) => snapshot.encoded_len(),
+                    RenderUpdate::Delta(delta) => delta.encoded_len(),
+                };
+                send_render_updates(shared_state, clients, vec![(remote_id, update, frame_size)])
+                    .await;
+            }
+        },
+        ConnectionEvent::RegionSubscribed { remote_id, rows } => {
+            let region = rows.map(|rows| rows.start as usize..rows.end as usize);
+            if region.as_ref().is_some_and(|region| region.is_empty()) {
+                log::warn!("Client {} subscribed to no rows: {:?}", remote_id, rows);
+                return Ok(());
+            }
+            let update = {
+                let mut state = shared_state.write().await;
+                let paused = state.manager.is_streaming_paused();
+                let session = state.manager.session_mut();
+                if !session.set_client_region(remote_id, region.clone()) {
+                    return Ok(());
+                }
+                log::info!("Client {} subscribed to rows {:?}", remote_id, region);
+                // The snapshot with the new rows goes out now, not on the next frame
+                if paused {
+                    None
+                } else {
+                    session.get_render_update(remote_id)
+                }
+            };
+            if let Some(update) = update {
+                let frame_size = match &update {
+                    RenderUpdate::Snapshot(snapshot) => snapshot.encoded_len(),
+                    RenderUpdate::Delta(delta) => delta.encoded_len(),
+                };
+                send_render_updates(shared_state, clients, vec![(remote_id, update, frame_size)])
+                    .await;
+            }
+        },
+        ConnectionEvent::CapabilitiesUpdated { remote_id, update } => {
+            let Some(client) = clients.get_mut(&remote_id) else {
+                return Ok(());
+            };
+            let reply = renegotiate_delivery(client.datagram_task_handle.is_some(), &update);
+            let datagrams = reply
+                .capabilities
+                .as_ref()
+                .is_some_and(|c| c.supports_datagrams);
+            let compression = reply.compression.clone().unwrap_or_default();
+            log::info!(
+                "Client {} updated capabilities: datagrams={}, compression={:?}",
+                remote_id,
+                datagrams,
+                compression
+            );
+            client.datagrams_negotiated = datagrams;
+            client.compression.send_replace(compression);
+            let envelope = StreamEnvelope {
+                msg: Some(stream_envelope::Msg::CapabilityUpdate(reply)),
+                envelope_seq: 0,
+            };
+            if client.sender.try_send(envelope).is_err() {
+                log::warn!(
+                    "Client {} channel full, dropping CapabilityUpdate",
+                    remote_id
+                );
+            }
+        },
+        ConnectionEvent::AttachRequested { remote_id, request } => {
+            let (response, update) = {
+                let mut state = shared_state.write().await;
+                let paused = state.manager.is_streaming_paused();
+                let session = state.manager.session_mut();
+                let response = session.apply_attach_request(remote_id, &request);
+                log::info!(
+                    "Remote client {} re-attached with mode={:?} force_snapshot={}: will_send_snapshot={}",
+                    remote_id,
+                    request.mode(),
+                    request.force_snapshot,
+                    response.will_send_snapshot
+                );
+                let update = if response.ok && !paused {
+                    session.get_render_update(remote_id)
+                } else {
+                    None
+                };
+                (response, update)
+            };
+            send_control_messages(
+                clients,
+                remote_id,
+                vec![stream_envelope::Msg::AttachResponse(response)],
+            );
+            if let Some(update) = update {
+                let frame_size = match &update {
+                    RenderUpdate::Snapshot(snapshot) => snapshot.encoded_len(),
+                    RenderUpdate::Delta(delta) => delta.encoded_len(),
+                };
+                send_render_updates(shared_state, clients, vec![(remote_id, update, frame_size)])
+                    .await;
+            }
+        },
+        ConnectionEvent::InputReceived { remote_id, input } => {
+            let batch = InputBatch {
+                client_time_ms: input.client_time_ms,
+                events: vec![input],
+            };
+            handle_remote_input(shared_state, clients, remote_id, batch).await;
+        },
+        ConnectionEvent::InputBatchReceived { remote_id, batch } => {
+            handle_remote_input(shared_state, clients, remote_id, batch).await;
+        },
+        ConnectionEvent::RequestControl { remote_id, request } => {
+            // M2: Clone result before releasing lock
+            let (responses, events) = {
+                let mut state = shared_state.write().await;
+                let now = std::time::Instant::now();
+                let responses = match state.control_throttle.check(remote_id, now) {
+                    ControlRequestDecision::Debounced => {
+                        log::debug!("Debounced control request from client {}", remote_id);
+                        return Ok(());
+                    },
+                    ControlRequestDecision::Muted { remaining } => {
+                        vec![control_muted_error(remaining)]
+                    },
+                    ControlRequestDecision::Allow => {
+                        if needs_approval(&state, remote_id, &request) {
+                            ask_approval(&mut state, remote_id, request, now);
+                            // Answered once the local user decides, or the request expires
+                            return Ok(());
+                        }
+                        grant_or_deny_control(&mut state, remote_id, &request, now)
+                    },
+                };
+                // A takeover is announced now, not on the next lease tick
+                (responses, state.manager.session_mut().drain_events())
+            };
+            // Lock released here
+
+            send_control_messages(clients, remote_id, responses);
+            deliver_session_events(clients, events);
+        },
+        ConnectionEvent::ControlHandoffRequested { remote_id, request } => {
+            let messages = {
+                let mut state = shared_state.write().await;
+                let now = std::time::Instant::now();
+                match state.control_throttle.check(remote_id, now) {
+                    ControlRequestDecision::Debounced => {
+                        log::debug!("Debounced handoff request from client {}", remote_id);
+                        return Ok(());
+                    },
+                    ControlRequestDecision::Muted { remaining } => {
+                        vec![(remote_id, control_muted_error(remaining))]
+                    },
+                    ControlRequestDecision::Allow => {
+                        let result = state
+                            .manager
+                            .session_mut()
+                            .lease_manager
+                            .request_handoff(remote_id, request.desired_size.clone());
+                        match result {
+                            HandoffRequestResult::Granted(lease) => {
+                                log::info!("Granted control to remote client {}", remote_id);
+                                state.control_throttle.record_grant(remote_id);
+                                report_presence(&mut state);
+                                vec![(
+                                    remote_id,
+                                    stream_envelope::Msg::GrantControl(GrantControl {
+                                        lease: Some(lease),
+                                    }),
+                                )]
+                            },
+                            HandoffRequestResult::Pending {
+                                handoff_id,
+                                controller,
+                                timeout_ms,
+                            } => {
+                                log::info!(
+                                    "Asking remote client {} to hand control to {} (handoff {})",
+                                    controller,
+                                    remote_id,
+                                    handoff_id
+                                );
+                                vec![(
+                                    controller,
+                                    stream_envelope::Msg::ControlHandoffRequest(
+                                        ControlHandoffRequest {
+                                            handoff_id,
+                                            requester_client_id: remote_id,
+                                            timeout_ms,
+                                            ..request
+                                        },
+                                    ),
+                                )]
+                            },
+                            HandoffRequestResult::Denied {
+                                reason,
+                                current_lease,
+                            } => {
+                                log::info!(
+                                    "Denied handoff to remote client {}: {}",
+                                    remote_id,
+                                    reason
+                                );
+                                vec![(
+                                    remote_id,
+                                    stream_envelope::Msg::DenyControl(DenyControl {
+                                        reason,
+                                        lease: current_lease,
+                                    }),
+                                )]
+                            },
+                        }
+                    },
+                }
+            };
+
+            for (client_id, message) in messages {
+                send_control_messages(clients, client_id, vec![message]);
+            }
+        },
+        ConnectionEvent::ControlHandoffAnswered {
+            remote_id,
+            response,
+        } => {
+            let outcome = {
+                let mut state = shared_state.write().await;
+                let outcome = state
+                    .manager
+                    .session_mut()
+                    .lease_manager
+                    .respond_to_handoff(remote_id, response.handoff_id, response.approved);
+                if outcome.is_none() {
+                    log::debug!(
+                        "Ignoring answer from client {} to handoff {} that is no longer pending",
+                        remote_id,
+                        response.handoff_id
+                    );
+                }
+                record_handoff_outcome(&mut state, outcome.as_ref());
+                outcome
+            };
+            if let Some(outcome) = outcome {
+                deliver_handoff_outcome(clients, outcome);
+            }
+        },
+        ConnectionEvent::RequestSnapshot { remote_id, request } => {
+            log::info!(
+                "Processing snapshot request from {}: reason={}, known_state={}",
+                remote_id,
+                request.reason,
+                request.known_state_id
+            );
+
+            let mut state = shared_state.write().await;
+            let session = state.manager.session_mut();
+            if request.reason == request_snapshot::Reason::DecodeError as i32 {
+                if let Some(mismatches) = session.record_frame_hash_mismatch(remote_id) {
+                    log::warn!(
+                        "Client {} screen didn't match frame hash at state {} ({} mismatches so far)",
+                        remote_id,
+                        request.known_state_id,
+                        mismatches
+                    );
+                }
+                // The client's style table may be as suspect as its screen
+                session.forget_client_styles(remote_id);
+            } else if request.reason == request_snapshot::Reason::BaseMismatch as i32 {
+                if let Some(divergences) = session.record_row_checksum_mismatch(remote_id) {
+                    log::warn!(
+                        "Client {} diverged at state {} ({} times so far)",
+                        remote_id,
+                        request.known_state_id,
+                        divergences
+                    );
+                }
+            }
+            session.force_client_snapshot(remote_id);
+        },
+        ConnectionEvent::StateAckReceived { remote_id, ack } => {
+            let mut state = shared_state.write().await;
+            state
+                .manager
+                .session_mut()
+                .process_state_ack(remote_id, &ack);
+            log::trace!(
+                "Processed StateAck from client {}: last_applied={}, advancing baseline",
+                remote_id,
+                ack.last_applied_state_id
+            );
+        },
+        ConnectionEvent::SetControllerSize { remote_id, request } => {
+            let state = shared_state.read().await;
+
+            let session = state.manager.session();
+            let has_lease = session.lease_manager.is_controller(remote_id);
+
+            if !has_lease {
+                log::warn!(
+                    "Client {} tried to set size but is not the controller",
+                    remote_id
+                );
+                return Ok(());
+            }
+
+            // Already checked against the size limits
+            if let Some(DisplaySize { cols, rows }) = request.size {
+                // Don't resize frame_store here - this is a viewport hint only.
+                // The actual terminal size is controlled by the Zellij client.
+                // FrameReady will detect dimension changes and do full copy.
+                log::info!(
+                    "Controller {} set viewport hint to {}x{} (actual resize handled by FrameReady)",
+                    remote_id,
+                    cols,
+                    rows
+                );
+            }
+        },
+        ConnectionEvent::FocusPaneRequested { remote_id, request } => {
+            let state = shared_state.read().await;
+            if !state
+                .manager
+                .session()
+                .lease_manager
+                .is_controller(remote_id)
+            {
+                log::warn!(
+                    "Client {} tried to focus a pane but is not the controller",
+                    remote_id
+                );
+                return Ok(());
+            }
+            let Some(client_id) = state.active_zellij_client else {
+                return Ok(());
+            };
+            let known = state.pane_layout.panes.iter().any(|pane| {
+                pane.pane_id == request.pane_id
+                    && pane.is_plugin == request.is_plugin
+                    && pane.selectable
+            });
+            if !known {
+                log::debug!(
+                    "Client {} asked to focus pane {} which it can't see",
+                    remote_id,
+                    request.pane_id
+                );
+                return Ok(());
+            }
+            let pane_id = focus_target(request.pane_id, request.is_plugin);
+            if let Err(e) = state.events.focus_pane(client_id, pane_id) {
+                log::warn!("Failed to focus pane for client {}: {}", remote_id, e);
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Apply a controller's input, given as a batch (a single `InputEvent` is a batch of
+/// one): route each new event to the screen thread, then ack the batch once. Mouse
+/// moves are coalesced per client; the acked seq still covers the ones held back.
+async fn handle_remote_input(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+    remote_id: u64,
+    batch: InputBatch,
+) {
+    // M2: Clone data needed, release lock before network I/O
+    let (is_controller, process_result, active_zellij_client, session_events) = {
+        let mut state = shared_state.write().await;
+        // Includes a controller just displaced by a takeover, during its grace period
+        let is_controller = state
+            .manager
+            .session_mut()
+            .lease_manager
+            .accepts_input(remote_id);
+        if !is_controller {
+            (false, None, None, None)
+        } else {
+            let result = state
+                .manager
+                .session_mut()
+                .process_input_batch(remote_id, &batch);
+            (
+                true,
+                Some(result),
+                state.active_zellij_client,
+                Some(state.events.clone()),
+            )
+        }
+    };
+    // Lock released here
+
+    if !is_controller {
+        log::warn!(
+            "Remote client {} sent input but is not the controller, denying",
+            remote_id
+        );
+
+        if let Some(client) = clients.get(&remote_id) {
+            let error = ProtocolError {
+                code: protocol_error::Code::LeaseDenied as i32,
+                message: "Not the controller".to_string(),
+                fatal: false,
+                retry_after_ms: 0,
+            };
+            let msg = StreamEnvelope {
+                msg: Some(stream_envelope::Msg::ProtocolError(error)),
+                envelope_seq: 0,
+            };
+            if let Err(mpsc::error::TrySendError::Full(_)) = client.sender.try_send(msg) {
+                log::warn!("Client {} channel full, dropping error message", remote_id);
+            }
+        }
+        return;
+    }
+
+    match process_result.unwrap() {
+        Ok((ack, events)) => {
+            let now = std::time::Instant::now();
+            let events: Vec<InputEvent> = match clients.get_mut(&remote_id) {
+                Some(client) => events
+                    .into_iter()
+                    .flat_map(|event| client.mouse_moves.push(event, now))
+                    .collect(),
+                None => events,
+            };
+            if let Some(session_events) = &session_events {
+                route_input(
+                    shared_state,
+                    remote_id,
+                    active_zellij_client,
+                    session_events,
+                    &events,
+                )
+                .await;
+            }
+            if let Some(client) = clients.get(&remote_id) {
+                let msg = StreamEnvelope {
+                    msg: Some(stream_envelope::Msg::InputAck(ack)),
+                    envelope_seq: 0,
+                };
+                if let Err(mpsc::error::TrySendError::Full(_)) = client.sender.try_send(msg) {
+                    log::warn!("Client {} channel full, dropping InputAck", remote_id);
+                }
+            }
+            log::trace!("Input from client {} processed", remote_id);
+        },
+        Err(InputError::Held) => {
+            log::trace!("Input from client {} arrived early, holding", remote_id);
+        },
+        Err(e) => {
+            log::warn!("Input error from client {}: {:?}", remote_id, e);
+        },
+    }
+}
+
+/// Route mouse moves held back by coalescing once their window has passed, unless
+/// the client lost the lease meanwhile.
+async fn handle_mouse_move_flush(
+    shared_state: &Arc<RwLock<SharedState>>,
+    clients: &mut HashMap<u64, ClientConnection>,
+) {
+    let now = std::time::Instant::now();
+    let flushed: Vec<(u64, InputEvent)> = clients
+        .iter_mut()
+        .filter_map(|(remote_id, client)| {
+            client
+                .mouse_moves
+                .flush(now)
+                .map(|event| (*remote_id, event))
+        })
+        .collect();
+    if flushed.is_empty() {
+        return;
+    }
+    let (active_zellij_client, session_events, flushed) = {
+        let state = shared_state.read().await;
+        let lease_manager = &state.manager.session().lease_manager;
+        let flushed: Vec<_> = flushed
+            .into_iter()
+            .filter(|(remote_id, _)| lease_manager.accepts_input(*remote_id))
+            .collect();
+        (state.active_zellij_client, state.events.clone(), flushed)
+    };
+    for (remote_id, event) in flushed {
+        route_input(
+            shared_state,
+            remote_id,
+            active_zellij_client,
+            &session_events,
+            &[event],
+        )
+        .await;
+    }
+}
+
+/// Send a controller's input on to the active zellij client's pane.
+async fn route_input(
+    shared_state: &Arc<RwLock<SharedState>>,
+    remote_id: u64,
+    active_zellij_client: Option<ClientId>,
+    session_events: &Arc<dyn OutboundSessionEvents>,
+    events: &[InputEvent],
+) {
+    for action in events.iter().filter_map(translate_input) {
+        match action {
+            zellij_utils::input::actions::Action::Write {
+                key_with_modifier,
+                bytes,
+                is_kitty_keyboard_protocol,
+            } => {
+                if let Some(zellij_client_id) = active_zellij_client {
+                    let send_result = session_events.write_to_pane(
+                        zellij_client_id,
+                        key_with_modifier,
+                        bytes,
+                        is_kitty_keyboard_protocol,
+                    );
+                    {
+                        // The heartbeat queues up behind the input, so its answer
+                        // shows the screen got past it, whether or not it draws
+                        let mut state = shared_state.write().await;
+                        let now = std::time::Instant::now();
+                        state.health.record_screen_send(send_result.is_ok(), now);
+                        send_heartbeat_if_due(&mut state, now);
+                    }
+                    if let Err(e) = send_result {
+                        log::error!("Failed to send to screen thread (may have crashed): {}", e);
+                    } else {
+                        log::trace!(
+                            "Routed input from remote client {} to zellij client {}",
+                            remote_id,
+                            zellij_client_id
+                        );
+                    }
+                } else {
+                    log::warn!(
+                        "No active Zellij client to route input from remote client {}",
+                        remote_id
+                    );
+                }
+            },
+            _ => {
+                log::debug!(
+                    "Non-write action from remote client {}, ignoring",
+                    remote_id
+                );
+            },
+        }
+    }
+}
+
+/// What a client opened its stream with
+enum HelloRead {
+    /// The hello, and whatever was read after it
+    Hello(ClientHello, BytesMut),
+    /// Something other than a well-formed ClientHello, and why it isn't one
+    Malformed(String),
+}
+
+/// Read the ClientHello a client opens its stream with. Errors only when the stream
+/// fails or closes first, as there's no one left to tell.
+async fn read_client_hello(recv: &mut wtransport::RecvStream) -> Result<HelloRead> {
+    let mut buffer = BytesMut::new();
+
+    loop {
+        let mut chunk = [0u8; 1024];
+        let n = recv.read(&mut chunk).await?.unwrap_or(0);
+        if n == 0 {
+            anyhow::bail!("connection closed during handshake");
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+
+        match decode_envelope(&mut buffer) {
+            Ok(Some(StreamEnvelope {
+                msg: Some(stream_envelope::Msg::ClientHello(hello)),
+                ..
+            })) => return Ok(HelloRead::Hello(hello, buffer)),
+            Ok(Some(_)) => {
+                return Ok(HelloRead::Malformed(
+                    "expected ClientHello, got other message".to_string(),
+                ))
+            },
+            Ok(None) => {},
+            Err(e) => return Ok(HelloRead::Malformed(e.to_string())),
+        }
+    }
+}
+
+/// Refuse a client whose hello asks for a protocol major version other than ours.
+/// Clients that leave the version out are taken to speak ours.
+fn check_client_version(client_hello: &ClientHello) -> std::result::Result<(), ProtocolError> {
+    match &client_hello.version {
+        Some(version) if version.major != zellij_remote_protocol::ZRP_VERSION_MAJOR => {
+            Err(handshake_error(
+                protocol_error::Code::BadVersion,
+                format!(
+                    "Unsupported protocol version {}.{}; this server speaks {}.{}",
+                    version.major,
+                    version.minor,
+                    zellij_remote_protocol::ZRP_VERSION_MAJOR,
+                    zellij_remote_protocol::ZRP_VERSION_MINOR
+                ),
+                0,
+            ))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// A fatal error ending the handshake
+fn handshake_error(
+    code: protocol_error::Code,
+    message: String,
+    retry_after_ms: u32,
+) -> ProtocolError {
+    ProtocolError {
+        code: code as i32,
+        message,
+        fatal: true,
+        retry_after_ms,
+    }
+}
+
+/// What a refused client is sent: a failed `AttachResponse` first if it asked to
+/// attach, so both kinds of client see why, then the error itself
+fn refusal_envelopes(attach_requested: bool, error: ProtocolError) -> Vec<StreamEnvelope> {
+    let mut envelopes = Vec::with_capacity(2);
+    if attach_requested {
+        envelopes.push(StreamEnvelope {
+            msg: Some(stream_envelope::Msg::AttachResponse(AttachResponse {
+                ok: false,
+                error_message: error.message.clone(),
+                ..Default::default()
+            })),
+            envelope_seq: 0,
+        });
+    }
+    envelopes.push(StreamEnvelope {
+        msg: Some(stream_envelope::Msg::ProtocolError(error)),
+        envelope_seq: 0,
+    });
+    envelopes
+}
+
+/// Tell a client why it is refused and finish its stream
+async fn refuse_client(
+    send: &mut wtransport::SendStream,
+    attach_requested: bool,
+    error: ProtocolError,
+) -> Result<()> {
+    for envelope in refusal_envelopes(attach_requested, error) {
+        send.write_all(&encode_envelope(&envelope)?).await?;
+    }
+    send.finish().await.ok();
+    Ok(())
+}
+
+fn decode_envelope(buf: &mut BytesMut) -> Result<Option<StreamEnvelope>> {
+    Ok(ZrpCodec::<StreamEnvelope>::with_max_frame_bytes(MAX_FRAME_SIZE).decode(buf)?)
+}
+
+/// Run a control request past the lease manager, recording the outcome with the throttle
+fn grant_or_deny_control(
+    state: &mut SharedState,
+    remote_id: u64,
+    request: &zellij_remote_protocol::RequestControl,
+    now: std::time::Instant,
+) -> Vec<stream_envelope::Msg> {
+    let max_duration = (request.max_duration_ms > 0)
+        .then(|| std::time::Duration::from_millis(request.max_duration_ms as u64));
+    let result = state
+        .manager
+        .session_mut()
+        .lease_manager
+        .request_time_boxed_control(
+            remote_id,
+            request.desired_size.clone(),
+            request.force,
+            max_duration,
+        );
+
+    match result {
+        LeaseResult::Granted(lease) => {
+            log::info!("Granted control to remote client {}", remote_id);
+            state.control_throttle.record_grant(remote_id);
+            // The displaced controller hears about it with the next session events
+            state.manager.session_mut().queue_takeover();
+            report_presence(state);
+            vec![stream_envelope::Msg::GrantControl(GrantControl {
+                lease: Some(lease),
+            })]
+        },
+        LeaseResult::Denied {
+            reason,
+            current_lease,
+        } => deny_control(state, remote_id, reason, current_lease, now),
+    }
+}
+
+fn deny_control(
+    state: &mut SharedState,
+    remote_id: u64,
+    reason: String,
+    current_lease: Option<ControllerLease>,
+    now: std::time::Instant,
+) -> Vec<stream_envelope::Msg> {
+    log::info!("Denied control to remote client {}: {}", remote_id, reason);
+    let mut responses = vec![stream_envelope::Msg::DenyControl(DenyControl {
+        reason,
+        lease: current_lease,
+    })];
+    if let Some(mute) = state.control_throttle.record_denial(remote_id, now) {
+        log::warn!(
+            "Muting control requests from client {} for {:?} after repeated denials",
+            remote_id,
+            mute
+        );
+        responses.push(control_muted_error(mute));
+    }
+    responses
+}
+
+/// Whether the local user has to approve this request first: a force takeover of a
+/// lease someone else holds, with approval prompts configured
+fn needs_approval(
+    state: &SharedState,
+    remote_id: u64,
+    request: &zellij_remote_protocol::RequestControl,
+) -> bool {
+    let lease_manager = &state.manager.session().lease_manager;
+    request.force
+        && state.manager.approvals_enabled()
+        && lease_manager
+            .get_current_lease()
+            .is_some_and(|lease| lease.owner_client_id != remote_id)
+}
+
+fn ask_approval(
+    state: &mut SharedState,
+    remote_id: u64,
+    request: zellij_remote_protocol::RequestControl,
+    now: std::time::Instant,
+) {
+    let controller = state
+        .manager
+        .session()
+        .lease_manager
+        .get_current_lease()
+        .map(|lease| lease.owner_client_id);
+    let client = state
+        .presence
+        .clients(controller)
+        .into_iter()
+        .find(|client| client.remote_id == remote_id)
+        .unwrap_or_default();
+    let Some(approvals) = state.manager.approvals_mut() else {
+        return;
+    };
+    let timeout_ms = approvals.timeout().as_millis() as u64;
+    let Some(pending) = approvals.request(remote_id, PendingAction::ForceTakeover(request), now)
+    else {
+        log::debug!(
+            "Remote client {} is already waiting for takeover approval",
+            remote_id
+        );
+        return;
+    };
+    let approval = RemoteApprovalRequest {
+        request_id: pending.request_id,
+        client,
+        action: pending.action.kind(),
+        timeout_ms,
+    };
+    log::info!(
+        "Asking the local user to approve a takeover by remote client {} (request {})",
+        remote_id,
+        approval.request_id
+    );
+    if let Err(e) = state.events.approval_requested(approval) {
+        log::warn!("Failed to ask for takeover approval: {}", e);
+    }
+}
+
+/// Carry out or refuse an approved or denied request, returning the client to reply to
+fn answer_approval(
+    state: &mut SharedState,
+    request_id: u64,
+    approved: bool,
+    now: std::time::Instant,
+) -> Option<(u64, Vec<stream_envelope::Msg>)> {
+    let pending = state.manager.approvals_mut()?.resolve(request_id)?;
+    if let Err(e) = state.events.approval_resolved(request_id) {
+        log::warn!("Failed to report approval {} resolved: {}", request_id, e);
+    }
+    let remote_id = pending.remote_id;
+    let responses = match pending.action {
+        PendingAction::ForceTakeover(request) if approved => {
+            grant_or_deny_control(state, remote_id, &request, now)
+        },
+        PendingAction::ForceTakeover(_) => {
+            let current_lease = state.manager.session().lease_manager.get_current_lease();
+            deny_control(
+                state,
+                remote_id,
+                "takeover declined by the host".to_string(),
+                current_lease,
+                now,
+            )
+        },
+    };
+    Some((remote_id, responses))
+}
+
+/// Deny the requests nobody answered in time
+fn expire_approvals(
+    state: &mut SharedState,
+    now: std::time::Instant,
+) -> Vec<(u64, Vec<stream_envelope::Msg>)> {
+    let expired = match state.manager.approvals_mut() {
+        Some(approvals) => approvals.expire(now),
+        None => return vec![],
+    };
+    expired
+        .into_iter()
+        .map(|pending| {
+            if let Err(e) = state.events.approval_resolved(pending.request_id) {
+                log::warn!(
+                    "Failed to report approval {} expired: {}",
+                    pending.request_id,
+                    e
+                );
+            }
+            let current_lease = state.manager.session().lease_manager.get_current_lease();
+            let responses = deny_control(
+                state,
+                pending.remote_id,
+                "takeover approval timed out".to_string(),
+                current_lease,
+                now,
+            );
+            (pending.remote_id, responses)
+        })
+        .collect()
+}
+
+fn send_control_messages(
+    clients: &HashMap<u64, ClientConnection>,
+    remote_id: u64,
+    messages: Vec<stream_envelope::Msg>,
+) {
+    let Some(client) = clients.get(&remote_id) else {
+        return;
+    };
+    for message in messages {
+        let msg = StreamEnvelope {
+            msg: Some(message),
+            envelope_seq: 0,
+        };
+        if let Err(mpsc::error::TrySendError::Full(_)) = client.sender.try_send(msg) {
+            log::warn!(
+                "Client {} channel full, dropping control response",
+                remote_id
+            );
+        }
+    }
+}
+
+/// Log a finished handoff and update throttling and presence for it
+fn record_handoff_outcome(state: &mut SharedState, outcome: Option<&HandoffOutcome>) {
+    match outcome {
+        Some(HandoffOutcome::Approved {
+            handoff_id,
+            requester,
+            previous_owner,
+            ..
+        }) => {
+            log::info!(
+                "Remote client {} handed control to {} (handoff {})",
+                previous_owner,
+                requester,
+                handoff_id
+            );
+            state.control_throttle.record_grant(*requester);
+            report_presence(state);
+        },
+        Some(HandoffOutcome::Denied {
+            handoff_id,
+            requester,
+            reason,
+            ..
+        }) => {
+            log::info!(
+                "Handoff {} to remote client {} denied: {}",
+                handoff_id,
+                requester,
+                reason
+            );
+            // Counts towards muting, so a declined requester can't keep nagging
+            let now = std::time::Instant::now();
+            if let Some(mute) = state.control_throttle.record_denial(*requester, now) {
+                log::warn!(
+                    "Muting control requests from client {} for {:?} after repeated denials",
+                    requester,
+                    mute
+                );
+            }
+        },
+        None => {},
+    }
+}
+
+/// Grant the requester control and revoke the old lease, or tell the requester no
+fn deliver_handoff_outcome(clients: &HashMap<u64, ClientConnection>, outcome: HandoffOutcome) {
+    match outcome {
+        HandoffOutcome::Approved {
+            requester,
+            previous_lease_id,
+            lease,
+            ..
+        } => {
+            broadcast_lease_revoked(clients, previous_lease_id, "handoff", None);
+            send_control_messages(
+                clients,
+                requester,
+                vec![stream_envelope::Msg::GrantControl(GrantControl {
+                    lease: Some(lease),
+                })],
+            );
+        },
+        HandoffOutcome::Denied {
+            requester,
+            reason,
+            current_lease,
+            ..
+        } => {
+            send_control_messages(
+                clients,
+                requester,
+                vec![stream_envelope::Msg::DenyControl(DenyControl {
+                    reason,
+                    lease: current_lease,
+                })],
+            );
+        },
+    }
+}
+
+fn deliver_lease_return(clients: &HashMap<u64, ClientConnection>, returned: LeaseReturn) {
+    let owner = returned.lease.owner_client_id;
+    log::info!(
+        "Control returned to remote client {} (lease {})",
+        owner,
+        returned.lease.lease_id
+    );
+    if let Some(lease_id) = returned.ended_lease_id {
+        broadcast_lease_revoked(clients, lease_id, "time_box", None);
+    }
+    send_control_messages(
+        clients,
+        owner,
+        vec![stream_envelope::Msg::GrantControl(GrantControl {
+            lease: Some(returned.lease),
+        })],
+    );
+}
+
+/// The terminal size a client message asks for, with the client it came from
+fn requested_size(event: &mut ConnectionEvent) -> Option<(u64, &mut DisplaySize)> {
+    let (remote_id, size) = match event {
+        ConnectionEvent::AttachRequested { remote_id, request } => {
+            (remote_id, request.desired_size.as_mut())
+        },
+        ConnectionEvent::RequestControl { remote_id, request } => {
+            (remote_id, request.desired_size.as_mut())
+        },
+        ConnectionEvent::ControlHandoffRequested { remote_id, request } => {
+            (remote_id, request.desired_size.as_mut())
+        },
+        ConnectionEvent::SetControllerSize { remote_id, request } => {
+            (remote_id, request.size.as_mut())
+        },
+        _ => return None,
+    };
+    Some((*remote_id, size?))
+}
+
+/// What a client asking to switch to `session_name` is told. Not finding the session
+/// isn't fatal: the client stays attached here.
+fn session_switch_reply(
+    session_name: String,
+    found: std::result::Result<zellij_remote_protocol::Endpoint, SwitchError>,
+) -> stream_envelope::Msg {
+    match found {
+        Ok(endpoint) => stream_envelope::Msg::SessionRedirect(SessionRedirect {
+            session_name,
+            endpoint: Some(endpoint),
+        }),
+        Err(e) => stream_envelope::Msg::ProtocolError(ProtocolError {
+            code: protocol_error::Code::SessionNotFound as i32,
+            message: format!("Can't switch to session {:?}: {}", session_name, e),
+            fatal: false,
+            retry_after_ms: 0,
+        }),
+    }
+}
+
+fn bad_size_error(e: &SizeError) -> stream_envelope::Msg {
+    stream_envelope::Msg::ProtocolError(ProtocolError {
+        code: protocol_error::Code::BadMessage as i32,
+        message: e.to_string(),
+        fatal: false,
+        retry_after_ms: 0,
+    })
+}
+
+fn bad_size_attach_response(e: &SizeError) -> AttachResponse {
+    AttachResponse {
+        ok: false,
+        error_message: e.to_string(),
+        ..Default::default()
+    }
+}
+
+/// What a client asking for a size out of range is sent: a failed `AttachResponse`
+/// first if it was attaching, so it isn't left waiting for one, then the error
+fn bad_size_replies(attach_requested: bool, e: &SizeError) -> Vec<stream_envelope::Msg> {
+    let mut replies = Vec::with_capacity(2);
+    if attach_requested {
+        replies.push(stream_envelope::Msg::AttachResponse(
+            bad_size_attach_response(e),
+        ));
+    }
+    replies.push(bad_size_error(e));
+    replies
+}
+
+/// Bring the size an `AttachRequest` sent along with a ClientHello asks for within
+/// `size_limits`, like a later `AttachRequest`'s, or say why it can't be used. It is
+/// checked before the client is recorded, so a bad one refuses the client outright.
+fn check_attach_size(
+    size_limits: &SizeLimits,
+    request: &mut AttachRequest,
+) -> Result<(), SizeError> {
+    if let Some(size) = request.desired_size.as_mut() {
+        *size = size_limits.check(size)?;
+    }
+    Ok(())
+}
+
+/// Apply the `AttachRequest` sent along with a ClientHello, its size already checked
+fn attach_from_hello(
+    state: &mut SharedState,
+    remote_id: u64,
+    request: AttachRequest,
+) -> AttachResponse {
+    let response = state
+        .manager
+        .session_mut()
+        .apply_attach_request(remote_id, &request);
+    log::info!(
+        "Remote client {} attached with mode={:?} force_snapshot={}: will_send_snapshot={}",
+        remote_id,
+        request.mode(),
+        request.force_snapshot,
+        response.will_send_snapshot
+    );
+    response
+}
+
+fn control_muted_error(remaining: std::time::Duration) -> stream_envelope::Msg {
+    stream_envelope::Msg::ProtocolError(ProtocolError {
+        code: protocol_error::Code::RateLimited as i32,
+        message: "Too many denied control requests; control requests are ignored for now"
+            .to_string(),
+        fatal: false,
+        retry_after_ms: remaining.as_millis().min(u32::MAX as u128) as u32,
+    })
+}
+
+/// The delivery strategy a client's `CapabilityUpdate` switches to, as the reply to
+/// send back. Datagrams stay off unless they were negotiated at connect.
+fn renegotiate_delivery(datagrams_at_connect: bool, update: &CapabilityUpdate) -> CapabilityUpdate {
+    let supports_datagrams = datagrams_at_connect
+        && update
+            .capabilities
+            .as_ref()
+            .is_some_and(|c| c.supports_datagrams);
+    CapabilityUpdate {
+        capabilities: Some(Capabilities {
+            supports_datagrams,
+            max_datagram_bytes: zellij_remote_protocol::DEFAULT_MAX_DATAGRAM_BYTES,
+            ..Default::default()
+        }),
+        supported_codecs: vec![],
+        compression: Some(negotiate_compression(&update.supported_codecs)),
+    }
+}
+
+/// The message size limit to honour for a client asking for `requested` bytes: none
+/// for 0, otherwise no lower than chunking can work with.
+fn negotiate_message_bytes(requested: u32) -> u32 {
+    match requested {
+        0 => 0,
+        _ => requested.max(zellij_remote_core::MIN_MESSAGE_BYTES as u32),
+    }
+}
+
+fn build_server_hello(
+    client_hello: &ClientHello,
+    client_id: u64,
+    lease: Option<ControllerLease>,
+    resume_token: Vec<u8>,
+    session_name: &str,
+    window_bounds: WindowBounds,
+    keepalive_interval_ms: u64,
+) -> ServerHello {
+    let negotiated_caps = Capabilities {
+        supports_datagrams: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_datagrams)
+            .unwrap_or(false),
+        max_datagram_bytes: zellij_remote_protocol::DEFAULT_MAX_DATAGRAM_BYTES,
+        supports_style_dictionary: true,
+        supports_styled_underlines: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_styled_underlines)
+            .unwrap_or(false),
+        supports_prediction: true,
+        supports_images: false,
+        supports_clipboard: false,
+        supports_hyperlinks: false,
+        supports_line_size: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_line_size)
+            .unwrap_or(false),
+        supports_text_mode: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_text_mode)
+            .unwrap_or(false),
+        supports_frame_hash: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_frame_hash)
+            .unwrap_or(false),
+        supports_style_retention: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_style_retention)
+            .unwrap_or(false),
+        supports_priority_lanes: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_priority_lanes)
+            .unwrap_or(false),
+        supports_row_checksums: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_row_checksums)
+            .unwrap_or(false),
+        supports_push_streams: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_push_streams)
+            .unwrap_or(false),
+        supports_bell: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_bell)
+            .unwrap_or(false),
+        supports_lease_status: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_lease_status)
+            .unwrap_or(false),
+        max_message_bytes: client_hello
+            .capabilities
+            .as_ref()
+            .map_or(0, |c| negotiate_message_bytes(c.max_message_bytes)),
+        supports_pane_layout: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_pane_layout)
+            .unwrap_or(false),
+        supports_keybinding_info: client_hello
+            .capabilities
+            .as_ref()
+            .map(|c| c.supports_keybinding_info)
+            .unwrap_or(false),
+    };
+
+    ServerHello {
+        negotiated_version: Some(ProtocolVersion {
+            major: zellij_remote_protocol::ZRP_VERSION_MAJOR,
+            minor: zellij_remote_protocol::ZRP_VERSION_MINOR,
+        }),
+        negotiated_capabilities: Some(negotiated_caps),
+        client_id,
+        session_name: session_name.to_string(),
+        session_state: SessionState::Running.into(),
+        lease,
+        resume_token,
+        snapshot_interval_ms: 5000,
+        max_inflight_inputs: 256,
+        render_window: window_bounds.clamp(zellij_remote_protocol::DEFAULT_RENDER_WINDOW),
+        min_render_window: window_bounds.min,
+        max_render_window: window_bounds.max,
+        keepalive_interval_ms: keepalive_interval_ms.min(u32::MAX as u64) as u32,
+        alternate_endpoints: vec![],
+        compression: Some(negotiate_compression(&client_hello.supported_codecs)),
+        extensions: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::panes::terminal_answers::TerminalAnswers;
+    use crate::panes::PaneId;
+    use std::sync::Mutex;
+    use zellij_remote_protocol::{input_event, InputEvent};
+    use zellij_utils::data::{KeyWithModifier, RemoteClientInfo};
+
+    #[derive(Default)]
+    struct RecordingEvents {
+        writes: Mutex<Vec<(ClientId, Vec<u8>)>>,
+        presence: Mutex<Vec<Vec<RemoteClientInfo>>>,
+        approvals: Mutex<Vec<RemoteApprovalRequest>>,
+        resolved: Mutex<Vec<u64>>,
+        heartbeats: Mutex<u32>,
+        focuses: Mutex<Vec<(ClientId, PaneId)>>,
+        input_latency: Mutex<Vec<Vec<RemoteInputLatency>>>,
+    }
+
+    impl OutboundSessionEvents for RecordingEvents {
+        fn write_to_pane(
+            &self,
+            client_id: ClientId,
+            _key_with_modifier: Option<KeyWithModifier>,
+            bytes: Vec<u8>,
+            _is_kitty_keyboard_protocol: bool,
+        ) -> Result<()> {
+            self.writes.lock().unwrap().push((client_id, bytes));
+            Ok(())
+        }
+
+        fn focus_pane(&self, client_id: ClientId, pane_id: PaneId) -> Result<()> {
+            self.focuses.lock().unwrap().push((client_id, pane_id));
+            Ok(())
+        }
+
+        fn resize(&self, _size: Size) -> Result<()> {
+            Ok(())
+        }
+
+        fn remote_clients_changed(&self, clients: Vec<RemoteClientInfo>) -> Result<()> {
+            self.presence.lock().unwrap().push(clients);
+            Ok(())
+        }
+
+        fn terminal_answers_changed(&self, _answers: Option<TerminalAnswers>) -> Result<()> {
+            Ok(())
+        }
+
+        fn frame_stages_changed(&self, _stages: Vec<RemoteFrameStage>) -> Result<()> {
+            Ok(())
+        }
+
+        fn input_latency_changed(&self, latency: Vec<RemoteInputLatency>) -> Result<()> {
+            self.input_latency.lock().unwrap().push(latency);
+            Ok(())
+        }
+
+        fn serving_changed(&self, _listen_addr: Option<SocketAddr>) -> Result<()> {
+            Ok(())
+        }
+
+        fn approval_requested(&self, request: RemoteApprovalRequest) -> Result<()> {
+            self.approvals.lock().unwrap().push(request);
+            Ok(())
+        }
+
+        fn approval_resolved(&self, request_id: u64) -> Result<()> {
+            self.resolved.lock().unwrap().push(request_id);
+            Ok(())
+        }
+
+        fn heartbeat(&self) -> Result<()> {
+            *self.heartbeats.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    fn test_state(events: Arc<RecordingEvents>) -> SharedState {
+        SharedState {
+            manager: RemoteManager::new(80, 24),
+            current_frame: None,
+            session_name: "zellij".to_string(),
+            events,
+            active_zellij_client: Some(7),
+            frame_count: 0,
+            delta_count: 0,
+            dropped_delta_count: 0,
+            idle_frame_count: 0,
+            health: SessionHealth::default(),
+            control_throttle: ControlRequestThrottle::default(),
+            presence: RemotePresence::default(),
+            terminal_answers: ControllerAnswers::default(),
+            keepalive_interval_ms: None,
+            alternate_endpoints: vec![],
+            metadata: SessionMetadata::default(),
+            pane_layout: PaneLayout::default(),
+            keybindings: KeybindingInfo::default(),
+            bells: BellCoalescer::new(),
+            started_at: std::time::Instant::now(),
+            lease_status_sent_ms: None,
+            size_limits: SizeLimits::default(),
+            writer_stall_timeout: std::time::Duration::from_millis(WRITER_STALL_TIMEOUT_MS),
+            stalled_writer_disconnects: 0,
+            envelope_seq_gaps: 0,
+            runtime_probe: RuntimeProbe::default(),
+            frame_stages: StageRecorder::default(),
+            frame_stages_reported: vec![],
+            input_latency_reported: vec![],
+            extensions: ExtensionRegistry::default(),
+            audit: None,
+        }
+    }
+
+    fn text_input(seq: u64, text: &str) -> InputBatch {
+        InputBatch {
+            client_time_ms: 0,
+            events: vec![InputEvent {
+                input_seq: seq,
+                payload: Some(input_event::Payload::TextUtf8(text.as_bytes().to_vec())),
+                ..Default::default()
+            }],
+        }
+    }
+
+    fn test_config() -> RemoteConfig {
+        RemoteConfig {
+            listen_addr: "127.0.0.1:4433".parse().unwrap(),
+            session_name: "zellij".to_string(),
+            initial_size: Size { cols: 80, rows: 24 },
+            events: Arc::new(RecordingEvents::default()),
+            bearer_token: None,
+            token_provider: None,
+            snapshot_token: None,
+            controller_idle_timeout_ms: None,
+            keepalive_interval_ms: None,
+            alternate_endpoints: vec![],
+            frame_rate_caps: FrameRateCaps::default(),
+            approval_timeout_ms: None,
+            persist_dir: None,
+            screen_stall_timeout_ms: None,
+            size_limits: SizeLimits::default(),
+            input_reorder_window: ReorderWindow::default(),
+            writer_stall_timeout_ms: None,
+            extensions: ExtensionRegistry::default(),
+            max_delta_bytes: None,
+            worker_threads: None,
+            security_profile: SecurityProfile::Default,
+            controller_policy: None,
+            audit_log: None,
+            i_know_what_im_doing: false,
+            auth_error: None,
+        }
+    }
+
+    #[test]
+    fn test_remote_config_default() {
+        let config = test_config();
+        assert_eq!(config.listen_addr.port(), 4433);
+        assert_eq!(config.session_name, "zellij");
+        assert_eq!(config.initial_size.cols, 80);
+        assert_eq!(config.initial_size.rows, 24);
+        assert!(config.bearer_token.is_none());
+    }
+
+    #[test]
+    fn test_security_profiles_refuse_unsafe_combinations() {
+        let token = Some(TokenHash::from_plaintext(b"session"));
+        let network = "0.0.0.0:4433".parse().unwrap();
+
+        // Default only refuses an unauthenticated session on the network
+        let hardening = security_profile::enforce(&test_config()).unwrap();
+        assert_eq!(
+            hardening.controller_policy,
+            ControllerPolicy::LastWriterWins
+        );
+        assert!(!hardening.audit_log);
+        assert_eq!(hardening.warnings.len(), 1);
+        let exposed = RemoteConfig {
+            listen_addr: network,
+            ..test_config()
+        };
+        let refusal = security_profile::enforce(&exposed).unwrap_err();
+        assert_eq!(refusal.profile, SecurityProfile::Default);
+        assert_eq!(refusal.problems.len(), 1);
+        assert!(security_profile::enforce(&RemoteConfig {
+            bearer_token: token.clone(),
+            ..exposed.clone()
+        })
+        .is_ok());
+
+        // Open lets it through with warnings
+        let open = security_profile::enforce(&RemoteConfig {
+            security_profile: SecurityProfile::Open,
+            ..exposed
+        })
+        .unwrap();
+        assert_eq!(open.warnings.len(), 1);
+
+        // Strict refuses everything it can't make safe itself
+        let strict = RemoteConfig {
+            security_profile: SecurityProfile::Strict,
+            listen_addr: network,
+            controller_policy: Some(ControllerPolicy::LastWriterWins),
+            audit_log: Some(false),
+            ..test_config()
+        };
+        assert_eq!(
+            security_profile::enforce(&strict)
+                .unwrap_err()
+                .problems
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_malformed_token_hash_refused_under_every_profile() {
+        for security_profile in [
+            SecurityProfile::Strict,
+            SecurityProfile::Default,
+            SecurityProfile::Open,
+        ] {
+            let refusal = security_profile::enforce(&RemoteConfig {
+                security_profile,
+                auth_error: Some("serving with a malformed ZELLIJ_REMOTE_TOKEN_HASH".to_string()),
+                i_know_what_im_doing: true,
+                ..test_config()
+            })
+            .unwrap_err();
+            assert!(!refusal.overridable);
+            assert!(!refusal.to_string().contains("--i-know-what-im-doing"));
+        }
+    }
+
+    #[test]
+    fn test_strict_profile_turns_on_what_it_can() {
+        let strict = RemoteConfig {
+            security_profile: SecurityProfile::Strict,
+            bearer_token: Some(TokenHash::from_plaintext(b"session")),
+            ..test_config()
+        };
+        let hardening = security_profile::enforce(&strict).unwrap();
+        assert_eq!(hardening.controller_policy, ControllerPolicy::ExplicitOnly);
+        assert!(hardening.audit_log);
+        assert!(hardening.warnings.is_empty());
+
+        // Unauthenticated, it only starts when told to, and says what it let through
+        let unauthenticated = RemoteConfig {
+            bearer_token: None,
+            ..strict
+        };
+        assert!(security_profile::enforce(&unauthenticated).is_err());
+        let hardening = security_profile::enforce(&RemoteConfig {
+            i_know_what_im_doing: true,
+            ..unauthenticated
+        })
+        .unwrap();
+        assert_eq!(hardening.controller_policy, ControllerPolicy::ExplicitOnly);
+        assert_eq!(hardening.warnings.len(), 1);
+        assert!(hardening.warnings[0].contains("--i-know-what-im-doing"));
+    }
+
+    #[test]
+    fn test_snapshot_token_only_grants_captures() {
+        let session_token = TokenHash::from_plaintext(b"session");
+        let snapshot_token = TokenHash::from_plaintext(b"dashboard");
+        let authorize_with =
+            |presented: &[u8]| authorize(Some(&session_token), Some(&snapshot_token), presented);
+
+        assert_eq!(authorize_with(b"session"), Some(ClientRole::Controller));
+        assert_eq!(authorize_with(b"dashboard"), Some(ClientRole::Snapshot));
+        assert_eq!(authorize_with(b"guess"), None);
+        // An unauthenticated session lets everyone in, whatever they present
+        assert_eq!(
+            authorize(None, Some(&snapshot_token), b"dashboard"),
+            Some(ClientRole::Controller)
+        );
+    }
+
+    #[test]
+    fn test_stopped_listener_keeps_up_with_session() {
+        let mut config = test_config();
+        let mut carry_over = SessionCarryOver::default();
+        let mut apply = |instruction| carry_over.apply_while_stopped(&mut config, instruction);
+
+        assert!(matches!(
+            apply(RemoteInstruction::FrameReady {
+                client_id: 1,
+                frame_store: FrameStore::new(80, 24),
+                style_table: Some(StyleTable::new()),
+                convert_time: std::time::Duration::ZERO,
+            }),
+            StoppedAction::Stay
+        ));
+        apply(RemoteInstruction::ClientConnected {
+            client_id: 1,
+            size: Size { cols: 80, rows: 24 },
+        });
+        apply(RemoteInstruction::ClientDisconnected { client_id: 2 });
+        apply(RemoteInstruction::FocusedPaneTitle {
+            title: "vim".to_string(),
+        });
+        apply(RemoteInstruction::FocusedPaneProcess {
+            cwd: Some(PathBuf::from("/src")),
+            command: None,
+        });
+        assert!(matches!(
+            apply(RemoteInstruction::Stop),
+            StoppedAction::Stay
+        ));
+        assert!(matches!(
+            apply(RemoteInstruction::ToggleStreamingPaused),
+            StoppedAction::Stay
+        ));
+        assert!(matches!(
+            apply(RemoteInstruction::Start),
+            StoppedAction::Start
+        ));
+
+        assert!(carry_over.last_frame.is_some());
+        assert_eq!(carry_over.active_zellij_client, Some(1));
+        assert_eq!(carry_over.metadata.pane_title, "vim");
+        assert_eq!(carry_over.metadata.cwd, "/src");
+        assert!(carry_over.streaming_paused);
+    }
+
+    #[test]
+    fn test_reconfigure_while_stopped_does_not_start() {
+        let mut config = test_config();
+        let mut carry_over = SessionCarryOver::default();
+        let new_config = RemoteConfig {
+            listen_addr: "0.0.0.0:5000".parse().unwrap(),
+            ..test_config()
+        };
+
+        assert!(matches!(
+            carry_over.apply_while_stopped(&mut config, RemoteInstruction::Reconfigure(new_config)),
+            StoppedAction::Stay
+        ));
+        assert_eq!(config.listen_addr.port(), 5000);
+        assert!(matches!(
+            carry_over.apply_while_stopped(&mut config, RemoteInstruction::Shutdown),
+            StoppedAction::Shutdown
+        ));
+    }
+
+    #[test]
+    fn test_quiet_screen_sent_one_heartbeat() {
+        let events = Arc::new(RecordingEvents::default());
+        let mut state = test_state(events.clone());
+        let start = std::time::Instant::now();
+        state.health.record_frame(start);
+
+        send_heartbeat_if_due(&mut state, start);
+        assert_eq!(*events.heartbeats.lock().unwrap(), 0);
+
+        let quiet = start + std::time::Duration::from_millis(SCREEN_STALL_TIMEOUT_MS / 2);
+        send_heartbeat_if_due(&mut state, quiet);
+        send_heartbeat_if_due(&mut state, quiet + std::time::Duration::from_secs(1));
+        assert_eq!(*events.heartbeats.lock().unwrap(), 1);
+
+        state.health.record_heartbeat(quiet);
+        send_heartbeat_if_due(&mut state, quiet + std::time::Duration::from_secs(1));
+        assert_eq!(*events.heartbeats.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_controller_input_written_to_active_pane() {
+        let events = Arc::new(RecordingEvents::default());
+        let shared_state = Arc::new(RwLock::new(test_state(events.clone())));
+        {
+            let mut state = shared_state.write().await;
+            let session = state.manager.session_mut();
+            session.add_client(1, 4);
+            session.add_client(2, 4);
+            session.lease_manager.request_control(1, None, false);
+        }
+        let mut clients = HashMap::new();
+
+        handle_remote_input(&shared_state, &mut clients, 1, text_input(1, "ls")).await;
+        // Not the controller, so nothing reaches the pane
+        handle_remote_input(&shared_state, &mut clients, 2, text_input(1, "rm")).await;
+
+        assert_eq!(*events.writes.lock().unwrap(), vec![(7, b"ls".to_vec())]);
+        // One heartbeat behind the written input, to tell a stall from input that
+        // draws nothing
+        assert_eq!(*events.heartbeats.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_controller_focuses_visible_panes() {
+        let events = Arc::new(RecordingEvents::default());
+        let shared_state = Arc::new(RwLock::new(test_state(events.clone())));
+        {
+            let mut state = shared_state.write().await;
+            let session = state.manager.session_mut();
+            session.add_client(1, 4);
+            session.add_client(2, 4);
+            session.lease_manager.request_control(1, None, false);
+        }
+        let mut clients = HashMap::new();
+        let pane = |pane_id, is_plugin, selectable| zellij_remote_protocol::PaneBoundary {
+            pane_id,
+            is_plugin,
+            selectable,
+            ..Default::default()
+        };
+        handle_instruction(
+            &shared_state,
+            &mut clients,
+            RemoteInstruction::PaneLayout(PaneLayout {
+                panes: vec![pane(1, false, true), pane(2, true, false)],
+            }),
+        )
+        .await
+        .unwrap();
+
+        let focus = |remote_id, pane_id, is_plugin| ConnectionEvent::FocusPaneRequested {
+            remote_id,
+            request: FocusPane { pane_id, is_plugin },
+        };
+        for event in [
+            focus(1, 1, false),
+            // The status bar can't take focus
+            focus(1, 2, true),
+            // Not in the layout
+            focus(1, 1, true),
+            focus(1, 9, false),
+            // Not the controller
+            focus(2, 1, false),
+        ] {
+            handle_connection_event(&shared_state, &mut clients, event)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            *events.focuses.lock().unwrap(),
+            vec![(7, PaneId::Terminal(1))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_input_goes_to_remote_screen_client() {
+        let events = Arc::new(RecordingEvents::default());
+        let shared_state = Arc::new(RwLock::new(test_state(events.clone())));
+        {
+            let mut state = shared_state.write().await;
+            let session = state.manager.session_mut();
+            session.add_client(1, 4);
+            session.lease_manager.request_control(1, None, false);
+        }
+        let mut clients = HashMap::new();
+        let size = Size { cols: 80, rows: 24 };
+        let connected = |client_id| RemoteInstruction::ClientConnected { client_id, size };
+
+        handle_instruction(&shared_state, &mut clients, connected(REMOTE_CLIENT_ID))
+            .await
+            .unwrap();
+        // A local client attaching doesn't take remote input away from it
+        handle_instruction(&shared_state, &mut clients, connected(8))
+            .await
+            .unwrap();
+        handle_remote_input(&shared_state, &mut clients, 1, text_input(1, "ls")).await;
+
+        handle_instruction(
+            &shared_state,
+            &mut clients,
+            RemoteInstruction::ClientDisconnected {
+                client_id: REMOTE_CLIENT_ID,
+            },
+        )
+        .await
+        .unwrap();
+        handle_instruction(&shared_state, &mut clients, connected(8))
+            .await
+            .unwrap();
+        handle_remote_input(&shared_state, &mut clients, 1, text_input(2, "pwd")).await;
 
-            if !is_controller {
-                log::warn!(
-                    "Remote client {} sent input but is not the controller, denying",
-                    remote_id
-                );
+        assert_eq!(
+            *events.writes.lock().unwrap(),
+            vec![(REMOTE_CLIENT_ID, b"ls".to_vec()), (8, b"pwd".to_vec())]
+        );
+    }
 
-                if let Some(client) = clients.get(&remote_id) {
-                    let error = ProtocolError {
-                        code: protocol_error::Code::LeaseDenied as i32,
-                        message: "Not the controller".to_string(),
-                        fatal: false,
-                    };
-                    let msg = StreamEnvelope {
-                        msg: Some(stream_envelope::Msg::ProtocolError(error)),
-                    };
-                    if let Err(mpsc::error::TrySendError::Full(_)) = client.sender.try_send(msg) {
-                        log::warn!("Client {} channel full, dropping error message", remote_id);
-                    }
-                }
-                return Ok(());
-            }
+    #[test]
+    fn test_presence_changes_reported_once() {
+        let events = Arc::new(RecordingEvents::default());
+        let mut state = test_state(events.clone());
+        state.presence.add_client(1, "alice@ios");
 
-            match process_result.unwrap() {
-                Ok(ack) => {
-                    if let Some(action) = translate_input(&input) {
-                        match action {
-                            zellij_utils::input::actions::Action::Write {
-                                key_with_modifier,
-                                bytes,
-                                is_kitty_keyboard_protocol,
-                            } => {
-                                if let Some(zellij_client_id) = active_zellij_client {
-                                    if let Some(ref to_screen) = to_screen {
-                                        if let Err(e) =
-                                            to_screen.send(ScreenInstruction::WriteCharacter(
-                                                key_with_modifier,
-                                                bytes,
-                                                is_kitty_keyboard_protocol,
-                                                zellij_client_id,
-                                                None,
-                                            ))
-                                        {
-                                            log::error!(
-                                                "Failed to send to screen thread (may have crashed): {}",
-                                                e
-                                            );
-                                        } else {
-                                            log::trace!(
-                                                "Routed input from remote client {} to zellij client {}",
-                                                remote_id,
-                                                zellij_client_id
-                                            );
-                                        }
-                                    }
-                                } else {
-                                    log::warn!(
-                                        "No active Zellij client to route input from remote client {}",
-                                        remote_id
-                                    );
-                                }
-                            },
-                            _ => {
-                                log::debug!(
-                                    "Non-write action from remote client {}, ignoring",
-                                    remote_id
-                                );
-                            },
-                        }
-                    }
-                    if let Some(client) = clients.get(&remote_id) {
-                        let msg = StreamEnvelope {
-                            msg: Some(stream_envelope::Msg::InputAck(ack)),
-                        };
-                        if let Err(mpsc::error::TrySendError::Full(_)) = client.sender.try_send(msg)
-                        {
-                            log::warn!("Client {} channel full, dropping InputAck", remote_id);
-                        }
-                    }
-                    log::trace!("Input from client {} processed", remote_id);
-                },
-                Err(e) => {
-                    log::warn!("Input error from client {}: {:?}", remote_id, e);
-                },
-            }
-        },
-        ConnectionEvent::RequestControl { remote_id, request } => {
-            // M2: Clone result before releasing lock
-            let response = {
-                let mut state = shared_state.write().await;
-                let result = state.manager.session_mut().lease_manager.request_control(
-                    remote_id,
-                    request.desired_size,
-                    request.force,
-                );
+        report_presence(&mut state);
+        report_presence(&mut state);
 
-                match result {
-                    LeaseResult::Granted(lease) => {
-                        log::info!("Granted control to remote client {}", remote_id);
-                        stream_envelope::Msg::GrantControl(GrantControl { lease: Some(lease) })
-                    },
-                    LeaseResult::Denied {
-                        reason,
-                        current_lease,
-                    } => {
-                        log::info!("Denied control to remote client {}: {}", remote_id, reason);
-                        stream_envelope::Msg::DenyControl(DenyControl {
-                            reason,
-                            lease: current_lease,
-                        })
-                    },
-                }
-            };
-            // Lock released here
+        let reported = events.presence.lock().unwrap();
+        assert_eq!(reported.len(), 1);
+        assert_eq!(reported[0][0].remote_id, 1);
+    }
 
-            if let Some(client) = clients.get(&remote_id) {
-                let msg = StreamEnvelope {
-                    msg: Some(response),
-                };
-                if let Err(mpsc::error::TrySendError::Full(_)) = client.sender.try_send(msg) {
-                    log::warn!(
-                        "Client {} channel full, dropping control response",
-                        remote_id
-                    );
-                }
-            }
-        },
-        ConnectionEvent::RequestSnapshot { remote_id, request } => {
-            log::info!(
-                "Processing snapshot request from {}: reason={}, known_state={}",
-                remote_id,
-                request.reason,
-                request.known_state_id
-            );
+    #[test]
+    fn test_input_latency_reported_while_connected() {
+        let events = Arc::new(RecordingEvents::default());
+        let mut state = test_state(events.clone());
+        state.manager.session_mut().add_client(1, 4);
+        state.presence.add_client(1, "alice@ios");
 
-            let mut state = shared_state.write().await;
-            state.manager.session_mut().force_client_snapshot(remote_id);
-        },
-        ConnectionEvent::StateAckReceived { remote_id, ack } => {
-            let mut state = shared_state.write().await;
-            state
-                .manager
-                .session_mut()
-                .process_state_ack(remote_id, &ack);
-            log::trace!(
-                "Processed StateAck from client {}: last_applied={}, advancing baseline",
-                remote_id,
-                ack.last_applied_state_id
-            );
-        },
-        ConnectionEvent::SetControllerSize { remote_id, request } => {
-            let state = shared_state.read().await;
+        // Nothing to report before the client sends LinkStats
+        report_input_latency(&mut state);
+        assert!(events.input_latency.lock().unwrap().is_empty());
 
-            let session = state.manager.session();
-            let has_lease = session.lease_manager.is_controller(remote_id);
+        let stats = LinkStats {
+            input_latency_p50_ms: 25,
+            input_latency_p95_ms: 90,
+            input_latency_p99_ms: 210,
+            input_latency_samples: 400,
+            ..Default::default()
+        };
+        state.manager.session_mut().record_link_stats(1, stats);
+        report_input_latency(&mut state);
+        report_input_latency(&mut state);
+        assert_eq!(
+            *events.input_latency.lock().unwrap(),
+            vec![vec![RemoteInputLatency {
+                client: "alice@ios".to_owned(),
+                p50_ms: 25,
+                p95_ms: 90,
+                p99_ms: 210,
+                samples: 400,
+            }]]
+        );
 
-            if !has_lease {
-                log::warn!(
-                    "Client {} tried to set size but is not the controller",
-                    remote_id
-                );
-                return Ok(());
-            }
+        state.manager.session_mut().remove_client(1);
+        state.presence.remove_client(1);
+        report_input_latency(&mut state);
+        assert_eq!(events.input_latency.lock().unwrap().last(), Some(&vec![]));
+    }
 
-            if let Some(size) = request.size {
-                // Clamp dimensions to prevent unbounded allocation DoS
-                const MAX_COLS: u32 = 500;
-                const MAX_ROWS: u32 = 500;
-                let cols = size.cols.min(MAX_COLS).max(1);
-                let rows = size.rows.min(MAX_ROWS).max(1);
+    fn takeover_pending_approval(events: Arc<RecordingEvents>) -> (SharedState, u64) {
+        let mut state = test_state(events.clone());
+        state
+            .manager
+            .set_approval_timeout(Some(std::time::Duration::from_secs(10)));
+        let session = state.manager.session_mut();
+        session.add_client(1, 4);
+        session.add_client(2, 4);
+        session.lease_manager.request_control(1, None, false);
+        state.presence.add_client(2, "bob@laptop");
 
-                if size.cols > MAX_COLS || size.rows > MAX_ROWS {
-                    log::warn!(
-                        "Controller {} requested oversized dimensions {}x{}, clamped to {}x{}",
-                        remote_id,
-                        size.cols,
-                        size.rows,
-                        cols,
-                        rows
-                    );
-                }
+        let request = zellij_remote_protocol::RequestControl {
+            force: true,
+            ..Default::default()
+        };
+        assert!(needs_approval(&state, 2, &request));
+        ask_approval(&mut state, 2, request, std::time::Instant::now());
 
-                // Don't resize frame_store here - this is a viewport hint only.
-                // The actual terminal size is controlled by the Zellij client.
-                // FrameReady will detect dimension changes and do full copy.
-                log::info!(
-                    "Controller {} set viewport hint to {}x{} (actual resize handled by FrameReady)",
-                    remote_id,
-                    cols,
-                    rows
-                );
-            }
-        },
+        let asked = events.approvals.lock().unwrap();
+        assert_eq!(asked.len(), 1);
+        assert_eq!(asked[0].client.name, "bob@laptop");
+        assert_eq!(asked[0].timeout_ms, 10_000);
+        let request_id = asked[0].request_id;
+        (state, request_id)
     }
-    Ok(())
-}
 
-async fn read_client_hello(recv: &mut wtransport::RecvStream) -> Result<ClientHello> {
-    let mut buffer = BytesMut::new();
+    #[test]
+    fn test_approved_takeover_granted() {
+        let events = Arc::new(RecordingEvents::default());
+        let (mut state, request_id) = takeover_pending_approval(events.clone());
+        assert!(state.manager.session().lease_manager.is_controller(1));
 
-    loop {
-        let mut chunk = [0u8; 1024];
-        let n = recv.read(&mut chunk).await?.unwrap_or(0);
-        if n == 0 {
-            anyhow::bail!("connection closed during handshake");
-        }
-        buffer.extend_from_slice(&chunk[..n]);
+        let (remote_id, responses) =
+            answer_approval(&mut state, request_id, true, std::time::Instant::now()).unwrap();
 
-        if let Some(envelope) = decode_envelope(&mut buffer)? {
-            match envelope.msg {
-                Some(stream_envelope::Msg::ClientHello(hello)) => {
-                    return Ok(hello);
-                },
-                _ => {
-                    anyhow::bail!("expected ClientHello, got other message");
-                },
-            }
+        assert_eq!(remote_id, 2);
+        assert!(matches!(
+            responses[..],
+            [stream_envelope::Msg::GrantControl(_)]
+        ));
+        assert!(state.manager.session().lease_manager.is_controller(2));
+        assert_eq!(*events.resolved.lock().unwrap(), vec![request_id]);
+        // Answering twice does nothing
+        assert!(answer_approval(&mut state, request_id, true, std::time::Instant::now()).is_none());
+    }
+
+    #[test]
+    fn test_declined_or_expired_takeover_denied() {
+        let events = Arc::new(RecordingEvents::default());
+        let (mut state, request_id) = takeover_pending_approval(events.clone());
+
+        let (_, responses) =
+            answer_approval(&mut state, request_id, false, std::time::Instant::now()).unwrap();
+        assert!(matches!(
+            responses[..],
+            [stream_envelope::Msg::DenyControl(_)]
+        ));
+        assert!(state.manager.session().lease_manager.is_controller(1));
+
+        let events = Arc::new(RecordingEvents::default());
+        let (mut state, request_id) = takeover_pending_approval(events.clone());
+        let later = std::time::Instant::now() + std::time::Duration::from_secs(11);
+        let denied = expire_approvals(&mut state, later);
+        assert_eq!(denied.len(), 1);
+        assert_eq!(denied[0].0, 2);
+        assert_eq!(*events.resolved.lock().unwrap(), vec![request_id]);
+        assert!(state.manager.session().lease_manager.is_controller(1));
+    }
+
+    #[test]
+    fn test_takeover_unprompted_without_approval_timeout() {
+        let mut state = test_state(Arc::new(RecordingEvents::default()));
+        let session = state.manager.session_mut();
+        session.add_client(1, 4);
+        session.lease_manager.request_control(1, None, false);
+        let request = zellij_remote_protocol::RequestControl {
+            force: true,
+            ..Default::default()
+        };
+        assert!(!needs_approval(&state, 2, &request));
+    }
+
+    #[test]
+    fn test_background_bells_more_urgent() {
+        let mut state = test_state(Arc::new(RecordingEvents::default()));
+
+        let background = ring_bell(&mut state, 1, false).unwrap();
+        assert_eq!(background.urgency(), BellUrgency::High);
+        let focused = ring_bell(&mut state, 2, true).unwrap();
+        assert_eq!(focused.urgency(), BellUrgency::Normal);
+        // Coalesced with the first bell of pane 1
+        assert!(ring_bell(&mut state, 1, false).is_none());
+    }
+
+    #[test]
+    fn test_lease_status_sent_once_per_interval() {
+        let mut state = test_state(Arc::new(RecordingEvents::default()));
+        assert!(due_lease_status(&mut state, 0, true).is_none());
+
+        let session = state.manager.session_mut();
+        session.add_client(1, 4);
+        session.lease_manager.request_control(1, None, false);
+        let status = due_lease_status(&mut state, 0, true).unwrap();
+        assert_eq!(status.lease.unwrap().owner_client_id, 1);
+        assert!(status.expires);
+
+        assert!(due_lease_status(&mut state, LEASE_STATUS_INTERVAL_MS - 1, true).is_none());
+        assert!(
+            due_lease_status(&mut state, LEASE_STATUS_INTERVAL_MS, false)
+                .is_some_and(|status| !status.expires)
+        );
+    }
+
+    #[test]
+    fn test_saved_session_restored_by_next_listener() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PERSIST_FILE_NAME);
+        let mut state = test_state(Arc::new(RecordingEvents::default()));
+        assert!(apply_frame(
+            &mut state,
+            frame("$ ls", 4),
+            Some(StyleTable::new())
+        ));
+        let remote_id = state.manager.add_client(1, Size { cols: 80, rows: 24 });
+        let token = state.manager.session_mut().generate_resume_token(remote_id);
+        save_session(&state, &path);
+
+        let mut carry_over = SessionCarryOver::default();
+        let manager = new_manager(&test_config(), Some(&path), &mut carry_over);
+        assert_eq!(
+            manager.session().session_id,
+            state.manager.session().session_id
+        );
+        assert_eq!(manager.next_remote_id(), remote_id + 1);
+        assert!(manager.session().predates_restore(&token));
+        let (frame_store, _) = carry_over.last_frame.unwrap();
+        assert_eq!(frame_store.current_frame().cursor.col, 4);
+
+        // Nothing saved: a new session
+        let mut carry_over = SessionCarryOver::default();
+        let manager = new_manager(&test_config(), None, &mut carry_over);
+        assert!(!manager.session().predates_restore(&token));
+        assert!(carry_over.last_frame.is_none());
+    }
+
+    fn frame(text: &str, cursor_col: u32) -> FrameStore {
+        let mut frame_store = FrameStore::new(80, 24);
+        for (col, c) in text.chars().enumerate() {
+            frame_store.update_row(0, |row| {
+                row.set_cell(
+                    col,
+                    zellij_remote_core::Cell {
+                        codepoint: c as u32,
+                        width: 1,
+                        style_id: 0,
+                    },
+                )
+            });
         }
+        frame_store.set_cursor(zellij_remote_core::Cursor {
+            col: cursor_col,
+            ..Default::default()
+        });
+        frame_store
     }
-}
 
-fn decode_envelope(buf: &mut BytesMut) -> Result<Option<StreamEnvelope>> {
-    use bytes::Buf;
+    #[test]
+    fn test_identical_frames_skipped() {
+        let mut state = test_state(Arc::new(RecordingEvents::default()));
+        assert!(apply_frame(
+            &mut state,
+            frame("$ ls", 4),
+            Some(StyleTable::new())
+        ));
+        let state_id = state.manager.session().frame_store.current_state_id();
+
+        // Same rows redrawn, same cursor
+        assert!(!apply_frame(
+            &mut state,
+            frame("$ ls", 4),
+            Some(StyleTable::new())
+        ));
+        assert_eq!(
+            state.manager.session().frame_store.current_state_id(),
+            state_id
+        );
+        assert_eq!(state.idle_frame_count, 1);
 
-    if buf.is_empty() {
-        return Ok(None);
+        assert!(apply_frame(
+            &mut state,
+            frame("$ ls", 5),
+            Some(StyleTable::new())
+        ));
+        assert!(apply_frame(
+            &mut state,
+            frame("$ ls -", 5),
+            Some(StyleTable::new())
+        ));
+        assert!(state.manager.session().frame_store.current_state_id() > state_id);
     }
 
-    let mut peek = &buf[..];
-    let len = match prost::encoding::decode_varint(&mut peek) {
-        Ok(len) => len as usize,
-        Err(_) => {
-            if buf.len() < 10 {
-                return Ok(None);
-            }
-            anyhow::bail!("invalid varint in frame header");
-        },
-    };
+    #[test]
+    fn test_cursor_blink_alone_skipped() {
+        let mut state = test_state(Arc::new(RecordingEvents::default()));
+        assert!(apply_frame(
+            &mut state,
+            frame("$ ls", 4),
+            Some(StyleTable::new())
+        ));
+        let state_id = state.manager.session().frame_store.current_state_id();
 
-    if len > MAX_FRAME_SIZE {
-        anyhow::bail!(
-            "frame size {} exceeds maximum allowed size {} bytes",
-            len,
-            MAX_FRAME_SIZE
+        for blink in [false, true, false] {
+            let mut blinking = frame("$ ls", 4);
+            blinking.set_cursor(zellij_remote_core::Cursor {
+                col: 4,
+                blink,
+                ..Default::default()
+            });
+            assert!(!apply_frame(&mut state, blinking, Some(StyleTable::new())));
+        }
+        let session = state.manager.session();
+        assert_eq!(session.frame_store.current_state_id(), state_id);
+        assert!(session.frame_store.current_frame().cursor.blink);
+
+        // The blink carries over with the next move
+        let mut moved = frame("$ ls", 5);
+        moved.set_cursor(zellij_remote_core::Cursor {
+            col: 5,
+            blink: false,
+            ..Default::default()
+        });
+        assert!(apply_frame(&mut state, moved, Some(StyleTable::new())));
+        assert!(
+            !state
+                .manager
+                .session()
+                .frame_store
+                .current_frame()
+                .cursor
+                .blink
         );
     }
 
-    let varint_len = buf.len() - peek.len();
-    let total_len = varint_len + len;
+    #[test]
+    fn test_echo_turning_off_is_a_change() {
+        let mut state = test_state(Arc::new(RecordingEvents::default()));
+        assert!(apply_frame(
+            &mut state,
+            frame("Password:", 9),
+            Some(StyleTable::new())
+        ));
 
-    if buf.len() < total_len {
-        return Ok(None);
+        let mut prompt = frame("Password:", 9);
+        prompt.set_echo_suppressed(true);
+        assert!(apply_frame(&mut state, prompt, Some(StyleTable::new())));
+        assert!(
+            state
+                .manager
+                .session()
+                .frame_store
+                .current_frame()
+                .echo_suppressed
+        );
     }
 
-    buf.advance(varint_len);
-    let frame_data = buf.split_to(len);
-    let envelope = StreamEnvelope::decode(&frame_data[..])?;
-    Ok(Some(envelope))
-}
+    #[test]
+    fn test_capability_update_downgrades_delivery() {
+        let update = CapabilityUpdate {
+            capabilities: Some(Capabilities::default()),
+            supported_codecs: vec![],
+            compression: None,
+        };
+        let reply = renegotiate_delivery(true, &update);
+        assert!(!reply.capabilities.unwrap().supports_datagrams);
+        assert_eq!(reply.compression.unwrap(), negotiate_compression(&[]),);
+    }
 
-fn build_server_hello(
-    client_hello: &ClientHello,
-    client_id: u64,
-    lease: Option<ControllerLease>,
-    resume_token: Vec<u8>,
-    session_name: &str,
-) -> ServerHello {
-    let negotiated_caps = Capabilities {
-        supports_datagrams: client_hello
-            .capabilities
-            .as_ref()
-            .map(|c| c.supports_datagrams)
-            .unwrap_or(false),
-        max_datagram_bytes: zellij_remote_protocol::DEFAULT_MAX_DATAGRAM_BYTES,
-        supports_style_dictionary: true,
-        supports_styled_underlines: false,
-        supports_prediction: true,
-        supports_images: false,
-        supports_clipboard: false,
-        supports_hyperlinks: false,
-    };
+    #[test]
+    fn test_capability_update_cannot_add_datagrams() {
+        let update = CapabilityUpdate {
+            capabilities: Some(Capabilities {
+                supports_datagrams: true,
+                ..Default::default()
+            }),
+            supported_codecs: vec![zellij_remote_protocol::Codec::Zstd as i32],
+            compression: None,
+        };
+        assert!(
+            renegotiate_delivery(true, &update)
+                .capabilities
+                .unwrap()
+                .supports_datagrams
+        );
+        assert!(
+            !renegotiate_delivery(false, &update)
+                .capabilities
+                .unwrap()
+                .supports_datagrams
+        );
+    }
 
-    ServerHello {
-        negotiated_version: Some(ProtocolVersion {
-            major: zellij_remote_protocol::ZRP_VERSION_MAJOR,
-            minor: zellij_remote_protocol::ZRP_VERSION_MINOR,
-        }),
-        negotiated_capabilities: Some(negotiated_caps),
-        client_id,
-        session_name: session_name.to_string(),
-        session_state: SessionState::Running.into(),
-        lease,
-        resume_token,
-        snapshot_interval_ms: 5000,
-        max_inflight_inputs: 256,
-        render_window: zellij_remote_protocol::DEFAULT_RENDER_WINDOW,
+    #[test]
+    fn test_requested_sizes_checked() {
+        let mut event = ConnectionEvent::RequestControl {
+            remote_id: 7,
+            request: zellij_remote_protocol::RequestControl {
+                desired_size: Some(DisplaySize {
+                    cols: u32::MAX,
+                    rows: 24,
+                }),
+                ..Default::default()
+            },
+        };
+        let (remote_id, size) = requested_size(&mut event).unwrap();
+        assert_eq!(remote_id, 7);
+        let e = SizeLimits::default().check(size).unwrap_err();
+        let stream_envelope::Msg::ProtocolError(error) = bad_size_error(&e) else {
+            panic!("expected a ProtocolError");
+        };
+        assert_eq!(error.code(), protocol_error::Code::BadMessage);
+        assert!(!error.fatal);
+
+        let mut event = ConnectionEvent::SetControllerSize {
+            remote_id: 7,
+            request: zellij_remote_protocol::SetControllerSize {
+                size: Some(DisplaySize { cols: 10, rows: 2 }),
+                request_snapshot: false,
+            },
+        };
+        let (_, size) = requested_size(&mut event).unwrap();
+        *size = SizeLimits::default().check(size).unwrap();
+        let ConnectionEvent::SetControllerSize { request, .. } = event else {
+            unreachable!();
+        };
+        assert_eq!(request.size, Some(DisplaySize { cols: 20, rows: 5 }));
+
+        let mut event = ConnectionEvent::ClientDisconnected { remote_id: 7 };
+        assert!(requested_size(&mut event).is_none());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_bad_attach_size_fails_the_attach() {
+        let e = SizeLimits::default()
+            .check(&DisplaySize {
+                cols: u32::MAX,
+                rows: 24,
+            })
+            .unwrap_err();
+
+        let replies = bad_size_replies(true, &e);
+        assert_eq!(replies.len(), 2);
+        let stream_envelope::Msg::AttachResponse(response) = &replies[0] else {
+            panic!("Expected an AttachResponse first, got {:?}", replies[0]);
+        };
+        assert!(!response.ok);
+        assert_eq!(response.error_message, e.to_string());
+        assert!(matches!(replies[1], stream_envelope::Msg::ProtocolError(_)));
+
+        // Other requests only get the error
+        assert_eq!(bad_size_replies(false, &e), vec![bad_size_error(&e)]);
+    }
 
     #[test]
-    fn test_remote_config_default() {
-        let (to_screen, _) = zellij_utils::channels::bounded(1);
-        let config = RemoteConfig {
-            listen_addr: "127.0.0.1:4433".parse().unwrap(),
-            session_name: "zellij".to_string(),
-            initial_size: Size { cols: 80, rows: 24 },
-            to_screen: zellij_utils::channels::SenderWithContext::new(to_screen),
-            bearer_token: None,
+    fn test_hello_attach_request_size_checked() {
+        let size_limits = SizeLimits::default();
+        let mut request = AttachRequest {
+            desired_size: Some(DisplaySize { cols: 0, rows: 24 }),
+            ..Default::default()
         };
-        assert_eq!(config.listen_addr.port(), 4433);
-        assert_eq!(config.session_name, "zellij");
-        assert_eq!(config.initial_size.cols, 80);
-        assert_eq!(config.initial_size.rows, 24);
-        assert!(config.bearer_token.is_none());
+        assert!(check_attach_size(&size_limits, &mut request).is_err());
+
+        let mut request = AttachRequest {
+            desired_size: Some(DisplaySize { cols: 10, rows: 2 }),
+            ..Default::default()
+        };
+        assert!(check_attach_size(&size_limits, &mut request).is_ok());
+        assert_eq!(
+            request.desired_size,
+            size_limits.check(&DisplaySize { cols: 10, rows: 2 }).ok()
+        );
+
+        let mut request = AttachRequest::default();
+        assert!(check_attach_size(&size_limits, &mut request).is_ok());
+        assert_eq!(request.desired_size, None);
     }
 
     #[test]
@@ -1304,4 +5752,68 @@ mod tests {
         let err = result.unwrap_err();
         assert!(err.to_string().contains("exceeds maximum allowed size"));
     }
+
+    #[test]
+    fn test_refusal_answers_attach_request_first() {
+        let error = handshake_error(
+            protocol_error::Code::SessionDegraded,
+            "Session degraded".to_string(),
+            DEGRADED_RETRY_AFTER_MS,
+        );
+        let sent = refusal_envelopes(true, error.clone());
+        assert_eq!(sent.len(), 2);
+        let Some(stream_envelope::Msg::AttachResponse(response)) = &sent[0].msg else {
+            panic!("Expected an AttachResponse first, got {:?}", sent[0]);
+        };
+        assert!(!response.ok);
+        assert_eq!(response.error_message, "Session degraded");
+        assert_eq!(
+            sent[1].msg,
+            Some(stream_envelope::Msg::ProtocolError(error.clone()))
+        );
+
+        let sent = refusal_envelopes(false, error);
+        assert_eq!(sent.len(), 1);
+        assert!(matches!(
+            sent[0].msg,
+            Some(stream_envelope::Msg::ProtocolError(_))
+        ));
+    }
+
+    #[test]
+    fn test_session_switch_reply() {
+        let endpoint = zellij_remote_protocol::Endpoint {
+            host: String::new(),
+            port: 4434,
+        };
+        let reply = session_switch_reply("work".to_string(), Ok(endpoint.clone()));
+        assert_eq!(
+            reply,
+            stream_envelope::Msg::SessionRedirect(SessionRedirect {
+                session_name: "work".to_string(),
+                endpoint: Some(endpoint),
+            })
+        );
+
+        let reply = session_switch_reply("gone".to_string(), Err(SwitchError::NotRunning));
+        let stream_envelope::Msg::ProtocolError(error) = reply else {
+            panic!("Expected a ProtocolError, got {:?}", reply);
+        };
+        assert_eq!(error.code(), protocol_error::Code::SessionNotFound);
+        assert!(!error.fatal);
+    }
+
+    #[test]
+    fn test_client_version_checked_by_major() {
+        let hello = |major, minor| ClientHello {
+            version: Some(ProtocolVersion { major, minor }),
+            ..Default::default()
+        };
+        let ours = zellij_remote_protocol::ZRP_VERSION_MAJOR;
+        assert!(check_client_version(&hello(ours, 99)).is_ok());
+        assert!(check_client_version(&ClientHello::default()).is_ok());
+        let error = check_client_version(&hello(ours + 1, 0)).unwrap_err();
+        assert_eq!(error.code(), protocol_error::Code::BadVersion);
+        assert!(error.fatal);
+    }
 }
@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a write to a client may wait for the peer to take the bytes before the
+/// client is considered gone and torn down
+pub const WRITER_STALL_TIMEOUT_MS: u64 = 15_000;
+
+/// When the write a sender task is blocked in began, shared with the main loop so it
+/// can tear the client down even if the task is stuck where its own write timeout
+/// doesn't reach. Cloning shares the timer.
+#[derive(Debug, Clone)]
+pub struct WriteTimer {
+    origin: Instant,
+    /// Milliseconds after `origin` the pending write began, plus one; 0 while idle
+    pending_since: Arc<AtomicU64>,
+}
+
+impl WriteTimer {
+    pub fn new(origin: Instant) -> Self {
+        Self {
+            origin,
+            pending_since: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn start(&self, now: Instant) {
+        let ms = now.saturating_duration_since(self.origin).as_millis() as u64;
+        self.pending_since.store(ms + 1, Ordering::Relaxed);
+    }
+
+    pub fn finish(&self) {
+        self.pending_since.store(0, Ordering::Relaxed);
+    }
+
+    /// How long the pending write has been waiting; None while idle
+    pub fn pending_for(&self, now: Instant) -> Option<Duration> {
+        let since = self.pending_since.load(Ordering::Relaxed).checked_sub(1)?;
+        let started = self.origin + Duration::from_millis(since);
+        Some(now.saturating_duration_since(started))
+    }
+
+    pub fn is_stalled(&self, now: Instant, stall_timeout: Duration) -> bool {
+        self.pending_for(now)
+            .is_some_and(|pending| pending >= stall_timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_pending_writes_stall() {
+        let origin = Instant::now();
+        let timeout = Duration::from_millis(WRITER_STALL_TIMEOUT_MS);
+        let timer = WriteTimer::new(origin);
+        let later = origin + timeout * 2;
+        assert_eq!(timer.pending_for(later), None);
+        assert!(!timer.is_stalled(later, timeout));
+
+        // Shared with the task doing the writing
+        timer.clone().start(origin);
+        assert_eq!(timer.pending_for(origin), Some(Duration::ZERO));
+        assert!(!timer.is_stalled(origin + timeout / 2, timeout));
+        assert!(timer.is_stalled(origin + timeout, timeout));
+
+        timer.finish();
+        assert!(!timer.is_stalled(later, timeout));
+    }
+}
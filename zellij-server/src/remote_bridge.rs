@@ -4,166 +4,20 @@
 //! FrameStore format for transmission to remote clients.
 
 use std::collections::HashMap;
-use std::rc::Rc;
 
-use crate::panes::grid::{Grid, Row as ZellijRow};
-#[cfg(test)]
-use crate::panes::terminal_character::DEFAULT_STYLES;
-use crate::panes::terminal_character::{
-    AnsiCode, AnsiStyledUnderline, CharacterStyles, CursorShape as ZellijCursorShape, NamedColor,
-    RcCharacterStyles, TerminalCharacter,
+use crate::panes::grid::{Grid, LineSize as ZellijLineSize, Row as ZellijRow};
+use crate::panes::terminal_character::{AnsiCode, CursorShape as ZellijCursorShape};
+use crate::remote::style_convert::get_cached_style_id;
+pub use crate::remote::style_convert::terminal_character_to_cell;
+use zellij_remote_core::{
+    Cell, Cursor, CursorColor, CursorShape, FrameStore, LineSize, RowData, StyleTable,
 };
-use zellij_remote_core::{Cell, Cursor, CursorShape, FrameStore, RowData, StyleTable};
-use zellij_remote_protocol::{color, Color, DefaultColor, Rgb, Style, UnderlineStyle};
-
-fn named_color_to_ansi256(color: NamedColor) -> u32 {
-    match color {
-        NamedColor::Black => 0,
-        NamedColor::Red => 1,
-        NamedColor::Green => 2,
-        NamedColor::Yellow => 3,
-        NamedColor::Blue => 4,
-        NamedColor::Magenta => 5,
-        NamedColor::Cyan => 6,
-        NamedColor::White => 7,
-        NamedColor::BrightBlack => 8,
-        NamedColor::BrightRed => 9,
-        NamedColor::BrightGreen => 10,
-        NamedColor::BrightYellow => 11,
-        NamedColor::BrightBlue => 12,
-        NamedColor::BrightMagenta => 13,
-        NamedColor::BrightCyan => 14,
-        NamedColor::BrightWhite => 15,
-    }
-}
-
-fn ansi_code_to_color(code: &Option<AnsiCode>) -> Option<Color> {
-    match code {
-        None => None,
-        Some(AnsiCode::Reset) => Some(Color {
-            value: Some(color::Value::DefaultColor(DefaultColor {})),
-        }),
-        Some(AnsiCode::NamedColor(named)) => Some(Color {
-            value: Some(color::Value::Ansi256(named_color_to_ansi256(*named))),
-        }),
-        Some(AnsiCode::RgbCode((r, g, b))) => Some(Color {
-            value: Some(color::Value::Rgb(Rgb {
-                r: *r as u32,
-                g: *g as u32,
-                b: *b as u32,
-            })),
-        }),
-        Some(AnsiCode::ColorIndex(idx)) => Some(Color {
-            value: Some(color::Value::Ansi256(*idx as u32)),
-        }),
-        Some(AnsiCode::On | AnsiCode::Underline(_)) => None,
-    }
-}
-
-fn ansi_code_to_underline_style(code: &AnsiCode) -> UnderlineStyle {
-    match code {
-        AnsiCode::On => UnderlineStyle::Single,
-        AnsiCode::Underline(Some(styled)) => match styled {
-            AnsiStyledUnderline::Double => UnderlineStyle::Double,
-            AnsiStyledUnderline::Undercurl => UnderlineStyle::Curly,
-            AnsiStyledUnderline::Underdotted => UnderlineStyle::Dotted,
-            AnsiStyledUnderline::Underdashed => UnderlineStyle::Dashed,
-        },
-        AnsiCode::Underline(None) => UnderlineStyle::Single,
-        _ => UnderlineStyle::None,
-    }
-}
-
-fn character_styles_to_style(styles: &CharacterStyles) -> Style {
-    Style {
-        fg: ansi_code_to_color(&styles.foreground),
-        bg: ansi_code_to_color(&styles.background),
-        bold: styles
-            .bold
-            .as_ref()
-            .map(|c| matches!(c, AnsiCode::On))
-            .unwrap_or(false),
-        dim: styles
-            .dim
-            .as_ref()
-            .map(|c| matches!(c, AnsiCode::On))
-            .unwrap_or(false),
-        italic: styles
-            .italic
-            .as_ref()
-            .map(|c| matches!(c, AnsiCode::On))
-            .unwrap_or(false),
-        reverse: styles
-            .reverse
-            .as_ref()
-            .map(|c| matches!(c, AnsiCode::On))
-            .unwrap_or(false),
-        hidden: styles
-            .hidden
-            .as_ref()
-            .map(|c| matches!(c, AnsiCode::On))
-            .unwrap_or(false),
-        strike: styles
-            .strike
-            .as_ref()
-            .map(|c| matches!(c, AnsiCode::On))
-            .unwrap_or(false),
-        blink_slow: styles
-            .slow_blink
-            .as_ref()
-            .map(|c| matches!(c, AnsiCode::On))
-            .unwrap_or(false),
-        blink_fast: styles
-            .fast_blink
-            .as_ref()
-            .map(|c| matches!(c, AnsiCode::On))
-            .unwrap_or(false),
-        underline: styles
-            .underline
-            .as_ref()
-            .map(|c| ansi_code_to_underline_style(c) as i32)
-            .unwrap_or(UnderlineStyle::Unspecified as i32),
-        underline_color: ansi_code_to_color(&styles.underline_color),
-    }
-}
-
-/// Cache style IDs by RcCharacterStyles pointer to avoid re-encoding
-fn get_cached_style_id(
-    styles: &RcCharacterStyles,
-    style_table: &mut StyleTable,
-    cache: &mut HashMap<usize, u16>,
-) -> u16 {
-    let ptr = match styles {
-        RcCharacterStyles::Reset => 0,
-        RcCharacterStyles::Rc(rc) => Rc::as_ptr(rc) as usize,
-    };
-
-    if let Some(&id) = cache.get(&ptr) {
-        return id;
-    }
-
-    let style = character_styles_to_style(styles);
-    let id = style_table.get_or_insert(&style);
-    cache.insert(ptr, id);
-    id
-}
-
-pub fn terminal_character_to_cell(tc: &TerminalCharacter, style_table: &mut StyleTable) -> Cell {
-    let style = character_styles_to_style(&tc.styles);
-    let style_id = style_table.get_or_insert(&style);
-
-    Cell {
-        codepoint: tc.character as u32,
-        width: tc.width() as u8,
-        style_id,
-    }
-}
 
 fn row_to_frame_row(
     zellij_row: &ZellijRow,
     cols: usize,
     style_table: &mut StyleTable,
-    style_cache: &mut HashMap<usize, u16>,
+    style_cache: &mut HashMap<usize, u32>,
 ) -> RowData {
     let mut cells = Vec::with_capacity(cols);
     let mut col = 0;
@@ -201,7 +55,19 @@ fn row_to_frame_row(
         col += 1;
     }
 
-    RowData { cells }
+    RowData {
+        cells,
+        line_size: zellij_line_size_to_zrp(zellij_row.line_size),
+    }
+}
+
+pub fn zellij_line_size_to_zrp(line_size: ZellijLineSize) -> LineSize {
+    match line_size {
+        ZellijLineSize::Single => LineSize::Single,
+        ZellijLineSize::DoubleWidth => LineSize::DoubleWidth,
+        ZellijLineSize::DoubleHeightTop => LineSize::DoubleHeightTop,
+        ZellijLineSize::DoubleHeightBottom => LineSize::DoubleHeightBottom,
+    }
 }
 
 pub fn zellij_cursor_shape_to_zrp(shape: &ZellijCursorShape) -> (CursorShape, bool) {
@@ -215,11 +81,20 @@ pub fn zellij_cursor_shape_to_zrp(shape: &ZellijCursorShape) -> (CursorShape, bo
     }
 }
 
+/// Only RGB cursor colors (what OSC 12 sets) carry over; the client can't resolve
+/// palette entries the way the local terminal would
+pub fn zellij_cursor_color_to_zrp(color: Option<AnsiCode>) -> Option<CursorColor> {
+    match color? {
+        AnsiCode::RgbCode((r, g, b)) => Some(CursorColor { r, g, b }),
+        _ => None,
+    }
+}
+
 pub fn grid_to_frame_store(grid: &Grid, style_table: &mut StyleTable) -> FrameStore {
     let cols = grid.width;
     let rows = grid.height;
     let mut store = FrameStore::new(cols, rows);
-    let mut style_cache: HashMap<usize, u16> = HashMap::new();
+    let mut style_cache: HashMap<usize, u32> = HashMap::new();
 
     for (row_idx, zellij_row) in grid.viewport().iter().enumerate() {
         if row_idx >= rows {
@@ -240,6 +115,7 @@ pub fn grid_to_frame_store(grid: &Grid, style_table: &mut StyleTable) -> FrameSt
             visible: !grid.cursor_is_hidden() && rows > 0 && cols > 0,
             blink: cursor_blink,
             shape: cursor_shape,
+            color: zellij_cursor_color_to_zrp(grid.cursor_color()),
         }
     } else {
         Cursor {
@@ -248,6 +124,7 @@ pub fn grid_to_frame_store(grid: &Grid, style_table: &mut StyleTable) -> FrameSt
             visible: false,
             blink: false,
             shape: CursorShape::Block,
+            color: None,
         }
     };
 
@@ -271,7 +148,7 @@ where
     I: Iterator<Item = &'a ZellijRow>,
 {
     let mut store = FrameStore::new(cols, rows);
-    let mut style_cache: HashMap<usize, u16> = HashMap::new();
+    let mut style_cache: HashMap<usize, u32> = HashMap::new();
 
     for (row_idx, zellij_row) in viewport.enumerate() {
         if row_idx >= rows {
@@ -289,6 +166,7 @@ where
         visible: cursor_visible && rows > 0 && cols > 0,
         blink: cursor_blink,
         shape: cursor_shape,
+        color: None,
     });
 
     store.advance_state();
@@ -298,6 +176,15 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::panes::terminal_character::{
+        AnsiCode, AnsiStyledUnderline, NamedColor, RcCharacterStyles, TerminalCharacter,
+        DEFAULT_STYLES,
+    };
+    use crate::remote::style_convert::{
+        ansi_code_to_color, ansi_code_to_underline_style, character_styles_to_style,
+        named_color_to_ansi256,
+    };
+    use zellij_remote_protocol::{color, UnderlineStyle};
 
     #[test]
     fn test_named_color_mapping() {
@@ -370,6 +257,18 @@ mod tests {
         assert_eq!(style.underline, UnderlineStyle::Unspecified as i32);
     }
 
+    #[test]
+    fn test_line_size_follows_grid_row() {
+        let mut zellij_row = ZellijRow::new().with_character(TerminalCharacter::new('A'));
+        zellij_row.line_size = ZellijLineSize::DoubleWidth;
+        let mut style_table = StyleTable::new();
+        let mut cache = HashMap::new();
+
+        let row = row_to_frame_row(&zellij_row, 4, &mut style_table, &mut cache);
+        assert_eq!(row.line_size, LineSize::DoubleWidth);
+        assert_eq!(row.cells.len(), 4);
+    }
+
     #[test]
     fn test_cursor_shape_conversion() {
         let (shape, blink) = zellij_cursor_shape_to_zrp(&ZellijCursorShape::Block);
@@ -381,6 +280,23 @@ mod tests {
         assert!(blink);
     }
 
+    #[test]
+    fn test_cursor_color_conversion() {
+        assert_eq!(
+            zellij_cursor_color_to_zrp(Some(AnsiCode::RgbCode((255, 128, 0)))),
+            Some(CursorColor {
+                r: 255,
+                g: 128,
+                b: 0
+            })
+        );
+        assert_eq!(
+            zellij_cursor_color_to_zrp(Some(AnsiCode::ColorIndex(3))),
+            None
+        );
+        assert_eq!(zellij_cursor_color_to_zrp(None), None);
+    }
+
     #[test]
     fn test_terminal_character_conversion() {
         let mut style_table = StyleTable::new();
@@ -393,7 +309,7 @@ mod tests {
     #[test]
     fn test_style_caching() {
         let mut style_table = StyleTable::new();
-        let mut cache: HashMap<usize, u16> = HashMap::new();
+        let mut cache: HashMap<usize, u32> = HashMap::new();
 
         let styles1 = RcCharacterStyles::default();
         let styles2 = styles1.clone();
@@ -1545,6 +1545,17 @@ pub(crate) fn route_action(
                 ))
                 .with_context(err_context)?;
         },
+        Action::ToggleRemoteStreaming => {
+            drop(completion_tx); // the remote thread doesn't report back
+            #[cfg(feature = "remote")]
+            if let Err(e) =
+                senders.send_to_remote(crate::remote::RemoteInstruction::ToggleStreamingPaused)
+            {
+                log::error!("Failed to pause or resume remote streaming: {:?}", e);
+            }
+            #[cfg(not(feature = "remote"))]
+            log::error!("This version of Zellij was compiled without remote access support!");
+        },
     }
     let result = wait_for_action_completion(completion_rx, &action_name, wait_forever);
     if let Some(exit_status) = result.exit_status {
@@ -13,8 +13,9 @@ use crate::route::NotificationEnd;
 use log::{debug, warn};
 use zellij_utils::data::{
     CommandOrPlugin, Direction, FloatingPaneCoordinates, KeyWithModifier, NewPanePlacement,
-    PaneContents, PaneManifest, PaneScrollbackResponse, PluginPermission, Resize, ResizeStrategy,
-    SessionInfo, Styling, WebSharing,
+    PaneContents, PaneManifest, PaneScrollbackResponse, PluginPermission, RemoteApprovalRequest,
+    RemoteClientInfo, RemoteFrameStage, RemoteInputLatency, Resize, ResizeStrategy, SessionInfo,
+    Styling, WebSharing,
 };
 use zellij_utils::errors::prelude::*;
 use zellij_utils::input::command::RunCommand;
@@ -39,6 +40,7 @@ use crate::background_jobs::BackgroundJob;
 use crate::os_input_output::ResizeCache;
 use crate::pane_groups::PaneGroups;
 use crate::panes::alacritty_functions::xparse_color;
+use crate::panes::terminal_answers::{set_controller_answers, TerminalAnswers};
 use crate::panes::terminal_character::AnsiCode;
 use crate::panes::terminal_pane::{BRACKETED_PASTE_BEGIN, BRACKETED_PASTE_END};
 use crate::session_layout_metadata::{PaneLayoutMetadata, SessionLayoutMetadata};
@@ -56,7 +58,10 @@ use crate::{
 };
 
 #[cfg(feature = "remote")]
-use crate::remote::{chunks_to_frame_store, RemoteInstruction};
+use crate::remote::{
+    keybinding_info, pane_layout, set_frame_cursor, HostCursor, RemoteCanvas, RemoteInstruction,
+    REMOTE_CLIENT_ID,
+};
 use zellij_utils::{
     data::{Event, InputMode, ModeInfo, Palette, PaletteColor, PluginCapabilities, Style, TabInfo},
     errors::{ContextType, ScreenContext},
@@ -504,6 +509,15 @@ pub enum ScreenInstruction {
     RemoveWatcherClient(ClientId),
     SetFollowedClient(ClientId),
     WatcherTerminalResize(ClientId, Size),
+    RemoteClientsChanged(Vec<RemoteClientInfo>),
+    RemoteServingChanged(Option<String>), // listen address, None when stopped
+    RemoteTerminalAnswers(Option<TerminalAnswers>), // None when no remote client is in control
+    RemoteFrameStages(Vec<RemoteFrameStage>),
+    RemoteInputLatency(Vec<RemoteInputLatency>),
+    ListRemoteClientsToPlugin(PluginId, ClientId),
+    RemoteApprovalRequested(RemoteApprovalRequest),
+    RemoteApprovalResolved(u64), // request_id
+    RemoteHeartbeat,
 }
 
 impl From<&ScreenInstruction> for ScreenContext {
@@ -746,6 +760,19 @@ impl From<&ScreenInstruction> for ScreenContext {
             ScreenInstruction::RemoveWatcherClient(..) => ScreenContext::RemoveWatcherClient,
             ScreenInstruction::SetFollowedClient(..) => ScreenContext::SetFollowedClient,
             ScreenInstruction::WatcherTerminalResize(..) => ScreenContext::WatcherTerminalResize, // NEW
+            ScreenInstruction::RemoteClientsChanged(..) => ScreenContext::RemoteClientsChanged,
+            ScreenInstruction::RemoteServingChanged(..) => ScreenContext::RemoteServingChanged,
+            ScreenInstruction::RemoteTerminalAnswers(..) => ScreenContext::RemoteTerminalAnswers,
+            ScreenInstruction::RemoteFrameStages(..) => ScreenContext::RemoteFrameStages,
+            ScreenInstruction::RemoteInputLatency(..) => ScreenContext::RemoteInputLatency,
+            ScreenInstruction::ListRemoteClientsToPlugin(..) => {
+                ScreenContext::ListRemoteClientsToPlugin
+            },
+            ScreenInstruction::RemoteApprovalRequested(..) => {
+                ScreenContext::RemoteApprovalRequested
+            },
+            ScreenInstruction::RemoteApprovalResolved(..) => ScreenContext::RemoteApprovalResolved,
+            ScreenInstruction::RemoteHeartbeat => ScreenContext::RemoteHeartbeat,
         }
     }
 }
@@ -925,6 +952,22 @@ pub(crate) struct Screen {
     render_blocker: RenderBlocker,
     watcher_clients: HashMap<ClientId, WatcherState>,
     followed_client_id: Option<ClientId>,
+    remote_clients: Vec<RemoteClientInfo>,
+    remote_listen_address: Option<String>,
+    remote_frame_stages: Vec<RemoteFrameStage>,
+    remote_input_latency: Vec<RemoteInputLatency>,
+    /// Focused pane and title last reported to the remote thread
+    #[cfg(feature = "remote")]
+    remote_focus: Option<(PaneId, String)>,
+    /// Pane layout last reported to the remote thread
+    #[cfg(feature = "remote")]
+    remote_pane_layout: Option<zellij_remote_protocol::PaneLayout>,
+    /// Keybindings and default mode last reported to the remote thread
+    #[cfg(feature = "remote")]
+    remote_keybinds: Option<(zellij_utils::data::KeybindsVec, InputMode)>,
+    /// The composited screen remote clients see, painted over by every render
+    #[cfg(feature = "remote")]
+    remote_canvas: RemoteCanvas,
 }
 
 impl Screen {
@@ -1010,6 +1053,18 @@ impl Screen {
             render_blocker: RenderBlocker::new(100),
             watcher_clients: HashMap::new(),
             followed_client_id: None,
+            remote_clients: vec![],
+            remote_listen_address: None,
+            remote_frame_stages: vec![],
+            remote_input_latency: vec![],
+            #[cfg(feature = "remote")]
+            remote_focus: None,
+            #[cfg(feature = "remote")]
+            remote_pane_layout: None,
+            #[cfg(feature = "remote")]
+            remote_keybinds: None,
+            #[cfg(feature = "remote")]
+            remote_canvas: RemoteCanvas::new(),
         }
     }
 
@@ -1481,13 +1536,18 @@ impl Screen {
     }
 
     #[cfg(feature = "remote")]
-    fn send_to_remote(&self, output: &Output, connected_clients: &HashSet<ClientId>) {
-        use zellij_remote_core::StyleTable;
-
-        // Send a single frame notification to the remote thread using the first available
-        // local client's frame data. The remote thread will broadcast to all WebTransport clients.
-        // This avoids sending duplicate frames when multiple local clients are connected.
-        if let Some(&client_id) = connected_clients.iter().next() {
+    fn send_to_remote(&mut self, output: &Output, connected_clients: &HashSet<ClientId>) {
+        // Send a single frame notification to the remote thread using the remote client's
+        // frame data, or the first available local client's. The remote thread will broadcast
+        // to all WebTransport clients. This avoids sending duplicate frames when multiple local
+        // clients are connected.
+        let client_id = if connected_clients.contains(&REMOTE_CLIENT_ID) {
+            Some(REMOTE_CLIENT_ID)
+        } else {
+            connected_clients.iter().next().copied()
+        };
+        if let Some(client_id) = client_id {
+            self.report_bells_to_remote(output, client_id);
             if let Some(chunks) = output.get_client_character_chunks(client_id) {
                 if chunks.is_empty() {
                     return;
@@ -1495,21 +1555,202 @@ impl Screen {
 
                 let size = self.size;
 
-                let mut style_table = StyleTable::new();
-                let frame_store =
-                    chunks_to_frame_store(chunks, size.cols, size.rows, &mut style_table);
+                let convert_started = std::time::Instant::now();
+                let (mut frame_store, style_table) = self
+                    .remote_canvas
+                    .paint(client_id, chunks, size.cols, size.rows);
+                set_frame_cursor(&mut frame_store, self.host_cursor(client_id));
+                frame_store.set_echo_suppressed(self.echo_suppressed(client_id));
 
                 let instruction = RemoteInstruction::FrameReady {
                     client_id,
                     frame_store,
                     style_table,
+                    convert_time: convert_started.elapsed(),
                 };
 
                 let _ = self.bus.senders.send_to_remote(instruction);
             }
+            self.report_focus_to_remote(client_id);
+            self.report_pane_layout_to_remote(client_id);
+            self.report_keybindings_to_remote(client_id);
         }
     }
 
+    /// Pass on the terminal panes that rang the bell, and whether each is the pane
+    /// `client_id` (whose frames remote clients see) has focused
+    #[cfg(feature = "remote")]
+    fn report_bells_to_remote(&self, output: &Output, client_id: ClientId) {
+        if output.bells().is_empty() || self.remote_clients.is_empty() {
+            return;
+        }
+        let focused = self
+            .get_active_tab(client_id)
+            .ok()
+            .and_then(|tab| tab.get_active_pane(client_id))
+            .map(|pane| pane.pid());
+        for pane_id in output.bells() {
+            if let PaneId::Terminal(terminal_id) = pane_id {
+                let _ = self.bus.senders.send_to_remote(RemoteInstruction::Bell {
+                    pane_id: *terminal_id,
+                    focused: focused == Some(*pane_id),
+                });
+            }
+        }
+    }
+
+    /// The cursor `client_id`'s terminal draws: the active pane's, unless it's hidden
+    #[cfg(feature = "remote")]
+    fn host_cursor(&self, client_id: ClientId) -> Option<HostCursor> {
+        let tab = self.get_active_tab(client_id).ok()?;
+        let (x, y) = tab.get_active_terminal_cursor_position(client_id)?;
+        let pane = tab.get_active_pane(client_id)?;
+        Some(HostCursor {
+            x,
+            y,
+            shape: pane.cursor_shape(),
+            color: pane.cursor_color(),
+        })
+    }
+
+    /// Whether the terminal `client_id` has focused is at a password prompt, so remote
+    /// clients shouldn't echo what they type locally.
+    #[cfg(feature = "remote")]
+    fn echo_suppressed(&self, client_id: ClientId) -> bool {
+        let terminal_id = match self
+            .get_active_tab(client_id)
+            .ok()
+            .and_then(|tab| tab.get_active_pane(client_id))
+            .map(|pane| pane.pid())
+        {
+            Some(PaneId::Terminal(terminal_id)) => terminal_id,
+            _ => return false,
+        };
+        self.bus
+            .os_input
+            .as_ref()
+            .and_then(|os_input| os_input.hides_typed_input(terminal_id))
+            .unwrap_or(false)
+    }
+
+    /// Tell the remote thread about the pane the frames are focused on, and the pty to
+    /// follow its cwd and command, when either changes. Skipped while no remote client
+    /// is attached so the pty isn't polling processes for nobody.
+    #[cfg(feature = "remote")]
+    fn report_focus_to_remote(&mut self, client_id: ClientId) {
+        if self.remote_clients.is_empty() {
+            return;
+        }
+        let focus = self
+            .get_active_tab(client_id)
+            .ok()
+            .and_then(|tab| tab.get_active_pane(client_id))
+            .map(|pane| (pane.pid(), pane.current_title()));
+        if focus == self.remote_focus {
+            return;
+        }
+        if let Some((pane_id, title)) = &focus {
+            let _ = self
+                .bus
+                .senders
+                .send_to_remote(RemoteInstruction::FocusedPaneTitle {
+                    title: title.clone(),
+                });
+            if self.remote_focus.as_ref().map(|(id, _)| id) != Some(pane_id) {
+                let _ = self
+                    .bus
+                    .senders
+                    .send_to_pty(PtyInstruction::ReportFocusToRemote(Some(*pane_id)));
+            }
+        }
+        self.remote_focus = focus;
+    }
+
+    /// Tell the remote thread where the panes of `client_id`'s tab are when they moved,
+    /// were retitled or the focus changed. Skipped while no remote client is attached.
+    #[cfg(feature = "remote")]
+    fn report_pane_layout_to_remote(&mut self, client_id: ClientId) {
+        if self.remote_clients.is_empty() {
+            return;
+        }
+        let Ok(tab) = self.get_active_tab(client_id) else {
+            return;
+        };
+        let layout = pane_layout(
+            tab.pane_infos(),
+            tab.get_active_pane_id(client_id),
+            tab.are_floating_panes_visible(),
+        );
+        if self.remote_pane_layout.as_ref() == Some(&layout) {
+            return;
+        }
+        let _ = self
+            .bus
+            .senders
+            .send_to_remote(RemoteInstruction::PaneLayout(layout.clone()));
+        self.remote_pane_layout = Some(layout);
+    }
+
+    /// Tell the remote thread the keybindings `client_id` has when they changed, e.g.
+    /// after a config reload. Skipped while no remote client is attached.
+    #[cfg(feature = "remote")]
+    fn report_keybindings_to_remote(&mut self, client_id: ClientId) {
+        if self.remote_clients.is_empty() {
+            return;
+        }
+        let mode_info = self
+            .mode_info
+            .get(&client_id)
+            .unwrap_or(&self.default_mode_info);
+        let default_mode = mode_info.base_mode.unwrap_or(InputMode::Normal);
+        if self
+            .remote_keybinds
+            .as_ref()
+            .is_some_and(|(keybinds, mode)| {
+                *keybinds == mode_info.keybinds && *mode == default_mode
+            })
+        {
+            return;
+        }
+        let _ = self
+            .bus
+            .senders
+            .send_to_remote(RemoteInstruction::Keybindings(keybinding_info(
+                &mode_info.keybinds,
+                default_mode,
+            )));
+        self.remote_keybinds = Some((mode_info.keybinds.clone(), default_mode));
+    }
+
+    /// Attach the client remote controllers act through, unless it already is
+    #[cfg(feature = "remote")]
+    fn attach_remote_client(&mut self) -> Result<()> {
+        if self
+            .connected_clients
+            .borrow()
+            .contains_key(&REMOTE_CLIENT_ID)
+        {
+            return Ok(());
+        }
+        self.add_client(REMOTE_CLIENT_ID, false)?;
+        self.log_and_report_session_state()?;
+        self.render(None)
+    }
+
+    /// Detach the client remote controllers act through, if it is attached
+    #[cfg(feature = "remote")]
+    fn detach_remote_client(&mut self) -> Result<()> {
+        if !self
+            .connected_clients
+            .borrow()
+            .contains_key(&REMOTE_CLIENT_ID)
+        {
+            return Ok(());
+        }
+        self.remove_client(REMOTE_CLIENT_ID)?;
+        self.render(None)
+    }
+
     pub fn render_to_clients(&mut self) -> Result<()> {
         // this method does the actual rendering and is triggered by a debounced BackgroundJob (see
         // the render method for more details)
@@ -2176,7 +2417,11 @@ impl Screen {
             name: self.session_name.clone(),
             tabs: tab_infos,
             panes: pane_manifest,
-            connected_clients: self.active_tab_indices.keys().len(),
+            connected_clients: self
+                .active_tab_indices
+                .keys()
+                .filter(|client_id| !is_remote_client(**client_id))
+                .count(),
             is_current_session: true,
             available_layouts,
             web_clients_allowed: self.web_sharing.web_clients_allowed(),
@@ -2186,6 +2431,15 @@ impl Screen {
                 .iter()
                 .filter(|(_client_id, is_web_client)| **is_web_client)
                 .count(),
+            remote_client_count: self.remote_clients.len(),
+            remote_controller: self
+                .remote_clients
+                .iter()
+                .find(|c| c.is_controller)
+                .map(|c| c.name.clone()),
+            remote_listen_address: self.remote_listen_address.clone(),
+            remote_frame_stages: self.remote_frame_stages.clone(),
+            remote_input_latency: self.remote_input_latency.clone(),
             plugins: Default::default(), // these are filled in by the wasm thread
             tab_history: self.tab_history.clone(),
             pane_history: self
@@ -6580,11 +6834,148 @@ pub(crate) fn screen_thread_main(
                 screen.set_watcher_size(client_id, size);
                 screen.render(None)?;
             },
+            ScreenInstruction::RemoteClientsChanged(remote_clients) => {
+                let events = remote_client_events(&screen.remote_clients, &remote_clients);
+                screen.remote_clients = remote_clients;
+                #[cfg(feature = "remote")]
+                if screen.remote_clients.is_empty() && screen.remote_focus.take().is_some() {
+                    let _ = screen
+                        .bus
+                        .senders
+                        .send_to_pty(PtyInstruction::ReportFocusToRemote(None));
+                }
+                #[cfg(feature = "remote")]
+                if screen.remote_clients.is_empty() {
+                    screen.remote_pane_layout = None;
+                    screen.remote_keybinds = None;
+                }
+                #[cfg(feature = "remote")]
+                if !screen.remote_clients.is_empty() {
+                    screen
+                        .attach_remote_client()
+                        .context("failed to attach the remote client")
+                        .non_fatal();
+                }
+                if !events.is_empty() {
+                    screen
+                        .bus
+                        .senders
+                        .send_to_plugin(PluginInstruction::Update(
+                            events.into_iter().map(|e| (None, None, e)).collect(),
+                        ))
+                        .context("failed to report remote client changes")
+                        .non_fatal();
+                }
+                screen.log_and_report_session_state()?;
+            },
+            ScreenInstruction::RemoteServingChanged(listen_address) => {
+                #[cfg(feature = "remote")]
+                if listen_address.is_none() {
+                    screen
+                        .detach_remote_client()
+                        .context("failed to detach the remote client")
+                        .non_fatal();
+                }
+                if listen_address.is_none() {
+                    set_controller_answers(None);
+                    screen.remote_frame_stages.clear();
+                    screen.remote_input_latency.clear();
+                }
+                screen.remote_listen_address = listen_address;
+                screen.log_and_report_session_state()?;
+            },
+            ScreenInstruction::RemoteTerminalAnswers(answers) => {
+                set_controller_answers(answers);
+            },
+            ScreenInstruction::RemoteFrameStages(stages) => {
+                screen.remote_frame_stages = stages;
+                screen.log_and_report_session_state()?;
+            },
+            ScreenInstruction::RemoteInputLatency(latency) => {
+                screen.remote_input_latency = latency;
+                screen.log_and_report_session_state()?;
+            },
+            ScreenInstruction::ListRemoteClientsToPlugin(plugin_id, client_id) => {
+                screen
+                    .bus
+                    .senders
+                    .send_to_plugin(PluginInstruction::Update(vec![(
+                        Some(plugin_id),
+                        Some(client_id),
+                        Event::ListRemoteClients(screen.remote_clients.clone()),
+                    )]))
+                    .context("failed to list remote clients")
+                    .non_fatal();
+            },
+            ScreenInstruction::RemoteApprovalRequested(request) => {
+                screen
+                    .bus
+                    .senders
+                    .send_to_plugin(PluginInstruction::Update(vec![(
+                        None,
+                        None,
+                        Event::RemoteApprovalRequested(request),
+                    )]))
+                    .context("failed to report remote approval request")
+                    .non_fatal();
+            },
+            ScreenInstruction::RemoteApprovalResolved(request_id) => {
+                screen
+                    .bus
+                    .senders
+                    .send_to_plugin(PluginInstruction::Update(vec![(
+                        None,
+                        None,
+                        Event::RemoteApprovalResolved(request_id),
+                    )]))
+                    .context("failed to report remote approval resolution")
+                    .non_fatal();
+            },
+            ScreenInstruction::RemoteHeartbeat => {
+                #[cfg(feature = "remote")]
+                let _ = screen
+                    .bus
+                    .senders
+                    .send_to_remote(RemoteInstruction::ScreenHeartbeat);
+            },
         }
     }
     Ok(())
 }
 
+/// Plugin events describing how the remote clients changed between two reports
+fn remote_client_events(previous: &[RemoteClientInfo], current: &[RemoteClientInfo]) -> Vec<Event> {
+    let mut events = vec![];
+    for client in previous {
+        if !current.iter().any(|c| c.remote_id == client.remote_id) {
+            events.push(Event::RemoteClientDetached(client.clone()));
+        }
+    }
+    for client in current {
+        if !previous.iter().any(|c| c.remote_id == client.remote_id) {
+            events.push(Event::RemoteClientAttached(client.clone()));
+        }
+    }
+    let previous_controller = previous.iter().find(|c| c.is_controller);
+    let current_controller = current.iter().find(|c| c.is_controller);
+    if previous_controller.map(|c| c.remote_id) != current_controller.map(|c| c.remote_id) {
+        events.push(Event::RemoteLeaseChanged(current_controller.cloned()));
+    }
+    events
+}
+
+/// Whether `client_id` is the client remote controllers act through rather than a
+/// local terminal
+#[cfg(feature = "remote")]
+fn is_remote_client(client_id: ClientId) -> bool {
+    client_id == REMOTE_CLIENT_ID
+}
+
+#[cfg(not(feature = "remote"))]
+fn is_remote_client(_client_id: ClientId) -> bool {
+    false
+}
+
 #[path = "./unit/screen_tests.rs"]
 #[cfg(test)]
 mod screen_tests;
@@ -38,6 +38,7 @@ use crate::{
     output::{CharacterChunk, Output, SixelImageChunk},
     panes::floating_panes::floating_pane_grid::half_size_middle_geom,
     panes::sixel::SixelImageStore,
+    panes::terminal_character::{AnsiCode, CursorShape},
     panes::{FloatingPanes, TiledPanes},
     panes::{LinkHandler, PaneId, PluginPane, TerminalPane},
     plugins::PluginInstruction,
@@ -359,6 +360,12 @@ pub trait Pane {
     fn cursor_shape_csi(&self) -> String {
         "\u{1b}[0 q".to_string() // default to non blinking block
     }
+    fn cursor_shape(&self) -> CursorShape {
+        CursorShape::Initial
+    }
+    fn cursor_color(&self) -> Option<AnsiCode> {
+        None
+    }
     fn contains(&self, position: &Position) -> bool {
         match self.geom_override() {
             Some(position_and_size) => position_and_size.contains(position),
@@ -92,6 +92,9 @@ impl<'a> PaneContentsAndUi<'a> {
                 self.z_index,
             );
             if let Some(raw_vte_output) = raw_vte_output {
+                if raw_vte_output.contains('\u{7}') {
+                    self.output.add_bell(self.pane.pid());
+                }
                 if !raw_vte_output.is_empty() {
                     self.output.add_post_vte_instruction_to_multiple_clients(
                         clients.iter().copied(),
@@ -124,6 +127,9 @@ impl<'a> PaneContentsAndUi<'a> {
                 self.z_index,
             );
             if let Some(raw_vte_output) = raw_vte_output {
+                if raw_vte_output.contains('\u{7}') {
+                    self.output.add_bell(self.pane.pid());
+                }
                 self.output.add_post_vte_instruction_to_client(
                     client_id,
                     &format!(
@@ -12,7 +12,6 @@ impl TestTerminal {
         TestTerminal { openpty }
     }
 
-    #[allow(dead_code)]
     pub fn master(&self) -> RawFd {
         self.openpty.master
     }
@@ -49,3 +48,39 @@ fn get_cwd() {
         pid
     );
 }
+
+fn server_with_terminal(terminal_id: u32, test_terminal: &TestTerminal) -> ServerOsInputOutput {
+    let mut terminal_id_to_raw_fd = BTreeMap::new();
+    terminal_id_to_raw_fd.insert(terminal_id, Some(test_terminal.master()));
+    ServerOsInputOutput {
+        orig_termios: Arc::new(Mutex::new(None)),
+        client_senders: Arc::default(),
+        terminal_id_to_raw_fd: Arc::new(Mutex::new(terminal_id_to_raw_fd)),
+        cached_resizes: Arc::default(),
+    }
+}
+
+#[test]
+fn canonical_terminal_without_echo_hides_typed_input() {
+    let test_terminal = TestTerminal::new();
+    let mut attrs = termios::tcgetattr(test_terminal.slave()).unwrap();
+    attrs.local_flags.remove(termios::LocalFlags::ECHO);
+    attrs.local_flags.insert(termios::LocalFlags::ICANON);
+    termios::tcsetattr(test_terminal.slave(), termios::SetArg::TCSANOW, &attrs).unwrap();
+
+    let server = server_with_terminal(1, &test_terminal);
+    assert_eq!(server.hides_typed_input(1), Some(true));
+    assert_eq!(server.hides_typed_input(2), None);
+}
+
+#[test]
+fn raw_terminal_without_echo_does_not_hide_typed_input() {
+    let test_terminal = TestTerminal::new();
+    let mut attrs = termios::tcgetattr(test_terminal.slave()).unwrap();
+    termios::cfmakeraw(&mut attrs);
+    assert!(!attrs.local_flags.contains(termios::LocalFlags::ECHO));
+    termios::tcsetattr(test_terminal.slave(), termios::SetArg::TCSANOW, &attrs).unwrap();
+
+    let server = server_with_terminal(1, &test_terminal);
+    assert_eq!(server.hides_typed_input(1), Some(false));
+}
@@ -4206,3 +4206,46 @@ pub fn send_cli_change_floating_pane_coordinates_action() {
     }
     assert_snapshot!(format!("{}", snapshot_count));
 }
+
+#[test]
+fn remote_client_changes_are_reported_as_plugin_events() {
+    use super::remote_client_events;
+    use zellij_utils::data::RemoteClientInfo;
+    let client = |remote_id: u64, name: &str, is_controller: bool| RemoteClientInfo {
+        remote_id,
+        name: name.to_owned(),
+        is_controller,
+        ..Default::default()
+    };
+
+    let alice = client(1, "alice@ios", true);
+    assert_eq!(
+        remote_client_events(&[], &[alice.clone()]),
+        vec![
+            Event::RemoteClientAttached(alice.clone()),
+            Event::RemoteLeaseChanged(Some(alice.clone())),
+        ]
+    );
+
+    let bob = client(2, "bob@laptop", false);
+    let both = vec![alice.clone(), bob.clone()];
+    assert_eq!(
+        remote_client_events(&[alice.clone()], &both),
+        vec![Event::RemoteClientAttached(bob.clone())]
+    );
+
+    let bob_in_control = vec![client(1, "alice@ios", false), client(2, "bob@laptop", true)];
+    assert_eq!(
+        remote_client_events(&both, &bob_in_control),
+        vec![Event::RemoteLeaseChanged(Some(bob_in_control[1].clone()))]
+    );
+
+    assert_eq!(
+        remote_client_events(&bob_in_control, &[]),
+        vec![
+            Event::RemoteClientDetached(bob_in_control[0].clone()),
+            Event::RemoteClientDetached(bob_in_control[1].clone()),
+            Event::RemoteLeaseChanged(None),
+        ]
+    );
+}
@@ -1026,6 +1026,56 @@ pub fn list_clients() {
     unsafe { host_run_plugin_command() };
 }
 
+/// Get a list of clients attached over the remote protocol back as an
+/// Event::ListRemoteClients (note: this event must be subscribed to)
+pub fn list_remote_clients() {
+    let plugin_command = PluginCommand::ListRemoteClients;
+    let protobuf_plugin_command: ProtobufPluginCommand = plugin_command.try_into().unwrap();
+    object_to_stdout(&protobuf_plugin_command.encode_to_vec());
+    unsafe { host_run_plugin_command() };
+}
+
+/// Take control away from the remote client holding it, leaving it attached as a viewer
+pub fn revoke_remote_lease() {
+    let plugin_command = PluginCommand::RevokeRemoteLease;
+    let protobuf_plugin_command: ProtobufPluginCommand = plugin_command.try_into().unwrap();
+    object_to_stdout(&protobuf_plugin_command.encode_to_vec());
+    unsafe { host_run_plugin_command() };
+}
+
+/// Start serving this session to remote clients again after `stop_remote_serving`
+pub fn start_remote_serving() {
+    let plugin_command = PluginCommand::StartRemoteServing;
+    let protobuf_plugin_command: ProtobufPluginCommand = plugin_command.try_into().unwrap();
+    object_to_stdout(&protobuf_plugin_command.encode_to_vec());
+    unsafe { host_run_plugin_command() };
+}
+
+/// Disconnect all remote clients of this session and stop listening for new ones
+pub fn stop_remote_serving() {
+    let plugin_command = PluginCommand::StopRemoteServing;
+    let protobuf_plugin_command: ProtobufPluginCommand = plugin_command.try_into().unwrap();
+    object_to_stdout(&protobuf_plugin_command.encode_to_vec());
+    unsafe { host_run_plugin_command() };
+}
+
+/// Approve or deny a remote client's request, as announced by `Event::RemoteApprovalRequested`
+pub fn respond_to_remote_approval(request_id: u64, approved: bool) {
+    let plugin_command = PluginCommand::RespondToRemoteApproval(request_id, approved);
+    let protobuf_plugin_command: ProtobufPluginCommand = plugin_command.try_into().unwrap();
+    object_to_stdout(&protobuf_plugin_command.encode_to_vec());
+    unsafe { host_run_plugin_command() };
+}
+
+/// Hold back everything sent to a remote client for `delay_ms`, e.g. to try prediction
+/// settings under a known latency; 0 stops delaying it
+pub fn set_remote_client_latency(remote_id: u64, delay_ms: u64) {
+    let plugin_command = PluginCommand::SetRemoteClientLatency(remote_id, delay_ms);
+    let protobuf_plugin_command: ProtobufPluginCommand = plugin_command.try_into().unwrap();
+    object_to_stdout(&protobuf_plugin_command.encode_to_vec());
+    unsafe { host_run_plugin_command() };
+}
+
 /// Change configuration for the current user
 pub fn reconfigure(new_config: String, save_configuration_file: bool) {
     let plugin_command = PluginCommand::Reconfigure(new_config, save_configuration_file);
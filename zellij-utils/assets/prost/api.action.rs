@@ -1015,6 +1015,7 @@ pub enum ActionName {
     NewBlockingPane = 91,
     NewInPlacePane = 92,
     OverrideLayout = 93,
+    ToggleRemoteStreaming = 94,
 }
 impl ActionName {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -1114,6 +1115,7 @@ impl ActionName {
             ActionName::NewBlockingPane => "NewBlockingPane",
             ActionName::NewInPlacePane => "NewInPlacePane",
             ActionName::OverrideLayout => "OverrideLayout",
+            ActionName::ToggleRemoteStreaming => "ToggleRemoteStreaming",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -1210,6 +1212,7 @@ impl ActionName {
             "NewBlockingPane" => Some(Self::NewBlockingPane),
             "NewInPlacePane" => Some(Self::NewInPlacePane),
             "OverrideLayout" => Some(Self::OverrideLayout),
+            "ToggleRemoteStreaming" => Some(Self::ToggleRemoteStreaming),
             _ => None,
         }
     }
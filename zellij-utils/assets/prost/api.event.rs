@@ -9,7 +9,7 @@ pub struct EventNameList {
 pub struct Event {
     #[prost(enumeration="EventType", tag="1")]
     pub name: i32,
-    #[prost(oneof="event::Payload", tags="2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33")]
+    #[prost(oneof="event::Payload", tags="2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38")]
     pub payload: ::core::option::Option<event::Payload>,
 }
 /// Nested message and enum types in `Event`.
@@ -81,10 +81,68 @@ pub mod event {
         ActionCompletePayload(super::ActionCompletePayload),
         #[prost(message, tag="33")]
         CwdChangedPayload(super::CwdChangedPayload),
+        #[prost(message, tag="34")]
+        RemoteClientPayload(super::RemoteClientInfo),
+        #[prost(message, tag="35")]
+        RemoteLeaseChangedPayload(super::RemoteLeaseChangedPayload),
+        #[prost(message, tag="36")]
+        ListRemoteClientsPayload(super::ListRemoteClientsPayload),
+        #[prost(message, tag="37")]
+        RemoteApprovalRequestedPayload(super::RemoteApprovalRequestedPayload),
+        #[prost(message, tag="38")]
+        RemoteApprovalResolvedPayload(super::RemoteApprovalResolvedPayload),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoteClientInfo {
+    #[prost(uint64, tag="1")]
+    pub remote_id: u64,
+    #[prost(string, tag="2")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(bool, tag="3")]
+    pub is_controller: bool,
+    #[prost(string, tag="4")]
+    pub timezone: ::prost::alloc::string::String,
+    #[prost(string, tag="5")]
+    pub locale: ::prost::alloc::string::String,
+    #[prost(int32, optional, tag="6")]
+    pub utc_offset_minutes: ::core::option::Option<i32>,
+    #[prost(uint64, tag="7")]
+    pub injected_latency_ms: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoteLeaseChangedPayload {
+    #[prost(message, optional, tag="1")]
+    pub controller: ::core::option::Option<RemoteClientInfo>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListRemoteClientsPayload {
+    #[prost(message, repeated, tag="1")]
+    pub remote_clients: ::prost::alloc::vec::Vec<RemoteClientInfo>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoteApprovalRequestedPayload {
+    #[prost(uint64, tag="1")]
+    pub request_id: u64,
+    #[prost(message, optional, tag="2")]
+    pub client: ::core::option::Option<RemoteClientInfo>,
+    #[prost(enumeration="RemoteApprovalAction", tag="3")]
+    pub action: i32,
+    #[prost(uint64, tag="4")]
+    pub timeout_ms: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoteApprovalResolvedPayload {
+    #[prost(uint64, tag="1")]
+    pub request_id: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CwdChangedPayload {
     #[prost(message, optional, tag="1")]
     pub pane_id: ::core::option::Option<PaneId>,
@@ -357,6 +415,12 @@ pub struct SessionManifest {
     pub tab_history: ::prost::alloc::vec::Vec<ClientTabHistory>,
     #[prost(message, repeated, tag="11")]
     pub pane_history: ::prost::alloc::vec::Vec<ClientPaneHistory>,
+    #[prost(uint32, tag="12")]
+    pub remote_client_count: u32,
+    #[prost(string, optional, tag="13")]
+    pub remote_controller: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag="14")]
+    pub remote_listen_address: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -674,6 +738,12 @@ pub enum EventType {
     UserAction = 37,
     ActionComplete = 38,
     CwdChanged = 39,
+    RemoteClientAttached = 40,
+    RemoteClientDetached = 41,
+    RemoteLeaseChanged = 42,
+    ListRemoteClients = 43,
+    RemoteApprovalRequested = 44,
+    RemoteApprovalResolved = 45,
 }
 impl EventType {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -721,6 +791,12 @@ impl EventType {
             EventType::UserAction => "UserAction",
             EventType::ActionComplete => "ActionComplete",
             EventType::CwdChanged => "CwdChanged",
+            EventType::RemoteClientAttached => "RemoteClientAttached",
+            EventType::RemoteClientDetached => "RemoteClientDetached",
+            EventType::RemoteLeaseChanged => "RemoteLeaseChanged",
+            EventType::ListRemoteClients => "ListRemoteClients",
+            EventType::RemoteApprovalRequested => "RemoteApprovalRequested",
+            EventType::RemoteApprovalResolved => "RemoteApprovalResolved",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -765,6 +841,41 @@ impl EventType {
             "UserAction" => Some(Self::UserAction),
             "ActionComplete" => Some(Self::ActionComplete),
             "CwdChanged" => Some(Self::CwdChanged),
+            "RemoteClientAttached" => Some(Self::RemoteClientAttached),
+            "RemoteClientDetached" => Some(Self::RemoteClientDetached),
+            "RemoteLeaseChanged" => Some(Self::RemoteLeaseChanged),
+            "ListRemoteClients" => Some(Self::ListRemoteClients),
+            "RemoteApprovalRequested" => Some(Self::RemoteApprovalRequested),
+            "RemoteApprovalResolved" => Some(Self::RemoteApprovalResolved),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum RemoteApprovalAction {
+    ForceTakeover = 0,
+    ClipboardRead = 1,
+    FileDownload = 2,
+}
+impl RemoteApprovalAction {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            RemoteApprovalAction::ForceTakeover => "ForceTakeover",
+            RemoteApprovalAction::ClipboardRead => "ClipboardRead",
+            RemoteApprovalAction::FileDownload => "FileDownload",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "ForceTakeover" => Some(Self::ForceTakeover),
+            "ClipboardRead" => Some(Self::ClipboardRead),
+            "FileDownload" => Some(Self::FileDownload),
             _ => None,
         }
     }
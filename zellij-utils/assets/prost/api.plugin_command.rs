@@ -3,7 +3,7 @@
 pub struct PluginCommand {
     #[prost(enumeration="CommandName", tag="1")]
     pub name: i32,
-    #[prost(oneof="plugin_command::Payload", tags="2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95, 96, 97, 98, 99, 100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110, 111, 112, 113, 114, 119, 120, 121, 122, 123")]
+    #[prost(oneof="plugin_command::Payload", tags="2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95, 96, 97, 98, 99, 100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110, 111, 112, 113, 114, 119, 120, 121, 122, 123, 124, 125")]
     pub payload: ::core::option::Option<plugin_command::Payload>,
 }
 /// Nested message and enum types in `PluginCommand`.
@@ -229,6 +229,10 @@ pub mod plugin_command {
         SendSigkillToPaneIdPayload(super::PaneId),
         #[prost(message, tag="123")]
         GetPanePidPayload(super::GetPanePidPayload),
+        #[prost(message, tag="124")]
+        RespondToRemoteApprovalPayload(super::RespondToRemoteApprovalPayload),
+        #[prost(message, tag="125")]
+        SetRemoteClientLatencyPayload(super::SetRemoteClientLatencyPayload),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -955,6 +959,22 @@ pub struct GetPanePidPayload {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RespondToRemoteApprovalPayload {
+    #[prost(uint64, tag="1")]
+    pub request_id: u64,
+    #[prost(bool, tag="2")]
+    pub approved: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetRemoteClientLatencyPayload {
+    #[prost(uint64, tag="1")]
+    pub remote_id: u64,
+    #[prost(uint64, tag="2")]
+    pub delay_ms: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetPanePidResponse {
     #[prost(oneof="get_pane_pid_response::Result", tags="1, 2")]
     pub result: ::core::option::Option<get_pane_pid_response::Result>,
@@ -1126,6 +1146,12 @@ pub enum CommandName {
     SendSigintToPaneId = 171,
     SendSigkillToPaneId = 172,
     GetPanePid = 173,
+    ListRemoteClients = 174,
+    RevokeRemoteLease = 175,
+    StartRemoteServing = 176,
+    StopRemoteServing = 177,
+    RespondToRemoteApproval = 178,
+    SetRemoteClientLatency = 179,
 }
 impl CommandName {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -1287,6 +1313,12 @@ impl CommandName {
             CommandName::SendSigintToPaneId => "SendSigintToPaneId",
             CommandName::SendSigkillToPaneId => "SendSigkillToPaneId",
             CommandName::GetPanePid => "GetPanePid",
+            CommandName::ListRemoteClients => "ListRemoteClients",
+            CommandName::RevokeRemoteLease => "RevokeRemoteLease",
+            CommandName::StartRemoteServing => "StartRemoteServing",
+            CommandName::StopRemoteServing => "StopRemoteServing",
+            CommandName::RespondToRemoteApproval => "RespondToRemoteApproval",
+            CommandName::SetRemoteClientLatency => "SetRemoteClientLatency",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -1445,6 +1477,12 @@ impl CommandName {
             "SendSigintToPaneId" => Some(Self::SendSigintToPaneId),
             "SendSigkillToPaneId" => Some(Self::SendSigkillToPaneId),
             "GetPanePid" => Some(Self::GetPanePid),
+            "ListRemoteClients" => Some(Self::ListRemoteClients),
+            "RevokeRemoteLease" => Some(Self::RevokeRemoteLease),
+            "StartRemoteServing" => Some(Self::StartRemoteServing),
+            "StopRemoteServing" => Some(Self::StopRemoteServing),
+            "RespondToRemoteApproval" => Some(Self::RespondToRemoteApproval),
+            "SetRemoteClientLatency" => Some(Self::SetRemoteClientLatency),
             _ => None,
         }
     }
@@ -6,7 +6,7 @@ use crate::{
 };
 use clap::{Args, Parser, Subcommand};
 use serde::{Deserialize, Serialize};
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use url::Url;
 
@@ -78,6 +78,10 @@ pub struct CliArgs {
     /// Specify emitting additional debug information
     #[clap(short, long, value_parser)]
     pub debug: bool,
+
+    /// Serve remote clients even with settings the remote security profile refuses
+    #[clap(long = "i-know-what-im-doing", value_parser)]
+    pub i_know_what_im_doing: bool,
 }
 
 impl CliArgs {
@@ -111,6 +115,10 @@ pub enum Command {
     #[clap(name = "web", value_parser)]
     Web(WebCli),
 
+    /// Manage remote client access
+    #[clap(name = "remote", subcommand)]
+    Remote(RemoteCli),
+
     /// Explore existing zellij sessions
     #[clap(flatten)]
     Sessions(Sessions),
@@ -211,6 +219,30 @@ impl WebCli {
     }
 }
 
+#[derive(Debug, Subcommand, Clone, Serialize, Deserialize)]
+pub enum RemoteCli {
+    /// Check that remote clients will be able to connect, and say what to fix if not
+    #[clap(name = "doctor")]
+    Doctor {
+        /// Path to the remote bridge config (TOML); built-in defaults are checked if omitted
+        #[clap(long, value_parser, env = "ZELLIJ_REMOTE_CONFIG")]
+        remote_config: Option<PathBuf>,
+        /// Address to listen on, overriding the config
+        #[clap(long, value_parser, env = "ZELLIJ_REMOTE_ADDR")]
+        addr: Option<SocketAddr>,
+        /// File holding the bearer token a client would log in with
+        #[clap(long, value_parser)]
+        token_file: Option<PathBuf>,
+    },
+    /// Show the running sessions serving remote clients
+    #[clap(name = "status")]
+    Status {
+        /// Also show how long each stage of sending frames takes, against its budget
+        #[clap(short, long, value_parser)]
+        verbose: bool,
+    },
+}
+
 #[derive(Debug, Subcommand, Clone, Serialize, Deserialize)]
 pub enum SessionCommand {
     /// Change the behaviour of zellij
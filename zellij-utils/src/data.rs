@@ -996,6 +996,14 @@ pub enum Event {
     PaneRenderReport(HashMap<PaneId, PaneContents>),
     ActionComplete(Action, Option<PaneId>, BTreeMap<String, String>), // Action, pane_id, context
     CwdChanged(PaneId, PathBuf, Vec<ClientId>), // pane_id, cwd, focused_client_ids
+    RemoteClientAttached(RemoteClientInfo),
+    RemoteClientDetached(RemoteClientInfo),
+    RemoteLeaseChanged(Option<RemoteClientInfo>), // the new controller, None if nobody has control
+    ListRemoteClients(Vec<RemoteClientInfo>),
+    /// A remote client asked for something the host has to approve, answer with
+    /// `respond_to_remote_approval`
+    RemoteApprovalRequested(RemoteApprovalRequest),
+    RemoteApprovalResolved(u64), // request_id, answered or timed out
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, EnumDiscriminants, Display, Serialize, Deserialize)]
@@ -1695,10 +1703,63 @@ pub struct SessionInfo {
     pub plugins: BTreeMap<u32, PluginInfo>,
     pub web_clients_allowed: bool,
     pub web_client_count: usize,
+    pub remote_client_count: usize,
+    pub remote_controller: Option<String>, // display name of the remote client in control
+    pub remote_listen_address: Option<String>, // set while the session serves remote clients
+    pub remote_frame_stages: Vec<RemoteFrameStage>, // frame latency per stage, while serving
+    pub remote_input_latency: Vec<RemoteInputLatency>, // per connected client, while serving
     pub tab_history: BTreeMap<ClientId, Vec<usize>>,
     pub pane_history: BTreeMap<ClientId, Vec<PaneId>>,
 }
 
+/// A client attached over the remote protocol
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RemoteClientInfo {
+    pub remote_id: u64,
+    pub name: String, // as announced by the client, eg. "alice@ios"
+    pub is_controller: bool,
+    pub timezone: String, // IANA name from the client, eg. "Europe/Berlin", empty if not sent
+    pub locale: String,   // BCP 47 tag from the client, eg. "de-DE", empty if not sent
+    pub utc_offset_minutes: Option<i32>, // the client's offset when it attached
+    pub injected_latency_ms: u64, // artificial delay on everything sent to it, 0 if none
+}
+
+/// How long one stage of getting frames to remote clients takes
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RemoteFrameStage {
+    pub name: String,    // eg. "delta_compute"
+    pub average_us: u64, // moving average
+    pub worst_us: u64,
+    pub budget_us: u64,
+    pub over_budget: u64, // runs that took longer than the budget
+}
+
+/// Keypress-to-screen latency a remote client measured, from its last `LinkStats`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RemoteInputLatency {
+    pub client: String, // display name, as in `RemoteClientInfo`
+    pub p50_ms: u32,
+    pub p95_ms: u32,
+    pub p99_ms: u32,
+    pub samples: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RemoteApprovalAction {
+    #[default]
+    ForceTakeover,
+    ClipboardRead,
+    FileDownload,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RemoteApprovalRequest {
+    pub request_id: u64,
+    pub client: RemoteClientInfo,
+    pub action: RemoteApprovalAction,
+    pub timeout_ms: u64, // the request is denied if not answered within this time
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct PluginInfo {
     pub location: String,
@@ -2920,4 +2981,10 @@ pub enum PluginCommand {
     // suppress_replaced_pane)
     RunAction(Action, BTreeMap<String, String>),
     CopyToClipboard(String), // text to copy
+    ListRemoteClients,
+    RevokeRemoteLease,
+    StartRemoteServing,
+    StopRemoteServing,
+    RespondToRemoteApproval(u64, bool), // request_id, approved
+    SetRemoteClientLatency(u64, u64),   // remote_id, delay_ms (0 to stop delaying)
 }
@@ -31,6 +31,16 @@ pub fn get_socket_dir() -> Result<String> {
     Ok(var(SOCKET_DIR_ENV_KEY)?)
 }
 
+/// Lets the server serve remote clients with a configuration its security profile
+/// refuses; set for the server by `zellij --i-know-what-im-doing`
+pub const REMOTE_I_KNOW_WHAT_IM_DOING_ENV_KEY: &str = "ZELLIJ_REMOTE_I_KNOW_WHAT_IM_DOING";
+pub fn get_remote_i_know_what_im_doing() -> bool {
+    var(REMOTE_I_KNOW_WHAT_IM_DOING_ENV_KEY).is_ok()
+}
+pub fn set_remote_i_know_what_im_doing() {
+    set_var(REMOTE_I_KNOW_WHAT_IM_DOING_ENV_KEY, "1");
+}
+
 /// Manage ENVIRONMENT VARIABLES from the configuration and the layout files
 #[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EnvironmentVariables {
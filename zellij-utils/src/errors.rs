@@ -395,6 +395,15 @@ pub enum ScreenContext {
     RemoveWatcherClient,
     SetFollowedClient,
     WatcherTerminalResize, // NEW
+    RemoteClientsChanged,
+    RemoteServingChanged,
+    RemoteTerminalAnswers,
+    RemoteFrameStages,
+    RemoteInputLatency,
+    ListRemoteClientsToPlugin,
+    RemoteApprovalRequested,
+    RemoteApprovalResolved,
+    RemoteHeartbeat,
 }
 
 /// Stack call representations corresponding to the different types of [`PtyInstruction`]s.
@@ -425,6 +434,7 @@ pub enum PtyContext {
     SendSigkillToPaneId,
     GetPanePid,
     UpdateAndReportCwds,
+    ReportFocusToRemote,
     Exit,
 }
 
@@ -456,6 +456,8 @@ pub enum Action {
     },
     TogglePaneInGroup,
     ToggleGroupMarking,
+    /// Pause streaming to remote clients, or resume it if paused
+    ToggleRemoteStreaming,
 }
 
 impl Default for Action {
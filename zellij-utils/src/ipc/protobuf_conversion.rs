@@ -734,8 +734,9 @@ impl From<crate::input::actions::Action>
             SwitchSessionAction, SwitchToModeAction, TabNameInputAction, ToggleActiveSyncTabAction,
             ToggleFloatingPanesAction, ToggleFocusFullscreenAction, ToggleGroupMarkingAction,
             ToggleMouseModeAction, TogglePaneEmbedOrFloatingAction, TogglePaneFramesAction,
-            TogglePaneInGroupAction, TogglePanePinnedAction, ToggleTabAction, UndoRenamePaneAction,
-            UndoRenameTabAction, WriteAction, WriteCharsAction,
+            TogglePaneInGroupAction, TogglePanePinnedAction, ToggleRemoteStreamingAction,
+            ToggleTabAction, UndoRenamePaneAction, UndoRenameTabAction, WriteAction,
+            WriteCharsAction,
         };
         use std::collections::HashMap;
 
@@ -1297,6 +1298,9 @@ impl From<crate::input::actions::Action>
             crate::input::actions::Action::ToggleGroupMarking => {
                 ActionType::ToggleGroupMarking(ToggleGroupMarkingAction {})
             },
+            crate::input::actions::Action::ToggleRemoteStreaming => {
+                ActionType::ToggleRemoteStreaming(ToggleRemoteStreamingAction {})
+            },
         };
 
         Self {
@@ -1920,6 +1924,9 @@ impl TryFrom<crate::client_server_contract::client_server_contract::Action>
             ActionType::ToggleGroupMarking(_) => {
                 Ok(crate::input::actions::Action::ToggleGroupMarking)
             },
+            ActionType::ToggleRemoteStreaming(_) => {
+                Ok(crate::input::actions::Action::ToggleRemoteStreaming)
+            },
         }
     }
 }
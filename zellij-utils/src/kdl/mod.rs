@@ -2,7 +2,8 @@ mod kdl_layout_parser;
 use crate::data::{
     BareKey, Direction, FloatingPaneCoordinates, InputMode, KeyWithModifier, LayoutInfo,
     MultiplayerColors, Palette, PaletteColor, PaneId, PaneInfo, PaneManifest, PermissionType,
-    Resize, SessionInfo, StyleDeclaration, Styling, TabInfo, WebSharing, DEFAULT_STYLES,
+    RemoteFrameStage, RemoteInputLatency, Resize, SessionInfo, StyleDeclaration, Styling, TabInfo,
+    WebSharing, DEFAULT_STYLES,
 };
 use crate::envs::EnvironmentVariables;
 use crate::home::{find_default_config_dir, get_layout_dir};
@@ -1259,6 +1260,7 @@ impl Action {
             Action::TogglePanePinned => Some(KdlNode::new("TogglePanePinned")),
             Action::TogglePaneInGroup => Some(KdlNode::new("TogglePaneInGroup")),
             Action::ToggleGroupMarking => Some(KdlNode::new("ToggleGroupMarking")),
+            Action::ToggleRemoteStreaming => Some(KdlNode::new("ToggleRemoteStreaming")),
             _ => None,
         }
     }
@@ -2137,6 +2139,7 @@ impl TryFrom<(&KdlNode, &Options)> for Action {
             "TogglePanePinned" => Ok(Action::TogglePanePinned),
             "TogglePaneInGroup" => Ok(Action::TogglePaneInGroup),
             "ToggleGroupMarking" => Ok(Action::ToggleGroupMarking),
+            "ToggleRemoteStreaming" => Ok(Action::ToggleRemoteStreaming),
             _ => Err(ConfigError::new_kdl_error(
                 format!("Unsupported action: {}", action_name).into(),
                 kdl_action.span().offset(),
@@ -5174,6 +5177,79 @@ impl SessionInfo {
             .and_then(|n| n.entries().iter().next())
             .and_then(|e| e.value().as_bool())
             .unwrap_or(false);
+        let remote_client_count = kdl_document
+            .get("remote_client_count")
+            .and_then(|n| n.entries().iter().next())
+            .and_then(|e| e.value().as_i64())
+            .map(|c| c as usize)
+            .unwrap_or(0);
+        let remote_controller = kdl_document
+            .get("remote_controller")
+            .and_then(|n| n.entries().iter().next())
+            .and_then(|e| e.value().as_string())
+            .map(|c| c.to_owned());
+        let remote_listen_address = kdl_document
+            .get("remote_listen_address")
+            .and_then(|n| n.entries().iter().next())
+            .and_then(|e| e.value().as_string())
+            .map(|a| a.to_owned());
+        let remote_frame_stages = kdl_document
+            .get("remote_frame_stages")
+            .and_then(|n| n.children())
+            .map(|stages| {
+                stages
+                    .nodes()
+                    .iter()
+                    .filter_map(|stage| {
+                        let name = stage.entries().iter().next()?.value().as_string()?;
+                        let field = |field: &str| {
+                            stage
+                                .entries()
+                                .iter()
+                                .find(|e| e.name().map(|n| n.value()) == Some(field))
+                                .and_then(|e| e.value().as_i64())
+                                .map(|v| v as u64)
+                                .unwrap_or(0)
+                        };
+                        Some(RemoteFrameStage {
+                            name: name.to_owned(),
+                            average_us: field("average_us"),
+                            worst_us: field("worst_us"),
+                            budget_us: field("budget_us"),
+                            over_budget: field("over_budget"),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let remote_input_latency = kdl_document
+            .get("remote_input_latency")
+            .and_then(|n| n.children())
+            .map(|clients| {
+                clients
+                    .nodes()
+                    .iter()
+                    .filter_map(|client| {
+                        let name = client.entries().iter().next()?.value().as_string()?;
+                        let field = |field: &str| {
+                            client
+                                .entries()
+                                .iter()
+                                .find(|e| e.name().map(|n| n.value()) == Some(field))
+                                .and_then(|e| e.value().as_i64())
+                                .unwrap_or(0)
+                        };
+                        Some(RemoteInputLatency {
+                            client: name.to_owned(),
+                            p50_ms: field("p50_ms") as u32,
+                            p95_ms: field("p95_ms") as u32,
+                            p99_ms: field("p99_ms") as u32,
+                            samples: field("samples") as u64,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
         let is_current_session = name == current_session_name;
         let mut tab_history = BTreeMap::new();
         if let Some(kdl_tab_history) = kdl_document.get("tab_history").and_then(|p| p.children()) {
@@ -5251,6 +5327,11 @@ impl SessionInfo {
             available_layouts,
             web_client_count,
             web_clients_allowed,
+            remote_client_count,
+            remote_controller,
+            remote_listen_address,
+            remote_frame_stages,
+            remote_input_latency,
             plugins: Default::default(), // we do not serialize plugin information
             tab_history,
             pane_history,
@@ -5284,6 +5365,55 @@ impl SessionInfo {
         let mut web_clients_allowed = KdlNode::new("web_clients_allowed");
         web_clients_allowed.push(self.web_clients_allowed);
 
+        let mut remote_client_count = KdlNode::new("remote_client_count");
+        remote_client_count.push(self.remote_client_count as i64);
+
+        let remote_controller = self.remote_controller.as_ref().map(|controller| {
+            let mut remote_controller = KdlNode::new("remote_controller");
+            remote_controller.push(controller.clone());
+            remote_controller
+        });
+
+        let remote_listen_address = self.remote_listen_address.as_ref().map(|address| {
+            let mut remote_listen_address = KdlNode::new("remote_listen_address");
+            remote_listen_address.push(address.clone());
+            remote_listen_address
+        });
+
+        let remote_frame_stages = (!self.remote_frame_stages.is_empty()).then(|| {
+            let mut remote_frame_stages = KdlNode::new("remote_frame_stages");
+            for stage in &self.remote_frame_stages {
+                let mut stage_node = KdlNode::new("stage");
+                stage_node.push(stage.name.clone());
+                stage_node.push(KdlEntry::new_prop("average_us", stage.average_us as i64));
+                stage_node.push(KdlEntry::new_prop("worst_us", stage.worst_us as i64));
+                stage_node.push(KdlEntry::new_prop("budget_us", stage.budget_us as i64));
+                stage_node.push(KdlEntry::new_prop("over_budget", stage.over_budget as i64));
+                remote_frame_stages
+                    .ensure_children()
+                    .nodes_mut()
+                    .push(stage_node);
+            }
+            remote_frame_stages
+        });
+
+        let remote_input_latency = (!self.remote_input_latency.is_empty()).then(|| {
+            let mut remote_input_latency = KdlNode::new("remote_input_latency");
+            for latency in &self.remote_input_latency {
+                let mut client_node = KdlNode::new("client");
+                client_node.push(latency.client.clone());
+                client_node.push(KdlEntry::new_prop("p50_ms", latency.p50_ms as i64));
+                client_node.push(KdlEntry::new_prop("p95_ms", latency.p95_ms as i64));
+                client_node.push(KdlEntry::new_prop("p99_ms", latency.p99_ms as i64));
+                client_node.push(KdlEntry::new_prop("samples", latency.samples as i64));
+                remote_input_latency
+                    .ensure_children()
+                    .nodes_mut()
+                    .push(client_node);
+            }
+            remote_input_latency
+        });
+
         let mut available_layouts = KdlNode::new("available_layouts");
         let mut available_layouts_children = KdlDocument::new();
         for layout_info in &self.available_layouts {
@@ -5353,6 +5483,19 @@ impl SessionInfo {
         kdl_document.nodes_mut().push(connected_clients);
         kdl_document.nodes_mut().push(web_clients_allowed);
         kdl_document.nodes_mut().push(web_client_count);
+        kdl_document.nodes_mut().push(remote_client_count);
+        if let Some(remote_controller) = remote_controller {
+            kdl_document.nodes_mut().push(remote_controller);
+        }
+        if let Some(remote_listen_address) = remote_listen_address {
+            kdl_document.nodes_mut().push(remote_listen_address);
+        }
+        if let Some(remote_frame_stages) = remote_frame_stages {
+            kdl_document.nodes_mut().push(remote_frame_stages);
+        }
+        if let Some(remote_input_latency) = remote_input_latency {
+            kdl_document.nodes_mut().push(remote_input_latency);
+        }
         kdl_document.nodes_mut().push(available_layouts);
         kdl_document.nodes_mut().push(tab_history);
         kdl_document.nodes_mut().push(pane_history);
@@ -5913,6 +6056,23 @@ fn serialize_and_deserialize_session_info_with_data() {
         plugins: Default::default(),
         web_client_count: 2,
         web_clients_allowed: true,
+        remote_client_count: 2,
+        remote_controller: Some("alice@ios".to_owned()),
+        remote_listen_address: Some("127.0.0.1:4433".to_owned()),
+        remote_frame_stages: vec![RemoteFrameStage {
+            name: "delta_compute".to_owned(),
+            average_us: 850,
+            worst_us: 5200,
+            budget_us: 4000,
+            over_budget: 3,
+        }],
+        remote_input_latency: vec![RemoteInputLatency {
+            client: "alice@ios".to_owned(),
+            p50_ms: 25,
+            p95_ms: 90,
+            p99_ms: 210,
+            samples: 400,
+        }],
         tab_history: Default::default(),
         pane_history: Default::default(),
     };
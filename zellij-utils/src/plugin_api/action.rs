@@ -946,6 +946,12 @@ impl TryFrom<ProtobufAction> for Action {
                     None => Ok(Action::ToggleGroupMarking),
                 }
             },
+            Some(ProtobufActionName::ToggleRemoteStreaming) => {
+                match protobuf_action.optional_payload {
+                    Some(_) => Err("ToggleRemoteStreaming should not have a payload"),
+                    None => Ok(Action::ToggleRemoteStreaming),
+                }
+            },
             Some(ProtobufActionName::KeybindPipe) => match protobuf_action.optional_payload {
                 Some(_) => Err("KeybindPipe should not have a payload"),
                 // TODO: at some point we might want to support a payload here
@@ -1749,6 +1755,10 @@ impl TryFrom<Action> for ProtobufAction {
                 name: ProtobufActionName::ToggleGroupMarking as i32,
                 optional_payload: None,
             }),
+            Action::ToggleRemoteStreaming => Ok(ProtobufAction {
+                name: ProtobufActionName::ToggleRemoteStreaming as i32,
+                optional_payload: None,
+            }),
             Action::NewStackedPane {
                 command: _,
                 pane_name: _,
@@ -15,9 +15,11 @@ pub use super::generated_api::api::{
         PaneManifest as ProtobufPaneManifest,
         PaneRenderReportPayload as ProtobufPaneRenderReportPayload,
         PaneScrollbackResponse as ProtobufPaneScrollbackResponse, PaneType as ProtobufPaneType,
-        PluginInfo as ProtobufPluginInfo, ResurrectableSession as ProtobufResurrectableSession,
-        SelectedText as ProtobufSelectedText, SessionManifest as ProtobufSessionManifest,
-        TabInfo as ProtobufTabInfo, UserActionPayload as ProtobufUserActionPayload,
+        PluginInfo as ProtobufPluginInfo, RemoteApprovalAction as ProtobufRemoteApprovalAction,
+        RemoteClientInfo as ProtobufRemoteClientInfo,
+        ResurrectableSession as ProtobufResurrectableSession, SelectedText as ProtobufSelectedText,
+        SessionManifest as ProtobufSessionManifest, TabInfo as ProtobufTabInfo,
+        UserActionPayload as ProtobufUserActionPayload,
         WebServerStatusPayload as ProtobufWebServerStatusPayload, WebSharing as ProtobufWebSharing,
         *,
     },
@@ -29,8 +31,9 @@ pub use super::generated_api::api::{
 use crate::data::{
     ClientId, ClientInfo, CopyDestination, Event, EventType, FileMetadata, InputMode,
     KeyWithModifier, LayoutInfo, ModeInfo, Mouse, PaneContents, PaneId, PaneInfo, PaneManifest,
-    PaneScrollbackResponse, PermissionStatus, PluginCapabilities, PluginInfo, SelectedText,
-    SessionInfo, Style, TabInfo, WebServerStatus, WebSharing,
+    PaneScrollbackResponse, PermissionStatus, PluginCapabilities, PluginInfo, RemoteApprovalAction,
+    RemoteApprovalRequest, RemoteClientInfo, SelectedText, SessionInfo, Style, TabInfo,
+    WebServerStatus, WebSharing,
 };
 
 use crate::errors::prelude::*;
@@ -458,6 +461,55 @@ impl TryFrom<ProtobufEvent> for Event {
                 },
                 _ => Err("Malformed payload for the CwdChanged Event"),
             },
+            Some(ProtobufEventType::RemoteClientAttached) => match protobuf_event.payload {
+                Some(ProtobufEventPayload::RemoteClientPayload(remote_client)) => {
+                    Ok(Event::RemoteClientAttached(remote_client.into()))
+                },
+                _ => Err("Malformed payload for the RemoteClientAttached Event"),
+            },
+            Some(ProtobufEventType::RemoteClientDetached) => match protobuf_event.payload {
+                Some(ProtobufEventPayload::RemoteClientPayload(remote_client)) => {
+                    Ok(Event::RemoteClientDetached(remote_client.into()))
+                },
+                _ => Err("Malformed payload for the RemoteClientDetached Event"),
+            },
+            Some(ProtobufEventType::RemoteLeaseChanged) => match protobuf_event.payload {
+                Some(ProtobufEventPayload::RemoteLeaseChangedPayload(payload)) => Ok(
+                    Event::RemoteLeaseChanged(payload.controller.map(|c| c.into())),
+                ),
+                _ => Err("Malformed payload for the RemoteLeaseChanged Event"),
+            },
+            Some(ProtobufEventType::ListRemoteClients) => match protobuf_event.payload {
+                Some(ProtobufEventPayload::ListRemoteClientsPayload(payload)) => {
+                    Ok(Event::ListRemoteClients(
+                        payload
+                            .remote_clients
+                            .into_iter()
+                            .map(|c| c.into())
+                            .collect(),
+                    ))
+                },
+                _ => Err("Malformed payload for the ListRemoteClients Event"),
+            },
+            Some(ProtobufEventType::RemoteApprovalRequested) => match protobuf_event.payload {
+                Some(ProtobufEventPayload::RemoteApprovalRequestedPayload(payload)) => {
+                    let action = ProtobufRemoteApprovalAction::from_i32(payload.action)
+                        .ok_or("Malformed action for the RemoteApprovalRequested Event")?;
+                    Ok(Event::RemoteApprovalRequested(RemoteApprovalRequest {
+                        request_id: payload.request_id,
+                        client: payload.client.map(|c| c.into()).unwrap_or_default(),
+                        action: action.into(),
+                        timeout_ms: payload.timeout_ms,
+                    }))
+                },
+                _ => Err("Malformed payload for the RemoteApprovalRequested Event"),
+            },
+            Some(ProtobufEventType::RemoteApprovalResolved) => match protobuf_event.payload {
+                Some(ProtobufEventPayload::RemoteApprovalResolvedPayload(payload)) => {
+                    Ok(Event::RemoteApprovalResolved(payload.request_id))
+                },
+                _ => Err("Malformed payload for the RemoteApprovalResolved Event"),
+            },
             None => Err("Unknown Protobuf Event"),
         }
     }
@@ -910,6 +962,98 @@ impl TryFrom<Event> for ProtobufEvent {
                     payload: Some(event::Payload::CwdChangedPayload(cwd_changed_payload)),
                 })
             },
+            Event::RemoteClientAttached(remote_client) => Ok(ProtobufEvent {
+                name: ProtobufEventType::RemoteClientAttached as i32,
+                payload: Some(event::Payload::RemoteClientPayload(remote_client.into())),
+            }),
+            Event::RemoteClientDetached(remote_client) => Ok(ProtobufEvent {
+                name: ProtobufEventType::RemoteClientDetached as i32,
+                payload: Some(event::Payload::RemoteClientPayload(remote_client.into())),
+            }),
+            Event::RemoteLeaseChanged(controller) => Ok(ProtobufEvent {
+                name: ProtobufEventType::RemoteLeaseChanged as i32,
+                payload: Some(event::Payload::RemoteLeaseChangedPayload(
+                    RemoteLeaseChangedPayload {
+                        controller: controller.map(|c| c.into()),
+                    },
+                )),
+            }),
+            Event::ListRemoteClients(remote_clients) => Ok(ProtobufEvent {
+                name: ProtobufEventType::ListRemoteClients as i32,
+                payload: Some(event::Payload::ListRemoteClientsPayload(
+                    ListRemoteClientsPayload {
+                        remote_clients: remote_clients.into_iter().map(|c| c.into()).collect(),
+                    },
+                )),
+            }),
+            Event::RemoteApprovalRequested(request) => {
+                let action: ProtobufRemoteApprovalAction = request.action.into();
+                Ok(ProtobufEvent {
+                    name: ProtobufEventType::RemoteApprovalRequested as i32,
+                    payload: Some(event::Payload::RemoteApprovalRequestedPayload(
+                        RemoteApprovalRequestedPayload {
+                            request_id: request.request_id,
+                            client: Some(request.client.into()),
+                            action: action as i32,
+                            timeout_ms: request.timeout_ms,
+                        },
+                    )),
+                })
+            },
+            Event::RemoteApprovalResolved(request_id) => Ok(ProtobufEvent {
+                name: ProtobufEventType::RemoteApprovalResolved as i32,
+                payload: Some(event::Payload::RemoteApprovalResolvedPayload(
+                    RemoteApprovalResolvedPayload { request_id },
+                )),
+            }),
+        }
+    }
+}
+
+impl From<ProtobufRemoteClientInfo> for RemoteClientInfo {
+    fn from(protobuf_remote_client: ProtobufRemoteClientInfo) -> Self {
+        RemoteClientInfo {
+            remote_id: protobuf_remote_client.remote_id,
+            name: protobuf_remote_client.name,
+            is_controller: protobuf_remote_client.is_controller,
+            timezone: protobuf_remote_client.timezone,
+            locale: protobuf_remote_client.locale,
+            utc_offset_minutes: protobuf_remote_client.utc_offset_minutes,
+            injected_latency_ms: protobuf_remote_client.injected_latency_ms,
+        }
+    }
+}
+
+impl From<RemoteClientInfo> for ProtobufRemoteClientInfo {
+    fn from(remote_client: RemoteClientInfo) -> Self {
+        ProtobufRemoteClientInfo {
+            remote_id: remote_client.remote_id,
+            name: remote_client.name,
+            is_controller: remote_client.is_controller,
+            timezone: remote_client.timezone,
+            locale: remote_client.locale,
+            utc_offset_minutes: remote_client.utc_offset_minutes,
+            injected_latency_ms: remote_client.injected_latency_ms,
+        }
+    }
+}
+
+impl From<ProtobufRemoteApprovalAction> for RemoteApprovalAction {
+    fn from(protobuf_action: ProtobufRemoteApprovalAction) -> Self {
+        match protobuf_action {
+            ProtobufRemoteApprovalAction::ForceTakeover => RemoteApprovalAction::ForceTakeover,
+            ProtobufRemoteApprovalAction::ClipboardRead => RemoteApprovalAction::ClipboardRead,
+            ProtobufRemoteApprovalAction::FileDownload => RemoteApprovalAction::FileDownload,
+        }
+    }
+}
+
+impl From<RemoteApprovalAction> for ProtobufRemoteApprovalAction {
+    fn from(action: RemoteApprovalAction) -> Self {
+        match action {
+            RemoteApprovalAction::ForceTakeover => ProtobufRemoteApprovalAction::ForceTakeover,
+            RemoteApprovalAction::ClipboardRead => ProtobufRemoteApprovalAction::ClipboardRead,
+            RemoteApprovalAction::FileDownload => ProtobufRemoteApprovalAction::FileDownload,
         }
     }
 }
@@ -950,6 +1094,9 @@ impl TryFrom<SessionInfo> for ProtobufSessionManifest {
                 .collect(),
             web_clients_allowed: session_info.web_clients_allowed,
             web_client_count: session_info.web_client_count as u32,
+            remote_client_count: session_info.remote_client_count as u32,
+            remote_controller: session_info.remote_controller,
+            remote_listen_address: session_info.remote_listen_address,
             tab_history: session_info
                 .tab_history
                 .into_iter()
@@ -1068,6 +1215,11 @@ impl TryFrom<ProtobufSessionManifest> for SessionInfo {
             plugins,
             web_clients_allowed: protobuf_session_manifest.web_clients_allowed,
             web_client_count: protobuf_session_manifest.web_client_count as usize,
+            remote_client_count: protobuf_session_manifest.remote_client_count as usize,
+            remote_controller: protobuf_session_manifest.remote_controller,
+            remote_listen_address: protobuf_session_manifest.remote_listen_address,
+            remote_frame_stages: vec![], // only written to the session info cache
+            remote_input_latency: vec![], // only written to the session info cache
             tab_history,
             pane_history,
         })
@@ -1620,6 +1772,12 @@ impl TryFrom<ProtobufEventType> for EventType {
             ProtobufEventType::UserAction => EventType::UserAction,
             ProtobufEventType::ActionComplete => EventType::ActionComplete,
             ProtobufEventType::CwdChanged => EventType::CwdChanged,
+            ProtobufEventType::RemoteClientAttached => EventType::RemoteClientAttached,
+            ProtobufEventType::RemoteClientDetached => EventType::RemoteClientDetached,
+            ProtobufEventType::RemoteLeaseChanged => EventType::RemoteLeaseChanged,
+            ProtobufEventType::ListRemoteClients => EventType::ListRemoteClients,
+            ProtobufEventType::RemoteApprovalRequested => EventType::RemoteApprovalRequested,
+            ProtobufEventType::RemoteApprovalResolved => EventType::RemoteApprovalResolved,
         })
     }
 }
@@ -1667,6 +1825,12 @@ impl TryFrom<EventType> for ProtobufEventType {
             EventType::UserAction => ProtobufEventType::UserAction,
             EventType::ActionComplete => ProtobufEventType::ActionComplete,
             EventType::CwdChanged => ProtobufEventType::CwdChanged,
+            EventType::RemoteClientAttached => ProtobufEventType::RemoteClientAttached,
+            EventType::RemoteClientDetached => ProtobufEventType::RemoteClientDetached,
+            EventType::RemoteLeaseChanged => ProtobufEventType::RemoteLeaseChanged,
+            EventType::ListRemoteClients => ProtobufEventType::ListRemoteClients,
+            EventType::RemoteApprovalRequested => ProtobufEventType::RemoteApprovalRequested,
+            EventType::RemoteApprovalResolved => ProtobufEventType::RemoteApprovalResolved,
         })
     }
 }
@@ -2275,6 +2439,11 @@ fn serialize_session_update_event_with_non_default_values() {
         plugins,
         web_clients_allowed: false,
         web_client_count: 1,
+        remote_client_count: 2,
+        remote_controller: Some("alice@ios".to_owned()),
+        remote_listen_address: Some("127.0.0.1:4433".to_owned()),
+        remote_frame_stages: vec![],
+        remote_input_latency: vec![],
         tab_history,
         pane_history: Default::default(),
     };
@@ -2294,6 +2463,11 @@ fn serialize_session_update_event_with_non_default_values() {
         plugins: Default::default(),
         web_clients_allowed: false,
         web_client_count: 0,
+        remote_client_count: 0,
+        remote_controller: None,
+        remote_listen_address: None,
+        remote_frame_stages: vec![],
+        remote_input_latency: vec![],
         tab_history: Default::default(),
         pane_history: Default::default(),
     };
@@ -2312,6 +2486,45 @@ fn serialize_session_update_event_with_non_default_values() {
     );
 }
 
+#[test]
+fn serialize_remote_client_events() {
+    use prost::Message;
+    let alice = RemoteClientInfo {
+        remote_id: 3,
+        name: "alice@ios".to_owned(),
+        is_controller: true,
+        timezone: "Europe/Berlin".to_owned(),
+        locale: "de-DE".to_owned(),
+        utc_offset_minutes: Some(120),
+        injected_latency_ms: 150,
+    };
+    let events = vec![
+        Event::RemoteClientAttached(alice.clone()),
+        Event::RemoteClientDetached(alice.clone()),
+        Event::RemoteLeaseChanged(Some(alice.clone())),
+        Event::RemoteLeaseChanged(None),
+        Event::RemoteApprovalRequested(RemoteApprovalRequest {
+            request_id: 9,
+            client: alice.clone(),
+            action: RemoteApprovalAction::ForceTakeover,
+            timeout_ms: 15_000,
+        }),
+        Event::RemoteApprovalResolved(9),
+        Event::ListRemoteClients(vec![alice, Default::default()]),
+    ];
+    for event in events {
+        let protobuf_event: ProtobufEvent = event.clone().try_into().unwrap();
+        let serialized_protobuf_event = protobuf_event.encode_to_vec();
+        let deserialized_protobuf_event: ProtobufEvent =
+            Message::decode(serialized_protobuf_event.as_slice()).unwrap();
+        let deserialized_event: Event = deserialized_protobuf_event.try_into().unwrap();
+        assert_eq!(
+            event, deserialized_event,
+            "Event properly serialized/deserialized without change"
+        );
+    }
+}
+
 // note: ProtobufPaneId and ProtobufPaneType are not the same as the ones defined in plugin_command.rs
 // this is a duplicate type - we are forced to do this because protobuffs do not support recursive
 // imports
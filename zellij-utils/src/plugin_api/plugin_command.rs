@@ -30,14 +30,14 @@ pub use super::generated_api::api::{
         ReconfigurePayload, ReloadPluginPayload, RenameWebLoginTokenPayload,
         RenameWebTokenResponse, ReplacePaneWithExistingPanePayload, RequestPluginPermissionPayload,
         RerunCommandPanePayload, ResizePaneIdWithDirectionPayload, ResizePayload,
-        RevokeAllWebTokensResponse, RevokeTokenResponse, RevokeWebLoginTokenPayload,
-        RunActionPayload, RunCommandPayload, ScrollDownInPaneIdPayload,
+        RespondToRemoteApprovalPayload, RevokeAllWebTokensResponse, RevokeTokenResponse,
+        RevokeWebLoginTokenPayload, RunActionPayload, RunCommandPayload, ScrollDownInPaneIdPayload,
         ScrollToBottomInPaneIdPayload, ScrollToTopInPaneIdPayload, ScrollUpInPaneIdPayload,
-        SetFloatingPanePinnedPayload, SetSelfMouseSelectionSupportPayload, SetTimeoutPayload,
-        ShowCursorPayload, ShowPaneWithIdPayload, StackPanesPayload, SubscribePayload,
-        SwitchSessionPayload, SwitchTabToPayload, TogglePaneEmbedOrEjectForPaneIdPayload,
-        TogglePaneIdFullscreenPayload, UnsubscribePayload, WebRequestPayload,
-        WriteCharsToPaneIdPayload, WriteToPaneIdPayload,
+        SetFloatingPanePinnedPayload, SetRemoteClientLatencyPayload,
+        SetSelfMouseSelectionSupportPayload, SetTimeoutPayload, ShowCursorPayload,
+        ShowPaneWithIdPayload, StackPanesPayload, SubscribePayload, SwitchSessionPayload,
+        SwitchTabToPayload, TogglePaneEmbedOrEjectForPaneIdPayload, TogglePaneIdFullscreenPayload,
+        UnsubscribePayload, WebRequestPayload, WriteCharsToPaneIdPayload, WriteToPaneIdPayload,
     },
     plugin_permission::PermissionType as ProtobufPermissionType,
     resize::ResizeAction as ProtobufResizeAction,
@@ -1411,6 +1411,34 @@ impl TryFrom<ProtobufPluginCommand> for PluginCommand {
                 Some(_) => Err("ListClients should have no payload, found a payload"),
                 None => Ok(PluginCommand::ListClients),
             },
+            Some(CommandName::ListRemoteClients) => match protobuf_plugin_command.payload {
+                Some(_) => Err("ListRemoteClients should have no payload, found a payload"),
+                None => Ok(PluginCommand::ListRemoteClients),
+            },
+            Some(CommandName::RevokeRemoteLease) => match protobuf_plugin_command.payload {
+                Some(_) => Err("RevokeRemoteLease should have no payload, found a payload"),
+                None => Ok(PluginCommand::RevokeRemoteLease),
+            },
+            Some(CommandName::StartRemoteServing) => match protobuf_plugin_command.payload {
+                Some(_) => Err("StartRemoteServing should have no payload, found a payload"),
+                None => Ok(PluginCommand::StartRemoteServing),
+            },
+            Some(CommandName::StopRemoteServing) => match protobuf_plugin_command.payload {
+                Some(_) => Err("StopRemoteServing should have no payload, found a payload"),
+                None => Ok(PluginCommand::StopRemoteServing),
+            },
+            Some(CommandName::RespondToRemoteApproval) => match protobuf_plugin_command.payload {
+                Some(Payload::RespondToRemoteApprovalPayload(payload)) => Ok(
+                    PluginCommand::RespondToRemoteApproval(payload.request_id, payload.approved),
+                ),
+                _ => Err("Mismatched payload for RespondToRemoteApproval"),
+            },
+            Some(CommandName::SetRemoteClientLatency) => match protobuf_plugin_command.payload {
+                Some(Payload::SetRemoteClientLatencyPayload(payload)) => Ok(
+                    PluginCommand::SetRemoteClientLatency(payload.remote_id, payload.delay_ms),
+                ),
+                _ => Err("Mismatched payload for SetRemoteClientLatency"),
+            },
             Some(CommandName::ChangeHostFolder) => match protobuf_plugin_command.payload {
                 Some(Payload::ChangeHostFolderPayload(change_host_folder_payload)) => {
                     Ok(PluginCommand::ChangeHostFolder(PathBuf::from(
@@ -2721,6 +2749,44 @@ impl TryFrom<PluginCommand> for ProtobufPluginCommand {
                 name: CommandName::ListClients as i32,
                 payload: None,
             }),
+            PluginCommand::ListRemoteClients => Ok(ProtobufPluginCommand {
+                name: CommandName::ListRemoteClients as i32,
+                payload: None,
+            }),
+            PluginCommand::RevokeRemoteLease => Ok(ProtobufPluginCommand {
+                name: CommandName::RevokeRemoteLease as i32,
+                payload: None,
+            }),
+            PluginCommand::StartRemoteServing => Ok(ProtobufPluginCommand {
+                name: CommandName::StartRemoteServing as i32,
+                payload: None,
+            }),
+            PluginCommand::StopRemoteServing => Ok(ProtobufPluginCommand {
+                name: CommandName::StopRemoteServing as i32,
+                payload: None,
+            }),
+            PluginCommand::RespondToRemoteApproval(request_id, approved) => {
+                Ok(ProtobufPluginCommand {
+                    name: CommandName::RespondToRemoteApproval as i32,
+                    payload: Some(Payload::RespondToRemoteApprovalPayload(
+                        RespondToRemoteApprovalPayload {
+                            request_id,
+                            approved,
+                        },
+                    )),
+                })
+            },
+            PluginCommand::SetRemoteClientLatency(remote_id, delay_ms) => {
+                Ok(ProtobufPluginCommand {
+                    name: CommandName::SetRemoteClientLatency as i32,
+                    payload: Some(Payload::SetRemoteClientLatencyPayload(
+                        SetRemoteClientLatencyPayload {
+                            remote_id,
+                            delay_ms,
+                        },
+                    )),
+                })
+            },
             PluginCommand::ChangeHostFolder(new_host_folder) => Ok(ProtobufPluginCommand {
                 name: CommandName::ChangeHostFolder as i32,
                 payload: Some(Payload::ChangeHostFolderPayload(ChangeHostFolderPayload {
@@ -1,8 +1,9 @@
 use crate::{
     consts::{
-        session_info_folder_for_session, session_layout_cache_file_name,
-        ZELLIJ_SESSION_INFO_CACHE_DIR, ZELLIJ_SOCK_DIR,
+        session_info_cache_file_name, session_info_folder_for_session,
+        session_layout_cache_file_name, ZELLIJ_SESSION_INFO_CACHE_DIR, ZELLIJ_SOCK_DIR,
     },
+    data::SessionInfo,
     envs,
     input::layout::Layout,
     ipc::{ClientToServerMsg, IpcReceiverWithContext, IpcSenderWithContext, ServerToClientMsg},
@@ -311,6 +312,86 @@ pub fn list_sessions(no_formatting: bool, short: bool, reverse: bool) {
     process::exit(exit_code);
 }
 
+/// Print each running session serving remote clients, as its session info last
+/// recorded it; with `verbose`, each client's input latency and how long each stage of
+/// getting frames to them takes.
+pub fn remote_status(verbose: bool) {
+    let running_sessions = match get_sessions() {
+        Ok(running_sessions) => running_sessions,
+        Err(e) => {
+            eprintln!("Error occurred: {:?}", e);
+            process::exit(1);
+        },
+    };
+    let mut serving: Vec<SessionInfo> = running_sessions
+        .iter()
+        .filter_map(|(name, _)| {
+            let raw_session_info = fs::read_to_string(session_info_cache_file_name(name)).ok()?;
+            SessionInfo::from_string(&raw_session_info, "").ok()
+        })
+        .filter(|session_info| session_info.remote_listen_address.is_some())
+        .collect();
+    if serving.is_empty() {
+        eprintln!("No sessions are serving remote clients.");
+        process::exit(1);
+    }
+    serving.sort_by(|a, b| a.name.cmp(&b.name));
+    for session_info in &serving {
+        print!("{}", describe_remote_session(session_info, verbose));
+    }
+}
+
+fn describe_remote_session(session_info: &SessionInfo, verbose: bool) -> String {
+    let mut description = format!(
+        "{}: listening on {}, {} remote client(s)",
+        session_info.name,
+        session_info
+            .remote_listen_address
+            .as_deref()
+            .unwrap_or_default(),
+        session_info.remote_client_count
+    );
+    if let Some(controller) = &session_info.remote_controller {
+        description.push_str(&format!(", {} in control", controller));
+    }
+    description.push('\n');
+    if !verbose {
+        return description;
+    }
+    for latency in &session_info.remote_input_latency {
+        description.push_str(&format!(
+            "  {} input latency p50={}ms p95={}ms p99={}ms ({} samples)\n",
+            latency.client, latency.p50_ms, latency.p95_ms, latency.p99_ms, latency.samples
+        ));
+    }
+    if session_info.remote_frame_stages.is_empty() {
+        description.push_str("  no frames sent yet\n");
+        return description;
+    }
+    let ms = |us: u64| format!("{:.2}ms", us as f64 / 1000.0);
+    description.push_str(&format!(
+        "  {:<14} {:>9} {:>9} {:>9} {:>12}\n",
+        "stage", "average", "worst", "budget", "over budget"
+    ));
+    for stage in &session_info.remote_frame_stages {
+        let warning = if stage.average_us > stage.budget_us {
+            "  <- over budget"
+        } else {
+            ""
+        };
+        description.push_str(&format!(
+            "  {:<14} {:>9} {:>9} {:>9} {:>12}{}\n",
+            stage.name,
+            ms(stage.average_us),
+            ms(stage.worst_us),
+            ms(stage.budget_us),
+            stage.over_budget,
+            warning
+        ));
+    }
+    description
+}
+
 #[derive(Debug, Clone)]
 pub enum SessionNameMatch {
     AmbiguousPrefix(Vec<String>),